@@ -0,0 +1,182 @@
+use crate::model::ToolCall;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::io::Write;
+use tokio::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalDecision {
+    Approved,
+    Denied,
+}
+
+#[async_trait]
+pub trait ApprovalGate: Send + Sync {
+    async fn review(&self, tool_call: &ToolCall) -> Result<ApprovalDecision>;
+}
+
+/// Verdict on a `propose_done` call or a submitted plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompletionDecision {
+    Accepted,
+    /// Sent back to the agent as the tool output.
+    Rejected {
+        feedback: String,
+    },
+}
+
+/// Second phase of completion: decides whether a proposal ends the run.
+#[async_trait]
+pub trait CompletionReviewer: Send + Sync {
+    async fn review_completion(&self, proposal: &ToolCall) -> Result<CompletionDecision>;
+}
+
+/// Decides whether a plan submitted in a `--plan` run may be executed.
+#[async_trait]
+pub trait PlanReviewer: Send + Sync {
+    async fn review_plan(&self, plan: &ToolCall) -> Result<CompletionDecision>;
+}
+
+/// Prompts on stdin before gated tool calls. Answering `always` approves
+/// every later call to the same tool for the rest of the run.
+#[derive(Default)]
+pub struct StdinApprovalGate {
+    always_allowed: Mutex<HashSet<String>>,
+}
+
+impl StdinApprovalGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ApprovalGate for StdinApprovalGate {
+    async fn review(&self, tool_call: &ToolCall) -> Result<ApprovalDecision> {
+        if self.always_allowed.lock().await.contains(&tool_call.name) {
+            return Ok(ApprovalDecision::Approved);
+        }
+
+        println!(
+            "confirm tool call: {} [{}]\n  arguments: {}",
+            tool_call.name, tool_call.id, tool_call.arguments
+        );
+
+        loop {
+            print!("Approve? [y]es / [n]o / [a]lways: ");
+            std::io::stdout().flush().ok();
+
+            let answer = tokio::task::spawn_blocking(|| {
+                let mut line = String::new();
+                std::io::stdin()
+                    .read_line(&mut line)
+                    .map(|read| (read, line))
+            })
+            .await
+            .context("approval prompt task failed")?
+            .context("failed to read approval answer from stdin")?;
+
+            let (read, line) = answer;
+            if read == 0 {
+                return Ok(ApprovalDecision::Denied);
+            }
+
+            match line.trim().to_lowercase().as_str() {
+                "y" | "yes" => return Ok(ApprovalDecision::Approved),
+                "n" | "no" => return Ok(ApprovalDecision::Denied),
+                "a" | "always" => {
+                    self.always_allowed
+                        .lock()
+                        .await
+                        .insert(tool_call.name.clone());
+                    return Ok(ApprovalDecision::Approved);
+                }
+                other => println!("Unrecognized answer '{}'.", other),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl CompletionReviewer for StdinApprovalGate {
+    /// An empty answer, `y`, or end of input accepts; anything else is
+    /// returned to the agent as feedback.
+    async fn review_completion(&self, proposal: &ToolCall) -> Result<CompletionDecision> {
+        println!("completion proposed [{}]", proposal.id);
+        if let Some(summary) = proposal.arguments.get("summary").and_then(|v| v.as_str()) {
+            println!("  summary: {}", summary);
+        }
+        if let Some(outcome) = proposal.arguments.get("outcome").and_then(|v| v.as_str()) {
+            println!("  outcome: {}", outcome);
+        }
+        match proposal.arguments.get("evidence") {
+            Some(serde_json::Value::Array(evidence)) => {
+                for entry in evidence {
+                    println!(
+                        "  evidence: {}",
+                        entry.as_str().unwrap_or(&entry.to_string())
+                    );
+                }
+            }
+            Some(serde_json::Value::String(evidence)) => println!("  evidence: {}", evidence),
+            _ => {}
+        }
+        match proposal.arguments.get("changes") {
+            Some(serde_json::Value::Array(changes)) => {
+                for change in changes {
+                    println!("  - {}", change.as_str().unwrap_or(&change.to_string()));
+                }
+            }
+            Some(serde_json::Value::String(changes)) => println!("  changes: {}", changes),
+            _ => {}
+        }
+        if let Some(verification) = proposal
+            .arguments
+            .get("verification")
+            .and_then(|v| v.as_str())
+        {
+            println!("  verification: {}", verification);
+        }
+        ask_for_verdict("Accept? [Y]es, or type feedback to send the agent back: ").await
+    }
+}
+
+#[async_trait]
+impl PlanReviewer for StdinApprovalGate {
+    /// Same answers as a completion review.
+    async fn review_plan(&self, plan: &ToolCall) -> Result<CompletionDecision> {
+        println!("plan proposed [{}]", plan.id);
+        let steps = plan.arguments.get("steps").and_then(|v| v.as_array());
+        for (index, step) in steps.into_iter().flatten().enumerate() {
+            println!(
+                "  {}. {}",
+                index + 1,
+                step.as_str().unwrap_or(&step.to_string())
+            );
+        }
+        ask_for_verdict("Approve plan? [Y]es, or type feedback to send the agent back: ").await
+    }
+}
+
+/// Reads one answer from stdin: empty, `y`, or end of input accepts;
+/// anything else is feedback.
+async fn ask_for_verdict(question: &str) -> Result<CompletionDecision> {
+    print!("{}", question);
+    std::io::stdout().flush().ok();
+
+    let line = tokio::task::spawn_blocking(|| {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line).map(|_| line)
+    })
+    .await
+    .context("review prompt task failed")?
+    .context("failed to read review answer from stdin")?;
+
+    Ok(match line.trim() {
+        "" | "y" | "Y" | "yes" => CompletionDecision::Accepted,
+        feedback => CompletionDecision::Rejected {
+            feedback: feedback.to_string(),
+        },
+    })
+}
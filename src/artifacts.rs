@@ -0,0 +1,185 @@
+use crate::event::Event;
+use crate::redact::Redactor;
+use crate::tools::fs::{compute_hash, write_atomically};
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Content-addressed blobs under `<data_dir>/artifacts`, named by their
+/// SHA-256. Events reference large payloads (shortened tool outputs, file
+/// snapshots) by artifact id instead of inlining them in the event log.
+#[derive(Debug, Clone)]
+pub struct ArtifactStore {
+    dir: PathBuf,
+    redactor: Option<Redactor>,
+}
+
+/// An artifact on disk, as listed by `rx artifacts list`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArtifactInfo {
+    pub id: String,
+    pub bytes: u64,
+}
+
+impl ArtifactStore {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            dir: data_dir.join("artifacts"),
+            redactor: None,
+        }
+    }
+
+    /// Redacts secrets from text artifacts before they are written, as the
+    /// event log does for event payloads.
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = (!redactor.is_empty()).then_some(redactor);
+        self
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf> {
+        if id.len() != 64 || !id.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(anyhow!("invalid artifact id {:?}", id));
+        }
+        Ok(self.dir.join(id.to_ascii_lowercase()))
+    }
+
+    /// Stores `bytes` as they are and returns their id. Storing the same
+    /// content twice writes it once.
+    pub async fn put(&self, bytes: &[u8]) -> Result<String> {
+        let id = compute_hash(bytes);
+        let path = self.path(&id)?;
+        if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            return Ok(id);
+        }
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .with_context(|| format!("failed to create {}", self.dir.display()))?;
+        write_atomically(&path, bytes)
+            .await
+            .with_context(|| format!("failed to write artifact {}", id))?;
+        Ok(id)
+    }
+
+    /// Stores `text` after secret redaction. The id is the hash of the
+    /// stored (redacted) text.
+    pub async fn put_text(&self, text: &str) -> Result<String> {
+        match &self.redactor {
+            Some(redactor) => self.put(redactor.redact_str(text).as_bytes()).await,
+            None => self.put(text.as_bytes()).await,
+        }
+    }
+
+    /// Stores `value` as JSON text, see [`ArtifactStore::put_text`].
+    pub async fn put_json(&self, value: &Value) -> Result<String> {
+        self.put_text(&value.to_string()).await
+    }
+
+    /// The content of artifact `id`, or `None` when it is not stored.
+    pub async fn get(&self, id: &str) -> Result<Option<Vec<u8>>> {
+        match tokio::fs::read(self.path(id)?).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error).with_context(|| format!("failed to read artifact {}", id)),
+        }
+    }
+
+    /// Artifact `id` parsed as JSON.
+    pub async fn get_json(&self, id: &str) -> Result<Option<Value>> {
+        self.get(id)
+            .await?
+            .map(|bytes| {
+                serde_json::from_slice(&bytes)
+                    .with_context(|| format!("artifact {} is not JSON", id))
+            })
+            .transpose()
+    }
+
+    /// Every stored artifact, sorted by id.
+    pub async fn list(&self) -> Result<Vec<ArtifactInfo>> {
+        let mut artifacts = Vec::new();
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(artifacts),
+            Err(error) => {
+                return Err(error).with_context(|| format!("failed to read {}", self.dir.display()))
+            }
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let Some(id) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            if self.path(&id).is_err() {
+                continue;
+            }
+            let bytes = entry.metadata().await.map(|meta| meta.len()).unwrap_or(0);
+            artifacts.push(ArtifactInfo { id, bytes });
+        }
+        artifacts.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(artifacts)
+    }
+}
+
+/// Artifact ids a goal's events point to: `artifact` fields of shortened
+/// tool outputs and the hashes of snapshotted files.
+pub fn referenced_artifacts(events: &[Event]) -> BTreeSet<String> {
+    let mut ids = BTreeSet::new();
+    for event in events {
+        match event.r#type.as_str() {
+            "file_snapshot" => ids.extend(
+                event.payload["files"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|file| file["hash"].as_str())
+                    .map(str::to_string),
+            ),
+            _ => ids.extend(event.payload["artifact"].as_str().map(str::to_string)),
+        }
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{referenced_artifacts, ArtifactStore};
+    use crate::event::Event;
+    use crate::redact::Redactor;
+    use crate::testing::TempDir;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[tokio::test]
+    async fn artifacts_are_stored_once_by_hash() {
+        let dir = TempDir::new("artifacts");
+        let store = ArtifactStore::new(&dir);
+        assert!(store.list().await.unwrap().is_empty());
+
+        let id = store.put(b"hello\n").await.unwrap();
+        assert_eq!(store.put(b"hello\n").await.unwrap(), id);
+        assert_eq!(store.get(&id).await.unwrap().unwrap(), b"hello\n");
+        let output = json!({ "stdout": "x".repeat(100) });
+        let json_id = store.put_json(&output).await.unwrap();
+        assert_eq!(store.get_json(&json_id).await.unwrap().unwrap(), output);
+        assert_eq!(store.list().await.unwrap().len(), 2);
+        assert!(store.get(&"0".repeat(64)).await.unwrap().is_none());
+        assert!(store.get("../secrets").await.is_err());
+
+        let env = BTreeMap::from([("TOKEN".to_string(), "s3cret-value".to_string())]);
+        let redacting =
+            ArtifactStore::new(&dir).with_redactor(Redactor::default().with_env_values(&env));
+        let id = redacting.put_text("token=s3cret-value").await.unwrap();
+        let stored = String::from_utf8(redacting.get(&id).await.unwrap().unwrap()).unwrap();
+        assert!(!stored.contains("s3cret-value"));
+
+        let events = vec![
+            Event::new("tool_output_full", json!({ "artifact": json_id })),
+            Event::new(
+                "file_snapshot",
+                json!({ "files": [{ "path": "a", "hash": id }] }),
+            ),
+        ];
+        assert_eq!(referenced_artifacts(&events).len(), 2);
+    }
+}
@@ -1,35 +1,33 @@
 use anyhow::{anyhow, bail, Context, Result};
+use rx::patch::{apply_hunks, normalize_lines, parse_patch, FileOp};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::{Component, Path, PathBuf};
+use std::path::{Path, PathBuf};
 
-#[derive(Debug)]
-enum FileOp {
-    Add {
-        path: String,
-        lines: Vec<String>,
-    },
-    Delete {
-        path: String,
-    },
-    Update {
-        path: String,
-        move_to: Option<String>,
-        hunks: Vec<Hunk>,
-    },
-}
+// The binary cannot reach the main crate's test fixtures; share the file.
+#[cfg(test)]
+#[path = "../testing.rs"]
+mod testing;
 
-#[derive(Debug, Clone)]
-struct Hunk {
-    lines: Vec<HunkLine>,
+#[derive(Debug, Default)]
+struct Options {
+    directory: Option<PathBuf>,
+    check: bool,
+    report_json: bool,
+    fuzz: usize,
+    patch_args: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
-enum HunkLine {
-    Context(String),
-    Remove(String),
-    Add(String),
+#[derive(Debug, Default)]
+struct Summary {
+    added_files: usize,
+    updated_files: usize,
+    deleted_files: usize,
+    moved_files: usize,
 }
 
+const USAGE: &str = "Usage: apply_patch [--directory ROOT] [--check] [--fuzz N] [--report json] [PATCH | -]\n\nReads the patch from stdin when no PATCH argument (or `-`) is given.\n  --directory ROOT   Resolve patch paths relative to ROOT instead of the current directory\n  --check            Validate that every operation applies, without writing anything\n  --fuzz N           Let hunks match ignoring whitespace and up to N edge context lines (max 3)\n  --report json      Print a JSON summary of the applied (or checked) operations";
+
 fn main() {
     if let Err(err) = run() {
         eprintln!("{}", err);
@@ -38,8 +36,8 @@ fn main() {
 }
 
 fn run() -> Result<()> {
-    let args: Vec<String> = std::env::args().skip(1).collect();
-    let patch_text = if args.is_empty() {
+    let options = parse_options(std::env::args().skip(1))?;
+    let patch_text = if options.patch_args.is_empty() || options.patch_args == ["-"] {
         use std::io::Read;
         let mut input = String::new();
         std::io::stdin()
@@ -47,363 +45,204 @@ fn run() -> Result<()> {
             .context("failed to read patch from stdin")?;
         input
     } else {
-        args.join(" ")
+        options.patch_args.join(" ")
     };
 
     if patch_text.trim().is_empty() {
         bail!("empty patch input")
     }
 
+    let root = options
+        .directory
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("."));
+    if !root.is_dir() {
+        bail!("--directory {} is not a directory", root.display())
+    }
+
     let ops = parse_patch(&patch_text)?;
-    apply_ops(&ops)?;
+    let (changes, summary) = stage_ops(&root, &ops, options.fuzz)?;
+    if !options.check {
+        write_changes(&changes)?;
+    }
+
+    if options.report_json {
+        let report = serde_json::json!({
+            "check": options.check,
+            "applied": !options.check,
+            "added_files": summary.added_files,
+            "updated_files": summary.updated_files,
+            "deleted_files": summary.deleted_files,
+            "moved_files": summary.moved_files,
+        });
+        println!("{}", report);
+    }
     Ok(())
 }
 
-fn parse_patch(input: &str) -> Result<Vec<FileOp>> {
-    let lines: Vec<&str> = input
-        .lines()
-        .map(|line| line.strip_suffix('\r').unwrap_or(line))
-        .collect();
-
-    if lines.is_empty() {
-        bail!("patch is empty")
-    }
-
-    let mut index = 0;
-    require_line(&lines, index, "*** Begin Patch")?;
-    index += 1;
+fn parse_options(args: impl Iterator<Item = String>) -> Result<Options> {
+    let mut options = Options::default();
+    let mut args = args;
 
-    let mut ops = Vec::new();
-
-    while index < lines.len() {
-        let line = lines[index];
-        if line == "*** End Patch" {
-            index += 1;
-            if index != lines.len() {
-                bail!("unexpected content after *** End Patch")
-            }
-            return Ok(ops);
-        }
-
-        if let Some(path) = line.strip_prefix("*** Add File: ") {
-            validate_relative_path(path)?;
-            index += 1;
-            let mut added = Vec::new();
-            while index < lines.len() {
-                let current = lines[index];
-                if is_file_header(current) || current == "*** End Patch" {
-                    break;
-                }
-                let content = current
-                    .strip_prefix('+')
-                    .ok_or_else(|| anyhow!("invalid add-file line: expected '+' prefix"))?;
-                added.push(content.to_string());
-                index += 1;
-            }
-            ops.push(FileOp::Add {
-                path: path.to_string(),
-                lines: added,
-            });
-            continue;
-        }
-
-        if let Some(path) = line.strip_prefix("*** Delete File: ") {
-            validate_relative_path(path)?;
-            ops.push(FileOp::Delete {
-                path: path.to_string(),
-            });
-            index += 1;
+    while let Some(arg) = args.next() {
+        if !options.patch_args.is_empty() {
+            options.patch_args.push(arg);
             continue;
         }
 
-        if let Some(path) = line.strip_prefix("*** Update File: ") {
-            validate_relative_path(path)?;
-            index += 1;
-
-            let mut move_to = None;
-            if index < lines.len() {
-                if let Some(new_path) = lines[index].strip_prefix("*** Move to: ") {
-                    validate_relative_path(new_path)?;
-                    move_to = Some(new_path.to_string());
-                    index += 1;
-                }
+        match arg.as_str() {
+            "--directory" | "-d" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--directory requires a path"))?;
+                options.directory = Some(PathBuf::from(value));
             }
-
-            let mut hunks = Vec::new();
-            while index < lines.len() {
-                let current = lines[index];
-                if is_file_header(current) || current == "*** End Patch" {
-                    break;
-                }
-                if !current.starts_with("@@") {
-                    bail!("expected hunk header '@@', got: {}", current);
-                }
-
-                index += 1;
-                let mut hunk_lines = Vec::new();
-                while index < lines.len() {
-                    let hline = lines[index];
-                    if hline.starts_with("@@") || is_file_header(hline) || hline == "*** End Patch"
-                    {
-                        break;
-                    }
-                    if hline == "*** End of File" {
-                        index += 1;
-                        break;
-                    }
-                    let mut chars = hline.chars();
-                    let marker = chars
-                        .next()
-                        .ok_or_else(|| anyhow!("empty hunk line is invalid"))?;
-                    let tail: String = chars.collect();
-                    match marker {
-                        ' ' => hunk_lines.push(HunkLine::Context(tail)),
-                        '-' => hunk_lines.push(HunkLine::Remove(tail)),
-                        '+' => hunk_lines.push(HunkLine::Add(tail)),
-                        _ => bail!("invalid hunk line prefix '{}'", marker),
-                    }
-                    index += 1;
-                }
-
-                if hunk_lines.is_empty() {
-                    bail!("empty hunk is invalid")
-                }
-                hunks.push(Hunk { lines: hunk_lines });
+            "--check" => options.check = true,
+            "--fuzz" | "-F" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--fuzz requires a level"))?;
+                options.fuzz = value
+                    .parse()
+                    .map_err(|_| anyhow!("--fuzz expects a number, got '{}'", value))?;
             }
-
-            if hunks.is_empty() {
-                bail!("update operation for '{}' has no hunks", path)
+            "--report" => match args.next().as_deref() {
+                Some("json") => options.report_json = true,
+                Some(other) => bail!("unsupported --report format '{}'; expected 'json'", other),
+                None => bail!("--report requires a format"),
+            },
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
             }
-
-            ops.push(FileOp::Update {
-                path: path.to_string(),
-                move_to,
-                hunks,
-            });
-            continue;
+            "--" => options.patch_args.extend(args.by_ref()),
+            _ => options.patch_args.push(arg),
         }
-
-        bail!("unknown patch section header: {}", line)
-    }
-
-    bail!("missing *** End Patch")
-}
-
-fn require_line(lines: &[&str], index: usize, expected: &str) -> Result<()> {
-    let found = lines
-        .get(index)
-        .copied()
-        .ok_or_else(|| anyhow!("patch ended early; expected {}", expected))?;
-    if found != expected {
-        bail!("expected '{}', got '{}'", expected, found);
     }
-    Ok(())
-}
 
-fn is_file_header(line: &str) -> bool {
-    line.starts_with("*** Add File: ")
-        || line.starts_with("*** Delete File: ")
-        || line.starts_with("*** Update File: ")
+    Ok(options)
 }
 
-fn validate_relative_path(path: &str) -> Result<()> {
-    if path.trim().is_empty() {
-        bail!("path cannot be empty")
-    }
+/// Pending file contents keyed by path; `None` marks a deletion. Every
+/// operation is staged against this overlay before anything touches disk, so
+/// a failing hunk never leaves a partially applied patch behind.
+type Changes = BTreeMap<PathBuf, Option<String>>;
 
-    let parsed = Path::new(path);
-    if parsed.is_absolute() {
-        bail!("path must be relative: {}", path)
-    }
-
-    for component in parsed.components() {
-        match component {
-            Component::CurDir | Component::Normal(_) => {}
-            Component::ParentDir => bail!("parent path '..' is not allowed: {}", path),
-            _ => bail!("invalid path component in {}", path),
-        }
-    }
+fn stage_ops(root: &Path, ops: &[FileOp], fuzz: usize) -> Result<(Changes, Summary)> {
+    let mut changes = Changes::new();
+    let mut summary = Summary::default();
 
-    Ok(())
-}
-
-fn apply_ops(ops: &[FileOp]) -> Result<()> {
     for op in ops {
         match op {
-            FileOp::Add { path, lines } => apply_add(path, lines)?,
-            FileOp::Delete { path } => apply_delete(path)?,
+            FileOp::Add { path, lines } => {
+                let full_path = root.join(path);
+                if staged_exists(&changes, &full_path) {
+                    bail!("add file failed: '{}' already exists", path)
+                }
+                changes.insert(full_path, Some(normalize_lines(lines)));
+                summary.added_files += 1;
+            }
+            FileOp::Delete { path } => {
+                let full_path = root.join(path);
+                if !staged_exists(&changes, &full_path) {
+                    bail!("delete file failed: '{}' does not exist", path)
+                }
+                changes.insert(full_path, None);
+                summary.deleted_files += 1;
+            }
             FileOp::Update {
                 path,
                 move_to,
                 hunks,
-            } => apply_update(path, move_to.as_deref(), hunks)?,
+            } => {
+                let source_path = root.join(path);
+                let original = match changes.get(&source_path) {
+                    Some(Some(content)) => content.clone(),
+                    Some(None) => bail!("update file failed: '{}' does not exist", path),
+                    None => {
+                        if !source_path.exists() {
+                            bail!("update file failed: '{}' does not exist", path)
+                        }
+                        fs::read_to_string(&source_path)
+                            .with_context(|| format!("failed to read {}", path))?
+                    }
+                };
+                let (updated, _) = apply_hunks(&original, hunks, fuzz)
+                    .with_context(|| format!("failed to patch {}", path))?;
+
+                match move_to {
+                    Some(target) if target != path => {
+                        changes.insert(root.join(target), Some(updated));
+                        changes.insert(source_path, None);
+                        summary.moved_files += 1;
+                    }
+                    _ => {
+                        changes.insert(source_path, Some(updated));
+                    }
+                }
+                summary.updated_files += 1;
+            }
         }
     }
-    Ok(())
-}
-
-fn apply_add(path: &str, lines: &[String]) -> Result<()> {
-    let full_path = PathBuf::from(path);
-    if full_path.exists() {
-        bail!("add file failed: '{}' already exists", path)
-    }
 
-    if let Some(parent) = full_path.parent() {
-        fs::create_dir_all(parent)
-            .with_context(|| format!("failed to create parent directories for {}", path))?;
-    }
-
-    fs::write(&full_path, normalize_lines(lines))
-        .with_context(|| format!("failed to write {}", path))?;
-    Ok(())
+    Ok((changes, summary))
 }
 
-fn apply_delete(path: &str) -> Result<()> {
-    let full_path = PathBuf::from(path);
-    if !full_path.exists() {
-        bail!("delete file failed: '{}' does not exist", path)
+fn staged_exists(changes: &Changes, path: &Path) -> bool {
+    match changes.get(path) {
+        Some(content) => content.is_some(),
+        None => path.exists(),
     }
-
-    fs::remove_file(&full_path).with_context(|| format!("failed to delete {}", path))?;
-    Ok(())
 }
 
-fn apply_update(path: &str, move_to: Option<&str>, hunks: &[Hunk]) -> Result<()> {
-    let source_path = PathBuf::from(path);
-    if !source_path.exists() {
-        bail!("update file failed: '{}' does not exist", path)
-    }
-
-    let original =
-        fs::read_to_string(&source_path).with_context(|| format!("failed to read {}", path))?;
-    let updated =
-        apply_hunks(&original, hunks).with_context(|| format!("failed to patch {}", path))?;
-
-    let dest_path = move_to
-        .map(PathBuf::from)
-        .unwrap_or_else(|| source_path.clone());
-    if let Some(parent) = dest_path.parent() {
-        fs::create_dir_all(parent).with_context(|| {
-            format!(
-                "failed to create parent directories for {}",
-                dest_path.display()
-            )
-        })?;
-    }
-
-    fs::write(&dest_path, updated)
-        .with_context(|| format!("failed to write {}", dest_path.display()))?;
-
-    if let Some(target) = move_to {
-        if target != path {
-            fs::remove_file(&source_path)
-                .with_context(|| format!("failed to remove moved source file {}", path))?;
+fn write_changes(changes: &Changes) -> Result<()> {
+    for (path, content) in changes {
+        match content {
+            Some(content) => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).with_context(|| {
+                        format!("failed to create parent directories for {}", path.display())
+                    })?;
+                }
+                fs::write(path, content)
+                    .with_context(|| format!("failed to write {}", path.display()))?;
+            }
+            None => {
+                if path.exists() {
+                    fs::remove_file(path)
+                        .with_context(|| format!("failed to delete {}", path.display()))?;
+                }
+            }
         }
     }
-
     Ok(())
 }
 
-fn apply_hunks(original: &str, hunks: &[Hunk]) -> Result<String> {
-    let mut lines: Vec<String> = original
-        .lines()
-        .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
-        .collect();
-    let mut cursor = 0usize;
-
-    for hunk in hunks {
-        let expected_old: Vec<&str> = hunk
-            .lines
-            .iter()
-            .filter_map(|line| match line {
-                HunkLine::Context(text) | HunkLine::Remove(text) => Some(text.as_str()),
-                HunkLine::Add(_) => None,
-            })
-            .collect();
-
-        let replacement: Vec<String> = hunk
-            .lines
-            .iter()
-            .filter_map(|line| match line {
-                HunkLine::Context(text) | HunkLine::Add(text) => Some(text.clone()),
-                HunkLine::Remove(_) => None,
-            })
-            .collect();
-
-        let match_pos = find_match(&lines, &expected_old, cursor)
-            .or_else(|| find_match(&lines, &expected_old, 0))
-            .ok_or_else(|| anyhow!("could not locate hunk context in target file"))?;
-
-        let old_len = expected_old.len();
-        lines.splice(match_pos..(match_pos + old_len), replacement.clone());
-        cursor = match_pos + replacement.len();
-    }
-
-    let mut output = lines.join("\n");
-    if original.ends_with('\n') {
-        output.push('\n');
-    }
-    Ok(output)
-}
-
-fn find_match(lines: &[String], expected_old: &[&str], start: usize) -> Option<usize> {
-    if expected_old.is_empty() {
-        return Some(start.min(lines.len()));
-    }
-    if expected_old.len() > lines.len() || start > lines.len() {
-        return None;
-    }
-
-    let end = lines.len() - expected_old.len();
-    for idx in start..=end {
-        let window = &lines[idx..idx + expected_old.len()];
-        if window.iter().zip(expected_old.iter()).all(|(a, b)| a == b) {
-            return Some(idx);
-        }
-    }
-    None
-}
-
-fn normalize_lines(lines: &[String]) -> String {
-    if lines.is_empty() {
-        return String::new();
-    }
-    let mut out = lines.join("\n");
-    out.push('\n');
-    out
-}
-
 #[cfg(test)]
 mod tests {
+    use super::testing::TempDir;
     use super::*;
 
     #[test]
-    fn parse_add_update_delete() {
-        let patch = "*** Begin Patch\n*** Add File: hello.txt\n+Hello\n*** Update File: src/a.rs\n@@\n-old\n+new\n*** Delete File: stale.txt\n*** End Patch\n";
-        let ops = parse_patch(patch).expect("patch should parse");
-        assert_eq!(ops.len(), 3);
-    }
-
-    #[test]
-    fn reject_absolute_path() {
-        let patch = "*** Begin Patch\n*** Add File: /tmp/evil\n+nope\n*** End Patch\n";
-        let err = parse_patch(patch).expect_err("absolute path should fail");
-        assert!(err.to_string().contains("path must be relative"));
+    fn parse_options_reads_flags_before_patch_text() {
+        let args = ["--directory", "repo", "--check", "--report", "json", "-"]
+            .iter()
+            .map(|arg| arg.to_string());
+        let options = parse_options(args).expect("options should parse");
+        assert_eq!(options.directory, Some(PathBuf::from("repo")));
+        assert!(options.check);
+        assert!(options.report_json);
+        assert_eq!(options.patch_args, vec!["-".to_string()]);
     }
 
     #[test]
-    fn apply_single_hunk() {
-        let original = "a\nb\nc\n";
-        let hunk = Hunk {
-            lines: vec![
-                HunkLine::Context("a".to_string()),
-                HunkLine::Remove("b".to_string()),
-                HunkLine::Add("x".to_string()),
-                HunkLine::Context("c".to_string()),
-            ],
-        };
+    fn stage_ops_rejects_missing_update_without_writing() {
+        let root = TempDir::new("apply-patch-test");
+        let patch = "*** Begin Patch\n*** Add File: new.txt\n+hi\n*** Update File: missing.txt\n@@\n-a\n+b\n*** End Patch\n";
+        let ops = parse_patch(patch).expect("patch should parse");
 
-        let out = apply_hunks(original, &[hunk]).expect("hunk should apply");
-        assert_eq!(out, "a\nx\nc\n");
+        let err = stage_ops(&root, &ops, 0).expect_err("missing file should fail");
+        assert!(err.to_string().contains("does not exist"));
+        assert!(!root.join("new.txt").exists());
     }
 }
@@ -0,0 +1,178 @@
+use crate::event::Event;
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::io::Write;
+use std::path::Path;
+use tokio::process::Command;
+
+const FILE_HEADER: &str = "# Blocked goals\n\nRuns that ended with a `blocked` outcome. Answer the blocker, then resume the goal.\n";
+
+/// Structured details of a run that ended with a `blocked` outcome.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockedReport {
+    pub goal_id: String,
+    pub goal: String,
+    pub summary: String,
+    pub evidence: Vec<String>,
+    pub details: Option<String>,
+    pub timestamp: String,
+}
+
+impl BlockedReport {
+    /// The report for `history` when its accepted completion was `blocked`.
+    pub fn from_history(goal_id: &str, history: &[Event]) -> Option<Self> {
+        let termination = history
+            .iter()
+            .rev()
+            .find(|event| event.r#type == "termination")?;
+        if termination.payload.get("outcome").and_then(Value::as_str) != Some("blocked") {
+            return None;
+        }
+        let proposal = &termination.payload["details"];
+        let text = |value: &Value| match value {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        let evidence = match &proposal["evidence"] {
+            Value::Array(entries) => entries.iter().map(text).collect(),
+            Value::Null => Vec::new(),
+            other => vec![text(other)],
+        };
+        Some(Self {
+            goal_id: goal_id.to_string(),
+            goal: history
+                .iter()
+                .find(|event| event.r#type == "goal")
+                .and_then(|event| event.payload.get("goal"))
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            summary: proposal["summary"].as_str().unwrap_or_default().to_string(),
+            evidence,
+            details: Some(&proposal["details"])
+                .filter(|details| !details.is_null())
+                .map(text),
+            timestamp: termination
+                .timestamp
+                .format("%Y-%m-%d %H:%M UTC")
+                .to_string(),
+        })
+    }
+
+    pub fn title(&self) -> String {
+        format!("rx blocked: {}", self.summary)
+    }
+
+    /// Markdown body shared by `BLOCKED.md` entries and issues.
+    pub fn body(&self) -> String {
+        let mut body = format!("**Goal:** {}\n\n**Blocker:** {}\n", self.goal, self.summary);
+        if !self.evidence.is_empty() {
+            body.push_str("\n**Evidence:**\n");
+            for entry in &self.evidence {
+                body.push_str(&format!("- {}\n", entry));
+            }
+        }
+        if let Some(details) = &self.details {
+            body.push_str(&format!("\n**Details:** {}\n", details));
+        }
+        body.push_str(&format!(
+            "\nResume with `rx resume {} \"<answer>\"`.\n",
+            self.goal_id
+        ));
+        body
+    }
+
+    /// Appends the report to a Markdown file, creating it with a header.
+    pub fn append_to(&self, path: &Path) -> Result<()> {
+        let new_file = !path.exists();
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        let mut entry = String::new();
+        if new_file {
+            entry.push_str(FILE_HEADER);
+        }
+        entry.push_str(&format!(
+            "\n## {} ({})\n\n{}",
+            self.goal_id,
+            self.timestamp,
+            self.body()
+        ));
+        file.write_all(entry.as_bytes())
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Opens a GitHub issue with the `gh` CLI and returns its URL.
+    pub async fn file_github_issue(&self, labels: &[String]) -> Result<String> {
+        let mut command = Command::new("gh");
+        command
+            .args(["issue", "create", "--title"])
+            .arg(self.title());
+        command.arg("--body").arg(self.body());
+        for label in labels {
+            command.arg("--label").arg(label);
+        }
+        let output = command
+            .kill_on_drop(true)
+            .output()
+            .await
+            .context("failed to run gh")?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "gh issue create failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlockedReport;
+    use crate::event::Event;
+    use serde_json::json;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn blocked_completions_are_appended_under_a_header() {
+        let mut history = vec![
+            Event::new("goal", json!({ "goal": "deploy the app" })),
+            Event::new(
+                "termination",
+                json!({
+                    "reason": "done",
+                    "outcome": "blocked",
+                    "details": {
+                        "summary": "missing deploy token",
+                        "evidence": ["DEPLOY_TOKEN is unset"],
+                        "details": null
+                    }
+                }),
+            ),
+        ];
+        let report = BlockedReport::from_history("g1", &history).expect("blocked report");
+        assert_eq!(report.title(), "rx blocked: missing deploy token");
+        assert!(report.body().contains("- DEPLOY_TOKEN is unset\n"));
+        assert!(report
+            .body()
+            .ends_with("Resume with `rx resume g1 \"<answer>\"`.\n"));
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("rx-blocked-{}.md", nanos));
+        report.append_to(&path).unwrap();
+        report.append_to(&path).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.matches("# Blocked goals").count(), 1);
+        assert_eq!(contents.matches("## g1 (").count(), 2);
+        let _ = std::fs::remove_file(path);
+
+        history[1].payload["outcome"] = json!("success");
+        assert_eq!(BlockedReport::from_history("g1", &history), None);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::model::ToolCall;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::Mutex;
+
+/// Tools that accept a file-state precondition, so a conflict is plausible.
+const CONFLICT_TOOLS: &[&str] = &["write_file", "replace_in_file", "edit_lines"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChaosFault {
+    Timeout,
+    TruncatedOutput,
+    PreconditionConflict,
+}
+
+/// Injects simulated tool failures into a fraction of calls (`--chaos`) to
+/// exercise prompts and recovery paths. Seeded, so a run can be repeated.
+#[derive(Debug)]
+pub struct Chaos {
+    rate: f64,
+    seed: u64,
+    state: Mutex<u64>,
+}
+
+impl Chaos {
+    pub fn new(rate: f64, seed: u64) -> Self {
+        Self {
+            rate: rate.clamp(0.0, 1.0),
+            seed,
+            state: Mutex::new(seed),
+        }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The fault to inject into this call, if any. `propose_done` is never
+    /// disturbed.
+    pub fn pick(&self, tool_call: &ToolCall) -> Option<ChaosFault> {
+        if tool_call.name == "propose_done" || self.next_unit() >= self.rate {
+            return None;
+        }
+        let mut faults = vec![ChaosFault::Timeout, ChaosFault::TruncatedOutput];
+        if CONFLICT_TOOLS.contains(&tool_call.name.as_str()) {
+            faults.push(ChaosFault::PreconditionConflict);
+        }
+        let index = (self.next_unit() * faults.len() as f64) as usize;
+        Some(faults[index.min(faults.len() - 1)])
+    }
+
+    /// A uniform value in `[0, 1)` from a splitmix64 sequence.
+    fn next_unit(&self) -> f64 {
+        let mut state = self.state.lock().expect("chaos rng lock");
+        *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Output standing in for a call that never runs because of `fault`, or
+/// `None` when the tool runs and its output is truncated instead.
+pub fn fault_output(fault: ChaosFault, tool_call: &ToolCall) -> Option<Value> {
+    match fault {
+        ChaosFault::Timeout => Some(json!({
+            "error": "timed_out",
+            "details": "the tool call was cancelled; try a different approach or a non-interactive command"
+        })),
+        ChaosFault::PreconditionConflict => Some(json!({
+            "success": false,
+            "error": "precondition_failed",
+            "path": tool_call.arguments.get("path").cloned().unwrap_or(Value::Null),
+            "details": "the file changed since it was last read; read it again before writing"
+        })),
+        ChaosFault::TruncatedOutput => None,
+    }
+}
+
+/// Cuts the longest string in `output` to half its length, as if the tool's
+/// output had been clipped mid-stream.
+pub fn truncate_output(mut output: Value) -> Value {
+    fn longest(value: &mut Value) -> Option<&mut String> {
+        match value {
+            Value::String(text) => Some(text),
+            Value::Array(items) => items
+                .iter_mut()
+                .filter_map(longest)
+                .max_by_key(|text| text.len()),
+            Value::Object(fields) => fields
+                .values_mut()
+                .filter_map(longest)
+                .max_by_key(|text| text.len()),
+            _ => None,
+        }
+    }
+    if let Some(text) = longest(&mut output) {
+        let keep = text.chars().count() / 2;
+        *text = text.chars().take(keep).collect();
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{truncate_output, Chaos, ChaosFault};
+    use crate::model::ToolCall;
+    use serde_json::json;
+
+    fn call(name: &str) -> ToolCall {
+        ToolCall {
+            id: "1".to_string(),
+            name: name.to_string(),
+            arguments: json!({ "path": "a.txt" }),
+        }
+    }
+
+    #[test]
+    fn faults_follow_the_rate_and_seed() {
+        let never = Chaos::new(0.0, 7);
+        assert!((0..100).all(|_| never.pick(&call("write_file")).is_none()));
+
+        let always = Chaos::new(1.0, 7);
+        assert!(always.pick(&call("propose_done")).is_none());
+        let faults: Vec<_> = (0..200)
+            .filter_map(|_| always.pick(&call("write_file")))
+            .collect();
+        assert_eq!(faults.len(), 200);
+        assert!(faults.contains(&ChaosFault::PreconditionConflict));
+        assert!(
+            (0..200).all(|_| always.pick(&call("exec")) != Some(ChaosFault::PreconditionConflict))
+        );
+
+        let first: Vec<_> = (0..20)
+            .map(|_| Chaos::new(0.5, 42))
+            .map(|chaos| chaos.pick(&call("exec")))
+            .collect();
+        assert!(first.windows(2).all(|pair| pair[0] == pair[1]));
+
+        assert_eq!(
+            truncate_output(json!({ "stdout": "abcdefgh", "stderr": "xy", "code": 0 })),
+            json!({ "stdout": "abcd", "stderr": "xy", "code": 0 })
+        );
+    }
+}
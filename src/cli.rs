@@ -0,0 +1,591 @@
+use crate::model::ReasoningEffort;
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Command-line interface. `rx <goal words>` is shorthand for `rx run`.
+#[derive(Debug, Parser)]
+#[command(
+    name = "rx",
+    version,
+    about = "Autonomous agent with a minimal kernel loop",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+
+    /// Only print warnings and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print more: -v adds tool inputs and outputs, -vv model requests and responses
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+}
+
+impl Cli {
+    /// Options of a command that runs the kernel (`run`, `resume`, `fork`,
+    /// or a bare goal).
+    pub fn run_options(&self) -> Option<&RunOptions> {
+        match &self.command {
+            None => Some(&self.run.options),
+            Some(Command::Run(run)) => Some(&run.options),
+            Some(Command::Resume { options, .. } | Command::Fork { options, .. }) => Some(options),
+            Some(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start a new goal
+    Run(RunArgs),
+    /// Continue a previous goal from its event log
+    Resume {
+        /// Goal id as printed by `rx list`; `goal_id@N` branches a new goal
+        /// from the history through event N
+        goal_id: String,
+
+        #[command(flatten)]
+        options: RunOptions,
+
+        /// Optional follow-up instruction added before continuing
+        #[arg(trailing_var_arg = true)]
+        message: Vec<String>,
+    },
+    /// Copy a goal's history into a new goal and continue from there
+    Fork {
+        /// Goal id as printed by `rx list`
+        goal_id: String,
+
+        #[command(flatten)]
+        options: RunOptions,
+
+        /// Optional new instruction for the fork
+        #[arg(trailing_var_arg = true)]
+        instruction: Vec<String>,
+    },
+    /// List goals that have an event log
+    List,
+    /// Print the event history of a goal
+    Show {
+        /// Goal id as printed by `rx list`
+        goal_id: String,
+
+        /// Print the raw events as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+    /// Aggregate analytics across every stored goal
+    Stats {
+        /// Print the aggregates as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Run a suite of benchmark tasks, each as a fresh goal in a temporary workspace
+    Eval {
+        /// TOML suite with one [[task]] table per task
+        suite: PathBuf,
+
+        /// Model for every task (passed to `rx run --model`)
+        #[arg(long = "model", value_name = "NAME")]
+        model_name: Option<String>,
+
+        /// Keep task workspaces for inspection
+        #[arg(long)]
+        keep: bool,
+
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Restore files changed by a goal to an earlier snapshot
+    Undo {
+        /// Goal id as printed by `rx list`
+        goal_id: String,
+
+        /// Undo every change from this snapshot on (sequence number from `--list`)
+        #[arg(long, value_name = "SEQ")]
+        to: Option<u64>,
+
+        /// List the changes that can be undone instead of undoing one
+        #[arg(long)]
+        list: bool,
+    },
+    /// Diff two goals' tool call sequences and outcomes
+    Compare {
+        /// Baseline goal id
+        goal_a: String,
+        /// Goal id to compare against the baseline
+        goal_b: String,
+    },
+    /// Write a goal's events to a JSONL file (stdout when --out is omitted)
+    Export {
+        /// Goal id as printed by `rx list`
+        goal_id: String,
+
+        /// Destination file
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+    /// Add a goal exported with `rx export` to the local logs
+    Import {
+        /// JSONL file produced by `rx export`
+        file: PathBuf,
+    },
+    /// List and print blobs in the content-addressed artifact store
+    Artifacts {
+        #[command(subcommand)]
+        command: ArtifactsCommand,
+    },
+    /// Build or refresh search indexes under .rx/index
+    Index {
+        #[command(subcommand)]
+        command: IndexCommand,
+    },
+    /// Print the effective configuration from the user and workspace config files
+    Config {
+        /// Show the tool selection and defaults of this agent profile
+        #[arg(long, value_name = "NAME")]
+        agent: Option<String>,
+    },
+    /// Interactively choose a provider, API key, default model, and auto-commit preference
+    Setup,
+    /// Export the built-in tools as an MCP server over stdio
+    McpServe,
+    /// Serve an HTTP API to start, list, stream, and cancel goals in this workspace
+    Serve {
+        /// Port to listen on
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+
+        /// Address to bind; keep the loopback default unless the network is trusted
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Goals to run at once; more wait in the queue (overrides [serve])
+        #[arg(long, value_name = "N", value_parser = parse_positive)]
+        max_concurrent: Option<usize>,
+    },
+    /// Enqueue and inspect goals of a running `rx serve`
+    Queue {
+        /// Server URL (default $RX_SERVER, then http://127.0.0.1:8080)
+        #[arg(long, value_name = "URL")]
+        server: Option<String>,
+
+        /// Workspace of the server's `[[serve.workspaces]]` (default `default`)
+        #[arg(long, value_name = "NAME")]
+        workspace: Option<String>,
+
+        #[command(subcommand)]
+        command: QueueCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum QueueCommand {
+    /// Queue a goal; options after `--` are passed to `rx run`
+    Add {
+        /// Goal or task description; multiple words are joined by spaces
+        #[arg(required = true)]
+        goal: Vec<String>,
+
+        /// Directory the goal works in
+        #[arg(long, value_name = "DIR")]
+        workdir: Option<PathBuf>,
+
+        /// `rx run` options for the goal
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+    /// List queued, running, and finished jobs
+    List {
+        /// Print the jobs as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print one job as JSON
+    Show {
+        /// Job id as printed by `rx queue add`
+        job_id: String,
+    },
+    /// Remove a queued job, or cancel its goal if it is running
+    Cancel {
+        /// Job id as printed by `rx queue add`
+        job_id: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ArtifactsCommand {
+    /// List stored artifacts with their sizes
+    List {
+        /// Only the artifacts this goal's events reference
+        goal_id: Option<String>,
+    },
+    /// Write an artifact's content to stdout (or a file)
+    Get {
+        /// Artifact id (SHA-256) as printed by `rx artifacts list`
+        id: String,
+        /// Destination file
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum IndexCommand {
+    /// Index the workspace for the search tools
+    Build {
+        /// Index to build
+        #[arg(long, value_enum, default_value_t = IndexKind::Keyword)]
+        kind: IndexKind,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum IndexKind {
+    /// BM25 keyword index used by keyword_search
+    Keyword,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable progress lines
+    Text,
+    /// One JSON object per event (iteration, tool_call, tool_output, usage, termination)
+    Json,
+}
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub options: RunOptions,
+
+    /// Goal or task description; multiple words are joined by spaces
+    #[arg(required = true, trailing_var_arg = true)]
+    pub goal: Vec<String>,
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct RunOptions {
+    /// Maximum number of kernel iterations
+    #[arg(long, value_name = "N", default_value_t = 50)]
+    pub max_iterations: usize,
+
+    /// Model name (overrides OPENAI_MODEL)
+    #[arg(long = "model", value_name = "NAME")]
+    pub model_name: Option<String>,
+
+    /// Small model for goal slugs and commit messages (overrides config)
+    #[arg(long = "small-model", value_name = "NAME")]
+    pub small_model: Option<String>,
+
+    /// Sampling temperature for agent requests, 0.0-2.0 (overrides
+    /// [model].temperature)
+    #[arg(long, value_name = "T", value_parser = parse_temperature)]
+    pub temperature: Option<f64>,
+
+    /// Reasoning effort for reasoning models (overrides
+    /// [model].reasoning_effort)
+    #[arg(long, value_enum, value_name = "LEVEL")]
+    pub reasoning_effort: Option<ReasoningEffort>,
+
+    /// Commit changes after each tool output when the staged diff is non-empty
+    #[arg(long)]
+    pub auto_commit: bool,
+
+    /// Auto-commit on a new branch rx/<goal-slug> and, when done, write a
+    /// pull request title and description
+    #[arg(long, conflicts_with = "isolated")]
+    pub branch: bool,
+
+    /// With --branch, push the branch and open the pull request with `gh`
+    #[arg(long, requires = "branch")]
+    pub pr: bool,
+
+    /// Print tool inputs and outputs
+    #[arg(long)]
+    pub tool_verbose: bool,
+
+    /// Mirror all events to a JSONL debug file
+    #[arg(long = "debug-log", value_name = "PATH")]
+    pub debug_log_path: Option<PathBuf>,
+
+    /// Ask before running exec/bash/write tool calls
+    #[arg(long, conflicts_with = "output")]
+    pub confirm: bool,
+
+    /// Full-screen view of the run (messages, current tool call, git diff,
+    /// tokens and cost) with keys to pause, approve, or abort
+    #[arg(long, conflicts_with = "output")]
+    pub tui: bool,
+
+    /// `json` prints the run's events as newline-delimited JSON on stdout
+    /// instead of progress lines
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    /// Work in DIR instead of the launch directory: the workspace root is
+    /// discovered from it and commands run there
+    #[arg(long, value_name = "DIR")]
+    pub workdir: Option<PathBuf>,
+
+    /// Run in a git worktree on branch rx/<goal_id>, then merge or write a
+    /// patch when done, leaving this checkout untouched meanwhile
+    #[arg(long)]
+    pub isolated: bool,
+
+    /// Confine file tools to this directory (relative to the launch directory or --workdir)
+    #[arg(long, value_name = "DIR")]
+    pub scope: Option<PathBuf>,
+
+    /// Export a variable to tool subprocesses (repeatable; overrides [env])
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env_assignment)]
+    pub env: Vec<(String, String)>,
+
+    /// Stop the run after this much wall-clock time (e.g. 90s, 15m, 1h30m)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub max_duration: Option<Duration>,
+
+    /// Preempt a tool call still running this long after its iteration began
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub max_iteration_time: Option<Duration>,
+
+    /// Stop the run once estimated model cost reaches this many USD
+    #[arg(long, value_name = "USD")]
+    pub max_cost: Option<f64>,
+
+    /// Inject tool timeouts, truncated outputs, and write conflicts into this
+    /// fraction of tool calls (0.0-1.0)
+    #[arg(long, value_name = "RATE", value_parser = parse_rate)]
+    pub chaos: Option<f64>,
+
+    /// Seed for --chaos so a run's faults can be repeated
+    #[arg(long, value_name = "N", requires = "chaos")]
+    pub chaos_seed: Option<u64>,
+
+    /// System prompt file (overrides the agent profile and [cli_defaults].prompt)
+    #[arg(long, value_name = "PATH")]
+    pub prompt: Option<PathBuf>,
+
+    /// Agent profile from the [agent] config section
+    #[arg(long, value_name = "NAME")]
+    pub agent: Option<String>,
+
+    /// Have the model submit a plan of steps for approval before changing anything
+    #[arg(long)]
+    pub plan: bool,
+
+    /// Shell command that must pass before a `success` proposal is accepted
+    /// (overrides [cli_defaults].verify_command)
+    #[arg(long, value_name = "CMD")]
+    pub verify_command: Option<String>,
+
+    /// Save every model request and response to a JSONL fixture
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Serve model responses from a --record fixture instead of a provider
+    #[arg(long, value_name = "PATH")]
+    pub replay: Option<PathBuf>,
+
+    /// Stream the run's events to WebSocket clients at ws://ADDR/ws
+    #[arg(long, value_name = "ADDR")]
+    pub listen: Option<String>,
+}
+
+/// Parses `<number><unit>` groups with units `s`, `m`, or `h`; a bare
+/// number is seconds.
+fn parse_duration(raw: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration '{}' (expected e.g. 90s, 15m, 1h30m)", raw);
+    if let Ok(seconds) = raw.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+    let mut total = 0u64;
+    let mut digits = String::new();
+    for ch in raw.chars() {
+        if ch.is_ascii_digit() {
+            digits.push(ch);
+            continue;
+        }
+        let unit = match ch {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            _ => return Err(invalid()),
+        };
+        let value: u64 = digits.parse().map_err(|_| invalid())?;
+        total += value * unit;
+        digits.clear();
+    }
+    if !digits.is_empty() || total == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(total))
+}
+
+fn parse_rate(raw: &str) -> Result<f64, String> {
+    match raw.parse::<f64>() {
+        Ok(rate) if (0.0..=1.0).contains(&rate) => Ok(rate),
+        _ => Err(format!("invalid rate '{}' (expected 0.0 to 1.0)", raw)),
+    }
+}
+
+fn parse_temperature(raw: &str) -> Result<f64, String> {
+    match raw.parse::<f64>() {
+        Ok(temperature) if (0.0..=2.0).contains(&temperature) => Ok(temperature),
+        _ => Err(format!(
+            "invalid temperature '{}' (expected 0.0 to 2.0)",
+            raw
+        )),
+    }
+}
+
+fn parse_positive(raw: &str) -> Result<usize, String> {
+    match raw.parse::<usize>() {
+        Ok(value) if value > 0 => Ok(value),
+        _ => Err(format!("invalid count '{}' (expected 1 or more)", raw)),
+    }
+}
+
+fn parse_env_assignment(raw: &str) -> Result<(String, String), String> {
+    let (key, value) = raw
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", raw))?;
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return Err(format!("invalid variable name '{}'", key));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// `rx run` options given as a word list, e.g. the `args` of a goal started
+/// through `rx serve`.
+#[derive(Debug, Parser)]
+#[command(name = "rx run", no_binary_name = true)]
+struct RunOptionsArgs {
+    #[command(flatten)]
+    options: RunOptions,
+}
+
+impl RunOptions {
+    pub fn parse_args(args: &[String]) -> Result<Self, clap::Error> {
+        RunOptionsArgs::try_parse_from(args).map(|parsed| parsed.options)
+    }
+}
+
+impl RunArgs {
+    pub fn goal_text(&self) -> String {
+        self.goal.join(" ").trim().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cli, Command, RunOptions};
+    use clap::Parser;
+    use std::time::Duration;
+
+    #[test]
+    fn goal_words_without_subcommand_are_shorthand_for_run() {
+        let cli = Cli::try_parse_from(["rx", "--max-iterations", "7", "fix", "the", "build"])
+            .expect("shorthand should parse");
+        assert!(cli.command.is_none());
+        assert_eq!(cli.run.options.max_iterations, 7);
+        assert_eq!(cli.run.goal_text(), "fix the build");
+
+        let cli = Cli::try_parse_from(["rx", "run", "--confirm", "fix"]).expect("run should parse");
+        let Some(Command::Run(run)) = cli.command else {
+            panic!("expected run subcommand");
+        };
+        assert!(run.options.confirm);
+        assert_eq!(run.goal_text(), "fix");
+    }
+
+    #[test]
+    fn unknown_flags_and_missing_goal_are_errors() {
+        assert!(Cli::try_parse_from(["rx", "--max-iteration", "3", "goal"]).is_err());
+        assert!(Cli::try_parse_from(["rx"]).is_err());
+        assert!(Cli::try_parse_from(["rx", "list"]).is_ok());
+        assert!(Cli::try_parse_from(["rx", "show"]).is_err());
+        assert!(Cli::try_parse_from(["rx", "--env", "NOVALUE", "goal"]).is_err());
+        assert!(Cli::try_parse_from(["rx", "--chaos", "1.5", "goal"]).is_err());
+        assert!(Cli::try_parse_from(["rx", "--temperature", "3", "goal"]).is_err());
+        assert!(Cli::try_parse_from(["rx", "--reasoning-effort", "max", "goal"]).is_err());
+        assert!(Cli::try_parse_from(["rx", "--chaos-seed", "3", "goal"]).is_err());
+        assert!(Cli::try_parse_from(["rx", "--output", "yaml", "goal"]).is_err());
+        assert!(Cli::try_parse_from(["rx", "--output", "json", "--confirm", "goal"]).is_err());
+        let args = |words: &[&str]| {
+            words
+                .iter()
+                .map(|word| word.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            RunOptions::parse_args(&args(&["--max-iterations", "3"]))
+                .unwrap()
+                .max_iterations,
+            3
+        );
+        assert!(RunOptions::parse_args(&args(&["--bogus"])).is_err());
+    }
+
+    #[test]
+    fn resume_accepts_a_follow_up_message() {
+        let cli = Cli::try_parse_from(["rx", "resume", "goal-1", "also", "add", "tests"])
+            .expect("resume should parse");
+        let Some(Command::Resume {
+            goal_id, message, ..
+        }) = cli.command
+        else {
+            panic!("expected resume subcommand");
+        };
+        assert_eq!(goal_id, "goal-1");
+        assert_eq!(message.join(" "), "also add tests");
+    }
+
+    #[test]
+    fn env_flags_split_on_the_first_equals_sign() {
+        let cli = Cli::try_parse_from([
+            "rx",
+            "--env",
+            "DATABASE_URL=postgres://u@h/db?a=b",
+            "--env",
+            "EMPTY=",
+            "goal",
+        ])
+        .expect("env flags should parse");
+        assert_eq!(
+            cli.run.options.env,
+            [
+                (
+                    "DATABASE_URL".to_string(),
+                    "postgres://u@h/db?a=b".to_string()
+                ),
+                ("EMPTY".to_string(), String::new())
+            ]
+        );
+    }
+
+    #[test]
+    fn max_duration_accepts_unit_groups_and_rejects_garbage() {
+        let cli =
+            Cli::try_parse_from(["rx", "--max-duration", "1h30m", "--max-cost", "2.5", "goal"])
+                .expect("budget flags should parse");
+        assert_eq!(
+            cli.run.options.max_duration,
+            Some(Duration::from_secs(5400))
+        );
+        assert_eq!(cli.run.options.max_cost, Some(2.5));
+
+        for invalid in ["15", "15x", "m", "10m5"] {
+            let parsed = Cli::try_parse_from(["rx", "--max-duration", invalid, "goal"]);
+            assert_eq!(parsed.is_ok(), invalid == "15", "{}", invalid);
+        }
+    }
+}
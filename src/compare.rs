@@ -0,0 +1,230 @@
+use crate::event::Event;
+use crate::model::Action;
+use serde_json::Value;
+use std::collections::HashMap;
+
+const ARGUMENT_PREVIEW_CHARS: usize = 100;
+
+/// One tool call and how it ended.
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    name: String,
+    arguments: String,
+    outcome: Outcome,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Outcome {
+    Ok,
+    Error(String),
+    Missing,
+}
+
+impl Outcome {
+    fn label(&self) -> String {
+        match self {
+            Outcome::Ok => "ok".to_string(),
+            Outcome::Error(error) => format!("error: {}", preview(error, 80)),
+            Outcome::Missing => "no output".to_string(),
+        }
+    }
+}
+
+/// Renders a diff of two goals' tool call sequences: `=` same call and
+/// outcome, `~` same call with a different outcome, `-` only in A, `+`
+/// only in B. Calls are aligned by name and arguments.
+pub fn render_comparison(a_id: &str, a: &[Event], b_id: &str, b: &[Event]) -> String {
+    let (steps_a, steps_b) = (steps(a), steps(b));
+    let mut out = format!(
+        "A: {} ({} calls, {})\nB: {} ({} calls, {})\n\n",
+        a_id,
+        steps_a.len(),
+        termination(a),
+        b_id,
+        steps_b.len(),
+        termination(b)
+    );
+
+    let mut first_divergence = None;
+    for (index, entry) in align(&steps_a, &steps_b).into_iter().enumerate() {
+        let line = match entry {
+            (Some(left), Some(right)) if left.outcome == right.outcome => {
+                format!("  = {}  {}", describe(left), left.outcome.label())
+            }
+            (Some(left), Some(right)) => {
+                first_divergence.get_or_insert(index + 1);
+                format!(
+                    "  ~ {}  A: {}  B: {}",
+                    describe(left),
+                    left.outcome.label(),
+                    right.outcome.label()
+                )
+            }
+            (Some(left), None) => {
+                first_divergence.get_or_insert(index + 1);
+                format!("  - {}  {}", describe(left), left.outcome.label())
+            }
+            (None, Some(right)) => {
+                first_divergence.get_or_insert(index + 1);
+                format!("  + {}  {}", describe(right), right.outcome.label())
+            }
+            (None, None) => continue,
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+
+    out.push('\n');
+    match first_divergence {
+        Some(step) => out.push_str(&format!("First divergence at step {}.\n", step)),
+        None => out.push_str("Tool calls and outcomes are identical.\n"),
+    }
+    out
+}
+
+fn steps(events: &[Event]) -> Vec<Step> {
+    let mut outputs: HashMap<String, Outcome> = HashMap::new();
+    for event in events.iter().filter(|event| event.r#type == "tool_output") {
+        let Some(id) = event.payload.get("tool_call_id").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let output = event.payload.get("output").cloned().unwrap_or(Value::Null);
+        let outcome = match output.get("error") {
+            Some(Value::String(error)) => Outcome::Error(error.clone()),
+            Some(error) => Outcome::Error(error.to_string()),
+            None => Outcome::Ok,
+        };
+        outputs.insert(id.to_string(), outcome);
+    }
+
+    events
+        .iter()
+        .filter(|event| event.r#type == "action")
+        .filter_map(
+            |event| match serde_json::from_value::<Action>(event.payload.clone()) {
+                Ok(Action::ToolCall(call)) => Some(Step {
+                    outcome: outputs.remove(&call.id).unwrap_or(Outcome::Missing),
+                    name: call.name,
+                    arguments: call.arguments.to_string(),
+                }),
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+/// Longest-common-subsequence alignment on (name, arguments).
+fn align<'a>(a: &'a [Step], b: &'a [Step]) -> Vec<(Option<&'a Step>, Option<&'a Step>)> {
+    let same = |x: &Step, y: &Step| x.name == y.name && x.arguments == y.arguments;
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if same(&a[i], &b[j]) {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut aligned = Vec::new();
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && same(&a[i], &b[j]) {
+            aligned.push((Some(&a[i]), Some(&b[j])));
+            i += 1;
+            j += 1;
+        } else if j >= b.len() || (i < a.len() && lengths[i + 1][j] >= lengths[i][j + 1]) {
+            aligned.push((Some(&a[i]), None));
+            i += 1;
+        } else {
+            aligned.push((None, Some(&b[j])));
+            j += 1;
+        }
+    }
+    aligned
+}
+
+fn termination(events: &[Event]) -> String {
+    events
+        .iter()
+        .rev()
+        .find(|event| event.r#type == "termination")
+        .and_then(|event| event.payload.get("reason"))
+        .and_then(|reason| reason.as_str())
+        .unwrap_or("incomplete")
+        .to_string()
+}
+
+fn describe(step: &Step) -> String {
+    format!(
+        "{} {}",
+        step.name,
+        preview(&step.arguments, ARGUMENT_PREVIEW_CHARS)
+    )
+}
+
+fn preview(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    text.chars().take(max_chars).collect::<String>() + "…"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_comparison;
+    use crate::event::Event;
+    use serde_json::{json, Value};
+
+    fn run(calls: &[(&str, Value, Value)], reason: &str) -> Vec<Event> {
+        let mut events = vec![Event::new("goal", json!({ "goal": "fix" }))];
+        for (index, (name, arguments, output)) in calls.iter().enumerate() {
+            let id = format!("call-{}", index);
+            events.push(Event::new(
+                "action",
+                json!({ "ToolCall": { "id": id, "name": name, "arguments": arguments } }),
+            ));
+            events.push(Event::new(
+                "tool_output",
+                json!({ "tool_call_id": id, "name": name, "output": output }),
+            ));
+        }
+        events.push(Event::new("termination", json!({ "reason": reason })));
+        events
+    }
+
+    #[test]
+    fn marks_shared_changed_and_one_sided_calls() {
+        let a = run(
+            &[
+                ("read_file", json!({ "path": "a.rs" }), json!({})),
+                (
+                    "exec",
+                    json!({ "command": "cargo" }),
+                    json!({ "error": "failed" }),
+                ),
+                ("done", json!({}), json!({})),
+            ],
+            "max_iterations",
+        );
+        let b = run(
+            &[
+                ("read_file", json!({ "path": "a.rs" }), json!({})),
+                ("list_dir", json!({ "path": "." }), json!({})),
+                ("exec", json!({ "command": "cargo" }), json!({ "code": 0 })),
+                ("done", json!({}), json!({})),
+            ],
+            "done",
+        );
+
+        let rendered = render_comparison("a", &a, "b", &b);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[0], "A: a (3 calls, max_iterations)");
+        assert!(lines[3].starts_with("  = read_file"));
+        assert!(lines[4].starts_with("  + list_dir"));
+        assert!(lines[5].starts_with("  ~ exec") && lines[5].ends_with("A: error: failed  B: ok"));
+        assert!(lines[6].starts_with("  = done"));
+        assert!(rendered.ends_with("First divergence at step 2.\n"));
+    }
+}
@@ -1,29 +1,466 @@
+use crate::goal_id::{GoalIdFormat, SlugStrategy};
+use crate::model::{PromptCacheRetention, ReasoningEffort};
+use crate::notify::LifecycleEvent;
+use crate::policy::PolicyAction;
+use crate::runtime_hooks::AutoCommitStrategy;
+use crate::scratch::ScratchCleanup;
+use crate::small_model::SmallTask;
+use crate::state::{StateBackend, DEFAULT_DATA_DIR};
 use serde::Deserialize;
-use std::collections::HashSet;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::warn;
 
-pub const AVAILABLE_TOOLS: [&str; 10] = [
+pub const AVAILABLE_TOOLS: [&str; 36] = [
     "exec",
+    "exec_background",
+    "process_status",
+    "process_logs",
+    "process_kill",
+    "open_session",
+    "send_input",
+    "read_output",
+    "close_session",
     "read_file",
     "write_file",
     "create_file",
     "append_file",
     "replace_in_file",
+    "edit_lines",
+    "multi_edit",
     "apply_patch",
     "apply_unified_patch",
+    "undo_last_change",
     "list_dir",
-    "done",
+    "directory_tree",
+    "get_working_dir",
+    "set_working_dir",
+    "find_files",
+    "grep_files",
+    "code_search",
+    "keyword_search",
+    "http_request",
+    "git_status",
+    "git_diff",
+    "git_log",
+    "git_commit",
+    "git_branch",
+    "workspace_info",
+    "remember",
+    "propose_done",
 ];
 
 #[derive(Debug, Deserialize, Default)]
 pub struct RxConfig {
+    pub cli_defaults: Option<CliDefaults>,
+    pub agent: Option<AgentConfig>,
+    pub agents: Option<BTreeMap<String, AgentConfig>>,
     pub tools: Option<ToolsConfig>,
+    pub policy: Option<PolicyConfig>,
+    pub workspace: Option<WorkspaceConfig>,
+    pub mcp: Option<McpConfig>,
+    pub model: Option<ModelConfig>,
+    pub small_model_router: Option<SmallModelRouterConfig>,
+    pub model_routes: Option<ModelRoutesConfig>,
+    pub goal_dedup: Option<GoalDedupConfig>,
+    pub tool_health: Option<ToolHealthConfig>,
+    pub state: Option<StateConfig>,
+    pub search: Option<SearchConfig>,
+    pub http: Option<HttpConfig>,
+    pub blockers: Option<BlockersConfig>,
+    pub goal_id: Option<GoalIdConfig>,
+    /// Variables exported to tool subprocesses; values are redacted in
+    /// event logs.
+    pub env: Option<BTreeMap<String, String>>,
+    pub env_policy: Option<EnvPolicyConfig>,
+    pub scratch: Option<ScratchConfig>,
+    pub memory: Option<MemoryConfig>,
+    pub review: Option<ReviewConfig>,
+    pub telemetry: Option<TelemetryConfig>,
+    pub redaction: Option<RedactionConfig>,
+    pub serve: Option<ServeConfig>,
+    pub notify: Option<NotifyConfig>,
+    pub notifications: Option<NotificationsConfig>,
+    pub loop_detection: Option<LoopDetectionConfig>,
+    pub write_quota: Option<WriteQuotaConfig>,
+}
+
+/// Per-goal limits on file tool writes; unset values keep the defaults of
+/// `WriteQuota`, and `0` disables a limit.
+#[derive(Debug, Deserialize, Default)]
+pub struct WriteQuotaConfig {
+    pub max_bytes: Option<u64>,
+    pub max_files: Option<usize>,
+}
+
+/// Which of rx's own environment variables the commands the agent runs
+/// inherit; see `EnvPolicy`.
+#[derive(Debug, Deserialize, Default)]
+pub struct EnvPolicyConfig {
+    /// Names (`*` wildcards) inherited on top of the built-in allowlist.
+    pub inherit: Option<Vec<String>>,
+    /// Names never inherited, on top of the built-in secret patterns.
+    pub deny: Option<Vec<String>>,
+    /// Inherit every variable that is not denied. Default `false`.
+    pub inherit_all: Option<bool>,
+}
+
+/// Reaction to the model repeating the identical tool call or replying
+/// without tool calls; unset values
+/// keep the defaults of `LoopDetection`.
+#[derive(Debug, Deserialize, Default)]
+pub struct LoopDetectionConfig {
+    /// Default `true`.
+    pub enabled: Option<bool>,
+    pub window: Option<usize>,
+    pub warn_after: Option<usize>,
+    pub terminate_after: Option<usize>,
+    /// Message-only iterations in a row before the model is nudged.
+    pub stall_iterations: Option<usize>,
+}
+
+/// Where to announce that a run ended: a desktop notification, a shell
+/// `command`, and/or a JSON POST to `webhook`.
+#[derive(Debug, Deserialize, Default)]
+pub struct NotifyConfig {
+    pub desktop: Option<bool>,
+    pub command: Option<String>,
+    pub webhook: Option<String>,
+    /// Runs shorter than this stay silent (default 0).
+    pub min_duration_secs: Option<u64>,
+}
+
+/// Run activity pushed to other services while a goal runs.
+#[derive(Debug, Deserialize, Default)]
+pub struct NotificationsConfig {
+    pub webhook: Option<WebhookConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Lifecycle events to post (default: all of them).
+    pub events: Option<Vec<LifecycleEvent>>,
+}
+
+/// `rx serve` settings.
+#[derive(Debug, Deserialize, Default)]
+pub struct ServeConfig {
+    /// Goals run at once; further goals wait in the queue (default 2).
+    pub max_concurrent_goals: Option<usize>,
+    /// Repositories served under `/workspaces/{name}`; without any, the
+    /// launch directory is served as `default`.
+    pub workspaces: Option<Vec<ServeWorkspaceConfig>>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ServeWorkspaceConfig {
+    /// URL-safe name used in routes.
+    pub name: String,
+    /// Repository root, relative to the launch directory.
+    pub root: PathBuf,
+}
+
+/// Secret patterns scrubbed from events before they are stored or logged.
+#[derive(Debug, Deserialize, Default)]
+pub struct RedactionConfig {
+    /// Redact common token formats (default `true`).
+    pub builtin: Option<bool>,
+    /// Extra regular expressions; a capture group limits the redaction to
+    /// the group.
+    pub patterns: Option<Vec<String>>,
+}
+
+/// OTLP/HTTP trace export; needs a build with `--features otel`.
+#[derive(Debug, Deserialize, Default)]
+pub struct TelemetryConfig {
+    /// Collector URL, e.g. `http://localhost:4318/v1/traces`.
+    pub endpoint: Option<String>,
+    /// Reported `service.name` (default `rx`).
+    pub service_name: Option<String>,
+}
+
+/// Small-model review of `propose_done` calls before they end the run.
+#[derive(Debug, Deserialize, Default)]
+pub struct ReviewConfig {
+    pub enabled: Option<bool>,
+    /// Critiques sent back before proposals are accepted unreviewed
+    /// (default 2).
+    pub max_review_rounds: Option<usize>,
+}
+
+/// Project memory files (`RX.md`, `AGENTS.md`, `CLAUDE.md`) at the
+/// workspace root.
+#[derive(Debug, Deserialize, Default)]
+pub struct MemoryConfig {
+    /// Inject the files into the system prompt (default `true`).
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ScratchConfig {
+    pub enabled: Option<bool>,
+    pub dir: Option<PathBuf>,
+    pub cleanup: Option<ScratchCleanup>,
+    pub max_age_hours: Option<u64>,
+}
+
+/// Ignore rules for search tools. `defaults = false` drops the built-in
+/// per-language excludes; `include` re-includes paths they would hide.
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct SearchConfig {
+    pub defaults: Option<bool>,
+    pub languages: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    pub include: Option<Vec<String>>,
+}
+
+/// Limits for the `http_request` tool. Without `allowed_domains` any host
+/// can be contacted.
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct HttpConfig {
+    pub allowed_domains: Option<Vec<String>>,
+    pub timeout_secs: Option<u64>,
+    pub max_response_bytes: Option<usize>,
+}
+
+/// Where blocked outcomes are recorded: appended to `file` (relative to the
+/// workspace root) and/or filed as a GitHub issue through `gh`.
+#[derive(Debug, Deserialize, Default)]
+pub struct BlockersConfig {
+    pub file: Option<PathBuf>,
+    pub github_issue: Option<bool>,
+    pub labels: Option<Vec<String>>,
+}
+
+/// Format of new goal IDs: a timestamp and slug, a ULID, or a UUID, or a
+/// `template` such as `"{date}-{ulid}"`, behind an optional `prefix`.
+#[derive(Debug, Deserialize, Default)]
+pub struct GoalIdConfig {
+    pub format: Option<GoalIdFormat>,
+    pub prefix: Option<String>,
+    pub slug: Option<SlugStrategy>,
+    pub template: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct StateConfig {
+    pub backend: Option<StateBackend>,
+    pub data_dir: Option<PathBuf>,
+    pub database_url: Option<String>,
+    /// Append a `workspace_diff` event after each tool call that changed
+    /// the git working tree.
+    pub diff_events: Option<bool>,
+}
+
+/// Cross-run failure tracking. A tool whose latest failure streak reaches
+/// `failure_threshold` is reported at startup and, with `quarantine`,
+/// left out of the registry for the run.
+#[derive(Debug, Deserialize, Default)]
+pub struct ToolHealthConfig {
+    pub enabled: Option<bool>,
+    pub failure_threshold: Option<u32>,
+    pub quarantine: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct GoalDedupConfig {
+    pub enabled: Option<bool>,
+    pub threshold: Option<f32>,
+    pub embedding_model: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ModelConfig {
+    /// Chain turns with `previous_response_id`, which needs `store: true`
+    /// so the provider keeps each response (default `false`).
+    pub response_chaining: Option<bool>,
+    pub max_output_tokens: Option<u64>,
+    pub temperature: Option<f64>,
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// Let the model request several tool calls in one response.
+    pub parallel_tool_calls: Option<bool>,
+    /// How long the provider keeps the cached prompt prefix: `in_memory`
+    /// or `24h`.
+    pub prompt_cache_retention: Option<PromptCacheRetention>,
+    /// Models to switch to, in order, when requests to the current one
+    /// keep failing.
+    pub fallback: Option<Vec<String>>,
+    /// Retries of a request failing with a rate limit, server error, or
+    /// connection error before the model gives up (default 2).
+    pub retries: Option<usize>,
+    /// USD per million tokens; overrides the built-in price table.
+    pub input_price: Option<f64>,
+    pub cached_input_price: Option<f64>,
+    pub output_price: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct McpConfig {
+    #[serde(default)]
+    pub servers: Vec<McpServerConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct McpServerConfig {
+    pub name: String,
+    pub command: Option<String>,
+    pub args: Option<Vec<String>>,
+    pub url: Option<String>,
+    /// Tools of this server (as it names them) that only read; the others
+    /// are gated by `--confirm` and `--plan`.
+    pub read_only_tools: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct WorkspaceConfig {
+    pub root: Option<PathBuf>,
+    pub allowed_paths: Option<Vec<PathBuf>>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct CliDefaults {
+    pub confirm: Option<bool>,
+    pub small_model: Option<String>,
+    pub model_name: Option<String>,
+    pub auto_commit: Option<bool>,
+    pub auto_commit_strategy: Option<AutoCommitStrategy>,
+    /// Write auto-commit messages as Conventional Commits.
+    pub conventional_commits: Option<bool>,
+    /// System prompt file, relative to the launch directory.
+    pub prompt: Option<PathBuf>,
+    /// Shell command that must pass before a `success` proposal is accepted.
+    pub verify_command: Option<String>,
+}
+
+impl CliDefaults {
+    /// These defaults with every value set in `overrides` replaced.
+    pub fn overlay(&self, overrides: &CliDefaults) -> CliDefaults {
+        CliDefaults {
+            confirm: overrides.confirm.or(self.confirm),
+            small_model: overrides
+                .small_model
+                .clone()
+                .or_else(|| self.small_model.clone()),
+            model_name: overrides
+                .model_name
+                .clone()
+                .or_else(|| self.model_name.clone()),
+            auto_commit: overrides.auto_commit.or(self.auto_commit),
+            auto_commit_strategy: overrides.auto_commit_strategy.or(self.auto_commit_strategy),
+            conventional_commits: overrides.conventional_commits.or(self.conventional_commits),
+            prompt: overrides.prompt.clone().or_else(|| self.prompt.clone()),
+            verify_command: overrides
+                .verify_command
+                .clone()
+                .or_else(|| self.verify_command.clone()),
+        }
+    }
+}
+
+/// A named profile selected with `--agent`, from `[agents.<name>]` or the
+/// single `[agent]` section.
+#[derive(Debug, Deserialize)]
+pub struct AgentConfig {
+    /// Required in `[agent]`; `[agents.<name>]` profiles get their key.
+    #[serde(default)]
+    pub name: String,
+    pub model: Option<String>,
+    /// System prompt file for this profile, relative to the launch directory.
+    pub prompt: Option<PathBuf>,
+    /// Replaces the `[tools]` lists that it sets.
+    pub tools: Option<AgentToolsConfig>,
+    pub cli_defaults_overrides: Option<CliDefaults>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct AgentToolsConfig {
+    pub enabled: Option<Vec<String>>,
+    pub disabled: Option<Vec<String>>,
+}
+
+/// Every configured profile by name: `[agents.<name>]` tables, then the
+/// single `[agent]` section.
+pub fn agent_profiles(config: Option<&RxConfig>) -> Vec<(&str, &AgentConfig)> {
+    let mut profiles: Vec<(&str, &AgentConfig)> = config
+        .and_then(|cfg| cfg.agents.as_ref())
+        .into_iter()
+        .flatten()
+        .map(|(name, agent)| (name.as_str(), agent))
+        .collect();
+    if let Some(agent) = config.and_then(|cfg| cfg.agent.as_ref()) {
+        profiles.push((agent.name.as_str(), agent));
+    }
+    profiles
+}
+
+/// The profile named `name`; an error listing the configured ones when
+/// none matches.
+pub fn find_agent<'a>(config: Option<&'a RxConfig>, name: &str) -> anyhow::Result<&'a AgentConfig> {
+    let profiles = agent_profiles(config);
+    if let Some((_, agent)) = profiles.iter().find(|(profile, _)| *profile == name) {
+        return Ok(agent);
+    }
+    if profiles.is_empty() {
+        return Err(anyhow::anyhow!(
+            "unknown agent profile '{}'; no [agents] or [agent] profiles are configured",
+            name
+        ));
+    }
+    Err(anyhow::anyhow!(
+        "unknown agent profile '{}'; configured profiles: {}",
+        name,
+        profiles
+            .iter()
+            .map(|(profile, _)| *profile)
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct SmallModelRouterConfig {
+    pub max_calls: Option<usize>,
+    pub max_concurrency: Option<usize>,
+}
+
+/// Models for individual phases of a run; unset phases keep the agent
+/// model (`planning`) or the small model (internal tasks).
+#[derive(Debug, Deserialize, Default)]
+pub struct ModelRoutesConfig {
+    pub planning: Option<String>,
+    #[serde(flatten)]
+    pub tasks: HashMap<SmallTask, String>,
 }
 
 #[derive(Debug, Deserialize, Default)]
 pub struct ToolsConfig {
     pub enabled: Option<Vec<String>>,
     pub disabled: Option<Vec<String>>,
+    /// Default per-call timeout in seconds; `0` disables it.
+    pub timeout_secs: Option<u64>,
+    /// Per-tool timeout overrides in seconds, keyed by tool name.
+    pub timeouts: Option<BTreeMap<String, u64>>,
+    /// Default fuzz level for `apply_patch` and `apply_unified_patch`.
+    pub patch_fuzz: Option<usize>,
+    /// Outputs over this many bytes reach the model as an excerpt
+    /// (default 16384; `0` sends every output whole).
+    pub max_output_bytes: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct PolicyConfig {
+    pub default: Option<PolicyAction>,
+    #[serde(default)]
+    pub rules: Vec<PolicyRuleConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PolicyRuleConfig {
+    pub tool: String,
+    pub argument: Option<String>,
+    pub pattern: Option<String>,
+    pub action: PolicyAction,
 }
 
 #[derive(Debug, Default)]
@@ -32,13 +469,89 @@ pub struct ToolSelection {
     pub warnings: Vec<String>,
 }
 
-pub fn load_config(path: &Path) -> Option<RxConfig> {
+/// Per-repository config, relative to the launch directory.
+pub const WORKSPACE_CONFIG_PATH: &str = ".rx/config.toml";
+
+/// Directory for user-level files written by `rx setup`:
+/// `$XDG_CONFIG_HOME/rx`, or `~/.config/rx`.
+pub fn user_config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("rx"))
+}
+
+/// Config files in precedence order: the user config, then the workspace
+/// config.
+pub fn config_layers() -> Vec<PathBuf> {
+    config_layers_in(Path::new(""))
+}
+
+/// Like [`config_layers`], with the workspace config of the repository at
+/// `root`.
+pub fn config_layers_in(root: &Path) -> Vec<PathBuf> {
+    user_config_dir()
+        .map(|dir| dir.join("config.toml"))
+        .into_iter()
+        .chain([root.join(WORKSPACE_CONFIG_PATH)])
+        .collect()
+}
+
+/// Loads the user and workspace config files.
+pub fn load_layered_config() -> Option<RxConfig> {
+    load_config(&config_layers())
+}
+
+/// Loads the user config and the workspace config of the repository at
+/// `root`, with `[state].data_dir` resolved against `root` so goals of
+/// different repositories never share a log directory.
+pub fn load_workspace_config(root: &Path) -> RxConfig {
+    let mut config = load_config(&config_layers_in(root)).unwrap_or_default();
+    let state = config.state.get_or_insert_with(StateConfig::default);
+    let data_dir = state
+        .data_dir
+        .take()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR));
+    state.data_dir = Some(root.join(data_dir));
+    config
+}
+
+/// Merges the given files, later ones overriding earlier ones key by key,
+/// and parses the result. Missing files are skipped; returns `None` when no
+/// file could be read or the merged config is invalid.
+pub fn load_config(layers: &[impl AsRef<Path>]) -> Option<RxConfig> {
+    let mut merged: Option<toml::Table> = None;
+    for path in layers {
+        if let Some(table) = read_config_table(path.as_ref()) {
+            match &mut merged {
+                Some(base) => merge_tables(base, table),
+                None => merged = Some(table),
+            }
+        }
+    }
+
+    match RxConfig::deserialize(toml::Value::Table(merged?)) {
+        Ok(mut config) => {
+            for (name, agent) in config.agents.iter_mut().flatten() {
+                agent.name = name.clone();
+            }
+            Some(config)
+        }
+        Err(error) => {
+            warn!("failed to parse config: {}", error);
+            None
+        }
+    }
+}
+
+fn read_config_table(path: &Path) -> Option<toml::Table> {
     let raw = match std::fs::read_to_string(path) {
         Ok(contents) => contents,
         Err(error) => {
             if error.kind() != std::io::ErrorKind::NotFound {
-                eprintln!(
-                    "Warning: failed to read config file at {}: {}",
+                warn!(
+                    "failed to read config file at {}: {}",
                     path.display(),
                     error
                 );
@@ -47,11 +560,11 @@ pub fn load_config(path: &Path) -> Option<RxConfig> {
         }
     };
 
-    match toml::from_str::<RxConfig>(&raw) {
-        Ok(config) => Some(config),
+    match raw.parse::<toml::Table>() {
+        Ok(table) => Some(table),
         Err(error) => {
-            eprintln!(
-                "Warning: failed to parse config file at {}: {}",
+            warn!(
+                "failed to parse config file at {}: {}",
                 path.display(),
                 error
             );
@@ -60,24 +573,60 @@ pub fn load_config(path: &Path) -> Option<RxConfig> {
     }
 }
 
-pub fn resolve_enabled_tools(config: Option<&ToolsConfig>) -> ToolSelection {
+/// Nested tables merge recursively; any other value in `overlay` replaces
+/// the one in `base`.
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(table)) => {
+                merge_tables(existing, table)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// The tools to register from `[tools]`, with an agent profile's
+/// `enabled`/`disabled` lists in place of the ones it sets.
+pub fn resolve_enabled_tools(
+    config: Option<&ToolsConfig>,
+    agent: Option<&AgentConfig>,
+) -> ToolSelection {
     let mut warnings = Vec::new();
     let available_set: HashSet<&str> = AVAILABLE_TOOLS.iter().copied().collect();
-    let mut selected: Vec<String> = match config.and_then(|c| c.enabled.as_ref()) {
+    let agent_tools = agent.and_then(|agent| agent.tools.as_ref());
+    let agent_label = agent.map(|agent| format!("Agent '{}' tools", agent.name));
+    let (enabled, enabled_label) = match agent_tools.and_then(|tools| tools.enabled.as_ref()) {
+        Some(enabled) => (Some(enabled), agent_label.clone().unwrap_or_default()),
+        None => (
+            config.and_then(|c| c.enabled.as_ref()),
+            "Config [tools]".to_string(),
+        ),
+    };
+    let (disabled, disabled_label) = match agent_tools.and_then(|tools| tools.disabled.as_ref()) {
+        Some(disabled) => (Some(disabled), agent_label.unwrap_or_default()),
+        None => (
+            config.and_then(|c| c.disabled.as_ref()),
+            "Config [tools]".to_string(),
+        ),
+    };
+    let mut selected: Vec<String> = match enabled {
         Some(enabled) => {
             let enabled_set = to_trimmed_set(enabled);
             if enabled_set.is_empty() {
-                warnings.push(
-                    "Config [tools].enabled is empty; no tools selected before safety checks."
-                        .to_string(),
-                );
+                warnings.push(format!(
+                    "{}.enabled is empty; no tools selected before safety checks.",
+                    enabled_label
+                ));
             }
 
             for name in &enabled_set {
                 if !available_set.contains(name.as_str()) {
                     warnings.push(format!(
-                        "Config [tools].enabled contains unknown tool '{}'; ignoring.",
-                        name
+                        "{}.enabled contains unknown tool '{}'; ignoring.",
+                        enabled_label, name
                     ));
                 }
             }
@@ -94,13 +643,13 @@ pub fn resolve_enabled_tools(config: Option<&ToolsConfig>) -> ToolSelection {
             .collect(),
     };
 
-    if let Some(disabled) = config.and_then(|c| c.disabled.as_ref()) {
+    if let Some(disabled) = disabled {
         let disabled_set = to_trimmed_set(disabled);
         for name in &disabled_set {
             if !available_set.contains(name.as_str()) {
                 warnings.push(format!(
-                    "Config [tools].disabled contains unknown tool '{}'; ignoring.",
-                    name
+                    "{}.disabled contains unknown tool '{}'; ignoring.",
+                    disabled_label, name
                 ));
             }
         }
@@ -108,10 +657,11 @@ pub fn resolve_enabled_tools(config: Option<&ToolsConfig>) -> ToolSelection {
         selected.retain(|name| !disabled_set.contains(name));
     }
 
-    if !selected.iter().any(|name| name == "done") {
-        warnings
-            .push("Tool 'done' cannot be disabled; forcing it to remain registered.".to_string());
-        selected.push("done".to_string());
+    if !selected.iter().any(|name| name == "propose_done") {
+        warnings.push(
+            "Tool 'propose_done' cannot be disabled; forcing it to remain registered.".to_string(),
+        );
+        selected.push("propose_done".to_string());
     }
 
     ToolSelection {
@@ -120,18 +670,27 @@ pub fn resolve_enabled_tools(config: Option<&ToolsConfig>) -> ToolSelection {
     }
 }
 
+/// Also maps the pre-`propose_done` name `done` to its replacement.
 fn to_trimmed_set(values: &[String]) -> HashSet<String> {
     values
         .iter()
-        .map(|value| value.trim().to_string())
+        .map(|value| match value.trim() {
+            "done" => "propose_done".to_string(),
+            name => name.to_string(),
+        })
         .filter(|value| !value.is_empty())
         .collect()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{resolve_enabled_tools, ToolsConfig, AVAILABLE_TOOLS};
+    use super::{
+        find_agent, load_workspace_config, resolve_enabled_tools, ToolsConfig, AVAILABLE_TOOLS,
+    };
     use crate::config::load_config;
+    use crate::model::ToolCall;
+    use crate::policy::{Policy, PolicyAction};
+    use crate::small_model::SmallTask;
     use std::fs;
     use std::path::PathBuf;
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -146,7 +705,7 @@ mod tests {
 
     #[test]
     fn defaults_to_all_tools() {
-        let selected = resolve_enabled_tools(None);
+        let selected = resolve_enabled_tools(None, None);
         assert_eq!(
             selected.enabled_tools,
             AVAILABLE_TOOLS
@@ -160,27 +719,29 @@ mod tests {
     #[test]
     fn enabled_list_limits_tools() {
         let cfg = ToolsConfig {
-            enabled: Some(vec!["read_file".to_string(), "done".to_string()]),
+            enabled: Some(vec!["read_file".to_string(), "propose_done".to_string()]),
             disabled: None,
+            ..Default::default()
         };
-        let selected = resolve_enabled_tools(Some(&cfg));
+        let selected = resolve_enabled_tools(Some(&cfg), None);
         assert_eq!(
             selected.enabled_tools,
-            vec!["read_file".to_string(), "done".to_string()]
+            vec!["read_file".to_string(), "propose_done".to_string()]
         );
         assert!(selected.warnings.is_empty());
     }
 
     #[test]
-    fn done_is_forced_even_when_disabled() {
+    fn propose_done_is_forced_even_when_disabled() {
         let cfg = ToolsConfig {
             enabled: Some(vec!["exec".to_string()]),
-            disabled: Some(vec!["done".to_string()]),
+            disabled: Some(vec!["propose_done".to_string()]),
+            ..Default::default()
         };
-        let selected = resolve_enabled_tools(Some(&cfg));
+        let selected = resolve_enabled_tools(Some(&cfg), None);
         assert_eq!(
             selected.enabled_tools,
-            vec!["exec".to_string(), "done".to_string()]
+            vec!["exec".to_string(), "propose_done".to_string()]
         );
         assert_eq!(selected.warnings.len(), 1);
     }
@@ -190,11 +751,12 @@ mod tests {
         let cfg = ToolsConfig {
             enabled: Some(vec!["read_file".to_string(), "not_real".to_string()]),
             disabled: Some(vec!["also_fake".to_string()]),
+            ..Default::default()
         };
-        let selected = resolve_enabled_tools(Some(&cfg));
+        let selected = resolve_enabled_tools(Some(&cfg), None);
         assert_eq!(
             selected.enabled_tools,
-            vec!["read_file".to_string(), "done".to_string()]
+            vec!["read_file".to_string(), "propose_done".to_string()]
         );
         assert_eq!(selected.warnings.len(), 3);
     }
@@ -204,11 +766,12 @@ mod tests {
         let cfg = ToolsConfig {
             enabled: Some(vec!["exec".to_string(), "read_file".to_string()]),
             disabled: Some(vec!["exec".to_string()]),
+            ..Default::default()
         };
-        let selected = resolve_enabled_tools(Some(&cfg));
+        let selected = resolve_enabled_tools(Some(&cfg), None);
         assert_eq!(
             selected.enabled_tools,
-            vec!["read_file".to_string(), "done".to_string()]
+            vec!["read_file".to_string(), "propose_done".to_string()]
         );
     }
 
@@ -225,7 +788,8 @@ disabled = ["exec"]
         )
         .expect("should write test config");
 
-        let loaded = load_config(&path).expect("config should parse");
+        let loaded = load_config(&[&path]).expect("config should parse");
+        assert!(loaded.cli_defaults.is_none());
         let tools = loaded.tools.expect("tools section should exist");
         assert_eq!(
             tools.enabled.expect("enabled should exist"),
@@ -239,14 +803,169 @@ disabled = ["exec"]
         let _ = fs::remove_file(path);
     }
 
+    #[test]
+    fn load_config_parses_cli_defaults_confirm() {
+        let path = temp_config_path("confirm");
+        fs::write(
+            &path,
+            r#"
+[cli_defaults]
+confirm = true
+max_iterations = 10
+"#,
+        )
+        .expect("should write test config");
+
+        let loaded = load_config(&[&path]).expect("config should parse");
+        let defaults = loaded.cli_defaults.expect("cli_defaults should exist");
+        assert_eq!(defaults.confirm, Some(true));
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn load_config_parses_model_routes() {
+        let path = temp_config_path("routes");
+        fs::write(
+            &path,
+            "[model_routes]\nplanning = \"gpt-5\"\ncommit_message = \"gpt-5-nano\"\n",
+        )
+        .expect("should write test config");
+
+        let loaded = load_config(&[&path]).expect("config should parse");
+        let routes = loaded.model_routes.expect("model_routes should exist");
+        assert_eq!(routes.planning.as_deref(), Some("gpt-5"));
+        assert_eq!(
+            routes
+                .tasks
+                .get(&SmallTask::CommitMessage)
+                .map(String::as_str),
+            Some("gpt-5-nano")
+        );
+
+        fs::write(&path, "[model_routes]\ncommit_mesage = \"gpt-5-nano\"\n")
+            .expect("should write test config");
+        assert!(load_config(&[&path]).is_none());
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn workspace_layer_overrides_user_layer_key_by_key() {
+        let user = temp_config_path("user");
+        let workspace = temp_config_path("workspace");
+        fs::write(
+            &user,
+            "[cli_defaults]\nmodel_name = \"gpt-4.1\"\nauto_commit = true\n",
+        )
+        .expect("should write test config");
+        fs::write(&workspace, "[cli_defaults]\nauto_commit = false\n")
+            .expect("should write test config");
+
+        let loaded = load_config(&[&user, &workspace]).expect("config should parse");
+        let defaults = loaded.cli_defaults.expect("cli_defaults should exist");
+        assert_eq!(defaults.model_name.as_deref(), Some("gpt-4.1"));
+        assert_eq!(defaults.auto_commit, Some(false));
+
+        let _ = fs::remove_file(user);
+        let _ = fs::remove_file(workspace);
+    }
+
+    #[test]
+    fn agent_profile_overlays_cli_defaults() {
+        let path = temp_config_path("agent");
+        fs::write(
+            &path,
+            r#"
+[cli_defaults]
+model_name = "gpt-4.1"
+confirm = true
+prompt = "prompts/default.md"
+
+[agent]
+name = "reviewer"
+prompt = "prompts/reviewer.md"
+
+[agent.cli_defaults_overrides]
+confirm = false
+
+[agents.writer]
+model = "gpt-5"
+
+[agents.writer.tools]
+disabled = ["exec", "nope"]
+"#,
+        )
+        .expect("should write test config");
+
+        let loaded = load_config(&[&path]).expect("config should parse");
+        let agent = find_agent(Some(&loaded), "reviewer").expect("profile should exist");
+        assert_eq!(
+            agent.prompt.as_deref(),
+            Some(std::path::Path::new("prompts/reviewer.md"))
+        );
+        let defaults = loaded
+            .cli_defaults
+            .as_ref()
+            .expect("cli_defaults should exist")
+            .overlay(agent.cli_defaults_overrides.as_ref().unwrap());
+        assert_eq!(defaults.confirm, Some(false));
+        assert_eq!(defaults.model_name.as_deref(), Some("gpt-4.1"));
+
+        let writer = find_agent(Some(&loaded), "writer").expect("profile should exist");
+        assert_eq!(writer.model.as_deref(), Some("gpt-5"));
+        let selected = resolve_enabled_tools(None, Some(writer));
+        assert!(!selected.enabled_tools.contains(&"exec".to_string()));
+        assert!(selected.enabled_tools.contains(&"read_file".to_string()));
+        assert!(selected.warnings[0].contains("unknown tool 'nope'"));
+        let error = find_agent(Some(&loaded), "refactorer").unwrap_err();
+        assert!(error.to_string().contains("writer, reviewer"));
+
+        let _ = fs::remove_file(path);
+    }
+
     #[test]
     fn load_config_returns_none_for_invalid_toml() {
         let path = temp_config_path("invalid");
         fs::write(&path, "[tools\nenabled = [\"read_file\"]").expect("should write test config");
 
-        let loaded = load_config(&path);
+        let loaded = load_config(&[&path]);
         assert!(loaded.is_none());
 
         let _ = fs::remove_file(path);
     }
+
+    #[test]
+    fn workspace_configs_keep_their_own_policy_and_state() {
+        let base = temp_config_path("workspaces").with_extension("");
+        let (api, web) = (base.join("api"), base.join("web"));
+        for (root, config) in [
+            (&api, "[[policy.rules]]\ntool = \"exec\"\naction = \"allow\"\n"),
+            (
+                &web,
+                "[state]\ndata_dir = \"goals\"\n\n[[policy.rules]]\ntool = \"exec\"\naction = \"deny\"\n",
+            ),
+        ] {
+            fs::create_dir_all(root.join(".rx")).expect("should create workspace");
+            fs::write(root.join(".rx/config.toml"), config).expect("should write test config");
+        }
+
+        let call = ToolCall {
+            id: "call-1".to_string(),
+            name: "exec".to_string(),
+            arguments: serde_json::json!({ "command": "make" }),
+        };
+        let action_in = |root: &PathBuf| {
+            let config = load_workspace_config(root);
+            let policy = Policy::from_config(config.policy.as_ref().unwrap()).unwrap();
+            (
+                policy.evaluate(&call).action,
+                config.state.unwrap().data_dir.unwrap(),
+            )
+        };
+        assert_eq!(action_in(&api), (PolicyAction::Allow, api.join("logs")));
+        assert_eq!(action_in(&web), (PolicyAction::Deny, web.join("goals")));
+
+        let _ = fs::remove_dir_all(base);
+    }
 }
@@ -0,0 +1,66 @@
+use crate::config::user_config_dir;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// File holding API keys saved by `rx setup`, readable only by the owner.
+pub fn credentials_path() -> Option<PathBuf> {
+    user_config_dir().map(|dir| dir.join("credentials.toml"))
+}
+
+/// The OpenAI API key: `OPENAI_API_KEY` when set and non-empty, otherwise
+/// the key stored by `rx setup`.
+pub fn openai_api_key() -> Option<String> {
+    std::env::var("OPENAI_API_KEY")
+        .ok()
+        .filter(|key| !key.trim().is_empty())
+        .or_else(|| credentials_path().and_then(|path| stored_api_key(&path, "openai")))
+}
+
+/// Reads `[<provider>].api_key` from a credentials file.
+pub fn stored_api_key(path: &Path, provider: &str) -> Option<String> {
+    let raw = std::fs::read_to_string(path).ok()?;
+    let table = raw.parse::<toml::Table>().ok()?;
+    table
+        .get(provider)?
+        .get("api_key")?
+        .as_str()
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(str::to_string)
+}
+
+/// Saves `[<provider>].api_key`, keeping other providers' entries, and
+/// restricts the file to owner read/write.
+pub fn store_api_key(path: &Path, provider: &str, api_key: &str) -> Result<()> {
+    let mut document = match std::fs::read_to_string(path) {
+        Ok(raw) => raw
+            .parse::<toml_edit::DocumentMut>()
+            .with_context(|| format!("failed to parse {}", path.display()))?,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Default::default(),
+        Err(error) => {
+            return Err(error).with_context(|| format!("failed to read {}", path.display()))
+        }
+    };
+    if !document.contains_table(provider) {
+        document[provider] = toml_edit::table();
+    }
+    document[provider]["api_key"] = toml_edit::value(api_key);
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    // `mode` only applies to new files; tighten an existing one as well.
+    #[cfg(unix)]
+    std::fs::set_permissions(path, std::os::unix::fs::PermissionsExt::from_mode(0o600))
+        .with_context(|| format!("failed to restrict {}", path.display()))?;
+    std::io::Write::write_all(&mut file, document.to_string().as_bytes())
+        .with_context(|| format!("failed to write {}", path.display()))
+}
@@ -0,0 +1,130 @@
+use crate::event::Event;
+use crate::model::ToolCall;
+use crate::small_model::{ModelRouter, SmallTask};
+use crate::workspace_diff::current_diff;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Characters of the workspace diff sent to the reviewer.
+const MAX_REVIEW_DIFF_CHARS: usize = 40_000;
+
+const REVIEWER_INSTRUCTIONS: &str = "You review the work of an autonomous coding agent before its completion is accepted. You are given the goal, the agent's completion proposal, and the diff of the workspace. If the goal is met and the diff shows no bugs, missing pieces, or unrelated changes, reply with APPROVE and nothing else. Otherwise reply with a short numbered list of concrete problems the agent must fix. Do not ask for stylistic changes.";
+
+/// Automated review of a `propose_done` call (`[review]`). A critique is
+/// sent back to the agent as a user message and the run continues.
+#[async_trait]
+pub trait CompletionCritic: Send + Sync {
+    /// Problems found in the proposal, or `None` when it may be accepted.
+    async fn critique(&self, proposal: &ToolCall, history: &[Event]) -> Result<Option<String>>;
+}
+
+/// Reviews the goal, the proposal, and the workspace diff with the small
+/// model.
+pub struct SmallModelCritic {
+    router: Arc<ModelRouter>,
+    root: PathBuf,
+    exclude: Vec<PathBuf>,
+}
+
+impl SmallModelCritic {
+    /// `exclude` lists directories under `root` left out of the diff, such
+    /// as the event log directory.
+    pub fn new(router: Arc<ModelRouter>, root: PathBuf, exclude: Vec<PathBuf>) -> Self {
+        Self {
+            router,
+            root,
+            exclude,
+        }
+    }
+}
+
+#[async_trait]
+impl CompletionCritic for SmallModelCritic {
+    async fn critique(&self, proposal: &ToolCall, history: &[Event]) -> Result<Option<String>> {
+        let diff = current_diff(&self.root, &self.exclude)
+            .await
+            .unwrap_or_else(|error| format!("(no diff available: {:#})", error));
+        let reply = self
+            .router
+            .complete(
+                SmallTask::CompletionReview,
+                REVIEWER_INSTRUCTIONS,
+                &review_input(history, proposal, &diff),
+            )
+            .await?;
+        Ok(parse_verdict(&reply))
+    }
+}
+
+/// The goal with its follow-up instructions, the proposal, and the diff.
+fn review_input(history: &[Event], proposal: &ToolCall, diff: &str) -> String {
+    let goal = history
+        .iter()
+        .filter_map(|event| match event.r#type.as_str() {
+            "goal" => event.payload.get("goal")?.as_str(),
+            // Earlier critiques are not instructions from the user.
+            "user_message" if event.payload.get("source").is_none() => {
+                event.payload.get("message")?.as_str()
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let diff = match diff.char_indices().nth(MAX_REVIEW_DIFF_CHARS) {
+        Some((cut, _)) => format!("{}\n[diff truncated]\n", &diff[..cut]),
+        None if diff.trim().is_empty() => "(no changes)".to_string(),
+        None => diff.to_string(),
+    };
+    format!(
+        "## Goal\n\n{}\n\n## Completion proposal\n\n{}\n\n## Workspace diff\n\n{}",
+        goal,
+        serde_json::to_string_pretty(&proposal.arguments).unwrap_or_default(),
+        diff
+    )
+}
+
+/// `None` when the reviewer approved, otherwise its critique.
+fn parse_verdict(reply: &str) -> Option<String> {
+    let reply = reply.trim();
+    let verdict = reply.trim_start_matches(|c: char| c == '*' || c == '#' || c.is_whitespace());
+    (!verdict.to_ascii_uppercase().starts_with("APPROVE")).then(|| reply.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_verdict, review_input};
+    use crate::event::Event;
+    use crate::model::ToolCall;
+    use serde_json::json;
+
+    #[test]
+    fn reviewer_sees_the_goal_and_diff_and_only_approve_passes() {
+        let history = vec![
+            Event::new("goal", json!({ "goal": "fix the parser" })),
+            Event::new("user_message", json!({ "message": "also add a test" })),
+            Event::new(
+                "user_message",
+                json!({ "message": "1. The test is missing.", "source": "reviewer" }),
+            ),
+        ];
+        let proposal = ToolCall {
+            id: "call-1".to_string(),
+            name: "propose_done".to_string(),
+            arguments: json!({ "summary": "fixed it" }),
+        };
+        let input = review_input(&history, &proposal, "");
+        assert!(input.starts_with("## Goal\n\nfix the parser\n\nalso add a test\n\n"));
+        assert!(!input.contains("The test is missing"));
+        assert!(input.contains("\"summary\": \"fixed it\""));
+        assert!(input.ends_with("## Workspace diff\n\n(no changes)"));
+
+        assert_eq!(parse_verdict(" **APPROVE**\n"), None);
+        assert_eq!(parse_verdict("Approve."), None);
+        assert_eq!(
+            parse_verdict("1. parse_range still drops the last element\n"),
+            Some("1. parse_range still drops the last element".to_string())
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use crate::event::Event;
+use crate::model::ModelUsage;
+use crate::state::DEFAULT_DATA_DIR;
+use crate::stats::Stats;
+use crate::tools::shell::shell_command;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::process::Command;
+
+/// Iteration budget of a task that does not set `max_iterations`.
+const DEFAULT_TASK_ITERATIONS: u32 = 30;
+/// Lines of a failed run's output kept in the report.
+const ERROR_TAIL_LINES: usize = 20;
+
+/// A benchmark suite for `rx eval`, read from TOML.
+#[derive(Debug, Deserialize)]
+pub struct EvalSuite {
+    #[serde(default, rename = "task")]
+    pub tasks: Vec<EvalTask>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EvalTask {
+    pub name: String,
+    pub goal: String,
+    /// Shell script that prepares the empty workspace before the run.
+    pub setup: Option<String>,
+    /// Shell command run in the workspace afterwards; exit 0 passes.
+    pub assert: String,
+    pub max_iterations: Option<u32>,
+    /// Extra `rx run` options, e.g. `["--replay", "/fixtures/hello.jsonl"]`.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl EvalSuite {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read eval suite {}", path.display()))?;
+        let suite: EvalSuite = toml::from_str(&content)
+            .with_context(|| format!("invalid eval suite {}", path.display()))?;
+        if suite.tasks.is_empty() {
+            return Err(anyhow!(
+                "eval suite {} has no [[task]] entries",
+                path.display()
+            ));
+        }
+        Ok(suite)
+    }
+}
+
+/// Outcome of one task.
+#[derive(Debug, Serialize)]
+pub struct TaskResult {
+    pub name: String,
+    pub passed: bool,
+    /// Why the task failed before its assertion could decide, or the
+    /// assertion's output tail.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub iterations: usize,
+    pub usage: ModelUsage,
+    /// Last termination of the goal (`done:<outcome>`, another reason, or
+    /// `incomplete`).
+    pub termination: String,
+    pub wall_ms: u128,
+    pub workspace: PathBuf,
+}
+
+/// Options shared by every task of a suite.
+pub struct EvalOptions {
+    /// The `rx` binary that runs each goal.
+    pub rx: PathBuf,
+    /// Directory of the suite file, exported to scripts as `RX_EVAL_DIR`.
+    pub suite_dir: PathBuf,
+    pub model: Option<String>,
+    /// Keep task workspaces instead of deleting them.
+    pub keep: bool,
+}
+
+/// Runs `task` as a fresh goal in a new temporary workspace and checks it
+/// with the task's assertion.
+pub async fn run_task(task: &EvalTask, options: &EvalOptions) -> Result<TaskResult> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let workspace = std::env::temp_dir().join(format!(
+        "rx-eval-{}-{}",
+        crate::utils::sanitize_goal_slug(&task.name),
+        nanos
+    ));
+    std::fs::create_dir_all(&workspace)
+        .with_context(|| format!("failed to create {}", workspace.display()))?;
+    let mut result = TaskResult {
+        name: task.name.clone(),
+        passed: false,
+        error: None,
+        iterations: 0,
+        usage: ModelUsage::default(),
+        termination: "incomplete".to_string(),
+        wall_ms: 0,
+        workspace: workspace.clone(),
+    };
+
+    if let Some(setup) = &task.setup {
+        let (ok, output) = shell(setup, &workspace, options).await?;
+        if !ok {
+            result.error = Some(failure("setup failed", &output));
+            if !options.keep {
+                let _ = std::fs::remove_dir_all(&workspace);
+            }
+            return Ok(result);
+        }
+    }
+
+    let mut command = Command::new(&options.rx);
+    command
+        .arg("run")
+        .arg("--max-iterations")
+        .arg(
+            task.max_iterations
+                .unwrap_or(DEFAULT_TASK_ITERATIONS)
+                .to_string(),
+        )
+        .args(&task.args);
+    if let Some(model) = &options.model {
+        command.arg("--model").arg(model);
+    }
+    command
+        .arg("--")
+        .arg(&task.goal)
+        .current_dir(&workspace)
+        .env("RX_EVAL_DIR", &options.suite_dir)
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true);
+    let started = Instant::now();
+    let run = command
+        .output()
+        .await
+        .with_context(|| format!("failed to start {}", options.rx.display()))?;
+    result.wall_ms = started.elapsed().as_millis();
+
+    let goals = load_task_goals(&workspace.join(DEFAULT_DATA_DIR));
+    let stats = Stats::from_goals(&goals);
+    result.iterations = goals
+        .iter()
+        .flatten()
+        .filter(|event| event.r#type == "action")
+        .count();
+    result.usage = stats.usage;
+    if let Some(termination) = stats.terminations.keys().next() {
+        result.termination = termination.clone();
+    }
+
+    if !run.status.success() {
+        let mut output = String::from_utf8_lossy(&run.stdout).into_owned();
+        output.push_str(&String::from_utf8_lossy(&run.stderr));
+        result.error = Some(failure(&format!("rx exited with {}", run.status), &output));
+    } else {
+        let (ok, output) = shell(&task.assert, &workspace, options).await?;
+        result.passed = ok;
+        if !ok {
+            result.error = Some(failure("assertion failed", &output));
+        }
+    }
+
+    if !options.keep {
+        let _ = std::fs::remove_dir_all(&workspace);
+    }
+    Ok(result)
+}
+
+/// Runs `script` through the platform shell in `workspace`; returns whether it
+/// succeeded and its combined output.
+async fn shell(script: &str, workspace: &Path, options: &EvalOptions) -> Result<(bool, String)> {
+    let output = shell_command(script)
+        .current_dir(workspace)
+        .env("RX_EVAL_DIR", &options.suite_dir)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await
+        .with_context(|| format!("failed to run '{}'", script))?;
+    let mut combined = String::from_utf8_lossy(&output.stdout).into_owned();
+    combined.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok((output.status.success(), combined))
+}
+
+/// Events of the goals the task's run wrote to the default JSONL logs.
+fn load_task_goals(data_dir: &Path) -> Vec<Vec<Event>> {
+    let Ok(entries) = std::fs::read_dir(data_dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("jsonl"))
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .map(|content| {
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str::<Event>(line).ok())
+                .collect()
+        })
+        .collect()
+}
+
+/// `reason` followed by the last lines of `output`, if there is any.
+fn failure(reason: &str, output: &str) -> String {
+    let lines: Vec<&str> = output.trim_end().lines().collect();
+    if lines.is_empty() {
+        return reason.to_string();
+    }
+    format!(
+        "{}:\n{}",
+        reason,
+        lines[lines.len().saturating_sub(ERROR_TAIL_LINES)..].join("\n")
+    )
+}
+
+/// One row per task plus a pass count.
+pub fn render_results(results: &[TaskResult]) -> String {
+    let mut out = format!(
+        "{:<24} {:<6} {:>10} {:>12} {:>12} {:>9}  {}\n",
+        "task", "result", "iterations", "input_tok", "output_tok", "wall", "termination"
+    );
+    for result in results {
+        out.push_str(&format!(
+            "{:<24} {:<6} {:>10} {:>12} {:>12} {:>8.1}s  {}\n",
+            result.name,
+            if result.passed { "pass" } else { "FAIL" },
+            result.iterations,
+            result.usage.input_tokens,
+            result.usage.output_tokens,
+            result.wall_ms as f64 / 1000.0,
+            result.termination
+        ));
+    }
+    let passed = results.iter().filter(|result| result.passed).count();
+    out.push_str(&format!("\n{}/{} tasks passed\n", passed, results.len()));
+    for result in results.iter().filter(|result| !result.passed) {
+        if let Some(error) = &result.error {
+            out.push_str(&format!("\n--- {} ---\n{}\n", result.name, error));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_results, EvalSuite, TaskResult};
+    use crate::model::ModelUsage;
+    use std::path::PathBuf;
+
+    #[test]
+    fn suite_parses_and_results_render_per_task() {
+        let suite: EvalSuite = toml::from_str(
+            r#"
+            [[task]]
+            name = "hello"
+            goal = "write hello.txt"
+            assert = "test -f hello.txt"
+
+            [[task]]
+            name = "fix-test"
+            goal = "make cargo test pass"
+            setup = "cp -r $RX_EVAL_DIR/fixtures/broken/. ."
+            assert = "cargo test"
+            max_iterations = 10
+            args = ["--verify-command", "cargo test"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(suite.tasks.len(), 2);
+        assert_eq!(suite.tasks[0].setup, None);
+        assert_eq!(suite.tasks[1].max_iterations, Some(10));
+        assert_eq!(suite.tasks[1].args, ["--verify-command", "cargo test"]);
+
+        let results = [
+            TaskResult {
+                name: "hello".to_string(),
+                passed: true,
+                error: None,
+                iterations: 3,
+                usage: ModelUsage {
+                    requests: 3,
+                    input_tokens: 1200,
+                    cached_input_tokens: 0,
+                    output_tokens: 80,
+                },
+                termination: "done:success".to_string(),
+                wall_ms: 2500,
+                workspace: PathBuf::from("/tmp/a"),
+            },
+            TaskResult {
+                name: "fix-test".to_string(),
+                passed: false,
+                error: Some("assertion failed:\n1 test failed".to_string()),
+                iterations: 10,
+                usage: ModelUsage::default(),
+                termination: "max_iterations".to_string(),
+                wall_ms: 61_000,
+                workspace: PathBuf::from("/tmp/b"),
+            },
+        ];
+        let report = render_results(&results);
+        assert!(report.contains("hello                    pass            3         1200"));
+        assert!(report.contains("   61.0s  max_iterations\n"));
+        assert!(report.contains("\n1/2 tasks passed\n"));
+        assert!(report.ends_with("--- fix-test ---\nassertion failed:\n1 test failed\n"));
+    }
+}
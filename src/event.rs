@@ -1,9 +1,18 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static LAST_EVENT_ID: AtomicU64 = AtomicU64::new(0);
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Event {
+    /// Nanosecond timestamp, bumped when needed so ids from one process are
+    /// strictly increasing.
     pub id: String,
+    /// Position within the goal's log, assigned by the state store on
+    /// append (starting at 1). Logs written before sequencing read as 0.
+    #[serde(default)]
+    pub seq: u64,
     pub timestamp: DateTime<Utc>,
     pub r#type: String,
     pub payload: serde_json::Value,
@@ -11,12 +20,17 @@ pub struct Event {
 
 impl Event {
     pub fn new(r#type: &str, payload: serde_json::Value) -> Self {
+        let now = Utc::now();
+        let nanos = now.timestamp_nanos_opt().unwrap_or_default().max(0) as u64;
+        let previous = LAST_EVENT_ID
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                Some(nanos.max(last + 1))
+            })
+            .unwrap_or_default();
         Self {
-            id: Utc::now()
-                .timestamp_nanos_opt()
-                .unwrap_or_default()
-                .to_string(),
-            timestamp: Utc::now(),
+            id: nanos.max(previous + 1).to_string(),
+            seq: 0,
+            timestamp: now,
             r#type: r#type.to_string(),
             payload,
         }
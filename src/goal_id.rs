@@ -0,0 +1,180 @@
+use crate::config::GoalIdConfig;
+use crate::small_model::{goal_slug, ModelRouter};
+use crate::utils::sanitize_goal_slug;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Deserialize;
+
+const PLACEHOLDERS: &[&str] = &["timestamp", "date", "time", "slug", "ulid", "uuid"];
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GoalIdFormat {
+    /// `20250101-120000-fix-the-build`
+    #[default]
+    Timestamp,
+    Ulid,
+    Uuid,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SlugStrategy {
+    /// The small model when one is configured, otherwise deterministic.
+    #[default]
+    Model,
+    /// Lowercased goal words joined by dashes.
+    Deterministic,
+    None,
+}
+
+/// How new goal IDs are built, from `[goal_id]`.
+#[derive(Debug, Clone, Default)]
+pub struct GoalIdScheme {
+    format: GoalIdFormat,
+    prefix: String,
+    slug: SlugStrategy,
+    template: Option<String>,
+}
+
+impl GoalIdScheme {
+    pub fn from_config(config: Option<&GoalIdConfig>) -> Result<Self> {
+        let Some(config) = config else {
+            return Ok(Self::default());
+        };
+        let prefix = config.prefix.clone().unwrap_or_default();
+        if !prefix.chars().all(is_id_char) {
+            return Err(anyhow!(
+                "[goal_id].prefix '{}' may only contain letters, digits, '-', '_' and '.'",
+                prefix
+            ));
+        }
+        if let Some(template) = &config.template {
+            for placeholder in placeholder_pattern().captures_iter(template) {
+                if !PLACEHOLDERS.contains(&&placeholder[1]) {
+                    return Err(anyhow!(
+                        "[goal_id].template has unknown placeholder '{{{}}}' (expected one of {})",
+                        &placeholder[1],
+                        PLACEHOLDERS.join(", ")
+                    ));
+                }
+            }
+        }
+        Ok(Self {
+            format: config.format.unwrap_or_default(),
+            prefix,
+            slug: config.slug.unwrap_or_default(),
+            template: config.template.clone(),
+        })
+    }
+
+    /// A new goal ID for `text`, generating the slug with the configured
+    /// strategy.
+    pub async fn generate(&self, router: Option<&ModelRouter>, text: &str) -> String {
+        let slug = match self.slug {
+            SlugStrategy::Model => goal_slug(router, text).await,
+            SlugStrategy::Deterministic => sanitize_goal_slug(text),
+            SlugStrategy::None => String::new(),
+        };
+        self.render(Utc::now(), &slug)
+    }
+
+    fn render(&self, now: DateTime<Utc>, slug: &str) -> String {
+        let value = |name: &str| match name {
+            "timestamp" => now.format("%Y%m%d-%H%M%S").to_string(),
+            "date" => now.format("%Y%m%d").to_string(),
+            "time" => now.format("%H%M%S").to_string(),
+            "slug" => slug.to_string(),
+            "ulid" => ulid::Ulid::from_datetime(now.into()).to_string(),
+            "uuid" => uuid::Uuid::new_v4().to_string(),
+            _ => String::new(),
+        };
+        let id = match (&self.template, self.format) {
+            (Some(template), _) => placeholder_pattern()
+                .replace_all(template, |caps: &regex::Captures| value(&caps[1]))
+                .into_owned(),
+            (None, GoalIdFormat::Timestamp) if slug.is_empty() => value("timestamp"),
+            (None, GoalIdFormat::Timestamp) => format!("{}-{}", value("timestamp"), slug),
+            (None, GoalIdFormat::Ulid) => value("ulid"),
+            (None, GoalIdFormat::Uuid) => value("uuid"),
+        };
+        // IDs name log files: drop other characters a template produced and
+        // the separators an empty slug leaves behind.
+        let id: String = id.chars().filter(|ch| is_id_char(*ch)).collect();
+        let id = id
+            .split('-')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        format!("{}{}", self.prefix, id)
+    }
+}
+
+fn is_id_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.')
+}
+
+fn placeholder_pattern() -> &'static Regex {
+    static PATTERN: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\{([a-z_]+)\}").expect("valid placeholder pattern"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GoalIdFormat, GoalIdScheme, SlugStrategy};
+    use crate::config::GoalIdConfig;
+    use chrono::{TimeZone, Utc};
+
+    fn scheme(config: GoalIdConfig) -> anyhow::Result<GoalIdScheme> {
+        GoalIdScheme::from_config(Some(&config))
+    }
+
+    #[test]
+    fn formats_prefixes_and_templates_render_safe_ids() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 4, 5, 6, 7).unwrap();
+        let default = GoalIdScheme::from_config(None).unwrap();
+        assert_eq!(
+            default.render(now, "fix-build"),
+            "20250304-050607-fix-build"
+        );
+        assert_eq!(default.render(now, ""), "20250304-050607");
+
+        let ulid = scheme(GoalIdConfig {
+            format: Some(GoalIdFormat::Ulid),
+            prefix: Some("rx-".to_string()),
+            ..GoalIdConfig::default()
+        })
+        .unwrap()
+        .render(now, "ignored");
+        assert!(ulid.starts_with("rx-01JNFRR1GR"), "{}", ulid);
+        assert_eq!(ulid.len(), 3 + 26);
+
+        let uuid = scheme(GoalIdConfig {
+            format: Some(GoalIdFormat::Uuid),
+            ..GoalIdConfig::default()
+        })
+        .unwrap()
+        .render(now, "ignored");
+        assert_eq!(uuid.len(), 36);
+
+        let templated = scheme(GoalIdConfig {
+            template: Some("{date}/{slug}".to_string()),
+            slug: Some(SlugStrategy::Deterministic),
+            ..GoalIdConfig::default()
+        })
+        .unwrap();
+        assert_eq!(templated.render(now, "fix-build"), "20250304fix-build");
+
+        assert!(scheme(GoalIdConfig {
+            template: Some("{date}-{nonce}".to_string()),
+            ..GoalIdConfig::default()
+        })
+        .is_err());
+        assert!(scheme(GoalIdConfig {
+            prefix: Some("a/b".to_string()),
+            ..GoalIdConfig::default()
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,207 @@
+use anyhow::{anyhow, Context, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// What to do with the changes of an `--isolated` run that ended `done`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsolatedChanges {
+    /// Merge the run's branch into the checkout's current branch.
+    Merge,
+    /// Write the run's changes to a patch file.
+    Patch,
+    /// Leave the worktree and branch for a later look.
+    Keep,
+}
+
+impl IsolatedChanges {
+    /// Asks on stdin; anything but `m` or `p` keeps the worktree.
+    pub async fn ask(branch: &str) -> Result<Self> {
+        print!(
+            "Take the changes on {}? [m]erge into this checkout, write a [p]atch, or [k]eep the worktree: ",
+            branch
+        );
+        std::io::stdout().flush().ok();
+        let line = tokio::task::spawn_blocking(|| {
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line).map(|_| line)
+        })
+        .await
+        .context("isolation prompt task failed")?
+        .context("failed to read answer from stdin")?;
+        Ok(match line.trim() {
+            "m" | "merge" => Self::Merge,
+            "p" | "patch" => Self::Patch,
+            _ => Self::Keep,
+        })
+    }
+}
+
+/// A git worktree on its own branch where an `--isolated` goal runs, so
+/// the user's checkout stays untouched until the changes are taken.
+#[derive(Debug, Clone)]
+pub struct IsolatedWorktree {
+    repo: PathBuf,
+    path: PathBuf,
+    branch: String,
+    base: String,
+}
+
+impl IsolatedWorktree {
+    /// Adds `<root>/<goal_id>` on branch `rx/<goal_id>` from the `HEAD` of
+    /// `repo`, or reuses it when a resumed goal already has one.
+    pub async fn create(repo: &Path, root: &Path, goal_id: &str) -> Result<Self> {
+        let path = root.join(goal_id);
+        let branch = format!("rx/{}", goal_id);
+        let base = git(repo, &["rev-parse", "HEAD"])
+            .await
+            .context("--isolated needs a git repository with at least one commit")?;
+        if path.join(".git").exists() {
+            let base = git(&path, &["merge-base", &base, "HEAD"]).await?;
+            return Ok(Self {
+                repo: repo.to_path_buf(),
+                path: path.canonicalize().unwrap_or(path),
+                branch,
+                base,
+            });
+        }
+        std::fs::create_dir_all(root)
+            .with_context(|| format!("failed to create {}", root.display()))?;
+        let target = path.to_string_lossy().into_owned();
+        git(repo, &["worktree", "add", "-b", &branch, &target, &base])
+            .await
+            .context("failed to create the isolated worktree")?;
+        Ok(Self {
+            repo: repo.to_path_buf(),
+            path: path.canonicalize().unwrap_or(path),
+            branch,
+            base,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn branch(&self) -> &str {
+        &self.branch
+    }
+
+    /// Commits everything left uncommitted in the worktree. Returns whether
+    /// the branch has any changes over the base commit.
+    pub async fn commit_all(&self, message: &str) -> Result<bool> {
+        git(&self.path, &["add", "-A"]).await?;
+        if !git(&self.path, &["status", "--porcelain"])
+            .await?
+            .is_empty()
+        {
+            git(&self.path, &["commit", "--no-verify", "-m", message]).await?;
+        }
+        let range = format!("{}..HEAD", self.base);
+        Ok(git(&self.path, &["rev-list", "--count", &range]).await? != "0")
+    }
+
+    /// Merges the branch into the checkout's current branch, aborting the
+    /// merge when it does not apply cleanly.
+    pub async fn merge(&self) -> Result<()> {
+        if let Err(error) = git(&self.repo, &["merge", "--no-edit", &self.branch]).await {
+            let _ = git(&self.repo, &["merge", "--abort"]).await;
+            return Err(error.context(format!("failed to merge {}", self.branch)));
+        }
+        Ok(())
+    }
+
+    /// Writes the branch's changes over the base commit to `path`, ready
+    /// for `git apply`.
+    pub async fn write_patch(&self, path: &Path) -> Result<()> {
+        let diff = git(&self.path, &["diff", "--binary", &self.base, "HEAD"]).await?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        std::fs::write(path, format!("{}\n", diff))
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Removes the worktree, and the branch too when `delete_branch` is set.
+    pub async fn remove(&self, delete_branch: bool) -> Result<()> {
+        let target = self.path.to_string_lossy().into_owned();
+        git(&self.repo, &["worktree", "remove", "--force", &target]).await?;
+        if delete_branch {
+            git(&self.repo, &["branch", "-D", &self.branch]).await?;
+        }
+        Ok(())
+    }
+}
+
+/// `$TMPDIR/rx-worktrees`.
+pub fn default_worktree_root() -> PathBuf {
+    std::env::temp_dir().join("rx-worktrees")
+}
+
+async fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("failed to run git")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{git, IsolatedWorktree};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[tokio::test]
+    async fn isolated_changes_stay_out_of_the_checkout_until_merged() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let base = std::env::temp_dir().join(format!("rx-isolation-{}", nanos));
+        let repo = base.join("repo");
+        std::fs::create_dir_all(&repo).unwrap();
+        for args in [
+            &["init", "-q"][..],
+            &["config", "user.email", "rx@example.com"],
+            &["config", "user.name", "rx"],
+            &["commit", "-q", "--allow-empty", "-m", "init"],
+        ] {
+            git(&repo, args).await.unwrap();
+        }
+
+        let worktree = IsolatedWorktree::create(&repo, &base.join("worktrees"), "g1")
+            .await
+            .unwrap();
+        assert_eq!(worktree.branch(), "rx/g1");
+        assert!(!worktree.commit_all("rx: nothing").await.unwrap());
+        std::fs::write(worktree.path().join("notes.txt"), "hello\n").unwrap();
+        assert!(worktree.commit_all("rx: add notes").await.unwrap());
+        assert!(!repo.join("notes.txt").exists());
+
+        let patch = base.join("g1.patch");
+        worktree.write_patch(&patch).await.unwrap();
+        assert!(std::fs::read_to_string(&patch)
+            .unwrap()
+            .contains("+++ b/notes.txt"));
+
+        worktree.merge().await.unwrap();
+        worktree.remove(true).await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(repo.join("notes.txt")).unwrap(),
+            "hello\n"
+        );
+        assert!(!worktree.path().exists());
+        let _ = std::fs::remove_dir_all(base);
+    }
+}
@@ -1,10 +1,115 @@
+use crate::approval::{
+    ApprovalDecision, ApprovalGate, CompletionDecision, CompletionReviewer, PlanReviewer,
+};
+use crate::artifacts::ArtifactStore;
+use crate::chaos::{fault_output, truncate_output, Chaos};
+use crate::critic::CompletionCritic;
 use crate::event::Event;
-use crate::model::{Action, Model, ToolCall};
+use crate::loop_detection::{LoopDetection, Repetition, LOOP_NOTE, STALL_NOTE};
+use crate::model::{Action, FallbackModel, Model, ModelPricing, ToolCall};
+use crate::policy::{Policy, PolicyAction};
+use crate::snapshot::Snapshots;
 use crate::state::StateStore;
-use crate::tool::ToolRegistry;
-use anyhow::Result;
+use crate::status_line::StatusLine;
+use crate::tool::{ToolError, ToolRegistry};
+use crate::tools::done::validate_completion;
+use crate::tools::plan::{PlanProgress, PLAN_REQUEST};
+use crate::tools::process::ProcessTable;
+use crate::tools::pty::PtySessions;
+use crate::tools::tool_output::limit_output;
+use crate::verify::Verifier;
+use crate::workspace_diff::WorkspaceDiff;
+use crate::write_quota::WriteQuota;
+use anyhow::{Context, Result};
 use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{debug, field, info, info_span, warn, Instrument};
+
+pub const DEFAULT_TOOL_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Run state requested through a [`KernelHandle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunSignal {
+    Run,
+    Pause,
+    Cancel,
+}
+
+/// Progress published by a spawned kernel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KernelStatus {
+    Starting,
+    Running {
+        iteration: usize,
+    },
+    /// Waiting before the model request for `iteration`.
+    Paused {
+        iteration: usize,
+    },
+    /// Terminated with the recorded termination reason.
+    Finished {
+        reason: String,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+impl KernelStatus {
+    /// Whether the run has ended, successfully or not.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Finished { .. } | Self::Failed { .. })
+    }
+}
+
+/// Controls a kernel started with [`Kernel::spawn`]. Dropping the handle
+/// detaches the run; a paused run then stops as if cancelled.
+pub struct KernelHandle {
+    signal: watch::Sender<RunSignal>,
+    status: watch::Receiver<KernelStatus>,
+    task: JoinHandle<Result<()>>,
+}
+
+impl KernelHandle {
+    /// Stops after the in-flight tool call with an `interrupted` termination.
+    pub fn cancel(&self) {
+        self.signal.send_replace(RunSignal::Cancel);
+    }
+
+    pub fn status(&self) -> watch::Receiver<KernelStatus> {
+        self.status.clone()
+    }
+
+    /// Waits for the run to end.
+    pub async fn join(self) -> Result<()> {
+        self.task.await.context("kernel task panicked")?
+    }
+
+    /// Holds the run before its next model request.
+    pub fn pause(&self) {
+        self.signal.send_if_modified(|signal| {
+            let pause = *signal == RunSignal::Run;
+            if pause {
+                *signal = RunSignal::Pause;
+            }
+            pause
+        });
+    }
+
+    pub fn resume(&self) {
+        self.signal.send_if_modified(|signal| {
+            let resume = *signal == RunSignal::Pause;
+            if resume {
+                *signal = RunSignal::Run;
+            }
+            resume
+        });
+    }
+}
 
 pub struct Kernel {
     goal_id: String,
@@ -12,6 +117,31 @@ pub struct Kernel {
     state_store: Arc<dyn StateStore>,
     tool_registry: ToolRegistry,
     max_iterations: usize,
+    approval_gate: Option<Arc<dyn ApprovalGate>>,
+    confirm: bool,
+    completion_reviewer: Option<Arc<dyn CompletionReviewer>>,
+    policy: Option<Arc<Policy>>,
+    max_duration: Option<Duration>,
+    max_iteration_duration: Option<Duration>,
+    pricing: Option<ModelPricing>,
+    max_cost: Option<f64>,
+    tool_timeout: Option<Duration>,
+    tool_timeouts: HashMap<String, Option<Duration>>,
+    processes: Option<Arc<ProcessTable>>,
+    chaos: Option<Chaos>,
+    pty_sessions: Option<Arc<PtySessions>>,
+    snapshots: Option<Snapshots>,
+    workspace_diff: Option<WorkspaceDiff>,
+    plan_mode: bool,
+    plan_reviewer: Option<Arc<dyn PlanReviewer>>,
+    critic: Option<Arc<dyn CompletionCritic>>,
+    max_review_rounds: usize,
+    verifier: Option<Verifier>,
+    fallback: Option<Arc<FallbackModel>>,
+    max_tool_output_bytes: Option<usize>,
+    artifacts: Option<Arc<ArtifactStore>>,
+    loop_detection: Option<LoopDetection>,
+    write_quota: Option<WriteQuota>,
 }
 
 impl Kernel {
@@ -28,80 +158,1898 @@ impl Kernel {
             state_store,
             tool_registry,
             max_iterations,
+            approval_gate: None,
+            confirm: false,
+            completion_reviewer: None,
+            policy: None,
+            max_duration: None,
+            max_iteration_duration: None,
+            pricing: None,
+            max_cost: None,
+            tool_timeout: Some(DEFAULT_TOOL_TIMEOUT),
+            tool_timeouts: HashMap::new(),
+            processes: None,
+            chaos: None,
+            pty_sessions: None,
+            snapshots: None,
+            workspace_diff: None,
+            plan_mode: false,
+            plan_reviewer: None,
+            critic: None,
+            max_review_rounds: 0,
+            verifier: None,
+            fallback: None,
+            max_tool_output_bytes: None,
+            artifacts: None,
+            loop_detection: None,
+            write_quota: None,
         }
     }
 
-    pub async fn run(&self) -> Result<()> {
-        println!("Starting goal {}", self.goal_id);
+    /// Answers `ask` policy decisions, and with `with_confirm` every
+    /// side-effecting tool call.
+    pub fn with_approval_gate(mut self, gate: Arc<dyn ApprovalGate>) -> Self {
+        self.approval_gate = Some(gate);
+        self
+    }
+
+    /// Asks the approval gate before every side-effecting tool call that no
+    /// `allow` rule covers (`--confirm`).
+    pub fn with_confirm(mut self) -> Self {
+        self.confirm = true;
+        self
+    }
+
+    /// Reviews `propose_done` calls; without one, well-formed proposals are
+    /// accepted.
+    pub fn with_completion_reviewer(mut self, reviewer: Arc<dyn CompletionReviewer>) -> Self {
+        self.completion_reviewer = Some(reviewer);
+        self
+    }
+
+    /// Reviews well-formed `propose_done` calls before the completion
+    /// reviewer (`[review]`). A critique is sent to the agent as a user
+    /// message and the run continues; after `max_rounds` critiques the
+    /// critic is no longer consulted.
+    pub fn with_critic(mut self, critic: Arc<dyn CompletionCritic>, max_rounds: usize) -> Self {
+        self.critic = Some(critic);
+        self.max_review_rounds = max_rounds;
+        self
+    }
+
+    /// Runs `verify_command` when the agent proposes a `success`; if it
+    /// fails, the proposal is rejected with the exit code and output tail.
+    pub fn with_verifier(mut self, verifier: Verifier) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+
+    /// Moves down `fallback`'s model chain when a model request fails,
+    /// recording a `model_switched` event. `fallback` should be (or sit
+    /// behind) the kernel's model.
+    pub fn with_model_fallback(mut self, fallback: Arc<FallbackModel>) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    pub fn with_policy(mut self, policy: Arc<Policy>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Wall-clock limit for the whole run, checked before each model request.
+    pub fn with_max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
+    /// Default limit for a single tool call; `None` lets calls run forever.
+    pub fn with_tool_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.tool_timeout = timeout;
+        self
+    }
+
+    /// Outputs bigger than `limit` bytes of JSON are stored in full in a
+    /// `tool_output_full` event while the `tool_output` the model sees
+    /// holds an excerpt; `read_tool_output` pages through the rest.
+    pub fn with_max_tool_output_bytes(mut self, limit: Option<usize>) -> Self {
+        self.max_tool_output_bytes = limit;
+        self
+    }
+
+    /// Keeps full tool outputs in `artifacts`, so `tool_output_full`
+    /// events hold an artifact id instead of the output itself.
+    pub fn with_artifacts(mut self, artifacts: Arc<ArtifactStore>) -> Self {
+        self.artifacts = Some(artifacts);
+        self
+    }
+
+    /// Overrides the tool call limit for one tool.
+    pub fn with_tool_timeout_for(mut self, tool: &str, timeout: Option<Duration>) -> Self {
+        self.tool_timeouts.insert(tool.to_string(), timeout);
+        self
+    }
+
+    /// Wall-clock cap for one iteration (model request plus tool call). A
+    /// tool still running when it expires is preempted so the model can try
+    /// something else.
+    pub fn with_max_iteration_duration(mut self, limit: Duration) -> Self {
+        self.max_iteration_duration = Some(limit);
+        self
+    }
 
+    /// Prices used to estimate run cost from the model's token usage.
+    pub fn with_pricing(mut self, pricing: ModelPricing) -> Self {
+        self.pricing = Some(pricing);
+        self
+    }
+
+    /// Cost limit in USD; needs [`Kernel::with_pricing`] to take effect.
+    pub fn with_max_cost(mut self, max_cost: f64) -> Self {
+        self.max_cost = Some(max_cost);
+        self
+    }
+
+    /// Background processes owned by this run; any still running are
+    /// killed when it ends.
+    pub fn with_process_table(mut self, processes: Arc<ProcessTable>) -> Self {
+        self.processes = Some(processes);
+        self
+    }
+
+    /// Terminal sessions owned by this run; they are closed when the kernel
+    /// is dropped.
+    pub fn with_pty_sessions(mut self, sessions: Arc<PtySessions>) -> Self {
+        self.pty_sessions = Some(sessions);
+        self
+    }
+
+    /// Injects simulated tool failures (`--chaos`).
+    pub fn with_chaos(mut self, chaos: Chaos) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// Warns, then stops with `loop_detected`, when the model keeps making
+    /// the identical tool call, and with `stalled` when it keeps replying
+    /// without any.
+    pub fn with_loop_detection(mut self, detection: LoopDetection) -> Self {
+        self.loop_detection = Some(detection);
+        self
+    }
+
+    /// Refuses file tool calls with `quota_exceeded` once the goal has
+    /// written `quota`'s bytes or touched its number of files.
+    pub fn with_write_quota(mut self, quota: WriteQuota) -> Self {
+        self.write_quota = Some(quota);
+        self
+    }
+
+    /// Records a `file_snapshot` event before each file tool call so the
+    /// change can be undone.
+    pub fn with_snapshots(mut self, snapshots: Snapshots) -> Self {
+        self.snapshots = Some(snapshots);
+        self
+    }
+
+    /// Appends a `workspace_diff` event after each tool call that changed
+    /// the git working tree (`[state].diff_events`).
+    pub fn with_workspace_diff(mut self, workspace_diff: WorkspaceDiff) -> Self {
+        self.workspace_diff = Some(workspace_diff);
+        self
+    }
+
+    /// Asks for a plan before any change (`--plan`). Gated tools are refused
+    /// until `submit_plan` is approved; progress is recorded as
+    /// `plan_step_started` and `plan_step_completed` events.
+    pub fn with_plan_mode(mut self) -> Self {
+        self.plan_mode = true;
+        self
+    }
+
+    /// Reviews submitted plans; without one, plans are approved as
+    /// submitted.
+    pub fn with_plan_reviewer(mut self, reviewer: Arc<dyn PlanReviewer>) -> Self {
+        self.plan_reviewer = Some(reviewer);
+        self
+    }
+
+    /// Runs to completion in the foreground. The first Ctrl-C cancels after
+    /// the in-flight tool call; a second one exits immediately.
+    pub async fn run(self) -> Result<()> {
+        let mut handle = self.spawn();
+        tokio::select! {
+            result = &mut handle.task => return result.context("kernel task panicked")?,
+            Ok(()) = tokio::signal::ctrl_c() => {
+                warn!("Interrupt received; stopping after the current step (Ctrl-C again to exit now)");
+                handle.cancel();
+            }
+        }
+        tokio::select! {
+            result = handle.join() => result,
+            Ok(()) = tokio::signal::ctrl_c() => std::process::exit(130),
+        }
+    }
+
+    /// Starts the run on a background task.
+    pub fn spawn(self) -> KernelHandle {
+        let (signal, signal_receiver) = watch::channel(RunSignal::Run);
+        let (status_sender, status) = watch::channel(KernelStatus::Starting);
+        let span = info_span!("kernel.run", goal_id = %self.goal_id);
+        let task = tokio::spawn(
+            async move {
+                info!("Starting goal {}", self.goal_id);
+                let result = self.run_loop(signal_receiver, &status_sender).await;
+                if let Some(processes) = &self.processes {
+                    let killed = processes.kill_all().await;
+                    if killed > 0 {
+                        info!("Stopped {} background process(es)", killed);
+                    }
+                }
+                status_sender.send_replace(match &result {
+                    Ok(reason) => KernelStatus::Finished {
+                        reason: reason.to_string(),
+                    },
+                    Err(error) => KernelStatus::Failed {
+                        error: format!("{:#}", error),
+                    },
+                });
+                result.map(|_| ())
+            }
+            .instrument(span),
+        );
+        KernelHandle {
+            signal,
+            status,
+            task,
+        }
+    }
+
+    /// Runs until a termination and returns its reason.
+    async fn run_loop(
+        &self,
+        mut signal: watch::Receiver<RunSignal>,
+        status: &watch::Sender<KernelStatus>,
+    ) -> Result<&'static str> {
+        let started = Instant::now();
+        let mut status_line = StatusLine::new(started);
+        if self.plan_mode {
+            self.request_plan().await?;
+        }
         for iteration in 1..=self.max_iterations {
-            println!("Iteration {}/{}", iteration, self.max_iterations);
+            let iteration_started = Instant::now();
+            if *signal.borrow() == RunSignal::Pause {
+                info!("Paused before iteration {}", iteration);
+                status.send_replace(KernelStatus::Paused { iteration });
+                if signal
+                    .wait_for(|signal| *signal != RunSignal::Pause)
+                    .await
+                    .is_err()
+                {
+                    return self.interrupted(iteration).await;
+                }
+            }
+            if *signal.borrow() == RunSignal::Cancel {
+                return self.interrupted(iteration).await;
+            }
+            if let Some((reason, details)) = self.budget_exceeded(started) {
+                return self.budget_termination(reason, iteration, details).await;
+            }
+            status.send_replace(KernelStatus::Running { iteration });
+            info!(
+                "{}",
+                status_line.render(
+                    iteration,
+                    self.max_iterations,
+                    self.model.usage(),
+                    self.pricing
+                )
+            );
+            self.state_store
+                .append_event(Event::new(
+                    "iteration_started",
+                    json!({
+                        "iteration": iteration,
+                        "elapsed_ms": started.elapsed().as_millis() as u64,
+                    }),
+                ))
+                .await?;
+            let usage_before = self.model.usage().unwrap_or_default();
             let history = self.state_store.load().await?;
-            let action = self.model.next_action(&history).await?;
+            // A model request has no side effects, so it is abandoned on
+            // cancel or when the time budget runs out; tool calls below
+            // always run to completion.
+            let deadline = self.max_duration.map(|limit| started + limit);
+            let action = tokio::select! {
+                action = self.next_action(&history, iteration) => action?,
+                true = cancel_requested(&mut signal) => {
+                    return self.interrupted(iteration).await;
+                }
+                _ = sleep_until(deadline) => {
+                    let details = self.time_budget_details(started);
+                    return self
+                        .budget_termination("time_budget_exceeded", iteration, details)
+                        .await;
+                }
+            };
 
             self.state_store
                 .append_event(Event::new("action", serde_json::json!(action.clone())))
                 .await?;
 
-            match action {
+            let tool = match &action {
+                Action::ToolCall(tool_call) => Some(tool_call.name.clone()),
+                Action::Message(_) => None,
+            };
+            let mut repetition = Repetition::Fine;
+            let accepted = match action {
                 Action::Message(message) => {
-                    println!("model message: {}", message);
+                    info!("model message: {}", message);
+                    if let Some(detection) = &self.loop_detection {
+                        repetition = detection.check_stall(&history);
+                    }
+                    None
                 }
                 Action::ToolCall(tool_call) => {
-                    println!("tool call: {} [{}]", tool_call.name, tool_call.id);
-                    let output = self.execute_tool(&tool_call).await;
+                    info!("tool call: {} [{}]", tool_call.name, tool_call.id);
+                    debug!("tool input {}: {}", tool_call.name, tool_call.arguments);
+                    if let Some(detection) = &self.loop_detection {
+                        repetition = detection.check(&history, &tool_call);
+                    }
+                    let invalid_completion = (tool_call.name == "propose_done")
+                        .then(|| validate_completion(&tool_call.arguments).err())
+                        .flatten();
+                    let plan_pending = self.plan_mode
+                        && self
+                            .tool_registry
+                            .side_effecting(&tool_call.name, &tool_call.arguments)
+                        && PlanProgress::from_history(&history).is_none();
+                    let violations = self
+                        .tool_registry
+                        .violations(&tool_call.name, &tool_call.arguments);
+                    let refusal = if plan_pending {
+                        Some(json!({
+                            "error": "plan_required",
+                            "details": "submit a plan with submit_plan and wait for its approval before changing anything",
+                        }))
+                    } else if invalid_completion.is_none() && !violations.is_empty() {
+                        info!(
+                            "tool call {} has {} invalid argument(s)",
+                            tool_call.name,
+                            violations.len()
+                        );
+                        Some(json!({
+                            "error": "invalid_arguments",
+                            "violations": violations,
+                            "details": "the arguments do not match the tool's parameters schema; fix them and call the tool again",
+                        }))
+                    } else if let Some(exceeded) = self
+                        .write_quota
+                        .and_then(|quota| quota.check(&history, &tool_call))
+                    {
+                        info!("tool call {} refused by the write quota", tool_call.name);
+                        Some(exceeded)
+                    } else {
+                        self.authorize(&tool_call).await?
+                    };
+                    let output = match refusal {
+                        Some(refusal) => refusal,
+                        None if invalid_completion.is_some() => json!({
+                            "error": "invalid_completion",
+                            "details": invalid_completion,
+                        }),
+                        None => {
+                            let preempt_at = self
+                                .max_iteration_duration
+                                .map(|limit| iteration_started + limit);
+                            self.snapshot(&tool_call).await?;
+                            self.execute_with_chaos(&tool_call, preempt_at).await?
+                        }
+                    };
+                    status_line.record(&tool_call, &output);
+                    let output = match tool_call.name.as_str() {
+                        _ if !self.plan_mode || output.get("error").is_some() => output,
+                        "submit_plan" => self.review_plan(&tool_call, output).await?,
+                        "complete_step" => self.complete_step(&history, output).await?,
+                        _ => output,
+                    };
+                    let (output, proposal_accepted, critique) =
+                        if tool_call.name == "propose_done" && output.get("error").is_none() {
+                            self.review_proposal(&tool_call, &history, output).await?
+                        } else {
+                            (output, false, None)
+                        };
 
+                    let output = ToolError::annotate(output);
+                    debug!("tool output {}: {}", tool_call.name, output);
+                    let shortened = self.max_tool_output_bytes.and_then(|limit| {
+                        if matches!(tool_call.name.as_str(), "propose_done" | "read_tool_output") {
+                            return None;
+                        }
+                        limit_output(&output, limit, &tool_call.id)
+                    });
+                    if shortened.is_some() {
+                        info!("tool output {} shortened for the model", tool_call.name);
+                        let mut full = json!({
+                            "tool_call_id": tool_call.id,
+                            "name": tool_call.name,
+                            "bytes": output.to_string().len(),
+                        });
+                        match self.store_artifact(&output).await {
+                            Some(artifact) => full["artifact"] = json!(artifact),
+                            None => full["output"] = output.clone(),
+                        }
+                        self.state_store
+                            .append_event(Event::new("tool_output_full", full))
+                            .await?;
+                    }
                     self.state_store
                         .append_event(Event::new(
                             "tool_output",
                             json!({
                                 "tool_call_id": tool_call.id,
                                 "name": tool_call.name,
-                                "output": output,
+                                "output": shortened.as_ref().unwrap_or(&output),
                             }),
                         ))
                         .await?;
-
-                    if tool_call.name == "done" {
-                        println!("termination requested by done tool");
+                    if tool_call.name == "set_working_dir" && output.get("error").is_none() {
+                        // Replayed on resume, see `restore_working_dir`.
+                        self.state_store
+                            .append_event(Event::new(
+                                "working_dir",
+                                json!({ "tool_call_id": tool_call.id, "cwd": output["cwd"] }),
+                            ))
+                            .await?;
+                    }
+                    self.record_workspace_diff(&tool_call).await?;
+                    if let Some(critique) = critique {
                         self.state_store
                             .append_event(Event::new(
-                                "termination",
+                                "user_message",
                                 json!({
-                                    "reason": "done",
-                                    "iteration": iteration,
-                                    "details": output,
+                                    "message": format!("A reviewer checked your completion proposal and found problems. Fix them, verify, and call propose_done again.\n\n{}", critique),
+                                    "source": "reviewer",
                                 }),
                             ))
                             .await?;
-                        return Ok(());
                     }
+
+                    proposal_accepted.then_some(output)
+                }
+            };
+
+            let usage = self.model.usage().unwrap_or_default().since(&usage_before);
+            self.state_store
+                .append_event(Event::new(
+                    "iteration_finished",
+                    json!({
+                        "iteration": iteration,
+                        "duration_ms": iteration_started.elapsed().as_millis() as u64,
+                        "elapsed_ms": started.elapsed().as_millis() as u64,
+                        "tool": tool,
+                        "usage": usage,
+                        "cache_hit_rate": usage.cache_hit_rate(),
+                    }),
+                ))
+                .await?;
+
+            if let Some(output) = accepted {
+                info!("completion proposal accepted");
+                self.state_store
+                    .append_event(Event::new(
+                        "termination",
+                        json!({
+                            "reason": "done",
+                            "outcome": output["outcome"],
+                            "iteration": iteration,
+                            "details": output,
+                            "usage": self.model.usage(),
+                            "cost_usd": self.cost(),
+                            "cache_savings_usd": self.cache_savings(),
+                        }),
+                    ))
+                    .await?;
+                self.print_usage();
+                return Ok("done");
+            }
+            match repetition {
+                Repetition::Fine => {}
+                Repetition::Warn(repeats) => {
+                    let (message, source) = match &tool {
+                        Some(_) => (LOOP_NOTE, "loop_detection"),
+                        None => (STALL_NOTE, "stall_watchdog"),
+                    };
+                    info!("{} after {} repeated actions", source, repeats);
+                    self.state_store
+                        .append_event(Event::new(
+                            "user_message",
+                            json!({
+                                "message": message,
+                                "source": source,
+                                "repeats": repeats,
+                            }),
+                        ))
+                        .await?;
+                }
+                Repetition::Terminate(repeats) => {
+                    let (reason, details) = match &tool {
+                        Some(tool) => {
+                            ("loop_detected", json!({ "tool": tool, "repeats": repeats }))
+                        }
+                        None => ("stalled", json!({ "messages": repeats })),
+                    };
+                    info!("Stopping at iteration {}: {}", iteration, reason);
+                    return self.terminate(reason, iteration, details).await;
                 }
             }
         }
 
-        println!("Max iterations reached ({})", self.max_iterations);
+        info!("Max iterations reached ({})", self.max_iterations);
         self.state_store
             .append_event(Event::new(
                 "termination",
                 json!({
                     "reason": "max_iterations",
                     "iterations": self.max_iterations,
+                    "usage": self.model.usage(),
+                    "cost_usd": self.cost(),
+                    "cache_savings_usd": self.cache_savings(),
+                }),
+            ))
+            .await?;
+        self.print_usage();
+
+        Ok("max_iterations")
+    }
+
+    /// The model's next action. A failed request moves to the next model of
+    /// the fallback chain, if any, and is sent again.
+    async fn next_action(&self, history: &[Event], iteration: usize) -> Result<Action> {
+        loop {
+            let error = match self
+                .model
+                .next_action(history)
+                .instrument(info_span!("model.next_action", iteration))
+                .await
+            {
+                Ok(action) => return Ok(action),
+                Err(error) => error,
+            };
+            let Some((from, to)) = self.fallback.as_ref().and_then(|chain| chain.switch()) else {
+                return Err(error);
+            };
+            warn!("model {} failed ({:#}); switching to {}", from, error, to);
+            self.state_store
+                .append_event(Event::new(
+                    "model_switched",
+                    json!({
+                        "from": from,
+                        "to": to,
+                        "error": format!("{:#}", error),
+                        "iteration": iteration,
+                    }),
+                ))
+                .await?;
+        }
+    }
+
+    /// The first exhausted budget, if any, with its termination details.
+    fn budget_exceeded(&self, started: Instant) -> Option<(&'static str, Value)> {
+        if self
+            .max_duration
+            .is_some_and(|limit| started.elapsed() >= limit)
+        {
+            return Some(("time_budget_exceeded", self.time_budget_details(started)));
+        }
+        let (Some(limit), Some(pricing)) = (self.max_cost, self.pricing) else {
+            return None;
+        };
+        let cost = self.model.usage().map(|usage| pricing.cost(&usage))?;
+        (cost >= limit).then(|| {
+            (
+                "cost_budget_exceeded",
+                json!({ "cost_usd": cost, "limit_usd": limit }),
+            )
+        })
+    }
+
+    fn time_budget_details(&self, started: Instant) -> Value {
+        json!({
+            "elapsed_secs": started.elapsed().as_secs(),
+            "limit_secs": self.max_duration.map(|limit| limit.as_secs()),
+        })
+    }
+
+    async fn budget_termination(
+        &self,
+        reason: &'static str,
+        iteration: usize,
+        details: Value,
+    ) -> Result<&'static str> {
+        info!("Budget exhausted ({}) at iteration {}", reason, iteration);
+        self.terminate(reason, iteration, details).await
+    }
+
+    /// Records a `termination` with `reason` and `details` for a goal that
+    /// can be resumed.
+    async fn terminate(
+        &self,
+        reason: &'static str,
+        iteration: usize,
+        details: Value,
+    ) -> Result<&'static str> {
+        let mut payload = json!({
+            "reason": reason,
+            "iteration": iteration,
+            "usage": self.model.usage(),
+            "cost_usd": self.cost(),
+            "cache_savings_usd": self.cache_savings(),
+        });
+        if let (Some(payload), Value::Object(details)) = (payload.as_object_mut(), details) {
+            payload.extend(details);
+        }
+        self.state_store
+            .append_event(Event::new("termination", payload))
+            .await?;
+        self.print_usage();
+        info!("Resume with: rx resume {}", self.goal_id);
+        Ok(reason)
+    }
+
+    async fn interrupted(&self, iteration: usize) -> Result<&'static str> {
+        info!("Interrupted at iteration {}", iteration);
+        self.state_store
+            .append_event(Event::new(
+                "termination",
+                json!({
+                    "reason": "interrupted",
+                    "iteration": iteration,
+                    "usage": self.model.usage(),
+                    "cost_usd": self.cost(),
+                    "cache_savings_usd": self.cache_savings(),
+                }),
+            ))
+            .await?;
+        self.print_usage();
+        info!("Resume with: rx resume {}", self.goal_id);
+        Ok("interrupted")
+    }
+
+    /// Estimated model cost so far, when prices are known.
+    fn cost(&self) -> Option<f64> {
+        Some(self.pricing?.cost(&self.model.usage()?))
+    }
+
+    /// Estimated USD the prompt cache saved so far, when prices are known.
+    fn cache_savings(&self) -> Option<f64> {
+        Some(self.pricing?.cache_savings(&self.model.usage()?))
+    }
+
+    fn print_usage(&self) {
+        if let Some(usage) = self.model.usage() {
+            info!(
+                "Model usage: {} requests, {} input tokens ({} cached, {:.1}% cache hit), {} output tokens",
+                usage.requests,
+                usage.input_tokens,
+                usage.cached_input_tokens,
+                usage.cache_hit_rate() * 100.0,
+                usage.output_tokens
+            );
+            if let Some(pricing) = self.pricing {
+                info!(
+                    "Estimated model cost: ${:.4} (prompt cache saved ${:.4})",
+                    pricing.cost(&usage),
+                    pricing.cache_savings(&usage)
+                );
+            }
+        }
+    }
+
+    /// Second phase of `propose_done`. A rejection is recorded as the
+    /// proposal's tool output so the agent sees the feedback.
+    async fn review_completion(&self, proposal: &ToolCall) -> Result<CompletionDecision> {
+        match &self.completion_reviewer {
+            Some(reviewer) => reviewer.review_completion(proposal).await,
+            None => Ok(CompletionDecision::Accepted),
+        }
+    }
+
+    /// Adds the plan instruction unless the goal already has it or a plan.
+    async fn request_plan(&self) -> Result<()> {
+        let history = self.state_store.load().await?;
+        let requested = history.iter().any(|event| {
+            event.r#type == "plan"
+                || (event.r#type == "user_message" && event.payload["message"] == PLAN_REQUEST)
+        });
+        if !requested {
+            self.state_store
+                .append_event(Event::new(
+                    "user_message",
+                    json!({ "message": PLAN_REQUEST }),
+                ))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Second phase of `submit_plan`: an approved plan is recorded as a
+    /// `plan` event and its first step is started.
+    async fn review_plan(&self, tool_call: &ToolCall, output: Value) -> Result<Value> {
+        let decision = match &self.plan_reviewer {
+            Some(reviewer) => reviewer.review_plan(tool_call).await?,
+            None => CompletionDecision::Accepted,
+        };
+        if let CompletionDecision::Rejected { feedback } = decision {
+            info!("plan rejected: {}", feedback);
+            return Ok(json!({
+                "status": "rejected",
+                "feedback": feedback,
+                "details": "revise the plan and call submit_plan again",
+            }));
+        }
+
+        let steps: Vec<String> = serde_json::from_value(output["steps"].clone())
+            .context("submit_plan returned malformed steps")?;
+        info!("plan approved ({} steps)", steps.len());
+        self.state_store
+            .append_event(Event::new(
+                "plan",
+                json!({ "tool_call_id": tool_call.id, "steps": steps }),
+            ))
+            .await?;
+        self.start_step(1, &steps).await?;
+        Ok(json!({
+            "status": "approved",
+            "steps": steps,
+            "current_step": { "index": 1, "step": steps[0] },
+            "details": "work on this step, then call complete_step",
+        }))
+    }
+
+    /// Records the current plan step as done and starts the next one.
+    async fn complete_step(&self, history: &[Event], output: Value) -> Result<Value> {
+        let Some(progress) = PlanProgress::from_history(history) else {
+            return Ok(json!({
+                "error": "no_plan",
+                "details": "call submit_plan and wait for its approval first",
+            }));
+        };
+        let Some((index, step)) = progress.current() else {
+            return Ok(json!({
+                "error": "plan_finished",
+                "details": "every step is complete; verify the goal and call propose_done",
+            }));
+        };
+        let total = progress.steps.len();
+        info!("plan step {}/{} completed", index, total);
+        self.state_store
+            .append_event(Event::new(
+                "plan_step_completed",
+                json!({
+                    "index": index,
+                    "total": total,
+                    "step": step,
+                    "summary": output["summary"],
+                }),
+            ))
+            .await?;
+        if index == total {
+            return Ok(json!({
+                "completed": index,
+                "remaining": 0,
+                "details": "every step is complete; verify the goal and call propose_done",
+            }));
+        }
+        self.start_step(index + 1, &progress.steps).await?;
+        Ok(json!({
+            "completed": index,
+            "remaining": total - index,
+            "current_step": { "index": index + 1, "step": progress.steps[index] },
+        }))
+    }
+
+    /// Appends `plan_step_started` for step `index` (numbered from 1).
+    async fn start_step(&self, index: usize, steps: &[String]) -> Result<()> {
+        info!("plan step {}/{}: {}", index, steps.len(), steps[index - 1]);
+        self.state_store
+            .append_event(Event::new(
+                "plan_step_started",
+                json!({
+                    "index": index,
+                    "total": steps.len(),
+                    "step": steps[index - 1],
+                }),
+            ))
+            .await?;
+        Ok(())
+    }
+
+    /// Second phase of `propose_done`: the verify command, the critic, and
+    /// the completion reviewer, in that order. Returns the output to record,
+    /// whether the proposal ends the run, and a critique to send back.
+    async fn review_proposal(
+        &self,
+        proposal: &ToolCall,
+        history: &[Event],
+        output: Value,
+    ) -> Result<(Value, bool, Option<String>)> {
+        let verification = self.verify(proposal).await;
+        let mut critique = None;
+        let decision = match &verification {
+            Some(result) if result["passed"] != true => CompletionDecision::Rejected {
+                feedback: "the verify command failed; make it pass before proposing done again"
+                    .to_string(),
+            },
+            _ => {
+                critique = self.critique(proposal, history).await?;
+                match critique {
+                    Some(_) => CompletionDecision::Rejected {
+                        feedback: "a reviewer found problems; see the review that follows"
+                            .to_string(),
+                    },
+                    None => self.review_completion(proposal).await?,
+                }
+            }
+        };
+        let accepted = decision == CompletionDecision::Accepted;
+        let mut output = match decision {
+            CompletionDecision::Accepted => with_status(output, "accepted"),
+            CompletionDecision::Rejected { feedback } => {
+                info!("completion proposal rejected: {}", feedback);
+                json!({
+                    "status": "rejected",
+                    "feedback": feedback,
+                    "proposal": output,
+                })
+            }
+        };
+        if let Some(verification) = verification {
+            output["verification"] = verification;
+        }
+        Ok((output, accepted, critique))
+    }
+
+    /// Runs the verify command for a `success` proposal. A command that
+    /// cannot be started counts as failed.
+    async fn verify(&self, proposal: &ToolCall) -> Option<Value> {
+        let verifier = self.verifier.as_ref()?;
+        if proposal.arguments["outcome"] != "success" {
+            return None;
+        }
+        info!("verifying completion: {}", verifier.command());
+        let result = verifier.run().await.unwrap_or_else(|error| {
+            json!({
+                "command": verifier.command(),
+                "passed": false,
+                "code": null,
+                "output_tail": format!("{:#}", error),
+            })
+        });
+        if result["passed"] != true {
+            info!("verify command failed (exit code {})", result["code"]);
+        }
+        Some(result)
+    }
+
+    /// Asks the critic about a proposal and records its verdict as a
+    /// `completion_review` event. Returns the critique when the proposal
+    /// must not end the run yet; a failed review lets it through.
+    async fn critique(&self, proposal: &ToolCall, history: &[Event]) -> Result<Option<String>> {
+        let Some(critic) = &self.critic else {
+            return Ok(None);
+        };
+        let round = history
+            .iter()
+            .filter(|event| event.r#type == "completion_review")
+            .count()
+            + 1;
+        if round > self.max_review_rounds {
+            return Ok(None);
+        }
+        let critique = match critic.critique(proposal, history).await {
+            Ok(critique) => critique,
+            Err(error) => {
+                warn!("completion review failed: {:#}", error);
+                return Ok(None);
+            }
+        };
+        info!(
+            "completion review {}/{}: {}",
+            round,
+            self.max_review_rounds,
+            if critique.is_some() {
+                "changes requested"
+            } else {
+                "approved"
+            }
+        );
+        self.state_store
+            .append_event(Event::new(
+                "completion_review",
+                json!({
+                    "tool_call_id": proposal.id,
+                    "round": round,
+                    "max_rounds": self.max_review_rounds,
+                    "approved": critique.is_none(),
+                    "critique": critique,
                 }),
             ))
             .await?;
+        Ok(critique)
+    }
+
+    /// Consults the policy and approval gate. Returns the refusal output to
+    /// record instead of executing the tool, or `None` when the call may run.
+    async fn authorize(&self, tool_call: &ToolCall) -> Result<Option<Value>> {
+        let mut needs_approval = self.confirm
+            && self
+                .tool_registry
+                .side_effecting(&tool_call.name, &tool_call.arguments);
+
+        if let Some(policy) = &self.policy {
+            let decision = policy.evaluate(tool_call);
+            self.state_store
+                .append_event(Event::new(
+                    "policy_decision",
+                    json!({
+                        "tool_call_id": tool_call.id,
+                        "name": tool_call.name,
+                        "decision": decision.action,
+                        "rule": decision.rule,
+                    }),
+                ))
+                .await?;
+
+            match decision.action {
+                PolicyAction::Allow if decision.rule.is_some() => needs_approval = false,
+                PolicyAction::Allow => {}
+                PolicyAction::Deny => return Ok(Some(json!({ "error": "denied_by_policy" }))),
+                PolicyAction::Ask => needs_approval = true,
+            }
+        }
+
+        if !needs_approval {
+            return Ok(None);
+        }
+
+        let Some(gate) = &self.approval_gate else {
+            return Ok(Some(json!({
+                "error": "denied_by_policy",
+                "details": "policy requires approval but no approval gate is configured"
+            })));
+        };
+
+        Ok(match gate.review(tool_call).await {
+            Ok(ApprovalDecision::Approved) => None,
+            Ok(ApprovalDecision::Denied) => Some(json!({ "error": "denied_by_user" })),
+            Err(error) => Some(json!({
+                "error": "denied_by_user",
+                "details": format!("approval prompt failed: {}", error)
+            })),
+        })
+    }
+
+    /// Records a `file_snapshot` event before a file tool call changes a file.
+    async fn snapshot(&self, tool_call: &ToolCall) -> Result<()> {
+        let Some(snapshots) = &self.snapshots else {
+            return Ok(());
+        };
+        if let Some(payload) = snapshots.capture(tool_call).await {
+            self.state_store
+                .append_event(Event::new("file_snapshot", payload))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Writes `output` to the artifact store, if there is one. Falls back
+    /// to `None` (inline in the event) when the write fails.
+    async fn store_artifact(&self, output: &Value) -> Option<String> {
+        let artifacts = self.artifacts.as_ref()?;
+        match artifacts.put_json(output).await {
+            Ok(id) => Some(id),
+            Err(error) => {
+                warn!("failed to store tool output artifact: {:#}", error);
+                None
+            }
+        }
+    }
 
+    async fn record_workspace_diff(&self, tool_call: &ToolCall) -> Result<()> {
+        let Some(workspace_diff) = &self.workspace_diff else {
+            return Ok(());
+        };
+        match workspace_diff.changes().await {
+            Ok(Some(files)) => {
+                self.state_store
+                    .append_event(Event::new(
+                        "workspace_diff",
+                        json!({
+                            "tool_call_id": tool_call.id,
+                            "name": tool_call.name,
+                            "files": files,
+                        }),
+                    ))
+                    .await?;
+            }
+            Ok(None) => {}
+            Err(error) => warn!("workspace diff unavailable: {:#}", error),
+        }
         Ok(())
     }
 
-    async fn execute_tool(&self, tool_call: &ToolCall) -> Value {
+    /// Runs the tool, unless chaos mode replaces or truncates this call's
+    /// output; injected faults are recorded as `chaos_fault` events.
+    async fn execute_with_chaos(
+        &self,
+        tool_call: &ToolCall,
+        preempt_at: Option<Instant>,
+    ) -> Result<Value> {
+        let Some(fault) = self.chaos.as_ref().and_then(|chaos| chaos.pick(tool_call)) else {
+            return Ok(self.execute_tool(tool_call, preempt_at).await);
+        };
+        info!("chaos: injecting {:?} into {}", fault, tool_call.name);
+        self.state_store
+            .append_event(Event::new(
+                "chaos_fault",
+                json!({
+                    "tool_call_id": tool_call.id,
+                    "name": tool_call.name,
+                    "fault": fault,
+                }),
+            ))
+            .await?;
+        Ok(match fault_output(fault, tool_call) {
+            Some(output) => output,
+            None => truncate_output(self.execute_tool(tool_call, preempt_at).await),
+        })
+    }
+
+    /// Runs the tool in a `tool.execute` span that records its error, if
+    /// any.
+    async fn execute_tool(&self, tool_call: &ToolCall, preempt_at: Option<Instant>) -> Value {
+        let span = info_span!(
+            "tool.execute",
+            tool = %tool_call.name,
+            tool_call_id = %tool_call.id,
+            error = field::Empty,
+        );
+        let output = self
+            .run_tool(tool_call, preempt_at)
+            .instrument(span.clone())
+            .await;
+        if let Some(error) = output.get("error") {
+            span.record("error", field::display(error));
+        }
+        output
+    }
+
+    /// Runs the tool under its timeout and, when `preempt_at` is set, the
+    /// iteration deadline. Either limit drops the tool future, which kills
+    /// any subprocess it spawned.
+    async fn run_tool(&self, tool_call: &ToolCall, preempt_at: Option<Instant>) -> Value {
         if let Some(tool) = self.tool_registry.get(&tool_call.name) {
-            match tool.execute(tool_call.arguments.clone()).await {
+            let timeout = self
+                .tool_timeouts
+                .get(&tool_call.name)
+                .copied()
+                .unwrap_or(self.tool_timeout);
+            let timeout_at = timeout.map(|limit| Instant::now() + limit);
+            let result = tokio::select! {
+                result = tool.execute(tool_call.arguments.clone()) => result,
+                _ = sleep_until(timeout_at) => {
+                    info!("tool {} timed out after {:?}", tool_call.name, timeout.unwrap_or_default());
+                    return json!({
+                        "error": "timed_out",
+                        "timeout_secs": timeout.unwrap_or_default().as_secs_f64(),
+                        "details": "the tool call was cancelled; try a different approach or a non-interactive command"
+                    });
+                }
+                _ = sleep_until(preempt_at) => {
+                    info!("tool {} preempted at the iteration time limit", tool_call.name);
+                    return json!({
+                        "error": "preempted",
+                        "iteration_limit_secs": self.max_iteration_duration.unwrap_or_default().as_secs_f64(),
+                        "details": "the iteration time limit ran out and the tool call was stopped; choose a different strategy, such as smaller steps or a command that cannot block"
+                    });
+                }
+            };
+            match result {
                 Ok(output) => output,
-                Err(error) => json!({ "error": error.to_string() }),
+                Err(error) => ToolError::from_error(&error).output(format!("{:#}", error)),
             }
         } else {
-            json!({ "error": format!("tool {} not registered", tool_call.name) })
+            json!({
+                "error": "unknown_tool",
+                "details": format!("tool {} not registered", tool_call.name),
+            })
+        }
+    }
+}
+
+impl Drop for Kernel {
+    fn drop(&mut self) {
+        if let Some(sessions) = &self.pty_sessions {
+            sessions.close_all();
         }
     }
 }
+
+fn with_status(mut output: Value, status: &str) -> Value {
+    if let Some(fields) = output.as_object_mut() {
+        fields.insert("status".to_string(), json!(status));
+    }
+    output
+}
+
+/// Resolves at `deadline`, or never without one.
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Resolves to `true` once cancel is requested, or `false` if the handle
+/// is dropped first.
+async fn cancel_requested(signal: &mut watch::Receiver<RunSignal>) -> bool {
+    signal
+        .wait_for(|signal| *signal == RunSignal::Cancel)
+        .await
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Kernel, KernelStatus};
+    use crate::approval::{ApprovalDecision, ApprovalGate, CompletionDecision, CompletionReviewer};
+    use crate::config::{PolicyConfig, PolicyRuleConfig};
+    use crate::critic::CompletionCritic;
+    use crate::event::Event;
+    use crate::loop_detection::LoopDetection;
+    use crate::model::{Action, FallbackModel, Model, ModelPricing, ModelUsage, ToolCall};
+    use crate::policy::{Policy, PolicyAction};
+    use crate::state::StateStore;
+    use crate::tool::{Tool, ToolRegistry};
+    use crate::tools::done::ProposeDoneTool;
+    use crate::tools::plan::{CompleteStepTool, SubmitPlanTool};
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::sync::Notify;
+
+    #[derive(Default)]
+    struct VecStore {
+        events: Mutex<Vec<Event>>,
+    }
+
+    #[async_trait]
+    impl StateStore for VecStore {
+        async fn load(&self) -> Result<Vec<Event>> {
+            Ok(self.events.lock().unwrap().clone())
+        }
+
+        async fn append_event(&self, mut event: Event) -> Result<Event> {
+            let mut events = self.events.lock().unwrap();
+            event.seq = events.len() as u64 + 1;
+            events.push(event.clone());
+            Ok(event)
+        }
+
+        async fn list_goals(&self) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+    }
+
+    struct SlowEditModel;
+
+    #[async_trait]
+    impl Model for SlowEditModel {
+        async fn next_action(&self, _history: &[Event]) -> Result<Action> {
+            Ok(Action::ToolCall(ToolCall {
+                id: "call-1".to_string(),
+                name: "slow_edit".to_string(),
+                arguments: json!({}),
+            }))
+        }
+    }
+
+    /// Reports that it started, then waits to be released before finishing.
+    struct SlowEditTool {
+        started: Arc<Notify>,
+        release: Arc<Notify>,
+    }
+
+    #[async_trait]
+    impl Tool for SlowEditTool {
+        fn name(&self) -> &str {
+            "slow_edit"
+        }
+
+        fn description(&self) -> &str {
+            "test tool"
+        }
+
+        fn parameters(&self) -> Value {
+            json!({ "type": "object" })
+        }
+
+        async fn execute(&self, _input: Value) -> Result<Value> {
+            self.started.notify_one();
+            self.release.notified().await;
+            Ok(json!({ "written": true }))
+        }
+    }
+
+    #[tokio::test]
+    async fn handle_pauses_between_steps_and_cancel_finishes_in_flight_tool() {
+        let (started, release) = (Arc::new(Notify::new()), Arc::new(Notify::new()));
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(SlowEditTool {
+            started: Arc::clone(&started),
+            release: Arc::clone(&release),
+        }));
+        let store = Arc::new(VecStore::default());
+        let handle = Kernel::new(
+            "goal".to_string(),
+            Arc::new(SlowEditModel),
+            Arc::clone(&store) as Arc<dyn StateStore>,
+            registry,
+            10,
+        )
+        .spawn();
+        let mut status = handle.status();
+
+        started.notified().await;
+        handle.pause();
+        release.notify_one();
+        status
+            .wait_for(|status| *status == KernelStatus::Paused { iteration: 2 })
+            .await
+            .expect("kernel should pause");
+        // iteration_started, action, tool_output, iteration_finished
+        assert_eq!(store.load().await.unwrap().len(), 4);
+
+        handle.cancel();
+        handle.join().await.expect("run should stop cleanly");
+        assert_eq!(
+            *status.borrow(),
+            KernelStatus::Finished {
+                reason: "interrupted".to_string()
+            }
+        );
+
+        let events = store.load().await.unwrap();
+        let types: Vec<&str> = events.iter().map(|event| event.r#type.as_str()).collect();
+        assert_eq!(
+            types,
+            [
+                "iteration_started",
+                "action",
+                "tool_output",
+                "iteration_finished",
+                "termination"
+            ]
+        );
+        assert_eq!(events[2].payload["output"], json!({ "written": true }));
+        assert_eq!(events[4].payload["reason"], "interrupted");
+        assert_eq!(events[4].payload["iteration"], 2);
+    }
+
+    struct MeteredModel;
+
+    #[async_trait]
+    impl Model for MeteredModel {
+        async fn next_action(&self, _history: &[Event]) -> Result<Action> {
+            Ok(Action::Message("thinking".to_string()))
+        }
+
+        fn usage(&self) -> Option<ModelUsage> {
+            Some(ModelUsage {
+                requests: 1,
+                input_tokens: 1_000_000,
+                cached_input_tokens: 500_000,
+                output_tokens: 100_000,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn budgets_terminate_with_distinct_reasons() {
+        let run = |kernel: Kernel, store: Arc<VecStore>| async move {
+            kernel.run().await.expect("run should stop cleanly");
+            store
+                .load()
+                .await
+                .unwrap()
+                .pop()
+                .expect("termination event")
+        };
+        let kernel = |store: &Arc<VecStore>| {
+            Kernel::new(
+                "goal".to_string(),
+                Arc::new(MeteredModel),
+                Arc::clone(store) as Arc<dyn StateStore>,
+                ToolRegistry::new(),
+                5,
+            )
+        };
+
+        let store = Arc::new(VecStore::default());
+        let pricing = ModelPricing {
+            input: 2.0,
+            cached_input: 1.0,
+            output: 10.0,
+        };
+        let termination = run(
+            kernel(&store).with_pricing(pricing).with_max_cost(2.0),
+            Arc::clone(&store),
+        )
+        .await;
+        assert_eq!(termination.payload["reason"], "cost_budget_exceeded");
+        assert_eq!(termination.payload["cost_usd"], 2.5);
+        assert_eq!(termination.payload["cache_savings_usd"], 0.5);
+
+        let store = Arc::new(VecStore::default());
+        let termination = run(
+            kernel(&store).with_max_duration(Duration::ZERO),
+            Arc::clone(&store),
+        )
+        .await;
+        assert_eq!(termination.payload["reason"], "time_budget_exceeded");
+        assert_eq!(termination.payload["iteration"], 1);
+    }
+
+    struct RepeatingModel;
+
+    #[async_trait]
+    impl Model for RepeatingModel {
+        async fn next_action(&self, history: &[Event]) -> Result<Action> {
+            Ok(Action::ToolCall(ToolCall {
+                id: format!("call-{}", history.len()),
+                name: "run_tests".to_string(),
+                arguments: json!({ "filter": "all" }),
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn repeated_calls_and_idle_messages_are_warned_about_then_stopped() {
+        let store = Arc::new(VecStore::default());
+        Kernel::new(
+            "goal".to_string(),
+            Arc::new(RepeatingModel),
+            Arc::clone(&store) as Arc<dyn StateStore>,
+            ToolRegistry::new(),
+            10,
+        )
+        .with_loop_detection(LoopDetection {
+            window: 5,
+            warn_after: 2,
+            terminate_after: 3,
+            stall_iterations: 0,
+        })
+        .run()
+        .await
+        .expect("run should stop cleanly");
+
+        let events = store.load().await.unwrap();
+        let notes: Vec<_> = events
+            .iter()
+            .filter(|event| event.payload["source"] == "loop_detection")
+            .collect();
+        assert_eq!(notes.len(), 1);
+        let termination = events.last().unwrap();
+        assert_eq!(termination.payload["reason"], "loop_detected");
+        assert_eq!(termination.payload["iteration"], 3);
+        assert_eq!(termination.payload["repeats"], 3);
+
+        let store = Arc::new(VecStore::default());
+        Kernel::new(
+            "goal".to_string(),
+            Arc::new(MeteredModel),
+            Arc::clone(&store) as Arc<dyn StateStore>,
+            ToolRegistry::new(),
+            10,
+        )
+        .with_loop_detection(LoopDetection {
+            stall_iterations: 2,
+            ..LoopDetection::default()
+        })
+        .run()
+        .await
+        .expect("run should stop cleanly");
+
+        let events = store.load().await.unwrap();
+        let nudges = events
+            .iter()
+            .filter(|event| event.payload["source"] == "stall_watchdog")
+            .count();
+        assert_eq!(nudges, 1);
+        let termination = events.last().unwrap();
+        assert_eq!(termination.payload["reason"], "stalled");
+        assert_eq!(termination.payload["iteration"], 4);
+    }
+
+    #[tokio::test]
+    async fn hung_tool_call_times_out_and_the_run_continues() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(SlowEditTool {
+            started: Arc::new(Notify::new()),
+            release: Arc::new(Notify::new()),
+        }));
+        let store = Arc::new(VecStore::default());
+        Kernel::new(
+            "goal".to_string(),
+            Arc::new(SlowEditModel),
+            Arc::clone(&store) as Arc<dyn StateStore>,
+            registry,
+            2,
+        )
+        .with_tool_timeout(None)
+        .with_tool_timeout_for("slow_edit", Some(Duration::from_millis(20)))
+        .run()
+        .await
+        .expect("run should finish");
+
+        let events = store.load().await.unwrap();
+        let outputs: Vec<&Value> = events
+            .iter()
+            .filter(|event| event.r#type == "tool_output")
+            .map(|event| &event.payload["output"]["error"])
+            .collect();
+        assert_eq!(outputs, [&json!("timed_out"), &json!("timed_out")]);
+        assert_eq!(events.last().unwrap().payload["reason"], "max_iterations");
+    }
+
+    struct ProposingModel;
+
+    #[async_trait]
+    impl Model for ProposingModel {
+        async fn next_action(&self, history: &[Event]) -> Result<Action> {
+            Ok(Action::ToolCall(ToolCall {
+                id: format!("call-{}", history.len()),
+                name: "propose_done".to_string(),
+                // The first proposal leaves out its evidence.
+                arguments: if history.iter().all(|event| event.r#type != "tool_output") {
+                    json!({ "summary": "fixed the bug", "outcome": "success" })
+                } else {
+                    json!({
+                        "summary": "fixed the bug",
+                        "outcome": "success",
+                        "evidence": ["cargo test passed"]
+                    })
+                },
+            }))
+        }
+    }
+
+    /// Rejects the first proposal, accepts the rest.
+    #[derive(Default)]
+    struct SecondTimeReviewer {
+        reviews: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl CompletionReviewer for SecondTimeReviewer {
+        async fn review_completion(&self, _proposal: &ToolCall) -> Result<CompletionDecision> {
+            let mut reviews = self.reviews.lock().unwrap();
+            *reviews += 1;
+            Ok(match *reviews {
+                1 => CompletionDecision::Rejected {
+                    feedback: "add a regression test".to_string(),
+                },
+                _ => CompletionDecision::Accepted,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_and_rejected_proposals_continue_and_accepted_one_terminates() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(ProposeDoneTool));
+        let store = Arc::new(VecStore::default());
+        Kernel::new(
+            "goal".to_string(),
+            Arc::new(ProposingModel),
+            Arc::clone(&store) as Arc<dyn StateStore>,
+            registry,
+            5,
+        )
+        .with_completion_reviewer(Arc::new(SecondTimeReviewer::default()))
+        .run()
+        .await
+        .expect("run should finish");
+
+        let events = store.load().await.unwrap();
+        let outputs: Vec<&Value> = events
+            .iter()
+            .filter(|event| event.r#type == "tool_output")
+            .map(|event| &event.payload["output"])
+            .collect();
+        assert_eq!(outputs.len(), 3);
+        assert_eq!(outputs[0]["error"], "invalid_completion");
+        assert_eq!(outputs[1]["status"], "rejected");
+        assert_eq!(outputs[1]["feedback"], "add a regression test");
+        assert_eq!(outputs[2]["status"], "accepted");
+        let termination = events.last().unwrap();
+        assert_eq!(termination.payload["reason"], "done");
+        assert_eq!(termination.payload["outcome"], "success");
+        assert_eq!(termination.payload["iteration"], 3);
+    }
+
+    struct UnavailableModel;
+
+    #[async_trait]
+    impl Model for UnavailableModel {
+        async fn next_action(&self, _history: &[Event]) -> Result<Action> {
+            Err(anyhow::anyhow!("OpenAI API error: status=404 model=gone"))
+        }
+    }
+
+    #[tokio::test]
+    async fn failing_model_switches_to_the_next_in_the_chain() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(ProposeDoneTool));
+        let chain = Arc::new(
+            FallbackModel::new(vec![
+                (
+                    "gone".to_string(),
+                    Arc::new(UnavailableModel) as Arc<dyn Model>,
+                ),
+                ("backup".to_string(), Arc::new(ProposingModel)),
+            ])
+            .with_retries(0),
+        );
+        let store = Arc::new(VecStore::default());
+        Kernel::new(
+            "goal".to_string(),
+            Arc::clone(&chain) as Arc<dyn Model>,
+            Arc::clone(&store) as Arc<dyn StateStore>,
+            registry,
+            5,
+        )
+        .with_model_fallback(Arc::clone(&chain))
+        .run()
+        .await
+        .expect("run should finish on the backup model");
+
+        let events = store.load().await.unwrap();
+        let switches: Vec<&Event> = events
+            .iter()
+            .filter(|event| event.r#type == "model_switched")
+            .collect();
+        assert_eq!(switches.len(), 1);
+        assert_eq!(switches[0].payload["from"], "gone");
+        assert_eq!(switches[0].payload["to"], "backup");
+        assert_eq!(switches[0].payload["iteration"], 1);
+        assert_eq!(events.last().unwrap().payload["reason"], "done");
+
+        // The chain is exhausted: the next failure ends the run.
+        assert!(chain.switch().is_none());
+    }
+
+    struct NitpickingCritic;
+
+    #[async_trait]
+    impl CompletionCritic for NitpickingCritic {
+        async fn critique(
+            &self,
+            _proposal: &ToolCall,
+            _history: &[Event],
+        ) -> Result<Option<String>> {
+            Ok(Some("1. The regression test is missing.".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn critique_is_sent_back_until_review_rounds_run_out() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(ProposeDoneTool));
+        let store = Arc::new(VecStore::default());
+        Kernel::new(
+            "goal".to_string(),
+            Arc::new(ProposingModel),
+            Arc::clone(&store) as Arc<dyn StateStore>,
+            registry,
+            5,
+        )
+        .with_critic(Arc::new(NitpickingCritic), 1)
+        .run()
+        .await
+        .expect("run should finish");
+
+        let events = store.load().await.unwrap();
+        let types: Vec<&str> = events.iter().map(|event| event.r#type.as_str()).collect();
+        assert_eq!(
+            types,
+            [
+                "iteration_started",
+                "action",
+                "tool_output",
+                "iteration_finished",
+                "iteration_started",
+                "action",
+                "completion_review",
+                "tool_output",
+                "user_message",
+                "iteration_finished",
+                "iteration_started",
+                "action",
+                "tool_output",
+                "iteration_finished",
+                "termination",
+            ]
+        );
+        assert_eq!(events[7].payload["output"]["status"], "rejected");
+        assert_eq!(events[8].payload["source"], "reviewer");
+        assert!(events[8].payload["message"]
+            .as_str()
+            .unwrap()
+            .ends_with("1. The regression test is missing."));
+        assert_eq!(events[13].payload["iteration"], 3);
+        assert_eq!(events[13].payload["tool"], "propose_done");
+        assert_eq!(events[14].payload["reason"], "done");
+    }
+
+    struct PlanningModel;
+
+    #[async_trait]
+    impl Model for PlanningModel {
+        async fn next_action(&self, history: &[Event]) -> Result<Action> {
+            let outputs = history
+                .iter()
+                .filter(|event| event.r#type == "tool_output")
+                .count();
+            let (name, arguments) = match outputs {
+                0 => ("write_file", json!({ "path": "a.txt", "content": "a" })),
+                1 => ("submit_plan", json!({ "steps": ["read", "fix"] })),
+                2 | 3 => ("complete_step", json!({ "summary": "done" })),
+                _ => (
+                    "propose_done",
+                    json!({
+                        "summary": "fixed",
+                        "outcome": "success",
+                        "evidence": ["both steps complete"]
+                    }),
+                ),
+            };
+            Ok(Action::ToolCall(ToolCall {
+                id: format!("call-{}", outputs),
+                name: name.to_string(),
+                arguments,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn plan_mode_refuses_changes_until_a_plan_is_approved() {
+        let runs = Arc::new(Mutex::new(0));
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(CountingWriteTool {
+            runs: Arc::clone(&runs),
+        }));
+        registry.register(Arc::new(ProposeDoneTool));
+        registry.register(Arc::new(SubmitPlanTool));
+        registry.register(Arc::new(CompleteStepTool));
+        let store = Arc::new(VecStore::default());
+        Kernel::new(
+            "goal".to_string(),
+            Arc::new(PlanningModel),
+            Arc::clone(&store) as Arc<dyn StateStore>,
+            registry,
+            10,
+        )
+        .with_plan_mode()
+        .run()
+        .await
+        .expect("run should finish");
+
+        let events = store.load().await.unwrap();
+        assert_eq!(events[0].r#type, "user_message");
+        let outputs: Vec<&Value> = events
+            .iter()
+            .filter(|event| event.r#type == "tool_output")
+            .map(|event| &event.payload["output"])
+            .collect();
+        assert_eq!(outputs[0]["error"], "plan_required");
+        assert_eq!(*runs.lock().unwrap(), 0);
+        assert_eq!(outputs[1]["status"], "approved");
+        assert_eq!(outputs[2]["current_step"]["step"], "fix");
+        assert_eq!(outputs[3]["remaining"], 0);
+        let progress: Vec<(&str, &Value)> = events
+            .iter()
+            .filter(|event| event.r#type.starts_with("plan_step_"))
+            .map(|event| (event.r#type.as_str(), &event.payload["index"]))
+            .collect();
+        assert_eq!(
+            progress,
+            [
+                ("plan_step_started", &json!(1)),
+                ("plan_step_completed", &json!(1)),
+                ("plan_step_started", &json!(2)),
+                ("plan_step_completed", &json!(2)),
+            ]
+        );
+        assert_eq!(events.last().unwrap().payload["reason"], "done");
+    }
+
+    #[tokio::test]
+    async fn tool_running_past_the_iteration_limit_is_preempted() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(SlowEditTool {
+            started: Arc::new(Notify::new()),
+            release: Arc::new(Notify::new()),
+        }));
+        let store = Arc::new(VecStore::default());
+        Kernel::new(
+            "goal".to_string(),
+            Arc::new(SlowEditModel),
+            Arc::clone(&store) as Arc<dyn StateStore>,
+            registry,
+            1,
+        )
+        .with_max_iteration_duration(Duration::from_millis(20))
+        .run()
+        .await
+        .expect("run should finish");
+
+        let events = store.load().await.unwrap();
+        assert_eq!(events[2].payload["output"]["error"], "preempted");
+        assert_eq!(events[4].payload["reason"], "max_iterations");
+    }
+
+    struct WritingModel;
+
+    #[async_trait]
+    impl Model for WritingModel {
+        async fn next_action(&self, history: &[Event]) -> Result<Action> {
+            Ok(Action::ToolCall(ToolCall {
+                id: format!("call-{}", history.len()),
+                name: "write_file".to_string(),
+                arguments: json!({ "path": "notes.txt", "content": "hi" }),
+            }))
+        }
+    }
+
+    /// Counts the calls that actually ran.
+    #[derive(Default)]
+    struct CountingWriteTool {
+        runs: Arc<Mutex<usize>>,
+    }
+
+    #[async_trait]
+    impl Tool for CountingWriteTool {
+        fn name(&self) -> &str {
+            "write_file"
+        }
+
+        fn description(&self) -> &str {
+            "test tool"
+        }
+
+        fn parameters(&self) -> Value {
+            json!({ "type": "object" })
+        }
+
+        async fn execute(&self, _input: Value) -> Result<Value> {
+            *self.runs.lock().unwrap() += 1;
+            Ok(json!({ "written": true }))
+        }
+    }
+
+    /// Denies every call it is asked about.
+    #[derive(Default)]
+    struct DenyingGate {
+        prompts: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl ApprovalGate for DenyingGate {
+        async fn review(&self, _tool_call: &ToolCall) -> Result<ApprovalDecision> {
+            *self.prompts.lock().unwrap() += 1;
+            Ok(ApprovalDecision::Denied)
+        }
+    }
+
+    fn policy(rules: Vec<(&str, PolicyAction)>) -> Arc<Policy> {
+        let rules = rules
+            .into_iter()
+            .map(|(tool, action)| PolicyRuleConfig {
+                tool: tool.to_string(),
+                argument: None,
+                pattern: None,
+                action,
+            })
+            .collect();
+        Arc::new(
+            Policy::from_config(&PolicyConfig {
+                default: None,
+                rules,
+            })
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn ask_rules_prompt_only_for_the_calls_they_match() {
+        let run = |kernel: fn(Kernel) -> Kernel| async move {
+            let tool = CountingWriteTool::default();
+            let runs = Arc::clone(&tool.runs);
+            let gate = Arc::new(DenyingGate::default());
+            let mut registry = ToolRegistry::new();
+            registry.register(Arc::new(tool));
+            let store = Arc::new(VecStore::default());
+            kernel(Kernel::new(
+                "goal".to_string(),
+                Arc::new(WritingModel),
+                Arc::clone(&store) as Arc<dyn StateStore>,
+                registry,
+                1,
+            ))
+            .with_approval_gate(Arc::clone(&gate) as Arc<dyn ApprovalGate>)
+            .run()
+            .await
+            .expect("run should finish");
+            let prompts = *gate.prompts.lock().unwrap();
+            let runs = *runs.lock().unwrap();
+            (prompts, runs)
+        };
+
+        // An `ask` rule for exec does not gate write_file without --confirm.
+        let unrelated =
+            |kernel: Kernel| kernel.with_policy(policy(vec![("exec", PolicyAction::Ask)]));
+        assert_eq!(run(unrelated).await, (0, 1));
+        let asked =
+            |kernel: Kernel| kernel.with_policy(policy(vec![("write_file", PolicyAction::Ask)]));
+        assert_eq!(run(asked).await, (1, 0));
+        // With --confirm, an explicit allow rule skips the prompt.
+        let allowed = |kernel: Kernel| {
+            kernel
+                .with_confirm()
+                .with_policy(policy(vec![("write_file", PolicyAction::Allow)]))
+        };
+        assert_eq!(run(allowed).await, (0, 1));
+    }
+
+    #[tokio::test]
+    async fn denied_call_is_logged_and_not_executed() {
+        let tool = CountingWriteTool::default();
+        let runs = Arc::clone(&tool.runs);
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(tool));
+        let store = Arc::new(VecStore::default());
+        Kernel::new(
+            "goal".to_string(),
+            Arc::new(WritingModel),
+            Arc::clone(&store) as Arc<dyn StateStore>,
+            registry,
+            1,
+        )
+        .with_confirm()
+        .with_approval_gate(Arc::new(DenyingGate::default()))
+        .run()
+        .await
+        .expect("run should finish");
+
+        assert_eq!(*runs.lock().unwrap(), 0);
+        let events = store.load().await.unwrap();
+        let action = events
+            .iter()
+            .find(|event| event.r#type == "action")
+            .unwrap();
+        let Ok(Action::ToolCall(call)) = serde_json::from_value(action.payload.clone()) else {
+            panic!("the denied call should be logged as an action");
+        };
+        assert_eq!(call.name, "write_file");
+        let output = events
+            .iter()
+            .find(|event| event.r#type == "tool_output")
+            .unwrap();
+        assert_eq!(output.payload["tool_call_id"], call.id.as_str());
+        assert_eq!(output.payload["output"]["error"], "denied_by_user");
+    }
+}
@@ -0,0 +1,257 @@
+use crate::tools::search::walk_files;
+use crate::tools::workspace::Workspace;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Lines per indexed chunk; results point at the best line inside a chunk.
+const CHUNK_LINES: usize = 40;
+const MAX_FILES: usize = 50_000;
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+/// `<root>/.rx/index/keyword.json`.
+pub fn keyword_index_path(root: &Path) -> PathBuf {
+    root.join(".rx").join("index").join("keyword.json")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Chunk {
+    path: String,
+    start_line: usize,
+    end_line: usize,
+    length: usize,
+}
+
+/// BM25 index over fixed-size line chunks of the workspace's text files.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeywordIndex {
+    pub built_at: DateTime<Utc>,
+    chunks: Vec<Chunk>,
+    /// Term to `(chunk, term frequency)` postings.
+    postings: BTreeMap<String, Vec<(u32, u32)>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeywordHit {
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub score: f64,
+}
+
+impl KeywordIndex {
+    /// Indexes every text file the workspace search rules do not hide, except
+    /// rx's own `.rx/` directory.
+    pub fn build(workspace: &Workspace) -> Self {
+        let root = workspace.root();
+        let files = walk_files(workspace, root, false, MAX_FILES, |relative| {
+            !relative.starts_with(".rx/")
+        })
+        .files;
+        let mut index = Self {
+            built_at: Utc::now(),
+            chunks: Vec::new(),
+            postings: BTreeMap::new(),
+        };
+        for relative in files {
+            if let Some(text) = read_text(&root.join(&relative)) {
+                index.add_file(&relative, &text);
+            }
+        }
+        index
+    }
+
+    fn add_file(&mut self, relative: &str, text: &str) {
+        let lines: Vec<&str> = text.lines().collect();
+        for (chunk_index, chunk_lines) in lines.chunks(CHUNK_LINES).enumerate() {
+            let mut frequencies: HashMap<String, u32> = HashMap::new();
+            let mut length = 0;
+            for term in chunk_lines.iter().flat_map(|line| tokenize(line)) {
+                *frequencies.entry(term).or_default() += 1;
+                length += 1;
+            }
+            if length == 0 {
+                continue;
+            }
+            let id = self.chunks.len() as u32;
+            let start_line = chunk_index * CHUNK_LINES + 1;
+            self.chunks.push(Chunk {
+                path: relative.to_string(),
+                start_line,
+                end_line: start_line + chunk_lines.len() - 1,
+                length,
+            });
+            for (term, frequency) in frequencies {
+                self.postings.entry(term).or_default().push((id, frequency));
+            }
+        }
+    }
+
+    pub fn file_count(&self) -> usize {
+        let mut paths: Vec<&str> = self
+            .chunks
+            .iter()
+            .map(|chunk| chunk.path.as_str())
+            .collect();
+        paths.dedup();
+        paths.len()
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Chunks ranked by BM25 score for `query`, best first; ties keep index
+    /// order so results are deterministic.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<KeywordHit> {
+        let total = self.chunks.len() as f64;
+        if total == 0.0 {
+            return Vec::new();
+        }
+        let average_length =
+            self.chunks.iter().map(|chunk| chunk.length).sum::<usize>() as f64 / total;
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+
+        let mut scores: HashMap<u32, f64> = HashMap::new();
+        for term in &terms {
+            let Some(postings) = self.postings.get(term) else {
+                continue;
+            };
+            let frequency = postings.len() as f64;
+            let idf = ((total - frequency + 0.5) / (frequency + 0.5) + 1.0).ln();
+            for (chunk, term_frequency) in postings {
+                let tf = f64::from(*term_frequency);
+                let length = self.chunks[*chunk as usize].length as f64;
+                let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * length / average_length);
+                *scores.entry(*chunk).or_default() += idf * tf * (BM25_K1 + 1.0) / (tf + norm);
+            }
+        }
+
+        let mut ranked: Vec<(u32, f64)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(id, score)| {
+                let chunk = &self.chunks[id as usize];
+                KeywordHit {
+                    path: chunk.path.clone(),
+                    start_line: chunk.start_line,
+                    end_line: chunk.end_line,
+                    score,
+                }
+            })
+            .collect()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        let json = serde_json::to_string(self)?;
+        std::fs::write(path, json).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("invalid index {}", path.display()))
+    }
+}
+
+/// Lowercased alphanumeric words, with `snake_case` and `camelCase`
+/// identifiers also split into their parts. Single characters are dropped.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for word in text.split(|ch: char| !ch.is_alphanumeric() && ch != '_') {
+        let parts = split_identifier(word);
+        if parts.len() > 1 {
+            terms.push(word.to_lowercase());
+        }
+        terms.extend(parts);
+    }
+    terms.retain(|term| term.chars().count() > 1);
+    terms
+}
+
+fn split_identifier(word: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    for piece in word.split('_').filter(|piece| !piece.is_empty()) {
+        let mut current = String::new();
+        let mut previous_lower = false;
+        for ch in piece.chars() {
+            if ch.is_uppercase() && previous_lower && !current.is_empty() {
+                parts.push(current.to_lowercase());
+                current.clear();
+            }
+            previous_lower = ch.is_lowercase() || ch.is_ascii_digit();
+            current.push(ch);
+        }
+        if !current.is_empty() {
+            parts.push(current.to_lowercase());
+        }
+    }
+    parts
+}
+
+fn read_text(path: &Path) -> Option<String> {
+    if std::fs::metadata(path).ok()?.len() > MAX_FILE_BYTES {
+        return None;
+    }
+    let bytes = std::fs::read(path).ok()?;
+    if bytes.iter().take(8192).any(|byte| *byte == 0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, KeywordIndex};
+    use chrono::Utc;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn tokenizer_splits_identifiers_and_ranking_prefers_dense_matches() {
+        assert_eq!(
+            tokenize("fn parseHttpHeader(raw_value)"),
+            [
+                "fn",
+                "parsehttpheader",
+                "parse",
+                "http",
+                "header",
+                "raw_value",
+                "raw",
+                "value"
+            ]
+        );
+
+        let mut index = KeywordIndex {
+            built_at: Utc::now(),
+            chunks: Vec::new(),
+            postings: BTreeMap::new(),
+        };
+        index.add_file(
+            "src/retry.rs",
+            "fn retry_request() {\n    // retry with backoff\n}\n",
+        );
+        index.add_file("src/client.rs", "fn send_request() {}\n");
+        index.add_file("README.md", "A client library.\n");
+
+        let hits = index.search("retry request", 10);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].path, "src/retry.rs");
+        assert_eq!((hits[0].start_line, hits[0].end_line), (1, 3));
+        assert_eq!(hits[1].path, "src/client.rs");
+        assert!(index.search("nothing matches", 10).is_empty());
+        assert_eq!(index.file_count(), 3);
+    }
+}
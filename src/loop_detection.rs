@@ -0,0 +1,177 @@
+use crate::event::Event;
+use crate::model::{Action, ToolCall};
+use crate::tools::fs::compute_hash;
+
+/// Note added once a call has been repeated `warn_after` times.
+pub const LOOP_NOTE: &str = "You have made the same tool call with the same arguments several times in a row and it is not getting you closer to the goal. Stop repeating it: read the last outputs carefully, then try a different approach, or call propose_done with outcome blocked if you cannot continue.";
+
+/// Note added once the model has only sent messages for
+/// `stall_iterations` iterations in a row.
+pub const STALL_NOTE: &str = "You appear stalled: your last replies were messages without any tool call, so nothing has changed. Take an action with a tool, or call propose_done if the goal is finished or you cannot continue.";
+
+/// How the kernel reacts to a model repeating the identical tool call, or
+/// replying without tool calls iteration after iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopDetection {
+    /// Recent tool calls, the current one included, compared with each other.
+    pub window: usize,
+    /// Repeats within the window that add a note telling the model to change
+    /// strategy.
+    pub warn_after: usize,
+    /// Repeats within the window that end the goal with `loop_detected`.
+    pub terminate_after: usize,
+    /// Message-only iterations in a row that add a nudge; twice as many end
+    /// the goal with `stalled`.
+    pub stall_iterations: usize,
+}
+
+impl Default for LoopDetection {
+    fn default() -> Self {
+        Self {
+            window: 10,
+            warn_after: 3,
+            terminate_after: 5,
+            stall_iterations: 3,
+        }
+    }
+}
+
+/// What the kernel does about the current action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repetition {
+    /// Not repeated often enough to matter.
+    Fine,
+    /// Repeated this many times: add [`LOOP_NOTE`] or [`STALL_NOTE`].
+    Warn(usize),
+    /// Repeated this many times: stop the goal.
+    Terminate(usize),
+}
+
+impl LoopDetection {
+    /// Counts `tool_call` among the last `window` tool calls of `history`
+    /// (which does not hold it yet) and itself.
+    pub fn check(&self, history: &[Event], tool_call: &ToolCall) -> Repetition {
+        let current = fingerprint(tool_call);
+        let previous: Vec<String> = history
+            .iter()
+            .filter(|event| event.r#type == "action")
+            .filter_map(
+                |event| match serde_json::from_value(event.payload.clone()) {
+                    Ok(Action::ToolCall(call)) => Some(fingerprint(&call)),
+                    _ => None,
+                },
+            )
+            .collect();
+        let recent = &previous[previous.len().saturating_sub(self.window.saturating_sub(1))..];
+        let repeats = 1 + recent.iter().filter(|hash| **hash == current).count();
+        if self.terminate_after > 0 && repeats >= self.terminate_after {
+            Repetition::Terminate(repeats)
+        } else if self.warn_after > 0 && repeats == self.warn_after {
+            Repetition::Warn(repeats)
+        } else {
+            Repetition::Fine
+        }
+    }
+
+    /// Counts the current message-only action and those right before it in
+    /// `history`, back to the last tool call or message from the user.
+    pub fn check_stall(&self, history: &[Event]) -> Repetition {
+        if self.stall_iterations == 0 {
+            return Repetition::Fine;
+        }
+        let messages = 1 + history
+            .iter()
+            .rev()
+            .filter(|event| matches!(event.r#type.as_str(), "action" | "user_message"))
+            .take_while(|event| {
+                event.r#type == "action" && event.payload.get("Message").is_some()
+                    || event.payload["source"] == "stall_watchdog"
+            })
+            .filter(|event| event.r#type == "action")
+            .count();
+        if messages >= 2 * self.stall_iterations {
+            Repetition::Terminate(messages)
+        } else if messages == self.stall_iterations {
+            Repetition::Warn(messages)
+        } else {
+            Repetition::Fine
+        }
+    }
+}
+
+/// Hash of the tool name and arguments; the call id is left out.
+fn fingerprint(tool_call: &ToolCall) -> String {
+    compute_hash(format!("{}\n{}", tool_call.name, tool_call.arguments).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LoopDetection, Repetition};
+    use crate::event::Event;
+    use crate::model::{Action, ToolCall};
+    use serde_json::json;
+
+    fn call(id: usize, command: &str) -> ToolCall {
+        ToolCall {
+            id: format!("call-{}", id),
+            name: "exec".to_string(),
+            arguments: json!({ "command": command }),
+        }
+    }
+
+    #[test]
+    fn identical_calls_warn_then_terminate() {
+        let detection = LoopDetection {
+            window: 4,
+            warn_after: 2,
+            terminate_after: 3,
+            stall_iterations: 2,
+        };
+        let mut history = Vec::new();
+        let record = |history: &mut Vec<Event>, call: ToolCall| {
+            history.push(Event::new("action", json!(Action::ToolCall(call))));
+        };
+        assert_eq!(
+            detection.check(&history, &call(1, "make")),
+            Repetition::Fine
+        );
+        record(&mut history, call(1, "make"));
+        assert_eq!(
+            detection.check(&history, &call(2, "make")),
+            Repetition::Warn(2)
+        );
+        record(&mut history, call(2, "make"));
+        assert_eq!(detection.check(&history, &call(3, "ls")), Repetition::Fine);
+        record(&mut history, call(3, "ls"));
+        assert_eq!(
+            detection.check(&history, &call(4, "make")),
+            Repetition::Terminate(3)
+        );
+        // Calls that dropped out of the window no longer count.
+        record(&mut history, call(4, "ls"));
+        record(&mut history, call(5, "ls"));
+        assert_eq!(
+            detection.check(&history, &call(6, "make")),
+            Repetition::Fine
+        );
+
+        let message = |history: &mut Vec<Event>| {
+            history.push(Event::new("action", json!(Action::Message("hmm".into()))));
+        };
+        assert_eq!(detection.check_stall(&history), Repetition::Fine);
+        message(&mut history);
+        assert_eq!(detection.check_stall(&history), Repetition::Warn(2));
+        message(&mut history);
+        history.push(Event::new(
+            "user_message",
+            json!({ "message": "nudge", "source": "stall_watchdog" }),
+        ));
+        message(&mut history);
+        assert_eq!(detection.check_stall(&history), Repetition::Terminate(4));
+        history.push(Event::new(
+            "user_message",
+            json!({ "message": "try again" }),
+        ));
+        assert_eq!(detection.check_stall(&history), Repetition::Fine);
+    }
+}
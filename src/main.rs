@@ -1,151 +1,586 @@
+mod approval;
+mod artifacts;
+mod blockers;
+mod chaos;
+mod cli;
+mod compare;
 mod config;
+mod credentials;
+mod critic;
+mod eval;
 mod event;
+mod goal_id;
+mod isolation;
 mod kernel;
+mod keyword_index;
+mod loop_detection;
+mod mcp_server;
 mod model;
+mod notify;
+mod output;
+mod policy;
+mod prompt_template;
+mod pull_request;
+mod queue;
+mod redact;
+mod replay;
 mod runtime_hooks;
+mod scratch;
+mod serve;
+mod setup;
+mod similar_goals;
+mod small_model;
+mod snapshot;
 mod state;
+#[cfg(feature = "postgres")]
+mod state_postgres;
+mod stats;
+mod status_line;
+mod telemetry;
+#[cfg(test)]
+mod testing;
+mod timeline;
 mod tool;
+mod tool_health;
 mod tools;
+mod tui;
 mod utils;
+mod verify;
+mod websocket;
+mod workspace_diff;
+mod write_quota;
 
-use crate::config::{load_config, resolve_enabled_tools};
+use crate::approval::{ApprovalGate, CompletionReviewer, PlanReviewer, StdinApprovalGate};
+use crate::artifacts::{referenced_artifacts, ArtifactStore};
+use crate::blockers::BlockedReport;
+use crate::chaos::Chaos;
+use crate::cli::{
+    ArtifactsCommand, Cli, Command, IndexCommand, IndexKind, OutputFormat, QueueCommand, RunArgs,
+    RunOptions,
+};
+use crate::compare::render_comparison;
+use crate::config::{
+    agent_profiles, config_layers, find_agent, load_layered_config, load_workspace_config,
+    resolve_enabled_tools, McpServerConfig, ModelConfig, RedactionConfig, RxConfig,
+    ServeWorkspaceConfig, AVAILABLE_TOOLS,
+};
+use crate::critic::SmallModelCritic;
+use crate::eval::{render_results, run_task, EvalOptions, EvalSuite};
 use crate::event::Event;
-use crate::kernel::Kernel;
-use crate::model::{MockModel, Model, OpenAIModel};
+use crate::goal_id::GoalIdScheme;
+use crate::isolation::{default_worktree_root, IsolatedChanges, IsolatedWorktree};
+use crate::kernel::{Kernel, KernelHandle, KernelStatus};
+use crate::keyword_index::{keyword_index_path, KeywordIndex};
+use crate::loop_detection::LoopDetection;
+use crate::model::{
+    FallbackModel, MockModel, Model, ModelPricing, OpenAIModel, PlannerModel, RequestParams,
+    DEFAULT_MODEL_RETRIES,
+};
+use crate::notify::{LifecycleWebhook, RunSummary, ALL_LIFECYCLE_EVENTS};
+use crate::policy::Policy;
+use crate::prompt_template::{render_prompt, PromptContext};
+use crate::pull_request::GoalBranch;
+use crate::queue::{render_jobs, GoalRequest, JobStatus, QueueClient, DEFAULT_SERVER};
+use crate::redact::Redactor;
+use crate::replay::{RecordingModel, ReplayModel};
 use crate::runtime_hooks::{
-    AutoCommitHook, DebugJsonlHook, EventHook, HeuristicCommitMessageGenerator, HookedStateStore,
-    ToolVerboseHook,
+    commit_message_generator, AutoCommitHook, DebugJsonlHook, EventHook, HookedStateStore,
+    JsonOutputHook, ToolVerboseHook,
+};
+use crate::scratch::{default_scratch_root, purge_stale, ScratchDir, DEFAULT_MAX_AGE_HOURS};
+use crate::serve::{
+    Daemon, GoalLauncher, ServedWorkspace, DEFAULT_MAX_CONCURRENT_GOALS, DEFAULT_WORKSPACE,
+    TOKEN_FILE,
 };
-use crate::state::{InMemoryStateStore, StateStore};
-use crate::tool::ToolRegistry;
-use crate::tools::done::DoneTool;
+use crate::similar_goals::{
+    find_similar_goals, goal_age, DEFAULT_EMBEDDING_MODEL, DEFAULT_SIMILARITY_THRESHOLD,
+};
+use crate::small_model::{ModelRouter, SmallModelLimits, SmallTask};
+use crate::snapshot::{restore_snapshots, snapshot_points, Snapshots};
+use crate::state::{
+    branch_goal, fork_goal, goal_log_path, list_stored_goals, load_goal, open_state_store,
+    parse_checkpoint, parse_goal_export, GoalSummary, StateBackend, StateSettings, StateStore,
+    DEFAULT_DATA_DIR,
+};
+use crate::stats::{render_stats, Stats};
+use crate::telemetry::{with_early_logging, Logging};
+use crate::timeline::render_timeline;
+use crate::tool::{Tool, ToolRegistry};
+use crate::tool_health::{recent_tool_health, unhealthy_tools, DEFAULT_FAILURE_THRESHOLD};
+use crate::tools::code_search::CodeSearchTool;
+use crate::tools::done::ProposeDoneTool;
+use crate::tools::edit_lines::EditLinesTool;
+use crate::tools::env::EnvPolicy;
 use crate::tools::exec::ExecTool;
 use crate::tools::fs::{
-    AppendFileTool, ApplyPatchTool, ApplyUnifiedPatchTool, CreateFileTool, ListDirTool, ReadFileTool,
-    ReplaceInFileTool, WriteFileTool,
+    AppendFileTool, ApplyPatchTool, ApplyUnifiedPatchTool, CreateFileTool, ListDirTool,
+    ReadFileTool, ReplaceInFileTool, WriteFileTool,
+};
+use crate::tools::git::{
+    is_git_repo, GitBranchTool, GitCommitTool, GitDiffTool, GitLogTool, GitStatusTool, GIT_TOOLS,
 };
+use crate::tools::grep::GrepFilesTool;
+use crate::tools::http::HttpRequestTool;
+use crate::tools::keyword_search::KeywordSearchTool;
+use crate::tools::mcp::{McpClient, McpTool};
+use crate::tools::memory::{memory_context, RememberTool};
+use crate::tools::multi_edit::MultiEditTool;
+use crate::tools::plan::{CompleteStepTool, SubmitPlanTool};
+use crate::tools::process::{
+    ExecBackgroundTool, ProcessKillTool, ProcessLogsTool, ProcessStatusTool, ProcessTable,
+};
+use crate::tools::pty::{
+    CloseSessionTool, OpenSessionTool, PtySessions, ReadOutputTool, SendInputTool,
+};
+use crate::tools::search::{FindFilesTool, SearchIgnore};
+use crate::tools::tool_output::{ReadToolOutputTool, DEFAULT_MAX_OUTPUT_BYTES};
+use crate::tools::tree::DirectoryTreeTool;
+use crate::tools::undo::UndoLastChangeTool;
+use crate::tools::working_dir::{restore_working_dir, GetWorkingDirTool, SetWorkingDirTool};
+use crate::tools::workspace::Workspace;
+use crate::tools::workspace_info::{detect_workspace, workspace_context, WorkspaceInfoTool};
+use crate::tui::{LogLines, Tui};
 use crate::utils::sanitize_goal_slug;
-use anyhow::{Context, Result};
-use chrono::Utc;
+use crate::verify::Verifier;
+use crate::websocket::{EventChannelHook, EVENT_BUFFER};
+use crate::workspace_diff::WorkspaceDiff;
+use crate::write_quota::WriteQuota;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use clap::Parser;
 use serde_json::json;
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::fs;
+use tracing::{info, warn};
 
-struct CliArgs {
-    goal: String,
-    max_iterations: usize,
-    model_name: Option<String>,
-    auto_commit: bool,
-    tool_verbose: bool,
-    debug_log_path: Option<PathBuf>,
-}
-
-fn parse_cli_args() -> CliArgs {
-    let mut args = std::env::args().skip(1);
-    let mut max_iterations = 50;
-    let mut model_name = None;
-    let mut auto_commit = false;
-    let mut tool_verbose = false;
-    let mut debug_log_path = None;
-    let mut goal_parts = Vec::new();
-    let mut stop_parsing = false;
-
-    while let Some(arg) = args.next() {
-        if stop_parsing {
-            goal_parts.push(arg);
-            continue;
-        }
+const DEFAULT_SMALL_MODEL: &str = "gpt-5-mini";
+/// Used when neither a prompt file is configured nor `LOOP_PROMPT.md` is
+/// present in the launch directory.
+const DEFAULT_SYSTEM_PROMPT: &str = include_str!("../LOOP_PROMPT.md");
 
-        match arg.as_str() {
-            "--max-iterations" => {
-                if let Some(value) = args.next() {
-                    if let Ok(parsed) = value.parse::<usize>() {
-                        max_iterations = parsed;
-                    } else {
-                        eprintln!("Warning: ignoring invalid max iterations '{}'.", value);
-                    }
-                } else {
-                    eprintln!(
-                        "Warning: --max-iterations requires a value. Using default {}.",
-                        max_iterations
-                    );
-                }
-            }
-            "--model" => {
-                if let Some(value) = args.next() {
-                    if value.trim().is_empty() {
-                        eprintln!("Warning: ignoring empty --model value.");
-                    } else {
-                        model_name = Some(value);
-                    }
-                } else {
-                    eprintln!("Warning: --model requires a value.");
-                }
-            }
-            "--auto-commit" => auto_commit = true,
-            "--tool-verbose" => tool_verbose = true,
-            "--debug-log" => {
-                if let Some(value) = args.next() {
-                    debug_log_path = Some(PathBuf::from(value));
-                } else {
-                    eprintln!("Warning: --debug-log requires a file path.");
-                }
-            }
-            "--help" => {
-                eprintln!(
-                    "Usage: rx [--max-iterations N] [--model NAME] [--auto-commit] [--tool-verbose] [--debug-log PATH] <goal>"
-                );
-                eprintln!(
-                    "Options:\n  --max-iterations NUM     Set the maximum number of iterations (default: 50)\n  --model NAME             Specify the model name to use\n  --auto-commit            Automatically commit changes\n  --tool-verbose           Enable verbose logging for tools\n  --debug-log PATH         Log debug information to a file\n  --help                   Show this help message\n  --                      Treat all following arguments as part of the goal"
-                );
-                std::process::exit(0);
-            }
-            "--" => {
-                stop_parsing = true;
-            }
-            other => goal_parts.push(other.to_string()),
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let run_options = cli.run_options();
+    // MCP and `--output json` keep stdout for machine-read output.
+    let stderr_only = matches!(cli.command, Some(Command::McpServe))
+        || run_options.is_some_and(|options| options.output == OutputFormat::Json);
+    // `--tui` shows log lines in a pane while its screen is up.
+    let tui_logs = run_options
+        .is_some_and(|options| options.tui)
+        .then(LogLines::default);
+    let mut logging = Logging::from_flags(cli.quiet, cli.verbose, stderr_only);
+    if let Some(lines) = &tui_logs {
+        logging = logging.with_capture(lines.clone());
+    }
+    let config = run_options.and_then(|_| with_early_logging(&logging, load_layered_config));
+    // Flushes exported spans when rx exits.
+    let _telemetry = telemetry::init(
+        config.as_ref().and_then(|cfg| cfg.telemetry.as_ref()),
+        logging,
+    )
+    .context("failed to set up [telemetry]")?;
+    match cli.command {
+        None => start_goal(cli.run, config, tui_logs).await,
+        Some(Command::Run(run)) => start_goal(run, config, tui_logs).await,
+        Some(Command::Resume {
+            goal_id,
+            options,
+            message,
+        }) => {
+            let message =
+                Some(message.join(" ").trim().to_string()).filter(|message| !message.is_empty());
+            run_goal(options, Session::Resume(goal_id, message), config, tui_logs).await
         }
+        Some(Command::Fork {
+            goal_id,
+            options,
+            instruction,
+        }) => {
+            let instruction = Some(instruction.join(" ").trim().to_string())
+                .filter(|instruction| !instruction.is_empty());
+            run_goal(
+                options,
+                Session::Fork {
+                    goal_id,
+                    instruction,
+                },
+                config,
+                tui_logs,
+            )
+            .await
+        }
+        Some(Command::List) => list_goals(&state_config()?).await,
+        Some(Command::Show { goal_id, json }) => show_goal(&state_config()?, &goal_id, json).await,
+        Some(Command::Stats { json }) => show_stats(&state_config()?, json).await,
+        Some(Command::Eval {
+            suite,
+            model_name,
+            keep,
+            json,
+        }) => run_eval(&suite, model_name, keep, json).await,
+        Some(Command::Undo { goal_id, to, list }) => {
+            undo_goal(&state_config()?, &goal_id, to, list).await
+        }
+        Some(Command::Compare { goal_a, goal_b }) => {
+            compare_goals(&state_config()?, &goal_a, &goal_b).await
+        }
+        Some(Command::Export { goal_id, out }) => {
+            export_goal(&state_config()?, &goal_id, out.as_deref()).await
+        }
+        Some(Command::Import { file }) => import_goal(&state_config()?, &file).await,
+        Some(Command::Artifacts { command }) => run_artifacts(&state_config()?, command).await,
+        Some(Command::Index {
+            command: IndexCommand::Build { kind },
+        }) => build_index(kind),
+        Some(Command::Config { agent }) => show_config(agent.as_deref()),
+        Some(Command::Setup) => setup::run_setup(),
+        Some(Command::McpServe) => run_mcp_serve().await,
+        Some(Command::Serve {
+            port,
+            host,
+            max_concurrent,
+        }) => run_serve(&host, port, max_concurrent).await,
+        Some(Command::Queue {
+            server,
+            workspace,
+            command,
+        }) => run_queue(server, workspace, command).await,
     }
+}
 
-    let goal = goal_parts.join(" ").trim().to_string();
-    if goal.is_empty() {
-        eprintln!(
-            "Usage: rx [--max-iterations N] [--model NAME] [--auto-commit] [--tool-verbose] [--debug-log PATH] <goal>"
-        );
-        std::process::exit(1);
-    }
+async fn start_goal(
+    run: RunArgs,
+    config: Option<RxConfig>,
+    tui_logs: Option<LogLines>,
+) -> Result<()> {
+    let goal = run.goal_text();
+    run_goal(run.options, Session::New(goal), config, tui_logs).await
+}
 
-    CliArgs {
-        goal,
-        max_iterations,
-        model_name,
-        auto_commit,
-        tool_verbose,
-        debug_log_path,
+enum Session {
+    New(String),
+    Resume(String, Option<String>),
+    Fork {
+        goal_id: String,
+        instruction: Option<String>,
+    },
+}
+
+/// Opens an existing goal for continuation and returns its goal text.
+async fn open_resumed_goal(
+    state: &StateSettings,
+    goal_id: &str,
+) -> Result<(String, Arc<dyn StateStore>)> {
+    let store = open_state_store(state, goal_id, true).await?;
+    let history = store.load().await?;
+    let goal = history
+        .iter()
+        .find(|event| event.r#type == "goal")
+        .and_then(|event| event.payload.get("goal"))
+        .and_then(|goal| goal.as_str())
+        .unwrap_or_default()
+        .to_string();
+    info!("Resuming goal {} ({} events)", goal_id, history.len());
+    Ok((goal, store))
+}
+
+/// Runs a goal in the foreground, on the `--tui` screen when `tui_logs`
+/// is set.
+async fn run_goal(
+    options: RunOptions,
+    session: Session,
+    config: Option<RxConfig>,
+    tui_logs: Option<LogLines>,
+) -> Result<()> {
+    let Some(logs) = tui_logs else {
+        let (kernel, wrap_up) =
+            prepare_goal(options, session, config, Vec::new(), Frontend::Terminal).await?;
+        let result = kernel.run().await;
+        let finished = wrap_up.finish().await;
+        return result.and(finished);
+    };
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        return Err(anyhow!("--tui needs an interactive terminal"));
     }
+    let tui = Arc::new(Tui::new(logs));
+    let (kernel, wrap_up) = prepare_goal(
+        options,
+        session,
+        config,
+        Vec::new(),
+        Frontend::Tui(Arc::clone(&tui)),
+    )
+    .await?;
+    let result = tui.run(kernel.spawn()).await;
+    let finished = wrap_up.finish().await;
+    result.and(finished)
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let CliArgs {
-        goal,
+/// Who watches a run and answers its confirmation prompts.
+enum Frontend {
+    /// Nobody (`rx serve`); `--confirm` is refused.
+    Headless,
+    /// Log lines on stdout and prompts on stdin.
+    Terminal,
+    /// The `--tui` screen.
+    Tui(Arc<Tui>),
+}
+
+/// Builds the kernel of a run from its options and config. `extra_hooks`
+/// see every event of the goal.
+async fn prepare_goal(
+    options: RunOptions,
+    session: Session,
+    config: Option<RxConfig>,
+    extra_hooks: Vec<Arc<dyn EventHook>>,
+    frontend: Frontend,
+) -> Result<(Kernel, GoalWrapUp)> {
+    let RunOptions {
         max_iterations,
         model_name,
         auto_commit,
+        branch,
+        pr,
         tool_verbose,
         debug_log_path,
-    } = parse_cli_args();
+        confirm,
+        small_model,
+        temperature,
+        reasoning_effort,
+        scope,
+        env,
+        max_duration,
+        max_iteration_time,
+        max_cost,
+        chaos,
+        chaos_seed,
+        prompt,
+        agent,
+        plan,
+        verify_command,
+        record,
+        replay,
+        output,
+        listen,
+        workdir,
+        isolated,
+        tui: _,
+    } = options;
+    let json_output = output == OutputFormat::Json;
+    let interactive = !matches!(frontend, Frontend::Headless);
+    let can_ask = interactive && std::io::stdin().is_terminal();
+
+    let mut workspace = build_workspace(config.as_ref(), scope.as_deref(), workdir.as_deref())?;
+    let mut tool_env = resolve_tool_env(config.as_ref(), env);
+    let redactor = build_redactor(
+        config.as_ref().and_then(|cfg| cfg.redaction.as_ref()),
+        &tool_env,
+    )?;
+    let agent = agent
+        .as_deref()
+        .map(|name| find_agent(config.as_ref(), name))
+        .transpose()?;
+    if let Some(agent) = agent {
+        info!("Agent profile: {}", agent.name);
+    }
+    let mut cli_defaults = config
+        .as_ref()
+        .and_then(|cfg| cfg.cli_defaults.clone())
+        .unwrap_or_default();
+    if let Some(overrides) = agent.and_then(|agent| agent.cli_defaults_overrides.as_ref()) {
+        cli_defaults = cli_defaults.overlay(overrides);
+    }
+    let auto_commit = auto_commit || branch || cli_defaults.auto_commit.unwrap_or(false);
+
+    // Slugs use a model only when one is configured explicitly; the other
+    // internal tasks and goal embeddings fall back to gpt-5-mini.
+    let configured_small_model = small_model.or_else(|| cli_defaults.small_model.clone());
+    let routes = config.as_ref().and_then(|cfg| cfg.model_routes.as_ref());
+    let model_router = credentials::openai_api_key().map(|api_key| {
+        let model_name = configured_small_model
+            .clone()
+            .unwrap_or_else(|| DEFAULT_SMALL_MODEL.to_string());
+        let router_config = config
+            .as_ref()
+            .and_then(|cfg| cfg.small_model_router.as_ref());
+        let defaults = SmallModelLimits::default();
+        let limits = SmallModelLimits {
+            max_calls: router_config
+                .and_then(|router| router.max_calls)
+                .unwrap_or(defaults.max_calls),
+            max_concurrency: router_config
+                .and_then(|router| router.max_concurrency)
+                .unwrap_or(defaults.max_concurrency),
+        };
+        let router = ModelRouter::new(api_key, model_name, limits)
+            .with_routes(
+                routes
+                    .map(|routes| routes.tasks.clone())
+                    .unwrap_or_default(),
+            )
+            .with_planning_model(routes.and_then(|routes| routes.planning.clone()));
+        Arc::new(router)
+    });
+    let slug_router = model_router
+        .as_deref()
+        .filter(|router| configured_small_model.is_some() || router.is_routed(SmallTask::GoalSlug));
+
+    let state = state_settings(config.as_ref());
+    let data_dir = state.data_dir.clone();
+    let goal_ids = GoalIdScheme::from_config(config.as_ref().and_then(|cfg| cfg.goal_id.as_ref()))
+        .context("invalid [goal_id] config")?;
+    let (goal_id, goal, base_state_store, is_new_goal, follow_up) = match session {
+        Session::New(goal) => {
+            let goal_id = goal_ids.generate(slug_router, &goal).await;
+            let store = open_state_store(&state, &goal_id, false).await?;
+            if let Some(router) = &model_router {
+                warn_similar_goals(
+                    router,
+                    store.as_ref(),
+                    config.as_ref(),
+                    &data_dir,
+                    &goal_id,
+                    &goal,
+                )
+                .await;
+            }
+            (goal_id, goal, store, true, None)
+        }
+        Session::Fork {
+            goal_id: source_id,
+            instruction,
+        } => {
+            let source_goal = load_goal(&state, &source_id)
+                .await?
+                .iter()
+                .find(|event| event.r#type == "goal")
+                .and_then(|event| event.payload.get("goal"))
+                .and_then(|goal| goal.as_str())
+                .unwrap_or_default()
+                .to_string();
+            let slug_source = instruction.as_deref().unwrap_or(&source_goal);
+            let fork_id = goal_ids.generate(slug_router, slug_source).await;
+            let goal_id = fork_goal(&state, &source_id, &format!("{}-fork", fork_id)).await?;
+            info!("Forked {} into {}", source_id, goal_id);
+            let (goal, store) = open_resumed_goal(&state, &goal_id).await?;
+            (goal_id, goal, store, false, instruction)
+        }
+        Session::Resume(spec, message) => {
+            let goal_id = match parse_checkpoint(&spec)? {
+                (goal_id, None) => goal_id,
+                (source_id, Some(seq)) => {
+                    let branch_id = branch_goal(&state, &source_id, seq).await?;
+                    info!("Branched {} at event {} into {}", source_id, seq, branch_id);
+                    branch_id
+                }
+            };
+            let (goal, store) = open_resumed_goal(&state, &goal_id).await?;
+            (goal_id, goal, store, false, message)
+        }
+    };
     let goal_slug = sanitize_goal_slug(&goal);
-    let timestamp = Utc::now().format("%Y%m%d-%H%M%S").to_string();
-    let goal_id = format!("{}-{}", timestamp, goal_slug);
 
-    let system_prompt = fs::read_to_string("LOOP_PROMPT.md")
-        .await
-        .context("failed to read LOOP_PROMPT.md")?;
+    let goal_branch = if branch {
+        let goal_branch = GoalBranch::start(workspace.root(), &goal_slug, !is_new_goal).await?;
+        info!("Committing on branch {}", goal_branch.name());
+        Some(goal_branch)
+    } else {
+        None
+    };
+    let isolation = if isolated {
+        let worktree =
+            IsolatedWorktree::create(workspace.root(), &default_worktree_root(), &goal_id).await?;
+        let subdir = workspace
+            .command_dir(None)
+            .strip_prefix(workspace.root())
+            .map(std::path::Path::to_path_buf)
+            .unwrap_or_default();
+        workspace = build_workspace(
+            config.as_ref(),
+            scope.as_deref(),
+            Some(&worktree.path().join(subdir)),
+        )?;
+        info!(
+            "Isolated worktree: {} (branch {})",
+            worktree.path().display(),
+            worktree.branch()
+        );
+        Some(worktree)
+    } else {
+        None
+    };
 
-    let base_state_store: Arc<dyn StateStore> = Arc::new(InMemoryStateStore::new(&goal_id).await?);
-    let mut hooks: Vec<Arc<dyn EventHook>> = Vec::new();
+    let scratch_config = config.as_ref().and_then(|cfg| cfg.scratch.as_ref());
+    let scratch = if scratch_config.and_then(|scratch| scratch.enabled) != Some(false) {
+        let root = scratch_config
+            .and_then(|scratch| scratch.dir.clone())
+            .unwrap_or_else(default_scratch_root);
+        let max_age_hours = scratch_config
+            .and_then(|scratch| scratch.max_age_hours)
+            .unwrap_or(DEFAULT_MAX_AGE_HOURS);
+        purge_stale(&root, Duration::from_secs(max_age_hours * 3600), &goal_id);
+        let scratch = ScratchDir::create(&root, &goal_id)?;
+        workspace = Arc::new((*workspace).clone().with_allowed_path(scratch.path()));
+        let path = scratch.path().display().to_string();
+        tool_env.insert("TMPDIR".to_string(), path.clone());
+        tool_env.insert("RX_SCRATCH_DIR".to_string(), path);
+        Some(scratch)
+    } else {
+        None
+    };
+
+    if !is_new_goal {
+        restore_working_dir(&workspace, &base_state_store.load().await?);
+    }
+
+    let prompt_path = prompt
+        .or_else(|| agent.and_then(|agent| agent.prompt.clone()))
+        .or_else(|| cli_defaults.prompt.clone());
+    let mut system_prompt = load_system_prompt(prompt_path.as_deref()).await?;
+    if let Some(scratch) = &scratch {
+        system_prompt.push_str(&format!(
+            "\n\n## Scratch directory\n\nPut throwaway files (experiments, temporary test inputs, downloads) in `{}` instead of the repository. Commands see it as `$TMPDIR` and `$RX_SCRATCH_DIR`; it is deleted when the goal finishes.\n",
+            scratch.path().display()
+        ));
+    }
+    if let Some(context) = workspace_context(&detect_workspace(workspace.root())) {
+        system_prompt.push_str("\n\n");
+        system_prompt.push_str(&context);
+    }
+    let memory_enabled = config
+        .as_ref()
+        .and_then(|cfg| cfg.memory.as_ref())
+        .and_then(|memory| memory.enabled)
+        .unwrap_or(true);
+    if let Some(memory) = memory_enabled
+        .then(|| memory_context(workspace.root()))
+        .flatten()
+    {
+        system_prompt.push_str("\n\n");
+        system_prompt.push_str(&memory);
+    }
+    if let Some(scope) = workspace.scope() {
+        system_prompt.push_str(&format!(
+            "\n\n## Scope\n\nThis run is scoped to `{}`. File tools reject paths outside it; keep reads, edits, and commands focused on that subtree.\n",
+            scope.strip_prefix(workspace.root()).unwrap_or(scope).display()
+        ));
+    }
+
+    if state.backend == StateBackend::Postgres {
+        info!("Event log: postgres rx_events (goal_id = {})", goal_id);
+    } else {
+        info!(
+            "Event log: {}",
+            goal_log_path(&data_dir, &goal_id).display()
+        );
+    }
+    let mut hooks = extra_hooks;
 
     if let Some(path) = debug_log_path {
         hooks.push(Arc::new(DebugJsonlHook::new(&path).await?));
@@ -153,79 +588,1705 @@ async fn main() -> Result<()> {
     if tool_verbose {
         hooks.push(Arc::new(ToolVerboseHook));
     }
+    if json_output {
+        hooks.push(Arc::new(JsonOutputHook));
+    }
+    if let Some(addr) = listen {
+        let listener = tokio::net::TcpListener::bind(&addr)
+            .await
+            .with_context(|| format!("failed to listen on {}", addr))?;
+        let (events, _) = tokio::sync::broadcast::channel(EVENT_BUFFER);
+        hooks.push(Arc::new(EventChannelHook::new(
+            goal_id.clone(),
+            events.clone(),
+        )));
+        info!("Streaming events on ws://{}/ws", listener.local_addr()?);
+        tokio::spawn(websocket::listen(listener, events));
+    }
+    if let Frontend::Tui(tui) = &frontend {
+        hooks.push(Arc::clone(tui) as Arc<dyn EventHook>);
+    }
     if auto_commit {
-        let generator = Arc::new(HeuristicCommitMessageGenerator);
-        hooks.push(Arc::new(AutoCommitHook::new(generator)));
+        let generator = commit_message_generator(
+            model_router.as_ref(),
+            cli_defaults.conventional_commits.unwrap_or(false),
+        );
+        hooks.push(Arc::new(
+            AutoCommitHook::new(generator)
+                .with_dir(&workspace.command_dir(None))
+                .with_strategy(cli_defaults.auto_commit_strategy.unwrap_or_default()),
+        ));
+    }
+    let webhook = config
+        .as_ref()
+        .and_then(|cfg| cfg.notifications.as_ref())
+        .and_then(|notifications| notifications.webhook.as_ref())
+        .map(|webhook| {
+            let events = webhook.events.as_deref().unwrap_or(&ALL_LIFECYCLE_EVENTS);
+            Arc::new(
+                LifecycleWebhook::new(&webhook.url, events, &goal_id, &goal)
+                    .with_dir(&workspace.command_dir(None)),
+            )
+        });
+    if let Some(webhook) = &webhook {
+        hooks.push(Arc::clone(webhook) as Arc<dyn EventHook>);
     }
 
-    let state_store: Arc<dyn StateStore> = if hooks.is_empty() {
+    let artifacts = Arc::new(ArtifactStore::new(&data_dir).with_redactor(redactor.clone()));
+    let state_store: Arc<dyn StateStore> = if hooks.is_empty() && redactor.is_empty() {
         Arc::clone(&base_state_store)
     } else {
-        Arc::new(HookedStateStore::new(Arc::clone(&base_state_store), hooks))
+        Arc::new(
+            HookedStateStore::new(Arc::clone(&base_state_store), hooks).with_redactor(redactor),
+        )
     };
 
-    state_store
-        .append_event(Event::new(
-            "goal",
-            json!({
-                "goal": goal.clone(),
-                "goal_id": goal_id.clone()
-            }),
-        ))
-        .await?;
+    if is_new_goal {
+        state_store
+            .append_event(Event::new(
+                "goal",
+                json!({
+                    "goal": goal.clone(),
+                    "goal_id": goal_id.clone()
+                }),
+            ))
+            .await?;
+    }
+    if let Some(message) = follow_up {
+        state_store
+            .append_event(Event::new("user_message", json!({ "message": message })))
+            .await?;
+    }
 
-    let config = load_config(std::path::Path::new(".rx/config.toml"));
-    let tool_selection = resolve_enabled_tools(config.as_ref().and_then(|cfg| cfg.tools.as_ref()));
+    let tool_selection =
+        resolve_enabled_tools(config.as_ref().and_then(|cfg| cfg.tools.as_ref()), agent);
     for warning in &tool_selection.warnings {
-        eprintln!("Warning: {}", warning);
+        warn!("{}", warning);
     }
 
-    let mut registry = ToolRegistry::new();
-    for tool_name in &tool_selection.enabled_tools {
-        match tool_name.as_str() {
-            "exec" => registry.register(Arc::new(ExecTool)),
-            "read_file" => registry.register(Arc::new(ReadFileTool)),
-            "write_file" => registry.register(Arc::new(WriteFileTool)),
-            "create_file" => registry.register(Arc::new(CreateFileTool)),
-            "append_file" => registry.register(Arc::new(AppendFileTool)),
-            "replace_in_file" => registry.register(Arc::new(ReplaceInFileTool)),
-            "apply_patch" => registry.register(Arc::new(ApplyPatchTool)),
-            "apply_unified_patch" => registry.register(Arc::new(ApplyUnifiedPatchTool)),
-            "list_dir" => registry.register(Arc::new(ListDirTool)),
-            "done" => registry.register(Arc::new(DoneTool)),
-            _ => {}
+    info!("Workspace root: {}", workspace.root().display());
+    if let Some(scope) = workspace.scope() {
+        info!("Agent scope: {}", scope.display());
+    }
+
+    if !tool_env.is_empty() {
+        info!(
+            "Tool environment: {}",
+            tool_env.keys().cloned().collect::<Vec<_>>().join(", ")
+        );
+    }
+    let processes = Arc::new(ProcessTable::default());
+    let sessions = Arc::new(PtySessions::default());
+    let mut registry = build_builtin_registry(
+        &tool_selection.enabled_tools,
+        &workspace,
+        &tool_env,
+        config.as_ref(),
+        &processes,
+        &sessions,
+    );
+    if let Frontend::Tui(tui) = &frontend {
+        if registry.get("exec").is_some() {
+            // Streams command output to the tool pane.
+            registry.register(Arc::new(
+                ExecTool::default()
+                    .with_env(tool_env.clone())
+                    .with_env_policy(env_policy(config.as_ref()))
+                    .with_workspace(Arc::clone(&workspace))
+                    .with_live_output(tui.live_output()),
+            ));
         }
     }
+    // Needs the goal's event log, so it is not part of the shared registry.
+    if tool_selection
+        .enabled_tools
+        .iter()
+        .any(|tool| tool == "undo_last_change")
+    {
+        registry.register(Arc::new(UndoLastChangeTool::new(
+            Arc::clone(&workspace),
+            Arc::clone(&state_store),
+            Arc::clone(&artifacts),
+        )));
+    }
+    let max_tool_output_bytes = config
+        .as_ref()
+        .and_then(|cfg| cfg.tools.as_ref())
+        .and_then(|tools| tools.max_output_bytes)
+        .unwrap_or(DEFAULT_MAX_OUTPUT_BYTES);
+    let max_tool_output_bytes = (max_tool_output_bytes > 0).then_some(max_tool_output_bytes);
+    if max_tool_output_bytes.is_some() {
+        registry.register(Arc::new(ReadToolOutputTool::new(
+            Arc::clone(&state_store),
+            Arc::clone(&artifacts),
+        )));
+    }
+    if plan {
+        registry.register(Arc::new(SubmitPlanTool));
+        registry.register(Arc::new(CompleteStepTool));
+    }
+
+    let mcp_servers = config
+        .as_ref()
+        .and_then(|cfg| cfg.mcp.as_ref())
+        .map(|mcp| mcp.servers.clone())
+        .unwrap_or_default();
+    for server in &mcp_servers {
+        match register_mcp_server(&mut registry, server, &tool_env).await {
+            Ok(count) => info!("MCP server '{}': registered {} tools", server.name, count),
+            Err(error) => warn!("MCP server '{}' unavailable: {:#}", server.name, error),
+        }
+    }
+
+    check_tool_health(&mut registry, &state, config.as_ref()).await;
 
     let resolved_model_name = model_name
+        .or_else(|| agent.and_then(|agent| agent.model.clone()))
         .or_else(|| std::env::var("OPENAI_MODEL").ok())
+        .or_else(|| cli_defaults.model_name.clone())
         .unwrap_or_else(|| "gpt-4o".to_string());
 
-    let model: Arc<dyn Model> = if let Ok(api_key) = std::env::var("OPENAI_API_KEY") {
-        if api_key.trim().is_empty() {
-            eprintln!("Warning: OPENAI_API_KEY is empty. Using MockModel.");
-            Arc::new(MockModel::new(system_prompt, goal, goal_slug))
-        } else {
-            Arc::new(OpenAIModel::new(
-                api_key,
+    let pricing = resolve_pricing(
+        config.as_ref().and_then(|cfg| cfg.model.as_ref()),
+        &resolved_model_name,
+    );
+    if max_cost.is_some() && pricing.is_none() {
+        return Err(anyhow!(
+            "--max-cost needs prices for model '{}'; set input_price and output_price (USD per million tokens) under [model]",
+            resolved_model_name
+        ));
+    }
+
+    let system_prompt = render_prompt(
+        &system_prompt,
+        &PromptContext {
+            goal: &goal,
+            workspace: &workspace,
+            registry: &registry,
+        },
+    )
+    .context("failed to render the system prompt")?;
+
+    let mut model_fallback = None;
+    let model: Arc<dyn Model> = if let Some(path) = &replay {
+        let replay = ReplayModel::load(path)?;
+        info!(
+            "Replaying {} model responses from {}",
+            replay.requests(),
+            path.display()
+        );
+        Arc::new(replay)
+    } else if let Some(api_key) = credentials::openai_api_key() {
+        let model_config = config.as_ref().and_then(|cfg| cfg.model.as_ref());
+        let fallback = model_config
+            .and_then(|model| model.fallback.clone())
+            .unwrap_or_default();
+        if !fallback.is_empty() {
+            info!(
+                "Model fallback: {} -> {}",
                 resolved_model_name,
-                &registry,
-                system_prompt,
-            ))
+                fallback.join(" -> ")
+            );
+        }
+        let params = RequestParams {
+            temperature: temperature.or(model_config.and_then(|model| model.temperature)),
+            reasoning_effort: reasoning_effort
+                .or(model_config.and_then(|model| model.reasoning_effort)),
+            max_output_tokens: model_config.and_then(|model| model.max_output_tokens),
+            parallel_tool_calls: model_config.and_then(|model| model.parallel_tool_calls),
+            prompt_cache_retention: model_config.and_then(|model| model.prompt_cache_retention),
+        };
+        let openai = |name: &str| -> Arc<dyn Model> {
+            Arc::new(
+                OpenAIModel::new(
+                    api_key.clone(),
+                    name.to_string(),
+                    &registry,
+                    system_prompt.clone(),
+                )
+                .with_response_chaining(
+                    model_config
+                        .and_then(|model| model.response_chaining)
+                        .unwrap_or(false),
+                )
+                .with_request_params(params),
+            )
+        };
+        let models = std::iter::once(resolved_model_name)
+            .chain(fallback)
+            .map(|name| {
+                let model = openai(&name);
+                (name, model)
+            })
+            .collect();
+        let chain = Arc::new(
+            FallbackModel::new(models).with_retries(
+                model_config
+                    .and_then(|model| model.retries)
+                    .unwrap_or(DEFAULT_MODEL_RETRIES),
+            ),
+        );
+        model_fallback = Some(Arc::clone(&chain));
+        let planning_model = model_router
+            .as_deref()
+            .and_then(ModelRouter::planning_model)
+            .filter(|_| plan);
+        match planning_model {
+            Some(name) => {
+                info!("Planning with {}", name);
+                Arc::new(PlannerModel::new(openai(name), chain))
+            }
+            None => chain,
         }
     } else {
-        eprintln!("Warning: OPENAI_API_KEY not set. Using MockModel.");
+        warn!("OPENAI_API_KEY not set and no key stored by `rx setup`. Using MockModel.");
         Arc::new(MockModel::new(system_prompt, goal, goal_slug))
     };
+    let model: Arc<dyn Model> = match &record {
+        Some(path) => {
+            info!("Recording model responses to {}", path.display());
+            Arc::new(RecordingModel::create(model, path)?)
+        }
+        None => model,
+    };
+
+    if confirm && !interactive {
+        return Err(anyhow!("--confirm needs an interactive terminal"));
+    }
+    // Nobody can answer a prompt on a machine-read stdout.
+    let confirm = confirm || (cli_defaults.confirm.unwrap_or(false) && !json_output && interactive);
 
-    let kernel = Kernel::new(
+    let mut kernel = Kernel::new(
         goal_id.clone(),
         model,
         Arc::clone(&state_store),
         registry,
         max_iterations,
+    )
+    .with_process_table(processes)
+    .with_pty_sessions(sessions)
+    .with_snapshots(Snapshots::new(
+        Arc::clone(&workspace),
+        Arc::clone(&artifacts),
+    ))
+    .with_max_tool_output_bytes(max_tool_output_bytes)
+    .with_artifacts(Arc::clone(&artifacts));
+    if let Some(chain) = model_fallback {
+        kernel = kernel.with_model_fallback(chain);
+    }
+    if let Some(detection) = loop_detection(config.as_ref()) {
+        kernel = kernel.with_loop_detection(detection);
+    }
+    kernel = kernel.with_write_quota(write_quota(config.as_ref()));
+    // The event log changes with every event; keep it out of the diffs.
+    let event_log_dir: Vec<PathBuf> = data_dir
+        .canonicalize()
+        .ok()
+        .and_then(|dir| Some(dir.strip_prefix(workspace.root()).ok()?.to_path_buf()))
+        .into_iter()
+        .collect();
+    let diff_events = config
+        .as_ref()
+        .and_then(|cfg| cfg.state.as_ref())
+        .and_then(|state| state.diff_events)
+        .unwrap_or(false);
+    if diff_events {
+        if is_git_repo(workspace.root()) {
+            kernel = kernel
+                .with_workspace_diff(WorkspaceDiff::start(workspace.root(), &event_log_dir).await?);
+        } else {
+            warn!("[state].diff_events needs a git repository; no workspace_diff events will be recorded");
+        }
+    }
+    let review_config = config.as_ref().and_then(|cfg| cfg.review.as_ref());
+    if review_config.and_then(|review| review.enabled) == Some(true) {
+        match &model_router {
+            Some(router) => {
+                kernel = kernel.with_critic(
+                    Arc::new(SmallModelCritic::new(
+                        Arc::clone(router),
+                        workspace.root().to_path_buf(),
+                        event_log_dir.clone(),
+                    )),
+                    review_config
+                        .and_then(|review| review.max_review_rounds)
+                        .unwrap_or(2),
+                );
+            }
+            None => warn!(
+                "[review] needs OPENAI_API_KEY for the small model; completion proposals will not be reviewed"
+            ),
+        }
+    }
+    let policy = match config.as_ref().and_then(|cfg| cfg.policy.as_ref()) {
+        Some(policy_config) => Some(Arc::new(
+            Policy::from_config(policy_config).context("failed to load [policy] config")?,
+        )),
+        None => None,
+    };
+
+    if confirm || policy.as_ref().is_some_and(|policy| policy.has_ask_rules()) {
+        kernel = match &frontend {
+            Frontend::Tui(tui) => with_prompts(kernel, Arc::clone(tui), confirm, plan),
+            _ => with_prompts(kernel, Arc::new(StdinApprovalGate::new()), confirm, plan),
+        };
+    }
+    if let Frontend::Tui(tui) = &frontend {
+        tui.attach(
+            &goal_id,
+            max_iterations,
+            pricing,
+            workspace.root().to_path_buf(),
+            event_log_dir.clone(),
+        );
+    }
+    if let Some(policy) = policy {
+        kernel = kernel.with_policy(policy);
+    }
+    if plan {
+        kernel = kernel.with_plan_mode();
+    }
+    if let Some(command) = verify_command.or_else(|| cli_defaults.verify_command.clone()) {
+        info!("Verify command: {}", command);
+        kernel = kernel.with_verifier(
+            Verifier::new(command, workspace.root().to_path_buf()).with_env(tool_env.clone()),
+        );
+    }
+    let tools_config = config.as_ref().and_then(|cfg| cfg.tools.as_ref());
+    let timeout = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+    if let Some(secs) = tools_config.and_then(|tools| tools.timeout_secs) {
+        kernel = kernel.with_tool_timeout(timeout(secs));
+    }
+    for (tool, secs) in tools_config
+        .and_then(|tools| tools.timeouts.as_ref())
+        .into_iter()
+        .flatten()
+    {
+        kernel = kernel.with_tool_timeout_for(tool, timeout(*secs));
+    }
+    if let Some(max_duration) = max_duration {
+        kernel = kernel.with_max_duration(max_duration);
+    }
+    if let Some(limit) = max_iteration_time {
+        kernel = kernel.with_max_iteration_duration(limit);
+    }
+    if let Some(pricing) = pricing {
+        kernel = kernel.with_pricing(pricing);
+    }
+    if let Some(max_cost) = max_cost {
+        kernel = kernel.with_max_cost(max_cost);
+    }
+    if let Some(rate) = chaos {
+        let seed = chaos_seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+        });
+        let chaos = Chaos::new(rate, seed);
+        info!(
+            "Chaos mode: injecting faults into {:.0}% of tool calls (--chaos-seed {})",
+            chaos.rate() * 100.0,
+            chaos.seed()
+        );
+        kernel = kernel.with_chaos(chaos);
+    }
+
+    Ok((
+        kernel,
+        GoalWrapUp {
+            goal_id,
+            state_store,
+            config,
+            workspace,
+            scratch,
+            model_router,
+            webhook,
+            isolation,
+            goal_branch: goal_branch.map(|branch| (branch, pr)),
+            can_ask,
+            data_dir,
+            started: Utc::now(),
+        },
+    ))
+}
+
+/// Sends `ask` policy decisions to `gate`, and with `--confirm` also every
+/// side-effecting tool call, completion proposals and, in a `--plan` run,
+/// the plan.
+fn with_prompts<G>(mut kernel: Kernel, gate: Arc<G>, confirm: bool, plan: bool) -> Kernel
+where
+    G: ApprovalGate + CompletionReviewer + PlanReviewer + 'static,
+{
+    if confirm {
+        kernel = kernel.with_confirm().with_completion_reviewer(gate.clone());
+        if plan {
+            kernel = kernel.with_plan_reviewer(gate.clone());
+        }
+    }
+    kernel.with_approval_gate(gate)
+}
+
+/// What is left to do once a run's kernel stops.
+struct GoalWrapUp {
+    goal_id: String,
+    state_store: Arc<dyn StateStore>,
+    config: Option<RxConfig>,
+    workspace: Arc<Workspace>,
+    scratch: Option<ScratchDir>,
+    model_router: Option<Arc<ModelRouter>>,
+    webhook: Option<Arc<LifecycleWebhook>>,
+    isolation: Option<IsolatedWorktree>,
+    /// The `--branch` branch, and whether to open its pull request.
+    goal_branch: Option<(GoalBranch, bool)>,
+    /// Whether the user can be asked on stdin once the run is over.
+    can_ask: bool,
+    data_dir: PathBuf,
+    started: DateTime<Utc>,
+}
+
+impl GoalWrapUp {
+    /// Records a blocker report, sends `[notify]` and webhook notifications,
+    /// cleans up the scratch directory, and prints small model usage.
+    async fn finish(self) -> Result<()> {
+        let GoalWrapUp {
+            goal_id,
+            state_store,
+            config,
+            workspace,
+            scratch,
+            model_router,
+            webhook,
+            isolation,
+            goal_branch,
+            can_ask,
+            data_dir,
+            started,
+        } = self;
+        let scratch_config = config.as_ref().and_then(|cfg| cfg.scratch.as_ref());
+        let history = state_store.load().await?;
+
+        if let Some(report) = BlockedReport::from_history(&goal_id, &history) {
+            record_blocker(config.as_ref(), workspace.root(), &report).await;
+        }
+
+        let summary = RunSummary::from_history(&goal_id, &history, started);
+        send_notifications(config.as_ref(), workspace.root(), &summary).await;
+        if let Some(webhook) = &webhook {
+            if let Err(error) = webhook.finish(&summary).await {
+                warn!("{:#}", error);
+            }
+        }
+        if let Some((branch, open)) = &goal_branch {
+            if summary.reason == "done" {
+                let path = data_dir.join(format!("{}.pr.md", goal_id));
+                let router = model_router.as_deref();
+                if let Err(error) =
+                    prepare_pull_request(branch, &summary, router, &path, *open).await
+                {
+                    warn!("{:#}", error);
+                }
+            }
+        }
+        if let Some(worktree) = &isolation {
+            let patch_path = data_dir.join(format!("{}.patch", goal_id));
+            if let Err(error) = finish_isolated(worktree, &summary, &patch_path, can_ask).await {
+                warn!("{:#}", error);
+                info!(
+                    "Isolated worktree kept: {} (branch {})",
+                    worktree.path().display(),
+                    worktree.branch()
+                );
+            }
+        }
+
+        if let Some(scratch) = &scratch {
+            let cleanup = scratch_config
+                .and_then(|scratch| scratch.cleanup)
+                .unwrap_or_default();
+            if !scratch.finish(cleanup, &history) {
+                info!("Scratch directory kept: {}", scratch.path().display());
+            }
+        }
+
+        if let Some(router) = &model_router {
+            for (model_name, usage) in router.usage_by_model() {
+                info!(
+                    "Internal model usage ({}): {} requests, {} input tokens, {} output tokens",
+                    model_name, usage.requests, usage.input_tokens, usage.output_tokens
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Warns when a prior goal looks like the new one, so finished work is
+/// resumed instead of rerun. Failures only skip the check.
+async fn warn_similar_goals(
+    router: &ModelRouter,
+    store: &dyn StateStore,
+    config: Option<&RxConfig>,
+    data_dir: &std::path::Path,
+    goal_id: &str,
+    goal: &str,
+) {
+    let dedup = config.and_then(|cfg| cfg.goal_dedup.as_ref());
+    if !dedup.and_then(|dedup| dedup.enabled).unwrap_or(true) {
+        return;
+    }
+    let embedding_model = dedup
+        .and_then(|dedup| dedup.embedding_model.as_deref())
+        .unwrap_or(DEFAULT_EMBEDDING_MODEL);
+    let threshold = dedup
+        .and_then(|dedup| dedup.threshold)
+        .unwrap_or(DEFAULT_SIMILARITY_THRESHOLD);
+
+    match find_similar_goals(
+        router,
+        store,
+        data_dir,
+        embedding_model,
+        threshold,
+        goal_id,
+        goal,
+    )
+    .await
+    {
+        Ok(similar) => {
+            for prior in similar.iter().take(3) {
+                warn!(
+                    "similar goal run {} (id {}, similarity {:.2}): \"{}\". Resume instead? rx resume {}",
+                    goal_age(&prior.goal_id).unwrap_or_else(|| "earlier".to_string()),
+                    prior.goal_id,
+                    prior.similarity,
+                    prior.goal,
+                    prior.goal_id
+                );
+            }
+        }
+        Err(error) => warn!("similar goal check skipped: {:#}", error),
+    }
+}
+
+/// Built-in prices for `model_name`, with any `[model]` price keys applied
+/// on top. Unknown models need at least `input_price` and `output_price`.
+/// `[loop_detection]` over the defaults, or `None` when it is disabled.
+fn loop_detection(config: Option<&RxConfig>) -> Option<LoopDetection> {
+    let settings = config.and_then(|cfg| cfg.loop_detection.as_ref());
+    if settings.and_then(|settings| settings.enabled) == Some(false) {
+        return None;
+    }
+    let defaults = LoopDetection::default();
+    Some(LoopDetection {
+        window: settings
+            .and_then(|settings| settings.window)
+            .unwrap_or(defaults.window),
+        warn_after: settings
+            .and_then(|settings| settings.warn_after)
+            .unwrap_or(defaults.warn_after),
+        terminate_after: settings
+            .and_then(|settings| settings.terminate_after)
+            .unwrap_or(defaults.terminate_after),
+        stall_iterations: settings
+            .and_then(|settings| settings.stall_iterations)
+            .unwrap_or(defaults.stall_iterations),
+    })
+}
+
+fn write_quota(config: Option<&RxConfig>) -> WriteQuota {
+    let settings = config.and_then(|cfg| cfg.write_quota.as_ref());
+    let defaults = WriteQuota::default();
+    WriteQuota {
+        max_bytes: settings
+            .and_then(|settings| settings.max_bytes)
+            .unwrap_or(defaults.max_bytes),
+        max_files: settings
+            .and_then(|settings| settings.max_files)
+            .unwrap_or(defaults.max_files),
+    }
+}
+
+/// `[env_policy]` over the built-in allowlist and secret patterns.
+fn env_policy(config: Option<&RxConfig>) -> EnvPolicy {
+    let settings = config.and_then(|cfg| cfg.env_policy.as_ref());
+    EnvPolicy::default()
+        .with_inherit(
+            settings
+                .and_then(|settings| settings.inherit.as_deref())
+                .unwrap_or_default(),
+        )
+        .with_deny(
+            settings
+                .and_then(|settings| settings.deny.as_deref())
+                .unwrap_or_default(),
+        )
+        .with_inherit_all(
+            settings
+                .and_then(|settings| settings.inherit_all)
+                .unwrap_or(false),
+        )
+}
+
+fn resolve_pricing(config: Option<&ModelConfig>, model_name: &str) -> Option<ModelPricing> {
+    let input = config.and_then(|model| model.input_price);
+    let cached_input = config.and_then(|model| model.cached_input_price);
+    let output = config.and_then(|model| model.output_price);
+    let base = ModelPricing::for_model(model_name).or_else(|| {
+        Some(ModelPricing {
+            input: input?,
+            cached_input: cached_input.or(input)?,
+            output: output?,
+        })
+    })?;
+    Some(ModelPricing {
+        input: input.unwrap_or(base.input),
+        cached_input: cached_input.unwrap_or(base.cached_input),
+        output: output.unwrap_or(base.output),
+    })
+}
+
+/// Warns about tools that kept failing in recent goals and, with
+/// `[tool_health].quarantine`, removes them from this run's registry.
+async fn check_tool_health(
+    registry: &mut ToolRegistry,
+    state: &StateSettings,
+    config: Option<&RxConfig>,
+) {
+    let health_config = config.and_then(|cfg| cfg.tool_health.as_ref());
+    if !health_config
+        .and_then(|health| health.enabled)
+        .unwrap_or(true)
+    {
+        return;
+    }
+    let threshold = health_config
+        .and_then(|health| health.failure_threshold)
+        .unwrap_or(DEFAULT_FAILURE_THRESHOLD)
+        .max(1);
+    let quarantine = health_config
+        .and_then(|health| health.quarantine)
+        .unwrap_or(false);
+
+    let health = match recent_tool_health(state).await {
+        Ok(health) => health,
+        Err(error) => {
+            warn!("tool health check skipped: {:#}", error);
+            return;
+        }
+    };
+    for (name, stats) in unhealthy_tools(&health, threshold) {
+        if registry.get(name).is_none() {
+            continue;
+        }
+        let action = if quarantine {
+            registry.unregister(name);
+            "quarantined for this run"
+        } else {
+            "set [tool_health].quarantine = true to exclude it"
+        };
+        warn!(
+            "tool '{}' failed its last {} calls ({:.0}% of {} calls in recent goals); {}",
+            name,
+            stats.consecutive_failures,
+            stats.failure_rate() * 100.0,
+            stats.calls,
+            action
+        );
+    }
+}
+
+async fn register_mcp_server(
+    registry: &mut ToolRegistry,
+    server: &McpServerConfig,
+    env: &BTreeMap<String, String>,
+) -> Result<usize> {
+    let client = Arc::new(McpClient::connect(server, env).await?);
+    let mut count = 0;
+    for listing in client.list_tools().await? {
+        let Some(tool) = McpTool::from_listing(Arc::clone(&client), &listing) else {
+            continue;
+        };
+        if registry.get(tool.name()).is_some() {
+            warn!(
+                "MCP tool '{}' collides with a registered tool; skipping.",
+                tool.name()
+            );
+            continue;
+        }
+        registry.register(Arc::new(tool));
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Records a blocked outcome where `[blockers]` asks; failures are warnings.
+async fn record_blocker(config: Option<&RxConfig>, root: &std::path::Path, report: &BlockedReport) {
+    let Some(blockers) = config.and_then(|cfg| cfg.blockers.as_ref()) else {
+        return;
+    };
+    if let Some(file) = &blockers.file {
+        let path = root.join(file);
+        match report.append_to(&path) {
+            Ok(()) => info!("Blocker recorded in {}", path.display()),
+            Err(error) => warn!("{:#}", error),
+        }
+    }
+    if blockers.github_issue == Some(true) {
+        let labels = blockers.labels.clone().unwrap_or_default();
+        match report.file_github_issue(&labels).await {
+            Ok(url) => info!("Blocker filed as {}", url),
+            Err(error) => warn!("{:#}", error),
+        }
+    }
+}
+
+/// Writes the pull request description of a `--branch` run to `path` and,
+/// with `--pr`, opens the pull request.
+async fn prepare_pull_request(
+    branch: &GoalBranch,
+    summary: &RunSummary,
+    router: Option<&ModelRouter>,
+    path: &std::path::Path,
+    open: bool,
+) -> Result<()> {
+    if branch.commits().await?.is_empty() {
+        info!("Branch {} has no commits; no pull request", branch.name());
+        return Ok(());
+    }
+    let text = branch
+        .describe(&summary.goal, &summary.goal_id, router)
+        .await?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::write(path, &text.body)
+        .await
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    info!("Pull request: {} ({})", text.title, path.display());
+    if open {
+        let url = branch.open_pull_request(&text).await?;
+        info!("Opened {}", url);
+    } else {
+        info!(
+            "Open it with: git push -u origin {} && gh pr create --head {} --title {:?} --body-file {}",
+            branch.name(),
+            branch.name(),
+            text.title,
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Takes the changes of an `--isolated` run that ended `done`: merged or
+/// written to `patch_path` as the user picks, or as a patch when nobody
+/// can be asked. Other runs keep the worktree for a resume.
+async fn finish_isolated(
+    worktree: &IsolatedWorktree,
+    summary: &RunSummary,
+    patch_path: &std::path::Path,
+    can_ask: bool,
+) -> Result<()> {
+    if summary.reason != "done" {
+        info!(
+            "Isolated worktree kept: {} (resume with: rx resume --isolated {})",
+            worktree.path().display(),
+            summary.goal_id
+        );
+        return Ok(());
+    }
+    let subject: String = summary
+        .goal
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .take(64)
+        .collect();
+    if !worktree.commit_all(&format!("rx: {}", subject)).await? {
+        info!("Isolated run made no changes");
+        return worktree.remove(true).await;
+    }
+    let choice = if can_ask {
+        IsolatedChanges::ask(worktree.branch()).await?
+    } else {
+        IsolatedChanges::Patch
+    };
+    match choice {
+        IsolatedChanges::Merge => {
+            worktree.merge().await?;
+            worktree.remove(true).await?;
+            info!("Merged {} into this checkout", worktree.branch());
+        }
+        IsolatedChanges::Patch => {
+            worktree.write_patch(patch_path).await?;
+            worktree.remove(false).await?;
+            info!(
+                "Changes written to {} (apply with: git apply {}); branch {} kept",
+                patch_path.display(),
+                patch_path.display(),
+                worktree.branch()
+            );
+        }
+        IsolatedChanges::Keep => info!(
+            "Isolated worktree kept: {} (branch {})",
+            worktree.path().display(),
+            worktree.branch()
+        ),
+    }
+    Ok(())
+}
+
+/// Announces the end of a run as configured in `[notify]`. Failures are
+/// only logged.
+async fn send_notifications(
+    config: Option<&RxConfig>,
+    root: &std::path::Path,
+    summary: &RunSummary,
+) {
+    let Some(notify) = config.and_then(|cfg| cfg.notify.as_ref()) else {
+        return;
+    };
+    if summary.duration_secs < notify.min_duration_secs.unwrap_or(0) {
+        return;
+    }
+    if notify.desktop == Some(true) {
+        if let Err(error) = summary.show_desktop().await {
+            warn!("desktop notification skipped: {:#}", error);
+        }
+    }
+    if let Some(command) = &notify.command {
+        if let Err(error) = summary.run_command(command, root).await {
+            warn!("{:#}", error);
+        }
+    }
+    if let Some(url) = &notify.webhook {
+        if let Err(error) = summary.post_webhook(url).await {
+            warn!("{:#}", error);
+        }
+    }
+}
+
+/// The base system prompt from `path`, else `LOOP_PROMPT.md` in the launch
+/// directory, else the built-in prompt.
+async fn load_system_prompt(path: Option<&std::path::Path>) -> Result<String> {
+    if let Some(path) = path {
+        return fs::read_to_string(path)
+            .await
+            .with_context(|| format!("failed to read prompt file {}", path.display()));
+    }
+    match fs::read_to_string("LOOP_PROMPT.md").await {
+        Ok(prompt) => Ok(prompt),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            Ok(DEFAULT_SYSTEM_PROMPT.to_string())
+        }
+        Err(error) => Err(error).context("failed to read LOOP_PROMPT.md"),
+    }
+}
+
+/// The workspace discovered from `workdir`, or from the launch directory.
+fn build_workspace(
+    config: Option<&RxConfig>,
+    scope: Option<&std::path::Path>,
+    workdir: Option<&std::path::Path>,
+) -> Result<Arc<Workspace>> {
+    let workspace_config = config.and_then(|cfg| cfg.workspace.as_ref());
+    let launch_dir = std::env::current_dir().context("failed to resolve current directory")?;
+    let cwd = match workdir {
+        Some(dir) => launch_dir
+            .join(dir)
+            .canonicalize()
+            .with_context(|| format!("working directory {} is not accessible", dir.display()))?,
+        None => launch_dir,
+    };
+    let workspace = Workspace::discover(
+        &cwd,
+        workspace_config.and_then(|ws| ws.root.as_deref()),
+        workspace_config
+            .and_then(|ws| ws.allowed_paths.as_deref())
+            .unwrap_or_default(),
+    )
+    .context("failed to initialize workspace root")?;
+    let search_ignore =
+        SearchIgnore::from_config(workspace.root(), config.and_then(|cfg| cfg.search.as_ref()));
+    let workspace = workspace.with_search_ignore(search_ignore);
+    let workspace = match scope {
+        Some(scope) => workspace.with_scope(scope)?,
+        None => workspace,
+    };
+    Ok(Arc::new(workspace))
+}
+
+/// `[env]` from config overlaid with `--env` flags.
+fn resolve_tool_env(
+    config: Option<&RxConfig>,
+    overrides: Vec<(String, String)>,
+) -> BTreeMap<String, String> {
+    let mut env = config.and_then(|cfg| cfg.env.clone()).unwrap_or_default();
+    env.extend(overrides);
+    env
+}
+
+/// Scrubs injected env values, built-in token formats (unless
+/// `[redaction].builtin = false`), and `[redaction].patterns` from events.
+fn build_redactor(
+    config: Option<&RedactionConfig>,
+    env: &BTreeMap<String, String>,
+) -> Result<Redactor> {
+    let mut redactor = Redactor::default().with_env_values(env);
+    if config
+        .and_then(|redaction| redaction.builtin)
+        .unwrap_or(true)
+    {
+        redactor = redactor.with_builtin_patterns();
+    }
+    let patterns = config
+        .and_then(|redaction| redaction.patterns.as_deref())
+        .unwrap_or_default();
+    redactor
+        .with_patterns(patterns)
+        .context("failed to load [redaction].patterns")
+}
+
+fn build_builtin_registry(
+    enabled_tools: &[String],
+    workspace: &Arc<Workspace>,
+    env: &BTreeMap<String, String>,
+    config: Option<&RxConfig>,
+    processes: &Arc<ProcessTable>,
+    sessions: &Arc<PtySessions>,
+) -> ToolRegistry {
+    let mut registry = ToolRegistry::new();
+    let http = config.and_then(|cfg| cfg.http.as_ref());
+    let tools = config.and_then(|cfg| cfg.tools.as_ref());
+    let env_policy = env_policy(config);
+    let patch_fuzz = tools.and_then(|tools| tools.patch_fuzz).unwrap_or(0);
+    // The git tools are offered only inside a repository.
+    let in_git_repo = is_git_repo(workspace.root());
+    for tool_name in enabled_tools {
+        if GIT_TOOLS.contains(&tool_name.as_str()) && !in_git_repo {
+            continue;
+        }
+        match tool_name.as_str() {
+            "exec" => registry.register(Arc::new(
+                ExecTool::default()
+                    .with_env(env.clone())
+                    .with_env_policy(env_policy.clone())
+                    .with_workspace(Arc::clone(workspace)),
+            )),
+            "exec_background" => registry.register(Arc::new(
+                ExecBackgroundTool::new(Arc::clone(processes))
+                    .with_env(env.clone())
+                    .with_env_policy(env_policy.clone())
+                    .with_workspace(Arc::clone(workspace)),
+            )),
+            "process_status" => {
+                registry.register(Arc::new(ProcessStatusTool::new(Arc::clone(processes))))
+            }
+            "process_logs" => {
+                registry.register(Arc::new(ProcessLogsTool::new(Arc::clone(processes))))
+            }
+            "process_kill" => {
+                registry.register(Arc::new(ProcessKillTool::new(Arc::clone(processes))))
+            }
+            "open_session" => registry.register(Arc::new(
+                OpenSessionTool::new(Arc::clone(sessions))
+                    .with_env(env.clone())
+                    .with_env_policy(env_policy.clone())
+                    .with_workspace(Arc::clone(workspace)),
+            )),
+            "send_input" => registry.register(Arc::new(SendInputTool::new(Arc::clone(sessions)))),
+            "read_output" => registry.register(Arc::new(ReadOutputTool::new(Arc::clone(sessions)))),
+            "close_session" => {
+                registry.register(Arc::new(CloseSessionTool::new(Arc::clone(sessions))))
+            }
+            "read_file" => registry.register(Arc::new(ReadFileTool::new(Arc::clone(workspace)))),
+            "write_file" => registry.register(Arc::new(WriteFileTool::new(Arc::clone(workspace)))),
+            "create_file" => {
+                registry.register(Arc::new(CreateFileTool::new(Arc::clone(workspace))))
+            }
+            "append_file" => {
+                registry.register(Arc::new(AppendFileTool::new(Arc::clone(workspace))))
+            }
+            "replace_in_file" => {
+                registry.register(Arc::new(ReplaceInFileTool::new(Arc::clone(workspace))))
+            }
+            "edit_lines" => registry.register(Arc::new(EditLinesTool::new(Arc::clone(workspace)))),
+            "multi_edit" => registry.register(Arc::new(MultiEditTool::new(Arc::clone(workspace)))),
+            "apply_patch" => registry.register(Arc::new(
+                ApplyPatchTool::new(Arc::clone(workspace)).with_fuzz(patch_fuzz),
+            )),
+            "apply_unified_patch" => registry.register(Arc::new(
+                ApplyUnifiedPatchTool::new(Arc::clone(workspace)).with_fuzz(patch_fuzz),
+            )),
+            "list_dir" => registry.register(Arc::new(ListDirTool::new(Arc::clone(workspace)))),
+            "directory_tree" => {
+                registry.register(Arc::new(DirectoryTreeTool::new(Arc::clone(workspace))))
+            }
+            "get_working_dir" => {
+                registry.register(Arc::new(GetWorkingDirTool::new(Arc::clone(workspace))))
+            }
+            "set_working_dir" => {
+                registry.register(Arc::new(SetWorkingDirTool::new(Arc::clone(workspace))))
+            }
+            "find_files" => registry.register(Arc::new(FindFilesTool::new(Arc::clone(workspace)))),
+            "grep_files" => registry.register(Arc::new(GrepFilesTool::new(Arc::clone(workspace)))),
+            "code_search" => {
+                registry.register(Arc::new(CodeSearchTool::new(Arc::clone(workspace))))
+            }
+            "keyword_search" => {
+                registry.register(Arc::new(KeywordSearchTool::new(Arc::clone(workspace))))
+            }
+            "workspace_info" => {
+                registry.register(Arc::new(WorkspaceInfoTool::new(Arc::clone(workspace))))
+            }
+            "remember" => registry.register(Arc::new(RememberTool::new(Arc::clone(workspace)))),
+            "http_request" => {
+                let mut tool =
+                    HttpRequestTool::new(http.and_then(|http| http.allowed_domains.clone()));
+                if let Some(secs) = http.and_then(|http| http.timeout_secs) {
+                    tool = tool.with_timeout(Duration::from_secs(secs));
+                }
+                if let Some(bytes) = http.and_then(|http| http.max_response_bytes) {
+                    tool = tool.with_max_response_bytes(bytes);
+                }
+                registry.register(Arc::new(tool))
+            }
+            "git_status" => registry.register(Arc::new(GitStatusTool::new(Arc::clone(workspace)))),
+            "git_diff" => registry.register(Arc::new(GitDiffTool::new(Arc::clone(workspace)))),
+            "git_log" => registry.register(Arc::new(GitLogTool::new(Arc::clone(workspace)))),
+            "git_commit" => registry.register(Arc::new(GitCommitTool::new(Arc::clone(workspace)))),
+            "git_branch" => registry.register(Arc::new(GitBranchTool::new(Arc::clone(workspace)))),
+            "propose_done" => registry.register(Arc::new(ProposeDoneTool)),
+            _ => {}
+        }
+    }
+    registry
+}
+
+/// Starts goals for `rx serve`, each one built like an `rx run` of the
+/// same options in its workspace root, with that workspace's config.
+struct WorkspaceLauncher {
+    /// Keep each job's `workdir` inside its workspace root, as declared
+    /// `[[serve.workspaces]]` do.
+    confine_workdir: bool,
+}
+
+impl WorkspaceLauncher {
+    /// The `rx run` options of `request`, with its `workdir` resolved
+    /// against the workspace root.
+    fn run_options(
+        &self,
+        workspace: &ServedWorkspace,
+        request: &GoalRequest,
+    ) -> Result<RunOptions> {
+        let mut options = RunOptions::parse_args(&request.args).map_err(|error| {
+            let message = error.to_string();
+            let first_line = message.lines().next().unwrap_or_default();
+            anyhow!("invalid args: {}", first_line.trim_start_matches("error: "))
+        })?;
+        if options.confirm || options.tui {
+            return Err(anyhow!(
+                "{} needs an interactive terminal",
+                if options.confirm {
+                    "--confirm"
+                } else {
+                    "--tui"
+                }
+            ));
+        }
+        let workdir = match request.workdir.as_ref().or(options.workdir.as_ref()) {
+            Some(dir) => workspace.root.join(dir),
+            None => workspace.root.clone(),
+        };
+        let workdir = workdir
+            .canonicalize()
+            .ok()
+            .filter(|dir| dir.is_dir())
+            .ok_or_else(|| anyhow!("working directory {} does not exist", workdir.display()))?;
+        if self.confine_workdir && !workdir.starts_with(&workspace.root) {
+            return Err(anyhow!(
+                "working directory {} is outside workspace {}",
+                workdir.display(),
+                workspace.name
+            ));
+        }
+        options.workdir = Some(workdir);
+        Ok(options)
+    }
+}
+
+#[async_trait]
+impl GoalLauncher for WorkspaceLauncher {
+    async fn launch(
+        &self,
+        workspace: &ServedWorkspace,
+        request: GoalRequest,
+        hook: Arc<dyn EventHook>,
+    ) -> Result<(String, KernelHandle)> {
+        let options = self.run_options(workspace, &request)?;
+        let (kernel, wrap_up) = prepare_goal(
+            options,
+            Session::New(request.goal),
+            Some(load_workspace_config(&workspace.root)),
+            vec![hook],
+            Frontend::Headless,
+        )
+        .await?;
+        let goal_id = wrap_up.goal_id.clone();
+        let handle = kernel.spawn();
+        let mut status = handle.status();
+        tokio::spawn(async move {
+            let _ = status.wait_for(KernelStatus::is_finished).await;
+            if let Err(error) = wrap_up.finish().await {
+                warn!("{:#}", error);
+            }
+        });
+        Ok((goal_id, handle))
+    }
+
+    fn check(&self, workspace: &ServedWorkspace, request: &GoalRequest) -> Result<()> {
+        self.run_options(workspace, request).map(|_| ())
+    }
+}
+
+/// The workspaces of `[[serve.workspaces]]`, or the launch directory as
+/// `default` when none are declared.
+fn served_workspaces(declared: &[ServeWorkspaceConfig]) -> Result<Vec<ServedWorkspace>> {
+    let launch_dir = std::env::current_dir().context("failed to resolve current directory")?;
+    let served = |name: &str, root: PathBuf| ServedWorkspace {
+        name: name.to_string(),
+        state: state_settings(Some(&load_workspace_config(&root))),
+        root,
+    };
+    if declared.is_empty() {
+        return Ok(vec![served(DEFAULT_WORKSPACE, launch_dir)]);
+    }
+    let mut workspaces: Vec<ServedWorkspace> = Vec::new();
+    for entry in declared {
+        let name = entry.name.as_str();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(anyhow!(
+                "invalid [[serve.workspaces]] name '{}': use letters, digits, '-' and '_'",
+                name
+            ));
+        }
+        if workspaces.iter().any(|workspace| workspace.name == name) {
+            return Err(anyhow!("duplicate [[serve.workspaces]] name '{}'", name));
+        }
+        let root = launch_dir
+            .join(&entry.root)
+            .canonicalize()
+            .ok()
+            .filter(|root| root.is_dir())
+            .ok_or_else(|| {
+                anyhow!(
+                    "root {} of workspace '{}' is not a directory",
+                    entry.root.display(),
+                    name
+                )
+            })?;
+        workspaces.push(served(name, root));
+    }
+    Ok(workspaces)
+}
+
+async fn run_serve(host: &str, port: u16, max_concurrent: Option<usize>) -> Result<()> {
+    let config = load_layered_config();
+    let serve_config = config.as_ref().and_then(|cfg| cfg.serve.as_ref());
+    let declared = serve_config
+        .and_then(|serve| serve.workspaces.clone())
+        .unwrap_or_default();
+    let workspaces = served_workspaces(&declared)?;
+    let max_concurrent = max_concurrent
+        .or_else(|| serve_config.and_then(|serve| serve.max_concurrent_goals))
+        .unwrap_or(DEFAULT_MAX_CONCURRENT_GOALS);
+    let listener = tokio::net::TcpListener::bind((host, port))
+        .await
+        .with_context(|| format!("failed to listen on {}:{}", host, port))?;
+    for workspace in &workspaces {
+        info!(
+            "Serving workspace {} from {}",
+            workspace.name,
+            workspace.root.display()
+        );
+    }
+    info!("Running up to {} goal(s) at once", max_concurrent);
+    let data_dir = state_settings(config.as_ref()).data_dir;
+    let token = serve::write_token(&data_dir)?;
+    info!(
+        "API token written to {}",
+        data_dir.join(TOKEN_FILE).display()
     );
+    let launcher = WorkspaceLauncher {
+        confine_workdir: !declared.is_empty(),
+    };
+    let daemon = Daemon::start(Arc::new(launcher), workspaces, max_concurrent);
+    serve::serve(listener, daemon, token).await
+}
 
-    kernel.run().await?;
+/// `rx queue`: enqueue and inspect goals of a running `rx serve`.
+async fn run_queue(
+    server: Option<String>,
+    workspace: Option<String>,
+    command: QueueCommand,
+) -> Result<()> {
+    let server = server
+        .or_else(|| {
+            std::env::var("RX_SERVER")
+                .ok()
+                .filter(|url| !url.trim().is_empty())
+        })
+        .unwrap_or_else(|| DEFAULT_SERVER.to_string());
+    // A server started from this directory left its token in the data dir.
+    let token = std::env::var("RX_SERVE_TOKEN")
+        .ok()
+        .or_else(|| {
+            let data_dir = state_settings(load_layered_config().as_ref()).data_dir;
+            std::fs::read_to_string(data_dir.join(TOKEN_FILE)).ok()
+        })
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty());
+    let mut client = match workspace {
+        Some(name) => QueueClient::new(&server).with_workspace(&name),
+        None => QueueClient::new(&server),
+    };
+    if let Some(token) = &token {
+        client = client.with_token(token);
+    }
+    match command {
+        QueueCommand::Add {
+            goal,
+            workdir,
+            args,
+        } => {
+            // The server resolves paths from its own launch directory.
+            let workdir = workdir
+                .map(|dir| {
+                    dir.canonicalize()
+                        .with_context(|| format!("{} is not accessible", dir.display()))
+                })
+                .transpose()?;
+            let job = client
+                .enqueue(&GoalRequest {
+                    goal: goal.join(" ").trim().to_string(),
+                    args,
+                    workdir,
+                })
+                .await?;
+            println!("Queued {}", job.job_id);
+        }
+        QueueCommand::List { json } => {
+            let jobs = client.list().await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&jobs)?);
+            } else {
+                print!("{}", render_jobs(&jobs));
+            }
+        }
+        QueueCommand::Show { job_id } => {
+            let job = client.get(&job_id).await?;
+            println!("{}", serde_json::to_string_pretty(&job)?);
+        }
+        QueueCommand::Cancel { job_id } => {
+            let job = client.cancel(&job_id).await?;
+            match job.status {
+                JobStatus::Cancelled => println!("Removed {} from the queue", job.job_id),
+                _ => println!(
+                    "Cancelling {} ({})",
+                    job.job_id,
+                    job.goal_id.as_deref().unwrap_or_default()
+                ),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Exports the built-in tools over stdio MCP. Stdout carries protocol
+/// messages only, so diagnostics go to stderr.
+async fn run_mcp_serve() -> Result<()> {
+    let config = load_layered_config();
+    let tool_selection =
+        resolve_enabled_tools(config.as_ref().and_then(|cfg| cfg.tools.as_ref()), None);
+    for warning in &tool_selection.warnings {
+        warn!("{}", warning);
+    }
+
+    let workspace = build_workspace(config.as_ref(), None, None)?;
+    eprintln!(
+        "rx mcp-serve: workspace root {}",
+        workspace.root().display()
+    );
+    let tool_env = resolve_tool_env(config.as_ref(), Vec::new());
+    let processes = Arc::new(ProcessTable::default());
+    let sessions = Arc::new(PtySessions::default());
+    let registry = build_builtin_registry(
+        &tool_selection.enabled_tools,
+        &workspace,
+        &tool_env,
+        config.as_ref(),
+        &processes,
+        &sessions,
+    );
+    mcp_server::serve_stdio(registry).await
+}
+
+async fn list_goals(state: &StateSettings) -> Result<()> {
+    for goal_id in list_stored_goals(state).await? {
+        let events = match load_goal(state, &goal_id).await {
+            Ok(events) => events,
+            Err(error) => {
+                warn!("{:#}", error);
+                continue;
+            }
+        };
+        let summary = GoalSummary::from_events(&events);
+        let started = summary
+            .started
+            .map(|started| started.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        println!(
+            "{}  {}  [{}]  {}",
+            goal_id, started, summary.status, summary.goal
+        );
+    }
+    Ok(())
+}
+
+async fn show_goal(state: &StateSettings, goal_id: &str, as_json: bool) -> Result<()> {
+    let events = load_goal(state, goal_id).await?;
+    if as_json {
+        println!("{}", serde_json::to_string_pretty(&events)?);
+    } else {
+        print!("{}", render_timeline(&events));
+    }
+    Ok(())
+}
+
+async fn show_stats(state: &StateSettings, json: bool) -> Result<()> {
+    let mut goals = Vec::new();
+    for goal_id in list_stored_goals(state).await? {
+        match load_goal(state, &goal_id).await {
+            Ok(events) => goals.push(events),
+            Err(error) => warn!("{:#}", error),
+        }
+    }
+    let stats = Stats::from_goals(&goals);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+    } else {
+        print!("{}", render_stats(&stats));
+    }
+    Ok(())
+}
+
+async fn run_eval(
+    suite_path: &std::path::Path,
+    model: Option<String>,
+    keep: bool,
+    json: bool,
+) -> Result<()> {
+    let suite = EvalSuite::load(suite_path)?;
+    let suite_dir = suite_path
+        .canonicalize()
+        .ok()
+        .and_then(|path| path.parent().map(std::path::Path::to_path_buf))
+        .unwrap_or_default();
+    let options = EvalOptions {
+        rx: std::env::current_exe().context("failed to locate the rx binary")?,
+        suite_dir,
+        model,
+        keep,
+    };
+    let mut results = Vec::new();
+    for (index, task) in suite.tasks.iter().enumerate() {
+        if !json {
+            println!("[{}/{}] {}", index + 1, suite.tasks.len(), task.name);
+        }
+        let result = run_task(task, &options).await?;
+        if keep && !json {
+            println!("  workspace: {}", result.workspace.display());
+        }
+        results.push(result);
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+    } else {
+        print!("\n{}", render_results(&results));
+    }
+    let failed = results.iter().filter(|result| !result.passed).count();
+    if failed > 0 {
+        return Err(anyhow!("{} of {} eval tasks failed", failed, results.len()));
+    }
+    Ok(())
+}
+
+async fn compare_goals(state: &StateSettings, goal_a: &str, goal_b: &str) -> Result<()> {
+    let events_a = load_goal(state, goal_a).await?;
+    let events_b = load_goal(state, goal_b).await?;
+    print!(
+        "{}",
+        render_comparison(goal_a, &events_a, goal_b, &events_b)
+    );
+    Ok(())
+}
+
+async fn run_artifacts(state: &StateSettings, command: ArtifactsCommand) -> Result<()> {
+    let artifacts = ArtifactStore::new(&state.data_dir);
+    match command {
+        ArtifactsCommand::List { goal_id } => {
+            let referenced = match goal_id {
+                Some(goal_id) => Some(referenced_artifacts(&load_goal(state, &goal_id).await?)),
+                None => None,
+            };
+            for artifact in artifacts.list().await? {
+                if referenced
+                    .as_ref()
+                    .is_some_and(|ids| !ids.contains(&artifact.id))
+                {
+                    continue;
+                }
+                println!("{}  {:>10}", artifact.id, artifact.bytes);
+            }
+        }
+        ArtifactsCommand::Get { id, out } => {
+            let content = artifacts
+                .get(&id)
+                .await?
+                .ok_or_else(|| anyhow!("artifact {} not found", id))?;
+            match out {
+                Some(path) => fs::write(&path, content)
+                    .await
+                    .with_context(|| format!("failed to write {}", path.display()))?,
+                None => std::io::stdout()
+                    .write_all(&content)
+                    .context("failed to write to stdout")?,
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn export_goal(
+    state: &StateSettings,
+    goal_id: &str,
+    out: Option<&std::path::Path>,
+) -> Result<()> {
+    let store = open_state_store(state, goal_id, true).await?;
+    let exported = store.export_goal().await?;
+    match out {
+        Some(path) => {
+            fs::write(path, exported)
+                .await
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            eprintln!("Exported {} to {}", goal_id, path.display());
+        }
+        None => print!("{}", exported),
+    }
+    Ok(())
+}
+
+/// Restores files changed by a goal: the latest change, or every change
+/// from snapshot `to` on.
+async fn undo_goal(
+    state: &StateSettings,
+    goal_id: &str,
+    to: Option<u64>,
+    list: bool,
+) -> Result<()> {
+    let store = open_state_store(state, goal_id, true).await?;
+    let events = store.load().await?;
+    let points = snapshot_points(&events);
+    if list {
+        if points.is_empty() {
+            println!("No file changes to undo in {}", goal_id);
+        }
+        for point in &points {
+            println!(
+                "{:>5}  {} [{}]  {}",
+                point.seq,
+                point.name,
+                point.tool_call_id,
+                point.paths().join(", ")
+            );
+        }
+        return Ok(());
+    }
+    let first = match to {
+        Some(seq) => points.iter().position(|point| point.seq >= seq),
+        None => points.len().checked_sub(1),
+    }
+    .ok_or_else(|| anyhow!("no file changes to undo in {}", goal_id))?;
+
+    let workspace = build_workspace(load_layered_config().as_ref(), None, None)?;
+    let artifacts = ArtifactStore::new(&state.data_dir);
+    let summary = restore_snapshots(&workspace, &artifacts, &points[first..]).await?;
+    store
+        .append_event(Event::new("undo", summary.clone()))
+        .await?;
+    for path in summary["restored"].as_array().into_iter().flatten() {
+        println!("restored {}", path.as_str().unwrap_or_default());
+    }
+    for path in summary["deleted"].as_array().into_iter().flatten() {
+        println!("deleted {}", path.as_str().unwrap_or_default());
+    }
+    println!("Undid {} change(s) in {}", points.len() - first, goal_id);
+    Ok(())
+}
+
+async fn import_goal(state: &StateSettings, file: &std::path::Path) -> Result<()> {
+    let contents = fs::read_to_string(file)
+        .await
+        .with_context(|| format!("failed to read {}", file.display()))?;
+    let (goal_id, events) = parse_goal_export(&contents)?;
+    if list_stored_goals(state).await?.contains(&goal_id) {
+        anyhow::bail!("goal '{}' already exists", goal_id);
+    }
+    let count = events.len();
+    open_state_store(state, &goal_id, false)
+        .await?
+        .import_goal(events)
+        .await?;
+    println!("Imported {} ({} events)", goal_id, count);
+    Ok(())
+}
+
+fn state_settings(config: Option<&RxConfig>) -> StateSettings {
+    let state = config.and_then(|cfg| cfg.state.as_ref());
+    StateSettings {
+        backend: state.and_then(|state| state.backend).unwrap_or_default(),
+        data_dir: state
+            .and_then(|state| state.data_dir.clone())
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR)),
+        database_url: std::env::var("RX_DATABASE_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+            .or_else(|| state.and_then(|state| state.database_url.clone())),
+    }
+}
+
+/// State settings for subcommands that only inspect stored goals.
+fn state_config() -> Result<StateSettings> {
+    Ok(state_settings(load_layered_config().as_ref()))
+}
+
+fn build_index(kind: IndexKind) -> Result<()> {
+    let config = load_layered_config();
+    let workspace = build_workspace(config.as_ref(), None, None)?;
+    match kind {
+        IndexKind::Keyword => {
+            let index = KeywordIndex::build(&workspace);
+            let path = keyword_index_path(workspace.root());
+            index.save(&path)?;
+            println!(
+                "Indexed {} files ({} chunks) into {}",
+                index.file_count(),
+                index.chunk_count(),
+                path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn show_config(agent: Option<&str>) -> Result<()> {
+    let config = load_layered_config();
+    let layers: Vec<_> = config_layers()
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect();
+    match (&config, layers.is_empty()) {
+        (Some(_), false) => {
+            for path in &layers {
+                println!("Config: {}", path.display());
+            }
+        }
+        _ => println!("Config: none (using defaults)"),
+    }
+
+    let profiles = agent_profiles(config.as_ref());
+    if !profiles.is_empty() {
+        let names: Vec<&str> = profiles.iter().map(|(name, _)| *name).collect();
+        println!("Agent profiles: {}", names.join(", "));
+    }
+    let agent = agent
+        .map(|name| find_agent(config.as_ref(), name))
+        .transpose()?;
+    if let Some(agent) = agent {
+        println!("Agent profile: {}", agent.name);
+        if let Some(model) = &agent.model {
+            println!("Agent model: {}", model);
+        }
+        if let Some(prompt) = &agent.prompt {
+            println!("Agent prompt: {}", prompt.display());
+        }
+    }
+
+    let tool_selection =
+        resolve_enabled_tools(config.as_ref().and_then(|cfg| cfg.tools.as_ref()), agent);
+    for warning in &tool_selection.warnings {
+        warn!("{}", warning);
+    }
+    println!("Enabled tools: {}", tool_selection.enabled_tools.join(", "));
+    let disabled: Vec<&str> = AVAILABLE_TOOLS
+        .iter()
+        .copied()
+        .filter(|name| !tool_selection.enabled_tools.iter().any(|tool| tool == name))
+        .collect();
+    if !disabled.is_empty() {
+        println!("Disabled tools: {}", disabled.join(", "));
+    }
+
+    let workspace = build_workspace(config.as_ref(), None, None)?;
+    if !is_git_repo(workspace.root())
+        && GIT_TOOLS
+            .iter()
+            .any(|name| tool_selection.enabled_tools.iter().any(|tool| tool == name))
+    {
+        println!("Git tools: not registered (workspace is not a git repository)");
+    }
+    let mcp_servers: Vec<&str> = config
+        .as_ref()
+        .and_then(|cfg| cfg.mcp.as_ref())
+        .map(|mcp| {
+            mcp.servers
+                .iter()
+                .map(|server| server.name.as_str())
+                .collect()
+        })
+        .unwrap_or_default();
+    if !mcp_servers.is_empty() {
+        println!(
+            "MCP servers: {} (tools are listed when a run connects)",
+            mcp_servers.join(", ")
+        );
+    }
+    println!("Workspace root: {}", workspace.root().display());
+    println!(
+        "Search ignore languages: {}",
+        workspace.search_ignore().languages().join(", ")
+    );
+
+    let state = state_settings(config.as_ref());
+    match state.backend {
+        StateBackend::Postgres => println!(
+            "State: postgres ({})",
+            if state.database_url.is_some() {
+                "database url set"
+            } else {
+                "no database url"
+            }
+        ),
+        backend => println!("State: {:?} in {}", backend, state.data_dir.display()),
+    }
+
+    if let Some(config) = &config {
+        println!("{:#?}", config);
+    }
     Ok(())
 }
@@ -0,0 +1,165 @@
+use crate::tool::ToolRegistry;
+use anyhow::Result;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Tools that only make sense inside the kernel loop and are not exported.
+const KERNEL_ONLY_TOOLS: [&str; 1] = ["propose_done"];
+
+/// Serves the registry's tools over newline-delimited JSON-RPC on stdio.
+/// This is a transport: it executes tools directly and never runs the kernel.
+pub async fn serve_stdio(registry: ToolRegistry) -> Result<()> {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => handle_message(&registry, &message).await,
+            Err(error) => Some(error_response(
+                Value::Null,
+                -32700,
+                &format!("parse error: {}", error),
+            )),
+        };
+
+        if let Some(response) = response {
+            let mut serialized = serde_json::to_string(&response)?;
+            serialized.push('\n');
+            stdout.write_all(serialized.as_bytes()).await?;
+            stdout.flush().await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_message(registry: &ToolRegistry, message: &Value) -> Option<Value> {
+    // Notifications carry no id and never get a response.
+    let id = message.get("id")?.clone();
+    let method = message
+        .get("method")
+        .and_then(|value| value.as_str())
+        .unwrap_or_default();
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = match method {
+        "initialize" => json!({
+            "protocolVersion": MCP_PROTOCOL_VERSION,
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "rx", "version": env!("CARGO_PKG_VERSION") }
+        }),
+        "ping" => json!({}),
+        "tools/list" => json!({ "tools": list_tools(registry) }),
+        "tools/call" => match call_tool(registry, &params).await {
+            Ok(result) => result,
+            Err(message) => return Some(error_response(id, -32602, &message)),
+        },
+        other => {
+            return Some(error_response(
+                id,
+                -32601,
+                &format!("method not found: {}", other),
+            ))
+        }
+    };
+
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+fn list_tools(registry: &ToolRegistry) -> Vec<Value> {
+    let mut tools: Vec<Value> = registry
+        .list()
+        .iter()
+        .filter(|tool| !KERNEL_ONLY_TOOLS.contains(&tool.name()))
+        .map(|tool| {
+            json!({
+                "name": tool.name(),
+                "description": tool.description(),
+                "inputSchema": tool.parameters()
+            })
+        })
+        .collect();
+    tools.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+    tools
+}
+
+async fn call_tool(registry: &ToolRegistry, params: &Value) -> Result<Value, String> {
+    let name = params
+        .get("name")
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| "'name' is required".to_string())?;
+    let tool = registry
+        .get(name)
+        .filter(|tool| !KERNEL_ONLY_TOOLS.contains(&tool.name()))
+        .ok_or_else(|| format!("unknown tool: {}", name))?;
+    let arguments = params
+        .get("arguments")
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+
+    let (output, is_error) = match tool.execute(arguments).await {
+        Ok(output) => {
+            let is_error = output.get("error").is_some();
+            (output, is_error)
+        }
+        Err(error) => (json!({ "error": error.to_string() }), true),
+    };
+
+    Ok(json!({
+        "content": [{ "type": "text", "text": output.to_string() }],
+        "structuredContent": output,
+        "isError": is_error
+    }))
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::handle_message;
+    use crate::tool::ToolRegistry;
+    use crate::tools::done::ProposeDoneTool;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn kernel_only_tools_are_hidden_and_notifications_ignored() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(ProposeDoneTool));
+
+        let listed = handle_message(
+            &registry,
+            &json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" }),
+        )
+        .await
+        .expect("request should get a response");
+        assert_eq!(listed["result"]["tools"], json!([]));
+
+        let call = handle_message(
+            &registry,
+            &json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": { "name": "propose_done" } }),
+        )
+        .await
+        .expect("request should get a response");
+        assert_eq!(call["error"]["code"], json!(-32602));
+
+        let notification = handle_message(
+            &registry,
+            &json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        )
+        .await;
+        assert!(notification.is_none());
+    }
+}
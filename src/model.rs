@@ -5,6 +5,12 @@ use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{trace, warn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCall {
@@ -22,6 +28,161 @@ pub enum Action {
 #[async_trait]
 pub trait Model: Send + Sync {
     async fn next_action(&self, history: &[Event]) -> Result<Action>;
+
+    /// Cumulative token usage reported by the provider, if it reports any.
+    fn usage(&self) -> Option<ModelUsage> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ModelUsage {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub cached_input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl ModelUsage {
+    /// Adds the `usage` block of one Responses API reply.
+    pub fn record(&mut self, usage: &Value) {
+        let count = |value: Option<&Value>| value.and_then(|v| v.as_u64()).unwrap_or(0);
+        self.requests += 1;
+        self.input_tokens += count(usage.get("input_tokens"));
+        self.cached_input_tokens += count(usage.pointer("/input_tokens_details/cached_tokens"));
+        self.output_tokens += count(usage.get("output_tokens"));
+    }
+
+    /// Usage added since `earlier` was taken.
+    pub fn since(&self, earlier: &ModelUsage) -> ModelUsage {
+        ModelUsage {
+            requests: self.requests.saturating_sub(earlier.requests),
+            input_tokens: self.input_tokens.saturating_sub(earlier.input_tokens),
+            cached_input_tokens: self
+                .cached_input_tokens
+                .saturating_sub(earlier.cached_input_tokens),
+            output_tokens: self.output_tokens.saturating_sub(earlier.output_tokens),
+        }
+    }
+
+    pub fn add(&mut self, other: &ModelUsage) {
+        self.requests += other.requests;
+        self.input_tokens += other.input_tokens;
+        self.cached_input_tokens += other.cached_input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+
+    /// Share of input tokens served from the provider's prompt cache.
+    pub fn cache_hit_rate(&self) -> f64 {
+        if self.input_tokens == 0 {
+            return 0.0;
+        }
+        self.cached_input_tokens as f64 / self.input_tokens as f64
+    }
+}
+
+/// USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub cached_input: f64,
+    pub output: f64,
+}
+
+/// Published list prices for common models, matched on the longest prefix so
+/// dated snapshots (`gpt-4o-2024-08-06`) resolve to their family.
+const MODEL_PRICES: &[(&str, ModelPricing)] = &[
+    (
+        "gpt-4o",
+        ModelPricing {
+            input: 2.5,
+            cached_input: 1.25,
+            output: 10.0,
+        },
+    ),
+    (
+        "gpt-4o-mini",
+        ModelPricing {
+            input: 0.15,
+            cached_input: 0.075,
+            output: 0.6,
+        },
+    ),
+    (
+        "gpt-4.1",
+        ModelPricing {
+            input: 2.0,
+            cached_input: 0.5,
+            output: 8.0,
+        },
+    ),
+    (
+        "gpt-4.1-mini",
+        ModelPricing {
+            input: 0.4,
+            cached_input: 0.1,
+            output: 1.6,
+        },
+    ),
+    (
+        "gpt-4.1-nano",
+        ModelPricing {
+            input: 0.1,
+            cached_input: 0.025,
+            output: 0.4,
+        },
+    ),
+    (
+        "gpt-5",
+        ModelPricing {
+            input: 1.25,
+            cached_input: 0.125,
+            output: 10.0,
+        },
+    ),
+    (
+        "gpt-5-mini",
+        ModelPricing {
+            input: 0.25,
+            cached_input: 0.025,
+            output: 2.0,
+        },
+    ),
+    (
+        "gpt-5-nano",
+        ModelPricing {
+            input: 0.05,
+            cached_input: 0.005,
+            output: 0.4,
+        },
+    ),
+];
+
+impl ModelPricing {
+    pub fn for_model(model: &str) -> Option<Self> {
+        MODEL_PRICES
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, pricing)| *pricing)
+    }
+
+    /// Estimated USD cost of `usage`; cached input tokens are billed at the
+    /// cached rate instead of the full input rate.
+    pub fn cost(&self, usage: &ModelUsage) -> f64 {
+        let cached = usage.cached_input_tokens.min(usage.input_tokens);
+        let uncached = usage.input_tokens - cached;
+        (uncached as f64 * self.input
+            + cached as f64 * self.cached_input
+            + usage.output_tokens as f64 * self.output)
+            / 1_000_000.0
+    }
+
+    /// USD saved by serving cached input tokens at the cached rate.
+    pub fn cache_savings(&self, usage: &ModelUsage) -> f64 {
+        let cached = usage.cached_input_tokens.min(usage.input_tokens);
+        cached as f64 * (self.input - self.cached_input).max(0.0) / 1_000_000.0
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -40,7 +201,7 @@ struct OpenAIErrorBody {
     code: Option<String>,
 }
 
-fn parse_output_text(response_body: &Value) -> String {
+pub fn parse_output_text(response_body: &Value) -> String {
     if let Some(text) = response_body
         .get("output_text")
         .and_then(|value| value.as_str())
@@ -83,12 +244,56 @@ fn truncate_for_error(input: &str, max_chars: usize) -> String {
     input.chars().take(max_chars).collect::<String>() + "..."
 }
 
+/// How much a reasoning model thinks before it answers (`reasoning.effort`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Minimal,
+    Low,
+    Medium,
+    High,
+}
+
+/// How long the provider keeps the cached request prefix
+/// (`prompt_cache_retention`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PromptCacheRetention {
+    #[serde(rename = "in_memory")]
+    InMemory,
+    #[serde(rename = "24h")]
+    Extended,
+}
+
+/// Optional sampling settings sent with every agent request; unset ones are
+/// left to the provider's defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RequestParams {
+    pub temperature: Option<f64>,
+    pub reasoning_effort: Option<ReasoningEffort>,
+    pub max_output_tokens: Option<u64>,
+    pub parallel_tool_calls: Option<bool>,
+    pub prompt_cache_retention: Option<PromptCacheRetention>,
+}
+
 pub struct OpenAIModel {
     client: Client,
     api_key: String,
     model_name: String,
     tools: Value,
     system_prompt: String,
+    prompt_cache_key: String,
+    response_chaining: bool,
+    params: RequestParams,
+    chain: Mutex<Option<ResponseChain>>,
+    usage: std::sync::Mutex<ModelUsage>,
+}
+
+/// The last stored response and how many history events it already covers,
+/// so the next turn only sends events appended since then.
+#[derive(Debug, Clone)]
+struct ResponseChain {
+    response_id: String,
+    events_covered: usize,
 }
 
 impl OpenAIModel {
@@ -98,8 +303,11 @@ impl OpenAIModel {
         registry: &ToolRegistry,
         system_prompt: String,
     ) -> Self {
-        let tools_json: Vec<Value> = registry
-            .list()
+        // The developer prompt and tool definitions form the request prefix;
+        // keeping them byte-identical across turns lets prompt caching hit.
+        let mut tools = registry.list();
+        tools.sort_by(|a, b| a.name().cmp(b.name()));
+        let tools_json: Vec<Value> = tools
             .iter()
             .map(|tool| {
                 json!({
@@ -111,46 +319,50 @@ impl OpenAIModel {
             })
             .collect();
 
+        let tools = json!(tools_json);
+        let prompt_cache_key = prefix_cache_key(&system_prompt, &tools);
+
         Self {
             client: Client::new(),
             api_key,
             model_name,
-            tools: json!(tools_json),
+            tools,
             system_prompt,
+            prompt_cache_key,
+            response_chaining: false,
+            params: RequestParams::default(),
+            chain: Mutex::new(None),
+            usage: std::sync::Mutex::new(ModelUsage::default()),
         }
     }
 
-    fn events_to_input(&self, history: &[Event]) -> Vec<Value> {
-        let mut input = vec![json!({
-            "role": "developer",
-            "content": self.system_prompt
-        })];
+    pub fn with_request_params(mut self, params: RequestParams) -> Self {
+        self.params = params;
+        self
+    }
 
-        for event in history {
+    /// Enables `previous_response_id` chaining with `store: true`. When off,
+    /// every turn resends the full event history.
+    pub fn with_response_chaining(mut self, enabled: bool) -> Self {
+        self.response_chaining = enabled;
+        self
+    }
+
+    /// Converts events appended after a stored response. The model's own
+    /// actions are already part of that response, and tool results must be
+    /// sent as `function_call_output` items that answer its function calls.
+    fn events_to_delta_input(&self, events: &[Event]) -> Vec<Value> {
+        let mut input = Vec::new();
+        for event in events {
             match event.r#type.as_str() {
                 "goal" => {
                     if let Some(goal) = event.payload.get("goal").and_then(|value| value.as_str()) {
                         input.push(json!({ "role": "user", "content": goal }));
                     }
                 }
-                "action" => {
-                    if let Ok(action) = serde_json::from_value::<Action>(event.payload.clone()) {
-                        match action {
-                            Action::Message(content) => {
-                                input.push(json!({ "role": "assistant", "content": content }));
-                            }
-                            Action::ToolCall(tool_call) => {
-                                input.push(json!({
-                                    "role": "assistant",
-                                    "content": format!(
-                                        "tool_call id={} name={} arguments={}",
-                                        tool_call.id,
-                                        tool_call.name,
-                                        tool_call.arguments
-                                    )
-                                }));
-                            }
-                        }
+                "user_message" => {
+                    if let Some(message) = user_message(event) {
+                        input.push(json!({ "role": "user", "content": message }));
                     }
                 }
                 "tool_output" => {
@@ -160,42 +372,59 @@ impl OpenAIModel {
                         .and_then(|value| value.as_str())
                         .unwrap_or("unknown");
                     let output = event.payload.get("output").cloned().unwrap_or(Value::Null);
-
                     input.push(json!({
-                        "role": "user",
-                        "content": format!(
-                            "tool_output tool_call_id={} output={}",
-                            tool_call_id,
-                            output
-                        )
+                        "type": "function_call_output",
+                        "call_id": tool_call_id,
+                        "output": output.to_string()
                     }));
                 }
                 _ => {}
             }
         }
-
         input
     }
-}
 
-#[async_trait]
-impl Model for OpenAIModel {
-    async fn next_action(&self, history: &[Event]) -> Result<Action> {
-        let endpoint = "https://api.openai.com/v1/responses";
-        let input = self.events_to_input(history);
-
-        let request_body = json!({
+    fn request_body(&self, input: Vec<Value>, previous_response_id: Option<&str>) -> Value {
+        let mut request_body = json!({
             "model": self.model_name,
             "input": input,
             "tools": self.tools,
-            "tool_choice": "auto"
+            "tool_choice": "auto",
+            "prompt_cache_key": self.prompt_cache_key
         });
+        if self.response_chaining {
+            request_body["store"] = json!(true);
+        }
+        if let Some(temperature) = self.params.temperature {
+            request_body["temperature"] = json!(temperature);
+        }
+        if let Some(effort) = self.params.reasoning_effort {
+            request_body["reasoning"] = json!({ "effort": effort });
+        }
+        if let Some(max_output_tokens) = self.params.max_output_tokens {
+            request_body["max_output_tokens"] = json!(max_output_tokens);
+        }
+        if let Some(parallel) = self.params.parallel_tool_calls {
+            request_body["parallel_tool_calls"] = json!(parallel);
+        }
+        if let Some(retention) = self.params.prompt_cache_retention {
+            request_body["prompt_cache_retention"] = json!(retention);
+        }
+        if let Some(previous) = previous_response_id {
+            request_body["previous_response_id"] = json!(previous);
+        }
+        request_body
+    }
+
+    async fn post_response(&self, request_body: &Value) -> Result<Value> {
+        let endpoint = "https://api.openai.com/v1/responses";
+        trace!("model request: {}", request_body);
 
         let response = self
             .client
             .post(endpoint)
             .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&request_body)
+            .json(request_body)
             .send()
             .await
             .context("failed to send request to OpenAI")?;
@@ -246,6 +475,7 @@ impl Model for OpenAIModel {
             .text()
             .await
             .context("failed to read OpenAI response body")?;
+        trace!("model response ({}): {}", request_id, response_text);
 
         let response_body: Value = serde_json::from_str(&response_text).map_err(|error| {
             anyhow!(
@@ -259,50 +489,401 @@ impl Model for OpenAIModel {
             )
         })?;
 
-        if let Some(output_items) = response_body
-            .get("output")
-            .and_then(|value| value.as_array())
-        {
-            for item in output_items {
-                let item_type = item
-                    .get("type")
-                    .and_then(|value| value.as_str())
-                    .unwrap_or_default();
-                if item_type == "function_call" || item_type == "tool_call" {
-                    let id = item
-                        .get("call_id")
-                        .or_else(|| item.get("id"))
-                        .and_then(|value| value.as_str())
-                        .unwrap_or("call-unknown")
-                        .to_string();
-                    let name = item
-                        .get("name")
-                        .or_else(|| item.get("function").and_then(|value| value.get("name")))
-                        .and_then(|value| value.as_str())
-                        .unwrap_or_default()
-                        .to_string();
+        if let Some(usage) = response_body.get("usage") {
+            if let Ok(mut total) = self.usage.lock() {
+                total.record(usage);
+            }
+        }
 
-                    let arguments = match item.get("arguments") {
-                        Some(Value::String(json_text)) => {
-                            serde_json::from_str::<Value>(json_text).unwrap_or(json!({}))
+        Ok(response_body)
+    }
+
+    /// Stateless input for retrying a turn cut off by `max_output_tokens`:
+    /// the text produced so far is replayed, while a cut-off tool call is
+    /// dropped and the model is asked to split it into smaller calls.
+    fn continuation_input(
+        &self,
+        history: &[Event],
+        partial_text: &str,
+        cut_tool_call: bool,
+    ) -> Vec<Value> {
+        let mut input = self.events_to_input(history);
+        if !partial_text.is_empty() {
+            input.push(json!({ "role": "assistant", "content": partial_text }));
+        }
+        let instruction = if cut_tool_call {
+            "Your previous tool call was cut off at the output token limit. Issue it again with smaller arguments, splitting large edits across several calls."
+        } else {
+            "Your previous response was cut off at the output token limit. Continue exactly where it stopped, without repeating text."
+        };
+        input.push(json!({ "role": "user", "content": instruction }));
+        input
+    }
+
+    fn events_to_input(&self, history: &[Event]) -> Vec<Value> {
+        let mut input = vec![json!({
+            "role": "developer",
+            "content": self.system_prompt
+        })];
+
+        for event in history {
+            match event.r#type.as_str() {
+                "goal" => {
+                    if let Some(goal) = event.payload.get("goal").and_then(|value| value.as_str()) {
+                        input.push(json!({ "role": "user", "content": goal }));
+                    }
+                }
+                "user_message" => {
+                    if let Some(message) = user_message(event) {
+                        input.push(json!({ "role": "user", "content": message }));
+                    }
+                }
+                "action" => {
+                    if let Ok(action) = serde_json::from_value::<Action>(event.payload.clone()) {
+                        match action {
+                            Action::Message(content) => {
+                                input.push(json!({ "role": "assistant", "content": content }));
+                            }
+                            Action::ToolCall(tool_call) => {
+                                input.push(json!({
+                                    "role": "assistant",
+                                    "content": format!(
+                                        "tool_call id={} name={} arguments={}",
+                                        tool_call.id,
+                                        tool_call.name,
+                                        tool_call.arguments
+                                    )
+                                }));
+                            }
                         }
-                        Some(value @ Value::Object(_)) => value.clone(),
-                        _ => json!({}),
-                    };
+                    }
+                }
+                "tool_output" => {
+                    let tool_call_id = event
+                        .payload
+                        .get("tool_call_id")
+                        .and_then(|value| value.as_str())
+                        .unwrap_or("unknown");
+                    let output = event.payload.get("output").cloned().unwrap_or(Value::Null);
 
-                    return Ok(Action::ToolCall(ToolCall {
-                        id,
-                        name,
-                        arguments,
+                    input.push(json!({
+                        "role": "user",
+                        "content": format!(
+                            "tool_output tool_call_id={} output={}",
+                            tool_call_id,
+                            output
+                        )
                     }));
                 }
+                _ => {}
+            }
+        }
+
+        input
+    }
+}
+
+#[async_trait]
+impl Model for OpenAIModel {
+    async fn next_action(&self, history: &[Event]) -> Result<Action> {
+        let chain = if self.response_chaining {
+            self.chain.lock().await.clone()
+        } else {
+            None
+        };
+
+        let delta = chain
+            .as_ref()
+            .filter(|chain| chain.events_covered <= history.len())
+            .map(|chain| {
+                (
+                    chain,
+                    self.events_to_delta_input(&history[chain.events_covered..]),
+                )
+            })
+            .filter(|(_, input)| !input.is_empty());
+
+        let response_body = match delta {
+            Some((chain, input)) => {
+                let request_body = self.request_body(input, Some(&chain.response_id));
+                match self.post_response(&request_body).await {
+                    Ok(body) => body,
+                    Err(error) => {
+                        warn!("chained request failed ({}); resending full history", error);
+                        let request_body = self.request_body(self.events_to_input(history), None);
+                        self.post_response(&request_body).await?
+                    }
+                }
+            }
+            None => {
+                let request_body = self.request_body(self.events_to_input(history), None);
+                self.post_response(&request_body).await?
+            }
+        };
+
+        let mut response_body = response_body;
+        let mut partial_text = String::new();
+        for attempt in 1..=MAX_CONTINUATIONS {
+            if !hit_output_limit(&response_body) {
+                break;
+            }
+            let cut_tool_call = has_function_call(&response_body);
+            if !cut_tool_call {
+                partial_text.push_str(&parse_output_text(&response_body));
+            }
+            warn!(
+                "response hit max_output_tokens; requesting continuation ({}/{})",
+                attempt, MAX_CONTINUATIONS
+            );
+            let request_body = self.request_body(
+                self.continuation_input(history, &partial_text, cut_tool_call),
+                None,
+            );
+            response_body = self.post_response(&request_body).await?;
+        }
+
+        if self.response_chaining {
+            *self.chain.lock().await = response_body
+                .get("id")
+                .and_then(|value| value.as_str())
+                .map(|id| ResponseChain {
+                    response_id: id.to_string(),
+                    events_covered: history.len(),
+                });
+        }
+
+        let truncated = hit_output_limit(&response_body);
+        match parse_action(&response_body)? {
+            Action::Message(text) if !partial_text.is_empty() || truncated => {
+                let mut message = partial_text + &text;
+                if truncated {
+                    warn!(
+                        "response still truncated after {} continuations",
+                        MAX_CONTINUATIONS
+                    );
+                    message.push_str("\n[truncated at max_output_tokens]");
+                }
+                Ok(Action::Message(message))
+            }
+            Action::ToolCall(_) if truncated => Err(anyhow!(
+                "tool call arguments truncated at max_output_tokens after {} continuations",
+                MAX_CONTINUATIONS
+            )),
+            action => Ok(action),
+        }
+    }
+
+    fn usage(&self) -> Option<ModelUsage> {
+        self.usage.lock().ok().map(|usage| *usage)
+    }
+}
+
+/// Stable identifier for the static request prefix, so requests sharing it
+/// are routed to the same prompt cache.
+fn prefix_cache_key(system_prompt: &str, tools: &Value) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(system_prompt.as_bytes());
+    hasher.update(tools.to_string().as_bytes());
+    let digest = hasher.finalize();
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("rx-{}", &hex[..16])
+}
+
+/// Continuation requests allowed for one turn cut off by `max_output_tokens`.
+const MAX_CONTINUATIONS: usize = 3;
+
+fn hit_output_limit(response_body: &Value) -> bool {
+    response_body.get("status").and_then(|value| value.as_str()) == Some("incomplete")
+        && response_body
+            .pointer("/incomplete_details/reason")
+            .and_then(|value| value.as_str())
+            == Some("max_output_tokens")
+}
+
+fn has_function_call(response_body: &Value) -> bool {
+    response_body
+        .get("output")
+        .and_then(|value| value.as_array())
+        .is_some_and(|items| {
+            items.iter().any(|item| {
+                matches!(
+                    item.get("type").and_then(|value| value.as_str()),
+                    Some("function_call" | "tool_call")
+                )
+            })
+        })
+}
+
+fn parse_action(response_body: &Value) -> Result<Action> {
+    if let Some(output_items) = response_body
+        .get("output")
+        .and_then(|value| value.as_array())
+    {
+        for item in output_items {
+            let item_type = item
+                .get("type")
+                .and_then(|value| value.as_str())
+                .unwrap_or_default();
+            if item_type == "function_call" || item_type == "tool_call" {
+                let id = item
+                    .get("call_id")
+                    .or_else(|| item.get("id"))
+                    .and_then(|value| value.as_str())
+                    .unwrap_or("call-unknown")
+                    .to_string();
+                let name = item
+                    .get("name")
+                    .or_else(|| item.get("function").and_then(|value| value.get("name")))
+                    .and_then(|value| value.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+
+                let arguments = match item.get("arguments") {
+                    Some(Value::String(json_text)) => {
+                        serde_json::from_str::<Value>(json_text).unwrap_or(json!({}))
+                    }
+                    Some(value @ Value::Object(_)) => value.clone(),
+                    _ => json!({}),
+                };
+
+                return Ok(Action::ToolCall(ToolCall {
+                    id,
+                    name,
+                    arguments,
+                }));
+            }
+        }
+    }
+
+    Ok(Action::Message(parse_output_text(response_body)))
+}
+
+/// Text of a follow-up instruction added to an existing goal.
+fn user_message(event: &Event) -> Option<&str> {
+    event
+        .payload
+        .get("message")
+        .and_then(|value| value.as_str())
+}
+
+/// Retries of a failed request per model when none are configured.
+pub const DEFAULT_MODEL_RETRIES: usize = 2;
+
+/// Models tried in order (`[model].fallback`): requests go to the active
+/// model, retried while they fail with a transient error, and the kernel
+/// calls `switch` to move on once one gives up.
+pub struct FallbackModel {
+    models: Vec<(String, Arc<dyn Model>)>,
+    active: AtomicUsize,
+    retries: usize,
+}
+
+impl FallbackModel {
+    pub fn new(models: Vec<(String, Arc<dyn Model>)>) -> Self {
+        Self {
+            models,
+            active: AtomicUsize::new(0),
+            retries: DEFAULT_MODEL_RETRIES,
+        }
+    }
+
+    pub fn with_retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Makes the next model active and returns the names of the old and
+    /// new one, or `None` when the last model is already active.
+    pub fn switch(&self) -> Option<(String, String)> {
+        let from = self.active.load(Ordering::SeqCst);
+        let to = from + 1;
+        if to >= self.models.len() {
+            return None;
+        }
+        self.active.store(to, Ordering::SeqCst);
+        Some((self.models[from].0.clone(), self.models[to].0.clone()))
+    }
+}
+
+#[async_trait]
+impl Model for FallbackModel {
+    async fn next_action(&self, history: &[Event]) -> Result<Action> {
+        let (name, model) = &self.models[self.active.load(Ordering::SeqCst)];
+        let mut attempt = 0;
+        loop {
+            match model.next_action(history).await {
+                Err(error) if attempt < self.retries && is_transient(&error) => {
+                    attempt += 1;
+                    let delay = Duration::from_secs(1 << attempt);
+                    warn!(
+                        "model {} request failed ({:#}); retry {}/{} in {}s",
+                        name,
+                        error,
+                        attempt,
+                        self.retries,
+                        delay.as_secs()
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
             }
         }
+    }
+
+    fn usage(&self) -> Option<ModelUsage> {
+        let mut total: Option<ModelUsage> = None;
+        for (_, model) in &self.models {
+            if let Some(usage) = model.usage() {
+                total.get_or_insert_with(ModelUsage::default).add(&usage);
+            }
+        }
+        total
+    }
+}
+
+/// Sends `--plan` requests to the planning model (`[model_routes].planning`)
+/// until an approved `plan` event is recorded, then to the executor.
+pub struct PlannerModel {
+    planner: Arc<dyn Model>,
+    executor: Arc<dyn Model>,
+}
+
+impl PlannerModel {
+    pub fn new(planner: Arc<dyn Model>, executor: Arc<dyn Model>) -> Self {
+        Self { planner, executor }
+    }
+}
+
+#[async_trait]
+impl Model for PlannerModel {
+    async fn next_action(&self, history: &[Event]) -> Result<Action> {
+        if history.iter().any(|event| event.r#type == "plan") {
+            self.executor.next_action(history).await
+        } else {
+            self.planner.next_action(history).await
+        }
+    }
 
-        Ok(Action::Message(parse_output_text(&response_body)))
+    fn usage(&self) -> Option<ModelUsage> {
+        match (self.planner.usage(), self.executor.usage()) {
+            (Some(mut planner), Some(executor)) => {
+                planner.add(&executor);
+                Some(planner)
+            }
+            (planner, executor) => planner.or(executor),
+        }
     }
 }
 
+/// Rate limits, server errors, and failed connections, which a later
+/// request may not hit.
+fn is_transient(error: &anyhow::Error) -> bool {
+    let message = format!("{:#}", error);
+    message.contains("failed to send request")
+        || message.contains("status=429")
+        || message.contains("status=5")
+}
+
 pub struct MockModel {
     _system_prompt: String,
     goal: String,
@@ -345,9 +926,12 @@ impl Model for MockModel {
             }),
             2 => Action::ToolCall(ToolCall {
                 id: call_id,
-                name: "done".to_string(),
+                name: "propose_done".to_string(),
                 arguments: json!({
-                    "reason": "mock completion",
+                    "summary": "mock completion",
+                    "outcome": "success",
+                    "evidence": ["hello.txt written", "list_dir shows hello.txt"],
+                    "changes": ["hello.txt: greeting for the goal"],
                     "details": {
                         "goal": self.goal,
                         "goal_slug": self.goal_slug
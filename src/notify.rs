@@ -0,0 +1,477 @@
+use crate::event::Event;
+use crate::runtime_hooks::EventHook;
+use crate::tools::shell::shell_command;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+/// Longest a `[notify]` command or webhook may take.
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How a run ended, as announced by `[notify]`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunSummary {
+    pub goal_id: String,
+    pub goal: String,
+    /// Termination reason, or `failed` when the run ended without one.
+    pub reason: String,
+    pub outcome: Option<String>,
+    pub duration_secs: u64,
+    pub cost_usd: Option<f64>,
+}
+
+impl RunSummary {
+    /// Summarizes the run of `history` that started at `started`.
+    pub fn from_history(goal_id: &str, history: &[Event], started: DateTime<Utc>) -> Self {
+        let termination = history
+            .iter()
+            .rev()
+            .find(|event| event.r#type == "termination" && event.timestamp >= started);
+        let field = |name: &str| {
+            termination
+                .and_then(|event| event.payload.get(name))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        };
+        Self {
+            goal_id: goal_id.to_string(),
+            goal: history
+                .iter()
+                .find(|event| event.r#type == "goal")
+                .and_then(|event| event.payload.get("goal"))
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            reason: field("reason").unwrap_or_else(|| "failed".to_string()),
+            outcome: field("outcome"),
+            duration_secs: (Utc::now() - started).num_seconds().max(0) as u64,
+            cost_usd: termination.and_then(|event| event.payload["cost_usd"].as_f64()),
+        }
+    }
+
+    /// E.g. `rx: done (success) after 12m04s`.
+    pub fn title(&self) -> String {
+        format!(
+            "rx: {}",
+            ending(&self.reason, self.outcome.as_deref(), self.duration_secs)
+        )
+    }
+
+    /// Shows a desktop notification through the platform's notification
+    /// service (D-Bus on Linux and BSD, Notification Center on macOS, toasts
+    /// on Windows).
+    pub async fn show_desktop(&self) -> Result<()> {
+        let mut notification = notify_rust::Notification::new();
+        notification
+            .appname("rx")
+            .summary(&self.title())
+            .body(&format!("{}\n{}", self.goal, self.goal_id));
+        let shown = tokio::task::spawn_blocking(move || notification.show().map(|_| ()));
+        tokio::time::timeout(NOTIFY_TIMEOUT, shown)
+            .await
+            .map_err(|_| anyhow!("desktop notification timed out"))?
+            .context("desktop notification task failed")?
+            .context("failed to show a desktop notification")
+    }
+
+    /// Runs `command` through the platform shell in `dir` with `RX_GOAL_ID`,
+    /// `RX_GOAL`, `RX_REASON`, `RX_OUTCOME`, and `RX_DURATION_SECS` set.
+    pub async fn run_command(&self, command: &str, dir: &Path) -> Result<()> {
+        let mut shell = shell_command(command);
+        shell
+            .current_dir(dir)
+            .env("RX_GOAL_ID", &self.goal_id)
+            .env("RX_GOAL", &self.goal)
+            .env("RX_REASON", &self.reason)
+            .env("RX_OUTCOME", self.outcome.as_deref().unwrap_or(""))
+            .env("RX_DURATION_SECS", self.duration_secs.to_string());
+        run(&mut shell, "[notify].command").await
+    }
+
+    /// POSTs the summary as JSON to `url`.
+    pub async fn post_webhook(&self, url: &str) -> Result<()> {
+        let response = reqwest::Client::new()
+            .post(url)
+            .timeout(NOTIFY_TIMEOUT)
+            .json(self)
+            .send()
+            .await
+            .with_context(|| format!("failed to reach [notify].webhook {}", url))?;
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "[notify].webhook {} answered {}",
+                url,
+                response.status()
+            ));
+        }
+        Ok(())
+    }
+}
+
+async fn run(command: &mut Command, name: &str) -> Result<()> {
+    let output = tokio::time::timeout(NOTIFY_TIMEOUT, command.kill_on_drop(true).output())
+        .await
+        .map_err(|_| anyhow!("{} timed out", name))?
+        .with_context(|| format!("failed to run {}", name))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} failed ({}): {}",
+            name,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// E.g. `done (success) after 12m04s`.
+fn ending(reason: &str, outcome: Option<&str>, duration_secs: u64) -> String {
+    let reason = match outcome {
+        Some(outcome) => format!("{} ({})", reason, outcome),
+        None => reason.to_string(),
+    };
+    format!(
+        "{} after {}m{:02}s",
+        reason,
+        duration_secs / 60,
+        duration_secs % 60
+    )
+}
+
+/// Run lifecycle events `[notifications.webhook]` can post.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LifecycleEvent {
+    /// The run started (or resumed).
+    Start,
+    /// `HEAD` moved: the agent, `--auto-commit`, or a command committed.
+    Commit,
+    /// A tool call returned an error.
+    Error,
+    /// The run ended, including runs that stopped with an error.
+    Termination,
+}
+
+pub const ALL_LIFECYCLE_EVENTS: [LifecycleEvent; 4] = [
+    LifecycleEvent::Start,
+    LifecycleEvent::Commit,
+    LifecycleEvent::Error,
+    LifecycleEvent::Termination,
+];
+
+/// Posts a JSON payload to a webhook as the run starts, commits, hits tool
+/// errors, and ends. Each payload carries a `text` line, so Slack incoming
+/// webhooks can take it as is.
+pub struct LifecycleWebhook {
+    url: String,
+    events: Vec<LifecycleEvent>,
+    goal_id: String,
+    goal: String,
+    started: DateTime<Utc>,
+    dir: Option<PathBuf>,
+    http: reqwest::Client,
+    state: Mutex<WebhookState>,
+}
+
+#[derive(Default)]
+struct WebhookState {
+    started: bool,
+    terminated: bool,
+    head: Option<String>,
+}
+
+impl LifecycleWebhook {
+    pub fn new(url: &str, events: &[LifecycleEvent], goal_id: &str, goal: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            events: events.to_vec(),
+            goal_id: goal_id.to_string(),
+            goal: goal.to_string(),
+            started: Utc::now(),
+            dir: None,
+            http: reqwest::Client::new(),
+            state: Mutex::new(WebhookState::default()),
+        }
+    }
+
+    /// Looks for commits in `dir` instead of the launch directory.
+    pub fn with_dir(mut self, dir: &Path) -> Self {
+        self.dir = Some(dir.to_path_buf());
+        self
+    }
+
+    /// Posts a `failed` termination when the run ended without recording
+    /// one.
+    pub async fn finish(&self, summary: &RunSummary) -> Result<()> {
+        if self.state.lock().await.terminated {
+            return Ok(());
+        }
+        let payload = json!({
+            "reason": summary.reason,
+            "outcome": summary.outcome,
+            "duration_secs": summary.duration_secs,
+            "cost_usd": summary.cost_usd,
+        });
+        self.post(LifecycleEvent::Termination, payload).await
+    }
+
+    fn wants(&self, event: LifecycleEvent) -> bool {
+        self.events.contains(&event)
+    }
+
+    /// The lifecycle event `event` stands for, if any, with its details.
+    fn describe(&self, event: &Event) -> Option<(LifecycleEvent, Value)> {
+        match event.r#type.as_str() {
+            "iteration_started" => Some((LifecycleEvent::Start, json!({}))),
+            "tool_output" => {
+                let output = &event.payload["output"];
+                let error = output.get("error")?;
+                Some((
+                    LifecycleEvent::Error,
+                    json!({
+                        "tool": event.payload["name"],
+                        "tool_call_id": event.payload["tool_call_id"],
+                        "error": error,
+                        "details": output.get("details"),
+                    }),
+                ))
+            }
+            "termination" => Some((
+                LifecycleEvent::Termination,
+                json!({
+                    "reason": event.payload["reason"],
+                    "outcome": event.payload.get("outcome"),
+                    "duration_secs": self.duration_secs(),
+                    "cost_usd": event.payload.get("cost_usd"),
+                }),
+            )),
+            _ => None,
+        }
+    }
+
+    /// The JSON body posted for `event`.
+    fn payload(&self, event: LifecycleEvent, mut details: Value) -> Value {
+        let text = match event {
+            LifecycleEvent::Start => format!("rx started {}: {}", self.goal_id, self.goal),
+            LifecycleEvent::Commit => format!(
+                "rx {} committed {}: {}",
+                self.goal_id,
+                details["commit"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .get(..8)
+                    .unwrap_or_default(),
+                details["subject"].as_str().unwrap_or_default()
+            ),
+            LifecycleEvent::Error => format!(
+                "rx {}: {} failed: {}",
+                self.goal_id,
+                details["tool"].as_str().unwrap_or("tool"),
+                details["error"].as_str().unwrap_or("error")
+            ),
+            LifecycleEvent::Termination => format!(
+                "rx {} ended: {}",
+                self.goal_id,
+                ending(
+                    details["reason"].as_str().unwrap_or("failed"),
+                    details["outcome"].as_str(),
+                    details["duration_secs"].as_u64().unwrap_or_default()
+                )
+            ),
+        };
+        let mut payload = json!({
+            "event": event,
+            "goal_id": self.goal_id,
+            "goal": self.goal,
+            "timestamp": Utc::now(),
+            "text": text,
+        });
+        if let (Some(payload), Some(details)) = (payload.as_object_mut(), details.as_object_mut()) {
+            payload.append(details);
+        }
+        payload
+    }
+
+    fn duration_secs(&self) -> u64 {
+        (Utc::now() - self.started).num_seconds().max(0) as u64
+    }
+
+    async fn post(&self, event: LifecycleEvent, details: Value) -> Result<()> {
+        if event == LifecycleEvent::Termination {
+            self.state.lock().await.terminated = true;
+        }
+        if !self.wants(event) {
+            return Ok(());
+        }
+        let response = self
+            .http
+            .post(&self.url)
+            .timeout(WEBHOOK_TIMEOUT)
+            .json(&self.payload(event, details))
+            .send()
+            .await
+            .with_context(|| format!("failed to reach [notifications.webhook] {}", self.url))?;
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "[notifications.webhook] {} answered {}",
+                self.url,
+                response.status()
+            ));
+        }
+        Ok(())
+    }
+
+    /// `HEAD` and its subject, if the directory is a repository with
+    /// commits.
+    async fn head(&self) -> Option<(String, String)> {
+        let mut git = Command::new("git");
+        git.args(["log", "-1", "--format=%H%n%s"]);
+        if let Some(dir) = &self.dir {
+            git.current_dir(dir);
+        }
+        let output = git.output().await.ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let text = String::from_utf8_lossy(&output.stdout);
+        let (commit, subject) = text
+            .trim_end()
+            .split_once('\n')
+            .unwrap_or((text.trim_end(), ""));
+        Some((commit.to_string(), subject.to_string()))
+    }
+}
+
+/// Longest a `[notifications.webhook]` request may take, so a slow
+/// endpoint only briefly holds up the run.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[async_trait]
+impl EventHook for LifecycleWebhook {
+    async fn on_event(&self, event: &Event) -> Result<()> {
+        if let Some((lifecycle, details)) = self.describe(event) {
+            if lifecycle == LifecycleEvent::Start {
+                let head = if self.wants(LifecycleEvent::Commit) {
+                    self.head().await.map(|(commit, _)| commit)
+                } else {
+                    None
+                };
+                let mut state = self.state.lock().await;
+                if state.started {
+                    return Ok(());
+                }
+                state.started = true;
+                state.head = head;
+            }
+            self.post(lifecycle, details).await?;
+        }
+        if event.r#type == "tool_output" && self.wants(LifecycleEvent::Commit) {
+            let Some((commit, subject)) = self.head().await else {
+                return Ok(());
+            };
+            let mut state = self.state.lock().await;
+            if state.head.as_deref() == Some(commit.as_str()) {
+                return Ok(());
+            }
+            state.head = Some(commit.clone());
+            drop(state);
+            self.post(
+                LifecycleEvent::Commit,
+                json!({ "commit": commit, "subject": subject }),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LifecycleEvent, LifecycleWebhook, RunSummary};
+    use crate::event::Event;
+    use chrono::{Duration, Utc};
+    use serde_json::json;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[tokio::test]
+    async fn summaries_name_this_runs_termination_and_reach_the_command() {
+        let started = Utc::now() - Duration::seconds(125);
+        let mut old_termination = Event::new("termination", json!({ "reason": "interrupted" }));
+        old_termination.timestamp = started - Duration::seconds(60);
+        let mut history = vec![
+            Event::new("goal", json!({ "goal": "fix the build" })),
+            old_termination,
+        ];
+        let summary = RunSummary::from_history("g1", &history, started);
+        assert_eq!(summary.reason, "failed");
+
+        history.push(Event::new(
+            "termination",
+            json!({ "reason": "done", "outcome": "success", "cost_usd": 0.25 }),
+        ));
+        let summary = RunSummary::from_history("g1", &history, started);
+        assert_eq!(summary.goal, "fix the build");
+        assert_eq!(summary.cost_usd, Some(0.25));
+        assert!(summary.duration_secs >= 125);
+        assert!(summary.title().starts_with("rx: done (success) after 2m0"));
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("rx-notify-{}", nanos));
+        std::fs::create_dir_all(&dir).unwrap();
+        summary
+            .run_command("echo \"$RX_GOAL_ID $RX_REASON $RX_OUTCOME\" > seen", &dir)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("seen")).unwrap(),
+            "g1 done success\n"
+        );
+        assert!(summary.run_command("exit 3", &dir).await.is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn lifecycle_payloads_carry_details_and_a_slack_text_line() {
+        let webhook = LifecycleWebhook::new(
+            "http://127.0.0.1:9/hook",
+            &[LifecycleEvent::Error],
+            "g1",
+            "fix the build",
+        );
+        assert!(webhook.wants(LifecycleEvent::Error));
+        assert!(!webhook.wants(LifecycleEvent::Start));
+
+        let failed = Event::new(
+            "tool_output",
+            json!({ "tool_call_id": "c1", "name": "exec", "output": { "error": "timeout" } }),
+        );
+        let (event, details) = webhook.describe(&failed).unwrap();
+        let payload = webhook.payload(event, details);
+        assert_eq!(payload["event"], "error");
+        assert_eq!(payload["tool"], "exec");
+        assert_eq!(payload["text"], "rx g1: exec failed: timeout");
+        let ok = Event::new(
+            "tool_output",
+            json!({ "name": "exec", "output": { "code": 0 } }),
+        );
+        assert!(webhook.describe(&ok).is_none());
+
+        let ended = Event::new(
+            "termination",
+            json!({ "reason": "done", "outcome": "success" }),
+        );
+        let (event, details) = webhook.describe(&ended).unwrap();
+        let payload = webhook.payload(event, details);
+        assert_eq!(payload["goal"], "fix the build");
+        assert_eq!(payload["text"], "rx g1 ended: done (success) after 0m00s");
+    }
+}
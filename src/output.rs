@@ -0,0 +1,85 @@
+use crate::event::Event;
+use crate::model::Action;
+use serde_json::{json, Value};
+
+/// The `--output json` line for `event`, or `None` for events that are not
+/// part of the machine-readable stream.
+pub fn json_line(event: &Event) -> Option<Value> {
+    let payload = &event.payload;
+    let mut line = match event.r#type.as_str() {
+        "goal" => json!({ "type": "goal", "goal": payload["goal"] }),
+        "iteration_started" => json!({
+            "type": "iteration",
+            "iteration": payload["iteration"],
+            "elapsed_ms": payload["elapsed_ms"],
+        }),
+        "action" => match serde_json::from_value::<Action>(payload.clone()).ok()? {
+            Action::Message(message) => json!({ "type": "message", "message": message }),
+            Action::ToolCall(tool_call) => json!({
+                "type": "tool_call",
+                "id": tool_call.id,
+                "name": tool_call.name,
+                "arguments": tool_call.arguments,
+            }),
+        },
+        "tool_output" => json!({
+            "type": "tool_output",
+            "tool_call_id": payload["tool_call_id"],
+            "name": payload["name"],
+            "output": payload["output"],
+        }),
+        "iteration_finished" => json!({
+            "type": "usage",
+            "iteration": payload["iteration"],
+            "duration_ms": payload["duration_ms"],
+            "usage": payload["usage"],
+        }),
+        "termination" => {
+            let mut line = payload.clone();
+            line.as_object_mut()?
+                .insert("type".to_string(), json!("termination"));
+            line
+        }
+        _ => return None,
+    };
+    line["timestamp"] = json!(event.timestamp);
+    Some(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::json_line;
+    use crate::event::Event;
+    use serde_json::json;
+
+    #[test]
+    fn run_events_map_to_typed_json_lines() {
+        let call = json_line(&Event::new(
+            "action",
+            json!({ "ToolCall": { "id": "call-1", "name": "read_file", "arguments": { "path": "a" } } }),
+        ))
+        .unwrap();
+        assert_eq!(call["type"], "tool_call");
+        assert_eq!(call["name"], "read_file");
+        assert_eq!(call["arguments"], json!({ "path": "a" }));
+
+        let usage = json_line(&Event::new(
+            "iteration_finished",
+            json!({ "iteration": 2, "duration_ms": 40, "tool": "read_file", "usage": { "requests": 1 } }),
+        ))
+        .unwrap();
+        assert_eq!(usage["type"], "usage");
+        assert_eq!(usage["usage"]["requests"], 1);
+
+        let termination = json_line(&Event::new(
+            "termination",
+            json!({ "reason": "done", "outcome": "success" }),
+        ))
+        .unwrap();
+        assert_eq!(termination["type"], "termination");
+        assert_eq!(termination["reason"], "done");
+        assert!(termination["timestamp"].is_string());
+
+        assert!(json_line(&Event::new("file_snapshot", json!({}))).is_none());
+    }
+}
@@ -0,0 +1,717 @@
+//! Parsing and hunk application for the `*** Begin Patch` envelope and unified
+//! diffs, shared by the `apply_patch` tools and the standalone binary.
+
+use anyhow::{anyhow, bail, Result};
+use diffy::{Line, Patch};
+use std::path::{Component, Path};
+
+#[derive(Debug)]
+pub enum FileOp {
+    Add {
+        path: String,
+        lines: Vec<String>,
+    },
+    Delete {
+        path: String,
+    },
+    Update {
+        path: String,
+        move_to: Option<String>,
+        hunks: Vec<Hunk>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    pub lines: Vec<HunkLine>,
+}
+
+#[derive(Debug, Clone)]
+pub enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+pub fn parse_patch(input: &str) -> Result<Vec<FileOp>> {
+    let lines: Vec<&str> = input
+        .lines()
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect();
+
+    if lines.is_empty() {
+        bail!("patch is empty")
+    }
+
+    let mut index = 0;
+    require_line(&lines, index, "*** Begin Patch")?;
+    index += 1;
+
+    let mut ops = Vec::new();
+
+    while index < lines.len() {
+        let line = lines[index];
+        if line == "*** End Patch" {
+            index += 1;
+            if index != lines.len() {
+                bail!("unexpected content after *** End Patch")
+            }
+            return Ok(ops);
+        }
+
+        if let Some(path) = line.strip_prefix("*** Add File: ") {
+            validate_relative_path(path)?;
+            index += 1;
+            let mut added = Vec::new();
+            while index < lines.len() {
+                let current = lines[index];
+                if is_file_header(current) || current == "*** End Patch" {
+                    break;
+                }
+                let content = current
+                    .strip_prefix('+')
+                    .ok_or_else(|| anyhow!("invalid add-file line: expected '+' prefix"))?;
+                added.push(content.to_string());
+                index += 1;
+            }
+            ops.push(FileOp::Add {
+                path: path.to_string(),
+                lines: added,
+            });
+            continue;
+        }
+
+        if let Some(path) = line.strip_prefix("*** Delete File: ") {
+            validate_relative_path(path)?;
+            ops.push(FileOp::Delete {
+                path: path.to_string(),
+            });
+            index += 1;
+            continue;
+        }
+
+        if let Some(path) = line.strip_prefix("*** Update File: ") {
+            validate_relative_path(path)?;
+            index += 1;
+
+            let mut move_to = None;
+            if index < lines.len() {
+                if let Some(new_path) = lines[index].strip_prefix("*** Move to: ") {
+                    validate_relative_path(new_path)?;
+                    move_to = Some(new_path.to_string());
+                    index += 1;
+                }
+            }
+
+            let mut hunks = Vec::new();
+            while index < lines.len() {
+                let current = lines[index];
+                if is_file_header(current) || current == "*** End Patch" {
+                    break;
+                }
+                if !current.starts_with("@@") {
+                    bail!("expected hunk header '@@', got: {}", current);
+                }
+
+                index += 1;
+                let mut hunk_lines = Vec::new();
+                while index < lines.len() {
+                    let hline = lines[index];
+                    if hline.starts_with("@@") || is_file_header(hline) || hline == "*** End Patch"
+                    {
+                        break;
+                    }
+                    if hline == "*** End of File" {
+                        index += 1;
+                        break;
+                    }
+                    let mut chars = hline.chars();
+                    let marker = chars
+                        .next()
+                        .ok_or_else(|| anyhow!("empty hunk line is invalid"))?;
+                    let tail: String = chars.collect();
+                    match marker {
+                        ' ' => hunk_lines.push(HunkLine::Context(tail)),
+                        '-' => hunk_lines.push(HunkLine::Remove(tail)),
+                        '+' => hunk_lines.push(HunkLine::Add(tail)),
+                        _ => bail!("invalid hunk line prefix '{}'", marker),
+                    }
+                    index += 1;
+                }
+
+                if hunk_lines.is_empty() {
+                    bail!("empty hunk is invalid")
+                }
+                hunks.push(Hunk { lines: hunk_lines });
+            }
+
+            if hunks.is_empty() {
+                bail!("update operation for '{}' has no hunks", path)
+            }
+
+            ops.push(FileOp::Update {
+                path: path.to_string(),
+                move_to,
+                hunks,
+            });
+            continue;
+        }
+
+        bail!("unknown patch section header: {}", line)
+    }
+
+    bail!("missing *** End Patch")
+}
+
+fn require_line(lines: &[&str], index: usize, expected: &str) -> Result<()> {
+    let found = lines
+        .get(index)
+        .copied()
+        .ok_or_else(|| anyhow!("patch ended early; expected {}", expected))?;
+    if found != expected {
+        bail!("expected '{}', got '{}'", expected, found);
+    }
+    Ok(())
+}
+
+fn is_file_header(line: &str) -> bool {
+    line.starts_with("*** Add File: ")
+        || line.starts_with("*** Delete File: ")
+        || line.starts_with("*** Update File: ")
+}
+
+pub fn validate_relative_path(path: &str) -> Result<()> {
+    if path.trim().is_empty() {
+        bail!("path cannot be empty")
+    }
+
+    let parsed = Path::new(path);
+    if parsed.is_absolute() {
+        bail!("path must be relative: {}", path)
+    }
+
+    for component in parsed.components() {
+        match component {
+            Component::CurDir | Component::Normal(_) => {}
+            Component::ParentDir => bail!("parent path '..' is not allowed: {}", path),
+            _ => bail!("invalid path component in {}", path),
+        }
+    }
+
+    Ok(())
+}
+
+/// One file's section of a `git diff`: the path before and after (`None`
+/// for an added or deleted file) and the unified diff of its content, empty
+/// for a pure rename or mode change.
+#[derive(Debug)]
+pub struct GitFilePatch {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub diff: String,
+}
+
+/// Splits `git diff` output into per-file patches. Text before the first
+/// `diff --git` line (such as a commit message) is ignored. Binary patches
+/// and copies are rejected.
+pub fn parse_git_diff(input: &str) -> Result<Vec<GitFilePatch>> {
+    let mut files = Vec::new();
+    let mut lines = input.split_inclusive('\n').peekable();
+    while let Some(line) = lines.next() {
+        let Some(header) = line.trim_end().strip_prefix("diff --git ") else {
+            continue;
+        };
+        let (header_old, header_new) = split_git_header(header)?;
+        let (mut old_path, mut new_path) = (Some(header_old), Some(header_new));
+        let mut diff = String::new();
+        while let Some(line) = lines.next_if(|line| !line.starts_with("diff --git ")) {
+            let trimmed = line.trim_end_matches(['\r', '\n']);
+            if !diff.is_empty() || trimmed.starts_with("--- ") || trimmed.starts_with("@@") {
+                diff.push_str(line);
+            } else if trimmed.starts_with("new file mode") {
+                old_path = None;
+            } else if trimmed.starts_with("deleted file mode") {
+                new_path = None;
+            } else if let Some(path) = trimmed.strip_prefix("rename from ") {
+                old_path = Some(path.to_string());
+            } else if let Some(path) = trimmed.strip_prefix("rename to ") {
+                new_path = Some(path.to_string());
+            } else if trimmed.starts_with("copy from ") || trimmed.starts_with("copy to ") {
+                bail!("copied files are not supported: {}", header)
+            } else if trimmed.starts_with("Binary files ") || trimmed == "GIT binary patch" {
+                bail!("binary patches are not supported: {}", header)
+            }
+        }
+        for path in old_path.iter().chain(new_path.iter()) {
+            validate_relative_path(path)?;
+        }
+        files.push(GitFilePatch {
+            old_path,
+            new_path,
+            diff,
+        });
+    }
+    if files.is_empty() {
+        bail!("no 'diff --git' sections found")
+    }
+    Ok(files)
+}
+
+/// Paths from `a/<old> b/<new>`; when a path contains ` b/`, the split that
+/// yields matching names wins.
+fn split_git_header(header: &str) -> Result<(String, String)> {
+    if header.starts_with('"') {
+        bail!("quoted paths are not supported: {}", header)
+    }
+    let rest = header
+        .strip_prefix("a/")
+        .ok_or_else(|| anyhow!("malformed diff --git header: {}", header))?;
+    let splits: Vec<(&str, &str)> = rest
+        .match_indices(" b/")
+        .map(|(index, _)| (&rest[..index], &rest[index + 3..]))
+        .collect();
+    splits
+        .iter()
+        .find(|(old, new)| old == new)
+        .or(splits.first())
+        .map(|(old, new)| (old.to_string(), new.to_string()))
+        .ok_or_else(|| anyhow!("malformed diff --git header: {}", header))
+}
+
+/// Where a hunk landed, as 1-based line numbers in the old and new file.
+/// `offset` is the distance from the header position for unified diffs.
+/// `fuzz` counts the context lines ignored at each end of the hunk, and
+/// `ignored_whitespace` is set when lines only matched ignoring whitespace.
+pub struct HunkReport {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub offset: Option<i64>,
+    pub fuzz: usize,
+    pub ignored_whitespace: bool,
+}
+
+/// Highest fuzz level accepted; larger requests are clamped to it.
+pub const MAX_FUZZ: usize = 3;
+
+/// Outcome of one hunk when checking a patch: where it lands, or why it
+/// cannot be placed.
+pub type HunkCheck = std::result::Result<HunkReport, String>;
+
+/// Applies `hunks`, relaxing matching up to `fuzz` (see [`check_hunks`]).
+pub fn apply_hunks(
+    original: &str,
+    hunks: &[Hunk],
+    fuzz: usize,
+) -> Result<(String, Vec<HunkReport>)> {
+    let (output, checks) = check_hunks(original, hunks, fuzz);
+    Ok((output, collect_reports(checks)?))
+}
+
+/// Places every hunk it can, skipping the ones whose context is missing, and
+/// returns the partially patched text with one result per hunk.
+///
+/// With `fuzz` above zero a hunk that does not match exactly may match with
+/// whitespace differences, then with up to `fuzz` context lines ignored at
+/// each end, like GNU patch's `--fuzz`. Matched context lines keep the
+/// file's text.
+pub fn check_hunks(original: &str, hunks: &[Hunk], fuzz: usize) -> (String, Vec<HunkCheck>) {
+    let mut lines: Vec<String> = original
+        .lines()
+        .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
+        .collect();
+    let mut cursor = 0usize;
+    let mut checks: Vec<HunkCheck> = Vec::with_capacity(hunks.len());
+
+    for hunk in hunks {
+        let placed = relaxations(fuzz).find_map(|(level, loose)| {
+            let (front, back) = trim_context(&hunk.lines, level, |line| {
+                matches!(line, HunkLine::Context(_))
+            })?;
+            let trimmed = &hunk.lines[front..hunk.lines.len() - back];
+            let expected_old: Vec<&str> = trimmed
+                .iter()
+                .filter_map(|line| match line {
+                    HunkLine::Context(text) | HunkLine::Remove(text) => Some(text.as_str()),
+                    HunkLine::Add(_) => None,
+                })
+                .collect();
+            let position = find_hunk_match(&lines, &expected_old, cursor, loose)
+                .or_else(|| find_hunk_match(&lines, &expected_old, 0, loose))?;
+            Some((position, level, loose, trimmed, expected_old.len()))
+        });
+        let Some((match_pos, level, loose, trimmed, old_len)) = placed else {
+            checks.push(Err(
+                "could not locate hunk context in target file".to_string()
+            ));
+            continue;
+        };
+
+        let mut matched = lines[match_pos..].iter();
+        let replacement: Vec<String> = trimmed
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(_) => matched.next().cloned(),
+                HunkLine::Remove(_) => matched.next().and(None),
+                HunkLine::Add(text) => Some(text.clone()),
+            })
+            .collect();
+
+        let shift_before: i64 = checks
+            .iter()
+            .flatten()
+            .filter(|report| report.new_start <= match_pos + 1)
+            .map(|report| report.new_lines as i64 - report.old_lines as i64)
+            .sum();
+        checks.push(Ok(HunkReport {
+            old_start: (match_pos as i64 - shift_before + 1).max(1) as usize,
+            old_lines: old_len,
+            new_start: match_pos + 1,
+            new_lines: replacement.len(),
+            offset: None,
+            fuzz: level,
+            ignored_whitespace: loose,
+        }));
+
+        lines.splice(match_pos..(match_pos + old_len), replacement.clone());
+        cursor = match_pos + replacement.len();
+    }
+
+    let mut output = lines.join("\n");
+    if original.ends_with('\n') {
+        output.push('\n');
+    }
+    (output, checks)
+}
+
+/// Applies unified diff hunks the same way `diffy::apply` does (search outward
+/// from the header position, never overlapping an already patched region),
+/// but records where each hunk landed.
+pub fn apply_unified_hunks(
+    base: &str,
+    patch: &Patch<'_, str>,
+    fuzz: usize,
+) -> Result<(String, Vec<HunkReport>)> {
+    let (output, checks) = check_unified_hunks(base, patch, fuzz);
+    Ok((output, collect_reports(checks)?))
+}
+
+/// Like [`apply_unified_hunks`], but skips hunks that cannot be placed and
+/// reports each one instead of stopping at the first failure. `fuzz` relaxes
+/// matching as in [`check_hunks`].
+pub fn check_unified_hunks(
+    base: &str,
+    patch: &Patch<'_, str>,
+    fuzz: usize,
+) -> (String, Vec<HunkCheck>) {
+    let mut image: Vec<(&str, bool)> = base.split_inclusive('\n').map(|l| (l, false)).collect();
+    let mut checks: Vec<HunkCheck> = Vec::with_capacity(patch.hunks().len());
+    let mut shift: i64 = 0;
+
+    for (index, hunk) in patch.hunks().iter().enumerate() {
+        let placed = relaxations(fuzz).find_map(|(level, loose)| {
+            let (front, back) =
+                trim_context(hunk.lines(), level, |line| matches!(line, Line::Context(_)))?;
+            let trimmed = &hunk.lines()[front..hunk.lines().len() - back];
+            let pre: Vec<&str> = trimmed
+                .iter()
+                .filter_map(|line| match line {
+                    Line::Context(text) | Line::Delete(text) => Some(*text),
+                    Line::Insert(_) => None,
+                })
+                .collect();
+            let expected = (hunk.new_range().start().saturating_sub(1) + front).min(image.len());
+            let matches_at = |pos: usize| match image.get(pos..pos + pre.len()) {
+                Some(window) => window
+                    .iter()
+                    .zip(pre.iter())
+                    .all(|((line, patched), want)| !patched && lines_match(line, want, loose)),
+                None => false,
+            };
+            let position = std::iter::once(expected)
+                .chain((1..=image.len()).flat_map(|distance| {
+                    let back = expected.checked_sub(distance);
+                    let forward = Some(expected + distance).filter(|pos| *pos < image.len());
+                    back.into_iter().chain(forward)
+                }))
+                .find(|pos| matches_at(*pos))?;
+            Some((position, expected, level, loose, trimmed, pre.len()))
+        });
+        let Some((position, expected, level, loose, trimmed, pre_len)) = placed else {
+            checks.push(Err(format!("hunk {} could not be applied", index + 1)));
+            continue;
+        };
+
+        let mut matched = image[position..].iter().map(|(line, _)| *line);
+        let post: Vec<&str> = trimmed
+            .iter()
+            .filter_map(|line| match line {
+                Line::Context(_) => matched.next(),
+                Line::Delete(_) => matched.next().and(None),
+                Line::Insert(text) => Some(*text),
+            })
+            .collect();
+
+        checks.push(Ok(HunkReport {
+            old_start: (position as i64 - shift + 1).max(1) as usize,
+            old_lines: pre_len,
+            new_start: position + 1,
+            new_lines: post.len(),
+            offset: Some(position as i64 - expected as i64),
+            fuzz: level,
+            ignored_whitespace: loose,
+        }));
+        shift += post.len() as i64 - pre_len as i64;
+
+        image.splice(
+            position..position + pre_len,
+            post.into_iter().map(|line| (line, true)),
+        );
+    }
+
+    (image.into_iter().map(|(line, _)| line).collect(), checks)
+}
+
+/// The reports of a fully applied patch, or the first hunk's failure.
+fn collect_reports(checks: Vec<HunkCheck>) -> Result<Vec<HunkReport>> {
+    checks
+        .into_iter()
+        .map(|check| check.map_err(|error| anyhow!(error)))
+        .collect()
+}
+
+/// Matching modes to try in order for a fuzz level: exact, then ignoring
+/// whitespace, at each number of ignored context lines up to `fuzz`.
+fn relaxations(fuzz: usize) -> impl Iterator<Item = (usize, bool)> {
+    let fuzz = fuzz.min(MAX_FUZZ);
+    (0..=fuzz).flat_map(move |level| {
+        [(level, false), (level, true)]
+            .into_iter()
+            .filter(move |(_, loose)| fuzz > 0 || !loose)
+    })
+}
+
+/// How many lines to drop from the start and end of a hunk at fuzz `level`:
+/// up to `level` context lines at each end. `None` when the level would
+/// drop nothing beyond the previous one, or would leave no lines to match.
+fn trim_context<T>(
+    lines: &[T],
+    level: usize,
+    is_context: impl Fn(&T) -> bool,
+) -> Option<(usize, usize)> {
+    if level == 0 {
+        return Some((0, 0));
+    }
+    let leading = lines.iter().take_while(|line| is_context(line)).count();
+    if leading == lines.len() {
+        return None;
+    }
+    let trailing = lines
+        .iter()
+        .rev()
+        .take_while(|line| is_context(line))
+        .count();
+    let (front, back) = (leading.min(level), trailing.min(level));
+    (front.max(back) == level).then_some((front, back))
+}
+
+fn lines_match(line: &str, want: &str, ignore_whitespace: bool) -> bool {
+    if ignore_whitespace {
+        line.split_whitespace().eq(want.split_whitespace())
+    } else {
+        line == want
+    }
+}
+
+fn find_hunk_match(
+    lines: &[String],
+    expected_old: &[&str],
+    start: usize,
+    ignore_whitespace: bool,
+) -> Option<usize> {
+    if expected_old.is_empty() {
+        return Some(start.min(lines.len()));
+    }
+    if expected_old.len() > lines.len() || start > lines.len() {
+        return None;
+    }
+
+    let end = lines.len() - expected_old.len();
+    for idx in start..=end {
+        let window = &lines[idx..idx + expected_old.len()];
+        if window
+            .iter()
+            .zip(expected_old.iter())
+            .all(|(a, b)| lines_match(a, b, ignore_whitespace))
+        {
+            return Some(idx);
+        }
+    }
+    None
+}
+
+pub fn normalize_lines(lines: &[String]) -> String {
+    if lines.is_empty() {
+        return String::new();
+    }
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_add_update_delete() {
+        let patch = "*** Begin Patch\n*** Add File: hello.txt\n+Hello\n*** Update File: src/a.rs\n@@\n-old\n+new\n*** Delete File: stale.txt\n*** End Patch\n";
+        let ops = parse_patch(patch).expect("patch should parse");
+        assert_eq!(ops.len(), 3);
+    }
+
+    #[test]
+    fn reject_absolute_path() {
+        let patch = "*** Begin Patch\n*** Add File: /tmp/evil\n+nope\n*** End Patch\n";
+        let err = parse_patch(patch).expect_err("absolute path should fail");
+        assert!(err.to_string().contains("path must be relative"));
+    }
+
+    #[test]
+    fn apply_single_hunk() {
+        let original = "a\nb\nc\n";
+        let hunk = Hunk {
+            lines: vec![
+                HunkLine::Context("a".to_string()),
+                HunkLine::Remove("b".to_string()),
+                HunkLine::Add("x".to_string()),
+                HunkLine::Context("c".to_string()),
+            ],
+        };
+
+        let (out, _) = apply_hunks(original, &[hunk], 0).expect("hunk should apply");
+        assert_eq!(out, "a\nx\nc\n");
+    }
+
+    #[test]
+    fn apply_hunks_reports_line_numbers() {
+        let original = "a\nb\nc\nd\ne\n";
+        let hunks = vec![
+            Hunk {
+                lines: vec![
+                    HunkLine::Context("a".to_string()),
+                    HunkLine::Add("a2".to_string()),
+                ],
+            },
+            Hunk {
+                lines: vec![
+                    HunkLine::Remove("d".to_string()),
+                    HunkLine::Add("x".to_string()),
+                ],
+            },
+        ];
+
+        let (output, reports) = apply_hunks(original, &hunks, 0).expect("hunks should apply");
+        assert_eq!(output, "a\na2\nb\nc\nx\ne\n");
+        assert_eq!((reports[0].old_start, reports[0].new_start), (1, 1));
+        assert_eq!((reports[1].old_start, reports[1].new_start), (4, 5));
+    }
+
+    #[test]
+    fn apply_unified_hunks_reports_offset() {
+        let base = "one\ntwo\nthree\nfour\n";
+        let patch_text = "--- a/f\n+++ b/f\n@@ -1,2 +1,2 @@\n three\n-four\n+FOUR\n";
+        let patch = Patch::from_str(patch_text).expect("patch should parse");
+
+        let (output, reports) = apply_unified_hunks(base, &patch, 0).expect("patch should apply");
+        assert_eq!(output, "one\ntwo\nthree\nFOUR\n");
+        assert_eq!(reports[0].old_start, 3);
+        assert_eq!(reports[0].new_start, 3);
+        assert_eq!(reports[0].offset, Some(2));
+    }
+
+    #[test]
+    fn check_hunks_reports_every_failure_without_stopping() {
+        let base = "one\ntwo\nthree\n";
+        let patch_text =
+            "--- a/f\n+++ b/f\n@@ -1,1 +1,1 @@\n-missing\n+x\n@@ -3,1 +3,1 @@\n-three\n+THREE\n";
+        let patch = Patch::from_str(patch_text).expect("patch should parse");
+
+        let (output, checks) = check_unified_hunks(base, &patch, 0);
+        assert_eq!(output, "one\ntwo\nTHREE\n");
+        assert_eq!(
+            checks[0].as_ref().err().map(String::as_str),
+            Some("hunk 1 could not be applied")
+        );
+        assert_eq!(checks[1].as_ref().map(|report| report.old_start), Ok(3));
+        assert!(apply_unified_hunks(base, &patch, 0).is_err());
+    }
+
+    #[test]
+    fn fuzz_tolerates_whitespace_and_context_drift() {
+        let original = "fn main() {\n    let x = 1;  \n    run(x);\n}\n";
+        let hunk = Hunk {
+            lines: vec![
+                HunkLine::Context("fn main() {".to_string()),
+                HunkLine::Context("  let x = 1;".to_string()),
+                HunkLine::Remove("  run(x);".to_string()),
+                HunkLine::Add("    run(x + 1);".to_string()),
+                HunkLine::Context("}".to_string()),
+            ],
+        };
+        assert!(apply_hunks(original, std::slice::from_ref(&hunk), 0).is_err());
+        let (output, reports) =
+            apply_hunks(original, std::slice::from_ref(&hunk), 1).expect("fuzz 1 should apply");
+        assert_eq!(
+            output,
+            "fn main() {\n    let x = 1;  \n    run(x + 1);\n}\n"
+        );
+        assert_eq!((reports[0].fuzz, reports[0].ignored_whitespace), (0, true));
+
+        let base = "a\nb\nc\nd\n";
+        let patch_text = "--- a/f\n+++ b/f\n@@ -1,4 +1,4 @@\n drifted\n b\n-c\n+C\n d\n";
+        let patch = Patch::from_str(patch_text).expect("patch should parse");
+        assert!(apply_unified_hunks(base, &patch, 0).is_err());
+        let (output, reports) = apply_unified_hunks(base, &patch, 1).expect("fuzz 1 should apply");
+        assert_eq!(output, "a\nb\nC\nd\n");
+        assert_eq!((reports[0].fuzz, reports[0].ignored_whitespace), (1, false));
+        assert_eq!(reports[0].old_start, 2);
+    }
+
+    #[test]
+    fn parse_git_diff_splits_files_with_modes_and_renames() {
+        let diff = "commit message\n\
+diff --git a/new.txt b/new.txt\n\
+new file mode 100644\n\
+index 0000000..3b18e51\n\
+--- /dev/null\n\
++++ b/new.txt\n\
+@@ -0,0 +1 @@\n\
++hello\n\
+diff --git a/src/old name.rs b/src/new name.rs\n\
+similarity index 100%\n\
+rename from src/old name.rs\n\
+rename to src/new name.rs\n\
+diff --git a/gone.txt b/gone.txt\n\
+deleted file mode 100644\n\
+--- a/gone.txt\n\
++++ /dev/null\n\
+@@ -1 +0,0 @@\n\
+-bye\n";
+        let files = parse_git_diff(diff).expect("diff should parse");
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0].old_path, None);
+        assert_eq!(files[0].new_path.as_deref(), Some("new.txt"));
+        assert!(files[0].diff.starts_with("--- /dev/null\n"));
+        assert_eq!(files[1].old_path.as_deref(), Some("src/old name.rs"));
+        assert_eq!(files[1].new_path.as_deref(), Some("src/new name.rs"));
+        assert!(files[1].diff.is_empty());
+        assert_eq!(files[2].new_path, None);
+
+        let patch = Patch::from_str(&files[0].diff).expect("section should parse");
+        let (output, _) = apply_unified_hunks("", &patch, 0).expect("add should apply");
+        assert_eq!(output, "hello\n");
+        assert!(parse_git_diff("diff --git a/x b/x\nGIT binary patch\n").is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use crate::config::{PolicyConfig, PolicyRuleConfig};
+use crate::model::ToolCall;
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    Allow,
+    Deny,
+    Ask,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyDecision {
+    pub action: PolicyAction,
+    /// Index of the matching rule in `[[policy.rules]]`, or `None` for the default.
+    pub rule: Option<usize>,
+}
+
+struct PolicyRule {
+    tool: String,
+    argument: Option<String>,
+    pattern: Option<Regex>,
+    action: PolicyAction,
+}
+
+/// First-match rule list consulted by the kernel before every tool execution.
+pub struct Policy {
+    rules: Vec<PolicyRule>,
+    default_action: PolicyAction,
+}
+
+impl Policy {
+    pub fn from_config(config: &PolicyConfig) -> Result<Self> {
+        let rules = config
+            .rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| {
+                compile_rule(rule)
+                    .with_context(|| format!("invalid [[policy.rules]] #{}", index + 1))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            rules,
+            default_action: config.default.unwrap_or(PolicyAction::Allow),
+        })
+    }
+
+    pub fn has_ask_rules(&self) -> bool {
+        self.default_action == PolicyAction::Ask
+            || self
+                .rules
+                .iter()
+                .any(|rule| rule.action == PolicyAction::Ask)
+    }
+
+    pub fn evaluate(&self, tool_call: &ToolCall) -> PolicyDecision {
+        for (index, rule) in self.rules.iter().enumerate() {
+            if rule.matches(tool_call) {
+                return PolicyDecision {
+                    action: rule.action,
+                    rule: Some(index),
+                };
+            }
+        }
+
+        PolicyDecision {
+            action: self.default_action,
+            rule: None,
+        }
+    }
+}
+
+impl PolicyRule {
+    fn matches(&self, tool_call: &ToolCall) -> bool {
+        if self.tool != "*" && self.tool != tool_call.name {
+            return false;
+        }
+
+        let Some(pattern) = &self.pattern else {
+            return true;
+        };
+
+        let subject = match &self.argument {
+            Some(key) => match tool_call.arguments.get(key) {
+                Some(value) => argument_text(value),
+                None => return false,
+            },
+            None => tool_call.arguments.to_string(),
+        };
+        pattern.is_match(&subject)
+    }
+}
+
+fn compile_rule(rule: &PolicyRuleConfig) -> Result<PolicyRule> {
+    let tool = rule.tool.trim();
+    if tool.is_empty() {
+        return Err(anyhow!("'tool' cannot be empty"));
+    }
+
+    let pattern = rule
+        .pattern
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .context("invalid 'pattern' regex")?;
+
+    Ok(PolicyRule {
+        tool: tool.to_string(),
+        argument: rule.argument.clone(),
+        pattern,
+        action: rule.action,
+    })
+}
+
+fn argument_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(argument_text)
+            .collect::<Vec<String>>()
+            .join(" "),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Policy, PolicyAction};
+    use crate::config::{PolicyConfig, PolicyRuleConfig};
+    use crate::model::ToolCall;
+    use serde_json::json;
+
+    fn rule(
+        tool: &str,
+        argument: Option<&str>,
+        pattern: Option<&str>,
+        action: PolicyAction,
+    ) -> PolicyRuleConfig {
+        PolicyRuleConfig {
+            tool: tool.to_string(),
+            argument: argument.map(str::to_string),
+            pattern: pattern.map(str::to_string),
+            action,
+        }
+    }
+
+    fn call(name: &str, arguments: serde_json::Value) -> ToolCall {
+        ToolCall {
+            id: "call-1".to_string(),
+            name: name.to_string(),
+            arguments,
+        }
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let policy = Policy::from_config(&PolicyConfig {
+            default: None,
+            rules: vec![
+                rule(
+                    "write_file",
+                    Some("path"),
+                    Some("^src/"),
+                    PolicyAction::Allow,
+                ),
+                rule("write_file", None, None, PolicyAction::Deny),
+            ],
+        })
+        .expect("policy should compile");
+
+        let inside = policy.evaluate(&call("write_file", json!({ "path": "src/lib.rs" })));
+        assert_eq!((inside.action, inside.rule), (PolicyAction::Allow, Some(0)));
+
+        let outside = policy.evaluate(&call("write_file", json!({ "path": "README.md" })));
+        assert_eq!(
+            (outside.action, outside.rule),
+            (PolicyAction::Deny, Some(1))
+        );
+    }
+
+    #[test]
+    fn array_arguments_are_joined_for_matching() {
+        let policy = Policy::from_config(&PolicyConfig {
+            default: Some(PolicyAction::Ask),
+            rules: vec![rule(
+                "exec",
+                Some("args"),
+                Some(r"\brm\b"),
+                PolicyAction::Deny,
+            )],
+        })
+        .expect("policy should compile");
+
+        let denied = policy.evaluate(&call(
+            "exec",
+            json!({ "command": "git", "args": ["rm", "x"] }),
+        ));
+        assert_eq!(denied.action, PolicyAction::Deny);
+
+        let fallback = policy.evaluate(&call("exec", json!({ "command": "ls" })));
+        assert_eq!((fallback.action, fallback.rule), (PolicyAction::Ask, None));
+        assert!(policy.has_ask_rules());
+    }
+
+    #[test]
+    fn invalid_pattern_is_rejected() {
+        let result = Policy::from_config(&PolicyConfig {
+            default: None,
+            rules: vec![rule("exec", None, Some("("), PolicyAction::Deny)],
+        });
+        assert!(result.is_err());
+    }
+}
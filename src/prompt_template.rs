@@ -0,0 +1,203 @@
+use crate::tool::ToolRegistry;
+use crate::tools::workspace::Workspace;
+use anyhow::{anyhow, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// Levels below the `{{tree}}` directory that are listed.
+const TREE_MAX_DEPTH: usize = 3;
+/// Entries listed by one `{{tree}}` before the rest is elided.
+const TREE_MAX_ENTRIES: usize = 200;
+
+/// Values substituted into the system prompt at startup.
+pub struct PromptContext<'a> {
+    pub goal: &'a str,
+    pub workspace: &'a Workspace,
+    pub registry: &'a ToolRegistry,
+}
+
+/// Replaces `{{goal}}`, `{{os}}`, `{{git_branch}}`, `{{workspace_root}}`,
+/// `{{tool_list}}`, and `{{tree <dir>}}` in `template`. Any other
+/// placeholder is an error so typos do not reach the model.
+pub fn render_prompt(template: &str, context: &PromptContext) -> Result<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        let placeholder = rest[start + 2..start + end].trim();
+        rendered.push_str(&expand(placeholder, context)?);
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+fn expand(placeholder: &str, context: &PromptContext) -> Result<String> {
+    let (name, argument) = placeholder
+        .split_once(char::is_whitespace)
+        .map_or((placeholder, ""), |(name, argument)| {
+            (name, argument.trim())
+        });
+    match (name, argument) {
+        ("goal", "") => Ok(context.goal.to_string()),
+        ("os", "") => Ok(format!(
+            "{} ({})",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        )),
+        ("git_branch", "") => Ok(git_branch(context.workspace.root())
+            .unwrap_or_else(|| "(not a git repository)".to_string())),
+        ("workspace_root", "") => Ok(context.workspace.root().display().to_string()),
+        ("tool_list", "") => Ok(tool_list(context.registry)),
+        ("tree", dir) => {
+            let dir = if dir.is_empty() { "." } else { dir };
+            let path = context.workspace.resolve(dir)?;
+            Ok(render_tree(context.workspace, &path))
+        }
+        _ => Err(anyhow!(
+            "unknown prompt placeholder '{{{{{}}}}}'; expected goal, os, git_branch, workspace_root, tool_list, or tree <dir>",
+            placeholder
+        )),
+    }
+}
+
+fn git_branch(root: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// One `- name: first sentence of the description` line per tool, by name.
+fn tool_list(registry: &ToolRegistry) -> String {
+    let mut tools = registry.list();
+    tools.sort_by(|a, b| a.name().cmp(b.name()));
+    tools
+        .iter()
+        .map(|tool| {
+            let description = tool.description();
+            let summary = description
+                .split_once(". ")
+                .map_or(description, |(first, _)| first)
+                .trim_end_matches('.');
+            format!("- {}: {}", tool.name(), summary)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// An indented listing of `dir`, honoring the workspace ignore rules and
+/// bounded in depth and size.
+fn render_tree(workspace: &Workspace, dir: &Path) -> String {
+    let mut lines = vec![format!(
+        "{}/",
+        dir.strip_prefix(workspace.root())
+            .ok()
+            .filter(|relative| !relative.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .display()
+    )];
+    let mut remaining = TREE_MAX_ENTRIES;
+    let elided = visit_tree(workspace, dir, 1, &mut remaining, &mut lines);
+    if elided > 0 {
+        lines.push(format!("... ({} more entries)", elided));
+    }
+    lines.join("\n")
+}
+
+/// Appends the entries of `dir` and returns how many were left out once
+/// `remaining` ran out.
+fn visit_tree(
+    workspace: &Workspace,
+    dir: &Path,
+    depth: usize,
+    remaining: &mut usize,
+    lines: &mut Vec<String>,
+) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    let mut entries: Vec<(String, bool)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let is_dir = entry.file_type().ok()?.is_dir();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let relative = entry.path();
+            let relative = relative.strip_prefix(workspace.root()).ok()?;
+            (name != ".git" && !workspace.search_ignore().is_ignored(relative, is_dir))
+                .then_some((name, is_dir))
+        })
+        .collect();
+    entries.sort();
+    let mut elided = 0;
+    for (name, is_dir) in entries {
+        if *remaining == 0 {
+            elided += 1;
+            continue;
+        }
+        *remaining -= 1;
+        let indent = "  ".repeat(depth);
+        if is_dir {
+            lines.push(format!("{}{}/", indent, name));
+            if depth < TREE_MAX_DEPTH {
+                elided += visit_tree(workspace, &dir.join(&name), depth + 1, remaining, lines);
+            }
+        } else {
+            lines.push(format!("{}{}", indent, name));
+        }
+    }
+    elided
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_prompt, PromptContext};
+    use crate::tool::ToolRegistry;
+    use crate::tools::done::ProposeDoneTool;
+    use crate::tools::workspace::Workspace;
+    use std::fs;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn placeholders_are_filled_from_the_environment() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("rx-prompt-template-{}", nanos));
+        fs::create_dir_all(root.join("src/deep/deeper/deepest")).unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+        fs::write(root.join("src/deep/deeper/deepest/hidden.rs"), "").unwrap();
+        let workspace = Workspace::new(&root, &root, &[]).unwrap();
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(ProposeDoneTool));
+
+        let context = PromptContext {
+            goal: "fix the build",
+            workspace: &workspace,
+            registry: &registry,
+        };
+        let rendered = render_prompt(
+            "Goal: {{ goal }}\nOS: {{os}}\nTools:\n{{tool_list}}\n{{tree src}}\n",
+            &context,
+        )
+        .unwrap();
+        assert!(rendered.starts_with("Goal: fix the build\nOS: "));
+        assert!(rendered.contains("- propose_done: "));
+        assert!(rendered.contains("src/\n  deep/\n    deeper/\n      deepest/\n  main.rs\n"));
+        assert!(!rendered.contains("hidden.rs"));
+
+        assert!(render_prompt("{{goal_text}}", &context).is_err());
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,291 @@
+use crate::small_model::{ModelRouter, SmallTask};
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use tracing::warn;
+
+const PULL_REQUEST_INSTRUCTIONS: &str = "Write a pull request for the changes below, made by a coding agent working on the goal. Reply with a title of at most 72 characters on the first line, a blank line, then a Markdown description: what changed and why, and how it can be checked. Do not invent changes that are not in the diff.";
+
+/// Diff characters sent to the small model with the commit list.
+const MAX_PULL_REQUEST_DIFF_CHARS: usize = 12_000;
+
+/// Git config key holding the branch a goal branch started from, so a
+/// resumed run still knows where its pull request goes.
+const BASE_CONFIG_KEY: &str = "rxBase";
+
+/// The `rx/<goal-slug>` branch a `--branch` run commits on.
+#[derive(Debug, Clone)]
+pub struct GoalBranch {
+    root: PathBuf,
+    name: String,
+    /// Branch the goal branch started from, when there was one.
+    base: Option<String>,
+}
+
+/// Title and Markdown body of a goal's pull request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PullRequestText {
+    pub title: String,
+    pub body: String,
+}
+
+impl GoalBranch {
+    /// Switches the checkout at `root` to a new branch `rx/<slug>` (with a
+    /// `-2`, `-3`, ... suffix when taken). A resumed goal stays on its
+    /// `rx/` branch, or switches back to `rx/<slug>`.
+    pub async fn start(root: &Path, slug: &str, resumed: bool) -> Result<Self> {
+        let current = git(root, &["rev-parse", "--abbrev-ref", "HEAD"])
+            .await
+            .context("--branch needs a git repository with at least one commit")?;
+        let exists = |name: String| async move {
+            git(
+                root,
+                &[
+                    "rev-parse",
+                    "--verify",
+                    "--quiet",
+                    &format!("refs/heads/{}", name),
+                ],
+            )
+            .await
+            .is_ok()
+        };
+        let name = if resumed && current.starts_with("rx/") {
+            current.clone()
+        } else if resumed && exists(format!("rx/{}", slug)).await {
+            let name = format!("rx/{}", slug);
+            git(root, &["checkout", "-q", &name]).await?;
+            name
+        } else {
+            let mut name = format!("rx/{}", slug);
+            let mut suffix = 1;
+            while exists(name.clone()).await {
+                suffix += 1;
+                name = format!("rx/{}-{}", slug, suffix);
+            }
+            git(root, &["checkout", "-q", "-b", &name]).await?;
+            if current != "HEAD" {
+                let key = format!("branch.{}.{}", name, BASE_CONFIG_KEY);
+                git(root, &["config", &key, &current]).await?;
+            }
+            name
+        };
+        let key = format!("branch.{}.{}", name, BASE_CONFIG_KEY);
+        let base = git(root, &["config", "--get", &key]).await.ok();
+        Ok(Self {
+            root: root.to_path_buf(),
+            name,
+            base,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// `<base>..HEAD`, or just `HEAD` without a base branch.
+    fn range(&self) -> String {
+        match &self.base {
+            Some(base) => format!("{}..HEAD", base),
+            None => "HEAD".to_string(),
+        }
+    }
+
+    /// Subjects of the commits the goal added, oldest first.
+    pub async fn commits(&self) -> Result<Vec<String>> {
+        let log = git(
+            &self.root,
+            &["log", "--reverse", "--format=%s", &self.range()],
+        )
+        .await?;
+        Ok(log.lines().map(str::to_string).collect())
+    }
+
+    /// Asks the small model for the pull request text, falling back to one
+    /// built from the goal and the commit subjects.
+    pub async fn describe(
+        &self,
+        goal: &str,
+        goal_id: &str,
+        router: Option<&ModelRouter>,
+    ) -> Result<PullRequestText> {
+        let commits = self.commits().await?;
+        let stat = match &self.base {
+            Some(base) => git(&self.root, &["diff", "--stat", &format!("{}...HEAD", base)]).await?,
+            None => String::new(),
+        };
+        let fallback = heuristic_text(goal, goal_id, &commits, &stat);
+        let (Some(router), Some(base)) = (router, &self.base) else {
+            return Ok(fallback);
+        };
+        let diff = git(&self.root, &["diff", &format!("{}...HEAD", base)]).await?;
+        let diff: String = diff.chars().take(MAX_PULL_REQUEST_DIFF_CHARS).collect();
+        let input = format!(
+            "Goal:\n{}\n\nCommits:\n{}\n\nFiles:\n{}\n\nDiff:\n{}",
+            goal,
+            commits.join("\n"),
+            stat,
+            diff
+        );
+        match router
+            .complete(SmallTask::PullRequest, PULL_REQUEST_INSTRUCTIONS, &input)
+            .await
+        {
+            Ok(reply) => Ok(parse_reply(&reply, goal_id).unwrap_or(fallback)),
+            Err(error) => {
+                warn!("pull request description generation failed: {:#}", error);
+                Ok(fallback)
+            }
+        }
+    }
+
+    /// Pushes the branch to `origin` and opens a pull request with the
+    /// `gh` CLI. Returns its URL.
+    pub async fn open_pull_request(&self, text: &PullRequestText) -> Result<String> {
+        git(&self.root, &["push", "-u", "origin", &self.name])
+            .await
+            .with_context(|| format!("failed to push {}", self.name))?;
+        let mut command = Command::new("gh");
+        command
+            .current_dir(&self.root)
+            .args(["pr", "create", "--head", &self.name, "--title"])
+            .arg(&text.title)
+            .arg("--body")
+            .arg(&text.body);
+        if let Some(base) = &self.base {
+            command.arg("--base").arg(base);
+        }
+        let output = command
+            .kill_on_drop(true)
+            .output()
+            .await
+            .context("failed to run gh")?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "gh pr create failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+fn heuristic_text(goal: &str, goal_id: &str, commits: &[String], stat: &str) -> PullRequestText {
+    let title: String = goal
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .take(72)
+        .collect();
+    let mut body = format!("## Goal\n\n{}\n", goal.trim());
+    if !commits.is_empty() {
+        body.push_str("\n## Commits\n\n");
+        for commit in commits {
+            body.push_str(&format!("- {}\n", commit));
+        }
+    }
+    if !stat.is_empty() {
+        body.push_str(&format!("\n```\n{}\n```\n", stat));
+    }
+    body.push_str(&footer(goal_id));
+    PullRequestText { title, body }
+}
+
+/// Splits a small model reply into a title and a body, or `None` when it
+/// has no title.
+fn parse_reply(reply: &str, goal_id: &str) -> Option<PullRequestText> {
+    let reply = reply.trim();
+    let (title, body) = reply.split_once('\n').unwrap_or((reply, ""));
+    let title = title
+        .trim()
+        .trim_start_matches('#')
+        .trim()
+        .trim_start_matches("Title:")
+        .trim()
+        .trim_matches('*')
+        .trim();
+    if title.is_empty() {
+        return None;
+    }
+    Some(PullRequestText {
+        title: title.to_string(),
+        body: format!("{}\n{}", body.trim(), footer(goal_id)),
+    })
+}
+
+fn footer(goal_id: &str) -> String {
+    format!(
+        "\nPrepared by rx from goal `{}` (`rx show {}`).\n",
+        goal_id, goal_id
+    )
+}
+
+async fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("failed to run git")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{git, parse_reply, GoalBranch};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[tokio::test]
+    async fn goal_branches_are_unique_and_describe_their_commits() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let repo = std::env::temp_dir().join(format!("rx-branch-{}", nanos));
+        std::fs::create_dir_all(&repo).unwrap();
+        for args in [
+            &["init", "-q", "-b", "main"][..],
+            &["config", "user.email", "rx@example.com"],
+            &["config", "user.name", "rx"],
+            &["commit", "-q", "--allow-empty", "-m", "init"],
+        ] {
+            git(&repo, args).await.unwrap();
+        }
+
+        let branch = GoalBranch::start(&repo, "fix-build", false).await.unwrap();
+        assert_eq!(branch.name(), "rx/fix-build");
+        std::fs::write(repo.join("build.txt"), "fixed\n").unwrap();
+        git(&repo, &["add", "."]).await.unwrap();
+        git(&repo, &["commit", "-q", "-m", "rx: fix build"])
+            .await
+            .unwrap();
+
+        let resumed = GoalBranch::start(&repo, "fix-build", true).await.unwrap();
+        assert_eq!(resumed.name(), "rx/fix-build");
+        let text = resumed.describe("Fix the build", "g1", None).await.unwrap();
+        assert_eq!(text.title, "Fix the build");
+        assert!(text.body.contains("- rx: fix build\n"));
+        assert!(text.body.contains("build.txt"));
+
+        git(&repo, &["checkout", "-q", "main"]).await.unwrap();
+        let again = GoalBranch::start(&repo, "fix-build", false).await.unwrap();
+        assert_eq!(again.name(), "rx/fix-build-2");
+        assert!(again.commits().await.unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(repo);
+
+        let parsed = parse_reply("Title: **Fix the build**\n\nSwaps the linker.", "g1").unwrap();
+        assert_eq!(parsed.title, "Fix the build");
+        assert!(parsed.body.starts_with("Swaps the linker.\n"));
+    }
+}
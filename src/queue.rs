@@ -0,0 +1,233 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Server `rx queue` talks to when neither `--server` nor `RX_SERVER` is set.
+pub const DEFAULT_SERVER: &str = "http://127.0.0.1:8080";
+
+/// A goal submitted to `rx serve` (`POST /goals`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoalRequest {
+    pub goal: String,
+    /// `rx run` options, e.g. `["--max-iterations", "10"]`.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Directory the goal works in (`rx run --workdir`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workdir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+/// A queued, running, or completed goal of one `rx serve` process.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub job_id: String,
+    /// Name of the served workspace the goal runs in.
+    pub workspace: String,
+    #[serde(flatten)]
+    pub request: GoalRequest,
+    pub status: JobStatus,
+    /// Set once the goal has started.
+    pub goal_id: Option<String>,
+    /// Termination reason of a finished goal, or why the job failed.
+    pub detail: Option<String>,
+    pub queued_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Jobs in submission order. Kept in memory: a restarted server starts
+/// with an empty queue.
+#[derive(Debug, Default)]
+pub struct JobQueue {
+    jobs: Vec<Job>,
+    next_id: u64,
+}
+
+impl JobQueue {
+    pub fn push(&mut self, workspace: &str, request: GoalRequest) -> Job {
+        self.next_id += 1;
+        let job = Job {
+            job_id: format!("job-{}", self.next_id),
+            workspace: workspace.to_string(),
+            request,
+            status: JobStatus::Queued,
+            goal_id: None,
+            detail: None,
+            queued_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+        };
+        self.jobs.push(job.clone());
+        job
+    }
+
+    pub fn get(&self, job_id: &str) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.job_id == job_id)
+    }
+
+    pub fn get_mut(&mut self, job_id: &str) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|job| job.job_id == job_id)
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    /// Marks the oldest queued job running and returns it.
+    pub fn start_next(&mut self) -> Option<Job> {
+        let job = self
+            .jobs
+            .iter_mut()
+            .find(|job| job.status == JobStatus::Queued)?;
+        job.status = JobStatus::Running;
+        job.started_at = Some(Utc::now());
+        Some(job.clone())
+    }
+}
+
+/// Client for the job routes of a running `rx serve` (`rx queue`).
+pub struct QueueClient {
+    base: String,
+    /// Route prefix of the addressed workspace; empty for `default`.
+    prefix: String,
+    /// Bearer token of the server, from its `serve.token`.
+    token: Option<String>,
+    http: Client,
+}
+
+impl QueueClient {
+    pub fn new(server: &str) -> Self {
+        Self {
+            base: server.trim_end_matches('/').to_string(),
+            prefix: String::new(),
+            token: None,
+            http: Client::new(),
+        }
+    }
+
+    /// Addresses the workspace `name` of a server with `[[serve.workspaces]]`.
+    pub fn with_workspace(mut self, name: &str) -> Self {
+        self.prefix = format!("/workspaces/{}", name);
+        self
+    }
+
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    fn url(&self, route: &str) -> String {
+        format!("{}{}{}", self.base, self.prefix, route)
+    }
+
+    pub async fn enqueue(&self, request: &GoalRequest) -> Result<Job> {
+        self.send(self.http.post(self.url("/goals")).json(request))
+            .await
+    }
+
+    pub async fn list(&self) -> Result<Vec<Job>> {
+        self.send(self.http.get(self.url("/jobs"))).await
+    }
+
+    pub async fn get(&self, job_id: &str) -> Result<Job> {
+        self.send(self.http.get(self.url(&format!("/jobs/{}", job_id))))
+            .await
+    }
+
+    pub async fn cancel(&self, job_id: &str) -> Result<Job> {
+        self.send(
+            self.http
+                .post(self.url(&format!("/jobs/{}/cancel", job_id))),
+        )
+        .await
+    }
+
+    async fn send<T: serde::de::DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T> {
+        let request = match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("failed to reach rx serve at {}", self.base))?;
+        let status = response.status();
+        let body: Value = response
+            .json()
+            .await
+            .context("invalid response from rx serve")?;
+        if !status.is_success() {
+            let message = body["error"].as_str().unwrap_or("request failed");
+            return Err(anyhow!("{} ({})", message, status));
+        }
+        serde_json::from_value(body).context("unexpected response from rx serve")
+    }
+}
+
+/// One row per job, oldest first.
+pub fn render_jobs(jobs: &[Job]) -> String {
+    let mut out = format!(
+        "{:<8} {:<10} {:<40} {}\n",
+        "job", "status", "goal_id", "goal"
+    );
+    for job in jobs {
+        out.push_str(&format!(
+            "{:<8} {:<10} {:<40} {}\n",
+            job.job_id,
+            serde_json::to_value(job.status)
+                .ok()
+                .and_then(|status| status.as_str().map(str::to_string))
+                .unwrap_or_default(),
+            job.goal_id.as_deref().unwrap_or("-"),
+            job.request.goal
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_jobs, GoalRequest, JobQueue, JobStatus};
+
+    #[test]
+    fn jobs_start_in_submission_order_and_render() {
+        let mut queue = JobQueue::default();
+        for goal in ["fix the build", "add tests"] {
+            queue.push(
+                "default",
+                GoalRequest {
+                    goal: goal.to_string(),
+                    args: Vec::new(),
+                    workdir: None,
+                },
+            );
+        }
+        let first = queue.start_next().unwrap();
+        assert_eq!(first.job_id, "job-1");
+        assert_eq!(queue.get("job-1").unwrap().status, JobStatus::Running);
+        queue.get_mut("job-1").unwrap().goal_id = Some("20260101-fix".to_string());
+        assert_eq!(queue.start_next().unwrap().job_id, "job-2");
+        assert!(queue.start_next().is_none());
+
+        let table = render_jobs(queue.jobs());
+        assert!(table.contains("job-1    running    20260101-fix"));
+        assert!(table
+            .ends_with("job-2    running    -                                        add tests\n"));
+    }
+}
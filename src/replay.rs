@@ -0,0 +1,237 @@
+use crate::event::Event;
+use crate::model::{Action, Model, ModelUsage};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// One `next_action` exchange, a line of a `--record` fixture.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exchange {
+    /// Events added to the history since the previous request.
+    pub events: Vec<Event>,
+    pub response: Action,
+    /// Tokens the request used, when the model reports usage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ModelUsage>,
+}
+
+/// Wraps a model and appends every request/response pair to a JSONL
+/// fixture (`--record`), written as the run goes so an interrupted run
+/// still leaves a usable prefix.
+pub struct RecordingModel {
+    inner: Arc<dyn Model>,
+    path: PathBuf,
+    state: Mutex<RecordingState>,
+}
+
+struct RecordingState {
+    file: File,
+    seen: usize,
+}
+
+impl RecordingModel {
+    pub fn create(inner: Arc<dyn Model>, path: &Path) -> Result<Self> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)
+            .with_context(|| format!("failed to create fixture {}", path.display()))?;
+        Ok(Self {
+            inner,
+            path: path.to_path_buf(),
+            state: Mutex::new(RecordingState { file, seen: 0 }),
+        })
+    }
+}
+
+#[async_trait]
+impl Model for RecordingModel {
+    async fn next_action(&self, history: &[Event]) -> Result<Action> {
+        let mut state = self.state.lock().await;
+        let usage_before = self.inner.usage();
+        let response = self.inner.next_action(history).await?;
+        let usage = match (self.inner.usage(), usage_before) {
+            (Some(after), Some(before)) => Some(after.since(&before)),
+            (after, _) => after,
+        };
+        let exchange = Exchange {
+            events: history[state.seen.min(history.len())..].to_vec(),
+            response: response.clone(),
+            usage,
+        };
+        state.seen = history.len();
+        let line = serde_json::to_string(&exchange)?;
+        writeln!(state.file, "{}", line)
+            .and_then(|_| state.file.flush())
+            .with_context(|| format!("failed to write fixture {}", self.path.display()))?;
+        Ok(response)
+    }
+
+    fn usage(&self) -> Option<ModelUsage> {
+        self.inner.usage()
+    }
+}
+
+/// Serves the responses of a `--record` fixture back in order
+/// (`--replay`), without a provider. A request whose new events differ in
+/// type from the recording fails the run, so behavior changes surface as
+/// errors instead of silently different transcripts.
+pub struct ReplayModel {
+    exchanges: Vec<Exchange>,
+    state: Mutex<ReplayState>,
+}
+
+#[derive(Default)]
+struct ReplayState {
+    next: usize,
+    seen: usize,
+    usage: Option<ModelUsage>,
+}
+
+impl ReplayModel {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read fixture {}", path.display()))?;
+        let exchanges = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).with_context(|| {
+                    format!(
+                        "invalid exchange on line {} of {}",
+                        index + 1,
+                        path.display()
+                    )
+                })
+            })
+            .collect::<Result<Vec<Exchange>>>()?;
+        Ok(Self::new(exchanges))
+    }
+
+    pub fn new(exchanges: Vec<Exchange>) -> Self {
+        Self {
+            exchanges,
+            state: Mutex::new(ReplayState::default()),
+        }
+    }
+
+    /// Model requests the fixture can answer.
+    pub fn requests(&self) -> usize {
+        self.exchanges.len()
+    }
+}
+
+#[async_trait]
+impl Model for ReplayModel {
+    async fn next_action(&self, history: &[Event]) -> Result<Action> {
+        let mut state = self.state.lock().await;
+        let exchange = self.exchanges.get(state.next).ok_or_else(|| {
+            anyhow!(
+                "replay fixture exhausted: the run made more than {} model requests",
+                self.exchanges.len()
+            )
+        })?;
+        let expected: Vec<&str> = exchange
+            .events
+            .iter()
+            .map(|event| event.r#type.as_str())
+            .collect();
+        let actual: Vec<&str> = history[state.seen.min(history.len())..]
+            .iter()
+            .map(|event| event.r#type.as_str())
+            .collect();
+        if expected != actual {
+            return Err(anyhow!(
+                "replay diverged at request {}: recorded events {:?}, got {:?}",
+                state.next + 1,
+                expected,
+                actual
+            ));
+        }
+        state.next += 1;
+        state.seen = history.len();
+        if let Some(usage) = exchange.usage {
+            let total = state.usage.get_or_insert_with(ModelUsage::default);
+            total.requests += usage.requests;
+            total.input_tokens += usage.input_tokens;
+            total.cached_input_tokens += usage.cached_input_tokens;
+            total.output_tokens += usage.output_tokens;
+        }
+        Ok(exchange.response.clone())
+    }
+
+    fn usage(&self) -> Option<ModelUsage> {
+        self.state.try_lock().ok().and_then(|state| state.usage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RecordingModel, ReplayModel};
+    use crate::event::Event;
+    use crate::model::{Action, MockModel, Model};
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[tokio::test]
+    async fn recorded_run_replays_and_divergence_fails() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("rx-replay-{}/run.jsonl", nanos));
+        let mock = MockModel::new(String::new(), "note".to_string(), "note".to_string());
+        let recorder = RecordingModel::create(Arc::new(mock), &path).unwrap();
+
+        let mut history = vec![Event::new("goal", json!({ "goal": "note" }))];
+        let mut recorded = Vec::new();
+        for _ in 0..3 {
+            let action = recorder.next_action(&history).await.unwrap();
+            history.push(Event::new("action", serde_json::to_value(&action).unwrap()));
+            history.push(Event::new("tool_output", json!({ "output": {} })));
+            recorded.push(action);
+        }
+
+        let replay = ReplayModel::load(&path).unwrap();
+        assert_eq!(replay.requests(), 3);
+        let mut replayed_history = vec![Event::new("goal", json!({ "goal": "note" }))];
+        for action in &recorded {
+            let replayed = replay.next_action(&replayed_history).await.unwrap();
+            assert_eq!(
+                serde_json::to_value(&replayed).unwrap(),
+                serde_json::to_value(action).unwrap()
+            );
+            replayed_history.push(Event::new("action", json!({})));
+            replayed_history.push(Event::new("tool_output", json!({})));
+        }
+        let exhausted = replay.next_action(&replayed_history).await.unwrap_err();
+        assert!(exhausted.to_string().contains("exhausted"));
+
+        let diverging = ReplayModel::load(&path).unwrap();
+        let wrong_start = vec![Event::new("user_message", json!({ "message": "hi" }))];
+        let error = diverging.next_action(&wrong_start).await.unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("replay diverged at request 1"));
+        assert!(matches!(
+            recorded[2],
+            Action::ToolCall(ref call) if call.name == "propose_done"
+        ));
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
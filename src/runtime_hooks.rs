@@ -1,15 +1,22 @@
 use crate::event::Event;
 use crate::model::Action;
+use crate::output::json_line;
+use crate::redact::Redactor;
+use crate::small_model::{ModelRouter, SmallTask};
 use crate::state::StateStore;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use serde::Deserialize;
 use serde_json::to_string;
-use std::path::Path;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::fs::{create_dir_all, File, OpenOptions};
 use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
 use tokio::sync::Mutex;
+use tracing::{info, warn};
 
 #[async_trait]
 pub trait EventHook: Send + Sync {
@@ -19,11 +26,22 @@ pub trait EventHook: Send + Sync {
 pub struct HookedStateStore {
     inner: Arc<dyn StateStore>,
     hooks: Vec<Arc<dyn EventHook>>,
+    redactor: Redactor,
 }
 
 impl HookedStateStore {
     pub fn new(inner: Arc<dyn StateStore>, hooks: Vec<Arc<dyn EventHook>>) -> Self {
-        Self { inner, hooks }
+        Self {
+            inner,
+            hooks,
+            redactor: Redactor::default(),
+        }
+    }
+
+    /// Redacts event payloads before they are stored or passed to hooks.
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        self
     }
 }
 
@@ -33,16 +51,19 @@ impl StateStore for HookedStateStore {
         self.inner.load().await
     }
 
-    async fn append_event(&self, event: Event) -> Result<()> {
-        self.inner.append_event(event.clone()).await?;
+    async fn append_event(&self, mut event: Event) -> Result<Event> {
+        if !self.redactor.is_empty() {
+            event.payload = self.redactor.redact_value(&event.payload);
+        }
+        let event = self.inner.append_event(event).await?;
 
         for hook in &self.hooks {
             if let Err(error) = hook.on_event(&event).await {
-                eprintln!("Warning: event hook failed: {}", error);
+                warn!("event hook failed: {}", error);
             }
         }
 
-        Ok(())
+        Ok(event)
     }
 }
 
@@ -83,6 +104,22 @@ impl EventHook for DebugJsonlHook {
     }
 }
 
+/// Prints the run's events as newline-delimited JSON on stdout
+/// (`--output json`).
+pub struct JsonOutputHook;
+
+#[async_trait]
+impl EventHook for JsonOutputHook {
+    async fn on_event(&self, event: &Event) -> Result<()> {
+        if let Some(line) = json_line(event) {
+            let mut stdout = std::io::stdout().lock();
+            writeln!(stdout, "{}", line)?;
+            stdout.flush()?;
+        }
+        Ok(())
+    }
+}
+
 pub struct ToolVerboseHook;
 
 #[async_trait]
@@ -93,10 +130,10 @@ impl EventHook for ToolVerboseHook {
                 if let Ok(action) = serde_json::from_value::<Action>(event.payload.clone()) {
                     match action {
                         Action::Message(message) => {
-                            println!("tool-verbose action message: {}", message);
+                            info!("tool-verbose action message: {}", message);
                         }
                         Action::ToolCall(tool_call) => {
-                            println!(
+                            info!(
                                 "tool-verbose tool input {} [{}]: {}",
                                 tool_call.name, tool_call.id, tool_call.arguments
                             );
@@ -111,7 +148,7 @@ impl EventHook for ToolVerboseHook {
                         .get("output")
                         .cloned()
                         .unwrap_or(serde_json::Value::Null);
-                    println!("tool-verbose tool output {}: {}", name, output);
+                    info!("tool-verbose tool output {}: {}", name, output);
                 }
             }
             _ => {}
@@ -125,56 +162,402 @@ pub trait CommitMessageGenerator: Send + Sync {
     async fn commit_message(&self, diff: &str) -> Result<String>;
 }
 
-pub struct HeuristicCommitMessageGenerator;
+/// Names the first changed file, or with `conventional` builds a
+/// `type(scope): subject` line from the changed paths.
+#[derive(Default)]
+pub struct HeuristicCommitMessageGenerator {
+    conventional: bool,
+}
+
+impl HeuristicCommitMessageGenerator {
+    pub fn with_conventional(mut self, conventional: bool) -> Self {
+        self.conventional = conventional;
+        self
+    }
+}
 
 #[async_trait]
 impl CommitMessageGenerator for HeuristicCommitMessageGenerator {
     async fn commit_message(&self, diff: &str) -> Result<String> {
-        let first_path = diff
-            .lines()
-            .find_map(|line| line.strip_prefix("+++ b/"))
-            .filter(|path| !path.is_empty() && *path != "/dev/null");
-
-        Ok(match first_path {
-            Some(path) => format!("rx: update {}", path),
+        let files = diff_files(diff);
+        if self.conventional {
+            return Ok(conventional_message(&files));
+        }
+        Ok(match files.first() {
+            Some(file) => format!("rx: update {}", file.path),
             None => "rx: update".to_string(),
         })
     }
 }
 
+/// Asks the small model for a commit message, falling back to the heuristic
+/// message when the call fails or the budget is spent.
+pub struct SmallModelCommitMessageGenerator {
+    router: Arc<ModelRouter>,
+    conventional: bool,
+}
+
+impl SmallModelCommitMessageGenerator {
+    pub fn new(router: Arc<ModelRouter>) -> Self {
+        Self {
+            router,
+            conventional: false,
+        }
+    }
+
+    /// Asks for Conventional Commits subjects (`type(scope): subject`).
+    pub fn with_conventional(mut self, conventional: bool) -> Self {
+        self.conventional = conventional;
+        self
+    }
+}
+
+/// The router's commit message generator, or the heuristic one without a
+/// router.
+pub fn commit_message_generator(
+    router: Option<&Arc<ModelRouter>>,
+    conventional: bool,
+) -> Arc<dyn CommitMessageGenerator> {
+    match router {
+        Some(router) => Arc::new(
+            SmallModelCommitMessageGenerator::new(Arc::clone(router))
+                .with_conventional(conventional),
+        ),
+        None => {
+            Arc::new(HeuristicCommitMessageGenerator::default().with_conventional(conventional))
+        }
+    }
+}
+
+const COMMIT_DIFF_MAX_CHARS: usize = 12_000;
+
+const COMMIT_INSTRUCTIONS: &str = "Write a one-line git commit subject (at most 72 characters) describing this staged diff. Reply with the subject only.";
+
+const CONVENTIONAL_COMMIT_INSTRUCTIONS: &str = "Write a one-line Conventional Commits subject (at most 72 characters) for this staged diff: `type(scope): subject`, where type is one of feat, fix, docs, test, refactor, perf, build, ci, style, or chore, scope is the main area touched (omit it with its parentheses when there is none), and subject is imperative and lowercase without a trailing period. Reply with the subject only.";
+
+#[async_trait]
+impl CommitMessageGenerator for SmallModelCommitMessageGenerator {
+    async fn commit_message(&self, diff: &str) -> Result<String> {
+        let fallback =
+            HeuristicCommitMessageGenerator::default().with_conventional(self.conventional);
+        let input = format!(
+            "Files:\n{}\n\nDiff:\n{}",
+            render_file_stats(&diff_files(diff)),
+            diff.chars().take(COMMIT_DIFF_MAX_CHARS).collect::<String>()
+        );
+        let instructions = if self.conventional {
+            CONVENTIONAL_COMMIT_INSTRUCTIONS
+        } else {
+            COMMIT_INSTRUCTIONS
+        };
+        match self
+            .router
+            .complete(SmallTask::CommitMessage, instructions, &input)
+            .await
+        {
+            Ok(message) => {
+                let subject = message
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .trim_matches('`')
+                    .to_string();
+                if self.conventional && !is_conventional(&subject) {
+                    warn!("commit message is not a conventional commit: {}", subject);
+                    return fallback.commit_message(diff).await;
+                }
+                Ok(subject)
+            }
+            Err(error) => {
+                warn!("commit message generation failed: {:#}", error);
+                fallback.commit_message(diff).await
+            }
+        }
+    }
+}
+
+/// One file of a unified diff with its line counts.
+#[derive(Debug, Clone, PartialEq)]
+struct DiffFile {
+    path: String,
+    added: usize,
+    removed: usize,
+    new: bool,
+    deleted: bool,
+}
+
+/// The files of a `git diff` in order of appearance.
+fn diff_files(diff: &str) -> Vec<DiffFile> {
+    let mut files: Vec<DiffFile> = Vec::new();
+    for line in diff.lines() {
+        if let Some(header) = line.strip_prefix("diff --git a/") {
+            let path = header
+                .split_once(" b/")
+                .map_or(header, |(_, path)| path)
+                .to_string();
+            files.push(DiffFile {
+                path,
+                added: 0,
+                removed: 0,
+                new: false,
+                deleted: false,
+            });
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+        if line.starts_with("new file mode") {
+            file.new = true;
+        } else if line.starts_with("deleted file mode") {
+            file.deleted = true;
+        } else if line.starts_with('+') && !line.starts_with("+++") {
+            file.added += 1;
+        } else if line.starts_with('-') && !line.starts_with("---") {
+            file.removed += 1;
+        }
+    }
+    files
+}
+
+/// `path | +A -R (new)` per file.
+fn render_file_stats(files: &[DiffFile]) -> String {
+    files
+        .iter()
+        .map(|file| {
+            let change = if file.new {
+                " (new)"
+            } else if file.deleted {
+                " (deleted)"
+            } else {
+                ""
+            };
+            format!(
+                "{} | +{} -{}{}",
+                file.path, file.added, file.removed, change
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `type(scope): subject` with the type inferred from the paths: `test`
+/// for tests, `docs` for documentation, `ci` and `build` for pipelines and
+/// manifests, `feat` for source, and `chore` otherwise.
+fn conventional_message(files: &[DiffFile]) -> String {
+    let paths: Vec<&str> = files.iter().map(|file| file.path.as_str()).collect();
+    let all = |kind: fn(&str) -> bool| !paths.is_empty() && paths.iter().all(|path| kind(path));
+    let kind = if all(is_test_path) {
+        "test"
+    } else if all(is_docs_path) {
+        "docs"
+    } else if all(|path| path.starts_with(".github/") || path.starts_with(".gitlab-ci")) {
+        "ci"
+    } else if all(is_build_path) {
+        "build"
+    } else if paths
+        .iter()
+        .any(|path| path.starts_with("src/") || path.starts_with("lib/"))
+    {
+        "feat"
+    } else {
+        "chore"
+    };
+    let scope = commit_scope(&paths)
+        .map(|scope| format!("({})", scope))
+        .unwrap_or_default();
+    let name = |path: &str| path.rsplit('/').next().unwrap_or(path).to_string();
+    let subject = match files {
+        [] => "update".to_string(),
+        [file] if file.new => format!("add {}", name(&file.path)),
+        [file] if file.deleted => format!("remove {}", name(&file.path)),
+        [file] => format!("update {}", name(&file.path)),
+        [file, rest @ ..] => format!(
+            "update {} and {} more file{}",
+            name(&file.path),
+            rest.len(),
+            if rest.len() == 1 { "" } else { "s" }
+        ),
+    };
+    format!("{}{}: {}", kind, scope, subject)
+}
+
+/// The directory every path shares below a top-level `src`, `tests`, or
+/// `docs` (or the top-level directory itself otherwise).
+fn commit_scope(paths: &[&str]) -> Option<String> {
+    let scopes: BTreeSet<&str> = paths
+        .iter()
+        .map(|path| {
+            let mut parts = path.split('/');
+            let first = parts.next().unwrap_or_default();
+            let second = parts.next();
+            let has_more = parts.next().is_some();
+            match (first, second) {
+                ("src" | "lib" | "tests" | "docs", Some(dir)) if has_more => dir,
+                (_, Some(_)) if !matches!(first, "src" | "lib" | "tests" | "docs") => first,
+                _ => "",
+            }
+        })
+        .collect();
+    match scopes.into_iter().collect::<Vec<_>>().as_slice() {
+        [scope] if !scope.is_empty() && !scope.starts_with('.') => Some(scope.to_string()),
+        _ => None,
+    }
+}
+
+fn is_test_path(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    path.starts_with("tests/")
+        || path.contains("/tests/")
+        || path.starts_with("test/")
+        || name.contains("_test.")
+        || name.contains(".test.")
+        || name.contains(".spec.")
+        || name.starts_with("test_")
+}
+
+fn is_docs_path(path: &str) -> bool {
+    path.starts_with("docs/")
+        || path.starts_with("doc/")
+        || path.ends_with(".md")
+        || path.ends_with(".rst")
+        || path.ends_with(".txt") && !path.contains('/')
+}
+
+fn is_build_path(path: &str) -> bool {
+    matches!(
+        path.rsplit('/').next().unwrap_or(path),
+        "Cargo.toml"
+            | "Cargo.lock"
+            | "package.json"
+            | "package-lock.json"
+            | "pyproject.toml"
+            | "go.mod"
+            | "go.sum"
+            | "Makefile"
+            | "Dockerfile"
+            | "build.rs"
+    )
+}
+
+/// Whether `subject` looks like `type(scope)!: subject`.
+fn is_conventional(subject: &str) -> bool {
+    let Some((head, rest)) = subject.split_once(": ") else {
+        return false;
+    };
+    let head = head.strip_suffix('!').unwrap_or(head);
+    let kind = match head.split_once('(') {
+        Some((kind, scope)) => {
+            if !scope.ends_with(')') || scope.len() < 2 {
+                return false;
+            }
+            kind
+        }
+        None => head,
+    };
+    !kind.is_empty() && kind.chars().all(|ch| ch.is_ascii_lowercase()) && !rest.trim().is_empty()
+}
+
+/// When `--auto-commit` commits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoCommitStrategy {
+    /// After every tool call that changed something.
+    #[default]
+    PerTool,
+    /// Once at the end of each iteration.
+    PerIteration,
+    /// Once, when the goal terminates with `done`.
+    OnDone,
+    /// Batches consecutive changes to the same files and commits a batch as
+    /// soon as a tool call changes a file outside it, and at the end of the
+    /// run.
+    Smart,
+}
+
 pub struct AutoCommitHook {
     generator: Arc<dyn CommitMessageGenerator>,
+    dir: Option<PathBuf>,
+    strategy: AutoCommitStrategy,
+    /// Files changed since the last commit under `Smart`.
+    batch: Mutex<BTreeSet<String>>,
 }
 
 impl AutoCommitHook {
     pub fn new(generator: Arc<dyn CommitMessageGenerator>) -> Self {
-        Self { generator }
+        Self {
+            generator,
+            dir: None,
+            strategy: AutoCommitStrategy::default(),
+            batch: Mutex::new(BTreeSet::new()),
+        }
     }
-}
 
-#[async_trait]
-impl EventHook for AutoCommitHook {
-    async fn on_event(&self, event: &Event) -> Result<()> {
-        if event.r#type != "tool_output" {
-            return Ok(());
-        }
+    /// Commits in `dir` instead of the launch directory.
+    pub fn with_dir(mut self, dir: &Path) -> Self {
+        self.dir = Some(dir.to_path_buf());
+        self
+    }
 
-        let tool_name = event
-            .payload
-            .get("name")
-            .and_then(|value| value.as_str())
-            .unwrap_or_default();
+    pub fn with_strategy(mut self, strategy: AutoCommitStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
 
-        if tool_name == "done" {
-            return Ok(());
+    fn git(&self) -> Command {
+        let mut command = Command::new("git");
+        if let Some(dir) = &self.dir {
+            command.current_dir(dir);
+        }
+        command
+    }
+
+    /// Paths with uncommitted changes, untracked files included.
+    async fn changed_files(&self) -> Result<BTreeSet<String>> {
+        let output = self
+            .git()
+            .args(["status", "--porcelain", "-z", "--untracked-files=all"])
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Ok(BTreeSet::new());
+        }
+        let status = String::from_utf8_lossy(&output.stdout);
+        let mut files = BTreeSet::new();
+        let mut entries = status.split('\0');
+        while let Some(entry) = entries.next() {
+            let Some(path) = entry.get(3..) else {
+                continue;
+            };
+            files.insert(path.to_string());
+            // Renames and copies are followed by their source path.
+            if entry.starts_with('R') || entry.starts_with('C') {
+                if let Some(source) = entries.next() {
+                    files.insert(source.to_string());
+                }
+            }
         }
+        Ok(files)
+    }
 
-        let add_output = Command::new("git").args(["add", "."]).output().await?;
+    /// Stages `paths` (everything when `None`) and commits them if the
+    /// staged diff is non-empty.
+    async fn commit(&self, paths: Option<&BTreeSet<String>>) -> Result<()> {
+        let mut add = self.git();
+        add.args(["add", "-A", "--"]);
+        match paths {
+            // Status paths are relative to the repository root.
+            Some(paths) => add.args(paths.iter().map(|path| format!(":(top,literal){}", path))),
+            None => add.arg("."),
+        };
+        let add_output = add.output().await?;
         if !add_output.status.success() {
             return Ok(());
         }
 
-        let diff_check = Command::new("git")
+        let diff_check = self
+            .git()
             .args(["diff", "--cached", "--quiet"])
             .output()
             .await?;
@@ -187,10 +570,7 @@ impl EventHook for AutoCommitHook {
             return Ok(());
         }
 
-        let diff_output = Command::new("git")
-            .args(["diff", "--cached"])
-            .output()
-            .await?;
+        let diff_output = self.git().args(["diff", "--cached"]).output().await?;
         if !diff_output.status.success() {
             return Ok(());
         }
@@ -208,11 +588,235 @@ impl EventHook for AutoCommitHook {
             .await
             .unwrap_or_else(|_| "rx: update".to_string());
 
-        let _ = Command::new("git")
+        let _ = self
+            .git()
             .args(["commit", "-m", message.trim()])
             .output()
             .await?;
 
         Ok(())
     }
+
+    /// Starts a new batch when a tool call changed a file outside the
+    /// current one, committing the current batch first.
+    async fn batch_changes(&self) -> Result<()> {
+        let changed = self.changed_files().await?;
+        let mut batch = self.batch.lock().await;
+        let fresh: BTreeSet<String> = changed.difference(&batch).cloned().collect();
+        if fresh.is_empty() {
+            return Ok(());
+        }
+        if !batch.is_empty() {
+            self.commit(Some(&batch)).await?;
+            batch.clear();
+        }
+        batch.extend(fresh);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventHook for AutoCommitHook {
+    async fn on_event(&self, event: &Event) -> Result<()> {
+        let tool_name = event
+            .payload
+            .get("name")
+            .and_then(|value| value.as_str())
+            .unwrap_or_default();
+        let done = event.payload.get("reason").and_then(|value| value.as_str()) == Some("done");
+
+        match (self.strategy, event.r#type.as_str()) {
+            (_, "tool_output") if tool_name == "propose_done" => Ok(()),
+            (AutoCommitStrategy::PerTool, "tool_output")
+            | (AutoCommitStrategy::PerIteration, "iteration_finished") => self.commit(None).await,
+            (AutoCommitStrategy::OnDone, "termination") if done => self.commit(None).await,
+            (AutoCommitStrategy::Smart, "tool_output") => self.batch_changes().await,
+            (AutoCommitStrategy::Smart, "termination") => {
+                self.batch.lock().await.clear();
+                self.commit(None).await
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_conventional, AutoCommitHook, AutoCommitStrategy, CommitMessageGenerator, EventHook,
+        HeuristicCommitMessageGenerator,
+    };
+    use crate::event::Event;
+    use serde_json::json;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .expect("git should run");
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    fn repo(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("rx-auto-commit-{}-{}", name, nanos));
+        std::fs::create_dir_all(&dir).unwrap();
+        git(&dir, &["init", "-q"]);
+        git(&dir, &["config", "user.email", "rx@example.com"]);
+        git(&dir, &["config", "user.name", "rx"]);
+        git(&dir, &["commit", "-q", "--allow-empty", "-m", "init"]);
+        dir
+    }
+
+    fn hook(dir: &Path, strategy: AutoCommitStrategy) -> AutoCommitHook {
+        AutoCommitHook::new(Arc::new(HeuristicCommitMessageGenerator::default()))
+            .with_dir(dir)
+            .with_strategy(strategy)
+    }
+
+    fn commits(dir: &Path) -> String {
+        git(dir, &["rev-list", "--count", "HEAD"])
+    }
+
+    fn tool_output() -> Event {
+        Event::new("tool_output", json!({ "name": "write_file", "output": {} }))
+    }
+
+    fn termination(reason: &str) -> Event {
+        Event::new("termination", json!({ "reason": reason }))
+    }
+
+    #[tokio::test]
+    async fn each_strategy_commits_at_its_own_points() {
+        let iteration_finished = Event::new("iteration_finished", json!({ "iteration": 1 }));
+
+        let dir = repo("per-tool");
+        let per_tool = hook(&dir, AutoCommitStrategy::PerTool);
+        std::fs::write(dir.join("a.txt"), "a\n").unwrap();
+        per_tool.on_event(&tool_output()).await.unwrap();
+        assert_eq!(commits(&dir), "2");
+        std::fs::write(dir.join("b.txt"), "b\n").unwrap();
+        per_tool.on_event(&iteration_finished).await.unwrap();
+        assert_eq!(commits(&dir), "2");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let dir = repo("per-iteration");
+        let per_iteration = hook(&dir, AutoCommitStrategy::PerIteration);
+        std::fs::write(dir.join("a.txt"), "a\n").unwrap();
+        per_iteration.on_event(&tool_output()).await.unwrap();
+        std::fs::write(dir.join("b.txt"), "b\n").unwrap();
+        per_iteration.on_event(&tool_output()).await.unwrap();
+        assert_eq!(commits(&dir), "1");
+        per_iteration.on_event(&iteration_finished).await.unwrap();
+        assert_eq!(commits(&dir), "2");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let dir = repo("on-done");
+        let on_done = hook(&dir, AutoCommitStrategy::OnDone);
+        std::fs::write(dir.join("a.txt"), "a\n").unwrap();
+        on_done.on_event(&tool_output()).await.unwrap();
+        on_done.on_event(&iteration_finished).await.unwrap();
+        on_done
+            .on_event(&termination("max_iterations"))
+            .await
+            .unwrap();
+        assert_eq!(commits(&dir), "1");
+        on_done.on_event(&termination("done")).await.unwrap();
+        assert_eq!(commits(&dir), "2");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let dir = repo("smart");
+        let smart = hook(&dir, AutoCommitStrategy::Smart);
+        std::fs::write(dir.join("a.txt"), "a\n").unwrap();
+        smart.on_event(&tool_output()).await.unwrap();
+        std::fs::write(dir.join("a.txt"), "a\nmore a\n").unwrap();
+        smart.on_event(&tool_output()).await.unwrap();
+        assert_eq!(commits(&dir), "1");
+        std::fs::write(dir.join("b.txt"), "b\n").unwrap();
+        smart.on_event(&tool_output()).await.unwrap();
+        assert_eq!(commits(&dir), "2");
+        assert_eq!(
+            git(&dir, &["show", "--format=", "--name-only", "HEAD"]),
+            "a.txt"
+        );
+        smart.on_event(&termination("interrupted")).await.unwrap();
+        assert_eq!(commits(&dir), "3");
+        assert_eq!(
+            git(&dir, &["show", "--format=", "--name-only", "HEAD"]),
+            "b.txt"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn conventional_messages_infer_type_and_scope_from_paths() {
+        let generator = HeuristicCommitMessageGenerator::default().with_conventional(true);
+        let diff = |paths: &[(&str, bool)]| {
+            paths
+                .iter()
+                .map(|(path, new)| {
+                    format!(
+                        "diff --git a/{path} b/{path}\n{}--- a/{path}\n+++ b/{path}\n@@ -1 +1 @@\n-old\n+new\n",
+                        if *new { "new file mode 100644\n" } else { "" }
+                    )
+                })
+                .collect::<String>()
+        };
+
+        assert_eq!(
+            generator
+                .commit_message(&diff(&[("tests/cli.rs", false)]))
+                .await
+                .unwrap(),
+            "test: update cli.rs"
+        );
+        assert_eq!(
+            generator
+                .commit_message(&diff(&[("docs/guide/setup.md", true)]))
+                .await
+                .unwrap(),
+            "docs(guide): add setup.md"
+        );
+        assert_eq!(
+            generator
+                .commit_message(&diff(&[
+                    ("src/tools/exec.rs", false),
+                    ("src/tools/fs.rs", false)
+                ]))
+                .await
+                .unwrap(),
+            "feat(tools): update exec.rs and 1 more file"
+        );
+        assert_eq!(
+            generator
+                .commit_message(&diff(&[
+                    ("src/main.rs", false),
+                    ("Cargo.toml", false),
+                    ("README.md", false)
+                ]))
+                .await
+                .unwrap(),
+            "feat: update main.rs and 2 more files"
+        );
+        assert_eq!(
+            HeuristicCommitMessageGenerator::default()
+                .commit_message(&diff(&[("src/main.rs", false)]))
+                .await
+                .unwrap(),
+            "rx: update src/main.rs"
+        );
+
+        assert!(is_conventional("fix(kernel)!: stop on cancel"));
+        assert!(!is_conventional("Fix the kernel"));
+        assert!(!is_conventional("fix(: x"));
+    }
 }
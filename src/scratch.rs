@@ -0,0 +1,124 @@
+use crate::event::Event;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+pub const DEFAULT_MAX_AGE_HOURS: u64 = 72;
+
+/// When a goal's scratch directory is deleted at the end of a run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScratchCleanup {
+    /// Delete after the goal terminates with `done`; keep it for inspection
+    /// or a later resume otherwise.
+    #[default]
+    OnDone,
+    Always,
+    Never,
+}
+
+/// Per-goal directory for throwaway files, outside the repository.
+#[derive(Debug, Clone)]
+pub struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    /// Creates (or reuses, on resume) `<root>/<goal_id>`.
+    pub fn create(root: &Path, goal_id: &str) -> Result<Self> {
+        let path = root.join(goal_id);
+        std::fs::create_dir_all(&path)
+            .with_context(|| format!("failed to create scratch directory {}", path.display()))?;
+        let path = path.canonicalize().unwrap_or(path);
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Applies `cleanup` given the goal's final events. Returns whether the
+    /// directory was removed.
+    pub fn finish(&self, cleanup: ScratchCleanup, history: &[Event]) -> bool {
+        let done = history
+            .iter()
+            .rev()
+            .find(|event| event.r#type == "termination")
+            .and_then(|event| event.payload.get("reason"))
+            .and_then(|reason| reason.as_str())
+            == Some("done");
+        let remove = match cleanup {
+            ScratchCleanup::OnDone => done,
+            ScratchCleanup::Always => true,
+            ScratchCleanup::Never => false,
+        };
+        remove && std::fs::remove_dir_all(&self.path).is_ok()
+    }
+}
+
+/// `$TMPDIR/rx-scratch`.
+pub fn default_scratch_root() -> PathBuf {
+    std::env::temp_dir().join("rx-scratch")
+}
+
+/// Deletes scratch directories under `root` untouched for longer than
+/// `max_age`, skipping `keep`. Returns how many were removed.
+pub fn purge_stale(root: &Path, max_age: Duration, keep: &str) -> usize {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return 0;
+    };
+    let now = SystemTime::now();
+    entries
+        .flatten()
+        .filter(|entry| entry.file_name() != keep)
+        .filter(|entry| {
+            entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|age| age > max_age)
+        })
+        .filter(|entry| std::fs::remove_dir_all(entry.path()).is_ok())
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{purge_stale, ScratchCleanup, ScratchDir};
+    use crate::event::Event;
+    use serde_json::json;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn cleanup_follows_policy_and_termination_reason() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("rx-scratch-test-{}", nanos));
+
+        let unfinished = ScratchDir::create(&root, "goal-a").expect("create");
+        let stopped = [Event::new(
+            "termination",
+            json!({ "reason": "max_iterations" }),
+        )];
+        assert!(!unfinished.finish(ScratchCleanup::OnDone, &stopped));
+        assert!(unfinished.path().exists());
+
+        let done = [Event::new("termination", json!({ "reason": "done" }))];
+        assert!(!unfinished.finish(ScratchCleanup::Never, &done));
+        assert!(unfinished.finish(ScratchCleanup::OnDone, &done));
+        assert!(!unfinished.path().exists());
+
+        ScratchDir::create(&root, "old").expect("create");
+        ScratchDir::create(&root, "current").expect("create");
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(purge_stale(&root, Duration::from_secs(3600), "current"), 0);
+        assert_eq!(purge_stale(&root, Duration::ZERO, "current"), 1);
+        assert!(root.join("current").exists());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
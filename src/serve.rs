@@ -0,0 +1,931 @@
+use crate::event::Event;
+use crate::kernel::{KernelHandle, KernelStatus};
+use crate::queue::{GoalRequest, Job, JobQueue, JobStatus};
+use crate::runtime_hooks::EventHook;
+use crate::state::{list_stored_goals, load_goal, GoalSummary, StateSettings};
+use crate::websocket::{self, is_loopback_origin, GoalEvent, EVENT_BUFFER};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::Utc;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full, Limited, StreamBody};
+use hyper::body::{Frame, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{header, Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, mpsc, watch, Mutex, Notify, Semaphore};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tracing::{info, warn};
+
+/// Largest request body accepted.
+const MAX_BODY_BYTES: usize = 1 << 20;
+
+/// File under the daemon's data directory holding the bearer token every
+/// request must send; rewritten each time `rx serve` starts.
+pub const TOKEN_FILE: &str = "serve.token";
+
+type Body = BoxBody<Bytes, Infallible>;
+
+/// Goals run at once when neither `--max-concurrent` nor
+/// `[serve].max_concurrent_goals` is set.
+pub const DEFAULT_MAX_CONCURRENT_GOALS: usize = 2;
+
+/// Workspace the unprefixed routes address: the launch directory when
+/// `[[serve.workspaces]]` is empty.
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+/// A repository the daemon runs goals in, served under
+/// `/workspaces/{name}`.
+pub struct ServedWorkspace {
+    pub name: String,
+    pub root: PathBuf,
+    /// Where the workspace's goals are stored.
+    pub state: StateSettings,
+}
+
+/// Starts goals for the daemon, the way `rx run` does.
+#[async_trait]
+pub trait GoalLauncher: Send + Sync {
+    /// Starts the goal with the `rx run` options of `request` in
+    /// `workspace`; `hook` sees every event of the goal. Returns the goal
+    /// id and its running kernel.
+    async fn launch(
+        &self,
+        workspace: &ServedWorkspace,
+        request: GoalRequest,
+        hook: Arc<dyn EventHook>,
+    ) -> Result<(String, KernelHandle)>;
+
+    /// Rejects a request that cannot start, before it is queued.
+    fn check(&self, _workspace: &ServedWorkspace, _request: &GoalRequest) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct RunningGoal {
+    handle: KernelHandle,
+    events: broadcast::Sender<Event>,
+}
+
+/// Shared state of `rx serve`: its workspaces, the job queue, and the
+/// goals it started that are still running.
+pub struct Daemon {
+    launcher: Arc<dyn GoalLauncher>,
+    workspaces: Vec<ServedWorkspace>,
+    jobs: Mutex<JobQueue>,
+    /// Wakes the dispatcher when a job is queued.
+    queued: Notify,
+    /// One permit per goal that may run at once, across workspaces.
+    slots: Arc<Semaphore>,
+    /// Keyed by workspace name and goal id.
+    running: Mutex<HashMap<(String, String), RunningGoal>>,
+    /// Every event of every goal this server runs, for `/ws` clients.
+    all_events: broadcast::Sender<GoalEvent>,
+}
+
+impl Daemon {
+    /// Creates the daemon and its dispatcher, which starts queued goals in
+    /// submission order, at most `max_concurrent` at a time.
+    pub fn start(
+        launcher: Arc<dyn GoalLauncher>,
+        workspaces: Vec<ServedWorkspace>,
+        max_concurrent: usize,
+    ) -> Arc<Self> {
+        let daemon = Arc::new(Self {
+            launcher,
+            workspaces,
+            jobs: Mutex::new(JobQueue::default()),
+            queued: Notify::new(),
+            slots: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            running: Mutex::new(HashMap::new()),
+            all_events: broadcast::channel(EVENT_BUFFER).0,
+        });
+        tokio::spawn(Arc::clone(&daemon).dispatch());
+        daemon
+    }
+
+    fn workspace(&self, name: &str) -> Option<&ServedWorkspace> {
+        self.workspaces
+            .iter()
+            .find(|workspace| workspace.name == name)
+    }
+
+    async fn enqueue(&self, workspace: &ServedWorkspace, request: GoalRequest) -> Job {
+        let job = self.jobs.lock().await.push(&workspace.name, request);
+        self.queued.notify_one();
+        job
+    }
+
+    async fn dispatch(self: Arc<Self>) {
+        loop {
+            let Ok(slot) = Arc::clone(&self.slots).acquire_owned().await else {
+                return;
+            };
+            let job = loop {
+                if let Some(job) = self.jobs.lock().await.start_next() {
+                    break job;
+                }
+                self.queued.notified().await;
+            };
+            let daemon = Arc::clone(&self);
+            tokio::spawn(async move {
+                daemon.run_job(job).await;
+                drop(slot);
+            });
+        }
+    }
+
+    /// Runs the job's goal to its end and records the outcome.
+    async fn run_job(&self, job: Job) {
+        let started = match self.workspace(&job.workspace) {
+            Some(workspace) => self.start_goal(workspace, job.request).await,
+            None => Err(anyhow!("unknown workspace {}", job.workspace)),
+        };
+        let (status, detail) = match started {
+            Ok((goal_id, mut kernel_status)) => {
+                if let Some(entry) = self.jobs.lock().await.get_mut(&job.job_id) {
+                    entry.goal_id = Some(goal_id.clone());
+                }
+                let _ = kernel_status.wait_for(KernelStatus::is_finished).await;
+                self.running
+                    .lock()
+                    .await
+                    .remove(&(job.workspace.clone(), goal_id));
+                let outcome = kernel_status.borrow().clone();
+                match outcome {
+                    KernelStatus::Finished { reason } => (JobStatus::Finished, reason),
+                    KernelStatus::Failed { error } => (JobStatus::Failed, error),
+                    _ => (
+                        JobStatus::Failed,
+                        "the kernel stopped unexpectedly".to_string(),
+                    ),
+                }
+            }
+            Err(error) => (JobStatus::Failed, format!("{:#}", error)),
+        };
+        if let Some(entry) = self.jobs.lock().await.get_mut(&job.job_id) {
+            entry.status = status;
+            entry.detail = Some(detail);
+            entry.finished_at = Some(Utc::now());
+        }
+    }
+
+    async fn start_goal(
+        &self,
+        workspace: &ServedWorkspace,
+        request: GoalRequest,
+    ) -> Result<(String, watch::Receiver<KernelStatus>)> {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        let hook = Arc::new(BroadcastHook {
+            events: events.clone(),
+        });
+        // Subscribed before launch so the goal's first events reach `/ws`.
+        let mut forward = events.subscribe();
+        let (goal_id, handle) = self.launcher.launch(workspace, request, hook).await?;
+        let all_events = self.all_events.clone();
+        let forward_id = goal_id.clone();
+        tokio::spawn(async move {
+            loop {
+                match forward.recv().await {
+                    Ok(event) => {
+                        let _ = all_events.send(GoalEvent {
+                            goal_id: forward_id.clone(),
+                            event,
+                        });
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+        });
+        let status = handle.status();
+        self.running.lock().await.insert(
+            (workspace.name.clone(), goal_id.clone()),
+            RunningGoal { handle, events },
+        );
+        Ok((goal_id, status))
+    }
+
+    /// Drops a queued job, or cancels the goal of a running one.
+    async fn cancel_job(
+        &self,
+        workspace: &str,
+        job_id: &str,
+    ) -> Result<Job, (StatusCode, &'static str)> {
+        let mut jobs = self.jobs.lock().await;
+        let job = jobs
+            .get_mut(job_id)
+            .filter(|job| job.workspace == workspace)
+            .ok_or((StatusCode::NOT_FOUND, "unknown job"))?;
+        match (job.status, &job.goal_id) {
+            (JobStatus::Queued, _) => {
+                job.status = JobStatus::Cancelled;
+                job.finished_at = Some(Utc::now());
+            }
+            (JobStatus::Running, Some(goal_id)) => {
+                self.cancel_goal(workspace, goal_id).await;
+            }
+            (JobStatus::Running, None) => {
+                return Err((StatusCode::CONFLICT, "job is starting; retry the cancel"))
+            }
+            _ => return Err((StatusCode::CONFLICT, "job has already ended")),
+        }
+        Ok(job.clone())
+    }
+
+    /// Cancels every running goal and waits for them to stop.
+    pub async fn shutdown(&self) {
+        let mut statuses = Vec::new();
+        for goal in self.running.lock().await.values() {
+            goal.handle.cancel();
+            statuses.push(goal.handle.status());
+        }
+        for mut status in statuses {
+            let _ = status.wait_for(KernelStatus::is_finished).await;
+        }
+    }
+
+    async fn list_goals(&self, workspace: &ServedWorkspace) -> Result<Value> {
+        let running = self.running.lock().await;
+        let mut goals = Vec::new();
+        for goal_id in list_stored_goals(&workspace.state).await? {
+            let events = match load_goal(&workspace.state, &goal_id).await {
+                Ok(events) => events,
+                Err(error) => {
+                    warn!("{:#}", error);
+                    continue;
+                }
+            };
+            let mut summary = GoalSummary::from_events(&events);
+            if running.contains_key(&(workspace.name.clone(), goal_id.clone())) {
+                summary.status = "running".to_string();
+            }
+            goals.push(json!({
+                "goal_id": goal_id,
+                "goal": summary.goal,
+                "started": summary.started,
+                "status": summary.status,
+            }));
+        }
+        Ok(Value::Array(goals))
+    }
+
+    async fn cancel_goal(&self, workspace: &str, goal_id: &str) -> bool {
+        let key = (workspace.to_string(), goal_id.to_string());
+        match self.running.lock().await.get(&key) {
+            Some(goal) => {
+                goal.handle.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Recorded events of the goal followed, while it runs, by live ones,
+    /// as server-sent events. `None` for an unknown goal.
+    async fn event_stream(&self, workspace: &ServedWorkspace, goal_id: &str) -> Option<Body> {
+        // Subscribe before loading so no event falls between the two.
+        let live = self
+            .running
+            .lock()
+            .await
+            .get(&(workspace.name.clone(), goal_id.to_string()))
+            .map(|goal| goal.events.subscribe());
+        let stored = load_goal(&workspace.state, goal_id)
+            .await
+            .unwrap_or_default();
+        if stored.is_empty() && live.is_none() {
+            return None;
+        }
+        let (sender, receiver) = mpsc::channel::<Bytes>(64);
+        tokio::spawn(async move {
+            let mut last_seq = 0;
+            for event in &stored {
+                last_seq = event.seq;
+                if sender.send(sse_frame(event)).await.is_err() {
+                    return;
+                }
+            }
+            let Some(mut live) = live else {
+                return;
+            };
+            loop {
+                match live.recv().await {
+                    Ok(event) if event.seq > last_seq => {
+                        let done = event.r#type == "termination";
+                        if sender.send(sse_frame(&event)).await.is_err() || done {
+                            return;
+                        }
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+        });
+        let frames = ReceiverStream::new(receiver).map(|chunk| Ok(Frame::data(chunk)));
+        Some(BodyExt::boxed(StreamBody::new(frames)))
+    }
+}
+
+/// Forwards a goal's events to its `/events` readers.
+struct BroadcastHook {
+    events: broadcast::Sender<Event>,
+}
+
+#[async_trait]
+impl EventHook for BroadcastHook {
+    async fn on_event(&self, event: &Event) -> Result<()> {
+        // No readers is fine.
+        let _ = self.events.send(event.clone());
+        Ok(())
+    }
+}
+
+fn sse_frame(event: &Event) -> Bytes {
+    let data = serde_json::to_string(event).unwrap_or_default();
+    Bytes::from(format!(
+        "id: {}\nevent: {}\ndata: {}\n\n",
+        event.seq, event.r#type, data
+    ))
+}
+
+/// Generates a fresh API token and writes it to [`TOKEN_FILE`] in
+/// `data_dir`, readable only by the owner.
+pub fn write_token(data_dir: &Path) -> Result<String> {
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    std::fs::create_dir_all(data_dir)
+        .with_context(|| format!("failed to create {}", data_dir.display()))?;
+    let path = data_dir.join(TOKEN_FILE);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(&path)
+        .and_then(|mut file| std::io::Write::write_all(&mut file, token.as_bytes()))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(token)
+}
+
+/// Serves the HTTP API on `listener` until Ctrl-C, then cancels the
+/// goals still running. Requests must carry `Authorization: Bearer
+/// <token>`.
+pub async fn serve(listener: TcpListener, daemon: Arc<Daemon>, token: String) -> Result<()> {
+    info!("Listening on http://{}", listener.local_addr()?);
+    let token: Arc<str> = Arc::from(token);
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted.context("failed to accept a connection")?;
+                let daemon = Arc::clone(&daemon);
+                let token = Arc::clone(&token);
+                tokio::spawn(async move {
+                    let service = service_fn(move |request| {
+                        route(Arc::clone(&daemon), Arc::clone(&token), request)
+                    });
+                    if let Err(error) = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .with_upgrades()
+                        .await
+                    {
+                        warn!("connection error: {}", error);
+                    }
+                });
+            }
+            Ok(()) = tokio::signal::ctrl_c() => break,
+        }
+    }
+    info!("Shutting down; cancelling running goals");
+    daemon.shutdown().await;
+    Ok(())
+}
+
+async fn route(
+    daemon: Arc<Daemon>,
+    token: Arc<str>,
+    request: Request<Incoming>,
+) -> Result<Response<Body>, Infallible> {
+    let header = |name: header::HeaderName| {
+        request
+            .headers()
+            .get(name)
+            .map(|value| value.to_str().unwrap_or_default())
+    };
+    if header(header::ORIGIN).is_some_and(|origin| !is_loopback_origin(origin)) {
+        return Ok(error_response(
+            StatusCode::FORBIDDEN,
+            "cross-origin requests are not allowed",
+        ));
+    }
+    let bearer = header(header::AUTHORIZATION).and_then(|value| value.strip_prefix("Bearer "));
+    if bearer != Some(&*token) {
+        return Ok(error_response(
+            StatusCode::UNAUTHORIZED,
+            "missing or wrong bearer token; see serve.token in the server's data directory",
+        ));
+    }
+    let method = request.method().clone();
+    let path = request.uri().path().trim_matches('/').to_string();
+    let segments: Vec<&str> = path.split('/').collect();
+    let response = match (&method, segments.as_slice()) {
+        (&Method::GET, ["workspaces"]) => {
+            let workspaces: Vec<Value> = daemon
+                .workspaces
+                .iter()
+                .map(|workspace| json!({ "name": workspace.name, "root": workspace.root }))
+                .collect();
+            json_response(StatusCode::OK, json!(workspaces))
+        }
+        (&Method::GET, ["ws"]) => {
+            let goal_id = request.uri().query().and_then(|query| {
+                query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("goal="))
+                    .map(str::to_string)
+            });
+            let events = daemon.all_events.subscribe();
+            websocket::upgrade(request, events, goal_id)
+                .unwrap_or_else(|error| error_response(StatusCode::BAD_REQUEST, &error.to_string()))
+        }
+        (_, ["workspaces", name, route @ ..]) => match daemon.workspace(name) {
+            Some(workspace) => workspace_route(&daemon, workspace, &method, route, request).await,
+            None => error_response(StatusCode::NOT_FOUND, "unknown workspace"),
+        },
+        (_, route) => match daemon.workspace(DEFAULT_WORKSPACE) {
+            Some(workspace) => workspace_route(&daemon, workspace, &method, route, request).await,
+            None => error_response(
+                StatusCode::NOT_FOUND,
+                "no default workspace; use /workspaces/{name}/...",
+            ),
+        },
+    };
+    Ok(response)
+}
+
+/// Routes of one workspace, below `/workspaces/{name}`.
+async fn workspace_route(
+    daemon: &Daemon,
+    workspace: &ServedWorkspace,
+    method: &Method,
+    route: &[&str],
+    request: Request<Incoming>,
+) -> Response<Body> {
+    match (method, route) {
+        (&Method::POST, ["goals"]) => enqueue(daemon, workspace, request).await,
+        (&Method::GET, ["jobs"]) => {
+            let jobs: Vec<Job> = daemon
+                .jobs
+                .lock()
+                .await
+                .jobs()
+                .iter()
+                .filter(|job| job.workspace == workspace.name)
+                .cloned()
+                .collect();
+            json_response(StatusCode::OK, json!(jobs))
+        }
+        (&Method::GET, ["jobs", job_id]) => match daemon
+            .jobs
+            .lock()
+            .await
+            .get(job_id)
+            .filter(|job| job.workspace == workspace.name)
+        {
+            Some(job) => json_response(StatusCode::OK, json!(job)),
+            None => error_response(StatusCode::NOT_FOUND, "unknown job"),
+        },
+        (&Method::POST, ["jobs", job_id, "cancel"]) => {
+            match daemon.cancel_job(&workspace.name, job_id).await {
+                Ok(job) => json_response(StatusCode::ACCEPTED, json!(job)),
+                Err((status, message)) => error_response(status, message),
+            }
+        }
+        (&Method::GET, ["goals"]) => match daemon.list_goals(workspace).await {
+            Ok(goals) => json_response(StatusCode::OK, goals),
+            Err(error) => {
+                error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("{:#}", error))
+            }
+        },
+        (&Method::GET, ["goals", goal_id, "events"]) => {
+            match daemon.event_stream(workspace, goal_id).await {
+                Some(body) => Response::builder()
+                    .header("content-type", "text/event-stream")
+                    .header("cache-control", "no-cache")
+                    .body(body)
+                    .expect("valid response"),
+                None => error_response(StatusCode::NOT_FOUND, "unknown goal"),
+            }
+        }
+        (&Method::POST, ["goals", goal_id, "cancel"]) => {
+            if daemon.cancel_goal(&workspace.name, goal_id).await {
+                json_response(
+                    StatusCode::ACCEPTED,
+                    json!({ "goal_id": goal_id, "status": "cancelling" }),
+                )
+            } else {
+                error_response(StatusCode::NOT_FOUND, "goal is not running")
+            }
+        }
+        _ => error_response(StatusCode::NOT_FOUND, "no such route"),
+    }
+}
+
+async fn enqueue(
+    daemon: &Daemon,
+    workspace: &ServedWorkspace,
+    request: Request<Incoming>,
+) -> Response<Body> {
+    let json = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim_start().starts_with("application/json"));
+    if !json {
+        return error_response(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "expected Content-Type: application/json",
+        );
+    }
+    let body = match Limited::new(request.into_body(), MAX_BODY_BYTES)
+        .collect()
+        .await
+    {
+        Ok(body) => body.to_bytes(),
+        Err(error) => return error_response(StatusCode::BAD_REQUEST, &error.to_string()),
+    };
+    let goal: GoalRequest = match serde_json::from_slice(&body) {
+        Ok(goal) => goal,
+        Err(error) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!(
+                    "expected {{\"goal\": ..., \"args\": [...], \"workdir\": ...}}: {}",
+                    error
+                ),
+            )
+        }
+    };
+    if goal.goal.trim().is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "'goal' must not be empty");
+    }
+    if let Err(error) = daemon.launcher.check(workspace, &goal) {
+        return error_response(StatusCode::BAD_REQUEST, &format!("{:#}", error));
+    }
+    json_response(
+        StatusCode::ACCEPTED,
+        json!(daemon.enqueue(workspace, goal).await),
+    )
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(BodyExt::boxed(Full::new(Bytes::from(body.to_string()))))
+        .expect("valid response")
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    json_response(status, json!({ "error": message }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        serve, write_token, Daemon, GoalLauncher, ServedWorkspace, DEFAULT_WORKSPACE, TOKEN_FILE,
+    };
+    use crate::event::Event;
+    use crate::kernel::{Kernel, KernelHandle};
+    use crate::model::{Action, Model, ToolCall};
+    use crate::queue::GoalRequest;
+    use crate::runtime_hooks::{EventHook, HookedStateStore};
+    use crate::state::{JsonlStateStore, StateBackend, StateSettings, StateStore};
+    use crate::tool::ToolRegistry;
+    use crate::tools::done::ProposeDoneTool;
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use serde_json::{json, Value};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use tokio::net::TcpListener;
+    use tokio::sync::Semaphore;
+
+    /// Proposes completion once `gate` lets it.
+    struct GatedDoneModel {
+        gate: Arc<Semaphore>,
+    }
+
+    #[async_trait]
+    impl Model for GatedDoneModel {
+        async fn next_action(&self, _history: &[Event]) -> Result<Action> {
+            self.gate.acquire().await?.forget();
+            Ok(Action::ToolCall(ToolCall {
+                id: "call-1".to_string(),
+                name: "propose_done".to_string(),
+                arguments: json!({
+                    "summary": "nothing to do",
+                    "outcome": "success",
+                    "evidence": ["no changes needed"],
+                }),
+            }))
+        }
+    }
+
+    struct TestLauncher {
+        gate: Arc<Semaphore>,
+        launched: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl GoalLauncher for TestLauncher {
+        async fn launch(
+            &self,
+            workspace: &ServedWorkspace,
+            request: GoalRequest,
+            hook: Arc<dyn EventHook>,
+        ) -> Result<(String, KernelHandle)> {
+            let goal_id = format!("goal-{}", self.launched.fetch_add(1, Ordering::SeqCst) + 1);
+            let store = Arc::new(JsonlStateStore::new(&workspace.state.data_dir, &goal_id).await?);
+            let store = Arc::new(HookedStateStore::new(store, vec![hook]));
+            store
+                .append_event(Event::new("goal", json!({ "goal": request.goal })))
+                .await?;
+            let mut registry = ToolRegistry::new();
+            registry.register(Arc::new(ProposeDoneTool));
+            let model = Arc::new(GatedDoneModel {
+                gate: Arc::clone(&self.gate),
+            });
+            let kernel = Kernel::new(goal_id.clone(), model, store, registry, 3);
+            Ok((goal_id, kernel.spawn()))
+        }
+    }
+
+    fn served(name: &str, data_dir: &Path) -> ServedWorkspace {
+        ServedWorkspace {
+            name: name.to_string(),
+            root: data_dir.to_path_buf(),
+            state: StateSettings {
+                backend: StateBackend::Jsonl,
+                data_dir: data_dir.to_path_buf(),
+                database_url: None,
+            },
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        std::env::temp_dir().join(format!("rx-serve-{}-{}", name, nanos))
+    }
+
+    /// A client that sends `token` with every request.
+    fn authorized(token: &str) -> reqwest::Client {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::AUTHORIZATION,
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+        reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .unwrap()
+    }
+
+    async fn wait_for_job(
+        client: &reqwest::Client,
+        base: &str,
+        job_id: &str,
+        done: impl Fn(&Value) -> bool,
+    ) -> Value {
+        for _ in 0..500 {
+            let job: Value = client
+                .get(format!("{}/jobs/{}", base, job_id))
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            if done(&job) {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("job {} did not reach the expected state", job_id);
+    }
+
+    #[tokio::test]
+    async fn queued_goals_run_one_at_a_time_and_stream_events() {
+        let data_dir = temp_dir("queue");
+        let gate = Arc::new(Semaphore::new(0));
+        let launcher = Arc::new(TestLauncher {
+            gate: Arc::clone(&gate),
+            launched: AtomicUsize::new(0),
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let workspaces = vec![served(DEFAULT_WORKSPACE, &data_dir)];
+        let token = write_token(&data_dir).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(data_dir.join(TOKEN_FILE)).unwrap(),
+            token
+        );
+        let daemon = Daemon::start(launcher, workspaces, 1);
+        tokio::spawn(serve(listener, daemon, token.clone()));
+        let client = authorized(&token);
+
+        let mut job_ids = Vec::new();
+        for goal in ["check the build", "add tests", "update docs"] {
+            let queued = client
+                .post(format!("{}/goals", base))
+                .json(&json!({ "goal": goal }))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(queued.status(), 202);
+            let job: Value = queued.json().await.unwrap();
+            assert_eq!(job["status"], "queued");
+            job_ids.push(job["job_id"].as_str().unwrap().to_string());
+        }
+        let first = wait_for_job(&client, &base, &job_ids[0], |job| {
+            job["goal_id"].is_string()
+        })
+        .await;
+        assert_eq!(first["status"], "running");
+        assert_eq!(first["goal_id"], "goal-1");
+        let second: Value = client
+            .get(format!("{}/jobs/{}", base, job_ids[1]))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(second["status"], "queued");
+        let cancelled: Value = client
+            .post(format!("{}/jobs/{}/cancel", base, job_ids[2]))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(cancelled["status"], "cancelled");
+
+        gate.add_permits(2);
+        let events = client
+            .get(format!("{}/goals/goal-1/events", base))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(events.headers()["content-type"], "text/event-stream");
+        let stream = events.text().await.unwrap();
+        assert!(stream.starts_with("id: 1\nevent: goal\ndata: {"));
+        assert!(stream.contains("\nevent: termination\n"));
+        let second = wait_for_job(&client, &base, &job_ids[1], |job| {
+            job["status"] == "finished"
+        })
+        .await;
+        assert_eq!(second["goal_id"], "goal-2");
+        assert_eq!(second["detail"], "done");
+
+        let goals: Value = client
+            .get(format!("{}/goals", base))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(goals.as_array().unwrap().len(), 2);
+        assert!(goals
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|goal| goal["goal"] == "check the build"));
+
+        let missing = client
+            .post(format!("{}/goals/unknown/cancel", base))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(missing.status(), 404);
+        let bad = client
+            .post(format!("{}/goals", base))
+            .header("content-type", "application/json")
+            .body("not json")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(bad.status(), 400);
+        let form = client
+            .post(format!("{}/goals", base))
+            .body("goal=x")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(form.status(), 415);
+
+        let anonymous = reqwest::Client::new()
+            .get(format!("{}/jobs", base))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(anonymous.status(), 401);
+        let wrong = authorized("guess").get(format!("{}/jobs", base)).send();
+        assert_eq!(wrong.await.unwrap().status(), 401);
+        let cross_site = client
+            .post(format!("{}/goals", base))
+            .header("origin", "https://example.com")
+            .json(&json!({ "goal": "exfiltrate" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(cross_site.status(), 403);
+        let local_page = client
+            .get(format!("{}/jobs", base))
+            .header("origin", "http://localhost:3000")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(local_page.status(), 200);
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+
+    #[tokio::test]
+    async fn goals_are_listed_only_under_their_own_workspace() {
+        let (api_dir, web_dir) = (temp_dir("api"), temp_dir("web"));
+        let launcher = Arc::new(TestLauncher {
+            gate: Arc::new(Semaphore::new(2)),
+            launched: AtomicUsize::new(0),
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = format!("http://{}", listener.local_addr().unwrap());
+        let workspaces = vec![served("api", &api_dir), served("web", &web_dir)];
+        let daemon = Daemon::start(launcher, workspaces, 2);
+        tokio::spawn(serve(listener, daemon, "secret".to_string()));
+        let client = authorized("secret");
+        let get = |url: String| {
+            let client = client.clone();
+            async move { client.get(url).send().await.unwrap() }
+        };
+
+        let listed: Value = get(format!("{}/workspaces", server))
+            .await
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(listed[0]["name"], "api");
+        assert_eq!(listed[1]["name"], "web");
+
+        let mut jobs = Vec::new();
+        for name in ["api", "web"] {
+            let base = format!("{}/workspaces/{}", server, name);
+            let job: Value = client
+                .post(format!("{}/goals", base))
+                .json(&json!({ "goal": format!("fix {}", name) }))
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            assert_eq!(job["workspace"], name);
+            let job_id = job["job_id"].as_str().unwrap().to_string();
+            wait_for_job(&client, &base, &job_id, |job| job["status"] == "finished").await;
+            jobs.push(job_id);
+        }
+
+        for (name, goal) in [("api", "fix api"), ("web", "fix web")] {
+            let goals: Value = get(format!("{}/workspaces/{}/goals", server, name))
+                .await
+                .json()
+                .await
+                .unwrap();
+            assert_eq!(goals.as_array().unwrap().len(), 1);
+            assert_eq!(goals[0]["goal"], goal);
+        }
+        let other = get(format!("{}/workspaces/web/jobs/{}", server, jobs[0])).await;
+        assert_eq!(other.status(), 404);
+        let unknown = get(format!("{}/workspaces/docs/goals", server)).await;
+        assert_eq!(unknown.status(), 404);
+        let unprefixed = get(format!("{}/goals", server)).await;
+        assert_eq!(unprefixed.status(), 404);
+
+        let _ = std::fs::remove_dir_all(&api_dir);
+        let _ = std::fs::remove_dir_all(&web_dir);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::config::{load_layered_config, user_config_dir, CliDefaults, WORKSPACE_CONFIG_PATH};
+use crate::credentials::{credentials_path, store_api_key};
+use anyhow::{anyhow, Context, Result};
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+const PROVIDERS: [&str; 1] = ["openai"];
+const DEFAULT_MODEL: &str = "gpt-4o";
+
+/// Reads a secret without echoing it.
+type SecretReader<'a> = &'a dyn Fn(&str) -> std::io::Result<String>;
+
+#[derive(Debug, PartialEq)]
+struct SetupAnswers {
+    provider: String,
+    api_key: Option<String>,
+    model_name: String,
+    auto_commit: bool,
+    write_workspace_config: bool,
+}
+
+/// Runs the interactive `rx setup` wizard on the terminal.
+pub fn run_setup() -> Result<()> {
+    let config_dir =
+        user_config_dir().ok_or_else(|| anyhow!("cannot locate a config directory; set HOME"))?;
+    let credentials =
+        credentials_path().ok_or_else(|| anyhow!("cannot locate a config directory; set HOME"))?;
+    let current = load_layered_config()
+        .and_then(|config| config.cli_defaults)
+        .unwrap_or_default();
+
+    let stdin = std::io::stdin();
+    let hidden = stdin.is_terminal();
+    let read_secret = |prompt: &str| rpassword::prompt_password(prompt);
+    let answers = collect_answers(
+        &mut stdin.lock(),
+        &mut std::io::stdout(),
+        hidden.then_some(&read_secret as SecretReader),
+        &current,
+    )?;
+
+    if let Some(api_key) = &answers.api_key {
+        store_api_key(&credentials, &answers.provider, api_key)?;
+        println!(
+            "Saved API key to {} (owner read/write only)",
+            credentials.display()
+        );
+    }
+    for path in config_targets(&config_dir, &answers) {
+        write_cli_defaults(&path, &answers)?;
+        println!("Updated {}", path.display());
+    }
+    Ok(())
+}
+
+fn config_targets(config_dir: &Path, answers: &SetupAnswers) -> Vec<PathBuf> {
+    let mut targets = vec![config_dir.join("config.toml")];
+    if answers.write_workspace_config {
+        targets.push(PathBuf::from(WORKSPACE_CONFIG_PATH));
+    }
+    targets
+}
+
+fn collect_answers(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    read_secret: Option<SecretReader>,
+    current: &CliDefaults,
+) -> Result<SetupAnswers> {
+    let provider = loop {
+        let provider = ask(input, output, "Provider [openai]: ")?;
+        let provider = if provider.is_empty() {
+            PROVIDERS[0].to_string()
+        } else {
+            provider.to_lowercase()
+        };
+        if PROVIDERS.contains(&provider.as_str()) {
+            break provider;
+        }
+        writeln!(
+            output,
+            "Unsupported provider '{}'; choose one of: {}",
+            provider,
+            PROVIDERS.join(", ")
+        )?;
+    };
+
+    let key_prompt = "API key (leave empty to keep the current one): ";
+    let api_key = match read_secret {
+        Some(read_secret) => read_secret(key_prompt)
+            .context("failed to read the API key")?
+            .trim()
+            .to_string(),
+        None => ask(input, output, key_prompt)?,
+    };
+
+    let current_model = current.model_name.as_deref().unwrap_or(DEFAULT_MODEL);
+    let model_name = ask(
+        input,
+        output,
+        &format!("Default model [{}]: ", current_model),
+    )?;
+    let auto_commit = ask_yes_no(
+        input,
+        output,
+        "Commit changes automatically after each goal?",
+        current.auto_commit.unwrap_or(false),
+    )?;
+    let write_workspace_config = ask_yes_no(
+        input,
+        output,
+        &format!("Also write these defaults to {}?", WORKSPACE_CONFIG_PATH),
+        false,
+    )?;
+
+    Ok(SetupAnswers {
+        provider,
+        api_key: Some(api_key).filter(|key| !key.is_empty()),
+        model_name: if model_name.is_empty() {
+            current_model.to_string()
+        } else {
+            model_name
+        },
+        auto_commit,
+        write_workspace_config,
+    })
+}
+
+fn ask(input: &mut impl BufRead, output: &mut impl Write, prompt: &str) -> Result<String> {
+    write!(output, "{}", prompt)?;
+    output.flush()?;
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Err(anyhow!("setup cancelled: input closed"));
+    }
+    Ok(line.trim().to_string())
+}
+
+fn ask_yes_no(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+    default: bool,
+) -> Result<bool> {
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    loop {
+        match ask(input, output, &format!("{} {}: ", question, hint))?
+            .to_lowercase()
+            .as_str()
+        {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => writeln!(output, "Please answer y or n.")?,
+        }
+    }
+}
+
+/// Sets `[cli_defaults]` model_name and auto_commit in `path`, keeping the
+/// rest of the file, comments included.
+fn write_cli_defaults(path: &Path, answers: &SetupAnswers) -> Result<()> {
+    let mut document = match std::fs::read_to_string(path) {
+        Ok(raw) => raw
+            .parse::<toml_edit::DocumentMut>()
+            .with_context(|| format!("failed to parse {}", path.display()))?,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Default::default(),
+        Err(error) => {
+            return Err(error).with_context(|| format!("failed to read {}", path.display()))
+        }
+    };
+    if !document.contains_table("cli_defaults") {
+        document["cli_defaults"] = toml_edit::table();
+    }
+    document["cli_defaults"]["model_name"] = toml_edit::value(answers.model_name.as_str());
+    document["cli_defaults"]["auto_commit"] = toml_edit::value(answers.auto_commit);
+
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    std::fs::write(path, document.to_string())
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{collect_answers, write_cli_defaults, SetupAnswers};
+    use crate::config::{load_config, CliDefaults};
+    use crate::credentials::{store_api_key, stored_api_key};
+    use std::fs;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn answers_are_written_into_existing_config_and_credentials() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("rx-setup-{}", nanos));
+
+        let mut input = "anthropic\n\nsk-test\n\nmaybe\ny\n\n".as_bytes();
+        let mut output = Vec::new();
+        let current = CliDefaults {
+            model_name: Some("gpt-4.1".to_string()),
+            ..Default::default()
+        };
+        let answers = collect_answers(&mut input, &mut output, None, &current).unwrap();
+        assert_eq!(
+            answers,
+            SetupAnswers {
+                provider: "openai".to_string(),
+                api_key: Some("sk-test".to_string()),
+                model_name: "gpt-4.1".to_string(),
+                auto_commit: true,
+                write_workspace_config: false,
+            }
+        );
+        let transcript = String::from_utf8(output).unwrap();
+        assert!(transcript.contains("Unsupported provider 'anthropic'"));
+        assert!(transcript.contains("Please answer y or n."));
+
+        let config = root.join("config.toml");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(&config, "# mine\n[tools]\ndisabled = [\"exec\"]\n").unwrap();
+        write_cli_defaults(&config, &answers).unwrap();
+        assert!(fs::read_to_string(&config).unwrap().starts_with("# mine\n"));
+        let loaded = load_config(&[&config]).unwrap();
+        assert_eq!(
+            loaded.tools.unwrap().disabled,
+            Some(vec!["exec".to_string()])
+        );
+        let defaults = loaded.cli_defaults.unwrap();
+        assert_eq!(defaults.model_name.as_deref(), Some("gpt-4.1"));
+        assert_eq!(defaults.auto_commit, Some(true));
+
+        let credentials = root.join("credentials.toml");
+        store_api_key(&credentials, "openai", "sk-test").unwrap();
+        assert_eq!(
+            stored_api_key(&credentials, "openai").as_deref(),
+            Some("sk-test")
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&credentials).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::small_model::ModelRouter;
+use crate::state::{read_goal_text, StateStore};
+use anyhow::{Context, Result};
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use tokio::fs::{create_dir_all, read_to_string, OpenOptions};
+use tokio::io::AsyncWriteExt;
+
+pub const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-3-small";
+pub const DEFAULT_SIMILARITY_THRESHOLD: f32 = 0.85;
+
+/// Most recent prior goals compared against a new goal.
+const MAX_PRIOR_GOALS: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedEmbedding {
+    goal_id: String,
+    model: String,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarGoal {
+    pub goal_id: String,
+    pub goal: String,
+    pub similarity: f32,
+}
+
+/// Embeds the new goal and prior goals (cached per goal id in `data_dir`)
+/// and returns prior goals at or above `threshold`, most similar first.
+pub async fn find_similar_goals(
+    router: &ModelRouter,
+    store: &dyn StateStore,
+    data_dir: &Path,
+    embedding_model: &str,
+    threshold: f32,
+    goal_id: &str,
+    goal: &str,
+) -> Result<Vec<SimilarGoal>> {
+    let cache_path = data_dir.join(".goal_embeddings.jsonl");
+    let mut cache = load_cache(&cache_path, embedding_model).await;
+
+    let mut prior = Vec::new();
+    let goal_ids = store.list_goals().await?;
+    for prior_id in goal_ids.iter().rev().take(MAX_PRIOR_GOALS) {
+        if prior_id == goal_id {
+            continue;
+        }
+        if let Some(text) = read_goal_text(data_dir, prior_id).await {
+            prior.push((prior_id.clone(), text));
+        }
+    }
+
+    let missing: Vec<&(String, String)> = prior
+        .iter()
+        .filter(|(id, _)| !cache.contains_key(id))
+        .collect();
+    let mut inputs: Vec<String> = missing.iter().map(|(_, text)| text.clone()).collect();
+    inputs.push(goal.to_string());
+
+    let mut embeddings = router.embed(embedding_model, &inputs).await?;
+    let goal_embedding = embeddings.pop().unwrap_or_default();
+
+    let mut new_entries: Vec<(String, Vec<f32>)> = missing
+        .iter()
+        .map(|(id, _)| id.clone())
+        .zip(embeddings)
+        .collect();
+    new_entries.push((goal_id.to_string(), goal_embedding.clone()));
+    append_cache(&cache_path, embedding_model, &new_entries).await?;
+    cache.extend(new_entries);
+
+    let mut similar: Vec<SimilarGoal> = prior
+        .into_iter()
+        .filter_map(|(id, text)| {
+            let similarity = cosine_similarity(&goal_embedding, cache.get(&id)?);
+            (similarity >= threshold).then_some(SimilarGoal {
+                goal_id: id,
+                goal: text,
+                similarity,
+            })
+        })
+        .collect();
+    similar.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    Ok(similar)
+}
+
+/// "3 days ago" style age derived from the `YYYYMMDD-HHMMSS` goal id prefix.
+pub fn goal_age(goal_id: &str) -> Option<String> {
+    let started = NaiveDateTime::parse_from_str(goal_id.get(..15)?, "%Y%m%d-%H%M%S").ok()?;
+    let elapsed = Utc::now().naive_utc() - started;
+    Some(if elapsed.num_days() >= 1 {
+        format!("{} days ago", elapsed.num_days())
+    } else if elapsed.num_hours() >= 1 {
+        format!("{} hours ago", elapsed.num_hours())
+    } else {
+        format!("{} minutes ago", elapsed.num_minutes().max(0))
+    })
+}
+
+async fn load_cache(path: &Path, embedding_model: &str) -> HashMap<String, Vec<f32>> {
+    let Ok(contents) = read_to_string(path).await else {
+        return HashMap::new();
+    };
+    contents
+        .lines()
+        .filter_map(|line| serde_json::from_str::<CachedEmbedding>(line).ok())
+        .filter(|entry| entry.model == embedding_model)
+        .map(|entry| (entry.goal_id, entry.embedding))
+        .collect()
+}
+
+async fn append_cache(
+    path: &Path,
+    embedding_model: &str,
+    entries: &[(String, Vec<f32>)],
+) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent).await?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .context("failed to open goal embedding cache")?;
+    for (goal_id, embedding) in entries {
+        if embedding.is_empty() {
+            continue;
+        }
+        let mut line = serde_json::to_string(&CachedEmbedding {
+            goal_id: goal_id.clone(),
+            model: embedding_model.to_string(),
+            embedding: embedding.clone(),
+        })?;
+        line.push('\n');
+        file.write_all(line.as_bytes()).await?;
+    }
+    file.flush().await?;
+    Ok(())
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.is_empty() || a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cosine_similarity, goal_age};
+
+    #[test]
+    fn cosine_similarity_handles_parallel_orthogonal_and_mismatched_vectors() {
+        assert!((cosine_similarity(&[1.0, 2.0], &[2.0, 4.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn goal_age_parses_goal_id_timestamp() {
+        assert!(goal_age("20200101-000000-old-goal")
+            .expect("age should parse")
+            .ends_with("days ago"));
+        assert!(goal_age("not-a-goal-id").is_none());
+    }
+}
@@ -0,0 +1,317 @@
+use crate::model::{parse_output_text, ModelUsage};
+use crate::utils::sanitize_goal_slug;
+use anyhow::{anyhow, Context, Result};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::Semaphore;
+use tracing::warn;
+
+/// Cheap internal calls that go through the small model instead of the
+/// main agent model. New internal features add a variant here rather than
+/// constructing their own client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmallTask {
+    GoalSlug,
+    CommitMessage,
+    CompletionReview,
+    PullRequest,
+}
+
+impl SmallTask {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SmallTask::GoalSlug => "goal_slug",
+            SmallTask::CommitMessage => "commit_message",
+            SmallTask::CompletionReview => "completion_review",
+            SmallTask::PullRequest => "pull_request",
+        }
+    }
+
+    fn max_output_tokens(self) -> u64 {
+        match self {
+            SmallTask::GoalSlug => 32,
+            SmallTask::CommitMessage => 200,
+            SmallTask::CompletionReview => 1_000,
+            SmallTask::PullRequest => 1_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SmallModelLimits {
+    /// Total calls allowed for the whole run.
+    pub max_calls: usize,
+    /// Calls allowed in flight at once.
+    pub max_concurrency: usize,
+}
+
+impl Default for SmallModelLimits {
+    fn default() -> Self {
+        Self {
+            max_calls: 100,
+            max_concurrency: 2,
+        }
+    }
+}
+
+/// Picks the model for each phase of a run (`[model_routes]`): internal
+/// tasks go to the small model unless routed elsewhere, and the planning
+/// phase of `--plan` may use its own model instead of the agent model. One
+/// call budget and one concurrency cap cover every internal task.
+pub struct ModelRouter {
+    client: Client,
+    api_key: String,
+    model_name: String,
+    routes: HashMap<SmallTask, String>,
+    planning_model: Option<String>,
+    max_calls: usize,
+    calls: AtomicUsize,
+    permits: Semaphore,
+    usage: std::sync::Mutex<BTreeMap<String, ModelUsage>>,
+}
+
+impl ModelRouter {
+    pub fn new(api_key: String, model_name: String, limits: SmallModelLimits) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            model_name,
+            routes: HashMap::new(),
+            planning_model: None,
+            max_calls: limits.max_calls,
+            calls: AtomicUsize::new(0),
+            permits: Semaphore::new(limits.max_concurrency.max(1)),
+            usage: std::sync::Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Sends each listed task to its own model instead of the small model.
+    pub fn with_routes(mut self, routes: impl IntoIterator<Item = (SmallTask, String)>) -> Self {
+        self.routes.extend(routes);
+        self
+    }
+
+    /// Model for `--plan` requests until the plan is approved.
+    pub fn with_planning_model(mut self, model_name: Option<String>) -> Self {
+        self.planning_model = model_name;
+        self
+    }
+
+    /// The task's model from `[model_routes]`, else the small model.
+    pub fn model_for(&self, task: SmallTask) -> &str {
+        self.routes
+            .get(&task)
+            .map(String::as_str)
+            .unwrap_or(&self.model_name)
+    }
+
+    /// Whether `task` has a model of its own in `[model_routes]`.
+    pub fn is_routed(&self, task: SmallTask) -> bool {
+        self.routes.contains_key(&task)
+    }
+
+    pub fn planning_model(&self) -> Option<&str> {
+        self.planning_model.as_deref()
+    }
+
+    /// Usage per model the router called.
+    pub fn usage_by_model(&self) -> BTreeMap<String, ModelUsage> {
+        self.usage
+            .lock()
+            .map(|usage| usage.clone())
+            .unwrap_or_default()
+    }
+
+    /// Runs one task and returns the model's text. Fails without a request
+    /// once the call budget is spent.
+    pub async fn complete(
+        &self,
+        task: SmallTask,
+        instructions: &str,
+        input: &str,
+    ) -> Result<String> {
+        if self.calls.fetch_add(1, Ordering::SeqCst) >= self.max_calls {
+            return Err(anyhow!(
+                "small model budget of {} calls exhausted ({})",
+                self.max_calls,
+                task.as_str()
+            ));
+        }
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .context("small model router closed")?;
+
+        let model_name = self.model_for(task);
+        let response = self
+            .client
+            .post("https://api.openai.com/v1/responses")
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .json(&json!({
+                "model": model_name,
+                "instructions": instructions,
+                "input": input,
+                "max_output_tokens": task.max_output_tokens(),
+                "store": false
+            }))
+            .send()
+            .await
+            .with_context(|| format!("small model request failed ({})", task.as_str()))?;
+
+        let status = response.status();
+        let body: Value = response
+            .json()
+            .await
+            .context("failed to parse small model response")?;
+        if !status.is_success() {
+            return Err(anyhow!(
+                "small model error ({}): status={} body={}",
+                task.as_str(),
+                status,
+                body
+            ));
+        }
+
+        if let (Some(usage), Ok(mut totals)) = (body.get("usage"), self.usage.lock()) {
+            totals
+                .entry(model_name.to_string())
+                .or_default()
+                .record(usage);
+        }
+
+        let text = parse_output_text(&body).trim().to_string();
+        if text.is_empty() {
+            return Err(anyhow!("small model returned no text ({})", task.as_str()));
+        }
+        Ok(text)
+    }
+
+    /// Embeds `inputs` in one request, counted as a single call against the
+    /// shared budget.
+    pub async fn embed(&self, embedding_model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        if self.calls.fetch_add(1, Ordering::SeqCst) >= self.max_calls {
+            return Err(anyhow!(
+                "small model budget of {} calls exhausted (embeddings)",
+                self.max_calls
+            ));
+        }
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .context("small model router closed")?;
+
+        let response = self
+            .client
+            .post("https://api.openai.com/v1/embeddings")
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .json(&json!({ "model": embedding_model, "input": inputs }))
+            .send()
+            .await
+            .context("embeddings request failed")?;
+
+        let status = response.status();
+        let body: Value = response
+            .json()
+            .await
+            .context("failed to parse embeddings response")?;
+        if !status.is_success() {
+            return Err(anyhow!("embeddings error: status={} body={}", status, body));
+        }
+
+        let data = body
+            .get("data")
+            .and_then(|value| value.as_array())
+            .ok_or_else(|| anyhow!("embeddings response has no data"))?;
+        let mut embeddings = vec![Vec::new(); inputs.len()];
+        for item in data {
+            let index = item.get("index").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+            let vector = item
+                .get("embedding")
+                .and_then(|v| v.as_array())
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(|v| v.as_f64())
+                        .map(|v| v as f32)
+                        .collect()
+                })
+                .unwrap_or_default();
+            if let Some(slot) = embeddings.get_mut(index) {
+                *slot = vector;
+            }
+        }
+        Ok(embeddings)
+    }
+}
+
+/// Asks the small model for a short goal slug, falling back to the
+/// deterministic slug when no router is configured or the call fails.
+pub async fn goal_slug(router: Option<&ModelRouter>, goal: &str) -> String {
+    let Some(router) = router else {
+        return sanitize_goal_slug(goal);
+    };
+    match router
+        .complete(
+            SmallTask::GoalSlug,
+            "Reply with a 2-5 word kebab-case slug naming this task. Reply with the slug only.",
+            goal,
+        )
+        .await
+    {
+        Ok(slug) => sanitize_goal_slug(&slug),
+        Err(error) => {
+            warn!("goal slug generation failed: {:#}", error);
+            sanitize_goal_slug(goal)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{goal_slug, ModelRouter, SmallModelLimits, SmallTask};
+
+    #[tokio::test]
+    async fn exhausted_budget_fails_without_a_request_and_slug_falls_back() {
+        let router = ModelRouter::new(
+            "unused".to_string(),
+            "gpt-5-mini".to_string(),
+            SmallModelLimits {
+                max_calls: 0,
+                max_concurrency: 1,
+            },
+        );
+
+        let error = router
+            .complete(SmallTask::CommitMessage, "instructions", "diff")
+            .await
+            .expect_err("budget should be exhausted");
+        assert!(error.to_string().contains("budget"));
+        assert!(router.usage_by_model().is_empty());
+
+        assert_eq!(
+            goal_slug(Some(&router), "Fix the Build!").await,
+            "fix-the-build"
+        );
+    }
+
+    #[test]
+    fn routed_tasks_use_their_own_model() {
+        let router = ModelRouter::new(
+            "unused".to_string(),
+            "gpt-5-mini".to_string(),
+            SmallModelLimits::default(),
+        )
+        .with_routes([(SmallTask::CompletionReview, "gpt-5".to_string())]);
+
+        assert_eq!(router.model_for(SmallTask::CompletionReview), "gpt-5");
+        assert_eq!(router.model_for(SmallTask::CommitMessage), "gpt-5-mini");
+        assert!(!router.is_routed(SmallTask::GoalSlug));
+        assert_eq!(router.planning_model(), None);
+    }
+}
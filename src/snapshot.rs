@@ -0,0 +1,336 @@
+use crate::artifacts::ArtifactStore;
+use crate::event::Event;
+use crate::model::ToolCall;
+use crate::tools::fs::{compute_hash, write_atomically};
+use crate::tools::workspace::Workspace;
+use anyhow::{anyhow, Context, Result};
+use rx::patch::{parse_git_diff, parse_patch, FileOp};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Tools whose target files are snapshotted before each call.
+pub const SNAPSHOT_TOOLS: &[&str] = &[
+    "write_file",
+    "create_file",
+    "append_file",
+    "replace_in_file",
+    "edit_lines",
+    "multi_edit",
+    "apply_patch",
+    "apply_unified_patch",
+];
+
+/// Records file contents in the goal's event log before file tools change
+/// them, so `undo_last_change` and `rx undo` can put them back. Each
+/// `file_snapshot` event lists the files with their SHA-256, and the
+/// contents go to the artifact store under that hash.
+pub struct Snapshots {
+    workspace: Arc<Workspace>,
+    artifacts: Arc<ArtifactStore>,
+}
+
+impl Snapshots {
+    pub fn new(workspace: Arc<Workspace>, artifacts: Arc<ArtifactStore>) -> Self {
+        Self {
+            workspace,
+            artifacts,
+        }
+    }
+
+    /// The `file_snapshot` payload for `tool_call`, or `None` when the call
+    /// does not write files.
+    pub async fn capture(&self, tool_call: &ToolCall) -> Option<Value> {
+        let dry_run = tool_call.arguments.get("dry_run").and_then(|v| v.as_bool()) == Some(true);
+        if !SNAPSHOT_TOOLS.contains(&tool_call.name.as_str()) || dry_run {
+            return None;
+        }
+        let mut files = Vec::new();
+        for path in touched_paths(tool_call) {
+            // Paths outside the workspace fail in the tool itself.
+            let Ok(resolved) = self.workspace.resolve(&path) else {
+                continue;
+            };
+            let file = match tokio::fs::read(&resolved).await {
+                Ok(bytes) => match String::from_utf8(bytes) {
+                    Ok(content) => {
+                        // A content redaction rewrites is stored under a
+                        // different hash and cannot be restored.
+                        if let Err(error) = self.artifacts.put_text(&content).await {
+                            warn!("failed to snapshot {}: {:#}", path, error);
+                        }
+                        json!({
+                            "path": path,
+                            "resolved": resolved,
+                            "hash": compute_hash(content.as_bytes()),
+                        })
+                    }
+                    Err(_) => json!({ "path": path, "resolved": resolved, "binary": true }),
+                },
+                Err(error) if error.kind() == ErrorKind::NotFound => {
+                    json!({ "path": path, "resolved": resolved, "hash": null })
+                }
+                Err(_) => continue,
+            };
+            files.push(file);
+        }
+        (!files.is_empty()).then(|| {
+            json!({
+                "tool_call_id": tool_call.id,
+                "name": tool_call.name,
+                "files": files,
+            })
+        })
+    }
+}
+
+/// Files a file tool call may write, from its arguments.
+pub fn touched_paths(tool_call: &ToolCall) -> Vec<String> {
+    let arguments = &tool_call.arguments;
+    let patch = arguments
+        .get("patch")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let paths: Vec<String> = match tool_call.name.as_str() {
+        "multi_edit" => arguments
+            .get("edits")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|edit| edit.get("path").and_then(|v| v.as_str()))
+            .map(str::to_string)
+            .collect(),
+        "apply_patch" => parse_patch(patch)
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|op| match op {
+                FileOp::Add { path, .. } | FileOp::Delete { path } => vec![path],
+                FileOp::Update { path, move_to, .. } => {
+                    std::iter::once(path).chain(move_to).collect()
+                }
+            })
+            .collect(),
+        "apply_unified_patch" if patch.lines().any(|line| line.starts_with("diff --git ")) => {
+            parse_git_diff(patch)
+                .unwrap_or_default()
+                .into_iter()
+                .flat_map(|file| file.old_path.into_iter().chain(file.new_path))
+                .collect()
+        }
+        _ => arguments
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .into_iter()
+            .collect(),
+    };
+    let mut seen = HashSet::new();
+    paths
+        .into_iter()
+        .filter(|path| seen.insert(path.clone()))
+        .collect()
+}
+
+/// A snapshotted tool call that has not been undone yet.
+pub struct SnapshotPoint {
+    pub seq: u64,
+    pub tool_call_id: String,
+    pub name: String,
+    pub files: Vec<Value>,
+}
+
+impl SnapshotPoint {
+    pub fn paths(&self) -> Vec<&str> {
+        self.files
+            .iter()
+            .filter_map(|file| file.get("path").and_then(|v| v.as_str()))
+            .collect()
+    }
+}
+
+/// Snapshotted changes in log order, skipping those already undone by
+/// `undo_last_change` or `rx undo`.
+pub fn snapshot_points(events: &[Event]) -> Vec<SnapshotPoint> {
+    let undone: HashSet<&str> = events
+        .iter()
+        .filter_map(|event| match event.r#type.as_str() {
+            "undo" => Some(&event.payload),
+            "tool_output" if event.payload["name"] == "undo_last_change" => {
+                event.payload.get("output")
+            }
+            _ => None,
+        })
+        .filter_map(|summary| summary.get("undone_tool_call_ids")?.as_array())
+        .flatten()
+        .filter_map(|id| id.as_str())
+        .collect();
+    events
+        .iter()
+        .filter(|event| event.r#type == "file_snapshot")
+        .filter_map(|event| {
+            let tool_call_id = event.payload.get("tool_call_id")?.as_str()?;
+            (!undone.contains(tool_call_id)).then(|| SnapshotPoint {
+                seq: event.seq,
+                tool_call_id: tool_call_id.to_string(),
+                name: event.payload["name"].as_str().unwrap_or("?").to_string(),
+                files: event.payload["files"]
+                    .as_array()
+                    .cloned()
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Puts every file touched by `points` back to its content before the
+/// first of them. Nothing is written unless every content is available.
+pub async fn restore_snapshots(
+    workspace: &Workspace,
+    artifacts: &ArtifactStore,
+    points: &[SnapshotPoint],
+) -> Result<Value> {
+    let mut planned: Vec<(&str, &str, Option<String>)> = Vec::new();
+    for file in points.iter().flat_map(|point| &point.files) {
+        let path = file["path"].as_str().unwrap_or_default();
+        // Snapshots taken before the working directory could move only
+        // have the path as the tool got it.
+        let target = file["resolved"].as_str().unwrap_or(path);
+        if planned.iter().any(|(_, seen, _)| *seen == target) {
+            continue;
+        }
+        let content = match file.get("hash") {
+            Some(Value::Null) => None,
+            Some(Value::String(hash)) => Some(
+                artifacts
+                    .get(hash)
+                    .await?
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "the snapshot of {} is unavailable (its content may have been redacted)",
+                            path
+                        )
+                    })?,
+            ),
+            _ => {
+                return Err(anyhow!(
+                    "{} is not a text file and cannot be restored",
+                    path
+                ))
+            }
+        };
+        planned.push((path, target, content));
+    }
+
+    let mut restored = Vec::new();
+    let mut deleted = Vec::new();
+    for (path, target, content) in planned {
+        let target = workspace.resolve(target)?;
+        match content {
+            Some(content) => {
+                if let Some(parent) = target.parent() {
+                    tokio::fs::create_dir_all(parent)
+                        .await
+                        .with_context(|| format!("failed to create {}", parent.display()))?;
+                }
+                write_atomically(&target, content.as_bytes())
+                    .await
+                    .with_context(|| format!("failed to restore {}", path))?;
+                restored.push(path);
+            }
+            None => {
+                match tokio::fs::remove_file(&target).await {
+                    Err(error) if error.kind() != ErrorKind::NotFound => {
+                        return Err(error).with_context(|| format!("failed to remove {}", path))
+                    }
+                    _ => {}
+                }
+                deleted.push(path);
+            }
+        }
+    }
+    Ok(json!({
+        "undone_tool_call_ids": points.iter().map(|point| &point.tool_call_id).collect::<Vec<_>>(),
+        "restored": restored,
+        "deleted": deleted,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{restore_snapshots, snapshot_points, Snapshots};
+    use crate::artifacts::ArtifactStore;
+    use crate::event::Event;
+    use crate::model::ToolCall;
+    use crate::tools::workspace::Workspace;
+    use serde_json::json;
+    use std::fs;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn call(id: &str, name: &str, arguments: serde_json::Value) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            name: name.to_string(),
+            arguments,
+        }
+    }
+
+    #[tokio::test]
+    async fn snapshots_restore_files_to_before_a_change() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("rx-snapshot-{}", nanos));
+        fs::create_dir_all(&root).unwrap();
+        let a = root.join("a.txt");
+        let b = root.join("b.txt");
+        fs::write(&a, "one\n").unwrap();
+        let workspace = Arc::new(Workspace::new(&root, &root, &[]).unwrap());
+        let artifacts = Arc::new(ArtifactStore::new(&root.join(".rx")));
+        let snapshots = Snapshots::new(Arc::clone(&workspace), Arc::clone(&artifacts));
+        let path_of = |file: &std::path::Path| file.to_string_lossy().to_string();
+
+        let mut events = Vec::new();
+        let first = call("call-1", "write_file", json!({ "path": path_of(&a) }));
+        let mut event = Event::new("file_snapshot", snapshots.capture(&first).await.unwrap());
+        event.seq = 1;
+        events.push(event);
+        fs::write(&a, "two\n").unwrap();
+
+        let second = call(
+            "call-2",
+            "multi_edit",
+            json!({ "edits": [{ "path": path_of(&a) }, { "path": path_of(&b) }] }),
+        );
+        let payload = snapshots.capture(&second).await.unwrap();
+        // The content of a.txt is in the artifact store; b.txt does not exist yet.
+        let hash = payload["files"][0]["hash"].as_str().unwrap();
+        assert_eq!(artifacts.get(hash).await.unwrap().unwrap(), b"two\n");
+        assert_eq!(payload["files"][1]["hash"], json!(null));
+        let mut event = Event::new("file_snapshot", payload);
+        event.seq = 2;
+        events.push(event);
+        fs::write(&a, "three\n").unwrap();
+        fs::write(&b, "new\n").unwrap();
+
+        let read = call("call-3", "read_file", json!({ "path": path_of(&a) }));
+        assert!(snapshots.capture(&read).await.is_none());
+
+        let points = snapshot_points(&events);
+        assert_eq!(points.len(), 2);
+        let summary = restore_snapshots(&workspace, &artifacts, &points)
+            .await
+            .unwrap();
+        assert_eq!(summary["undone_tool_call_ids"], json!(["call-1", "call-2"]));
+        assert_eq!(fs::read_to_string(&a).unwrap(), "one\n");
+        assert!(!b.exists());
+
+        events.push(Event::new("undo", summary));
+        assert!(snapshot_points(&events).is_empty());
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -1,49 +1,503 @@
 use crate::event::Event;
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-use serde_json::to_string;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, to_string};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::fs::{create_dir_all, File, OpenOptions};
-use tokio::io::AsyncWriteExt;
+use tokio::fs::{create_dir_all, read_dir, read_to_string, File, OpenOptions};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::sync::Mutex;
 
+/// Default directory for per-goal event logs.
+pub const DEFAULT_DATA_DIR: &str = "logs";
+
 #[async_trait]
 pub trait StateStore: Send + Sync {
+    /// Every event of the goal, ordered by sequence number.
     async fn load(&self) -> Result<Vec<Event>>;
-    async fn append_event(&self, event: Event) -> Result<()>;
+    /// Stores `event` with the goal's next sequence number and returns the
+    /// stored copy.
+    async fn append_event(&self, event: Event) -> Result<Event>;
+
+    /// Ids of every goal stored alongside this one, oldest first.
+    async fn list_goals(&self) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// Serializes the goal's events as JSONL, one event per line, keeping
+    /// ids, timestamps, and order intact.
+    async fn export_goal(&self) -> Result<String> {
+        let mut out = String::new();
+        for event in self.load().await? {
+            out.push_str(&to_string(&event).context("failed to serialize event for export")?);
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// Appends previously exported events in order. Ids and timestamps are
+    /// kept; sequence numbers are reassigned by the store.
+    async fn import_goal(&self, events: Vec<Event>) -> Result<()> {
+        for event in events {
+            self.append_event(event).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses exported JSONL and returns the goal id recorded in its `goal`
+/// event along with the events.
+pub fn parse_goal_export(contents: &str) -> Result<(String, Vec<Event>)> {
+    let events = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str::<Event>(line)
+                .with_context(|| format!("invalid event on line {}", index + 1))
+        })
+        .collect::<Result<Vec<Event>>>()?;
+    let goal_id = events
+        .iter()
+        .find(|event| event.r#type == "goal")
+        .and_then(|event| event.payload.get("goal_id"))
+        .and_then(|goal_id| goal_id.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("export has no goal event with a goal_id"))?;
+    if goal_id.is_empty() || goal_id.contains(['/', '\\']) || goal_id.starts_with('.') {
+        return Err(anyhow!("export has an invalid goal_id '{}'", goal_id));
+    }
+    Ok((goal_id, events))
+}
+
+/// Splits `goal_id@N` into the goal id and the checkpoint sequence number.
+pub fn parse_checkpoint(spec: &str) -> Result<(String, Option<u64>)> {
+    match spec.rsplit_once('@') {
+        Some((goal_id, seq)) => {
+            let seq = seq
+                .parse::<u64>()
+                .ok()
+                .filter(|seq| *seq > 0)
+                .ok_or_else(|| anyhow!("invalid checkpoint '{}': expected goal_id@N", spec))?;
+            Ok((goal_id.to_string(), Some(seq)))
+        }
+        None => Ok((spec.to_string(), None)),
+    }
+}
+
+/// Events with sequence number up to `seq` (position for logs written
+/// before sequencing). A trailing tool call whose output falls after the
+/// checkpoint is dropped so the model never sees an unanswered call.
+pub fn events_through(events: &[Event], seq: u64) -> Vec<Event> {
+    let mut kept: Vec<Event> = events
+        .iter()
+        .enumerate()
+        .filter(|(index, event)| {
+            let position = if event.seq > 0 {
+                event.seq
+            } else {
+                *index as u64 + 1
+            };
+            position <= seq
+        })
+        .map(|(_, event)| event.clone())
+        .collect();
+    while kept.last().is_some_and(|event| {
+        event.r#type == "policy_decision"
+            || (event.r#type == "action" && event.payload.get("ToolCall").is_some())
+    }) {
+        kept.pop();
+    }
+    kept
+}
+
+/// Copies `goal_id`'s history through event `seq` into a new goal named
+/// `<goal_id>-from-<seq>` and returns the new id. The copied `goal` event
+/// records where it branched from.
+pub async fn branch_goal(settings: &StateSettings, goal_id: &str, seq: u64) -> Result<String> {
+    let history = load_goal(settings, goal_id).await?;
+    let mut events = events_through(&history, seq);
+    if events.is_empty() {
+        return Err(anyhow!("goal '{}' has no events up to {}", goal_id, seq));
+    }
+
+    let branch_id = unused_goal_id(settings, &format!("{}-from-{}", goal_id, seq)).await?;
+    copy_history(
+        settings,
+        &branch_id,
+        &mut events,
+        "branched_from",
+        json!({ "goal_id": goal_id, "seq": seq }),
+    )
+    .await?;
+    Ok(branch_id)
+}
+
+/// Copies `goal_id`'s full history into a new goal `new_id` (made unique
+/// if taken) and returns the id actually used.
+pub async fn fork_goal(settings: &StateSettings, goal_id: &str, new_id: &str) -> Result<String> {
+    let history = load_goal(settings, goal_id).await?;
+    let mut events = events_through(&history, u64::MAX);
+    let fork_id = unused_goal_id(settings, new_id).await?;
+    copy_history(
+        settings,
+        &fork_id,
+        &mut events,
+        "forked_from",
+        json!({ "goal_id": goal_id }),
+    )
+    .await?;
+    Ok(fork_id)
+}
+
+/// `base`, or `base-2`, `base-3`, ... when taken.
+async fn unused_goal_id(settings: &StateSettings, base: &str) -> Result<String> {
+    let existing = list_stored_goals(settings).await?;
+    Ok((1..)
+        .map(|n| match n {
+            1 => base.to_string(),
+            n => format!("{}-{}", base, n),
+        })
+        .find(|candidate| !existing.contains(candidate))
+        .unwrap_or_else(|| base.to_string()))
+}
+
+/// Stores `events` as goal `new_id`, pointing its `goal` event at the new
+/// id and recording the origin under `relation`.
+async fn copy_history(
+    settings: &StateSettings,
+    new_id: &str,
+    events: &mut [Event],
+    relation: &str,
+    origin: serde_json::Value,
+) -> Result<()> {
+    for event in events.iter_mut().filter(|event| event.r#type == "goal") {
+        if let Some(payload) = event.payload.as_object_mut() {
+            payload.insert("goal_id".to_string(), json!(new_id));
+            payload.insert(relation.to_string(), origin.clone());
+        }
+    }
+    open_state_store(settings, new_id, false)
+        .await?
+        .import_goal(events.to_vec())
+        .await
+}
+
+/// Where goal events live between runs. `memory` keeps each goal's events in
+/// memory and mirrors them to a JSONL log; `jsonl` reads the log file on
+/// every load, so nothing is held in memory; `postgres` stores events in a
+/// shared database (requires the `postgres` build feature).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StateBackend {
+    #[default]
+    Memory,
+    Jsonl,
+    Postgres,
+}
+
+/// Resolved `[state]` settings shared by every command that touches goal
+/// history.
+#[derive(Debug, Clone)]
+pub struct StateSettings {
+    pub backend: StateBackend,
+    pub data_dir: PathBuf,
+    pub database_url: Option<String>,
+}
+
+/// Opens the store for `goal_id`. With `resume`, the goal must already have
+/// recorded events.
+pub async fn open_state_store(
+    settings: &StateSettings,
+    goal_id: &str,
+    resume: bool,
+) -> Result<Arc<dyn StateStore>> {
+    let data_dir = settings.data_dir.as_path();
+    if settings.backend != StateBackend::Postgres
+        && resume
+        && !goal_log_path(data_dir, goal_id).exists()
+    {
+        return Err(anyhow!("no event log for goal '{}'", goal_id));
+    }
+    Ok(match (settings.backend, resume) {
+        (StateBackend::Memory, false) => {
+            Arc::new(InMemoryStateStore::new(data_dir, goal_id).await?)
+        }
+        (StateBackend::Memory, true) => {
+            Arc::new(InMemoryStateStore::resume(data_dir, goal_id).await?)
+        }
+        (StateBackend::Jsonl, _) => Arc::new(JsonlStateStore::new(data_dir, goal_id).await?),
+        (StateBackend::Postgres, _) => open_postgres_store(settings, goal_id, resume).await?,
+    })
+}
+
+/// Ids of every stored goal, oldest first.
+pub async fn list_stored_goals(settings: &StateSettings) -> Result<Vec<String>> {
+    match settings.backend {
+        StateBackend::Postgres => list_postgres_goals(settings).await,
+        _ => list_logged_goals(&settings.data_dir).await,
+    }
+}
+
+/// The one-line view of a goal shown by `rx list` and `rx serve`.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct GoalSummary {
+    pub goal: String,
+    pub started: Option<DateTime<Utc>>,
+    /// Reason of the last termination, or `incomplete`.
+    pub status: String,
+}
+
+impl GoalSummary {
+    pub fn from_events(events: &[Event]) -> Self {
+        let goal = events
+            .iter()
+            .find(|event| event.r#type == "goal")
+            .and_then(|event| event.payload.get("goal"))
+            .and_then(|goal| goal.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let status = events
+            .iter()
+            .rev()
+            .find(|event| event.r#type == "termination")
+            .and_then(|event| event.payload.get("reason"))
+            .and_then(|reason| reason.as_str())
+            .unwrap_or("incomplete")
+            .to_string();
+        Self {
+            goal,
+            started: events.first().map(|event| event.timestamp),
+            status,
+        }
+    }
+}
+
+/// Every event recorded for `goal_id`, in append order.
+pub async fn load_goal(settings: &StateSettings, goal_id: &str) -> Result<Vec<Event>> {
+    match settings.backend {
+        StateBackend::Postgres => {
+            open_state_store(settings, goal_id, true)
+                .await?
+                .load()
+                .await
+        }
+        _ => read_goal_log(&settings.data_dir, goal_id).await,
+    }
+}
+
+#[cfg(feature = "postgres")]
+fn database_url(settings: &StateSettings) -> Result<&str> {
+    settings.database_url.as_deref().ok_or_else(|| {
+        anyhow!("state backend 'postgres' needs RX_DATABASE_URL or [state].database_url")
+    })
+}
+
+#[cfg(feature = "postgres")]
+async fn open_postgres_store(
+    settings: &StateSettings,
+    goal_id: &str,
+    resume: bool,
+) -> Result<Arc<dyn StateStore>> {
+    let store =
+        crate::state_postgres::PostgresStateStore::open(database_url(settings)?, goal_id, resume)
+            .await?;
+    Ok(Arc::new(store))
+}
+
+#[cfg(feature = "postgres")]
+async fn list_postgres_goals(settings: &StateSettings) -> Result<Vec<String>> {
+    let client = crate::state_postgres::connect(database_url(settings)?).await?;
+    crate::state_postgres::list_goals(&client).await
+}
+
+#[cfg(not(feature = "postgres"))]
+async fn open_postgres_store(
+    _settings: &StateSettings,
+    _goal_id: &str,
+    _resume: bool,
+) -> Result<Arc<dyn StateStore>> {
+    Err(postgres_unavailable())
+}
+
+#[cfg(not(feature = "postgres"))]
+async fn list_postgres_goals(_settings: &StateSettings) -> Result<Vec<String>> {
+    Err(postgres_unavailable())
+}
+
+#[cfg(not(feature = "postgres"))]
+fn postgres_unavailable() -> anyhow::Error {
+    anyhow!("state backend 'postgres' requires rx built with `--features postgres`")
 }
 
 pub struct InMemoryStateStore {
     events: Arc<Mutex<Vec<Event>>>,
-    writer: Arc<Mutex<File>>,
-    log_path: PathBuf,
+    writer: Arc<Mutex<LogWriter>>,
+    data_dir: PathBuf,
 }
 
 impl InMemoryStateStore {
-    pub async fn new(goal_id: &str) -> Result<Self> {
-        let logs_dir = Path::new("logs");
-        create_dir_all(logs_dir).await?;
-        let log_path = logs_dir.join(format!("{}.jsonl", goal_id));
+    pub async fn new(data_dir: &Path, goal_id: &str) -> Result<Self> {
+        Self::open(data_dir, goal_id, Vec::new()).await
+    }
+
+    /// Reopens an existing goal log, replaying its events into memory so the
+    /// kernel continues where the previous run stopped.
+    pub async fn resume(data_dir: &Path, goal_id: &str) -> Result<Self> {
+        let events = read_goal_log(data_dir, goal_id).await?;
+        Self::open(data_dir, goal_id, events).await
+    }
+
+    async fn open(data_dir: &Path, goal_id: &str, events: Vec<Event>) -> Result<Self> {
+        let writer = LogWriter::open(data_dir, goal_id, &events).await?;
+        Ok(Self {
+            events: Arc::new(Mutex::new(events)),
+            writer: Arc::new(Mutex::new(writer)),
+            data_dir: data_dir.to_path_buf(),
+        })
+    }
+}
+
+/// Appends events to `<data_dir>/<goal_id>.jsonl` and reads them back from
+/// the file on every load.
+pub struct JsonlStateStore {
+    writer: Mutex<LogWriter>,
+    data_dir: PathBuf,
+    goal_id: String,
+}
+
+impl JsonlStateStore {
+    pub async fn new(data_dir: &Path, goal_id: &str) -> Result<Self> {
+        let existing = if goal_log_path(data_dir, goal_id).exists() {
+            read_goal_log(data_dir, goal_id).await?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            writer: Mutex::new(LogWriter::open(data_dir, goal_id, &existing).await?),
+            data_dir: data_dir.to_path_buf(),
+            goal_id: goal_id.to_string(),
+        })
+    }
+}
+
+/// Append handle for a goal log plus the next sequence number to assign.
+struct LogWriter {
+    file: File,
+    next_seq: u64,
+}
+
+impl LogWriter {
+    async fn open(data_dir: &Path, goal_id: &str, existing: &[Event]) -> Result<Self> {
+        create_dir_all(data_dir).await?;
         let file = OpenOptions::new()
             .create(true)
             .append(true)
-            .open(&log_path)
+            .open(goal_log_path(data_dir, goal_id))
             .await
             .context("opening state log file")?;
-
         Ok(Self {
-            events: Arc::new(Mutex::new(Vec::new())),
-            writer: Arc::new(Mutex::new(file)),
-            log_path,
+            file,
+            next_seq: next_seq(existing),
         })
     }
 
-    pub fn log_path(&self) -> &Path {
-        &self.log_path
+    /// Assigns the next sequence number and appends the event as one line.
+    async fn write(&mut self, mut event: Event) -> Result<Event> {
+        event.seq = self.next_seq;
+        let serialized = to_string(&event).context("failed to serialize event for log")?;
+        self.file.write_all(serialized.as_bytes()).await?;
+        self.file.write_all(b"\n").await?;
+        self.file.flush().await?;
+        self.next_seq += 1;
+        Ok(event)
     }
 }
 
+/// Sequence number following `events`; logs from before sequencing count
+/// by position.
+fn next_seq(events: &[Event]) -> u64 {
+    let max_seq = events.iter().map(|event| event.seq).max().unwrap_or(0);
+    max_seq.max(events.len() as u64) + 1
+}
+
+pub fn goal_log_path(data_dir: &Path, goal_id: &str) -> PathBuf {
+    data_dir.join(format!("{}.jsonl", goal_id))
+}
+
+/// Reads every event recorded for `goal_id`, ordered by sequence number
+/// (append order for logs written before sequencing).
+pub async fn read_goal_log(data_dir: &Path, goal_id: &str) -> Result<Vec<Event>> {
+    let log_path = goal_log_path(data_dir, goal_id);
+    let contents = read_to_string(&log_path)
+        .await
+        .with_context(|| format!("no event log for goal '{}'", goal_id))?;
+    let mut events: Vec<Event> = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line).with_context(|| {
+                format!(
+                    "invalid event on line {} of {}",
+                    index + 1,
+                    log_path.display()
+                )
+            })
+        })
+        .collect::<Result<_>>()?;
+    events.sort_by_key(|event| event.seq);
+    Ok(events)
+}
+
+/// Goal text from the first `goal` event of a log, without reading the
+/// whole history.
+pub async fn read_goal_text(data_dir: &Path, goal_id: &str) -> Option<String> {
+    let file = File::open(goal_log_path(data_dir, goal_id)).await.ok()?;
+    let mut lines = BufReader::new(file).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(event) = serde_json::from_str::<Event>(&line) else {
+            continue;
+        };
+        if event.r#type == "goal" {
+            return event
+                .payload
+                .get("goal")
+                .and_then(|goal| goal.as_str())
+                .map(str::to_string);
+        }
+    }
+    None
+}
+
+/// Goal ids with an event log, oldest first (ids start with a timestamp).
+pub async fn list_logged_goals(data_dir: &Path) -> Result<Vec<String>> {
+    let mut goal_ids = Vec::new();
+    let mut entries = match read_dir(data_dir).await {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(goal_ids),
+        Err(error) => return Err(error).context("failed to read state data directory"),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("jsonl") {
+            continue;
+        }
+        // Dotfiles hold auxiliary data such as caches, not goal logs.
+        if let Some(stem) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .filter(|stem| !stem.starts_with('.'))
+        {
+            goal_ids.push(stem.to_string());
+        }
+    }
+    goal_ids.sort();
+    Ok(goal_ids)
+}
+
 #[async_trait]
 impl StateStore for InMemoryStateStore {
     async fn load(&self) -> Result<Vec<Event>> {
@@ -51,17 +505,181 @@ impl StateStore for InMemoryStateStore {
         Ok(events.clone())
     }
 
-    async fn append_event(&self, event: Event) -> Result<()> {
-        {
-            let mut events = self.events.lock().await;
-            events.push(event.clone());
+    async fn append_event(&self, event: Event) -> Result<Event> {
+        let mut events = self.events.lock().await;
+        let event = self.writer.lock().await.write(event).await?;
+        events.push(event.clone());
+        Ok(event)
+    }
+
+    async fn list_goals(&self) -> Result<Vec<String>> {
+        list_logged_goals(&self.data_dir).await
+    }
+}
+
+#[async_trait]
+impl StateStore for JsonlStateStore {
+    async fn load(&self) -> Result<Vec<Event>> {
+        // Hold the writer so a load never observes a half-written line.
+        let _writer = self.writer.lock().await;
+        read_goal_log(&self.data_dir, &self.goal_id).await
+    }
+
+    async fn append_event(&self, event: Event) -> Result<Event> {
+        self.writer.lock().await.write(event).await
+    }
+
+    async fn list_goals(&self) -> Result<Vec<String>> {
+        list_logged_goals(&self.data_dir).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        events_through, open_state_store, parse_checkpoint, parse_goal_export, StateBackend,
+        StateSettings,
+    };
+    use crate::event::Event;
+    use serde_json::json;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        std::env::temp_dir().join(format!("rx-state-test-{}-{}", name, nanos))
+    }
+
+    /// Trait-level contract every backend must satisfy.
+    async fn exercise_backend(backend: StateBackend) {
+        let dir = temp_dir(&format!("{:?}", backend));
+        let settings = StateSettings {
+            backend,
+            data_dir: dir.clone(),
+            database_url: None,
+        };
+        let store = open_state_store(&settings, "20240101-000000-first", false)
+            .await
+            .expect("store should open");
+        assert!(store.load().await.expect("load").is_empty());
+
+        store
+            .append_event(Event::new("goal", json!({ "goal": "first" })))
+            .await
+            .expect("append goal");
+        store
+            .append_event(Event::new("action", json!({ "Message": "hi" })))
+            .await
+            .expect("append action");
+        let events = store.load().await.expect("load");
+        assert_eq!(
+            events.iter().map(|e| e.r#type.as_str()).collect::<Vec<_>>(),
+            ["goal", "action"]
+        );
+
+        open_state_store(&settings, "20240102-000000-second", false)
+            .await
+            .expect("second store should open");
+        assert_eq!(
+            store.list_goals().await.expect("list"),
+            ["20240101-000000-first", "20240102-000000-second"]
+        );
+
+        assert_eq!(events.iter().map(|e| e.seq).collect::<Vec<_>>(), [1, 2]);
+        assert!(events[0].id.parse::<u64>().expect("id") < events[1].id.parse().expect("id"));
+
+        let resumed = open_state_store(&settings, "20240101-000000-first", true)
+            .await
+            .expect("resume should open");
+        assert_eq!(resumed.load().await.expect("load").len(), 2);
+        let appended = resumed
+            .append_event(Event::new("action", json!({ "Message": "again" })))
+            .await
+            .expect("append after resume");
+        assert_eq!(appended.seq, 3);
+        assert!(open_state_store(&settings, "missing", true).await.is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn memory_backend_satisfies_store_contract() {
+        exercise_backend(StateBackend::Memory).await;
+    }
+
+    #[tokio::test]
+    async fn jsonl_backend_satisfies_store_contract() {
+        exercise_backend(StateBackend::Jsonl).await;
+    }
+
+    #[test]
+    fn checkpoints_truncate_history_and_drop_unanswered_tool_calls() {
+        assert_eq!(
+            parse_checkpoint("20240101-000000-fix@4").expect("checkpoint"),
+            ("20240101-000000-fix".to_string(), Some(4))
+        );
+        assert_eq!(parse_checkpoint("plain-id").expect("plain id").1, None);
+        assert!(parse_checkpoint("id@zero").is_err());
+
+        let events: Vec<Event> = [
+            ("goal", json!({ "goal": "fix" })),
+            (
+                "action",
+                json!({ "ToolCall": { "id": "c1", "name": "exec", "arguments": {} } }),
+            ),
+            ("tool_output", json!({ "tool_call_id": "c1", "output": {} })),
+            (
+                "action",
+                json!({ "ToolCall": { "id": "c2", "name": "exec", "arguments": {} } }),
+            ),
+            ("policy_decision", json!({ "tool_call_id": "c2" })),
+            ("tool_output", json!({ "tool_call_id": "c2", "output": {} })),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(index, (kind, payload))| Event {
+            seq: index as u64 + 1,
+            ..Event::new(kind, payload)
+        })
+        .collect();
+
+        assert_eq!(events_through(&events, 3).len(), 3);
+        assert_eq!(events_through(&events, 5).len(), 3);
+        assert_eq!(events_through(&events, 6).len(), 6);
+    }
+
+    #[test]
+    fn goal_export_round_trips_ids_timestamps_and_order() {
+        let events = [
+            Event::new(
+                "goal",
+                json!({ "goal": "ship it", "goal_id": "20240101-000000-ship-it" }),
+            ),
+            Event::new("action", json!({ "Message": "working" })),
+        ];
+        let exported: String = events
+            .iter()
+            .map(|event| serde_json::to_string(event).expect("serialize") + "\n")
+            .collect();
+
+        let (goal_id, imported) = parse_goal_export(&exported).expect("export should parse");
+        assert_eq!(goal_id, "20240101-000000-ship-it");
+        assert_eq!(imported.len(), 2);
+        for (original, copy) in events.iter().zip(&imported) {
+            assert_eq!(original.id, copy.id);
+            assert_eq!(original.timestamp, copy.timestamp);
+            assert_eq!(original.r#type, copy.r#type);
         }
 
-        let serialized = to_string(&event).context("failed to serialize event for log")?;
-        let mut writer = self.writer.lock().await;
-        writer.write_all(serialized.as_bytes()).await?;
-        writer.write_all(b"\n").await?;
-        writer.flush().await?;
-        Ok(())
+        assert!(parse_goal_export("{\"not\": \"an event\"}").is_err());
+        let traversal = serde_json::to_string(&Event::new(
+            "goal",
+            json!({ "goal": "x", "goal_id": "../escape" }),
+        ))
+        .expect("serialize");
+        assert!(parse_goal_export(&traversal).is_err());
     }
 }
@@ -0,0 +1,143 @@
+use crate::event::Event;
+use crate::state::StateStore;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::sync::Arc;
+use tokio_postgres::Client;
+use tokio_postgres_rustls::MakeRustlsConnect;
+use tracing::warn;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS rx_events (
+    id BIGSERIAL PRIMARY KEY,
+    goal_id TEXT NOT NULL,
+    seq BIGINT NOT NULL,
+    event_id TEXT NOT NULL,
+    timestamp TIMESTAMPTZ NOT NULL,
+    type TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    UNIQUE (goal_id, seq)
+);
+";
+
+/// Stores goal events in a shared Postgres `rx_events` table so several
+/// machines see the same goal history.
+pub struct PostgresStateStore {
+    client: Client,
+    goal_id: String,
+}
+
+impl PostgresStateStore {
+    /// Connects and creates the schema if needed. With `resume`, the goal
+    /// must already have events.
+    pub async fn open(database_url: &str, goal_id: &str, resume: bool) -> Result<Self> {
+        let client = connect(database_url).await?;
+        if resume {
+            let row = client
+                .query_one(
+                    "SELECT EXISTS (SELECT 1 FROM rx_events WHERE goal_id = $1)",
+                    &[&goal_id],
+                )
+                .await?;
+            if !row.get::<_, bool>(0) {
+                return Err(anyhow!("no event log for goal '{}'", goal_id));
+            }
+        }
+        Ok(Self {
+            client,
+            goal_id: goal_id.to_string(),
+        })
+    }
+}
+
+/// Opens a connection (TLS when the server offers it) and ensures the
+/// schema exists.
+pub async fn connect(database_url: &str) -> Result<Client> {
+    let roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let tls = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .context("failed to configure TLS for Postgres")?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    let (client, connection) = tokio_postgres::connect(database_url, MakeRustlsConnect::new(tls))
+        .await
+        .context("failed to connect to the Postgres state database")?;
+    tokio::spawn(async move {
+        if let Err(error) = connection.await {
+            warn!("Postgres state connection closed: {}", error);
+        }
+    });
+    client
+        .batch_execute(SCHEMA)
+        .await
+        .context("failed to create the rx_events table")?;
+    Ok(client)
+}
+
+/// Goal ids with stored events, oldest first.
+pub async fn list_goals(client: &Client) -> Result<Vec<String>> {
+    let rows = client
+        .query(
+            "SELECT goal_id FROM rx_events GROUP BY goal_id ORDER BY goal_id",
+            &[],
+        )
+        .await?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+#[async_trait]
+impl StateStore for PostgresStateStore {
+    async fn load(&self) -> Result<Vec<Event>> {
+        let rows = self
+            .client
+            .query(
+                "SELECT event_id, seq, timestamp, type, payload FROM rx_events \
+                 WHERE goal_id = $1 ORDER BY seq",
+                &[&self.goal_id],
+            )
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| Event {
+                id: row.get(0),
+                seq: row.get::<_, i64>(1) as u64,
+                timestamp: row.get::<_, DateTime<Utc>>(2),
+                r#type: row.get(3),
+                payload: row.get::<_, Value>(4),
+            })
+            .collect())
+    }
+
+    async fn append_event(&self, mut event: Event) -> Result<Event> {
+        // The (goal_id, seq) unique constraint rejects a concurrent writer
+        // that computed the same next sequence number.
+        let row = self
+            .client
+            .query_one(
+                "INSERT INTO rx_events (goal_id, seq, event_id, timestamp, type, payload) \
+                 SELECT $1, COALESCE(MAX(seq), 0) + 1, $2, $3, $4, $5 \
+                 FROM rx_events WHERE goal_id = $1 RETURNING seq",
+                &[
+                    &self.goal_id,
+                    &event.id,
+                    &event.timestamp,
+                    &event.r#type,
+                    &event.payload,
+                ],
+            )
+            .await
+            .context("failed to append event to Postgres")?;
+        event.seq = row.get::<_, i64>(0) as u64;
+        Ok(event)
+    }
+
+    async fn list_goals(&self) -> Result<Vec<String>> {
+        list_goals(&self.client).await
+    }
+}
@@ -0,0 +1,238 @@
+use crate::event::Event;
+use crate::model::{Action, ModelUsage};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Tools listed in the text report; `--json` includes all of them.
+const TOP_TOOLS: usize = 10;
+
+/// Aggregates over every stored goal, for `rx stats`.
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct Stats {
+    pub goals: usize,
+    /// Goals by the ISO week (`2026-W07`) of their first event.
+    pub goals_per_week: BTreeMap<String, usize>,
+    pub average_iterations: f64,
+    /// Most-called first.
+    pub tools: Vec<ToolStats>,
+    /// Summed over every run (a resumed goal has one per run).
+    pub usage: ModelUsage,
+    pub cost_usd: f64,
+    /// Estimated USD saved by cached input tokens, over the priced runs.
+    pub cache_savings_usd: f64,
+    /// Runs whose termination carried a cost estimate.
+    pub priced_runs: usize,
+    pub runs: usize,
+    /// Goals by their last termination: `done:<outcome>`, another reason,
+    /// or `incomplete`.
+    pub terminations: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ToolStats {
+    pub name: String,
+    pub calls: usize,
+    pub errors: usize,
+}
+
+impl Stats {
+    pub fn from_goals(goals: &[Vec<Event>]) -> Self {
+        let mut stats = Stats {
+            goals: goals.len(),
+            ..Stats::default()
+        };
+        let mut tools: HashMap<String, (usize, usize)> = HashMap::new();
+        let mut iterations = 0;
+        for events in goals {
+            if let Some(first) = events.first() {
+                *stats
+                    .goals_per_week
+                    .entry(first.timestamp.format("%G-W%V").to_string())
+                    .or_default() += 1;
+            }
+            let mut last_termination = None;
+            for event in events {
+                match event.r#type.as_str() {
+                    "action" => {
+                        iterations += 1;
+                        if let Ok(Action::ToolCall(tool_call)) =
+                            serde_json::from_value::<Action>(event.payload.clone())
+                        {
+                            tools.entry(tool_call.name).or_default().0 += 1;
+                        }
+                    }
+                    "tool_output" if event.payload["output"].get("error").is_some() => {
+                        if let Some(name) = event.payload["name"].as_str() {
+                            tools.entry(name.to_string()).or_default().1 += 1;
+                        }
+                    }
+                    "termination" => {
+                        stats.runs += 1;
+                        if let Ok(usage) =
+                            serde_json::from_value::<ModelUsage>(event.payload["usage"].clone())
+                        {
+                            stats.usage.requests += usage.requests;
+                            stats.usage.input_tokens += usage.input_tokens;
+                            stats.usage.cached_input_tokens += usage.cached_input_tokens;
+                            stats.usage.output_tokens += usage.output_tokens;
+                        }
+                        if let Some(cost) = event.payload["cost_usd"].as_f64() {
+                            stats.cost_usd += cost;
+                            stats.priced_runs += 1;
+                        }
+                        if let Some(savings) = event.payload["cache_savings_usd"].as_f64() {
+                            stats.cache_savings_usd += savings;
+                        }
+                        last_termination = Some(&event.payload);
+                    }
+                    _ => {}
+                }
+            }
+            let termination = match last_termination {
+                Some(payload) => match (payload["reason"].as_str(), payload["outcome"].as_str()) {
+                    (Some("done"), Some(outcome)) => format!("done:{}", outcome),
+                    (Some(reason), _) => reason.to_string(),
+                    (None, _) => "unknown".to_string(),
+                },
+                None => "incomplete".to_string(),
+            };
+            *stats.terminations.entry(termination).or_default() += 1;
+        }
+        if stats.goals > 0 {
+            stats.average_iterations = iterations as f64 / stats.goals as f64;
+        }
+        stats.tools = tools
+            .into_iter()
+            .map(|(name, (calls, errors))| ToolStats {
+                name,
+                calls,
+                errors,
+            })
+            .collect();
+        stats
+            .tools
+            .sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.name.cmp(&b.name)));
+        stats
+    }
+}
+
+/// Plain-text report with one table per breakdown.
+pub fn render_stats(stats: &Stats) -> String {
+    let mut out = format!(
+        "Goals: {}\nAverage iterations per goal: {:.1}\n",
+        stats.goals, stats.average_iterations
+    );
+
+    out.push_str("\nGoals per week\n");
+    for (week, count) in &stats.goals_per_week {
+        out.push_str(&format!("  {:<10} {:>6}\n", week, count));
+    }
+
+    out.push_str(&format!(
+        "\nMost-used tools\n  {:<24} {:>6} {:>6}\n",
+        "tool", "calls", "errors"
+    ));
+    for tool in stats.tools.iter().take(TOP_TOOLS) {
+        out.push_str(&format!(
+            "  {:<24} {:>6} {:>6}\n",
+            tool.name, tool.calls, tool.errors
+        ));
+    }
+
+    out.push_str(&format!(
+        "\nModel usage: {} requests, {} input tokens ({} cached, {:.1}% cache hit), {} output tokens\n",
+        stats.usage.requests,
+        stats.usage.input_tokens,
+        stats.usage.cached_input_tokens,
+        stats.usage.cache_hit_rate() * 100.0,
+        stats.usage.output_tokens
+    ));
+    out.push_str(&format!(
+        "Estimated cost: ${:.4} ({} of {} runs priced), prompt cache saved ${:.4}\n",
+        stats.cost_usd, stats.priced_runs, stats.runs, stats.cache_savings_usd
+    ));
+
+    out.push_str("\nTerminations\n");
+    for (reason, count) in &stats.terminations {
+        out.push_str(&format!("  {:<24} {:>6}\n", reason, count));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_stats, Stats};
+    use crate::event::Event;
+    use serde_json::json;
+
+    fn call(name: &str) -> Event {
+        Event::new(
+            "action",
+            json!({ "ToolCall": { "id": "call", "name": name, "arguments": {} } }),
+        )
+    }
+
+    fn output(name: &str, output: serde_json::Value) -> Event {
+        Event::new("tool_output", json!({ "name": name, "output": output }))
+    }
+
+    #[test]
+    fn goals_are_aggregated_across_runs() {
+        let usage = json!({
+            "requests": 2,
+            "input_tokens": 100,
+            "cached_input_tokens": 40,
+            "output_tokens": 10
+        });
+        let done = vec![
+            Event::new("goal", json!({ "goal": "fix" })),
+            call("read_file"),
+            output("read_file", json!({ "content": "" })),
+            call("propose_done"),
+            output("propose_done", json!({ "status": "accepted" })),
+            Event::new(
+                "termination",
+                json!({
+                    "reason": "done",
+                    "outcome": "success",
+                    "usage": usage,
+                    "cost_usd": 0.5,
+                    "cache_savings_usd": 0.1
+                }),
+            ),
+        ];
+        let resumed = vec![
+            Event::new("goal", json!({ "goal": "deploy" })),
+            call("exec"),
+            output("exec", json!({ "error": "timed_out" })),
+            Event::new(
+                "termination",
+                json!({ "reason": "interrupted", "usage": usage }),
+            ),
+            call("exec"),
+            Event::new(
+                "termination",
+                json!({ "reason": "max_iterations", "usage": usage }),
+            ),
+        ];
+        let unfinished = vec![Event::new("goal", json!({ "goal": "wip" }))];
+
+        let stats = Stats::from_goals(&[done, resumed, unfinished]);
+        assert_eq!(stats.goals, 3);
+        assert_eq!(stats.goals_per_week.values().sum::<usize>(), 3);
+        assert!((stats.average_iterations - 4.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.tools[0].name, "exec");
+        assert_eq!((stats.tools[0].calls, stats.tools[0].errors), (2, 1));
+        assert_eq!(stats.usage.input_tokens, 300);
+        assert_eq!((stats.priced_runs, stats.runs), (1, 3));
+        assert_eq!(stats.terminations["done:success"], 1);
+        assert_eq!(stats.terminations["max_iterations"], 1);
+        assert_eq!(stats.terminations["incomplete"], 1);
+
+        let report = render_stats(&stats);
+        assert!(report.starts_with("Goals: 3\nAverage iterations per goal: 1.3\n"));
+        assert!(report.contains("(120 cached, 40.0% cache hit)"));
+        assert!(report
+            .contains("Estimated cost: $0.5000 (1 of 3 runs priced), prompt cache saved $0.1000"));
+    }
+}
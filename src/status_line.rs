@@ -0,0 +1,159 @@
+use crate::model::{ModelPricing, ModelUsage, ToolCall};
+use rx::patch::{parse_patch, FileOp};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+/// Tools that write the file named by their `path` argument.
+const PATH_TOOLS: &[&str] = &[
+    "write_file",
+    "create_file",
+    "append_file",
+    "replace_in_file",
+    "edit_lines",
+    "apply_unified_patch",
+];
+
+/// Session progress shown before each iteration: elapsed time, tokens,
+/// estimated cost, and files changed through the file tools.
+#[derive(Debug)]
+pub struct StatusLine {
+    started: Instant,
+    changed_files: BTreeSet<String>,
+}
+
+impl StatusLine {
+    pub fn new(started: Instant) -> Self {
+        Self {
+            started,
+            changed_files: BTreeSet::new(),
+        }
+    }
+
+    /// Notes the files a successful tool call changed.
+    pub fn record(&mut self, tool_call: &ToolCall, output: &Value) {
+        let dry_run = output.get("dry_run").and_then(|v| v.as_bool()) == Some(true);
+        if output.get("error").is_some() || dry_run {
+            return;
+        }
+        // Multi-file tools (multi_edit, git diffs) list what they wrote.
+        let paths = output.get("files_changed").and_then(|v| v.as_array());
+        for path in paths.into_iter().flatten().filter_map(|v| v.as_str()) {
+            self.changed_files.insert(path.to_string());
+        }
+        let arguments = &tool_call.arguments;
+        if PATH_TOOLS.contains(&tool_call.name.as_str()) {
+            if let Some(path) = arguments.get("path").and_then(|v| v.as_str()) {
+                self.changed_files.insert(path.to_string());
+            }
+        } else if tool_call.name == "apply_patch" {
+            let patch = arguments
+                .get("patch")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            for op in parse_patch(patch).unwrap_or_default() {
+                match op {
+                    FileOp::Add { path, .. } | FileOp::Delete { path } => {
+                        self.changed_files.insert(path);
+                    }
+                    FileOp::Update { path, move_to, .. } => {
+                        self.changed_files.insert(path);
+                        self.changed_files.extend(move_to);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn render(
+        &self,
+        iteration: usize,
+        max_iterations: usize,
+        usage: Option<ModelUsage>,
+        pricing: Option<ModelPricing>,
+    ) -> String {
+        let elapsed = self.started.elapsed().as_secs();
+        let mut parts = vec![
+            format!("Iteration {}/{}", iteration, max_iterations),
+            format!("{}m{:02}s", elapsed / 60, elapsed % 60),
+        ];
+        if let Some(usage) = usage {
+            parts.push(format!(
+                "{} tokens",
+                compact(usage.input_tokens + usage.output_tokens)
+            ));
+            if let Some(pricing) = pricing {
+                parts.push(format!("~${:.4}", pricing.cost(&usage)));
+            }
+        }
+        parts.push(match self.changed_files.len() {
+            1 => "1 file changed".to_string(),
+            count => format!("{} files changed", count),
+        });
+        parts.join(" | ")
+    }
+}
+
+fn compact(count: u64) -> String {
+    match count {
+        0..=999 => count.to_string(),
+        1_000..=999_999 => format!("{:.1}k", count as f64 / 1_000.0),
+        _ => format!("{:.1}M", count as f64 / 1_000_000.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StatusLine;
+    use crate::model::{ModelPricing, ModelUsage, ToolCall};
+    use serde_json::json;
+    use std::time::Instant;
+
+    fn call(name: &str, arguments: serde_json::Value) -> ToolCall {
+        ToolCall {
+            id: "1".to_string(),
+            name: name.to_string(),
+            arguments,
+        }
+    }
+
+    #[test]
+    fn counts_changed_files_and_formats_usage() {
+        let mut status = StatusLine::new(Instant::now());
+        status.record(
+            &call("write_file", json!({ "path": "a.rs" })),
+            &json!({ "path": "a.rs" }),
+        );
+        status.record(
+            &call("write_file", json!({ "path": "denied.rs" })),
+            &json!({ "error": "denied_by_policy" }),
+        );
+        status.record(
+            &call(
+                "apply_patch",
+                json!({ "patch": "*** Begin Patch\n*** Add File: b.rs\n+x\n*** Delete File: a.rs\n*** End Patch" }),
+            ),
+            &json!({ "patched": true }),
+        );
+
+        let usage = ModelUsage {
+            requests: 2,
+            input_tokens: 12_000,
+            cached_input_tokens: 0,
+            output_tokens: 345,
+        };
+        let pricing = ModelPricing {
+            input: 1.0,
+            cached_input: 0.5,
+            output: 10.0,
+        };
+        assert_eq!(
+            status.render(3, 50, Some(usage), Some(pricing)),
+            "Iteration 3/50 | 0m00s | 12.3k tokens | ~$0.0155 | 2 files changed"
+        );
+        assert_eq!(
+            status.render(1, 5, None, None),
+            "Iteration 1/5 | 0m00s | 2 files changed"
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use crate::config::TelemetryConfig;
+use crate::tui::LogLines;
+use anyhow::{Context, Result};
+use std::fmt;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::writer::{BoxMakeWriter, MakeWriterExt};
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// Keeps the OTLP exporter of a run alive; dropping it flushes the spans
+/// still buffered.
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    provider: opentelemetry_sdk::trace::SdkTracerProvider,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Err(error) = self.provider.shutdown() {
+            eprintln!("Warning: failed to flush telemetry: {}", error);
+        }
+    }
+}
+
+/// How much of rx's own log output is printed (`-q`, `-v`, `-vv`).
+#[derive(Debug, Clone)]
+pub struct Logging {
+    pub level: LevelFilter,
+    /// Print every line to stderr, keeping stdout for machine output.
+    pub stderr_only: bool,
+    /// Lines go here instead, for the `--tui` log pane.
+    pub capture: Option<LogLines>,
+}
+
+impl Logging {
+    /// `-q` keeps warnings and errors, `-v` adds tool inputs and outputs,
+    /// and `-vv` adds model requests and responses.
+    pub fn from_flags(quiet: bool, verbose: u8, stderr_only: bool) -> Self {
+        let level = match (quiet, verbose) {
+            (true, _) => LevelFilter::WARN,
+            (false, 0) => LevelFilter::INFO,
+            (false, 1) => LevelFilter::DEBUG,
+            (false, _) => LevelFilter::TRACE,
+        };
+        Self {
+            level,
+            stderr_only,
+            capture: None,
+        }
+    }
+
+    pub fn with_capture(mut self, lines: LogLines) -> Self {
+        self.capture = Some(lines);
+        self
+    }
+}
+
+/// Runs `f` with the log output of `logging` installed, for what is logged
+/// before `init` can run (loading the config it reads).
+pub fn with_early_logging<T>(logging: &Logging, f: impl FnOnce() -> T) -> T {
+    let subscriber = tracing_subscriber::registry().with(log_layer(logging.clone()));
+    tracing::subscriber::with_default(subscriber, f)
+}
+
+/// Installs the log output and, when `[telemetry].endpoint` is set, the
+/// OTLP exporter of the `kernel.run`, `model.next_action`, and
+/// `tool.execute` spans.
+pub fn init(config: Option<&TelemetryConfig>, logging: Logging) -> Result<Option<Telemetry>> {
+    let endpoint = config.and_then(|telemetry| telemetry.endpoint.clone());
+    let service_name = config
+        .and_then(|telemetry| telemetry.service_name.clone())
+        .unwrap_or_else(|| "rx".to_string());
+    let registry = tracing_subscriber::registry().with(log_layer(logging));
+
+    #[cfg(feature = "otel")]
+    {
+        use opentelemetry::trace::TracerProvider as _;
+
+        let provider = endpoint
+            .map(|endpoint| provider(endpoint, service_name))
+            .transpose()?;
+        let layer = provider
+            .as_ref()
+            .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer("rx")));
+        registry
+            .with(layer)
+            .try_init()
+            .context("failed to install the tracing subscriber")?;
+        Ok(provider.map(|provider| Telemetry { provider }))
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        let _ = service_name;
+        registry
+            .try_init()
+            .context("failed to install the tracing subscriber")?;
+        if endpoint.is_some() {
+            tracing::warn!(
+                "[telemetry].endpoint is set but rx was built without OpenTelemetry; rebuild with `cargo build --features otel`"
+            );
+        }
+        Ok(None)
+    }
+}
+
+/// Plain lines for rx's own events: progress on stdout, warnings and
+/// errors on stderr.
+fn log_layer<S>(logging: Logging) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let writer = if let Some(lines) = logging.capture {
+        BoxMakeWriter::new(lines)
+    } else if logging.stderr_only {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(
+            std::io::stderr
+                .with_max_level(Level::WARN)
+                .or_else(std::io::stdout),
+        )
+    };
+    tracing_subscriber::fmt::layer()
+        .event_format(PlainLines)
+        .with_writer(writer)
+        .with_filter(Targets::new().with_target("rx", logging.level))
+}
+
+/// The message alone, with `Warning:` or `Error:` in front of warnings and
+/// errors.
+struct PlainLines;
+
+impl<S, N> FormatEvent<S, N> for PlainLines
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        match *event.metadata().level() {
+            Level::ERROR => write!(writer, "Error: ")?,
+            Level::WARN => write!(writer, "Warning: ")?,
+            _ => {}
+        }
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
+
+#[cfg(feature = "otel")]
+fn provider(
+    endpoint: String,
+    service_name: String,
+) -> Result<opentelemetry_sdk::trace::SdkTracerProvider> {
+    use anyhow::anyhow;
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+
+    // The exporter's blocking HTTP client cannot be built on a runtime thread.
+    let exporter = std::thread::spawn(move || {
+        SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()
+    })
+    .join()
+    .map_err(|_| anyhow!("telemetry exporter setup panicked"))?
+    .context("failed to build the OTLP exporter")?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(service_name).build())
+        .build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Logging;
+    use tracing_subscriber::filter::LevelFilter;
+
+    #[test]
+    fn flags_map_to_log_levels() {
+        assert_eq!(
+            Logging::from_flags(false, 0, false).level,
+            LevelFilter::INFO
+        );
+        assert_eq!(Logging::from_flags(true, 0, false).level, LevelFilter::WARN);
+        assert_eq!(
+            Logging::from_flags(false, 1, false).level,
+            LevelFilter::DEBUG
+        );
+        assert_eq!(
+            Logging::from_flags(false, 3, true).level,
+            LevelFilter::TRACE
+        );
+    }
+}
@@ -0,0 +1,43 @@
+//! Fixtures shared by unit tests.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A fresh directory under the system temp dir, removed with its contents
+/// when dropped, even if the test panics.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates `rx-<name>-<pid>-<nanos>`, so tests and parallel test
+    /// processes never share one.
+    pub fn new(name: &str) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let path =
+            std::env::temp_dir().join(format!("rx-{}-{}-{}", name, std::process::id(), nanos));
+        std::fs::create_dir_all(&path).expect("should create temp dir");
+        Self(path)
+    }
+}
+
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::event::Event;
+use crate::model::Action;
+use serde_json::Value;
+
+const ARGUMENT_PREVIEW_CHARS: usize = 120;
+const OUTPUT_PREVIEW_CHARS: usize = 200;
+
+/// Renders a goal's events as a human-readable timeline, one entry per event.
+pub fn render_timeline(events: &[Event]) -> String {
+    let mut out = String::new();
+    for event in events {
+        let time = event.timestamp.format("%H:%M:%S");
+        out.push_str(&format!("{}  {}\n", time, describe(event)));
+    }
+    out
+}
+
+fn describe(event: &Event) -> String {
+    let payload = &event.payload;
+    match event.r#type.as_str() {
+        "goal" => format!("goal: {}", str_field(payload, "goal")),
+        "user_message" => format!(
+            "{}: {}",
+            payload
+                .get("source")
+                .and_then(|v| v.as_str())
+                .unwrap_or("user"),
+            str_field(payload, "message")
+        ),
+        "action" => match serde_json::from_value::<Action>(payload.clone()) {
+            Ok(Action::Message(message)) => format!("model: {}", message),
+            Ok(Action::ToolCall(tool_call)) => format!(
+                "call {} [{}] {}",
+                tool_call.name,
+                tool_call.id,
+                preview(&tool_call.arguments.to_string(), ARGUMENT_PREVIEW_CHARS)
+            ),
+            Err(_) => format!("action: {}", payload),
+        },
+        "tool_output" => {
+            let output = payload.get("output").cloned().unwrap_or(Value::Null);
+            let marker = if output.get("error").is_some() {
+                "error"
+            } else {
+                "output"
+            };
+            format!(
+                "{} {} [{}] {}",
+                marker,
+                str_field(payload, "name"),
+                str_field(payload, "tool_call_id"),
+                preview(&output.to_string(), OUTPUT_PREVIEW_CHARS)
+            )
+        }
+        "policy_decision" => format!(
+            "policy {} [{}] {}",
+            str_field(payload, "name"),
+            str_field(payload, "tool_call_id"),
+            payload.get("decision").unwrap_or(&Value::Null)
+        ),
+        "tool_output_full" => format!(
+            "full output of {} [{}] stored ({} bytes){}",
+            str_field(payload, "name"),
+            str_field(payload, "tool_call_id"),
+            payload["bytes"]
+                .as_u64()
+                .unwrap_or_else(|| payload["output"].to_string().len() as u64),
+            payload["artifact"]
+                .as_str()
+                .map(|id| format!(" as artifact {}", id))
+                .unwrap_or_default()
+        ),
+        "model_switched" => format!(
+            "model {} -> {}: {}",
+            str_field(payload, "from"),
+            str_field(payload, "to"),
+            preview(str_field(payload, "error"), OUTPUT_PREVIEW_CHARS)
+        ),
+        "file_snapshot" => format!(
+            "snapshot {} [{}] {}",
+            str_field(payload, "name"),
+            str_field(payload, "tool_call_id"),
+            paths(payload.get("files"))
+        ),
+        "workspace_diff" => format!(
+            "diff after [{}] {}",
+            str_field(payload, "tool_call_id"),
+            payload["files"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|file| match file.get("reverted") {
+                    Some(_) => format!("{} (reverted)", str_field(file, "path")),
+                    None => format!(
+                        "{} (+{} -{})",
+                        str_field(file, "path"),
+                        file["additions"],
+                        file["deletions"]
+                    ),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        "working_dir" => format!("working directory {}", str_field(payload, "cwd")),
+        "undo" => format!(
+            "undo {} restored, {} deleted",
+            payload["restored"].as_array().map_or(0, Vec::len),
+            payload["deleted"].as_array().map_or(0, Vec::len)
+        ),
+        "plan" => {
+            let steps = payload["steps"].as_array().map_or(&[][..], Vec::as_slice);
+            format!(
+                "plan: {} steps: {}",
+                steps.len(),
+                steps
+                    .iter()
+                    .enumerate()
+                    .map(|(index, step)| format!("{}. {}", index + 1, step.as_str().unwrap_or("?")))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            )
+        }
+        "plan_step_started" => format!(
+            "step {}/{} started: {}",
+            payload["index"],
+            payload["total"],
+            str_field(payload, "step")
+        ),
+        "plan_step_completed" => format!(
+            "step {}/{} completed: {}",
+            payload["index"],
+            payload["total"],
+            str_field(payload, "summary")
+        ),
+        "completion_review" => format!(
+            "review {}/{} [{}] {}",
+            payload["round"],
+            payload["max_rounds"],
+            str_field(payload, "tool_call_id"),
+            match payload.get("critique").and_then(|v| v.as_str()) {
+                Some(critique) => preview(critique, OUTPUT_PREVIEW_CHARS),
+                None => "approved".to_string(),
+            }
+        ),
+        "iteration_started" => format!("iteration {}", payload["iteration"]),
+        "iteration_finished" => {
+            let mut line = format!(
+                "iteration {} took {} ms",
+                payload["iteration"], payload["duration_ms"]
+            );
+            if let Some(tool) = payload.get("tool").and_then(|v| v.as_str()) {
+                line.push_str(&format!(" ({})", tool));
+            }
+            if payload.pointer("/usage/requests").and_then(|v| v.as_u64()) > Some(0) {
+                line.push_str(&format!(
+                    ", {} input ({} cached) / {} output tokens",
+                    payload["usage"]["input_tokens"],
+                    payload["usage"]["cached_input_tokens"],
+                    payload["usage"]["output_tokens"]
+                ));
+            }
+            line
+        }
+        "termination" => {
+            let mut line = format!("termination: {}", str_field(payload, "reason"));
+            if let Some(reason) = payload.pointer("/details/reason").and_then(|v| v.as_str()) {
+                line.push_str(&format!(" — {}", reason));
+            }
+            line
+        }
+        other => format!(
+            "{}: {}",
+            other,
+            preview(&payload.to_string(), OUTPUT_PREVIEW_CHARS)
+        ),
+    }
+}
+
+/// Comma-separated `path` fields of a snapshot's file list.
+fn paths(files: Option<&Value>) -> String {
+    files
+        .and_then(|files| files.as_array())
+        .into_iter()
+        .flatten()
+        .map(|file| str_field(file, "path"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn str_field<'a>(payload: &'a Value, key: &str) -> &'a str {
+    payload
+        .get(key)
+        .and_then(|value| value.as_str())
+        .unwrap_or("?")
+}
+
+fn preview(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    text.chars().take(max_chars).collect::<String>() + "…"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_timeline;
+    use crate::event::Event;
+    use serde_json::json;
+
+    #[test]
+    fn renders_calls_outputs_and_termination() {
+        let events = vec![
+            Event::new("goal", json!({ "goal": "add tests", "goal_id": "g" })),
+            Event::new(
+                "action",
+                json!({ "ToolCall": { "id": "call-1", "name": "exec", "arguments": { "command": "x".repeat(300) } } }),
+            ),
+            Event::new(
+                "tool_output",
+                json!({ "tool_call_id": "call-1", "name": "exec", "output": { "error": "boom" } }),
+            ),
+            Event::new(
+                "termination",
+                json!({ "reason": "done", "details": { "status": "done", "reason": "finished" } }),
+            ),
+        ];
+
+        let rendered = render_timeline(&events);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].ends_with("goal: add tests"));
+        assert!(lines[1].contains("call exec [call-1]") && lines[1].ends_with('…'));
+        assert!(lines[2].contains("error exec [call-1]"));
+        assert!(lines[3].ends_with("termination: done — finished"));
+    }
+}
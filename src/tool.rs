@@ -1,31 +1,214 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use serde_json::Value;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::fmt;
+use std::io::ErrorKind;
 use std::sync::Arc;
 
 #[async_trait]
 pub trait Tool: Send + Sync {
-    fn name(&self) -> &'static str;
-    fn description(&self) -> &'static str;
+    fn name(&self) -> &str;
+    fn description(&self) -> &str;
     fn parameters(&self) -> Value; // JSON Schema
     async fn execute(&self, input: Value) -> Result<Value>;
+
+    /// Whether the call may change the workspace, a process, or anything
+    /// outside rx. Such calls wait for approval under `--confirm` and for an
+    /// approved plan under `--plan`; read-only tools opt out.
+    fn side_effecting(&self, _input: &Value) -> bool {
+        true
+    }
+}
+
+/// Class of a failed tool call. The kernel adds it as `error_class` (with
+/// `retryable`) to every tool output that has an `error`, next to the
+/// tool's own error code. Tools may also return it as their `Err`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolError {
+    /// The file, tool, process, or record named in the call does not exist.
+    NotFound,
+    /// Refused by policy, the user, or an allowlist.
+    PermissionDenied,
+    /// Cancelled by a time limit.
+    Timeout,
+    /// The workspace is not in the state the call expects (stale hash,
+    /// ambiguous match, file already exists, ...).
+    PreconditionFailed,
+    /// The arguments are malformed or out of range.
+    InvalidArgs,
+    /// The tool ran and failed.
+    ExecutionFailed,
+}
+
+impl ToolError {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ToolError::NotFound => "not_found",
+            ToolError::PermissionDenied => "permission_denied",
+            ToolError::Timeout => "timeout",
+            ToolError::PreconditionFailed => "precondition_failed",
+            ToolError::InvalidArgs => "invalid_args",
+            ToolError::ExecutionFailed => "execution_failed",
+        }
+    }
+
+    /// Whether the identical call may succeed when sent again; the other
+    /// classes need different arguments or a changed workspace.
+    pub fn retryable(self) -> bool {
+        matches!(self, ToolError::Timeout | ToolError::ExecutionFailed)
+    }
+
+    /// The class of a tool's `error` code.
+    pub fn classify(code: &str) -> Self {
+        match code {
+            "not_found" | "unknown_tool" | "nothing_to_undo" => ToolError::NotFound,
+            "permission_denied" | "denied_by_policy" | "denied_by_user" | "domain_not_allowed"
+            | "plan_required" => ToolError::PermissionDenied,
+            "timeout" | "timed_out" | "preempted" => ToolError::Timeout,
+            "precondition_failed"
+            | "already_exists"
+            | "unexpected_match_count"
+            | "context_mismatch"
+            | "nothing_to_commit"
+            | "quota_exceeded"
+            | "no_plan"
+            | "plan_finished" => ToolError::PreconditionFailed,
+            "invalid_args" | "invalid_arguments" | "invalid_completion" | "invalid_range"
+            | "invalid_offset" | "invalid_cursor" => ToolError::InvalidArgs,
+            _ => ToolError::ExecutionFailed,
+        }
+    }
+
+    /// The class of an `Err` a tool returned: a `ToolError` itself, or the
+    /// kind of an I/O error in its chain.
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        if let Some(class) = error.downcast_ref::<ToolError>() {
+            return *class;
+        }
+        let kind = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<std::io::Error>())
+            .map(|io| io.kind());
+        match kind {
+            Some(ErrorKind::NotFound) => ToolError::NotFound,
+            Some(ErrorKind::PermissionDenied) => ToolError::PermissionDenied,
+            Some(ErrorKind::TimedOut) => ToolError::Timeout,
+            Some(ErrorKind::AlreadyExists) => ToolError::PreconditionFailed,
+            Some(ErrorKind::InvalidInput) => ToolError::InvalidArgs,
+            _ => ToolError::ExecutionFailed,
+        }
+    }
+
+    /// `{error, error_class, retryable, details}` for a failed call.
+    pub fn output(self, details: impl Into<Value>) -> Value {
+        json!({
+            "error": self.as_str(),
+            "error_class": self.as_str(),
+            "retryable": self.retryable(),
+            "details": details.into(),
+        })
+    }
+
+    /// Adds `error_class` and `retryable` to an output with an `error`. An
+    /// `error` that is a message rather than a code becomes the class code,
+    /// and the message moves to `details`.
+    pub fn annotate(mut output: Value) -> Value {
+        let Some(fields) = output.as_object_mut() else {
+            return output;
+        };
+        let Some(error) = fields.get("error").cloned() else {
+            return output;
+        };
+        let code = error.as_str().filter(|code| {
+            !code.is_empty()
+                && code
+                    .bytes()
+                    .all(|byte| byte.is_ascii_lowercase() || byte == b'_')
+        });
+        let class = code.map_or(ToolError::ExecutionFailed, ToolError::classify);
+        if code.is_none() {
+            fields.insert("error".to_string(), json!(class.as_str()));
+            fields.entry("details").or_insert(match error {
+                Value::String(message) => json!(message),
+                other => other,
+            });
+        }
+        fields
+            .entry("error_class")
+            .or_insert_with(|| json!(class.as_str()));
+        fields
+            .entry("retryable")
+            .or_insert_with(|| json!(class.retryable()));
+        output
+    }
+}
+
+impl fmt::Display for ToolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
+impl std::error::Error for ToolError {}
+
 #[derive(Clone, Default)]
 pub struct ToolRegistry {
     tools: HashMap<String, Arc<dyn Tool>>,
+    /// Compiled `parameters()` schemas; tools whose schema does not
+    /// compile have none and are not checked.
+    validators: HashMap<String, Arc<jsonschema::Validator>>,
 }
 
 impl ToolRegistry {
     pub fn new() -> Self {
-        Self {
-            tools: HashMap::new(),
-        }
+        Self::default()
     }
 
     pub fn register(&mut self, tool: Arc<dyn Tool>) {
-        self.tools.insert(tool.name().to_string(), tool);
+        let name = tool.name().to_string();
+        match jsonschema::validator_for(&tool.parameters()) {
+            Ok(validator) => {
+                self.validators.insert(name.clone(), Arc::new(validator));
+            }
+            Err(_) => {
+                self.validators.remove(&name);
+            }
+        }
+        self.tools.insert(name, tool);
+    }
+
+    pub fn unregister(&mut self, name: &str) -> Option<Arc<dyn Tool>> {
+        self.validators.remove(name);
+        self.tools.remove(name)
+    }
+
+    /// Where `arguments` break the tool's parameters schema, as `path` (a
+    /// JSON pointer into the arguments) and `message` pairs. Empty when they
+    /// fit, or when the tool is unknown or has no usable schema.
+    pub fn violations(&self, name: &str, arguments: &Value) -> Vec<Value> {
+        let Some(validator) = self.validators.get(name) else {
+            return Vec::new();
+        };
+        validator
+            .iter_errors(arguments)
+            .map(|error| {
+                json!({
+                    "path": error.instance_path.to_string(),
+                    "message": error.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Whether the call is gated (see [`Tool::side_effecting`]). Unknown
+    /// tools run nothing and are not.
+    pub fn side_effecting(&self, name: &str, arguments: &Value) -> bool {
+        self.tools
+            .get(name)
+            .is_some_and(|tool| tool.side_effecting(arguments))
     }
 
     pub fn get(&self, name: &str) -> Option<Arc<dyn Tool>> {
@@ -36,3 +219,98 @@ impl ToolRegistry {
         self.tools.values().cloned().collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Tool, ToolError, ToolRegistry};
+    use crate::tools::done::ProposeDoneTool;
+    use anyhow::{Context, Result};
+    use async_trait::async_trait;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    struct PathTool;
+
+    #[async_trait]
+    impl Tool for PathTool {
+        fn name(&self) -> &str {
+            "read"
+        }
+
+        fn description(&self) -> &str {
+            "test tool"
+        }
+
+        fn parameters(&self) -> Value {
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string" },
+                    "limit": { "type": "integer" }
+                },
+                "required": ["path"]
+            })
+        }
+
+        async fn execute(&self, _input: Value) -> Result<Value> {
+            Ok(json!({}))
+        }
+    }
+
+    #[test]
+    fn arguments_are_checked_against_the_parameters_schema() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(PathTool));
+
+        assert!(registry
+            .violations("read", &json!({ "path": "a.txt" }))
+            .is_empty());
+        let violations = registry.violations("read", &json!({ "limit": "ten" }));
+        assert_eq!(violations.len(), 2);
+        assert!(violations
+            .iter()
+            .any(|violation| violation["message"].as_str().unwrap().contains("path")));
+        assert!(violations
+            .iter()
+            .any(|violation| violation["path"] == "/limit"));
+        assert!(registry.violations("missing", &json!(null)).is_empty());
+    }
+
+    #[test]
+    fn tools_are_gated_unless_they_opt_out() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(PathTool));
+        registry.register(Arc::new(ProposeDoneTool));
+        assert!(registry.side_effecting("read", &json!({ "path": "a.txt" })));
+        assert!(!registry.side_effecting("propose_done", &json!({})));
+        assert!(!registry.side_effecting("missing", &json!({})));
+    }
+
+    #[test]
+    fn tool_errors_are_classified() {
+        let refused = ToolError::annotate(json!({ "error": "denied_by_policy" }));
+        assert_eq!(refused["error"], "denied_by_policy");
+        assert_eq!(refused["error_class"], "permission_denied");
+        assert_eq!(refused["retryable"], false);
+
+        let message = ToolError::annotate(json!({ "error": "exit status 1", "stdout": "" }));
+        assert_eq!(message["error"], "execution_failed");
+        assert_eq!(message["details"], "exit status 1");
+        assert_eq!(message["retryable"], true);
+        assert_eq!(
+            ToolError::annotate(json!({ "ok": true })),
+            json!({ "ok": true })
+        );
+
+        let missing = std::fs::read("/nonexistent/rx-file")
+            .context("failed to read /nonexistent/rx-file")
+            .unwrap_err();
+        assert_eq!(ToolError::from_error(&missing), ToolError::NotFound);
+        let typed = anyhow::Error::new(ToolError::PreconditionFailed);
+        assert_eq!(ToolError::from_error(&typed), ToolError::PreconditionFailed);
+        assert_eq!(
+            ToolError::Timeout.output("slow")["error_class"],
+            json!("timeout")
+        );
+    }
+}
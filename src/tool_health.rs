@@ -0,0 +1,165 @@
+use crate::event::Event;
+use crate::state::{list_stored_goals, load_goal, StateSettings};
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+
+/// Most recent goals scanned for tool outcomes.
+const MAX_RECENT_GOALS: usize = 50;
+
+/// Errors that say nothing about the tool itself.
+const NON_TOOL_ERRORS: &[&str] = &["denied_by_policy", "denied_by_user", "unknown_tool"];
+
+/// Outcome counts for one tool across recent goals.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolHealth {
+    pub calls: u32,
+    pub failures: u32,
+    /// Failures since the tool last succeeded.
+    pub consecutive_failures: u32,
+}
+
+impl ToolHealth {
+    pub fn failure_rate(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            f64::from(self.failures) / f64::from(self.calls)
+        }
+    }
+}
+
+/// Tallies tool outcomes from the most recent stored goals, oldest first, so
+/// `consecutive_failures` reflects the latest streak.
+pub async fn recent_tool_health(state: &StateSettings) -> Result<BTreeMap<String, ToolHealth>> {
+    let goal_ids = list_stored_goals(state).await?;
+    let start = goal_ids.len().saturating_sub(MAX_RECENT_GOALS);
+    let mut health = BTreeMap::new();
+    for goal_id in &goal_ids[start..] {
+        if let Ok(events) = load_goal(state, goal_id).await {
+            tally(&events, &mut health);
+        }
+    }
+    Ok(health)
+}
+
+fn tally(events: &[Event], health: &mut BTreeMap<String, ToolHealth>) {
+    let mut names: HashMap<&str, &str> = HashMap::new();
+    for event in events {
+        match event.r#type.as_str() {
+            "action" => {
+                let call = event.payload.get("ToolCall");
+                let id = call.and_then(|call| call.get("id")).and_then(Value::as_str);
+                let name = call
+                    .and_then(|call| call.get("name"))
+                    .and_then(Value::as_str);
+                if let (Some(id), Some(name)) = (id, name) {
+                    names.insert(id, name);
+                }
+            }
+            "tool_output" => {
+                let Some(name) = event
+                    .payload
+                    .get("tool_call_id")
+                    .and_then(Value::as_str)
+                    .and_then(|id| names.get(id))
+                else {
+                    continue;
+                };
+                let error = event
+                    .payload
+                    .get("output")
+                    .and_then(|output| output.get("error"))
+                    .map(|error| {
+                        error
+                            .as_str()
+                            .map(str::to_string)
+                            .unwrap_or_else(|| error.to_string())
+                    });
+                if error.as_deref().is_some_and(|error| {
+                    NON_TOOL_ERRORS.contains(&error) || error.ends_with("not registered")
+                }) {
+                    continue;
+                }
+                let entry = health.entry(name.to_string()).or_default();
+                entry.calls += 1;
+                if error.is_some() {
+                    entry.failures += 1;
+                    entry.consecutive_failures += 1;
+                } else {
+                    entry.consecutive_failures = 0;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Tools whose latest failure streak reached `threshold`. `propose_done` is
+/// never flagged.
+pub fn unhealthy_tools(
+    health: &BTreeMap<String, ToolHealth>,
+    threshold: u32,
+) -> Vec<(&str, &ToolHealth)> {
+    health
+        .iter()
+        .filter(|(name, stats)| {
+            name.as_str() != "propose_done" && stats.consecutive_failures >= threshold
+        })
+        .map(|(name, stats)| (name.as_str(), stats))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tally, unhealthy_tools};
+    use crate::event::Event;
+    use serde_json::{json, Value};
+    use std::collections::BTreeMap;
+
+    fn call(id: &str, name: &str, output: Value) -> [Event; 2] {
+        [
+            Event::new(
+                "action",
+                json!({ "ToolCall": { "id": id, "name": name, "arguments": {} } }),
+            ),
+            Event::new(
+                "tool_output",
+                json!({ "tool_call_id": id, "name": name, "output": output }),
+            ),
+        ]
+    }
+
+    #[test]
+    fn streaks_reset_on_success_and_ignore_denials() {
+        let runs = [
+            [
+                call("1", "mcp__search", json!({ "error": "timeout" })),
+                call("2", "exec", json!({ "error": "denied_by_policy" })),
+            ]
+            .concat(),
+            [
+                call("1", "exec", json!({ "error": "exit 1" })),
+                call("2", "exec", json!({ "code": 0 })),
+                call("3", "mcp__search", json!({ "error": "timeout" })),
+            ]
+            .concat(),
+            call("1", "mcp__search", json!({ "error": "connection reset" })).to_vec(),
+        ];
+        let mut health = BTreeMap::new();
+        for events in &runs {
+            tally(events, &mut health);
+        }
+
+        assert_eq!(health["exec"].calls, 2);
+        assert_eq!(health["exec"].consecutive_failures, 0);
+        assert_eq!(health["mcp__search"].failures, 3);
+        assert_eq!(health["mcp__search"].failure_rate(), 1.0);
+
+        let flagged = unhealthy_tools(&health, 3);
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].0, "mcp__search");
+    }
+}
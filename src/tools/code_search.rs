@@ -0,0 +1,531 @@
+use crate::tool::Tool;
+use crate::tools::grep::{read_searchable, search_files, MAX_FILES, MAX_LINE_CHARS};
+use crate::tools::search::{path_glob_matches, walk_files};
+use crate::tools::workspace::Workspace;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use regex::{Regex, RegexBuilder};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+const DEFAULT_MAX_MATCHES: usize = 50;
+const DEFAULT_CONTEXT_LINES: usize = 2;
+const MAX_CONTEXT_LINES: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+struct CodeMatch {
+    path: String,
+    line: usize,
+    /// 1-based byte column of the first match on the line.
+    column: usize,
+    text: String,
+    before: Vec<String>,
+    after: Vec<String>,
+}
+
+impl CodeMatch {
+    fn to_json(&self) -> Value {
+        json!({
+            "path": self.path,
+            "line": self.line,
+            "column": self.column,
+            "text": self.text,
+            "before": self.before,
+            "after": self.after
+        })
+    }
+}
+
+/// Search parameters shared by both engines.
+struct Query {
+    regex: Regex,
+    pattern: String,
+    literal: bool,
+    case_insensitive: bool,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    include_ignored: bool,
+    context: usize,
+}
+
+impl Query {
+    fn accepts(&self, relative: &str) -> bool {
+        (self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|glob| path_glob_matches(glob, relative)))
+            && !self
+                .exclude
+                .iter()
+                .any(|glob| path_glob_matches(glob, relative))
+    }
+}
+
+/// Content search with column and context lines. Uses `rg --json` when
+/// ripgrep is on `PATH`, and the built-in parallel scanner otherwise.
+pub struct CodeSearchTool {
+    workspace: Arc<Workspace>,
+    ripgrep: Option<PathBuf>,
+}
+
+impl CodeSearchTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self {
+            workspace,
+            ripgrep: find_in_path("rg"),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for CodeSearchTool {
+    fn name(&self) -> &'static str {
+        "code_search"
+    }
+
+    fn description(&self) -> &'static str {
+        "Fast content search returning file, line, column, and surrounding context lines for each match, ordered by path. Uses ripgrep when installed. Honors the same ignore rules as find_files; page through results with cursor."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Recursive content search with context.",
+            "properties": {
+                "pattern": {
+                    "type": "string",
+                    "description": "Regular expression (Rust syntax), or a plain string when literal is true."
+                },
+                "literal": {
+                    "type": "boolean",
+                    "description": "Treat pattern as a plain string. Defaults to false."
+                },
+                "case_insensitive": {
+                    "type": "boolean",
+                    "description": "Ignore case. Defaults to false."
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Directory to search. Defaults to current directory."
+                },
+                "include": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Only search files matching one of these globs, e.g. ['*.rs']."
+                },
+                "exclude": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Skip files matching any of these globs."
+                },
+                "context": {
+                    "type": "integer",
+                    "description": "Lines of context before and after each match. Defaults to 2, at most 10."
+                },
+                "max_matches": {
+                    "type": "integer",
+                    "description": "Maximum matches returned. Defaults to 50."
+                },
+                "cursor": {
+                    "type": "integer",
+                    "description": "Number of matches to skip; pass next_cursor from the previous page."
+                },
+                "include_ignored": {
+                    "type": "boolean",
+                    "description": "Also search paths hidden by the [search] ignore rules."
+                }
+            },
+            "required": ["pattern"],
+            "examples": [
+                { "pattern": "fn execute", "literal": true, "include": ["*.rs"] },
+                { "pattern": "retry|backoff", "path": "src", "context": 4 }
+            ]
+        })
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let pattern = input
+            .get("pattern")
+            .and_then(|v| v.as_str())
+            .filter(|pattern| !pattern.is_empty())
+            .ok_or_else(|| anyhow!("missing pattern"))?;
+        let flag = |key: &str| input.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+        let (literal, case_insensitive) = (flag("literal"), flag("case_insensitive"));
+        let source = if literal {
+            regex::escape(pattern)
+        } else {
+            pattern.to_string()
+        };
+        let regex = RegexBuilder::new(&source)
+            .case_insensitive(case_insensitive)
+            .build()
+            .with_context(|| format!("invalid pattern '{}'", pattern))?;
+        let globs = |key: &str| -> Vec<String> {
+            match input.get(key) {
+                Some(Value::String(glob)) => vec![glob.clone()],
+                Some(Value::Array(globs)) => globs
+                    .iter()
+                    .filter_map(|glob| glob.as_str().map(str::to_string))
+                    .collect(),
+                _ => Vec::new(),
+            }
+        };
+        let number = |key: &str| input.get(key).and_then(|v| v.as_u64()).map(|n| n as usize);
+        let max_matches = number("max_matches").unwrap_or(DEFAULT_MAX_MATCHES).max(1);
+        let cursor = number("cursor").unwrap_or(0);
+        let query = Query {
+            regex,
+            pattern: pattern.to_string(),
+            literal,
+            case_insensitive,
+            include: globs("include"),
+            exclude: globs("exclude"),
+            include_ignored: flag("include_ignored"),
+            context: number("context")
+                .unwrap_or(DEFAULT_CONTEXT_LINES)
+                .min(MAX_CONTEXT_LINES),
+        };
+
+        let dir = self
+            .workspace
+            .resolve(input.get("path").and_then(|v| v.as_str()).unwrap_or("."))?;
+        let limit = cursor + max_matches;
+        let (engine, (matches, more, files_truncated)) = match &self.ripgrep {
+            Some(rg) => (
+                "ripgrep",
+                search_with_ripgrep(rg, &self.workspace, &dir, &query, limit).await?,
+            ),
+            None => {
+                let workspace = Arc::clone(&self.workspace);
+                let found = tokio::task::spawn_blocking(move || {
+                    search_builtin(&workspace, &dir, &query, limit)
+                })
+                .await
+                .context("code_search task failed")?;
+                ("builtin", found)
+            }
+        };
+
+        let page: Vec<Value> = matches
+            .iter()
+            .skip(cursor)
+            .map(CodeMatch::to_json)
+            .collect();
+        Ok(json!({
+            "matches": page,
+            "next_cursor": more.then_some(limit),
+            "engine": engine,
+            "files_truncated": files_truncated
+        }))
+    }
+}
+
+/// First executable named `name` on `PATH`.
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    let file_name = format!("{}{}", name, std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Returns up to `limit` matches in path order, whether more exist, and
+/// whether the file walk was truncated.
+fn search_builtin(
+    workspace: &Workspace,
+    dir: &Path,
+    query: &Query,
+    limit: usize,
+) -> (Vec<CodeMatch>, bool, bool) {
+    let walk = walk_files(
+        workspace,
+        dir,
+        query.include_ignored,
+        MAX_FILES,
+        |relative| query.accepts(relative),
+    );
+    let (matches, _, more) = search_files(dir, &walk.files, limit, |path, relative| {
+        let Some(text) = read_searchable(path) else {
+            return Vec::new();
+        };
+        let lines: Vec<&str> = text.lines().collect();
+        lines
+            .iter()
+            .enumerate()
+            .filter_map(|(index, line)| {
+                let found = query.regex.find(line)?;
+                let before = index.saturating_sub(query.context);
+                let after = (index + 1 + query.context).min(lines.len());
+                Some(CodeMatch {
+                    path: relative.to_string(),
+                    line: index + 1,
+                    column: found.start() + 1,
+                    text: truncate_line(line),
+                    before: lines[before..index]
+                        .iter()
+                        .map(|l| truncate_line(l))
+                        .collect(),
+                    after: lines[index + 1..after]
+                        .iter()
+                        .map(|l| truncate_line(l))
+                        .collect(),
+                })
+            })
+            .collect()
+    });
+    (matches, more, walk.truncated)
+}
+
+async fn search_with_ripgrep(
+    rg: &Path,
+    workspace: &Workspace,
+    dir: &Path,
+    query: &Query,
+    limit: usize,
+) -> Result<(Vec<CodeMatch>, bool, bool)> {
+    let mut command = Command::new(rg);
+    command
+        .args([
+            "--json",
+            "--sort",
+            "path",
+            "--no-ignore",
+            "--hidden",
+            "--no-messages",
+        ])
+        .arg("--context")
+        .arg(query.context.to_string());
+    if query.literal {
+        command.arg("--fixed-strings");
+    }
+    if query.case_insensitive {
+        command.arg("--ignore-case");
+    }
+    if !query.include_ignored {
+        for pattern in workspace.search_ignore().external_excludes() {
+            command.arg("--glob").arg(format!("!{}", pattern));
+        }
+    }
+    let mut child = command
+        .arg("--regexp")
+        .arg(&query.pattern)
+        .arg(".")
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("failed to run {}", rg.display()))?;
+    let stdout = child.stdout.take().context("ripgrep stdout unavailable")?;
+
+    let prefix = dir.strip_prefix(workspace.root()).unwrap_or(Path::new(""));
+    let accepts = |relative: &str| {
+        query.accepts(relative)
+            && (query.include_ignored
+                || !workspace.search_ignore().hides_file(&prefix.join(relative)))
+    };
+    let mut output = RipgrepOutput::new(query.context, limit);
+    let mut lines = BufReader::new(stdout).lines();
+    while let Some(line) = lines.next_line().await? {
+        if !output.push(&line, &accepts) {
+            break;
+        }
+    }
+    let _ = child.start_kill();
+    let (matches, more) = output.finish();
+    Ok((matches, more, false))
+}
+
+/// Assembles `rg --json` messages into matches with context. Context lines
+/// arrive as separate messages, so each file's matches are completed when
+/// the file ends.
+struct RipgrepOutput {
+    context: usize,
+    limit: usize,
+    matches: Vec<CodeMatch>,
+    /// Index into `matches` of the current file's first match.
+    file_start: usize,
+    file_lines: BTreeMap<usize, String>,
+    skipping_file: bool,
+    more: bool,
+}
+
+impl RipgrepOutput {
+    fn new(context: usize, limit: usize) -> Self {
+        Self {
+            context,
+            limit,
+            matches: Vec::new(),
+            file_start: 0,
+            file_lines: BTreeMap::new(),
+            skipping_file: false,
+            more: false,
+        }
+    }
+
+    /// Consumes one JSON line; returns false once more than `limit` matches
+    /// were seen.
+    fn push(&mut self, line: &str, accepts: &dyn Fn(&str) -> bool) -> bool {
+        let Ok(message) = serde_json::from_str::<Value>(line) else {
+            return true;
+        };
+        let data = &message["data"];
+        let path = data["path"]["text"]
+            .as_str()
+            .map(|path| path.trim_start_matches("./"));
+        match message["type"].as_str() {
+            Some("begin") => {
+                self.finish_file();
+                self.skipping_file = !path.is_some_and(accepts);
+            }
+            Some("end") => self.finish_file(),
+            Some(kind @ ("match" | "context")) if !self.skipping_file => {
+                let (Some(path), Some(number), Some(text)) = (
+                    path,
+                    data["line_number"].as_u64().map(|n| n as usize),
+                    data["lines"]["text"].as_str(),
+                ) else {
+                    return true;
+                };
+                let text = text.trim_end_matches(['\n', '\r']);
+                self.file_lines.insert(number, truncate_line(text));
+                if kind == "match" {
+                    if self.matches.len() == self.limit {
+                        self.more = true;
+                        return false;
+                    }
+                    let column = data["submatches"][0]["start"].as_u64().unwrap_or(0) as usize;
+                    self.matches.push(CodeMatch {
+                        path: path.to_string(),
+                        line: number,
+                        column: column + 1,
+                        text: truncate_line(text),
+                        before: Vec::new(),
+                        after: Vec::new(),
+                    });
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn finish_file(&mut self) {
+        let lines = std::mem::take(&mut self.file_lines);
+        for found in &mut self.matches[self.file_start..] {
+            let first = found.line.saturating_sub(self.context);
+            found.before = lines
+                .range(first..found.line)
+                .map(|(_, text)| text.clone())
+                .collect();
+            found.after = lines
+                .range(found.line + 1..=found.line + self.context)
+                .map(|(_, text)| text.clone())
+                .collect();
+        }
+        self.file_start = self.matches.len();
+    }
+
+    fn finish(mut self) -> (Vec<CodeMatch>, bool) {
+        self.finish_file();
+        (self.matches, self.more)
+    }
+}
+
+fn truncate_line(line: &str) -> String {
+    line.chars().take(MAX_LINE_CHARS).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CodeSearchTool, RipgrepOutput};
+    use crate::tool::Tool;
+    use crate::tools::search::SearchIgnore;
+    use crate::tools::workspace::Workspace;
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[tokio::test]
+    async fn builtin_engine_reports_columns_and_context() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("rx-code-search-test-{}", nanos));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+        std::fs::write(root.join("src/a.rs"), "use x;\nfn a() { retry(); }\n}\n").unwrap();
+        std::fs::write(root.join("src/b.rs"), "retry\n").unwrap();
+        std::fs::write(root.join("target/gen.rs"), "retry\n").unwrap();
+        let root = root.canonicalize().unwrap();
+        let workspace = Workspace::new(&root, &root, &[])
+            .unwrap()
+            .with_search_ignore(SearchIgnore::from_config(&root, None));
+        let tool = CodeSearchTool {
+            workspace: Arc::new(workspace),
+            ripgrep: None,
+        };
+
+        let output = tool
+            .execute(json!({ "pattern": "retry", "context": 1, "max_matches": 1 }))
+            .await
+            .expect("search should run");
+        assert_eq!(output["engine"], "builtin");
+        assert_eq!(
+            output["matches"],
+            json!([{
+                "path": "src/a.rs",
+                "line": 2,
+                "column": 10,
+                "text": "fn a() { retry(); }",
+                "before": ["use x;"],
+                "after": ["}"]
+            }])
+        );
+        assert_eq!(output["next_cursor"], 1);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn ripgrep_messages_become_matches_with_shared_context() {
+        let messages = [
+            json!({ "type": "begin", "data": { "path": { "text": "./target/gen.rs" } } }),
+            json!({ "type": "match", "data": { "path": { "text": "./target/gen.rs" }, "lines": { "text": "retry\n" }, "line_number": 1, "submatches": [{ "start": 0 }] } }),
+            json!({ "type": "end", "data": { "path": { "text": "./target/gen.rs" } } }),
+            json!({ "type": "begin", "data": { "path": { "text": "./src/a.rs" } } }),
+            json!({ "type": "context", "data": { "path": { "text": "./src/a.rs" }, "lines": { "text": "use x;\n" }, "line_number": 1, "submatches": [] } }),
+            json!({ "type": "match", "data": { "path": { "text": "./src/a.rs" }, "lines": { "text": "retry();\n" }, "line_number": 2, "submatches": [{ "start": 0 }] } }),
+            json!({ "type": "match", "data": { "path": { "text": "./src/a.rs" }, "lines": { "text": "  retry();\n" }, "line_number": 3, "submatches": [{ "start": 2 }] } }),
+            json!({ "type": "match", "data": { "path": { "text": "./src/a.rs" }, "lines": { "text": "retry\n" }, "line_number": 4, "submatches": [{ "start": 0 }] } }),
+        ];
+        let mut output = RipgrepOutput::new(1, 2);
+        let accepts = |path: &str| !path.starts_with("target/");
+        let consumed = messages
+            .iter()
+            .take_while(|message| output.push(&message.to_string(), &accepts))
+            .count();
+        assert_eq!(consumed, 7);
+
+        let (matches, more) = output.finish();
+        assert!(more);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].path, "src/a.rs");
+        assert_eq!(matches[0].before, ["use x;"]);
+        assert_eq!(matches[0].after, ["  retry();"]);
+        assert_eq!((matches[1].line, matches[1].column), (3, 3));
+        assert_eq!(matches[1].before, ["retry();"]);
+    }
+}
@@ -1,18 +1,67 @@
 use crate::tool::Tool;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use serde_json::{json, Value};
 
-pub struct DoneTool;
+/// Machine-readable verdicts a completion proposal can carry.
+pub const OUTCOMES: [&str; 4] = ["success", "partial", "blocked", "cannot_reproduce"];
+
+/// Checks that a `propose_done` call names a known `outcome` and at least one
+/// `evidence` entry. The kernel rejects proposals that fail before review.
+pub fn validate_completion(arguments: &Value) -> Result<(), String> {
+    let summary = arguments
+        .get("summary")
+        .or_else(|| arguments.get("reason"))
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    if summary.trim().is_empty() {
+        return Err("summary is required".to_string());
+    }
+    match arguments.get("outcome").and_then(|v| v.as_str()) {
+        Some(outcome) if OUTCOMES.contains(&outcome) => {}
+        Some(outcome) => {
+            return Err(format!(
+                "unknown outcome '{}'; expected one of {}",
+                outcome,
+                OUTCOMES.join(", ")
+            ))
+        }
+        None => {
+            return Err(format!(
+                "outcome is required: one of {}",
+                OUTCOMES.join(", ")
+            ))
+        }
+    }
+    let has_evidence = match arguments.get("evidence") {
+        Some(Value::Array(entries)) => entries
+            .iter()
+            .any(|entry| entry.as_str().is_some_and(|entry| !entry.trim().is_empty())),
+        Some(Value::String(entry)) => !entry.trim().is_empty(),
+        _ => false,
+    };
+    if !has_evidence {
+        return Err(
+            "evidence is required: commands run, test results, or files that show the outcome"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// First phase of completion: the agent proposes that the goal is done with
+/// an outcome and evidence. The kernel reviews the proposal and either
+/// terminates the run or returns the reviewer's feedback as the tool output.
+pub struct ProposeDoneTool;
 
 #[async_trait]
-impl Tool for DoneTool {
+impl Tool for ProposeDoneTool {
     fn name(&self) -> &'static str {
-        "done"
+        "propose_done"
     }
 
     fn description(&self) -> &'static str {
-        "Signal that work is complete and request loop termination. Include a concise reason and optional structured details summarizing final outcome, checks, or artifacts."
+        "Propose that work is complete. Give a summary of the outcome and an overview of the changes made; the proposal is reviewed and either accepted (ending the run) or rejected with feedback to act on."
     }
 
     fn parameters(&self) -> Value {
@@ -20,44 +69,103 @@ impl Tool for DoneTool {
             "type": "object",
             "description": "Use only when the task is complete or cannot progress further.",
             "properties": {
-                "reason": {
+                "summary": {
+                    "type": "string",
+                    "description": "What was achieved, or why the task cannot progress."
+                },
+                "outcome": {
+                    "type": "string",
+                    "enum": OUTCOMES,
+                    "description": "success: goal met; partial: some of it done; blocked: cannot continue without outside help; cannot_reproduce: the reported problem does not occur."
+                },
+                "evidence": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Facts that back the outcome, e.g. commands run with their results or the missing credential."
+                },
+                "changes": {
+                    "type": ["array", "string"],
+                    "items": { "type": "string" },
+                    "description": "Overview of the diff: one entry per changed file with what changed."
+                },
+                "verification": {
                     "type": "string",
-                    "description": "Short completion reason. Example: `implemented feature and verified tests`."
+                    "description": "How the result was checked, e.g. `cargo test passed`."
                 },
                 "details": {
                     "type": ["object", "string", "null"],
                     "description": "Optional structured summary of results."
                 }
             },
+            "required": ["summary", "outcome", "evidence"],
             "examples": [
                 {
-                    "reason": "goal achieved",
-                    "details": {
-                        "files_updated": 3,
-                        "tests": "cargo test passed"
-                    }
+                    "summary": "Added retry with backoff to the HTTP client",
+                    "outcome": "success",
+                    "evidence": ["cargo test: 42 passed", "tests/client.rs covers 503 retries"],
+                    "changes": [
+                        "src/client.rs: wrap requests in retry loop",
+                        "tests/client.rs: cover 503 retries"
+                    ],
+                    "verification": "cargo test passed"
                 },
                 {
-                    "reason": "blocked by missing credentials",
+                    "summary": "Blocked by missing credentials",
+                    "outcome": "blocked",
+                    "evidence": ["STRIPE_API_KEY is not set; integration tests exit with 401"],
                     "details": "Cannot continue without API key."
-                },
-                {
-                    "reason": "done"
                 }
             ]
         })
     }
 
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
     async fn execute(&self, input: Value) -> Result<Value> {
-        let reason = input
-            .get("reason")
+        let summary = input
+            .get("summary")
+            .or_else(|| input.get("reason"))
             .and_then(|v| v.as_str())
-            .unwrap_or("done");
-        let details = input.get("details").cloned().unwrap_or(json!(null));
+            .filter(|summary| !summary.trim().is_empty())
+            .ok_or_else(|| anyhow!("missing summary"))?;
         Ok(json!({
-            "status": "done",
-            "reason": reason,
-            "details": details,
+            "status": "proposed",
+            "summary": summary,
+            "outcome": input.get("outcome").cloned().unwrap_or(json!(null)),
+            "evidence": input.get("evidence").cloned().unwrap_or(json!([])),
+            "changes": input.get("changes").cloned().unwrap_or(json!([])),
+            "verification": input.get("verification").cloned().unwrap_or(json!(null)),
+            "details": input.get("details").cloned().unwrap_or(json!(null)),
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::validate_completion;
+    use serde_json::json;
+
+    #[test]
+    fn proposals_need_a_known_outcome_and_evidence() {
+        let valid = json!({
+            "summary": "fixed",
+            "outcome": "success",
+            "evidence": ["cargo test passed"]
+        });
+        assert_eq!(validate_completion(&valid), Ok(()));
+
+        let mut unknown = valid.clone();
+        unknown["outcome"] = json!("done");
+        assert!(validate_completion(&unknown)
+            .unwrap_err()
+            .starts_with("unknown outcome 'done'"));
+
+        let mut empty_evidence = valid.clone();
+        empty_evidence["evidence"] = json!(["  "]);
+        assert!(validate_completion(&empty_evidence)
+            .unwrap_err()
+            .starts_with("evidence is required"));
+    }
+}
@@ -0,0 +1,324 @@
+use crate::tool::Tool;
+use crate::tools::fs::{apply_precondition, write_atomically};
+use crate::tools::workspace::Workspace;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::fs::read_to_string;
+
+/// A line-addressed edit; lines are 1-based and ranges inclusive.
+#[derive(Debug, Clone, PartialEq)]
+enum LineEdit {
+    /// Inserts before `line`; one past the last line appends.
+    InsertAt {
+        line: usize,
+        content: String,
+    },
+    DeleteRange {
+        start: usize,
+        end: usize,
+    },
+    ReplaceRange {
+        start: usize,
+        end: usize,
+        content: String,
+    },
+}
+
+impl LineEdit {
+    fn parse(input: &Value) -> Result<Self> {
+        let number = |field: &str| {
+            input
+                .get(field)
+                .and_then(|v| v.as_u64())
+                .map(|n| n as usize)
+                .ok_or_else(|| anyhow!("'{}' parameter is required", field))
+        };
+        let content = || {
+            input
+                .get("content")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("'content' parameter is required"))
+        };
+        let op = input
+            .get("op")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("'op' parameter is required"))?;
+        match op {
+            "insert_at" => Ok(Self::InsertAt {
+                line: number("line")?,
+                content: content()?,
+            }),
+            "delete_range" => Ok(Self::DeleteRange {
+                start: number("start_line")?,
+                end: number("end_line")?,
+            }),
+            "replace_range" => Ok(Self::ReplaceRange {
+                start: number("start_line")?,
+                end: number("end_line")?,
+                content: content()?,
+            }),
+            other => Err(anyhow!(
+                "unknown op '{}' (expected insert_at, delete_range, or replace_range)",
+                other
+            )),
+        }
+    }
+
+    /// The existing lines the edit is anchored to, as a 0-based range. An
+    /// insert is anchored to the line it goes before.
+    fn anchor(&self, line_count: usize) -> Result<std::ops::Range<usize>, String> {
+        let (start, end) = match self {
+            Self::InsertAt { line, .. } => {
+                if *line == 0 || *line > line_count + 1 {
+                    return Err(format!(
+                        "line {} is outside 1..={} (the file has {} lines)",
+                        line,
+                        line_count + 1,
+                        line_count
+                    ));
+                }
+                return Ok(*line - 1..(*line).min(line_count));
+            }
+            Self::DeleteRange { start, end } | Self::ReplaceRange { start, end, .. } => {
+                (*start, *end)
+            }
+        };
+        if start == 0 || start > end || end > line_count {
+            return Err(format!(
+                "range {}..={} is invalid (the file has {} lines)",
+                start, end, line_count
+            ));
+        }
+        Ok(start - 1..end)
+    }
+}
+
+/// Applies `edit` to `contents`, keeping the file's line ending style and
+/// whether it ends with a newline.
+fn apply_line_edit(
+    contents: &str,
+    edit: &LineEdit,
+    expected_context: Option<&str>,
+) -> Result<String, Value> {
+    let mut lines: Vec<String> = contents.split_inclusive('\n').map(str::to_string).collect();
+    let anchor = edit
+        .anchor(lines.len())
+        .map_err(|details| json!({ "error": "invalid_range", "details": details }))?;
+    let actual = lines[anchor.clone()].concat();
+    if let Some(expected) = expected_context {
+        if normalize(expected) != normalize(&actual) {
+            return Err(json!({
+                "error": "context_mismatch",
+                "details": "expected_context does not match the current lines; read the file again",
+                "actual_context": actual,
+            }));
+        }
+    }
+
+    let eol = if contents.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let to_lines = |content: &str| -> Vec<String> {
+        content
+            .lines()
+            .map(|line| format!("{}{}", line, eol))
+            .collect()
+    };
+    let ends_without_newline = !contents.is_empty() && !contents.ends_with('\n');
+    match edit {
+        LineEdit::InsertAt { line, content } => {
+            if *line > lines.len() {
+                if let Some(last) = lines.last_mut().filter(|_| ends_without_newline) {
+                    last.push_str(eol);
+                }
+            }
+            lines.splice(line - 1..line - 1, to_lines(content));
+        }
+        LineEdit::DeleteRange { .. } => {
+            lines.drain(anchor.clone());
+        }
+        LineEdit::ReplaceRange { content, .. } => {
+            lines.splice(anchor.clone(), to_lines(content));
+        }
+    }
+    let mut edited = lines.concat();
+    let touches_end = anchor.end == contents.split_inclusive('\n').count();
+    if ends_without_newline && touches_end && !matches!(edit, LineEdit::InsertAt { .. }) {
+        let trimmed = edited.trim_end_matches(['\r', '\n']).len();
+        edited.truncate(trimmed);
+    }
+    Ok(edited)
+}
+
+fn normalize(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .trim_end_matches('\n')
+        .to_string()
+}
+
+pub struct EditLinesTool {
+    workspace: Arc<Workspace>,
+}
+
+impl EditLinesTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for EditLinesTool {
+    fn name(&self) -> &'static str {
+        "edit_lines"
+    }
+
+    fn description(&self) -> &'static str {
+        "Edit a file by 1-based line numbers: insert_at (before `line`), delete_range, or replace_range (inclusive). Pass the current text of the affected lines as `expected_context` so a stale line number cannot edit the wrong code. Prefer replace_in_file when the exact text is easier to quote."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Line-number based edit guarded by the expected current lines.",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "File path to modify."
+                },
+                "op": {
+                    "type": "string",
+                    "enum": ["insert_at", "delete_range", "replace_range"]
+                },
+                "line": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "description": "insert_at: the new lines go before this line; one past the last line appends."
+                },
+                "start_line": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "description": "delete_range/replace_range: first line, inclusive."
+                },
+                "end_line": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "description": "delete_range/replace_range: last line, inclusive."
+                },
+                "content": {
+                    "type": "string",
+                    "description": "insert_at/replace_range: lines to write."
+                },
+                "expected_context": {
+                    "type": "string",
+                    "description": "Current text of start_line..=end_line (insert_at: of `line`, empty when appending). The edit is refused with context_mismatch when it differs."
+                },
+                "precondition": {
+                    "type": "object",
+                    "description": "Optional file-state guard with expected_hash, expected_mtime_unix_ms, or expected_size_bytes."
+                }
+            },
+            "required": ["path", "op"],
+            "examples": [
+                {
+                    "path": "src/main.rs",
+                    "op": "replace_range",
+                    "start_line": 12,
+                    "end_line": 13,
+                    "content": "    let limit = 50;\n    run(limit);",
+                    "expected_context": "    let limit = 25;\n    run(limit);"
+                },
+                {
+                    "path": "src/lib.rs",
+                    "op": "insert_at",
+                    "line": 1,
+                    "content": "//! Crate docs.",
+                    "expected_context": "use std::fmt;"
+                },
+                {
+                    "path": "notes.md",
+                    "op": "delete_range",
+                    "start_line": 4,
+                    "end_line": 6
+                }
+            ]
+        })
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let path = input
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("'path' parameter is required"))?;
+        let edit = LineEdit::parse(&input)?;
+        let path_buf = self.workspace.resolve(path)?;
+
+        if let Some(conflict) = apply_precondition(&input, &path_buf).await? {
+            return Ok(conflict);
+        }
+
+        let contents = read_to_string(&path_buf)
+            .await
+            .context("failed to read target file")?;
+        let expected_context = input.get("expected_context").and_then(|v| v.as_str());
+        let edited = match apply_line_edit(&contents, &edit, expected_context) {
+            Ok(edited) => edited,
+            Err(mut refusal) => {
+                refusal["success"] = json!(false);
+                refusal["path"] = json!(path);
+                return Ok(refusal);
+            }
+        };
+
+        write_atomically(&path_buf, edited.as_bytes())
+            .await
+            .context("failed to write edited content")?;
+
+        Ok(json!({
+            "path": path,
+            "lines_before": contents.lines().count(),
+            "lines_after": edited.lines().count()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_line_edit, LineEdit};
+
+    #[test]
+    fn line_edits_keep_line_endings_and_check_context() {
+        let replace = LineEdit::ReplaceRange {
+            start: 2,
+            end: 3,
+            content: "B\nC2".to_string(),
+        };
+        assert_eq!(
+            apply_line_edit("a\r\nb\r\nc\r\nd\r\n", &replace, Some("b\nc\n")).unwrap(),
+            "a\r\nB\r\nC2\r\nd\r\n"
+        );
+        let refusal = apply_line_edit("a\nb\nc\n", &replace, Some("x")).unwrap_err();
+        assert_eq!(refusal["error"], "context_mismatch");
+        assert_eq!(refusal["actual_context"], "b\nc\n");
+
+        let append = LineEdit::InsertAt {
+            line: 3,
+            content: "c".to_string(),
+        };
+        assert_eq!(
+            apply_line_edit("a\nb", &append, Some("")).unwrap(),
+            "a\nb\nc\n"
+        );
+        let delete_last = LineEdit::DeleteRange { start: 2, end: 2 };
+        assert_eq!(apply_line_edit("a\nb", &delete_last, None).unwrap(), "a");
+        let out_of_range = LineEdit::DeleteRange { start: 2, end: 4 };
+        assert_eq!(
+            apply_line_edit("a\nb\n", &out_of_range, None).unwrap_err()["error"],
+            "invalid_range"
+        );
+    }
+}
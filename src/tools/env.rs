@@ -0,0 +1,190 @@
+use crate::tools::glob::glob_match;
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Variables commands inherit from rx by default: locale, paths, and
+/// toolchain locations, plus what Windows programs need to start.
+const DEFAULT_INHERIT: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "TERM",
+    "COLORTERM",
+    "LANG",
+    "LANGUAGE",
+    "LC_*",
+    "TZ",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "XDG_*",
+    "CARGO_HOME",
+    "RUSTUP_HOME",
+    "RUSTUP_TOOLCHAIN",
+    "GOPATH",
+    "GOROOT",
+    "JAVA_HOME",
+    "NVM_DIR",
+    "PYENV_ROOT",
+    "VIRTUAL_ENV",
+    "SSL_CERT_FILE",
+    "SSL_CERT_DIR",
+    "SYSTEMROOT",
+    "SYSTEMDRIVE",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+    "USERNAME",
+    "USERPROFILE",
+    "HOMEDRIVE",
+    "HOMEPATH",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "PROGRAMDATA",
+    "PROGRAMFILES*",
+    "NUMBER_OF_PROCESSORS",
+    "PROCESSOR_ARCHITECTURE",
+    "OS",
+];
+
+/// Names that look like credentials; never inherited, even when an
+/// inherit pattern matches them.
+const DEFAULT_DENY: &[&str] = &[
+    "*TOKEN*",
+    "*SECRET*",
+    "*PASSWORD*",
+    "*PASSWD*",
+    "*API_KEY*",
+    "*APIKEY*",
+    "*PRIVATE_KEY*",
+    "*ACCESS_KEY*",
+    "*CREDENTIAL*",
+    "*_KEY",
+];
+
+/// Which of rx's own environment variables reach the commands the agent
+/// runs. Everything else is dropped, so API keys in the parent shell are
+/// not handed to every command; `[env]` values and a call's `env` argument
+/// are set explicitly on top. Names match case-insensitively.
+#[derive(Debug, Clone)]
+pub struct EnvPolicy {
+    inherit: Vec<String>,
+    deny: Vec<String>,
+    inherit_all: bool,
+}
+
+impl Default for EnvPolicy {
+    fn default() -> Self {
+        Self {
+            inherit: DEFAULT_INHERIT
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            deny: DEFAULT_DENY.iter().map(|name| name.to_string()).collect(),
+            inherit_all: false,
+        }
+    }
+}
+
+impl EnvPolicy {
+    /// Inherits names matching `patterns` as well.
+    pub fn with_inherit(mut self, patterns: &[String]) -> Self {
+        self.inherit.extend(patterns.iter().cloned());
+        self
+    }
+
+    /// Never inherits names matching `patterns`.
+    pub fn with_deny(mut self, patterns: &[String]) -> Self {
+        self.deny.extend(patterns.iter().cloned());
+        self
+    }
+
+    /// Inherits every name that is not denied.
+    pub fn with_inherit_all(mut self, inherit_all: bool) -> Self {
+        self.inherit_all = inherit_all;
+        self
+    }
+
+    pub fn allows(&self, name: &str) -> bool {
+        let matches = |patterns: &[String]| {
+            patterns.iter().any(|pattern| {
+                glob_match(&pattern.to_ascii_uppercase(), &name.to_ascii_uppercase())
+            })
+        };
+        (self.inherit_all || matches(&self.inherit)) && !matches(&self.deny)
+    }
+
+    /// The allowed part of rx's environment. Variables that are not valid
+    /// UTF-8 are dropped.
+    pub fn inherited(&self) -> BTreeMap<String, String> {
+        std::env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+            .filter(|(name, _)| self.allows(name))
+            .collect()
+    }
+}
+
+/// JSON schema of the `env` argument of command tools.
+pub fn env_parameter() -> Value {
+    serde_json::json!({
+        "type": "object",
+        "additionalProperties": { "type": "string" },
+        "description": "Extra environment variables for this command, e.g. {\"RUST_LOG\": \"debug\"}. Commands only inherit a safe subset of rx's environment (PATH, HOME, locale, toolchain paths); secrets are not passed on."
+    })
+}
+
+/// The `env` argument of a command tool call.
+pub fn call_env(input: &Value) -> Result<BTreeMap<String, String>> {
+    let Some(env) = input.get("env").filter(|env| !env.is_null()) else {
+        return Ok(BTreeMap::new());
+    };
+    let env = env
+        .as_object()
+        .ok_or_else(|| anyhow!("'env' must be an object of strings"))?;
+    env.iter()
+        .map(|(name, value)| {
+            if name.is_empty() || name.contains(['=', '\0']) {
+                return Err(anyhow!("invalid environment variable name {:?}", name));
+            }
+            value
+                .as_str()
+                .map(|value| (name.clone(), value.to_string()))
+                .ok_or_else(|| anyhow!("environment variable {} must be a string", name))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{call_env, EnvPolicy};
+    use serde_json::json;
+
+    #[test]
+    fn secrets_are_not_inherited() {
+        let policy = EnvPolicy::default();
+        assert!(policy.allows("PATH"));
+        assert!(policy.allows("LC_ALL"));
+        assert!(!policy.allows("OPENAI_API_KEY"));
+        assert!(!policy.allows("GITHUB_TOKEN"));
+        assert!(!policy.allows("RUST_LOG"));
+        assert!(!policy.allows("XDG_SECRET_DIR"));
+
+        let policy = EnvPolicy::default()
+            .with_inherit(&["RUST_*".to_string()])
+            .with_deny(&["RUST_BACKTRACE".to_string()]);
+        assert!(policy.allows("rust_log"));
+        assert!(!policy.allows("RUST_BACKTRACE"));
+        let policy = EnvPolicy::default().with_inherit_all(true);
+        assert!(policy.allows("RUST_LOG"));
+        assert!(!policy.allows("AWS_SECRET_ACCESS_KEY"));
+
+        let env = call_env(&json!({ "env": { "RUST_LOG": "debug" } })).unwrap();
+        assert_eq!(env["RUST_LOG"], "debug");
+        assert!(call_env(&json!({})).unwrap().is_empty());
+        assert!(call_env(&json!({ "env": { "N": 1 } })).is_err());
+        assert!(call_env(&json!({ "env": { "A=B": "x" } })).is_err());
+    }
+}
@@ -1,10 +1,54 @@
 use crate::tool::Tool;
+use crate::tools::env::{call_env, env_parameter, EnvPolicy};
+use crate::tools::shell::resolve_program;
+use crate::tools::workspace::Workspace;
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::process::{Output, Stdio};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::process::Command;
+use tokio::sync::mpsc::UnboundedSender;
 
-pub struct ExecTool;
+#[derive(Default)]
+pub struct ExecTool {
+    env: BTreeMap<String, String>,
+    env_policy: Option<EnvPolicy>,
+    workspace: Option<Arc<Workspace>>,
+    live_output: Option<UnboundedSender<String>>,
+}
+
+impl ExecTool {
+    /// Variables exported to every spawned command, on top of the inherited
+    /// environment.
+    pub fn with_env(mut self, env: BTreeMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Passes on only the variables `env_policy` allows instead of the whole
+    /// environment.
+    pub fn with_env_policy(mut self, env_policy: EnvPolicy) -> Self {
+        self.env_policy = Some(env_policy);
+        self
+    }
+
+    /// Runs commands from the workspace's base directory and resolves
+    /// `stdout_path` artifacts inside the workspace.
+    pub fn with_workspace(mut self, workspace: Arc<Workspace>) -> Self {
+        self.workspace = Some(workspace);
+        self
+    }
+
+    /// Sends stdout and stderr chunks here as the command prints them
+    /// (`rx run --tui`); the result still carries the full output.
+    pub fn with_live_output(mut self, live_output: UnboundedSender<String>) -> Self {
+        self.live_output = Some(live_output);
+        self
+    }
+}
 
 #[async_trait]
 impl Tool for ExecTool {
@@ -33,6 +77,11 @@ impl Tool for ExecTool {
                 "cwd": {
                     "type": "string",
                     "description": "Optional working directory for the command."
+                },
+                "env": env_parameter(),
+                "stdout_path": {
+                    "type": "string",
+                    "description": "Write stdout to this workspace file instead of returning it; use for binary or very large output (archives, coverage reports, images). The result has the path and size."
                 }
             },
             "required": ["command"],
@@ -49,6 +98,16 @@ impl Tool for ExecTool {
                 {
                     "command": "git",
                     "args": ["status", "--short"]
+                },
+                {
+                    "command": "cargo",
+                    "args": ["run"],
+                    "env": { "RUST_LOG": "debug" }
+                },
+                {
+                    "command": "git",
+                    "args": ["archive", "HEAD"],
+                    "stdout_path": ".rx/artifacts/source.tar"
                 }
             ]
         })
@@ -71,14 +130,67 @@ impl Tool for ExecTool {
             })
             .unwrap_or_default();
 
-        let mut cmd = Command::new(command);
-        cmd.args(&args);
+        let call_env = call_env(&input)?;
+        let mut cmd = Command::new(resolve_program(command));
+        if let Some(policy) = &self.env_policy {
+            cmd.env_clear().envs(policy.inherited());
+        }
+        // The kernel drops this future on timeout; take the child with it.
+        cmd.args(&args)
+            .envs(&self.env)
+            .envs(&call_env)
+            .kill_on_drop(true);
 
-        if let Some(cwd) = input.get("cwd").and_then(|v| v.as_str()) {
-            cmd.current_dir(cwd);
+        let cwd = input.get("cwd").and_then(|v| v.as_str());
+        match (&self.workspace, cwd) {
+            (Some(workspace), cwd) => {
+                cmd.current_dir(workspace.command_dir(cwd));
+            }
+            (None, Some(cwd)) => {
+                cmd.current_dir(cwd);
+            }
+            (None, None) => {}
         }
 
-        let output = cmd.output().await.context("failed to execute command")?;
+        if let Some(path) = input.get("stdout_path").and_then(|v| v.as_str()) {
+            let workspace = self
+                .workspace
+                .as_ref()
+                .ok_or_else(|| anyhow!("stdout_path needs a workspace"))?;
+            let artifact = workspace.resolve(path)?;
+            if let Some(parent) = artifact.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("failed to create {}", parent.display()))?;
+            }
+            let file = std::fs::File::create(&artifact)
+                .with_context(|| format!("failed to create {}", path))?;
+            // `output()` would replace the redirect with a pipe.
+            let output = cmd
+                .stdin(Stdio::null())
+                .stdout(Stdio::from(file))
+                .stderr(Stdio::piped())
+                .spawn()
+                .context("failed to execute command")?
+                .wait_with_output()
+                .await
+                .context("failed to execute command")?;
+            let stdout_bytes = std::fs::metadata(&artifact)
+                .with_context(|| format!("failed to stat {}", path))?
+                .len();
+            return Ok(json!({
+                "stdout_path": path,
+                "stdout_bytes": stdout_bytes,
+                "stderr": String::from_utf8_lossy(&output.stderr),
+                "status": output.status.to_string(),
+                "code": output.status.code(),
+            }));
+        }
+
+        let output = match &self.live_output {
+            Some(live_output) => output_with_live(cmd, live_output).await,
+            None => cmd.output().await,
+        }
+        .context("failed to execute command")?;
 
         Ok(json!({
             "stdout": String::from_utf8_lossy(&output.stdout),
@@ -88,3 +200,88 @@ impl Tool for ExecTool {
         }))
     }
 }
+
+/// Like `Command::output`, also sending each chunk to `live_output`.
+async fn output_with_live(
+    mut cmd: Command,
+    live_output: &UnboundedSender<String>,
+) -> std::io::Result<Output> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let (stdout, stderr, status) = tokio::try_join!(
+        tee(stdout, live_output),
+        tee(stderr, live_output),
+        child.wait()
+    )?;
+    Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+async fn tee<R: AsyncRead + Unpin>(
+    mut pipe: R,
+    live_output: &UnboundedSender<String>,
+) -> std::io::Result<Vec<u8>> {
+    let mut collected = Vec::new();
+    let mut chunk = [0u8; 8192];
+    loop {
+        let read = pipe.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(collected);
+        }
+        collected.extend_from_slice(&chunk[..read]);
+        // A closed screen is not the command's problem.
+        let _ = live_output.send(String::from_utf8_lossy(&chunk[..read]).into_owned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExecTool;
+    use crate::tool::Tool;
+    use crate::tools::workspace::Workspace;
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[tokio::test]
+    async fn stdout_path_writes_output_to_a_workspace_file() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("rx-exec-artifact-{}", nanos));
+        std::fs::create_dir_all(&root).unwrap();
+        let tool = ExecTool::default()
+            .with_workspace(Arc::new(Workspace::new(&root, &root, &[]).unwrap()));
+
+        let output = tool
+            .execute(json!({
+                "command": "sh",
+                "args": ["-c", "printf 'binary\\000data'; echo warn >&2"],
+                "stdout_path": "out/data.bin"
+            }))
+            .await
+            .unwrap();
+        assert_eq!(output["stdout_bytes"], 11);
+        assert_eq!(output["stderr"], "warn\n");
+        assert!(output.get("stdout").is_none());
+        assert_eq!(
+            std::fs::read(root.join("out/data.bin")).unwrap(),
+            b"binary\0data"
+        );
+
+        let escaped = tool
+            .execute(json!({ "command": "true", "stdout_path": "../outside.bin" }))
+            .await;
+        assert!(escaped.is_err());
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
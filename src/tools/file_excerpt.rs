@@ -0,0 +1,295 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+use std::io::SeekFrom;
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// Bytes of text `read_file` returns unless `max_bytes` is passed.
+pub const DEFAULT_READ_BYTES: usize = 64 * 1024;
+
+/// Upper bound on `max_bytes` for one `read_file` call.
+pub const MAX_READ_BYTES: usize = 1024 * 1024;
+
+/// Leading bytes inspected to tell binary files from text.
+const SNIFF_BYTES: usize = 8 * 1024;
+
+/// Leading bytes of a binary file shown as hex.
+const HEX_PREVIEW_BYTES: usize = 64;
+
+const BINARY_DETAILS: &str = "binary file; its content is not returned as text. Inspect it with a dedicated command through exec (for example `file`, `xxd`, or an image or archive tool).";
+
+/// The `read_file` fields for the file at `path` of `size` bytes: the whole
+/// `content` when it fits in `max_bytes`, else its head and tail around an
+/// omission marker with a `truncated` notice and a `next_cursor` to page on
+/// from the end of the head. With `cursor`, the page of up to `max_bytes`
+/// starting at that byte offset instead. A binary file gets `binary`, a
+/// guessed `kind`, and a hex preview instead of content. Only the parts
+/// returned are loaded into memory.
+pub async fn read_excerpt(
+    path: &Path,
+    size: u64,
+    max_bytes: usize,
+    cursor: Option<u64>,
+) -> Result<Map<String, Value>> {
+    let mut file = File::open(path).await.context("failed to read file")?;
+    let head = read_at(&mut file, 0, max_bytes.max(SNIFF_BYTES)).await?;
+    let mut fields = Map::new();
+    if is_binary(&head, size as usize <= head.len()) {
+        fields.insert("content".to_string(), Value::Null);
+        fields.insert("binary".to_string(), json!(true));
+        fields.insert("kind".to_string(), json!(binary_kind(&head)));
+        fields.insert(
+            "head_hex".to_string(),
+            json!(hex(&head[..head.len().min(HEX_PREVIEW_BYTES)])),
+        );
+        fields.insert("details".to_string(), json!(BINARY_DETAILS));
+        return Ok(fields);
+    }
+    if let Some(cursor) = cursor {
+        // At least one whole character, so every page moves forward.
+        let bytes = read_at(&mut file, cursor, max_bytes.max(4)).await?;
+        let skipped = partial_start(&bytes);
+        let eof = cursor + bytes.len() as u64 >= size;
+        let (content, used) = decode_page(&bytes[skipped..], max_bytes, eof);
+        let end = cursor + (skipped + used) as u64;
+        fields.insert("content".to_string(), json!(content));
+        fields.insert("cursor".to_string(), json!(cursor));
+        fields.insert(
+            "next_cursor".to_string(),
+            json!((end < size).then_some(end)),
+        );
+        return Ok(fields);
+    }
+    if size as usize <= max_bytes {
+        fields.insert(
+            "content".to_string(),
+            json!(decode_page(&head, head.len(), true).0),
+        );
+        return Ok(fields);
+    }
+    let (head, head_used) = decode_page(&head, max_bytes / 2, false);
+    let tail_len = max_bytes / 2;
+    let tail_bytes = read_at(&mut file, size - tail_len as u64, tail_len).await?;
+    let tail_start = partial_start(&tail_bytes);
+    let (tail, _) = decode_page(&tail_bytes[tail_start..], tail_len, true);
+    let omitted = size - head_used as u64 - (tail_bytes.len() - tail_start) as u64;
+    let details = format!(
+        "the file is larger than max_bytes ({}); only its head and tail are shown. Pass next_cursor as cursor to read the rest page by page",
+        max_bytes
+    );
+    fields.insert(
+        "content".to_string(),
+        json!(format!(
+            "{}\n[... {} bytes omitted ...]\n{}",
+            head, omitted, tail
+        )),
+    );
+    fields.insert(
+        "truncated".to_string(),
+        json!({
+            "bytes": size,
+            "omitted": omitted,
+            "details": details,
+        }),
+    );
+    fields.insert("next_cursor".to_string(), json!(head_used));
+    Ok(fields)
+}
+
+/// SHA-256 of the whole file, read in chunks.
+pub async fn hash_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path).await.context("failed to read file")?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .await
+            .context("failed to read file")?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+async fn read_at(file: &mut File, offset: u64, len: usize) -> Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset))
+        .await
+        .context("failed to read file")?;
+    let mut bytes = Vec::with_capacity(len);
+    file.take(len as u64)
+        .read_to_end(&mut bytes)
+        .await
+        .context("failed to read file")?;
+    Ok(bytes)
+}
+
+/// A NUL byte or invalid UTF-8 among the leading bytes. A multi-byte
+/// character cut off at the end of `head` counts as text unless `head` is
+/// the whole file.
+fn is_binary(head: &[u8], whole: bool) -> bool {
+    let sniffed = &head[..head.len().min(SNIFF_BYTES)];
+    if sniffed.contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => false,
+        Err(error) => whole || error.error_len().is_some(),
+    }
+}
+
+/// Up to `limit` bytes of `bytes` as text, and the bytes it covers.
+/// Invalid UTF-8 becomes U+FFFD so a page never stops short of it; a
+/// character cut off at the end is left for the next page unless `eof`.
+/// The first character is taken even when it is longer than `limit`.
+fn decode_page(bytes: &[u8], limit: usize, eof: bool) -> (String, usize) {
+    let mut text = String::new();
+    let mut used = 0;
+    while used < bytes.len() {
+        let rest = &bytes[used..];
+        let (valid, invalid) = match std::str::from_utf8(rest) {
+            Ok(valid) => (valid, 0),
+            Err(error) => {
+                let valid = std::str::from_utf8(&rest[..error.valid_up_to()]).unwrap_or_default();
+                let cut_off = rest.len() - error.valid_up_to();
+                let invalid = error.error_len().unwrap_or(if eof { cut_off } else { 0 });
+                (valid, invalid)
+            }
+        };
+        for ch in valid.chars() {
+            if used > 0 && used + ch.len_utf8() > limit {
+                return (text, used);
+            }
+            text.push(ch);
+            used += ch.len_utf8();
+        }
+        if invalid == 0 || (used > 0 && used + invalid > limit) {
+            break;
+        }
+        text.push(char::REPLACEMENT_CHARACTER);
+        used += invalid;
+    }
+    (text, used)
+}
+
+/// Continuation bytes at the start of `bytes`, left over from a character
+/// that began before it.
+fn partial_start(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .take(3)
+        .take_while(|byte| (**byte & 0b1100_0000) == 0b1000_0000)
+        .count()
+}
+
+/// File type from well-known magic numbers.
+fn binary_kind(head: &[u8]) -> &'static str {
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"\x89PNG", "png"),
+        (b"\xff\xd8\xff", "jpeg"),
+        (b"GIF8", "gif"),
+        (b"%PDF", "pdf"),
+        (b"PK\x03\x04", "zip"),
+        (b"\x1f\x8b", "gzip"),
+        (b"\x7fELF", "elf"),
+        (b"MZ", "pe"),
+        (b"\0asm", "wasm"),
+        (b"SQLite format 3", "sqlite"),
+    ];
+    MAGIC
+        .iter()
+        .find(|(magic, _)| head.starts_with(magic))
+        .map_or("unknown", |(_, kind)| kind)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        write!(out, "{:02x}", byte).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_file, read_excerpt};
+    use crate::testing::TempDir;
+    use crate::tools::fs::compute_hash;
+
+    #[tokio::test]
+    async fn large_and_binary_files_are_bounded() {
+        let dir = TempDir::new("excerpt");
+
+        let small = dir.join("small.txt");
+        std::fs::write(&small, "héllo\n").unwrap();
+        let read = read_excerpt(&small, 7, 1024, None).await.unwrap();
+        assert_eq!(read["content"], "héllo\n");
+        assert!(read.get("truncated").is_none());
+
+        let big = dir.join("big.log");
+        let text = format!("{}{}", "é".repeat(3_000), "z".repeat(6_000));
+        std::fs::write(&big, &text).unwrap();
+        let read = read_excerpt(&big, text.len() as u64, 1_001, None)
+            .await
+            .unwrap();
+        let content = read["content"].as_str().unwrap();
+        assert!(content.starts_with("éé") && content.ends_with("zzz"));
+        assert!(content.contains("bytes omitted"));
+        assert_eq!(read["truncated"]["bytes"], text.len());
+
+        // Paging from next_cursor walks the whole file without splitting
+        // characters.
+        let mut cursor = read["next_cursor"].as_u64();
+        let mut paged = content[..content.find('\n').unwrap()].to_string();
+        while let Some(at) = cursor {
+            let page = read_excerpt(&big, text.len() as u64, 1_001, Some(at))
+                .await
+                .unwrap();
+            paged.push_str(page["content"].as_str().unwrap());
+            cursor = page["next_cursor"].as_u64();
+        }
+        assert_eq!(paged, text);
+        assert_eq!(
+            hash_file(&big).await.unwrap(),
+            compute_hash(text.as_bytes())
+        );
+
+        let image = dir.join("logo.png");
+        let bytes = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        std::fs::write(&image, &bytes).unwrap();
+        let read = read_excerpt(&image, bytes.len() as u64, 1024, None)
+            .await
+            .unwrap();
+        assert_eq!(read["binary"], true);
+        assert_eq!(read["kind"], "png");
+        assert!(read["content"].is_null());
+        assert!(read["head_hex"].as_str().unwrap().starts_with("89504e47"));
+
+        // Invalid UTF-8 past the sniffed head is replaced, and paging still
+        // reaches the end.
+        let mixed = dir.join("mixed.log");
+        let mut bytes = "a".repeat(10_000).into_bytes();
+        bytes.push(0xff);
+        bytes.extend("b".repeat(100).bytes());
+        std::fs::write(&mixed, &bytes).unwrap();
+        let (mut cursor, mut paged, mut pages) = (Some(0), String::new(), 0);
+        while let Some(at) = cursor {
+            let page = read_excerpt(&mixed, bytes.len() as u64, 1_000, Some(at))
+                .await
+                .unwrap();
+            paged.push_str(page["content"].as_str().unwrap());
+            cursor = page["next_cursor"].as_u64();
+            pages += 1;
+            assert!(pages <= 11, "paging must move forward");
+        }
+        assert_eq!(
+            paged,
+            format!("{}\u{fffd}{}", "a".repeat(10_000), "b".repeat(100))
+        );
+    }
+}
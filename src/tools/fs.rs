@@ -1,14 +1,21 @@
 use crate::tool::Tool;
+use crate::tools::file_excerpt::{hash_file, read_excerpt, DEFAULT_READ_BYTES, MAX_READ_BYTES};
+use crate::tools::workspace::Workspace;
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-use diffy::{apply, Patch};
+use diffy::Patch;
+use rx::patch::{
+    apply_hunks, apply_unified_hunks, check_hunks, check_unified_hunks, normalize_lines,
+    parse_git_diff, parse_patch, FileOp, GitFilePatch, HunkCheck, HunkReport, MAX_FUZZ,
+};
 use serde_json::{json, Map, Number, Value};
 use sha2::{Digest, Sha256};
 use std::fmt::Write;
 use std::fs;
 use std::io::ErrorKind;
-use std::path::{Component, Path, PathBuf};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::fs::{
     create_dir_all, metadata, read, read_dir, read_to_string, remove_file, rename, OpenOptions,
@@ -17,14 +24,108 @@ use tokio::io::AsyncWriteExt;
 
 static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
-pub struct ReadFileTool;
-pub struct WriteFileTool;
-pub struct ListDirTool;
-pub struct CreateFileTool;
-pub struct AppendFileTool;
-pub struct ReplaceInFileTool;
-pub struct ApplyPatchTool;
-pub struct ApplyUnifiedPatchTool;
+pub struct ReadFileTool {
+    workspace: Arc<Workspace>,
+}
+
+pub struct WriteFileTool {
+    workspace: Arc<Workspace>,
+}
+
+pub struct ListDirTool {
+    workspace: Arc<Workspace>,
+}
+
+pub struct CreateFileTool {
+    workspace: Arc<Workspace>,
+}
+
+pub struct AppendFileTool {
+    workspace: Arc<Workspace>,
+}
+
+pub struct ReplaceInFileTool {
+    workspace: Arc<Workspace>,
+}
+
+pub struct ApplyPatchTool {
+    workspace: Arc<Workspace>,
+    fuzz: usize,
+}
+
+pub struct ApplyUnifiedPatchTool {
+    workspace: Arc<Workspace>,
+    fuzz: usize,
+}
+
+impl ReadFileTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+impl WriteFileTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+impl ListDirTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+impl CreateFileTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+impl AppendFileTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+impl ReplaceInFileTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+impl ApplyPatchTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace, fuzz: 0 }
+    }
+
+    /// Fuzz level used when a call does not pass `fuzz`.
+    pub fn with_fuzz(mut self, fuzz: usize) -> Self {
+        self.fuzz = fuzz;
+        self
+    }
+}
+
+impl ApplyUnifiedPatchTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace, fuzz: 0 }
+    }
+
+    /// Fuzz level used when a call does not pass `fuzz`.
+    pub fn with_fuzz(mut self, fuzz: usize) -> Self {
+        self.fuzz = fuzz;
+        self
+    }
+}
+
+/// The call's `fuzz` argument, or the tool's default.
+fn fuzz_level(input: &Value, default: usize) -> usize {
+    input
+        .get("fuzz")
+        .and_then(|v| v.as_u64())
+        .map_or(default, |fuzz| fuzz as usize)
+        .min(MAX_FUZZ)
+}
 
 #[async_trait]
 impl Tool for ReadFileTool {
@@ -33,47 +134,77 @@ impl Tool for ReadFileTool {
     }
 
     fn description(&self) -> &'static str {
-        "Read an entire UTF-8 text file and return content plus metadata (hash, mtime, size). Use this to inspect current file state before planning edits."
+        "Read a UTF-8 text file and return content plus metadata (hash, mtime, size). Use this to inspect current file state before planning edits. Files over max_bytes (default 64 KiB) come back as head and tail with a truncated notice and a next_cursor to page through the rest with cursor; binary files return a type guess and hex preview instead of content."
     }
 
     fn parameters(&self) -> Value {
         json!({
             "type": "object",
-            "description": "Read full text content of a file path.",
+            "description": "Read the text content of a file path.",
             "properties": {
                 "path": {
                     "type": "string",
                     "description": "File path to read."
+                },
+                "max_bytes": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "maximum": MAX_READ_BYTES,
+                    "description": "Most bytes of content to return (default 65536); larger files are shown as head and tail."
+                },
+                "cursor": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "Byte offset to read a page from; pass next_cursor from the previous call to walk a large file."
                 }
             },
             "required": ["path"],
             "examples": [
                 { "path": "README.md" },
                 { "path": "src/main.rs" },
-                { "path": ".rx/config.toml" }
+                { "path": "logs/build.log", "max_bytes": 8192 },
+                { "path": "logs/build.log", "cursor": 32768 }
             ]
         })
     }
 
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
     async fn execute(&self, input: Value) -> Result<Value> {
         let path = input
             .get("path")
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow!("'path' parameter is required"))?;
-        let contents = read_to_string(path).await.context("failed to read file")?;
-        let metadata = metadata(path).await.context("failed to stat file")?;
+        let max_bytes = input
+            .get("max_bytes")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_READ_BYTES, |bytes| bytes as usize)
+            .clamp(1, MAX_READ_BYTES);
+        let path_buf = self.workspace.resolve(path)?;
+        let metadata = metadata(&path_buf).await.context("failed to stat file")?;
         let size_bytes = metadata.len();
         let mtime_unix_ms = metadata.modified().ok().and_then(system_time_to_unix_ms);
-        let hash = compute_hash(contents.as_bytes());
+        let cursor = input.get("cursor").and_then(|v| v.as_u64());
+        if let Some(cursor) = cursor.filter(|cursor| *cursor > size_bytes) {
+            return Ok(json!({
+                "error": "invalid_cursor",
+                "details": format!("cursor {} is past the end of the file ({} bytes)", cursor, size_bytes),
+            }));
+        }
+        let mut output = read_excerpt(&path_buf, size_bytes, max_bytes, cursor).await?;
+        let hash = hash_file(&path_buf).await?;
 
-        Ok(json!({
-            "content": contents,
-            "metadata": {
+        output.insert(
+            "metadata".to_string(),
+            json!({
                 "hash": hash,
                 "mtime_unix_ms": mtime_unix_ms,
                 "size_bytes": size_bytes
-            }
-        }))
+            }),
+        );
+        Ok(Value::Object(output))
     }
 }
 
@@ -154,7 +285,7 @@ impl Tool for WriteFileTool {
             .and_then(|v| v.as_str())
             .unwrap_or("overwrite");
 
-        let path_buf = PathBuf::from(path);
+        let path_buf = self.workspace.resolve(path)?;
 
         if let Some(conflict) = apply_precondition(&input, &path_buf).await? {
             return Ok(conflict);
@@ -209,11 +340,18 @@ impl Tool for ListDirTool {
         })
     }
 
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
     async fn execute(&self, input: Value) -> Result<Value> {
         let path = input.get("path").and_then(|v| v.as_str()).unwrap_or(".");
 
         let mut entries = Vec::new();
-        let mut dir = read_dir(path).await.context("failed to read directory")?;
+        let dir_path = self.workspace.resolve(path)?;
+        let mut dir = read_dir(&dir_path)
+            .await
+            .context("failed to read directory")?;
         while let Some(entry) = dir
             .next_entry()
             .await
@@ -301,7 +439,7 @@ impl Tool for CreateFileTool {
             .get("content")
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow!("'content' parameter is required"))?;
-        let path_buf = PathBuf::from(path);
+        let path_buf = self.workspace.resolve(path)?;
 
         if let Some(conflict) = apply_precondition(&input, &path_buf).await? {
             return Ok(conflict);
@@ -383,7 +521,7 @@ impl Tool for AppendFileTool {
             .get("content")
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow!("'content' parameter is required"))?;
-        let path_buf = PathBuf::from(path);
+        let path_buf = self.workspace.resolve(path)?;
 
         if let Some(conflict) = apply_precondition(&input, &path_buf).await? {
             return Ok(conflict);
@@ -402,7 +540,7 @@ impl Tool for AppendFileTool {
 
         Ok(json!({
             "path": path,
-            "appended_bytes": content.as_bytes().len()
+            "appended_bytes": content.len()
         }))
     }
 }
@@ -489,7 +627,7 @@ impl Tool for ReplaceInFileTool {
             .and_then(|v| v.as_u64())
             .map(|v| v as usize)
             .unwrap_or(1);
-        let path_buf = PathBuf::from(path);
+        let path_buf = self.workspace.resolve(path)?;
 
         if let Some(conflict) = apply_precondition(&input, &path_buf).await? {
             return Ok(conflict);
@@ -541,6 +679,16 @@ impl Tool for ApplyPatchTool {
                 "patch": {
                     "type": "string",
                     "description": "Patch text in apply_patch format."
+                },
+                "dry_run": {
+                    "type": "boolean",
+                    "description": "Validate the patch and report which files and hunks would apply, without writing anything."
+                },
+                "fuzz": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "maximum": 3,
+                    "description": "Retry a hunk that does not match exactly: 1+ ignores whitespace differences, and level N also ignores up to N context lines at each end of the hunk. Each hunk result reports the fuzz used. Defaults to 0 (exact) unless configured."
                 }
             },
             "required": ["patch"],
@@ -561,15 +709,22 @@ impl Tool for ApplyPatchTool {
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow!("'patch' parameter is required"))?;
 
-        let patch_ops = parse_apply_patch(patch_text).context("failed to parse patch")?;
-        let summary = apply_patch_ops(&patch_ops).await.context("failed to apply patch")?;
+        let patch_ops = parse_patch(patch_text).context("failed to parse patch")?;
+        let fuzz = fuzz_level(&input, self.fuzz);
+        if input.get("dry_run").and_then(|v| v.as_bool()) == Some(true) {
+            return check_patch_ops(&self.workspace, &patch_ops, fuzz).await;
+        }
+        let summary = apply_patch_ops(&self.workspace, &patch_ops, fuzz)
+            .await
+            .context("failed to apply patch")?;
 
         Ok(json!({
             "patched": true,
             "added_files": summary.added_files,
             "updated_files": summary.updated_files,
             "deleted_files": summary.deleted_files,
-            "moved_files": summary.moved_files
+            "moved_files": summary.moved_files,
+            "hunks": summary.hunks
         }))
     }
 }
@@ -581,7 +736,7 @@ impl Tool for ApplyUnifiedPatchTool {
     }
 
     fn description(&self) -> &'static str {
-        "Apply a unified diff to a single target file. Use when edits are easier to express as contextual hunks than full rewrites."
+        "Apply a unified diff to a single target file, or `git diff` output covering several files (including added, deleted, and renamed files). Use when edits are easier to express as contextual hunks than full rewrites. Returns per-hunk old/new line numbers so follow-up edits can target lines directly."
     }
 
     fn parameters(&self) -> Value {
@@ -591,11 +746,21 @@ impl Tool for ApplyUnifiedPatchTool {
             "properties": {
                 "path": {
                     "type": "string",
-                    "description": "Target file path."
+                    "description": "Target file path. Omit it for `diff --git` patches, which name their own files."
                 },
                 "patch": {
                     "type": "string",
-                    "description": "Unified diff patch text to apply."
+                    "description": "Unified diff patch text to apply, or multi-file `git diff` output. A multi-file patch is all-or-nothing."
+                },
+                "dry_run": {
+                    "type": "boolean",
+                    "description": "Report which hunks would match (with line numbers) and which would fail, without writing."
+                },
+                "fuzz": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "maximum": 3,
+                    "description": "Retry a hunk that does not match exactly: 1+ ignores whitespace differences, and level N also ignores up to N context lines at each end of the hunk. Each hunk result reports the fuzz used. Defaults to 0 (exact) unless configured."
                 },
                 "expected_hash": {
                     "type": "string",
@@ -611,7 +776,7 @@ impl Tool for ApplyUnifiedPatchTool {
                     "description": "Optional size precondition."
                 }
             },
-            "required": ["path", "patch"],
+            "required": ["patch"],
             "examples": [
                 {
                     "path": "src/lib.rs",
@@ -620,21 +785,33 @@ impl Tool for ApplyUnifiedPatchTool {
                 {
                     "path": "README.md",
                     "patch": "--- a/README.md\n+++ b/README.md\n@@\n-Old line\n+New line\n"
+                },
+                {
+                    "patch": "diff --git a/src/util.rs b/src/helpers.rs\nsimilarity index 90%\nrename from src/util.rs\nrename to src/helpers.rs\n--- a/src/util.rs\n+++ b/src/helpers.rs\n@@ -1 +1 @@\n-pub fn util() {}\n+pub fn helper() {}\ndiff --git a/NOTES.md b/NOTES.md\nnew file mode 100644\n--- /dev/null\n+++ b/NOTES.md\n@@ -0,0 +1 @@\n+# Notes\n"
                 }
             ]
         })
     }
 
     async fn execute(&self, input: Value) -> Result<Value> {
-        let path = input
-            .get("path")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow!("'path' parameter is required"))?;
         let patch_text = input
             .get("patch")
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow!("'patch' parameter is required"))?;
-        let path_buf = PathBuf::from(path);
+        let fuzz = fuzz_level(&input, self.fuzz);
+        let dry_run = input.get("dry_run").and_then(|v| v.as_bool()) == Some(true);
+        if patch_text
+            .lines()
+            .any(|line| line.starts_with("diff --git "))
+        {
+            let files = parse_git_diff(patch_text).context("failed to parse patch")?;
+            return apply_git_diff(&self.workspace, &files, fuzz, dry_run).await;
+        }
+        let path = input
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("'path' parameter is required"))?;
+        let path_buf = self.workspace.resolve(path)?;
 
         if let Some(conflict) = apply_precondition(&input, &path_buf).await? {
             return Ok(conflict);
@@ -644,13 +821,27 @@ impl Tool for ApplyUnifiedPatchTool {
             .await
             .context("failed to read target file")?;
         let patch = Patch::from_str(patch_text).context("failed to parse patch")?;
-        let patched = apply(&base_content, &patch).context("failed to apply patch")?;
+        if dry_run {
+            let (_, checks) = check_unified_hunks(&base_content, &patch, fuzz);
+            return Ok(json!({
+                "path": path,
+                "dry_run": true,
+                "would_apply": checks.iter().all(|check| check.is_ok()),
+                "hunks": hunk_checks_to_json(&checks)
+            }));
+        }
+        let (patched, hunks) =
+            apply_unified_hunks(&base_content, &patch, fuzz).context("failed to apply patch")?;
 
         write_atomically(&path_buf, patched.as_bytes())
             .await
             .context("failed to write patched content")?;
 
-        Ok(json!({ "path": path, "patched": true }))
+        Ok(json!({
+            "path": path,
+            "patched": true,
+            "hunks": hunk_reports_to_json(&hunks)
+        }))
     }
 }
 
@@ -799,7 +990,9 @@ async fn gather_file_metadata(path: &Path) -> Result<FileMetadata> {
     })
 }
 
-async fn apply_precondition(input: &Value, path: &Path) -> Result<Option<Value>> {
+/// Checks the optional `precondition` guard; returns the conflict output
+/// when the file no longer matches.
+pub async fn apply_precondition(input: &Value, path: &Path) -> Result<Option<Value>> {
     if let Some(pre_val) = input.get("precondition") {
         let precondition = Precondition::try_from(pre_val).context("invalid precondition")?;
         return precondition
@@ -811,7 +1004,8 @@ async fn apply_precondition(input: &Value, path: &Path) -> Result<Option<Value>>
     Ok(None)
 }
 
-fn replace_n(source: &str, from: &str, to: &str, mut remaining: usize) -> String {
+/// Replaces the first `remaining` occurrences of `from`.
+pub fn replace_n(source: &str, from: &str, to: &str, mut remaining: usize) -> String {
     if remaining == 0 {
         return source.to_string();
     }
@@ -834,7 +1028,8 @@ fn replace_n(source: &str, from: &str, to: &str, mut remaining: usize) -> String
     output
 }
 
-fn compute_hash(data: &[u8]) -> String {
+/// Lowercase hex SHA-256 of `data`, as compared by `expected_hash`.
+pub fn compute_hash(data: &[u8]) -> String {
     let mut hasher = Sha256::new();
     hasher.update(data);
     let digest = hasher.finalize();
@@ -851,7 +1046,9 @@ fn system_time_to_unix_ms(time: SystemTime) -> Option<i64> {
         .map(|dur| dur.as_millis() as i64)
 }
 
-async fn write_atomically(path: &Path, data: &[u8]) -> Result<()> {
+/// Writes through a temporary file and rename so readers never see a
+/// partial file.
+pub async fn write_atomically(path: &Path, data: &[u8]) -> Result<()> {
     let parent = path.parent().unwrap_or_else(|| Path::new("."));
     let file_name = path
         .file_name()
@@ -895,233 +1092,272 @@ async fn sync_parent_dir(parent: &Path) {
     let _ = OpenOptions::new().read(true).open(parent).await;
 }
 
-#[derive(Debug)]
-enum ApplyPatchOp {
-    Add {
-        path: String,
-        lines: Vec<String>,
-    },
-    Delete {
-        path: String,
-    },
-    Update {
-        path: String,
-        move_to: Option<String>,
-        hunks: Vec<ApplyPatchHunk>,
-    },
-}
-
-#[derive(Debug, Clone)]
-struct ApplyPatchHunk {
-    lines: Vec<ApplyPatchHunkLine>,
-}
-
-#[derive(Debug, Clone)]
-enum ApplyPatchHunkLine {
-    Context(String),
-    Remove(String),
-    Add(String),
-}
-
 #[derive(Default)]
 struct ApplyPatchSummary {
     added_files: usize,
     updated_files: usize,
     deleted_files: usize,
     moved_files: usize,
+    hunks: Vec<Value>,
 }
 
-fn parse_apply_patch(input: &str) -> Result<Vec<ApplyPatchOp>> {
-    let lines: Vec<&str> = input
-        .lines()
-        .map(|line| line.strip_suffix('\r').unwrap_or(line))
-        .collect();
-
-    if lines.is_empty() {
-        return Err(anyhow!("patch is empty"));
-    }
+fn hunk_reports_to_json(reports: &[HunkReport]) -> Vec<Value> {
+    reports
+        .iter()
+        .enumerate()
+        .map(|(index, report)| {
+            json!({
+                "hunk": index + 1,
+                "old_start": report.old_start,
+                "old_lines": report.old_lines,
+                "new_start": report.new_start,
+                "new_lines": report.new_lines,
+                "offset": report.offset,
+                "fuzz": report.fuzz,
+                "ignored_whitespace": report.ignored_whitespace
+            })
+        })
+        .collect()
+}
 
-    let mut index = 0usize;
-    expect_line(&lines, index, "*** Begin Patch")?;
-    index += 1;
-
-    let mut ops = Vec::new();
-    while index < lines.len() {
-        let line = lines[index];
-        if line == "*** End Patch" {
-            index += 1;
-            if index != lines.len() {
-                return Err(anyhow!("unexpected content after *** End Patch"));
-            }
-            return Ok(ops);
-        }
-
-        if let Some(path) = line.strip_prefix("*** Add File: ") {
-            validate_relative_path(path)?;
-            index += 1;
-            let mut added = Vec::new();
-            while index < lines.len() {
-                let current = lines[index];
-                if is_patch_header(current) || current == "*** End Patch" {
-                    break;
-                }
-                let content = current
-                    .strip_prefix('+')
-                    .ok_or_else(|| anyhow!("invalid add-file line: expected '+' prefix"))?;
-                added.push(content.to_string());
-                index += 1;
+/// Dry-run results as JSON: the usual report for hunks that would apply,
+/// the failure for the rest.
+fn hunk_checks_to_json(checks: &[HunkCheck]) -> Vec<Value> {
+    checks
+        .iter()
+        .enumerate()
+        .map(|(index, check)| match check {
+            Ok(report) => {
+                let mut value = hunk_reports_to_json(std::slice::from_ref(report)).remove(0);
+                value["hunk"] = json!(index + 1);
+                value["status"] = json!("ok");
+                value
             }
+            Err(error) => json!({ "hunk": index + 1, "status": "failed", "error": error }),
+        })
+        .collect()
+}
 
-            ops.push(ApplyPatchOp::Add {
-                path: path.to_string(),
-                lines: added,
-            });
-            continue;
-        }
+/// A `diff --git` file section checked against the workspace, with the
+/// content to write when it applies.
+struct StagedGitFile {
+    report: Value,
+    source: Option<PathBuf>,
+    target: Option<(PathBuf, String)>,
+}
 
-        if let Some(path) = line.strip_prefix("*** Delete File: ") {
-            validate_relative_path(path)?;
-            ops.push(ApplyPatchOp::Delete {
-                path: path.to_string(),
-            });
-            index += 1;
-            continue;
+async fn stage_git_file(
+    workspace: &Workspace,
+    file: &GitFilePatch,
+    fuzz: usize,
+) -> Result<StagedGitFile> {
+    let source = file
+        .old_path
+        .as_deref()
+        .map(|path| workspace.resolve(path))
+        .transpose()?;
+    let target = file
+        .new_path
+        .as_deref()
+        .map(|path| workspace.resolve(path))
+        .transpose()?;
+    let (op, path) = match (&file.old_path, &file.new_path) {
+        (None, Some(new)) => ("add", new),
+        (Some(old), None) => ("delete", old),
+        (Some(old), Some(new)) if old != new => ("rename", new),
+        (_, Some(new)) => ("update", new),
+        (None, None) => unreachable!("a git diff section names at least one path"),
+    };
+    let mut report = json!({ "path": path, "op": op, "ok": true });
+    if op == "rename" {
+        report["from"] = json!(file.old_path);
+    }
+    let fail = |mut report: Value, error: String| {
+        report["ok"] = json!(false);
+        report["error"] = json!(error);
+        StagedGitFile {
+            report,
+            source: None,
+            target: None,
         }
+    };
 
-        if let Some(path) = line.strip_prefix("*** Update File: ") {
-            validate_relative_path(path)?;
-            index += 1;
-
-            let mut move_to = None;
-            if index < lines.len() {
-                if let Some(target) = lines[index].strip_prefix("*** Move to: ") {
-                    validate_relative_path(target)?;
-                    move_to = Some(target.to_string());
-                    index += 1;
-                }
-            }
-
-            let mut hunks = Vec::new();
-            while index < lines.len() {
-                let current = lines[index];
-                if is_patch_header(current) || current == "*** End Patch" {
-                    break;
-                }
-
-                if !current.starts_with("@@") {
-                    return Err(anyhow!("expected hunk header '@@', got: {}", current));
-                }
-
-                index += 1;
-                let mut hunk_lines = Vec::new();
-                while index < lines.len() {
-                    let hline = lines[index];
-                    if hline.starts_with("@@") || is_patch_header(hline) || hline == "*** End Patch"
-                    {
-                        break;
-                    }
-                    if hline == "*** End of File" {
-                        index += 1;
-                        break;
-                    }
-
-                    let mut chars = hline.chars();
-                    let marker = chars
-                        .next()
-                        .ok_or_else(|| anyhow!("empty hunk line is invalid"))?;
-                    let tail: String = chars.collect();
-                    match marker {
-                        ' ' => hunk_lines.push(ApplyPatchHunkLine::Context(tail)),
-                        '-' => hunk_lines.push(ApplyPatchHunkLine::Remove(tail)),
-                        '+' => hunk_lines.push(ApplyPatchHunkLine::Add(tail)),
-                        _ => return Err(anyhow!("invalid hunk line prefix '{}'", marker)),
-                    }
-                    index += 1;
-                }
-
-                if hunk_lines.is_empty() {
-                    return Err(anyhow!("empty hunk is invalid"));
-                }
-                hunks.push(ApplyPatchHunk { lines: hunk_lines });
-            }
-
-            if hunks.is_empty() {
-                return Err(anyhow!("update operation for '{}' has no hunks", path));
+    let base = match &source {
+        Some(source) => match read_to_string(source).await {
+            Ok(base) => base,
+            Err(error) => {
+                let message = format!(
+                    "failed to read {}: {}",
+                    file.old_path.as_deref().unwrap_or_default(),
+                    error
+                );
+                return Ok(fail(report, message));
             }
-
-            ops.push(ApplyPatchOp::Update {
-                path: path.to_string(),
-                move_to,
-                hunks,
-            });
-            continue;
+        },
+        None => String::new(),
+    };
+    if let Some(target) = target
+        .as_ref()
+        .filter(|target| Some(*target) != source.as_ref())
+    {
+        if metadata(target).await.is_ok() {
+            return Ok(fail(report, format!("'{}' already exists", path)));
         }
-
-        return Err(anyhow!("unknown patch section header: {}", line));
     }
 
-    Err(anyhow!("missing *** End Patch"))
+    let patched = if file.diff.is_empty() {
+        base
+    } else {
+        let patch = match Patch::from_str(&file.diff) {
+            Ok(patch) => patch,
+            Err(error) => return Ok(fail(report, format!("failed to parse hunks: {}", error))),
+        };
+        let (patched, checks) = check_unified_hunks(&base, &patch, fuzz);
+        report["hunks"] = json!(hunk_checks_to_json(&checks));
+        if checks.iter().any(|check| check.is_err()) {
+            return Ok(fail(report, "hunks failed to apply".to_string()));
+        }
+        patched
+    };
+    Ok(StagedGitFile {
+        report,
+        source: source.filter(|source| Some(source) != target.as_ref()),
+        target: target.map(|target| (target, patched)),
+    })
 }
 
-fn expect_line(lines: &[&str], index: usize, expected: &str) -> Result<()> {
-    let found = lines
-        .get(index)
-        .copied()
-        .ok_or_else(|| anyhow!("patch ended early; expected {}", expected))?;
-    if found != expected {
-        return Err(anyhow!("expected '{}', got '{}'", expected, found));
+/// Applies every file section of a `git diff`, or none of them: each one is
+/// checked against the current files before anything is written.
+async fn apply_git_diff(
+    workspace: &Workspace,
+    files: &[GitFilePatch],
+    fuzz: usize,
+    dry_run: bool,
+) -> Result<Value> {
+    let mut staged = Vec::with_capacity(files.len());
+    for file in files {
+        staged.push(stage_git_file(workspace, file, fuzz).await?);
     }
-    Ok(())
-}
-
-fn is_patch_header(line: &str) -> bool {
-    line.starts_with("*** Add File: ")
-        || line.starts_with("*** Delete File: ")
-        || line.starts_with("*** Update File: ")
-}
-
-fn validate_relative_path(path: &str) -> Result<()> {
-    if path.trim().is_empty() {
-        return Err(anyhow!("path cannot be empty"));
+    let reports: Vec<&Value> = staged.iter().map(|file| &file.report).collect();
+    let would_apply = reports.iter().all(|report| report["ok"] == true);
+    if dry_run {
+        return Ok(json!({ "dry_run": true, "would_apply": would_apply, "files": reports }));
     }
-
-    let parsed = Path::new(path);
-    if parsed.is_absolute() {
-        return Err(anyhow!("path must be relative: {}", path));
+    if !would_apply {
+        return Ok(json!({
+            "patched": false,
+            "error": "patch_failed",
+            "details": "no files were changed",
+            "files": reports
+        }));
     }
 
-    for component in parsed.components() {
-        match component {
-            Component::CurDir | Component::Normal(_) => {}
-            Component::ParentDir => return Err(anyhow!("parent path '..' is not allowed: {}", path)),
-            _ => return Err(anyhow!("invalid path component in {}", path)),
+    let mut files_changed = Vec::new();
+    for file in &staged {
+        if let Some((target, contents)) = &file.target {
+            if let Some(parent) = target.parent() {
+                create_dir_all(parent).await.with_context(|| {
+                    format!(
+                        "failed to create parent directories for {}",
+                        target.display()
+                    )
+                })?;
+            }
+            write_atomically(target, contents.as_bytes())
+                .await
+                .with_context(|| format!("failed to write {}", target.display()))?;
         }
+        if let Some(source) = &file.source {
+            remove_file(source)
+                .await
+                .with_context(|| format!("failed to remove {}", source.display()))?;
+        }
+        files_changed.extend(file.report.get("from").cloned());
+        files_changed.push(file.report["path"].clone());
     }
-    Ok(())
+    Ok(json!({ "patched": true, "files_changed": files_changed, "files": reports }))
+}
+
+/// Checks each operation against the current files without writing.
+/// Operations are checked independently of one another.
+async fn check_patch_ops(workspace: &Workspace, ops: &[FileOp], fuzz: usize) -> Result<Value> {
+    let mut files = Vec::with_capacity(ops.len());
+    for op in ops {
+        let file = match op {
+            FileOp::Add { path, .. } => {
+                let exists = metadata(workspace.resolve(path)?).await.is_ok();
+                let mut file = json!({ "path": path, "op": "add", "ok": !exists });
+                if exists {
+                    file["error"] = json!(format!("'{}' already exists", path));
+                }
+                file
+            }
+            FileOp::Delete { path } => {
+                let exists = metadata(workspace.resolve(path)?).await.is_ok();
+                let mut file = json!({ "path": path, "op": "delete", "ok": exists });
+                if !exists {
+                    file["error"] = json!(format!("'{}' does not exist", path));
+                }
+                file
+            }
+            FileOp::Update {
+                path,
+                move_to,
+                hunks,
+            } => match read_to_string(workspace.resolve(path)?).await {
+                Ok(original) => {
+                    let (_, checks) = check_hunks(&original, hunks, fuzz);
+                    json!({
+                        "path": path,
+                        "op": "update",
+                        "move_to": move_to,
+                        "ok": checks.iter().all(|check| check.is_ok()),
+                        "hunks": hunk_checks_to_json(&checks)
+                    })
+                }
+                Err(error) => json!({
+                    "path": path,
+                    "op": "update",
+                    "ok": false,
+                    "error": format!("failed to read {}: {}", path, error)
+                }),
+            },
+        };
+        files.push(file);
+    }
+    Ok(json!({
+        "dry_run": true,
+        "would_apply": files.iter().all(|file| file["ok"] == true),
+        "files": files
+    }))
 }
 
-async fn apply_patch_ops(ops: &[ApplyPatchOp]) -> Result<ApplyPatchSummary> {
+async fn apply_patch_ops(
+    workspace: &Workspace,
+    ops: &[FileOp],
+    fuzz: usize,
+) -> Result<ApplyPatchSummary> {
     let mut summary = ApplyPatchSummary::default();
 
     for op in ops {
         match op {
-            ApplyPatchOp::Add { path, lines } => {
-                let target = PathBuf::from(path);
+            FileOp::Add { path, lines } => {
+                let target = workspace.resolve(path)?;
                 if metadata(&target).await.is_ok() {
                     return Err(anyhow!("add file failed: '{}' already exists", path));
                 }
                 if let Some(parent) = target.parent() {
-                    create_dir_all(parent)
-                        .await
-                        .with_context(|| format!("failed to create parent directories for {}", path))?;
+                    create_dir_all(parent).await.with_context(|| {
+                        format!("failed to create parent directories for {}", path)
+                    })?;
                 }
-                write_atomically(&target, normalize_patch_lines(lines).as_bytes())
+                write_atomically(&target, normalize_lines(lines).as_bytes())
                     .await
                     .with_context(|| format!("failed to write {}", path))?;
                 summary.added_files += 1;
             }
-            ApplyPatchOp::Delete { path } => {
-                let target = PathBuf::from(path);
+            FileOp::Delete { path } => {
+                let target = workspace.resolve(path)?;
                 if metadata(&target).await.is_err() {
                     return Err(anyhow!("delete file failed: '{}' does not exist", path));
                 }
@@ -1130,22 +1366,26 @@ async fn apply_patch_ops(ops: &[ApplyPatchOp]) -> Result<ApplyPatchSummary> {
                     .with_context(|| format!("failed to delete {}", path))?;
                 summary.deleted_files += 1;
             }
-            ApplyPatchOp::Update {
+            FileOp::Update {
                 path,
                 move_to,
                 hunks,
             } => {
-                let source_path = PathBuf::from(path);
+                let source_path = workspace.resolve(path)?;
                 let original = read_to_string(&source_path)
                     .await
                     .with_context(|| format!("failed to read {}", path))?;
-                let updated = apply_patch_hunks(&original, hunks)
+                let (updated, reports) = apply_hunks(&original, hunks, fuzz)
                     .with_context(|| format!("failed to patch {}", path))?;
+                summary.hunks.push(json!({
+                    "path": move_to.as_deref().unwrap_or(path),
+                    "hunks": hunk_reports_to_json(&reports)
+                }));
 
-                let dest_path = move_to
-                    .as_ref()
-                    .map(PathBuf::from)
-                    .unwrap_or_else(|| source_path.clone());
+                let dest_path = match move_to {
+                    Some(target) => workspace.resolve(target)?,
+                    None => source_path.clone(),
+                };
 
                 if let Some(parent) = dest_path.parent() {
                     create_dir_all(parent).await.with_context(|| {
@@ -1175,76 +1415,3 @@ async fn apply_patch_ops(ops: &[ApplyPatchOp]) -> Result<ApplyPatchSummary> {
 
     Ok(summary)
 }
-
-fn apply_patch_hunks(original: &str, hunks: &[ApplyPatchHunk]) -> Result<String> {
-    let mut lines: Vec<String> = original
-        .lines()
-        .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
-        .collect();
-    let mut cursor = 0usize;
-
-    for hunk in hunks {
-        let expected_old: Vec<&str> = hunk
-            .lines
-            .iter()
-            .filter_map(|line| match line {
-                ApplyPatchHunkLine::Context(text) | ApplyPatchHunkLine::Remove(text) => {
-                    Some(text.as_str())
-                }
-                ApplyPatchHunkLine::Add(_) => None,
-            })
-            .collect();
-
-        let replacement: Vec<String> = hunk
-            .lines
-            .iter()
-            .filter_map(|line| match line {
-                ApplyPatchHunkLine::Context(text) | ApplyPatchHunkLine::Add(text) => {
-                    Some(text.clone())
-                }
-                ApplyPatchHunkLine::Remove(_) => None,
-            })
-            .collect();
-
-        let match_pos = find_hunk_match(&lines, &expected_old, cursor)
-            .or_else(|| find_hunk_match(&lines, &expected_old, 0))
-            .ok_or_else(|| anyhow!("could not locate hunk context in target file"))?;
-
-        let old_len = expected_old.len();
-        lines.splice(match_pos..(match_pos + old_len), replacement.clone());
-        cursor = match_pos + replacement.len();
-    }
-
-    let mut output = lines.join("\n");
-    if original.ends_with('\n') {
-        output.push('\n');
-    }
-    Ok(output)
-}
-
-fn find_hunk_match(lines: &[String], expected_old: &[&str], start: usize) -> Option<usize> {
-    if expected_old.is_empty() {
-        return Some(start.min(lines.len()));
-    }
-    if expected_old.len() > lines.len() || start > lines.len() {
-        return None;
-    }
-
-    let end = lines.len() - expected_old.len();
-    for idx in start..=end {
-        let window = &lines[idx..idx + expected_old.len()];
-        if window.iter().zip(expected_old.iter()).all(|(a, b)| a == b) {
-            return Some(idx);
-        }
-    }
-    None
-}
-
-fn normalize_patch_lines(lines: &[String]) -> String {
-    if lines.is_empty() {
-        return String::new();
-    }
-    let mut out = lines.join("\n");
-    out.push('\n');
-    out
-}
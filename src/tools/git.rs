@@ -0,0 +1,706 @@
+use crate::tool::Tool;
+use crate::tools::workspace::Workspace;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::process::Command;
+
+pub const GIT_TOOLS: [&str; 5] = [
+    "git_status",
+    "git_diff",
+    "git_log",
+    "git_commit",
+    "git_branch",
+];
+
+const DEFAULT_LOG_COUNT: u64 = 20;
+/// Diff lines returned before the rest is cut off.
+const MAX_DIFF_LINES: usize = 2_000;
+
+/// Whether `root` is inside a git work tree.
+pub fn is_git_repo(root: &Path) -> bool {
+    root.ancestors().any(|dir| dir.join(".git").exists())
+}
+
+/// Runs git in the workspace root and returns stdout, or an error carrying
+/// git's stderr.
+async fn git(workspace: &Workspace, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(workspace.root())
+        .args(args)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("failed to run git")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Workspace-checked paths from a string or array argument.
+fn paths_argument(workspace: &Workspace, input: &Value, key: &str) -> Result<Vec<String>> {
+    let paths: Vec<&str> = match input.get(key) {
+        Some(Value::String(path)) => vec![path.as_str()],
+        Some(Value::Array(paths)) => paths.iter().filter_map(|path| path.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    paths
+        .into_iter()
+        .map(|path| Ok(workspace.resolve(path)?.to_string_lossy().into_owned()))
+        .collect()
+}
+
+pub struct GitStatusTool {
+    workspace: Arc<Workspace>,
+}
+
+impl GitStatusTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for GitStatusTool {
+    fn name(&self) -> &'static str {
+        "git_status"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show the current branch, upstream with ahead/behind counts, and changed files with their staged and unstaged state."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Repository status.",
+            "properties": {},
+            "examples": [{}]
+        })
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
+    async fn execute(&self, _input: Value) -> Result<Value> {
+        let output = git(
+            &self.workspace,
+            &["status", "--porcelain=v2", "--branch", "-z"],
+        )
+        .await?;
+        Ok(parse_status(&output))
+    }
+}
+
+/// Parses `git status --porcelain=v2 --branch -z`.
+fn parse_status(output: &str) -> Value {
+    let mut branch = Value::Null;
+    let mut upstream = Value::Null;
+    let (mut ahead, mut behind) = (0i64, 0i64);
+    let mut files = Vec::new();
+    let mut untracked = Vec::new();
+    let mut entries = output.split('\0').filter(|entry| !entry.is_empty());
+    let code = |xy: &str, index: usize| -> Value {
+        match xy.chars().nth(index) {
+            Some('.') | None => Value::Null,
+            Some(code) => json!(status_name(code)),
+        }
+    };
+    while let Some(entry) = entries.next() {
+        let fields: Vec<&str> = entry.split(' ').collect();
+        match fields[0] {
+            "#" => match (fields.get(1), fields.get(2)) {
+                (Some(&"branch.head"), Some(head)) if *head != "(detached)" => branch = json!(head),
+                (Some(&"branch.upstream"), Some(name)) => upstream = json!(name),
+                (Some(&"branch.ab"), Some(a)) => {
+                    ahead = a.trim_start_matches('+').parse().unwrap_or(0);
+                    behind = fields
+                        .get(3)
+                        .and_then(|b| b.trim_start_matches('-').parse().ok())
+                        .unwrap_or(0);
+                }
+                _ => {}
+            },
+            "1" if fields.len() >= 9 => files.push(json!({
+                "path": fields[8..].join(" "),
+                "staged": code(fields[1], 0),
+                "unstaged": code(fields[1], 1),
+            })),
+            // Renames are followed by the original path as its own entry.
+            "2" if fields.len() >= 10 => files.push(json!({
+                "path": fields[9..].join(" "),
+                "staged": code(fields[1], 0),
+                "unstaged": code(fields[1], 1),
+                "original_path": entries.next(),
+            })),
+            "u" if fields.len() >= 11 => files.push(json!({
+                "path": fields[10..].join(" "),
+                "staged": "unmerged",
+                "unstaged": "unmerged",
+            })),
+            "?" => untracked.push(entry[2..].to_string()),
+            _ => {}
+        }
+    }
+    json!({
+        "branch": branch,
+        "upstream": upstream,
+        "ahead": ahead,
+        "behind": behind,
+        "clean": files.is_empty() && untracked.is_empty(),
+        "files": files,
+        "untracked": untracked,
+    })
+}
+
+fn status_name(code: char) -> &'static str {
+    match code {
+        'M' => "modified",
+        'T' => "type_changed",
+        'A' => "added",
+        'D' => "deleted",
+        'R' => "renamed",
+        'C' => "copied",
+        'U' => "unmerged",
+        _ => "unknown",
+    }
+}
+
+pub struct GitDiffTool {
+    workspace: Arc<Workspace>,
+}
+
+impl GitDiffTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for GitDiffTool {
+    fn name(&self) -> &'static str {
+        "git_diff"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show changes as structured files and hunks: unstaged by default, staged with staged=true, or against a commit with base."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Structured diff.",
+            "properties": {
+                "staged": {
+                    "type": "boolean",
+                    "description": "Diff the index against HEAD instead of the work tree against the index."
+                },
+                "base": {
+                    "type": "string",
+                    "description": "Commit, branch, or range to diff against, e.g. 'HEAD~1' or 'main...HEAD'."
+                },
+                "paths": {
+                    "type": ["array", "string"],
+                    "items": { "type": "string" },
+                    "description": "Limit the diff to these paths."
+                }
+            },
+            "examples": [
+                {},
+                { "staged": true },
+                { "base": "main...HEAD", "paths": ["src"] }
+            ]
+        })
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let mut args = vec!["diff".to_string(), "--no-color".to_string()];
+        if input
+            .get("staged")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+        {
+            args.push("--cached".to_string());
+        }
+        if let Some(base) = input.get("base").and_then(|v| v.as_str()) {
+            if base.starts_with('-') {
+                return Err(anyhow!("invalid base '{}'", base));
+            }
+            args.push(base.to_string());
+        }
+        args.push("--".to_string());
+        args.extend(paths_argument(&self.workspace, &input, "paths")?);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let output = git(&self.workspace, &args).await?;
+        Ok(parse_diff(&output))
+    }
+}
+
+/// Parses unified `git diff` output into files with hunks.
+fn parse_diff(output: &str) -> Value {
+    let mut files: Vec<Value> = Vec::new();
+    let mut lines_returned = 0;
+    let mut truncated = false;
+    for line in output.lines() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let path = rest.rsplit_once(" b/").map_or(rest, |(_, path)| path);
+            files.push(json!({
+                "path": path,
+                "status": "modified",
+                "additions": 0,
+                "deletions": 0,
+                "hunks": [],
+            }));
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+        if line.starts_with("new file mode") {
+            file["status"] = json!("added");
+        } else if line.starts_with("deleted file mode") {
+            file["status"] = json!("deleted");
+        } else if let Some(from) = line.strip_prefix("rename from ") {
+            file["status"] = json!("renamed");
+            file["original_path"] = json!(from);
+        } else if line.starts_with("Binary files ") {
+            file["binary"] = json!(true);
+        } else if line.starts_with("@@") {
+            let (old_start, old_lines, new_start, new_lines) = parse_hunk_header(line);
+            file["hunks"]
+                .as_array_mut()
+                .expect("hunks array")
+                .push(json!({
+                    "header": line,
+                    "old_start": old_start,
+                    "old_lines": old_lines,
+                    "new_start": new_start,
+                    "new_lines": new_lines,
+                    "lines": [],
+                }));
+        } else if file["hunks"]
+            .as_array()
+            .is_some_and(|hunks| !hunks.is_empty())
+        {
+            let counter = match line.chars().next() {
+                Some('+') => Some("additions"),
+                Some('-') => Some("deletions"),
+                _ => None,
+            };
+            if let Some(counter) = counter {
+                file[counter] = json!(file[counter].as_u64().unwrap_or(0) + 1);
+            }
+            if lines_returned == MAX_DIFF_LINES {
+                truncated = true;
+                continue;
+            }
+            lines_returned += 1;
+            if let Some(lines) = file["hunks"]
+                .as_array_mut()
+                .and_then(|hunks| hunks.last_mut())
+                .and_then(|hunk| hunk["lines"].as_array_mut())
+            {
+                lines.push(json!(line));
+            }
+        }
+    }
+    json!({ "files": files, "truncated": truncated })
+}
+
+/// `@@ -a,b +c,d @@` into `(a, b, c, d)`; omitted counts are 1.
+fn parse_hunk_header(header: &str) -> (u64, u64, u64, u64) {
+    let mut ranges = header.split_whitespace().skip(1).take(2).map(|range| {
+        let range = range.trim_start_matches(['-', '+']);
+        let (start, count) = range.split_once(',').unwrap_or((range, "1"));
+        (start.parse().unwrap_or(0), count.parse().unwrap_or(1))
+    });
+    let (old_start, old_lines) = ranges.next().unwrap_or((0, 0));
+    let (new_start, new_lines) = ranges.next().unwrap_or((0, 0));
+    (old_start, old_lines, new_start, new_lines)
+}
+
+pub struct GitLogTool {
+    workspace: Arc<Workspace>,
+}
+
+impl GitLogTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for GitLogTool {
+    fn name(&self) -> &'static str {
+        "git_log"
+    }
+
+    fn description(&self) -> &'static str {
+        "List recent commits with hash, author, date, and subject, optionally for a revision or paths."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Commit history.",
+            "properties": {
+                "max_count": {
+                    "type": "integer",
+                    "description": "Maximum commits returned. Defaults to 20."
+                },
+                "revision": {
+                    "type": "string",
+                    "description": "Branch, commit, or range, e.g. 'main..HEAD'. Defaults to HEAD."
+                },
+                "paths": {
+                    "type": ["array", "string"],
+                    "items": { "type": "string" },
+                    "description": "Only commits touching these paths."
+                }
+            },
+            "examples": [
+                {},
+                { "max_count": 5, "paths": ["src/kernel.rs"] }
+            ]
+        })
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let max_count = input
+            .get("max_count")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(DEFAULT_LOG_COUNT)
+            .max(1);
+        let mut args = vec![
+            "log".to_string(),
+            format!("--max-count={}", max_count),
+            "--format=%H%x1f%an%x1f%ae%x1f%aI%x1f%s%x1e".to_string(),
+        ];
+        if let Some(revision) = input.get("revision").and_then(|v| v.as_str()) {
+            if revision.starts_with('-') {
+                return Err(anyhow!("invalid revision '{}'", revision));
+            }
+            args.push(revision.to_string());
+        }
+        args.push("--".to_string());
+        args.extend(paths_argument(&self.workspace, &input, "paths")?);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let output = git(&self.workspace, &args).await?;
+        let commits: Vec<Value> = output
+            .split('\x1e')
+            .filter_map(|record| {
+                let fields: Vec<&str> = record.trim().split('\x1f').collect();
+                (fields.len() == 5).then(|| {
+                    json!({
+                        "hash": fields[0],
+                        "author": fields[1],
+                        "email": fields[2],
+                        "date": fields[3],
+                        "subject": fields[4],
+                    })
+                })
+            })
+            .collect();
+        Ok(json!({ "commits": commits }))
+    }
+}
+
+pub struct GitCommitTool {
+    workspace: Arc<Workspace>,
+}
+
+impl GitCommitTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for GitCommitTool {
+    fn name(&self) -> &'static str {
+        "git_commit"
+    }
+
+    fn description(&self) -> &'static str {
+        "Stage the given paths (or all changes with all=true) and commit them with a message. Returns the new commit hash."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Create a commit.",
+            "properties": {
+                "message": {
+                    "type": "string",
+                    "description": "Commit message; the first line is the subject."
+                },
+                "paths": {
+                    "type": ["array", "string"],
+                    "items": { "type": "string" },
+                    "description": "Paths to stage before committing."
+                },
+                "all": {
+                    "type": "boolean",
+                    "description": "Stage every change, including untracked files."
+                }
+            },
+            "required": ["message"],
+            "examples": [
+                { "message": "Add retry to HTTP client", "paths": ["src/client.rs"] },
+                { "message": "Update docs", "all": true }
+            ]
+        })
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let message = input
+            .get("message")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|message| !message.is_empty())
+            .ok_or_else(|| anyhow!("missing message"))?;
+        let paths = paths_argument(&self.workspace, &input, "paths")?;
+        if input.get("all").and_then(|v| v.as_bool()).unwrap_or(false) {
+            git(&self.workspace, &["add", "--all"]).await?;
+        } else if !paths.is_empty() {
+            let mut args = vec!["add", "--"];
+            args.extend(paths.iter().map(String::as_str));
+            git(&self.workspace, &args).await?;
+        }
+        let staged = git(&self.workspace, &["diff", "--cached", "--name-only"]).await?;
+        let files: Vec<&str> = staged.lines().collect();
+        if files.is_empty() {
+            return Ok(json!({
+                "error": "nothing_to_commit",
+                "details": "No staged changes; pass paths or all=true."
+            }));
+        }
+        git(&self.workspace, &["commit", "--quiet", "-m", message]).await?;
+        let hash = git(&self.workspace, &["rev-parse", "HEAD"]).await?;
+        Ok(json!({
+            "commit": hash.trim(),
+            "subject": message.lines().next().unwrap_or_default(),
+            "files": files,
+        }))
+    }
+}
+
+pub struct GitBranchTool {
+    workspace: Arc<Workspace>,
+}
+
+impl GitBranchTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for GitBranchTool {
+    fn name(&self) -> &'static str {
+        "git_branch"
+    }
+
+    fn description(&self) -> &'static str {
+        "List local branches with upstream and ahead/behind counts, create a branch, or switch to one."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Branch management.",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["list", "create", "switch"],
+                    "description": "Defaults to list."
+                },
+                "name": {
+                    "type": "string",
+                    "description": "Branch to create or switch to."
+                },
+                "start_point": {
+                    "type": "string",
+                    "description": "Commit the new branch starts from. Defaults to HEAD."
+                },
+                "switch": {
+                    "type": "boolean",
+                    "description": "With create, also switch to the new branch."
+                }
+            },
+            "examples": [
+                {},
+                { "action": "create", "name": "fix-retry", "switch": true },
+                { "action": "switch", "name": "main" }
+            ]
+        })
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let name = || {
+            input
+                .get("name")
+                .and_then(|v| v.as_str())
+                .filter(|name| !name.is_empty() && !name.starts_with('-'))
+                .ok_or_else(|| anyhow!("missing or invalid name"))
+        };
+        match input
+            .get("action")
+            .and_then(|v| v.as_str())
+            .unwrap_or("list")
+        {
+            "list" => {
+                let output = git(
+                    &self.workspace,
+                    &[
+                        "branch",
+                        "--format=%(refname:short)%1f%(HEAD)%1f%(upstream:short)%1f%(upstream:track,nobracket)",
+                    ],
+                )
+                .await?;
+                let branches: Vec<Value> = output.lines().map(parse_branch).collect();
+                Ok(json!({ "branches": branches }))
+            }
+            "create" => {
+                let name = name()?;
+                let mut args = vec!["branch", name];
+                if let Some(start) = input.get("start_point").and_then(|v| v.as_str()) {
+                    args.push(start);
+                }
+                git(&self.workspace, &args).await?;
+                let switched = input.get("switch").and_then(|v| v.as_bool()) == Some(true);
+                if switched {
+                    git(&self.workspace, &["switch", "--quiet", name]).await?;
+                }
+                Ok(json!({ "created": name, "switched": switched }))
+            }
+            "switch" => {
+                let name = name()?;
+                git(&self.workspace, &["switch", "--quiet", name]).await?;
+                Ok(json!({ "switched": name }))
+            }
+            other => Err(anyhow!("unknown action '{}'", other)),
+        }
+    }
+}
+
+fn parse_branch(line: &str) -> Value {
+    let fields: Vec<&str> = line.split('\x1f').collect();
+    let field = |index: usize| fields.get(index).copied().unwrap_or_default();
+    let count = |label: &str| -> i64 {
+        field(3)
+            .split(", ")
+            .find_map(|part| part.strip_prefix(label))
+            .and_then(|n| n.trim().parse().ok())
+            .unwrap_or(0)
+    };
+    json!({
+        "name": field(0),
+        "current": field(1) == "*",
+        "upstream": Some(field(2)).filter(|upstream| !upstream.is_empty()),
+        "ahead": count("ahead "),
+        "behind": count("behind "),
+        "gone": field(3) == "gone",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_diff, GitCommitTool, GitLogTool, GitStatusTool};
+    use crate::tool::Tool;
+    use crate::tools::workspace::Workspace;
+    use serde_json::json;
+    use std::process::Command;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[tokio::test]
+    async fn status_commit_and_log_return_structured_results() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("rx-git-test-{}", nanos));
+        std::fs::create_dir_all(&root).unwrap();
+        let root = root.canonicalize().unwrap();
+        for args in [
+            &["init", "--quiet", "--initial-branch=main"][..],
+            &["config", "user.name", "rx test"],
+            &["config", "user.email", "rx@example.com"],
+        ] {
+            Command::new("git")
+                .arg("-C")
+                .arg(&root)
+                .args(args)
+                .status()
+                .unwrap();
+        }
+        std::fs::write(root.join("a.txt"), "one\n").unwrap();
+        let workspace = Arc::new(Workspace::new(&root, &root, &[]).unwrap());
+
+        let status = GitStatusTool::new(Arc::clone(&workspace))
+            .execute(json!({}))
+            .await
+            .unwrap();
+        assert_eq!(status["branch"], "main");
+        assert_eq!(status["untracked"], json!(["a.txt"]));
+
+        let commit = GitCommitTool::new(Arc::clone(&workspace));
+        let empty = commit.execute(json!({ "message": "empty" })).await.unwrap();
+        assert_eq!(empty["error"], "nothing_to_commit");
+        let committed = commit
+            .execute(json!({ "message": "Add a", "paths": ["a.txt"] }))
+            .await
+            .unwrap();
+        assert_eq!(committed["files"], json!(["a.txt"]));
+
+        let log = GitLogTool::new(Arc::clone(&workspace))
+            .execute(json!({}))
+            .await
+            .unwrap();
+        assert_eq!(log["commits"][0]["hash"], committed["commit"]);
+        assert_eq!(log["commits"][0]["subject"], "Add a");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn diff_is_split_into_files_and_hunks() {
+        let diff = "diff --git a/src/a.rs b/src/a.rs\nindex 1..2 100644\n--- a/src/a.rs\n+++ b/src/a.rs\n@@ -1,2 +1,2 @@ fn a()\n-old\n+new\n same\ndiff --git a/b.txt b/b.txt\nnew file mode 100644\n--- /dev/null\n+++ b/b.txt\n@@ -0,0 +1 @@\n+hello\n";
+        let parsed = parse_diff(diff);
+        let files = parsed["files"].as_array().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0]["path"], "src/a.rs");
+        assert_eq!(
+            (
+                files[0]["additions"].as_u64(),
+                files[0]["deletions"].as_u64()
+            ),
+            (Some(1), Some(1))
+        );
+        assert_eq!(
+            files[0]["hunks"][0]["lines"],
+            json!(["-old", "+new", " same"])
+        );
+        assert_eq!(files[1]["status"], "added");
+        assert_eq!(files[1]["hunks"][0]["new_start"], 1);
+        assert_eq!(files[1]["hunks"][0]["old_lines"], 0);
+    }
+}
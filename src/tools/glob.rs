@@ -0,0 +1,48 @@
+/// Glob match over `/`-separated paths: `*` and `?` stay within one
+/// segment, and a `**` segment matches any number of segments.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let text: Vec<&str> = text.split('/').collect();
+    match_segments(&pattern, &text)
+}
+
+fn match_segments(pattern: &[&str], text: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((&"**", rest)) => (0..=text.len()).any(|skip| match_segments(rest, &text[skip..])),
+        Some((first, rest)) => match text.split_first() {
+            Some((segment, text_rest)) => {
+                match_segment(first.as_bytes(), segment.as_bytes())
+                    && match_segments(rest, text_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+fn match_segment(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| match_segment(rest, &text[skip..])),
+        Some((b'?', rest)) => !text.is_empty() && match_segment(rest, &text[1..]),
+        Some((byte, rest)) => text.first() == Some(byte) && match_segment(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    #[test]
+    fn glob_supports_single_and_multi_segment_wildcards() {
+        assert!(glob_match("*.rs", "main.rs"));
+        assert!(!glob_match("*.rs", "src/main.rs"));
+        assert!(glob_match("src/**/mod.rs", "src/tools/mod.rs"));
+        assert!(glob_match("src/**/mod.rs", "src/mod.rs"));
+        assert!(glob_match("file?.txt", "file1.txt"));
+        assert!(!glob_match("file?.txt", "file10.txt"));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("*-svc*", "billing-svc-v2"));
+        assert!(!glob_match("pkg-*", "lib-api"));
+    }
+}
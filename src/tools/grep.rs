@@ -0,0 +1,299 @@
+use crate::tool::Tool;
+use crate::tools::search::{path_glob_matches, walk_files};
+use crate::tools::workspace::Workspace;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use regex::{Regex, RegexBuilder};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::Arc;
+
+const DEFAULT_MAX_MATCHES: usize = 100;
+/// Upper bound on files considered in one search.
+pub const MAX_FILES: usize = 20_000;
+/// Files are read in parallel, this many at a time.
+const READ_BATCH: usize = 64;
+/// Larger files are skipped; they are almost always generated or data.
+pub const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+pub const MAX_LINE_CHARS: usize = 300;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Match {
+    path: String,
+    line: usize,
+    text: String,
+}
+
+pub struct GrepFilesTool {
+    workspace: Arc<Workspace>,
+}
+
+impl GrepFilesTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for GrepFilesTool {
+    fn name(&self) -> &'static str {
+        "grep_files"
+    }
+
+    fn description(&self) -> &'static str {
+        "Search file contents below a directory for a regex or literal string and return matching lines with paths and line numbers. Honors the same ignore rules as find_files; page through results with cursor."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Recursive content search.",
+            "properties": {
+                "pattern": {
+                    "type": "string",
+                    "description": "Regular expression (Rust syntax), or a plain string when literal is true."
+                },
+                "literal": {
+                    "type": "boolean",
+                    "description": "Treat pattern as a plain string. Defaults to false."
+                },
+                "case_insensitive": {
+                    "type": "boolean",
+                    "description": "Ignore case. Defaults to false."
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Directory to search. Defaults to current directory."
+                },
+                "include": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Only search files matching one of these globs, e.g. ['*.rs']."
+                },
+                "exclude": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Skip files matching any of these globs."
+                },
+                "max_matches": {
+                    "type": "integer",
+                    "description": "Maximum matching lines returned. Defaults to 100."
+                },
+                "cursor": {
+                    "type": "integer",
+                    "description": "Number of matches to skip; pass next_cursor from the previous page."
+                },
+                "include_ignored": {
+                    "type": "boolean",
+                    "description": "Also search paths hidden by the [search] ignore rules."
+                }
+            },
+            "required": ["pattern"],
+            "examples": [
+                { "pattern": "fn main", "literal": true },
+                { "pattern": "TODO|FIXME", "include": ["*.rs"], "path": "src" },
+                { "pattern": "timeout", "case_insensitive": true, "cursor": 100 }
+            ]
+        })
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let pattern = input
+            .get("pattern")
+            .and_then(|v| v.as_str())
+            .filter(|pattern| !pattern.is_empty())
+            .ok_or_else(|| anyhow!("missing pattern"))?;
+        let flag = |key: &str| input.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+        let source = if flag("literal") {
+            regex::escape(pattern)
+        } else {
+            pattern.to_string()
+        };
+        let regex = RegexBuilder::new(&source)
+            .case_insensitive(flag("case_insensitive"))
+            .build()
+            .with_context(|| format!("invalid pattern '{}'", pattern))?;
+        let globs = |key: &str| -> Vec<String> {
+            match input.get(key) {
+                Some(Value::String(glob)) => vec![glob.clone()],
+                Some(Value::Array(globs)) => globs
+                    .iter()
+                    .filter_map(|glob| glob.as_str().map(str::to_string))
+                    .collect(),
+                _ => Vec::new(),
+            }
+        };
+        let (include, exclude) = (globs("include"), globs("exclude"));
+        let number = |key: &str| input.get(key).and_then(|v| v.as_u64()).map(|n| n as usize);
+        let max_matches = number("max_matches").unwrap_or(DEFAULT_MAX_MATCHES).max(1);
+        let cursor = number("cursor").unwrap_or(0);
+        let include_ignored = flag("include_ignored");
+
+        let dir = self
+            .workspace
+            .resolve(input.get("path").and_then(|v| v.as_str()).unwrap_or("."))?;
+        let workspace = Arc::clone(&self.workspace);
+        let (matches, files_searched, more, files_truncated) =
+            tokio::task::spawn_blocking(move || {
+                let walk = walk_files(&workspace, &dir, include_ignored, MAX_FILES, |relative| {
+                    (include.is_empty()
+                        || include.iter().any(|glob| path_glob_matches(glob, relative)))
+                        && !exclude.iter().any(|glob| path_glob_matches(glob, relative))
+                });
+                let (matches, searched, more) =
+                    search_files(&dir, &walk.files, cursor + max_matches, |path, relative| {
+                        search_file(path, relative, &regex)
+                    });
+                (matches, searched, more, walk.truncated)
+            })
+            .await
+            .context("grep_files task failed")?;
+
+        let page: Vec<Value> = matches
+            .into_iter()
+            .skip(cursor)
+            .map(|found| json!({ "path": found.path, "line": found.line, "text": found.text }))
+            .collect();
+        Ok(json!({
+            "matches": page,
+            "next_cursor": more.then_some(cursor + max_matches),
+            "files_searched": files_searched,
+            "files_truncated": files_truncated
+        }))
+    }
+}
+
+/// Runs `per_file` over `files` in parallel batches and returns up to
+/// `limit` results in file order, the number of files read, and whether more
+/// results exist.
+pub fn search_files<T: Send>(
+    dir: &Path,
+    files: &[String],
+    limit: usize,
+    per_file: impl Fn(&Path, &str) -> Vec<T> + Sync,
+) -> (Vec<T>, usize, bool) {
+    let workers = std::thread::available_parallelism().map_or(4, |n| n.get().min(8));
+    let per_file = &per_file;
+    let mut matches = Vec::new();
+    let mut searched = 0;
+    for batch in files.chunks(READ_BATCH) {
+        let per_worker = batch.len().div_ceil(workers);
+        let results: Vec<Vec<T>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = batch
+                .chunks(per_worker)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|relative| per_file(&dir.join(relative), relative))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap_or_default())
+                .collect()
+        });
+        for found in results {
+            searched += 1;
+            matches.extend(found);
+            if matches.len() > limit {
+                matches.truncate(limit);
+                return (matches, searched, true);
+            }
+        }
+    }
+    (matches, searched, false)
+}
+
+/// File contents for searching, or `None` for large or binary files.
+pub fn read_searchable(path: &Path) -> Option<String> {
+    if std::fs::metadata(path).map_or(true, |meta| meta.len() > MAX_FILE_BYTES) {
+        return None;
+    }
+    let bytes = std::fs::read(path).ok()?;
+    if bytes.iter().take(8192).any(|byte| *byte == 0) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn search_file(path: &Path, relative: &str, regex: &Regex) -> Vec<Match> {
+    let Some(text) = read_searchable(path) else {
+        return Vec::new();
+    };
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| regex.is_match(line))
+        .map(|(index, line)| Match {
+            path: relative.to_string(),
+            line: index + 1,
+            text: line.chars().take(MAX_LINE_CHARS).collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GrepFilesTool;
+    use crate::tool::Tool;
+    use crate::tools::search::SearchIgnore;
+    use crate::tools::workspace::Workspace;
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[tokio::test]
+    async fn finds_matches_across_the_tree_and_pages_with_a_cursor() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("rx-grep-test-{}", nanos));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+        std::fs::write(
+            root.join("src/a.rs"),
+            "// TODO one\nfn a() {}\n// todo two\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("src/b.txt"), "TODO in text\n").unwrap();
+        std::fs::write(root.join("target/gen.rs"), "// TODO generated\n").unwrap();
+        let root = root.canonicalize().unwrap();
+        let workspace = Workspace::new(&root, &root, &[])
+            .unwrap()
+            .with_search_ignore(SearchIgnore::from_config(&root, None));
+        let tool = GrepFilesTool::new(Arc::new(workspace));
+
+        let first = tool
+            .execute(json!({ "pattern": "todo", "case_insensitive": true, "include": ["*.rs"], "max_matches": 1 }))
+            .await
+            .expect("search should run");
+        assert_eq!(
+            first["matches"],
+            json!([{ "path": "src/a.rs", "line": 1, "text": "// TODO one" }])
+        );
+        assert_eq!(first["next_cursor"], 1);
+
+        let second = tool
+            .execute(json!({ "pattern": "todo", "case_insensitive": true, "include": ["*.rs"], "max_matches": 1, "cursor": 1 }))
+            .await
+            .expect("search should run");
+        assert_eq!(second["matches"][0]["line"], 3);
+        assert_eq!(second["next_cursor"], json!(null));
+
+        let literal = tool
+            .execute(json!({ "pattern": "fn a()", "literal": true }))
+            .await
+            .expect("search should run");
+        assert_eq!(literal["matches"][0]["path"], "src/a.rs");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
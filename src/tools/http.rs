@@ -0,0 +1,349 @@
+use crate::tool::Tool;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{redirect, Client, Method, Url};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const DEFAULT_HTTP_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 100_000;
+const MAX_REDIRECTS: usize = 5;
+
+/// HTTP requests from the agent. When `allowed_domains` is set, only those
+/// hosts and their subdomains can be contacted, including via redirects.
+pub struct HttpRequestTool {
+    client: Client,
+    allowed_domains: Arc<Option<Vec<String>>>,
+    timeout: Duration,
+    max_response_bytes: usize,
+}
+
+impl HttpRequestTool {
+    pub fn new(allowed_domains: Option<Vec<String>>) -> Self {
+        let allowed_domains = Arc::new(allowed_domains.map(|domains| {
+            domains
+                .iter()
+                .map(|domain| domain.trim().trim_start_matches("*.").to_lowercase())
+                .filter(|domain| !domain.is_empty())
+                .collect()
+        }));
+        let redirect_domains = Arc::clone(&allowed_domains);
+        let policy = redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if domain_allowed(&redirect_domains, attempt.url()) {
+                attempt.follow()
+            } else {
+                attempt.stop()
+            }
+        });
+        Self {
+            client: Client::builder()
+                .redirect(policy)
+                .build()
+                .unwrap_or_default(),
+            allowed_domains,
+            timeout: Duration::from_secs(DEFAULT_HTTP_TIMEOUT_SECS),
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.max_response_bytes = max_response_bytes;
+        self
+    }
+}
+
+#[async_trait]
+impl Tool for HttpRequestTool {
+    fn name(&self) -> &'static str {
+        "http_request"
+    }
+
+    fn description(&self) -> &'static str {
+        "Send an HTTP GET, POST, or HEAD request and return the status, headers, and body (truncated to a byte limit). Use this instead of curl through exec."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Single HTTP request.",
+            "properties": {
+                "url": {
+                    "type": "string",
+                    "description": "Absolute http:// or https:// URL."
+                },
+                "method": {
+                    "type": "string",
+                    "enum": ["GET", "POST", "HEAD"],
+                    "description": "Defaults to GET."
+                },
+                "headers": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "description": "Request headers."
+                },
+                "body": {
+                    "type": ["string", "object", "array"],
+                    "description": "POST body. Objects and arrays are sent as JSON."
+                },
+                "timeout_secs": {
+                    "type": "integer",
+                    "description": "Request timeout. Defaults to the configured timeout (30s)."
+                },
+                "max_bytes": {
+                    "type": "integer",
+                    "description": "Maximum body bytes returned; cannot exceed the configured limit."
+                }
+            },
+            "required": ["url"],
+            "examples": [
+                { "url": "https://api.github.com/repos/rust-lang/rust" },
+                { "url": "https://httpbin.org/post", "method": "POST", "body": { "name": "rx" } },
+                { "url": "https://example.com", "method": "HEAD" }
+            ]
+        })
+    }
+
+    /// Only a GET runs without approval; a POST may change the remote side.
+    fn side_effecting(&self, input: &Value) -> bool {
+        !input
+            .get("method")
+            .and_then(|v| v.as_str())
+            .unwrap_or("GET")
+            .eq_ignore_ascii_case("GET")
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let raw_url = input
+            .get("url")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("missing url"))?;
+        let url = Url::parse(raw_url).with_context(|| format!("invalid url '{}'", raw_url))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(anyhow!("unsupported scheme '{}'", url.scheme()));
+        }
+        if !domain_allowed(&self.allowed_domains, &url) {
+            return Ok(json!({
+                "error": "domain_not_allowed",
+                "details": format!(
+                    "{} is not in [http].allowed_domains",
+                    url.host_str().unwrap_or_default()
+                )
+            }));
+        }
+        let method = match input
+            .get("method")
+            .and_then(|v| v.as_str())
+            .unwrap_or("GET")
+            .to_uppercase()
+            .as_str()
+        {
+            "GET" => Method::GET,
+            "POST" => Method::POST,
+            "HEAD" => Method::HEAD,
+            other => return Err(anyhow!("unsupported method '{}'", other)),
+        };
+        let timeout = input
+            .get("timeout_secs")
+            .and_then(|v| v.as_u64())
+            .map_or(self.timeout, Duration::from_secs);
+        let max_bytes = input
+            .get("max_bytes")
+            .and_then(|v| v.as_u64())
+            .map_or(self.max_response_bytes, |n| n as usize)
+            .min(self.max_response_bytes);
+
+        let mut request = self
+            .client
+            .request(method.clone(), url.clone())
+            .timeout(timeout);
+        if let Some(headers) = input.get("headers").and_then(|v| v.as_object()) {
+            for (name, value) in headers {
+                let value = value
+                    .as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| value.to_string());
+                request = request.header(name.as_str(), value);
+            }
+        }
+        match input.get("body") {
+            Some(Value::String(body)) => request = request.body(body.clone()),
+            Some(body @ (Value::Object(_) | Value::Array(_))) => request = request.json(body),
+            _ => {}
+        }
+
+        let mut response = match request.send().await {
+            Ok(response) => response,
+            Err(error) if error.is_timeout() => {
+                return Ok(json!({
+                    "error": "timed_out",
+                    "timeout_secs": timeout.as_secs(),
+                    "details": error.to_string()
+                }))
+            }
+            Err(error) => return Err(anyhow!("request to {} failed: {}", url, error)),
+        };
+
+        let status = response.status();
+        let final_url = response.url().to_string();
+        let mut headers = Map::new();
+        for (name, value) in response.headers() {
+            let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+            match headers.get_mut(name.as_str()) {
+                Some(Value::String(existing)) => {
+                    existing.push_str(", ");
+                    existing.push_str(&value);
+                }
+                _ => {
+                    headers.insert(name.to_string(), Value::String(value));
+                }
+            }
+        }
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
+        let mut body = Vec::new();
+        let mut truncated = false;
+        if method != Method::HEAD {
+            while let Some(chunk) = response
+                .chunk()
+                .await
+                .with_context(|| format!("failed to read response from {}", url))?
+            {
+                let room = max_bytes - body.len();
+                if chunk.len() > room {
+                    body.extend_from_slice(&chunk[..room]);
+                    truncated = true;
+                    break;
+                }
+                body.extend_from_slice(&chunk);
+            }
+        }
+
+        let mut output = json!({
+            "status": status.as_u16(),
+            "reason": status.canonical_reason(),
+            "url": final_url,
+            "headers": headers,
+            "content_type": content_type,
+            "body_bytes": body.len(),
+            "truncated": truncated
+        });
+        let text = if is_textual(content_type.as_deref()) {
+            Some(String::from_utf8_lossy(&body).into_owned())
+        } else if content_type.is_none() {
+            String::from_utf8(body).ok()
+        } else {
+            None
+        };
+        match text {
+            Some(text) => {
+                let is_json = content_type
+                    .as_deref()
+                    .is_some_and(|ct| ct.contains("json"));
+                if is_json && !truncated {
+                    if let Ok(parsed) = serde_json::from_str::<Value>(&text) {
+                        output["json"] = parsed;
+                    }
+                }
+                output["body"] = Value::String(text);
+            }
+            None if method != Method::HEAD => {
+                output["body"] = Value::Null;
+                output["details"] = json!("binary body omitted");
+            }
+            None => {}
+        }
+        Ok(output)
+    }
+}
+
+fn domain_allowed(allowed: &Option<Vec<String>>, url: &Url) -> bool {
+    let Some(domains) = allowed else {
+        return true;
+    };
+    let Some(host) = url.host_str().map(str::to_lowercase) else {
+        return false;
+    };
+    domains
+        .iter()
+        .any(|domain| domain == "*" || host == *domain || host.ends_with(&format!(".{}", domain)))
+}
+
+fn is_textual(content_type: Option<&str>) -> bool {
+    let Some(content_type) = content_type else {
+        return false;
+    };
+    let mime = content_type.to_lowercase();
+    mime.starts_with("text/")
+        || ["json", "xml", "javascript", "x-www-form-urlencoded", "yaml"]
+            .iter()
+            .any(|kind| mime.contains(kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HttpRequestTool;
+    use crate::tool::Tool;
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn returns_status_headers_and_truncated_body_within_allowed_domains() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = vec![0; 4096];
+                let read = socket.read(&mut request).await.unwrap_or(0);
+                let body = if String::from_utf8_lossy(&request[..read]).starts_with("POST") {
+                    "{\"ok\":true}"
+                } else {
+                    "{\"items\":[1,2,3,4,5,6,7,8,9]}"
+                };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\nx-test: yes\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        let url = format!("http://127.0.0.1:{}/items", port);
+        let tool =
+            HttpRequestTool::new(Some(vec!["127.0.0.1".to_string()])).with_max_response_bytes(20);
+
+        assert!(!tool.side_effecting(&json!({ "url": url, "method": "get" })));
+        assert!(tool.side_effecting(&json!({ "url": url, "method": "POST" })));
+        let output = tool.execute(json!({ "url": url })).await.unwrap();
+        assert_eq!(output["status"], 200);
+        assert_eq!(output["headers"]["x-test"], "yes");
+        assert_eq!(output["body"], "{\"items\":[1,2,3,4,5,");
+        assert_eq!(output["truncated"], true);
+        assert_eq!(output.get("json"), None);
+
+        let posted = tool
+            .execute(json!({ "url": url, "method": "POST", "body": { "a": 1 } }))
+            .await
+            .unwrap();
+        assert_eq!(posted["json"], json!({ "ok": true }));
+
+        let denied = HttpRequestTool::new(Some(vec!["example.com".to_string()]))
+            .execute(json!({ "url": url }))
+            .await
+            .unwrap();
+        assert_eq!(denied["error"], "domain_not_allowed");
+    }
+}
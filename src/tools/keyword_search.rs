@@ -0,0 +1,129 @@
+use crate::keyword_index::{keyword_index_path, tokenize, KeywordIndex};
+use crate::tool::Tool;
+use crate::tools::workspace::Workspace;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+const DEFAULT_MAX_RESULTS: usize = 20;
+const MAX_LINE_CHARS: usize = 300;
+
+pub struct KeywordSearchTool {
+    workspace: Arc<Workspace>,
+}
+
+impl KeywordSearchTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for KeywordSearchTool {
+    fn name(&self) -> &'static str {
+        "keyword_search"
+    }
+
+    fn description(&self) -> &'static str {
+        "Ranked keyword search over the workspace using the offline BM25 index built by `rx index build --kind keyword`. Returns the best matching files with line numbers; use grep_files for exact patterns."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "BM25 keyword search.",
+            "properties": {
+                "query": {
+                    "type": "string",
+                    "description": "Words or identifiers to look for; camelCase and snake_case are split into parts."
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Maximum results returned. Defaults to 20."
+                }
+            },
+            "required": ["query"],
+            "examples": [
+                { "query": "retry backoff http client" },
+                { "query": "parseConfig", "max_results": 5 }
+            ]
+        })
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let query = input
+            .get("query")
+            .and_then(|v| v.as_str())
+            .filter(|query| !query.trim().is_empty())
+            .ok_or_else(|| anyhow!("missing query"))?
+            .to_string();
+        let max_results = input
+            .get("max_results")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_MAX_RESULTS, |n| n as usize)
+            .max(1);
+
+        let root = self.workspace.root().to_path_buf();
+        let index_path = keyword_index_path(&root);
+        if !index_path.exists() {
+            return Ok(json!({
+                "error": "keyword index not built",
+                "details": "Run `rx index build --kind keyword` in the workspace, or use grep_files."
+            }));
+        }
+        tokio::task::spawn_blocking(move || {
+            let index = KeywordIndex::load(&index_path)?;
+            let terms: HashSet<String> = tokenize(&query).into_iter().collect();
+            let results: Vec<Value> = index
+                .search(&query, max_results)
+                .into_iter()
+                .map(|hit| {
+                    let text = std::fs::read_to_string(root.join(&hit.path)).unwrap_or_default();
+                    let (line, text) = best_line(&text, hit.start_line, hit.end_line, &terms);
+                    json!({
+                        "path": hit.path,
+                        "line": line,
+                        "start_line": hit.start_line,
+                        "end_line": hit.end_line,
+                        "score": (hit.score * 1000.0).round() / 1000.0,
+                        "text": text
+                    })
+                })
+                .collect();
+            Ok(json!({ "results": results, "index_built_at": index.built_at }))
+        })
+        .await
+        .context("keyword_search task failed")?
+    }
+}
+
+/// The line in `start..=end` containing the most distinct query terms; the
+/// index may be older than the file, so missing lines yield empty text.
+fn best_line(text: &str, start: usize, end: usize, terms: &HashSet<String>) -> (usize, String) {
+    let mut best = (start, String::new(), 0);
+    for (index, line) in text
+        .lines()
+        .enumerate()
+        .skip(start - 1)
+        .take(end + 1 - start)
+    {
+        let found: HashSet<String> = tokenize(line)
+            .into_iter()
+            .filter(|term| terms.contains(term))
+            .collect();
+        if found.len() > best.2 {
+            best = (
+                index + 1,
+                line.trim().chars().take(MAX_LINE_CHARS).collect(),
+                found.len(),
+            );
+        }
+    }
+    (best.0, best.1)
+}
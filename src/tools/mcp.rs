@@ -0,0 +1,352 @@
+use crate::config::McpServerConfig;
+use crate::tool::Tool;
+use crate::tools::shell::resolve_program;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::Mutex;
+
+const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+const MCP_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+enum McpTransport {
+    Stdio {
+        _child: Box<Child>,
+        io: Box<Mutex<(ChildStdin, BufReader<ChildStdout>)>>,
+    },
+    Http {
+        client: Client,
+        url: String,
+        session_id: Mutex<Option<String>>,
+    },
+}
+
+/// JSON-RPC client for one MCP server, over a child process's stdio or
+/// streamable HTTP.
+pub struct McpClient {
+    server: String,
+    read_only_tools: Vec<String>,
+    transport: McpTransport,
+    next_id: AtomicU64,
+}
+
+impl McpClient {
+    /// Connects to the server; stdio servers are spawned with `env` added to
+    /// the inherited environment.
+    pub async fn connect(config: &McpServerConfig, env: &BTreeMap<String, String>) -> Result<Self> {
+        let transport = match (&config.command, &config.url) {
+            (Some(command), None) => {
+                let mut child = Command::new(resolve_program(command))
+                    .args(config.args.as_deref().unwrap_or_default())
+                    .envs(env)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::inherit())
+                    .kill_on_drop(true)
+                    .spawn()
+                    .with_context(|| format!("failed to spawn MCP server '{}'", config.name))?;
+                let stdin = child
+                    .stdin
+                    .take()
+                    .ok_or_else(|| anyhow!("MCP server stdin unavailable"))?;
+                let stdout = child
+                    .stdout
+                    .take()
+                    .ok_or_else(|| anyhow!("MCP server stdout unavailable"))?;
+                McpTransport::Stdio {
+                    _child: Box::new(child),
+                    io: Box::new(Mutex::new((stdin, BufReader::new(stdout)))),
+                }
+            }
+            (None, Some(url)) => McpTransport::Http {
+                client: Client::new(),
+                url: url.clone(),
+                session_id: Mutex::new(None),
+            },
+            _ => {
+                return Err(anyhow!(
+                    "MCP server '{}' must set exactly one of 'command' or 'url'",
+                    config.name
+                ))
+            }
+        };
+
+        let client = Self {
+            server: config.name.clone(),
+            read_only_tools: config.read_only_tools.clone().unwrap_or_default(),
+            transport,
+            next_id: AtomicU64::new(1),
+        };
+
+        client
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": MCP_PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": { "name": "rx", "version": env!("CARGO_PKG_VERSION") }
+                }),
+            )
+            .await
+            .with_context(|| format!("MCP server '{}' failed to initialize", config.name))?;
+        client
+            .notify("notifications/initialized", json!({}))
+            .await?;
+
+        Ok(client)
+    }
+
+    pub async fn list_tools(&self) -> Result<Vec<Value>> {
+        let result = self.request("tools/list", json!({})).await?;
+        Ok(result
+            .get("tools")
+            .and_then(|tools| tools.as_array())
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value> {
+        self.request(
+            "tools/call",
+            json!({ "name": name, "arguments": arguments }),
+        )
+        .await
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+
+        let response = tokio::time::timeout(MCP_REQUEST_TIMEOUT, self.exchange(id, &message))
+            .await
+            .map_err(|_| anyhow!("MCP server '{}' timed out on {}", self.server, method))??;
+
+        if let Some(error) = response.get("error") {
+            return Err(anyhow!(
+                "MCP server '{}' returned error for {}: {}",
+                self.server,
+                method,
+                error
+            ));
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        let message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        match &self.transport {
+            McpTransport::Stdio { io, .. } => {
+                let mut io = io.lock().await;
+                write_line(&mut io.0, &message).await
+            }
+            McpTransport::Http { .. } => self.post(&message).await.map(|_| ()),
+        }
+    }
+
+    async fn exchange(&self, id: u64, message: &Value) -> Result<Value> {
+        match &self.transport {
+            McpTransport::Stdio { io, .. } => {
+                let mut io = io.lock().await;
+                write_line(&mut io.0, message).await?;
+                loop {
+                    let mut line = String::new();
+                    if io.1.read_line(&mut line).await? == 0 {
+                        return Err(anyhow!("MCP server '{}' closed its stdout", self.server));
+                    }
+                    let Ok(value) = serde_json::from_str::<Value>(line.trim()) else {
+                        continue;
+                    };
+                    if value.get("id").and_then(|v| v.as_u64()) == Some(id) {
+                        return Ok(value);
+                    }
+                }
+            }
+            McpTransport::Http { .. } => {
+                let body = self.post(message).await?;
+                find_response(&body, id).ok_or_else(|| {
+                    anyhow!(
+                        "MCP server '{}' sent no response for request {}",
+                        self.server,
+                        id
+                    )
+                })
+            }
+        }
+    }
+
+    async fn post(&self, message: &Value) -> Result<String> {
+        let McpTransport::Http {
+            client,
+            url,
+            session_id,
+        } = &self.transport
+        else {
+            return Err(anyhow!("not an HTTP MCP transport"));
+        };
+
+        let mut request = client
+            .post(url)
+            .header("Accept", "application/json, text/event-stream")
+            .json(message);
+        if let Some(session) = session_id.lock().await.as_ref() {
+            request = request.header("Mcp-Session-Id", session);
+        }
+
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("failed to reach MCP server '{}'", self.server))?;
+        if let Some(session) = response
+            .headers()
+            .get("mcp-session-id")
+            .and_then(|value| value.to_str().ok())
+        {
+            *session_id.lock().await = Some(session.to_string());
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "MCP server '{}' responded with status {}",
+                self.server,
+                response.status()
+            ));
+        }
+        Ok(response.text().await?)
+    }
+}
+
+async fn write_line(stdin: &mut ChildStdin, message: &Value) -> Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    stdin.write_all(line.as_bytes()).await?;
+    stdin.flush().await?;
+    Ok(())
+}
+
+/// Accepts either a plain JSON body or an SSE stream of `data:` lines.
+fn find_response(body: &str, id: u64) -> Option<Value> {
+    let matches = |value: &Value| value.get("id").and_then(|v| v.as_u64()) == Some(id);
+    if let Ok(value) = serde_json::from_str::<Value>(body) {
+        return Some(value).filter(matches);
+    }
+    body.lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .filter_map(|data| serde_json::from_str::<Value>(data.trim()).ok())
+        .find(matches)
+}
+
+/// A tool exported by an MCP server, registered as `<server>__<tool>`.
+pub struct McpTool {
+    client: Arc<McpClient>,
+    name: String,
+    remote_name: String,
+    description: String,
+    parameters: Value,
+    read_only: bool,
+}
+
+impl McpTool {
+    pub fn from_listing(client: Arc<McpClient>, listing: &Value) -> Option<Self> {
+        let remote_name = listing.get("name")?.as_str()?.to_string();
+        let name = format!("{}__{}", client.server, remote_name);
+        // Annotations come from the server itself, so only config can
+        // mark a remote tool read-only.
+        let read_only = client.read_only_tools.contains(&remote_name);
+        Some(Self {
+            description: listing
+                .get("description")
+                .and_then(|value| value.as_str())
+                .unwrap_or("MCP tool")
+                .to_string(),
+            parameters: listing
+                .get("inputSchema")
+                .cloned()
+                .unwrap_or_else(|| json!({ "type": "object" })),
+            client,
+            name,
+            remote_name,
+            read_only,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for McpTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters(&self) -> Value {
+        self.parameters.clone()
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        !self.read_only
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let result = self.client.call_tool(&self.remote_name, input).await?;
+        if result.get("isError").and_then(|v| v.as_bool()) == Some(true) {
+            return Ok(json!({
+                "error": "mcp_tool_error",
+                "content": result.get("content").cloned().unwrap_or(Value::Null)
+            }));
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_response, McpClient, McpTool, McpTransport};
+    use crate::tool::Tool;
+    use reqwest::Client;
+    use serde_json::json;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    #[test]
+    fn find_response_reads_plain_json_and_sse() {
+        let plain = r#"{"jsonrpc":"2.0","id":3,"result":{"tools":[]}}"#;
+        assert_eq!(
+            find_response(plain, 3).expect("plain")["result"],
+            json!({"tools": []})
+        );
+
+        let sse = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"ping\"}\n\ndata: {\"jsonrpc\":\"2.0\",\"id\":4,\"result\":{}}\n\n";
+        assert_eq!(find_response(sse, 4).expect("sse")["id"], json!(4));
+        assert!(find_response(sse, 5).is_none());
+    }
+
+    #[test]
+    fn mcp_tools_are_gated_unless_configured_read_only() {
+        let client = Arc::new(McpClient {
+            server: "docs".to_string(),
+            read_only_tools: vec!["search".to_string()],
+            transport: McpTransport::Http {
+                client: Client::new(),
+                url: "http://127.0.0.1:9/mcp".to_string(),
+                session_id: Mutex::new(None),
+            },
+            next_id: AtomicU64::new(1),
+        });
+        let tool = |name: &str| {
+            McpTool::from_listing(Arc::clone(&client), &json!({ "name": name })).unwrap()
+        };
+        assert_eq!(tool("search").name(), "docs__search");
+        assert!(!tool("search").side_effecting(&json!({})));
+        assert!(tool("delete_page").side_effecting(&json!({})));
+    }
+}
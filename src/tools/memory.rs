@@ -0,0 +1,174 @@
+use crate::tool::Tool;
+use crate::tools::fs::write_atomically;
+use crate::tools::workspace::Workspace;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Project memory files looked up at the workspace root, in order.
+pub const MEMORY_FILES: [&str; 3] = ["RX.md", "AGENTS.md", "CLAUDE.md"];
+/// Characters of one memory file injected into the system prompt.
+const MAX_MEMORY_CHARS: usize = 16_000;
+/// Heading that `remember` appends notes under.
+const NOTES_HEADING: &str = "## Notes from rx";
+
+/// Prompt section with the contents of every project memory file, or
+/// `None` when the workspace root has none.
+pub fn memory_context(root: &Path) -> Option<String> {
+    let mut out = String::new();
+    for name in MEMORY_FILES {
+        let Ok(content) = std::fs::read_to_string(root.join(name)) else {
+            continue;
+        };
+        if content.trim().is_empty() {
+            continue;
+        }
+        if out.is_empty() {
+            out.push_str(
+                "## Project memory\n\nConventions recorded for this repository; follow them.\n",
+            );
+        }
+        out.push_str(&format!("\n### {}\n\n", name));
+        match content.char_indices().nth(MAX_MEMORY_CHARS) {
+            Some((cut, _)) => {
+                out.push_str(&content[..cut]);
+                out.push_str(&format!("\n… truncated; read {} for the rest\n", name));
+            }
+            None => out.push_str(content.trim_end()),
+        }
+        out.push('\n');
+    }
+    (!out.is_empty()).then_some(out)
+}
+
+/// The file `remember` writes to: the first memory file present, or
+/// `RX.md` when there is none.
+fn memory_file(root: &Path) -> PathBuf {
+    MEMORY_FILES
+        .iter()
+        .map(|name| root.join(name))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| root.join(MEMORY_FILES[0]))
+}
+
+pub struct RememberTool {
+    workspace: Arc<Workspace>,
+}
+
+impl RememberTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for RememberTool {
+    fn name(&self) -> &'static str {
+        "remember"
+    }
+
+    fn description(&self) -> &'static str {
+        "Append a durable note to the project memory file (RX.md, AGENTS.md, or CLAUDE.md at the workspace root) so future goals start with it. Record conventions and hard-won facts about this repository, not progress on the current goal."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Save one short, self-contained note for future runs.",
+            "properties": {
+                "note": {
+                    "type": "string",
+                    "description": "The convention or fact to remember, in one or two sentences."
+                }
+            },
+            "required": ["note"],
+            "examples": [
+                { "note": "Run `cargo test --workspace` from the repository root; crate-level runs miss the integration tests." },
+                { "note": "Migrations live in db/migrations and must be numbered sequentially." }
+            ]
+        })
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let note = input
+            .get("note")
+            .and_then(|v| v.as_str())
+            .map(|note| note.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|note| !note.is_empty())
+            .ok_or_else(|| anyhow!("'note' must be a non-empty string"))?;
+
+        let path = memory_file(self.workspace.root());
+        let mut content = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                "# Project memory\n".to_string()
+            }
+            Err(error) => {
+                return Err(error).with_context(|| format!("failed to read {}", path.display()))
+            }
+        };
+        if !content.lines().any(|line| line.trim() == NOTES_HEADING) {
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+            content.push_str(&format!("\n{}\n\n", NOTES_HEADING));
+        } else if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(&format!("- {}\n", note));
+        write_atomically(&path, content.as_bytes())
+            .await
+            .with_context(|| format!("failed to write {}", path.display()))?;
+
+        let file = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(json!({ "remembered": note, "file": file }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{memory_context, RememberTool};
+    use crate::tool::Tool;
+    use crate::tools::workspace::Workspace;
+    use serde_json::json;
+    use std::fs;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[tokio::test]
+    async fn notes_are_appended_to_the_memory_file_and_loaded_next_time() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("rx-memory-{}", nanos));
+        fs::create_dir_all(&root).unwrap();
+        assert!(memory_context(&root).is_none());
+
+        fs::write(root.join("AGENTS.md"), "# Agents\n\nUse tabs.").unwrap();
+        let tool = RememberTool::new(Arc::new(Workspace::new(&root, &root, &[]).unwrap()));
+        let output = tool
+            .execute(json!({ "note": "Tests need\n  `make fixtures` first." }))
+            .await
+            .unwrap();
+        assert_eq!(output["file"], "AGENTS.md");
+        tool.execute(json!({ "note": "Docs are in docs/." }))
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("AGENTS.md")).unwrap(),
+            "# Agents\n\nUse tabs.\n\n## Notes from rx\n\n- Tests need `make fixtures` first.\n- Docs are in docs/.\n"
+        );
+        assert!(tool.execute(json!({ "note": "  " })).await.is_err());
+
+        let context = memory_context(&root).unwrap();
+        assert!(context.contains("### AGENTS.md\n\n# Agents"));
+        assert!(context.contains("- Docs are in docs/."));
+        let _ = fs::remove_dir_all(root);
+    }
+}
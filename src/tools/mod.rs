@@ -1,3 +1,26 @@
+pub mod code_search;
 pub mod done;
+pub mod edit_lines;
+pub mod env;
 pub mod exec;
+pub mod file_excerpt;
 pub mod fs;
+pub mod git;
+pub mod glob;
+pub mod grep;
+pub mod http;
+pub mod keyword_search;
+pub mod mcp;
+pub mod memory;
+pub mod multi_edit;
+pub mod plan;
+pub mod process;
+pub mod pty;
+pub mod search;
+pub mod shell;
+pub mod tool_output;
+pub mod tree;
+pub mod undo;
+pub mod working_dir;
+pub mod workspace;
+pub mod workspace_info;
@@ -0,0 +1,258 @@
+use crate::tool::Tool;
+use crate::tools::fs::{replace_n, write_atomically};
+use crate::tools::workspace::Workspace;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::fs::read_to_string;
+
+struct FileEdit {
+    path: String,
+    old_text: String,
+    new_text: String,
+    expected_matches: usize,
+}
+
+impl FileEdit {
+    fn parse(index: usize, value: &Value) -> Result<Self> {
+        let field = |name: &str| {
+            value
+                .get(name)
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("edit {}: '{}' is required", index, name))
+        };
+        let old_text = field("old_text")?;
+        if old_text.is_empty() {
+            return Err(anyhow!("edit {}: 'old_text' cannot be empty", index));
+        }
+        Ok(Self {
+            path: field("path")?,
+            old_text,
+            new_text: field("new_text")?,
+            expected_matches: value
+                .get("expected_matches")
+                .and_then(|v| v.as_u64())
+                .map_or(1, |n| n as usize),
+        })
+    }
+}
+
+/// A file touched by the batch: its original text and the edited result.
+struct StagedFile {
+    path: String,
+    resolved: PathBuf,
+    original: String,
+    edited: String,
+}
+
+pub struct MultiEditTool {
+    workspace: Arc<Workspace>,
+}
+
+impl MultiEditTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for MultiEditTool {
+    fn name(&self) -> &'static str {
+        "multi_edit"
+    }
+
+    fn description(&self) -> &'static str {
+        "Apply a batch of exact-text replacements across one or more files all-or-nothing. Every edit is validated against its expected match count before anything is written; if any edit fails, no file changes. Edits to the same file apply in order."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Atomic batch of replace_in_file style edits.",
+            "properties": {
+                "edits": {
+                    "type": "array",
+                    "minItems": 1,
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "path": { "type": "string", "description": "File path to modify." },
+                            "old_text": { "type": "string", "description": "Exact text to find." },
+                            "new_text": { "type": "string", "description": "Replacement text." },
+                            "expected_matches": {
+                                "type": "integer",
+                                "minimum": 1,
+                                "description": "Required number of matches. Defaults to 1."
+                            }
+                        },
+                        "required": ["path", "old_text", "new_text"]
+                    }
+                }
+            },
+            "required": ["edits"],
+            "examples": [{
+                "edits": [
+                    { "path": "src/config.rs", "old_text": "fn load(", "new_text": "fn load_config(" },
+                    { "path": "src/main.rs", "old_text": "config::load(", "new_text": "config::load_config(", "expected_matches": 2 }
+                ]
+            }]
+        })
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let edits = input
+            .get("edits")
+            .and_then(|v| v.as_array())
+            .filter(|edits| !edits.is_empty())
+            .ok_or_else(|| anyhow!("'edits' must be a non-empty array"))?
+            .iter()
+            .enumerate()
+            .map(|(index, edit)| FileEdit::parse(index, edit))
+            .collect::<Result<Vec<_>>>()?;
+
+        // Validate every edit against the in-memory result of the ones
+        // before it, so nothing is written unless the whole batch applies.
+        let mut staged: Vec<StagedFile> = Vec::new();
+        let mut results = Vec::new();
+        let mut all_valid = true;
+        for (index, edit) in edits.iter().enumerate() {
+            let resolved = self.workspace.resolve(&edit.path)?;
+            let position = match staged.iter().position(|file| file.resolved == resolved) {
+                Some(position) => position,
+                None => {
+                    let original = read_to_string(&resolved)
+                        .await
+                        .with_context(|| format!("edit {}: failed to read {}", index, edit.path))?;
+                    staged.push(StagedFile {
+                        path: edit.path.clone(),
+                        resolved,
+                        edited: original.clone(),
+                        original,
+                    });
+                    staged.len() - 1
+                }
+            };
+            let file = &mut staged[position];
+            let found = file.edited.matches(edit.old_text.as_str()).count();
+            let ok = found == edit.expected_matches;
+            if ok {
+                file.edited = replace_n(
+                    &file.edited,
+                    &edit.old_text,
+                    &edit.new_text,
+                    edit.expected_matches,
+                );
+            }
+            all_valid &= ok;
+            results.push(json!({
+                "index": index,
+                "path": edit.path,
+                "expected_matches": edit.expected_matches,
+                "actual_matches": found,
+                "ok": ok,
+            }));
+        }
+        if !all_valid {
+            return Ok(json!({
+                "success": false,
+                "error": "unexpected_match_count",
+                "details": "no files were changed",
+                "results": results,
+            }));
+        }
+
+        let changed: Vec<&StagedFile> = staged
+            .iter()
+            .filter(|file| file.edited != file.original)
+            .collect();
+        for (written, file) in changed.iter().enumerate() {
+            if let Err(error) = write_atomically(&file.resolved, file.edited.as_bytes()).await {
+                let mut rolled_back = Vec::new();
+                for restored in &changed[..written] {
+                    write_atomically(&restored.resolved, restored.original.as_bytes())
+                        .await
+                        .with_context(|| {
+                            format!("rollback failed; {} is left edited", restored.path)
+                        })?;
+                    rolled_back.push(restored.path.clone());
+                }
+                return Ok(json!({
+                    "success": false,
+                    "error": "write_failed",
+                    "path": file.path,
+                    "details": format!("{:#}", error),
+                    "rolled_back": rolled_back,
+                    "results": results,
+                }));
+            }
+        }
+
+        Ok(json!({
+            "success": true,
+            "files_changed": changed.iter().map(|file| file.path.as_str()).collect::<Vec<_>>(),
+            "results": results,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MultiEditTool;
+    use crate::tool::Tool;
+    use crate::tools::workspace::Workspace;
+    use serde_json::json;
+    use std::fs;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[tokio::test]
+    async fn batch_is_written_only_when_every_edit_matches() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("rx-multi-edit-{}", nanos));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("a.rs"), "fn load() {}\nload();\n").unwrap();
+        fs::write(root.join("b.rs"), "a::load();\n").unwrap();
+        let tool = MultiEditTool::new(Arc::new(Workspace::new(&root, &root, &[]).unwrap()));
+
+        let rejected = tool
+            .execute(json!({ "edits": [
+                { "path": "a.rs", "old_text": "load(", "new_text": "load_all(", "expected_matches": 2 },
+                { "path": "b.rs", "old_text": "missing", "new_text": "x" }
+            ]}))
+            .await
+            .unwrap();
+        assert_eq!(rejected["error"], "unexpected_match_count");
+        assert_eq!(rejected["results"][0]["ok"], true);
+        assert_eq!(rejected["results"][1]["actual_matches"], 0);
+        assert_eq!(
+            fs::read_to_string(root.join("a.rs")).unwrap(),
+            "fn load() {}\nload();\n"
+        );
+
+        let applied = tool
+            .execute(json!({ "edits": [
+                { "path": "a.rs", "old_text": "load(", "new_text": "load_all(", "expected_matches": 2 },
+                { "path": "a.rs", "old_text": "fn load_all", "new_text": "pub fn load_all" },
+                { "path": "b.rs", "old_text": "a::load(", "new_text": "a::load_all(" }
+            ]}))
+            .await
+            .unwrap();
+        assert_eq!(applied["success"], true);
+        assert_eq!(applied["files_changed"], json!(["a.rs", "b.rs"]));
+        assert_eq!(
+            fs::read_to_string(root.join("a.rs")).unwrap(),
+            "pub fn load_all() {}\nload_all();\n"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("b.rs")).unwrap(),
+            "a::load_all();\n"
+        );
+        let _ = fs::remove_dir_all(root);
+    }
+}
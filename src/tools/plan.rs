@@ -0,0 +1,196 @@
+use crate::event::Event;
+use crate::tool::Tool;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+/// Steps accepted in one plan.
+const MAX_PLAN_STEPS: usize = 30;
+
+/// Instruction added before the first model request of a `--plan` run.
+pub const PLAN_REQUEST: &str = "Plan mode: before changing anything, inspect what you need and call submit_plan with the ordered steps you will take. Tools that change the workspace are refused until the plan is approved. Then work through the steps in order and call complete_step after finishing each one.";
+
+/// Progress through the latest approved plan of a goal, derived from its
+/// `plan` and `plan_step_completed` events.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PlanProgress {
+    pub steps: Vec<String>,
+    pub completed: usize,
+}
+
+impl PlanProgress {
+    pub fn from_history(events: &[Event]) -> Option<Self> {
+        let start = events.iter().rposition(|event| event.r#type == "plan")?;
+        let steps = events[start]
+            .payload
+            .get("steps")?
+            .as_array()?
+            .iter()
+            .filter_map(|step| step.as_str().map(str::to_string))
+            .collect();
+        let completed = events[start..]
+            .iter()
+            .filter(|event| event.r#type == "plan_step_completed")
+            .count();
+        Some(Self { steps, completed })
+    }
+
+    /// The step being worked on, numbered from 1.
+    pub fn current(&self) -> Option<(usize, &str)> {
+        self.steps
+            .get(self.completed)
+            .map(|step| (self.completed + 1, step.as_str()))
+    }
+}
+
+/// First phase of a `--plan` run; the kernel records the approved plan.
+pub struct SubmitPlanTool;
+
+#[async_trait]
+impl Tool for SubmitPlanTool {
+    fn name(&self) -> &'static str {
+        "submit_plan"
+    }
+
+    fn description(&self) -> &'static str {
+        "Submit the ordered steps you will take to reach the goal. The plan is reviewed before you may change the workspace; if it is rejected, revise it and submit again. Submitting again later replaces the remaining plan."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Propose a plan of concrete, checkable steps.",
+            "properties": {
+                "steps": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Steps in execution order, each one sentence."
+                }
+            },
+            "required": ["steps"],
+            "examples": [
+                {
+                    "steps": [
+                        "Reproduce the failing test in tests/parser.rs",
+                        "Fix the off-by-one in src/parser.rs",
+                        "Run cargo test and confirm it passes"
+                    ]
+                }
+            ]
+        })
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let steps = input
+            .get("steps")
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| anyhow!("'steps' must be an array of strings"))?
+            .iter()
+            .map(|step| {
+                step.as_str()
+                    .map(str::trim)
+                    .filter(|step| !step.is_empty())
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow!("every step must be a non-empty string"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if steps.is_empty() || steps.len() > MAX_PLAN_STEPS {
+            return Err(anyhow!(
+                "a plan needs between 1 and {} steps, got {}",
+                MAX_PLAN_STEPS,
+                steps.len()
+            ));
+        }
+        Ok(json!({ "steps": steps }))
+    }
+}
+
+/// Marks the current plan step done; the kernel moves on to the next one.
+pub struct CompleteStepTool;
+
+#[async_trait]
+impl Tool for CompleteStepTool {
+    fn name(&self) -> &'static str {
+        "complete_step"
+    }
+
+    fn description(&self) -> &'static str {
+        "Mark the current step of the approved plan as finished and get the next one. Call it once per step, in order, after the step's work is done."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Report what the current step accomplished.",
+            "properties": {
+                "summary": {
+                    "type": "string",
+                    "description": "What was done for this step, in one or two sentences."
+                }
+            },
+            "required": ["summary"],
+            "examples": [
+                { "summary": "Reproduced the failure: parse_range drops the last element." }
+            ]
+        })
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let summary = input
+            .get("summary")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|summary| !summary.is_empty())
+            .ok_or_else(|| anyhow!("'summary' must be a non-empty string"))?;
+        Ok(json!({ "summary": summary }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PlanProgress, SubmitPlanTool};
+    use crate::event::Event;
+    use crate::tool::Tool;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn progress_follows_the_latest_plan() {
+        let plan = SubmitPlanTool
+            .execute(json!({ "steps": [" read ", "fix"] }))
+            .await
+            .unwrap();
+        assert_eq!(plan["steps"], json!(["read", "fix"]));
+        assert!(SubmitPlanTool
+            .execute(json!({ "steps": [] }))
+            .await
+            .is_err());
+        assert!(SubmitPlanTool
+            .execute(json!({ "steps": ["read", ""] }))
+            .await
+            .is_err());
+
+        let mut events = vec![Event::new("goal", json!({ "goal": "fix it" }))];
+        assert!(PlanProgress::from_history(&events).is_none());
+        events.push(Event::new("plan", json!({ "steps": ["old"] })));
+        events.push(Event::new("plan_step_completed", json!({ "index": 1 })));
+        events.push(Event::new("plan", plan));
+        let progress = PlanProgress::from_history(&events).unwrap();
+        assert_eq!(progress.current(), Some((1, "read")));
+
+        events.push(Event::new("plan_step_completed", json!({ "index": 1 })));
+        assert_eq!(
+            PlanProgress::from_history(&events).unwrap().current(),
+            Some((2, "fix"))
+        );
+        events.push(Event::new("plan_step_completed", json!({ "index": 2 })));
+        assert_eq!(PlanProgress::from_history(&events).unwrap().current(), None);
+    }
+}
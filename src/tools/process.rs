@@ -0,0 +1,500 @@
+use crate::tool::Tool;
+use crate::tools::env::{call_env, env_parameter, EnvPolicy};
+use crate::tools::shell::resolve_program;
+use crate::tools::workspace::Workspace;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, VecDeque};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+
+/// Output lines kept per process; older lines are dropped.
+const LOG_CAPACITY: usize = 1_000;
+const DEFAULT_TAIL_LINES: usize = 50;
+
+type LogBuffer = Arc<Mutex<VecDeque<(&'static str, String)>>>;
+
+struct ManagedProcess {
+    command: String,
+    child: Child,
+    started: Instant,
+    logs: LogBuffer,
+    dropped_lines: Arc<Mutex<u64>>,
+}
+
+/// Background processes started by `exec_background`. The kernel kills
+/// whatever is still running when the run ends, and dropping the table
+/// kills the children too.
+#[derive(Default)]
+pub struct ProcessTable {
+    processes: tokio::sync::Mutex<BTreeMap<String, ManagedProcess>>,
+    next_id: Mutex<u64>,
+}
+
+impl ProcessTable {
+    async fn spawn(&self, mut command: Command, display: String) -> Result<(String, Option<u32>)> {
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("failed to start '{}'", display))?;
+        let logs: LogBuffer = Arc::default();
+        let dropped_lines = Arc::new(Mutex::new(0));
+        if let Some(stdout) = child.stdout.take() {
+            collect_lines(
+                stdout,
+                "stdout",
+                Arc::clone(&logs),
+                Arc::clone(&dropped_lines),
+            );
+        }
+        if let Some(stderr) = child.stderr.take() {
+            collect_lines(
+                stderr,
+                "stderr",
+                Arc::clone(&logs),
+                Arc::clone(&dropped_lines),
+            );
+        }
+        let id = {
+            let mut next_id = self.next_id.lock().expect("process id lock");
+            *next_id += 1;
+            format!("proc-{}", next_id)
+        };
+        let pid = child.id();
+        self.processes.lock().await.insert(
+            id.clone(),
+            ManagedProcess {
+                command: display,
+                child,
+                started: Instant::now(),
+                logs,
+                dropped_lines,
+            },
+        );
+        Ok((id, pid))
+    }
+
+    async fn status(&self, id: Option<&str>) -> Result<Value> {
+        let mut processes = self.processes.lock().await;
+        let mut statuses = Vec::new();
+        for (process_id, process) in processes.iter_mut() {
+            if id.is_some_and(|id| id != process_id) {
+                continue;
+            }
+            let (state, code) = match process.child.try_wait() {
+                Ok(None) => ("running", None),
+                Ok(Some(status)) => ("exited", status.code()),
+                Err(_) => ("unknown", None),
+            };
+            statuses.push(json!({
+                "id": process_id,
+                "command": process.command,
+                "pid": process.child.id(),
+                "state": state,
+                "code": code,
+                "uptime_secs": process.started.elapsed().as_secs(),
+            }));
+        }
+        if let (Some(id), true) = (id, statuses.is_empty()) {
+            return Err(anyhow!("unknown process '{}'", id));
+        }
+        Ok(json!({ "processes": statuses }))
+    }
+
+    async fn logs(&self, id: &str, lines: usize, stream: Option<&str>) -> Result<Value> {
+        let processes = self.processes.lock().await;
+        let process = processes
+            .get(id)
+            .ok_or_else(|| anyhow!("unknown process '{}'", id))?;
+        let logs = process.logs.lock().expect("log buffer lock");
+        let matching: Vec<Value> = logs
+            .iter()
+            .filter(|(source, _)| stream.is_none_or(|stream| stream == *source))
+            .map(|(source, line)| json!({ "stream": source, "line": line }))
+            .collect();
+        let start = matching.len().saturating_sub(lines);
+        Ok(json!({
+            "id": id,
+            "lines": matching[start..],
+            "omitted_lines": start as u64 + *process.dropped_lines.lock().expect("log counter lock"),
+        }))
+    }
+
+    async fn kill(&self, id: &str) -> Result<Value> {
+        let mut processes = self.processes.lock().await;
+        let process = processes
+            .get_mut(id)
+            .ok_or_else(|| anyhow!("unknown process '{}'", id))?;
+        let already_exited = process.child.try_wait()?.is_some();
+        if !already_exited {
+            process
+                .child
+                .kill()
+                .await
+                .with_context(|| format!("failed to kill {}", id))?;
+        }
+        let code = process.child.try_wait()?.and_then(|status| status.code());
+        Ok(json!({ "id": id, "killed": !already_exited, "code": code }))
+    }
+
+    /// Kills every process still running and returns how many there were.
+    pub async fn kill_all(&self) -> usize {
+        let mut processes = self.processes.lock().await;
+        let mut killed = 0;
+        for process in processes.values_mut() {
+            if matches!(process.child.try_wait(), Ok(None)) && process.child.kill().await.is_ok() {
+                killed += 1;
+            }
+        }
+        killed
+    }
+}
+
+fn collect_lines(
+    reader: impl AsyncRead + Unpin + Send + 'static,
+    stream: &'static str,
+    logs: LogBuffer,
+    dropped_lines: Arc<Mutex<u64>>,
+) {
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let mut logs = logs.lock().expect("log buffer lock");
+            if logs.len() == LOG_CAPACITY {
+                logs.pop_front();
+                *dropped_lines.lock().expect("log counter lock") += 1;
+            }
+            logs.push_back((stream, line));
+        }
+    });
+}
+
+fn process_id(input: &Value) -> Result<&str> {
+    input
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("missing id"))
+}
+
+pub struct ExecBackgroundTool {
+    processes: Arc<ProcessTable>,
+    env: BTreeMap<String, String>,
+    env_policy: Option<EnvPolicy>,
+    workspace: Option<Arc<Workspace>>,
+}
+
+impl ExecBackgroundTool {
+    pub fn new(processes: Arc<ProcessTable>) -> Self {
+        Self {
+            processes,
+            env: BTreeMap::new(),
+            env_policy: None,
+            workspace: None,
+        }
+    }
+
+    /// Variables exported to every spawned process, like `exec`.
+    pub fn with_env(mut self, env: BTreeMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Limits the inherited environment, like `exec`.
+    pub fn with_env_policy(mut self, env_policy: EnvPolicy) -> Self {
+        self.env_policy = Some(env_policy);
+        self
+    }
+
+    /// Starts processes from the workspace's base directory, like `exec`.
+    pub fn with_workspace(mut self, workspace: Arc<Workspace>) -> Self {
+        self.workspace = Some(workspace);
+        self
+    }
+}
+
+#[async_trait]
+impl Tool for ExecBackgroundTool {
+    fn name(&self) -> &'static str {
+        "exec_background"
+    }
+
+    fn description(&self) -> &'static str {
+        "Start a long-running process (dev server, watcher) without waiting for it and return a process id. Inspect it with process_status and process_logs and stop it with process_kill; it is stopped automatically when the run ends."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Spawn a background process directly, without a shell.",
+            "properties": {
+                "command": {
+                    "type": "string",
+                    "description": "Executable name or path."
+                },
+                "args": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Positional arguments passed exactly as provided."
+                },
+                "cwd": {
+                    "type": "string",
+                    "description": "Optional working directory for the process."
+                },
+                "env": env_parameter()
+            },
+            "required": ["command"],
+            "examples": [
+                { "command": "npm", "args": ["run", "dev"] },
+                { "command": "cargo", "args": ["watch", "-x", "test"] }
+            ]
+        })
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let command = input
+            .get("command")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("'command' field is required"))?;
+        let args: Vec<String> = input
+            .get("args")
+            .and_then(|v| v.as_array())
+            .map(|array| {
+                array
+                    .iter()
+                    .filter_map(|value| value.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let call_env = call_env(&input)?;
+        let mut cmd = Command::new(resolve_program(command));
+        if let Some(policy) = &self.env_policy {
+            cmd.env_clear().envs(policy.inherited());
+        }
+        cmd.args(&args).envs(&self.env).envs(&call_env);
+        let cwd = input.get("cwd").and_then(|v| v.as_str());
+        match (&self.workspace, cwd) {
+            (Some(workspace), cwd) => {
+                cmd.current_dir(workspace.command_dir(cwd));
+            }
+            (None, Some(cwd)) => {
+                cmd.current_dir(cwd);
+            }
+            (None, None) => {}
+        }
+        let display = std::iter::once(command.to_string())
+            .chain(args)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let (id, pid) = self.processes.spawn(cmd, display).await?;
+        Ok(json!({ "id": id, "pid": pid, "state": "running" }))
+    }
+}
+
+pub struct ProcessStatusTool {
+    processes: Arc<ProcessTable>,
+}
+
+impl ProcessStatusTool {
+    pub fn new(processes: Arc<ProcessTable>) -> Self {
+        Self { processes }
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessStatusTool {
+    fn name(&self) -> &'static str {
+        "process_status"
+    }
+
+    fn description(&self) -> &'static str {
+        "Report whether background processes started with exec_background are running or have exited, with exit codes."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Background process status.",
+            "properties": {
+                "id": {
+                    "type": "string",
+                    "description": "Process id from exec_background. Omit to list all."
+                }
+            },
+            "examples": [{}, { "id": "proc-1" }]
+        })
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        self.processes
+            .status(input.get("id").and_then(|v| v.as_str()))
+            .await
+    }
+}
+
+pub struct ProcessLogsTool {
+    processes: Arc<ProcessTable>,
+}
+
+impl ProcessLogsTool {
+    pub fn new(processes: Arc<ProcessTable>) -> Self {
+        Self { processes }
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessLogsTool {
+    fn name(&self) -> &'static str {
+        "process_logs"
+    }
+
+    fn description(&self) -> &'static str {
+        "Return the most recent output lines of a background process. Only the last 1000 lines are kept."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Tail of a background process's output.",
+            "properties": {
+                "id": {
+                    "type": "string",
+                    "description": "Process id from exec_background."
+                },
+                "lines": {
+                    "type": "integer",
+                    "description": "Number of lines from the end. Defaults to 50."
+                },
+                "stream": {
+                    "type": "string",
+                    "enum": ["stdout", "stderr"],
+                    "description": "Only this stream. Defaults to both, interleaved."
+                }
+            },
+            "required": ["id"],
+            "examples": [{ "id": "proc-1" }, { "id": "proc-1", "lines": 200, "stream": "stderr" }]
+        })
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let lines = input
+            .get("lines")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_TAIL_LINES, |n| n as usize);
+        self.processes
+            .logs(
+                process_id(&input)?,
+                lines,
+                input.get("stream").and_then(|v| v.as_str()),
+            )
+            .await
+    }
+}
+
+pub struct ProcessKillTool {
+    processes: Arc<ProcessTable>,
+}
+
+impl ProcessKillTool {
+    pub fn new(processes: Arc<ProcessTable>) -> Self {
+        Self { processes }
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessKillTool {
+    fn name(&self) -> &'static str {
+        "process_kill"
+    }
+
+    fn description(&self) -> &'static str {
+        "Stop a background process started with exec_background."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Kill a background process.",
+            "properties": {
+                "id": {
+                    "type": "string",
+                    "description": "Process id from exec_background."
+                }
+            },
+            "required": ["id"],
+            "examples": [{ "id": "proc-1" }]
+        })
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        self.processes.kill(process_id(&input)?).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ExecBackgroundTool, ProcessKillTool, ProcessLogsTool, ProcessStatusTool, ProcessTable,
+    };
+    use crate::tool::Tool;
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn background_process_logs_are_tailed_and_killed_on_cleanup() {
+        let processes = Arc::new(ProcessTable::default());
+        let started = ExecBackgroundTool::new(Arc::clone(&processes))
+            .execute(json!({ "command": "sh", "args": ["-c", "echo one; echo two >&2; sleep 30"] }))
+            .await
+            .unwrap();
+        let id = started["id"].as_str().unwrap().to_string();
+        assert_eq!(id, "proc-1");
+
+        let logs = ProcessLogsTool::new(Arc::clone(&processes));
+        let mut output = json!(null);
+        for _ in 0..100 {
+            output = logs.execute(json!({ "id": id })).await.unwrap();
+            if output["lines"].as_array().unwrap().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let stdout = logs
+            .execute(json!({ "id": id, "stream": "stdout" }))
+            .await
+            .unwrap();
+        assert_eq!(
+            stdout["lines"],
+            json!([{ "stream": "stdout", "line": "one" }])
+        );
+        assert_eq!(output["lines"].as_array().unwrap().len(), 2);
+
+        let status = ProcessStatusTool::new(Arc::clone(&processes));
+        let kill = ProcessKillTool::new(Arc::clone(&processes));
+        assert!(!status.side_effecting(&json!({})) && !logs.side_effecting(&json!({})));
+        assert!(kill.side_effecting(&json!({ "id": id })));
+        let running = status.execute(json!({ "id": id })).await.unwrap();
+        assert_eq!(running["processes"][0]["state"], "running");
+
+        assert_eq!(processes.kill_all().await, 1);
+        let stopped = status.execute(json!({})).await.unwrap();
+        assert_eq!(stopped["processes"][0]["state"], "exited");
+        assert!(status.execute(json!({ "id": "proc-9" })).await.is_err());
+    }
+}
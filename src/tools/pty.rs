@@ -0,0 +1,577 @@
+use crate::tool::Tool;
+use crate::tools::env::EnvPolicy;
+use crate::tools::shell::default_interactive_shell;
+use crate::tools::workspace::Workspace;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+const MAX_SESSIONS: usize = 8;
+/// Unread output kept per session; older bytes are dropped.
+const BUFFER_BYTES: usize = 256 * 1024;
+const DEFAULT_READ_BYTES: usize = 8_000;
+const DEFAULT_WAIT_MS: u64 = 500;
+const MAX_WAIT_MS: u64 = 30_000;
+
+#[derive(Default)]
+struct OutputBuffer {
+    bytes: VecDeque<u8>,
+    dropped: u64,
+}
+
+struct Session {
+    command: String,
+    child: Box<dyn Child + Send>,
+    writer: Box<dyn Write + Send>,
+    // Keeps the terminal open for the life of the session.
+    _master: Box<dyn MasterPty + Send>,
+    output: Arc<Mutex<OutputBuffer>>,
+}
+
+/// Interactive terminal sessions opened by `open_session`. The kernel closes
+/// any left open when it is dropped at the end of the goal.
+#[derive(Default)]
+pub struct PtySessions {
+    sessions: Mutex<BTreeMap<String, Session>>,
+    next_id: Mutex<u64>,
+}
+
+impl PtySessions {
+    fn open(
+        &self,
+        command: &str,
+        args: &[String],
+        cwd: Option<&Path>,
+        env: &BTreeMap<String, String>,
+        env_policy: Option<&EnvPolicy>,
+    ) -> Result<String> {
+        if self.sessions.lock().expect("session lock").len() >= MAX_SESSIONS {
+            return Err(anyhow!(
+                "too many open sessions (limit {}); close one first",
+                MAX_SESSIONS
+            ));
+        }
+        let pair = native_pty_system()
+            .openpty(PtySize {
+                rows: 40,
+                cols: 120,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(|error| anyhow!("failed to open a pty: {}", error))?;
+        let mut builder = CommandBuilder::new(command);
+        builder.args(args);
+        match cwd {
+            Some(cwd) => builder.cwd(cwd),
+            None => builder.cwd(std::env::current_dir().context("no current directory")?),
+        }
+        if let Some(policy) = env_policy {
+            builder.env_clear();
+            for (key, value) in policy.inherited() {
+                builder.env(key, value);
+            }
+        }
+        for (key, value) in env {
+            builder.env(key, value);
+        }
+        let child = pair
+            .slave
+            .spawn_command(builder)
+            .map_err(|error| anyhow!("failed to start '{}': {}", command, error))?;
+        drop(pair.slave);
+
+        let mut reader = pair
+            .master
+            .try_clone_reader()
+            .map_err(|error| anyhow!("failed to read the pty: {}", error))?;
+        let writer = pair
+            .master
+            .take_writer()
+            .map_err(|error| anyhow!("failed to write the pty: {}", error))?;
+        let output = Arc::new(Mutex::new(OutputBuffer::default()));
+        let sink = Arc::clone(&output);
+        std::thread::spawn(move || {
+            let mut chunk = [0u8; 4096];
+            while let Ok(read) = reader.read(&mut chunk) {
+                if read == 0 {
+                    break;
+                }
+                let mut buffer = sink.lock().expect("pty output lock");
+                buffer.bytes.extend(&chunk[..read]);
+                let excess = buffer.bytes.len().saturating_sub(BUFFER_BYTES);
+                buffer.bytes.drain(..excess);
+                buffer.dropped += excess as u64;
+            }
+        });
+
+        let id = {
+            let mut next_id = self.next_id.lock().expect("session id lock");
+            *next_id += 1;
+            format!("session-{}", next_id)
+        };
+        let display = std::iter::once(command)
+            .chain(args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.sessions.lock().expect("session lock").insert(
+            id.clone(),
+            Session {
+                command: display,
+                child,
+                writer,
+                _master: pair.master,
+                output,
+            },
+        );
+        Ok(id)
+    }
+
+    fn send(&self, id: &str, input: &str) -> Result<()> {
+        let mut sessions = self.sessions.lock().expect("session lock");
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| anyhow!("unknown session '{}'", id))?;
+        session
+            .writer
+            .write_all(input.as_bytes())
+            .and_then(|()| session.writer.flush())
+            .with_context(|| format!("failed to write to {}", id))
+    }
+
+    /// Takes up to `max_bytes` of unread output, ending on a character
+    /// boundary, and reports whether the session's process has exited.
+    fn take_output(&self, id: &str, max_bytes: usize) -> Result<(String, usize, u64, bool)> {
+        let mut sessions = self.sessions.lock().expect("session lock");
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| anyhow!("unknown session '{}'", id))?;
+        let exited = matches!(session.child.try_wait(), Ok(Some(_)));
+        let mut buffer = session.output.lock().expect("pty output lock");
+        let take = buffer.bytes.len().min(max_bytes);
+        let mut bytes: Vec<u8> = buffer.bytes.iter().take(take).copied().collect();
+        if let Err(error) = std::str::from_utf8(&bytes) {
+            if error.error_len().is_none() && error.valid_up_to() > 0 {
+                bytes.truncate(error.valid_up_to());
+            }
+        }
+        buffer.bytes.drain(..bytes.len());
+        let dropped = std::mem::take(&mut buffer.dropped);
+        Ok((
+            String::from_utf8_lossy(&bytes).into_owned(),
+            buffer.bytes.len(),
+            dropped,
+            exited,
+        ))
+    }
+
+    fn has_output(&self, id: &str) -> bool {
+        let sessions = self.sessions.lock().expect("session lock");
+        sessions.get(id).is_some_and(|session| {
+            !session
+                .output
+                .lock()
+                .expect("pty output lock")
+                .bytes
+                .is_empty()
+        })
+    }
+
+    fn close(&self, id: &str) -> Result<Value> {
+        let mut session = self
+            .sessions
+            .lock()
+            .expect("session lock")
+            .remove(id)
+            .ok_or_else(|| anyhow!("unknown session '{}'", id))?;
+        let code = match session.child.try_wait()? {
+            Some(status) => Some(status.exit_code()),
+            None => {
+                session
+                    .child
+                    .kill()
+                    .with_context(|| format!("failed to stop {}", id))?;
+                None
+            }
+        };
+        Ok(json!({ "session_id": id, "command": session.command, "closed": true, "code": code }))
+    }
+
+    /// Kills every open session.
+    pub fn close_all(&self) {
+        let mut sessions = self.sessions.lock().expect("session lock");
+        for session in sessions.values_mut() {
+            if matches!(session.child.try_wait(), Ok(None)) {
+                let _ = session.child.kill();
+            }
+        }
+        sessions.clear();
+    }
+}
+
+/// Removes terminal escape sequences and carriage returns so the model sees
+/// plain text.
+fn strip_terminal_codes(text: &str) -> String {
+    static ESCAPES: OnceLock<Regex> = OnceLock::new();
+    let escapes = ESCAPES.get_or_init(|| {
+        Regex::new(r"\x1b\[[0-9;?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(\x07|\x1b\\)|\x1b[()][A-Za-z0-9]|\x1b[=>]")
+            .expect("valid terminal escape pattern")
+    });
+    escapes.replace_all(text, "").replace('\r', "")
+}
+
+fn session_id(input: &Value) -> Result<&str> {
+    input
+        .get("session_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("missing session_id"))
+}
+
+pub struct OpenSessionTool {
+    sessions: Arc<PtySessions>,
+    env: BTreeMap<String, String>,
+    env_policy: Option<EnvPolicy>,
+    workspace: Option<Arc<Workspace>>,
+}
+
+impl OpenSessionTool {
+    pub fn new(sessions: Arc<PtySessions>) -> Self {
+        Self {
+            sessions,
+            env: BTreeMap::new(),
+            env_policy: None,
+            workspace: None,
+        }
+    }
+
+    /// Variables exported to every session, like `exec`.
+    pub fn with_env(mut self, env: BTreeMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Limits the inherited environment, like `exec`.
+    pub fn with_env_policy(mut self, env_policy: EnvPolicy) -> Self {
+        self.env_policy = Some(env_policy);
+        self
+    }
+
+    /// Starts sessions from the workspace's base directory, like `exec`.
+    pub fn with_workspace(mut self, workspace: Arc<Workspace>) -> Self {
+        self.workspace = Some(workspace);
+        self
+    }
+}
+
+#[async_trait]
+impl Tool for OpenSessionTool {
+    fn name(&self) -> &'static str {
+        "open_session"
+    }
+
+    fn description(&self) -> &'static str {
+        "Open a persistent interactive terminal session (a shell or a REPL) that keeps its state between calls, for example an activated virtualenv. Drive it with send_input and read_output, and close it with close_session."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Start a program on a pseudo-terminal.",
+            "properties": {
+                "command": {
+                    "type": "string",
+                    "description": "Program to run. Defaults to bash (powershell on Windows)."
+                },
+                "args": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Arguments passed exactly as provided."
+                },
+                "cwd": {
+                    "type": "string",
+                    "description": "Optional working directory."
+                }
+            },
+            "examples": [{}, { "command": "python3" }, { "command": "bash", "cwd": "backend" }]
+        })
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let command = input
+            .get("command")
+            .and_then(|v| v.as_str())
+            .unwrap_or(default_interactive_shell());
+        let args: Vec<String> = input
+            .get("args")
+            .and_then(|v| v.as_array())
+            .map(|array| {
+                array
+                    .iter()
+                    .filter_map(|value| value.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let cwd = input.get("cwd").and_then(|v| v.as_str());
+        let cwd = match &self.workspace {
+            Some(workspace) => Some(workspace.command_dir(cwd)),
+            None => cwd.map(PathBuf::from),
+        };
+        let id = self.sessions.open(
+            command,
+            &args,
+            cwd.as_deref(),
+            &self.env,
+            self.env_policy.as_ref(),
+        )?;
+        Ok(json!({ "session_id": id, "command": command }))
+    }
+}
+
+pub struct SendInputTool {
+    sessions: Arc<PtySessions>,
+}
+
+impl SendInputTool {
+    pub fn new(sessions: Arc<PtySessions>) -> Self {
+        Self { sessions }
+    }
+}
+
+#[async_trait]
+impl Tool for SendInputTool {
+    fn name(&self) -> &'static str {
+        "send_input"
+    }
+
+    fn description(&self) -> &'static str {
+        "Type input into an open terminal session. A newline is appended unless newline is false. Use read_output afterwards to see the result."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Send keystrokes to a session.",
+            "properties": {
+                "session_id": {
+                    "type": "string",
+                    "description": "Session id from open_session."
+                },
+                "input": {
+                    "type": "string",
+                    "description": "Text to send. Use \"\\u0003\" for Ctrl-C."
+                },
+                "newline": {
+                    "type": "boolean",
+                    "description": "Append a newline (press Enter). Defaults to true."
+                }
+            },
+            "required": ["session_id", "input"],
+            "examples": [
+                { "session_id": "session-1", "input": "source .venv/bin/activate" },
+                { "session_id": "session-1", "input": "\u{3}", "newline": false }
+            ]
+        })
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let id = session_id(&input)?;
+        let mut text = input
+            .get("input")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("missing input"))?
+            .to_string();
+        if input
+            .get("newline")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true)
+        {
+            text.push('\n');
+        }
+        self.sessions.send(id, &text)?;
+        Ok(json!({ "session_id": id, "bytes_sent": text.len() }))
+    }
+}
+
+pub struct ReadOutputTool {
+    sessions: Arc<PtySessions>,
+}
+
+impl ReadOutputTool {
+    pub fn new(sessions: Arc<PtySessions>) -> Self {
+        Self { sessions }
+    }
+}
+
+#[async_trait]
+impl Tool for ReadOutputTool {
+    fn name(&self) -> &'static str {
+        "read_output"
+    }
+
+    fn description(&self) -> &'static str {
+        "Read new output from a terminal session, waiting up to wait_ms for it to appear. Returns at most max_bytes; call again while pending_bytes is non-zero."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Unread session output with terminal escape codes removed.",
+            "properties": {
+                "session_id": {
+                    "type": "string",
+                    "description": "Session id from open_session."
+                },
+                "max_bytes": {
+                    "type": "integer",
+                    "description": "Maximum bytes to return. Defaults to 8000."
+                },
+                "wait_ms": {
+                    "type": "integer",
+                    "description": "How long to wait for output to settle, up to 30000. Defaults to 500."
+                }
+            },
+            "required": ["session_id"],
+            "examples": [{ "session_id": "session-1" }, { "session_id": "session-1", "wait_ms": 5000 }]
+        })
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let id = session_id(&input)?;
+        let max_bytes = input
+            .get("max_bytes")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_READ_BYTES, |n| n.max(1) as usize);
+        let wait = Duration::from_millis(
+            input
+                .get("wait_ms")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(DEFAULT_WAIT_MS)
+                .min(MAX_WAIT_MS),
+        );
+        // Wait for output to appear, then one more tick so the rest of a
+        // command's output usually arrives in the same read.
+        let deadline = Instant::now() + wait;
+        let mut seen = false;
+        while Instant::now() < deadline {
+            let has_output = self.sessions.has_output(id);
+            if seen && has_output {
+                break;
+            }
+            seen |= has_output;
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let (output, pending_bytes, dropped_bytes, exited) =
+            self.sessions.take_output(id, max_bytes)?;
+        Ok(json!({
+            "session_id": id,
+            "output": strip_terminal_codes(&output),
+            "pending_bytes": pending_bytes,
+            "dropped_bytes": dropped_bytes,
+            "exited": exited,
+        }))
+    }
+}
+
+pub struct CloseSessionTool {
+    sessions: Arc<PtySessions>,
+}
+
+impl CloseSessionTool {
+    pub fn new(sessions: Arc<PtySessions>) -> Self {
+        Self { sessions }
+    }
+}
+
+#[async_trait]
+impl Tool for CloseSessionTool {
+    fn name(&self) -> &'static str {
+        "close_session"
+    }
+
+    fn description(&self) -> &'static str {
+        "Close a terminal session, stopping its program if it is still running."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "End a session.",
+            "properties": {
+                "session_id": {
+                    "type": "string",
+                    "description": "Session id from open_session."
+                }
+            },
+            "required": ["session_id"],
+            "examples": [{ "session_id": "session-1" }]
+        })
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        self.sessions.close(session_id(&input)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        strip_terminal_codes, CloseSessionTool, OpenSessionTool, PtySessions, ReadOutputTool,
+        SendInputTool,
+    };
+    use crate::tool::Tool;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn session_keeps_shell_state_between_inputs() {
+        let sessions = Arc::new(PtySessions::default());
+        let opened = OpenSessionTool::new(Arc::clone(&sessions))
+            .execute(json!({ "command": "sh" }))
+            .await
+            .unwrap();
+        let id = opened["session_id"].as_str().unwrap().to_string();
+        let send = SendInputTool::new(Arc::clone(&sessions));
+        send.execute(json!({ "session_id": id, "input": "GREETING=hello-pty" }))
+            .await
+            .unwrap();
+        send.execute(json!({ "session_id": id, "input": "echo \"got:$GREETING\"" }))
+            .await
+            .unwrap();
+
+        let read = ReadOutputTool::new(Arc::clone(&sessions));
+        let mut output = String::new();
+        for _ in 0..20 {
+            let chunk = read
+                .execute(json!({ "session_id": id, "wait_ms": 200 }))
+                .await
+                .unwrap();
+            output.push_str(chunk["output"].as_str().unwrap());
+            if output.contains("got:hello-pty") {
+                break;
+            }
+        }
+        assert!(output.contains("got:hello-pty"), "{}", output);
+
+        let close = CloseSessionTool::new(Arc::clone(&sessions));
+        assert!(!read.side_effecting(&json!({ "session_id": id })));
+        assert!(close.side_effecting(&json!({ "session_id": id })));
+        let closed = close.execute(json!({ "session_id": id })).await.unwrap();
+        assert_eq!(closed["closed"], true);
+        assert!(read.execute(json!({ "session_id": id })).await.is_err());
+
+        assert_eq!(
+            strip_terminal_codes("\x1b[1;32mok\x1b[0m\r\n\x1b]0;title\x07$ "),
+            "ok\n$ "
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use crate::config::SearchConfig;
+use crate::tool::Tool;
+use crate::tools::glob::glob_match;
+use crate::tools::workspace::{to_slash, Workspace};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const DEFAULT_MAX_RESULTS: usize = 200;
+
+/// Excluded everywhere: VCS metadata, OS litter, and minified or generated
+/// web assets.
+const COMMON_IGNORES: &[&str] = &[
+    ".git/",
+    ".hg/",
+    ".svn/",
+    ".DS_Store",
+    "*.min.js",
+    "*.min.css",
+    "*.map",
+];
+
+/// Per-language build output, vendored dependencies, and lockfiles. A
+/// language applies when one of its marker files sits at the workspace root.
+const LANGUAGE_IGNORES: &[(&str, &[&str], &[&str])] = &[
+    ("rust", &["Cargo.toml"], &["target/", "Cargo.lock"]),
+    (
+        "node",
+        &["package.json"],
+        &[
+            "node_modules/",
+            "dist/",
+            "build/",
+            ".next/",
+            "coverage/",
+            "package-lock.json",
+            "pnpm-lock.yaml",
+            "yarn.lock",
+        ],
+    ),
+    (
+        "python",
+        &["pyproject.toml", "setup.py", "requirements.txt"],
+        &[
+            "__pycache__/",
+            ".venv/",
+            "venv/",
+            ".tox/",
+            ".mypy_cache/",
+            ".pytest_cache/",
+            "*.egg-info/",
+            "*.pyc",
+            "poetry.lock",
+            "uv.lock",
+        ],
+    ),
+    ("go", &["go.mod"], &["vendor/", "go.sum"]),
+    (
+        "java",
+        &["pom.xml", "build.gradle", "build.gradle.kts"],
+        &["target/", "build/", ".gradle/", "*.class"],
+    ),
+];
+
+/// Paths hidden from search tools. Patterns ending in `/` match directories
+/// only; patterns without another `/` match a file or directory name at any
+/// depth; other patterns match the whole path relative to the workspace
+/// root. `*` and `?` stay within one segment and `**` spans segments.
+#[derive(Debug, Clone, Default)]
+pub struct SearchIgnore {
+    exclude: Vec<String>,
+    include: Vec<String>,
+    languages: Vec<String>,
+}
+
+impl SearchIgnore {
+    /// Builds the rule set from `[search]`, detecting languages from marker
+    /// files in `root` unless `languages` is configured.
+    pub fn from_config(root: &Path, config: Option<&SearchConfig>) -> Self {
+        let languages: Vec<String> = match config.and_then(|cfg| cfg.languages.clone()) {
+            Some(languages) => languages,
+            None => LANGUAGE_IGNORES
+                .iter()
+                .filter(|(_, markers, _)| markers.iter().any(|marker| root.join(marker).exists()))
+                .map(|(language, _, _)| language.to_string())
+                .collect(),
+        };
+
+        let mut exclude = Vec::new();
+        if config.and_then(|cfg| cfg.defaults).unwrap_or(true) {
+            exclude.extend(COMMON_IGNORES.iter().map(|pattern| pattern.to_string()));
+            for (language, _, patterns) in LANGUAGE_IGNORES {
+                if languages.iter().any(|name| name == language) {
+                    exclude.extend(patterns.iter().map(|pattern| pattern.to_string()));
+                }
+            }
+        }
+        if let Some(extra) = config.and_then(|cfg| cfg.exclude.as_ref()) {
+            exclude.extend(extra.iter().cloned());
+        }
+        exclude.dedup();
+
+        Self {
+            exclude,
+            include: config
+                .and_then(|cfg| cfg.include.clone())
+                .unwrap_or_default(),
+            languages,
+        }
+    }
+
+    pub fn languages(&self) -> &[String] {
+        &self.languages
+    }
+
+    /// Exclusions safe to hand to an external search tool as-is. Empty when
+    /// `[search].include` re-includes paths, since a pruned directory could
+    /// not be re-entered.
+    pub fn external_excludes(&self) -> &[String] {
+        if self.include.is_empty() {
+            &self.exclude
+        } else {
+            &[]
+        }
+    }
+
+    /// Whether the file `relative` or any directory above it is hidden.
+    pub fn hides_file(&self, relative: &Path) -> bool {
+        relative
+            .ancestors()
+            .skip(1)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .any(|dir| self.is_ignored(dir, true))
+            || self.is_ignored(relative, false)
+    }
+
+    /// Whether `relative` (a path under the workspace root) is hidden.
+    /// `include` patterns win over excludes.
+    pub fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        let path = relative.to_string_lossy().replace('\\', "/");
+        let matches = |pattern: &String| pattern_matches(pattern, &path, is_dir);
+        self.exclude.iter().any(matches) && !self.include.iter().any(matches)
+    }
+}
+
+/// Whether one ignore pattern (see [`SearchIgnore`]) matches `path`.
+pub fn pattern_matches(pattern: &str, path: &str, is_dir: bool) -> bool {
+    let (pattern, dir_only) = match pattern.strip_suffix('/') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    if dir_only && !is_dir {
+        return false;
+    }
+    if pattern.contains('/') {
+        glob_match(pattern.trim_start_matches('/'), path)
+    } else {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        glob_match(pattern, name)
+    }
+}
+
+/// Whether a glob given to a search tool matches `relative`: patterns without
+/// `/` match the file name, others the whole relative path.
+pub fn path_glob_matches(pattern: &str, relative: &str) -> bool {
+    pattern_matches(pattern, relative, false)
+}
+
+pub struct FindFilesTool {
+    workspace: Arc<Workspace>,
+}
+
+impl FindFilesTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for FindFilesTool {
+    fn name(&self) -> &'static str {
+        "find_files"
+    }
+
+    fn description(&self) -> &'static str {
+        "Recursively find files whose path matches a glob. Build output, vendored dependencies, lockfiles, and minified assets are skipped by default; set include_ignored to search them too."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Glob search below a directory.",
+            "properties": {
+                "pattern": {
+                    "type": "string",
+                    "description": "Glob such as '*.rs' (matches file names) or 'src/**/mod.rs' (matches paths relative to `path`)."
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Directory to search. Defaults to current directory."
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Maximum paths returned. Defaults to 200."
+                },
+                "include_ignored": {
+                    "type": "boolean",
+                    "description": "Also search paths hidden by the [search] ignore rules."
+                }
+            },
+            "required": ["pattern"],
+            "examples": [
+                { "pattern": "*.rs" },
+                { "pattern": "**/tests/*.py", "path": "services" },
+                { "pattern": "package.json", "max_results": 50 }
+            ]
+        })
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let pattern = input
+            .get("pattern")
+            .and_then(|v| v.as_str())
+            .filter(|pattern| !pattern.trim().is_empty())
+            .ok_or_else(|| anyhow!("missing pattern"))?
+            .to_string();
+        let path = input.get("path").and_then(|v| v.as_str()).unwrap_or(".");
+        let max_results = input
+            .get("max_results")
+            .and_then(|v| v.as_u64())
+            .map(|n| n as usize)
+            .unwrap_or(DEFAULT_MAX_RESULTS);
+        let include_ignored = input
+            .get("include_ignored")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let dir = self.workspace.resolve(path)?;
+        let workspace = Arc::clone(&self.workspace);
+        let walk = tokio::task::spawn_blocking(move || {
+            walk_files(&workspace, &dir, include_ignored, max_results, |relative| {
+                pattern_matches(&pattern, relative, false)
+            })
+        })
+        .await
+        .context("find_files task failed")?;
+
+        Ok(json!({
+            "files": walk.files,
+            "truncated": walk.truncated,
+            "ignored_entries": walk.skipped
+        }))
+    }
+}
+
+/// Files found below a directory, as paths relative to it.
+pub struct WalkResult {
+    pub files: Vec<String>,
+    /// Entries hidden by the ignore rules.
+    pub skipped: usize,
+    /// Whether `max_results` cut the walk short.
+    pub truncated: bool,
+}
+
+/// Walks `start` in sorted order, honoring the workspace ignore rules unless
+/// `include_ignored`, and keeps files whose relative path satisfies
+/// `matches`.
+pub fn walk_files(
+    workspace: &Workspace,
+    start: &Path,
+    include_ignored: bool,
+    max_results: usize,
+    matches: impl Fn(&str) -> bool,
+) -> WalkResult {
+    let mut walk = Walk {
+        workspace,
+        start,
+        include_ignored,
+        max_results,
+        matches: &matches,
+        files: Vec::new(),
+        skipped: 0,
+        truncated: false,
+    };
+    walk.visit(start);
+    WalkResult {
+        files: walk.files,
+        skipped: walk.skipped,
+        truncated: walk.truncated,
+    }
+}
+
+struct Walk<'a> {
+    workspace: &'a Workspace,
+    start: &'a Path,
+    include_ignored: bool,
+    max_results: usize,
+    matches: &'a dyn Fn(&str) -> bool,
+    files: Vec<String>,
+    skipped: usize,
+    truncated: bool,
+}
+
+impl Walk<'_> {
+    fn visit(&mut self, dir: &Path) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut entries: Vec<(PathBuf, bool)> = entries
+            .flatten()
+            .filter_map(|entry| Some((entry.path(), entry.file_type().ok()?.is_dir())))
+            .collect();
+        entries.sort();
+        for (path, is_dir) in entries {
+            if self.truncated {
+                return;
+            }
+            let relative_to_root = path
+                .strip_prefix(self.workspace.root())
+                .unwrap_or(&path)
+                .to_path_buf();
+            if !self.include_ignored
+                && self
+                    .workspace
+                    .search_ignore()
+                    .is_ignored(&relative_to_root, is_dir)
+            {
+                self.skipped += 1;
+                continue;
+            }
+            if is_dir {
+                self.visit(&path);
+                continue;
+            }
+            let relative = to_slash(path.strip_prefix(self.start).unwrap_or(&path));
+            if (self.matches)(&relative) {
+                if self.files.len() >= self.max_results {
+                    self.truncated = true;
+                    return;
+                }
+                self.files.push(relative);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SearchIgnore;
+    use crate::config::SearchConfig;
+    use std::path::Path;
+
+    #[test]
+    fn language_defaults_apply_and_include_overrides_them() {
+        let config = SearchConfig {
+            languages: Some(vec!["rust".to_string(), "node".to_string()]),
+            exclude: Some(vec!["fixtures/big/".to_string()]),
+            include: Some(vec!["dist/".to_string()]),
+            defaults: None,
+        };
+        let ignore = SearchIgnore::from_config(Path::new("/nonexistent"), Some(&config));
+
+        assert!(ignore.is_ignored(Path::new("target"), true));
+        assert!(ignore.is_ignored(Path::new("web/node_modules"), true));
+        assert!(ignore.is_ignored(Path::new("Cargo.lock"), false));
+        assert!(ignore.is_ignored(Path::new("web/app.min.js"), false));
+        assert!(ignore.is_ignored(Path::new("fixtures/big"), true));
+        assert!(!ignore.is_ignored(Path::new("dist"), true));
+        assert!(!ignore.is_ignored(Path::new("src/target.rs"), false));
+        assert!(!ignore.is_ignored(Path::new("venv"), true));
+
+        let no_defaults = SearchIgnore::from_config(
+            Path::new("/nonexistent"),
+            Some(&SearchConfig {
+                defaults: Some(false),
+                ..config
+            }),
+        );
+        assert!(!no_defaults.is_ignored(Path::new("target"), true));
+    }
+}
@@ -0,0 +1,108 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Platform shell for a command string: `sh -c` on Unix. On Windows,
+/// PowerShell (`pwsh`, then Windows PowerShell) with `-NoProfile
+/// -NonInteractive -Command`, or `cmd /C` when neither is on `PATH`.
+pub fn shell_command(script: &str) -> Command {
+    if cfg!(windows) {
+        match ["pwsh", "powershell"]
+            .into_iter()
+            .find_map(|shell| find_program(shell, &path_dirs(), &windows_extensions()))
+        {
+            Some(powershell) => {
+                let mut command = Command::new(powershell);
+                command.args(["-NoProfile", "-NonInteractive", "-Command", script]);
+                command
+            }
+            None => {
+                let mut command = Command::new("cmd");
+                command.arg("/C").arg(script);
+                command
+            }
+        }
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        command
+    }
+}
+
+/// Shell `open_session` starts when no command is given.
+pub fn default_interactive_shell() -> &'static str {
+    if cfg!(windows) {
+        "powershell"
+    } else {
+        "bash"
+    }
+}
+
+/// The program to spawn for `command`. On Windows a bare name is looked up
+/// on `PATH` with the `PATHEXT` extensions, so `npm` finds `npm.cmd`, which
+/// spawning the name alone would miss (only `.exe` is tried). Elsewhere, and
+/// when nothing is found, `command` is returned as is.
+pub fn resolve_program(command: &str) -> OsString {
+    if cfg!(windows) && Path::new(command).extension().is_none() {
+        if let Some(found) = find_program(command, &path_dirs(), &windows_extensions()) {
+            return found.into_os_string();
+        }
+    }
+    OsString::from(command)
+}
+
+fn path_dirs() -> Vec<PathBuf> {
+    std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default()
+}
+
+fn windows_extensions() -> Vec<String> {
+    std::env::var("PATHEXT")
+        .unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string())
+        .split(';')
+        .filter(|extension| !extension.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect()
+}
+
+/// First `<dir>/<name><extension>` that is a file, in `dirs` order.
+fn find_program(name: &str, dirs: &[PathBuf], extensions: &[String]) -> Option<PathBuf> {
+    if name.contains(['/', '\\']) {
+        return None;
+    }
+    dirs.iter()
+        .flat_map(|dir| {
+            extensions
+                .iter()
+                .map(move |extension| dir.join(format!("{}{}", name, extension)))
+        })
+        .find(|candidate| candidate.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_program, shell_command};
+    use crate::testing::TempDir;
+
+    #[test]
+    fn programs_are_found_with_their_extension() {
+        let dir = TempDir::new("shell");
+        std::fs::write(dir.join("npm.cmd"), "@echo off\n").unwrap();
+        let extensions = vec![".exe".to_string(), ".cmd".to_string()];
+        let dirs = vec![dir.join("missing"), dir.to_path_buf()];
+        assert_eq!(
+            find_program("npm", &dirs, &extensions),
+            Some(dir.join("npm.cmd"))
+        );
+        assert_eq!(find_program("yarn", &dirs, &extensions), None);
+        assert_eq!(find_program("bin/npm", &dirs, &extensions), None);
+    }
+
+    #[tokio::test]
+    async fn shell_command_runs_a_script() {
+        let output = shell_command("echo rx-shell").output().await.unwrap();
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("rx-shell"));
+    }
+}
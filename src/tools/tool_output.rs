@@ -0,0 +1,275 @@
+use crate::artifacts::ArtifactStore;
+use crate::state::StateStore;
+use crate::tool::Tool;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+
+/// Default `[tools].max_output_bytes`.
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 16 * 1024;
+
+/// Bytes `read_tool_output` returns per call unless `max_bytes` is passed.
+const DEFAULT_PAGE_BYTES: usize = 8 * 1024;
+
+/// Upper bound on `max_bytes` for one `read_tool_output` call.
+const MAX_PAGE_BYTES: usize = 64 * 1024;
+
+/// Fits an output over `max_bytes` of JSON for the model: long strings keep
+/// their head and tail around an omission marker, and an output that is
+/// still too big is replaced by the head and tail of its JSON. The result
+/// carries `output_truncated` with the full size and how to read the rest.
+/// Returns `None` when the output already fits.
+pub fn limit_output(output: &Value, max_bytes: usize, tool_call_id: &str) -> Option<Value> {
+    let bytes = output.to_string().len();
+    if bytes <= max_bytes {
+        return None;
+    }
+    let mut excerpt = output.clone();
+    clip_strings(&mut excerpt, max_bytes / 2);
+    if excerpt.to_string().len() > max_bytes {
+        let text = output.to_string();
+        excerpt = json!({
+            "head": head(&text, max_bytes / 4),
+            "tail": tail(&text, max_bytes / 4),
+        });
+    }
+    let notice = json!({
+        "bytes": bytes,
+        "tool_call_id": tool_call_id,
+        "details": "this output was shortened; call read_tool_output with this tool_call_id to page through the full output",
+    });
+    Some(match excerpt {
+        Value::Object(mut fields) => {
+            fields.insert("output_truncated".to_string(), notice);
+            Value::Object(fields)
+        }
+        other => json!({ "output": other, "output_truncated": notice }),
+    })
+}
+
+/// Cuts every string longer than `limit` bytes to its head and tail.
+fn clip_strings(value: &mut Value, limit: usize) {
+    match value {
+        Value::String(text) if text.len() > limit => {
+            let omitted = text.len() - 2 * (limit / 2);
+            *text = format!(
+                "{}\n[... {} bytes omitted ...]\n{}",
+                head(text, limit / 2),
+                omitted,
+                tail(text, limit / 2)
+            );
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| clip_strings(item, limit)),
+        Value::Object(fields) => fields
+            .values_mut()
+            .for_each(|field| clip_strings(field, limit)),
+        _ => {}
+    }
+}
+
+fn head(text: &str, bytes: usize) -> &str {
+    let mut end = bytes.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+fn tail(text: &str, bytes: usize) -> &str {
+    let mut start = text.len().saturating_sub(bytes);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+/// Plain text of a stored output: strings as they are, and object fields
+/// under a `[name]` header, so command output reads without JSON escapes.
+fn render_text(output: &Value) -> String {
+    match output {
+        Value::String(text) => text.clone(),
+        Value::Object(fields) => render_fields(fields),
+        other => other.to_string(),
+    }
+}
+
+fn render_fields(fields: &Map<String, Value>) -> String {
+    fields
+        .iter()
+        .map(|(name, value)| match value {
+            Value::String(text) => format!("[{}]\n{}\n", name, text),
+            other => format!("[{}] {}\n", name, other),
+        })
+        .collect()
+}
+
+/// Pages through a tool output the kernel shortened for the model, read
+/// from the artifact the `tool_output_full` event recorded next to it
+/// points to (or from the event itself when the output is inline).
+pub struct ReadToolOutputTool {
+    state_store: Arc<dyn StateStore>,
+    artifacts: Arc<ArtifactStore>,
+}
+
+impl ReadToolOutputTool {
+    pub fn new(state_store: Arc<dyn StateStore>, artifacts: Arc<ArtifactStore>) -> Self {
+        Self {
+            state_store,
+            artifacts,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for ReadToolOutputTool {
+    fn name(&self) -> &'static str {
+        "read_tool_output"
+    }
+
+    fn description(&self) -> &'static str {
+        "Read the full text of a tool output that was shortened (it has an output_truncated field), a page at a time. Pass the tool_call_id from output_truncated and continue from next_offset until it is null."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Page through a shortened tool output.",
+            "properties": {
+                "tool_call_id": {
+                    "type": "string",
+                    "description": "tool_call_id named in output_truncated"
+                },
+                "offset": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "Byte offset into the output text (default 0)"
+                },
+                "max_bytes": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "maximum": MAX_PAGE_BYTES,
+                    "description": "Bytes to return (default 8192)"
+                }
+            },
+            "required": ["tool_call_id"],
+            "examples": [{ "tool_call_id": "call_abc123", "offset": 8192 }]
+        })
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let tool_call_id = input["tool_call_id"].as_str().unwrap_or_default();
+        let offset = input["offset"].as_u64().unwrap_or(0) as usize;
+        let max_bytes = input["max_bytes"]
+            .as_u64()
+            .map_or(DEFAULT_PAGE_BYTES, |bytes| bytes as usize)
+            .min(MAX_PAGE_BYTES);
+        let events = self.state_store.load().await?;
+        let Some(full) = events.iter().rev().find(|event| {
+            event.r#type == "tool_output_full" && event.payload["tool_call_id"] == tool_call_id
+        }) else {
+            return Ok(json!({
+                "error": "not_found",
+                "details": format!("no shortened output is stored for tool_call_id {}", tool_call_id),
+            }));
+        };
+        let output = match full.payload["artifact"].as_str() {
+            Some(artifact) => match self.artifacts.get_json(artifact).await? {
+                Some(output) => output,
+                None => {
+                    return Ok(json!({
+                        "error": "not_found",
+                        "details": format!("artifact {} of tool_call_id {} is missing", artifact, tool_call_id),
+                    }))
+                }
+            },
+            None => full.payload["output"].clone(),
+        };
+        let text = render_text(&output);
+        if offset > text.len() {
+            return Ok(json!({
+                "error": "invalid_offset",
+                "details": format!("offset {} is past the end of the output ({} bytes)", offset, text.len()),
+            }));
+        }
+        let rest = tail(&text, text.len() - offset);
+        let content = head(rest, max_bytes);
+        let end = text.len() - rest.len() + content.len();
+        Ok(json!({
+            "tool_call_id": tool_call_id,
+            "offset": text.len() - rest.len(),
+            "bytes": text.len(),
+            "content": content,
+            "next_offset": (end < text.len()).then_some(end),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{limit_output, ReadToolOutputTool};
+    use crate::artifacts::ArtifactStore;
+    use crate::event::Event;
+    use crate::state::{JsonlStateStore, StateStore};
+    use crate::testing::TempDir;
+    use crate::tool::Tool;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn big_outputs_are_shortened_and_paged_back() {
+        let stdout = format!("{}{}", "a".repeat(3_000), "z".repeat(3_000));
+        let output = json!({ "exit_code": 1, "stdout": stdout, "stderr": "boom" });
+        assert!(limit_output(&output, 10_000, "call-1").is_none());
+
+        let short = limit_output(&output, 1_000, "call-1").unwrap();
+        assert!(short.to_string().len() < 1_500);
+        assert_eq!(short["exit_code"], 1);
+        assert_eq!(short["stderr"], "boom");
+        let clipped = short["stdout"].as_str().unwrap();
+        assert!(clipped.starts_with("aaa") && clipped.ends_with("zzz"));
+        assert!(clipped.contains("bytes omitted"));
+        assert_eq!(short["output_truncated"]["tool_call_id"], "call-1");
+
+        let many = json!((0..500).map(|n| n.to_string()).collect::<Vec<_>>());
+        let short = limit_output(&many, 200, "call-2").unwrap();
+        assert!(short["head"].as_str().unwrap().starts_with("[\"0\""));
+
+        let dir = TempDir::new("tool-output");
+        let store = Arc::new(JsonlStateStore::new(&dir, "g1").await.unwrap());
+        let artifacts = Arc::new(ArtifactStore::new(&dir));
+        let artifact = artifacts.put_json(&output).await.unwrap();
+        store
+            .append_event(Event::new(
+                "tool_output_full",
+                json!({ "tool_call_id": "call-1", "name": "exec", "artifact": artifact }),
+            ))
+            .await
+            .unwrap();
+        let tool = ReadToolOutputTool::new(store, artifacts);
+        let page = tool
+            .execute(json!({ "tool_call_id": "call-1", "max_bytes": 4_000 }))
+            .await
+            .unwrap();
+        assert!(page["content"]
+            .as_str()
+            .unwrap()
+            .starts_with("[exit_code] 1\n"));
+        let next = page["next_offset"].as_u64().unwrap();
+        let rest = tool
+            .execute(json!({ "tool_call_id": "call-1", "offset": next }))
+            .await
+            .unwrap();
+        assert!(rest["content"].as_str().unwrap().ends_with("zzz\n"));
+        assert!(rest["next_offset"].is_null());
+        let missing = tool
+            .execute(json!({ "tool_call_id": "call-9" }))
+            .await
+            .unwrap();
+        assert_eq!(missing["error"], "not_found");
+    }
+}
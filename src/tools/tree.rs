@@ -0,0 +1,421 @@
+use crate::tool::Tool;
+use crate::tools::search::pattern_matches;
+use crate::tools::workspace::{to_slash, Workspace};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Map, Value};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+const DEFAULT_MAX_DEPTH: usize = 3;
+const DEFAULT_MAX_ENTRIES: usize = 500;
+
+/// Entries counted below one call; sizes and counts past it are partial.
+const MAX_WALKED: usize = 100_000;
+
+/// Nested view of a directory with file sizes and per-directory totals, so
+/// the model can orient itself in one call instead of many `list_dir`s.
+/// Honors `.gitignore` files and the `[search]` ignore rules.
+pub struct DirectoryTreeTool {
+    workspace: Arc<Workspace>,
+}
+
+impl DirectoryTreeTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for DirectoryTreeTool {
+    fn name(&self) -> &'static str {
+        "directory_tree"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show the directory tree below a path up to max_depth, with file sizes and, for each directory, its total size and file and directory counts. Skips .gitignore'd paths, build output, and vendored dependencies. Use it first to get oriented in a repository."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Recursive directory listing with sizes and counts.",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Directory to show. Defaults to the current directory."
+                },
+                "max_depth": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "description": "Levels of entries listed (default 3); deeper directories are summarized by their counts."
+                },
+                "max_entries": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "description": "Most entries listed in total (default 500)."
+                },
+                "format": {
+                    "type": "string",
+                    "enum": ["json", "text"],
+                    "description": "`json` (default) for nested objects, `text` for an indented listing."
+                },
+                "include_ignored": {
+                    "type": "boolean",
+                    "description": "Also show paths hidden by .gitignore and the [search] ignore rules."
+                }
+            },
+            "examples": [
+                {},
+                { "path": "src", "max_depth": 2 },
+                { "format": "text", "max_depth": 4 }
+            ]
+        })
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let path = input.get("path").and_then(|v| v.as_str()).unwrap_or(".");
+        let number = |field: &str, default: usize| {
+            input
+                .get(field)
+                .and_then(|v| v.as_u64())
+                .map_or(default, |n| (n as usize).max(1))
+        };
+        let max_depth = number("max_depth", DEFAULT_MAX_DEPTH);
+        let max_entries = number("max_entries", DEFAULT_MAX_ENTRIES);
+        let text = input.get("format").and_then(|v| v.as_str()) == Some("text");
+        let include_ignored = input
+            .get("include_ignored")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let dir = self.workspace.resolve(path)?;
+        if !dir.is_dir() {
+            return Err(anyhow!("'{}' is not a directory", path));
+        }
+        let workspace = Arc::clone(&self.workspace);
+        let (tree, walk) = tokio::task::spawn_blocking(move || {
+            let mut walk = TreeWalk {
+                workspace: &workspace,
+                include_ignored,
+                max_depth,
+                max_entries,
+                listed: 0,
+                walked: 0,
+                ignored: 0,
+                truncated: false,
+            };
+            let rules = if include_ignored {
+                Vec::new()
+            } else {
+                gitignore_rules_above(workspace.root(), &dir)
+            };
+            let name = workspace.display_path(&dir);
+            let tree = walk.visit(&dir, name, 0, rules);
+            (
+                tree,
+                (walk.listed, walk.ignored, walk.truncated, walk.walked),
+            )
+        })
+        .await
+        .context("directory_tree task failed")?;
+        let (listed, ignored, truncated, walked) = walk;
+
+        let mut output = json!({
+            "entries": listed,
+            "ignored_entries": ignored,
+            "truncated": truncated,
+            "partial_counts": walked >= MAX_WALKED,
+        });
+        output["tree"] = if text {
+            let mut rendered = String::new();
+            render_text(&tree, 0, &mut rendered);
+            json!(rendered)
+        } else {
+            tree.to_json()
+        };
+        Ok(output)
+    }
+}
+
+struct Node {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    files: usize,
+    dirs: usize,
+    /// `None` past `max_depth` or once `max_entries` are listed.
+    children: Option<Vec<Node>>,
+}
+
+impl Node {
+    fn to_json(&self) -> Value {
+        let mut fields = Map::new();
+        fields.insert("name".to_string(), json!(self.name));
+        fields.insert(
+            "type".to_string(),
+            json!(if self.is_dir { "dir" } else { "file" }),
+        );
+        fields.insert("size".to_string(), json!(self.size));
+        if self.is_dir {
+            fields.insert("files".to_string(), json!(self.files));
+            fields.insert("dirs".to_string(), json!(self.dirs));
+            if let Some(children) = &self.children {
+                fields.insert(
+                    "children".to_string(),
+                    children.iter().map(Node::to_json).collect(),
+                );
+            }
+        }
+        Value::Object(fields)
+    }
+}
+
+fn render_text(node: &Node, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    if node.is_dir {
+        let _ = writeln!(
+            out,
+            "{}{}/ ({} files, {} dirs, {})",
+            indent,
+            node.name,
+            node.files,
+            node.dirs,
+            human_size(node.size)
+        );
+        for child in node.children.iter().flatten() {
+            render_text(child, depth + 1, out);
+        }
+    } else {
+        let _ = writeln!(out, "{}{} ({})", indent, node.name, human_size(node.size));
+    }
+}
+
+fn human_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+/// A line of a `.gitignore`, with the directory it applies below.
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    /// Directory of the `.gitignore`, relative to the workspace root.
+    base: String,
+    pattern: String,
+    negated: bool,
+}
+
+impl IgnoreRule {
+    fn matches(&self, relative: &str, is_dir: bool) -> bool {
+        let below_base = if self.base.is_empty() {
+            Some(relative)
+        } else {
+            relative
+                .strip_prefix(self.base.as_str())
+                .and_then(|rest| rest.strip_prefix('/'))
+        };
+        below_base.is_some_and(|path| pattern_matches(&self.pattern, path, is_dir))
+    }
+}
+
+/// The rules of `dir/.gitignore`; `base` is `dir` relative to the root.
+fn read_gitignore(dir: &Path, base: &str) -> Vec<IgnoreRule> {
+    let Ok(text) = fs::read_to_string(dir.join(".gitignore")) else {
+        return Vec::new();
+    };
+    text.lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (negated, pattern) = match line.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+            IgnoreRule {
+                base: base.to_string(),
+                pattern: pattern.to_string(),
+                negated,
+            }
+        })
+        .collect()
+}
+
+/// Rules of the `.gitignore` files from the root down to `dir`, inclusive.
+fn gitignore_rules_above(root: &Path, dir: &Path) -> Vec<IgnoreRule> {
+    let Ok(relative) = dir.strip_prefix(root) else {
+        return read_gitignore(dir, "");
+    };
+    let mut rules = read_gitignore(root, "");
+    let mut current = root.to_path_buf();
+    for component in relative.components() {
+        current.push(component);
+        let base = to_slash(current.strip_prefix(root).unwrap_or(&current));
+        rules.extend(read_gitignore(&current, &base));
+    }
+    rules
+}
+
+/// Whether the last rule matching `relative` ignores it.
+fn gitignored(rules: &[IgnoreRule], relative: &str, is_dir: bool) -> bool {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| rule.matches(relative, is_dir))
+        .is_some_and(|rule| !rule.negated)
+}
+
+struct TreeWalk<'a> {
+    workspace: &'a Workspace,
+    include_ignored: bool,
+    max_depth: usize,
+    max_entries: usize,
+    listed: usize,
+    walked: usize,
+    ignored: usize,
+    truncated: bool,
+}
+
+impl TreeWalk<'_> {
+    /// The node of `dir`, listing its entries while `depth < max_depth`.
+    fn visit(&mut self, dir: &Path, name: String, depth: usize, rules: Vec<IgnoreRule>) -> Node {
+        let mut node = Node {
+            name,
+            is_dir: true,
+            size: 0,
+            files: 0,
+            dirs: 0,
+            children: (depth < self.max_depth).then(Vec::new),
+        };
+        let Ok(entries) = fs::read_dir(dir) else {
+            return node;
+        };
+        let mut entries: Vec<(String, bool, u64)> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let meta = entry.metadata().ok()?;
+                Some((
+                    entry.file_name().to_string_lossy().to_string(),
+                    meta.is_dir(),
+                    meta.len(),
+                ))
+            })
+            .collect();
+        entries.sort();
+        for (entry_name, is_dir, len) in entries {
+            if self.walked >= MAX_WALKED {
+                break;
+            }
+            self.walked += 1;
+            let path = dir.join(&entry_name);
+            let relative = path.strip_prefix(self.workspace.root()).unwrap_or(&path);
+            if !self.include_ignored
+                && (self.workspace.search_ignore().is_ignored(relative, is_dir)
+                    || gitignored(&rules, &to_slash(relative), is_dir))
+            {
+                self.ignored += 1;
+                continue;
+            }
+            let listed = node.children.is_some() && self.listed < self.max_entries;
+            if node.children.is_some() && !listed {
+                self.truncated = true;
+            }
+            if listed {
+                self.listed += 1;
+            }
+            let child = if is_dir {
+                let mut child_rules = rules.clone();
+                if !self.include_ignored {
+                    child_rules.extend(read_gitignore(&path, &to_slash(relative)));
+                }
+                let depth = if listed { depth + 1 } else { self.max_depth };
+                let child = self.visit(&path, entry_name, depth, child_rules);
+                node.dirs += 1 + child.dirs;
+                node.files += child.files;
+                child
+            } else {
+                node.files += 1;
+                Node {
+                    name: entry_name,
+                    is_dir: false,
+                    size: len,
+                    files: 0,
+                    dirs: 0,
+                    children: None,
+                }
+            };
+            node.size += child.size;
+            if listed {
+                if let Some(children) = node.children.as_mut() {
+                    children.push(child);
+                }
+            }
+        }
+        node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DirectoryTreeTool;
+    use crate::testing::TempDir;
+    use crate::tool::Tool;
+    use crate::tools::workspace::Workspace;
+    use serde_json::json;
+    use std::fs;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn tree_has_sizes_and_counts_and_skips_gitignored_paths() {
+        let root = TempDir::new("tree");
+        fs::create_dir_all(root.join("src/tools")).unwrap();
+        fs::create_dir_all(root.join("logs")).unwrap();
+        fs::write(root.join(".gitignore"), "logs/\n*.tmp\n!keep.tmp\n").unwrap();
+        fs::write(root.join("README.md"), "hello").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("src/tools/mod.rs"), "pub mod a;").unwrap();
+        fs::write(root.join("src/tools/.gitignore"), "generated.rs\n").unwrap();
+        fs::write(root.join("src/tools/generated.rs"), "x").unwrap();
+        fs::write(root.join("scratch.tmp"), "x").unwrap();
+        fs::write(root.join("keep.tmp"), "x").unwrap();
+        fs::write(root.join("logs/run.log"), "x").unwrap();
+        let tool = DirectoryTreeTool::new(Arc::new(Workspace::new(&root, &root, &[]).unwrap()));
+
+        let output = tool.execute(json!({ "max_depth": 1 })).await.unwrap();
+        let tree = &output["tree"];
+        assert_eq!(tree["name"], ".");
+        let names: Vec<&str> = tree["children"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|child| child["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, [".gitignore", "README.md", "keep.tmp", "src"]);
+        let src = &tree["children"][3];
+        assert_eq!(src["files"], 3);
+        assert_eq!(src["dirs"], 1);
+        assert!(src.get("children").is_none());
+        assert_eq!(output["ignored_entries"], 3);
+
+        let text = tool
+            .execute(json!({ "path": "src", "format": "text" }))
+            .await
+            .unwrap();
+        let text = text["tree"].as_str().unwrap();
+        assert!(text.starts_with("src/ (3 files, 1 dirs,"));
+        assert!(text.contains("\n    mod.rs (10 B)\n"));
+        assert!(!text.contains("generated.rs"));
+
+        let capped = tool.execute(json!({ "max_entries": 2 })).await.unwrap();
+        assert_eq!(capped["entries"], 2);
+        assert_eq!(capped["truncated"], true);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::artifacts::ArtifactStore;
+use crate::snapshot::{restore_snapshots, snapshot_points};
+use crate::state::StateStore;
+use crate::tool::Tool;
+use crate::tools::workspace::Workspace;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Reverts the most recent file change of the current goal from the
+/// snapshots the kernel records before each file tool call.
+pub struct UndoLastChangeTool {
+    workspace: Arc<Workspace>,
+    state_store: Arc<dyn StateStore>,
+    artifacts: Arc<ArtifactStore>,
+}
+
+impl UndoLastChangeTool {
+    pub fn new(
+        workspace: Arc<Workspace>,
+        state_store: Arc<dyn StateStore>,
+        artifacts: Arc<ArtifactStore>,
+    ) -> Self {
+        Self {
+            workspace,
+            state_store,
+            artifacts,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for UndoLastChangeTool {
+    fn name(&self) -> &'static str {
+        "undo_last_change"
+    }
+
+    fn description(&self) -> &'static str {
+        "Restore the files changed by the most recent file tool call (write_file, replace_in_file, apply_patch, ...) to their content before it; files it created are deleted. Call it again to step further back."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Undo the latest file change of this goal.",
+            "properties": {},
+            "examples": [{}]
+        })
+    }
+
+    async fn execute(&self, _input: Value) -> Result<Value> {
+        let events = self.state_store.load().await?;
+        let points = snapshot_points(&events);
+        let Some(last) = points.len().checked_sub(1) else {
+            return Ok(json!({
+                "error": "nothing_to_undo",
+                "details": "no file changes have been recorded for this goal"
+            }));
+        };
+        let mut summary =
+            restore_snapshots(&self.workspace, &self.artifacts, &points[last..]).await?;
+        summary["undone"] = json!(points[last].name);
+        Ok(summary)
+    }
+}
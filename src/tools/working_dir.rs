@@ -0,0 +1,172 @@
+use crate::event::Event;
+use crate::tool::Tool;
+use crate::tools::workspace::Workspace;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Reports the directory relative paths and commands resolve against.
+pub struct GetWorkingDirTool {
+    workspace: Arc<Workspace>,
+}
+
+impl GetWorkingDirTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+/// Moves the working directory of every file and command tool. The kernel
+/// records each move as a `working_dir` event so a resumed goal starts where
+/// it left off.
+pub struct SetWorkingDirTool {
+    workspace: Arc<Workspace>,
+}
+
+impl SetWorkingDirTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for GetWorkingDirTool {
+    fn name(&self) -> &'static str {
+        "get_working_dir"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show the current working directory: relative paths in file tools and commands run by exec resolve against it."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "No arguments.",
+            "properties": {},
+            "examples": [{}]
+        })
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
+    async fn execute(&self, _input: Value) -> Result<Value> {
+        let cwd = self.workspace.working_dir();
+        Ok(json!({
+            "cwd": self.workspace.display_path(&cwd),
+            "path": cwd.display().to_string(),
+        }))
+    }
+}
+
+#[async_trait]
+impl Tool for SetWorkingDirTool {
+    fn name(&self) -> &'static str {
+        "set_working_dir"
+    }
+
+    fn description(&self) -> &'static str {
+        "Change the working directory for all later file tools and commands (like cd). The path is relative to the current working directory and must be a directory inside the workspace. Returns the new directory relative to the workspace root."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "Change the working directory.",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Directory to move to, relative to the current working directory"
+                }
+            },
+            "required": ["path"],
+            "examples": [{ "path": "crates/core" }, { "path": ".." }]
+        })
+    }
+
+    async fn execute(&self, input: Value) -> Result<Value> {
+        let path = input["path"].as_str().unwrap_or_default();
+        let previous = self.workspace.working_dir();
+        let cwd = match self.workspace.set_working_dir(path) {
+            Ok(cwd) => cwd,
+            Err(error) => {
+                let code = match self.workspace.resolve(path) {
+                    Ok(_) => "not_found",
+                    Err(_) => "permission_denied",
+                };
+                return Ok(json!({ "error": code, "details": error.to_string() }));
+            }
+        };
+        Ok(json!({
+            "cwd": self.workspace.display_path(&cwd),
+            "path": cwd.display().to_string(),
+            "previous": self.workspace.display_path(&previous),
+        }))
+    }
+}
+
+/// Moves the working directory back to where the last `working_dir` event
+/// of a resumed goal left it. A directory that is gone is skipped with a
+/// warning.
+pub fn restore_working_dir(workspace: &Workspace, events: &[Event]) {
+    let Some(cwd) = events
+        .iter()
+        .rev()
+        .find(|event| event.r#type == "working_dir")
+        .and_then(|event| event.payload["cwd"].as_str())
+    else {
+        return;
+    };
+    let path = workspace.root().join(cwd);
+    if let Err(error) = workspace.set_working_dir(&path.to_string_lossy()) {
+        warn!("working directory {} not restored: {:#}", cwd, error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{restore_working_dir, GetWorkingDirTool, SetWorkingDirTool};
+    use crate::event::Event;
+    use crate::testing::TempDir;
+    use crate::tool::Tool;
+    use crate::tools::workspace::Workspace;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn working_dir_is_changed_and_restored() {
+        let root = TempDir::new("working-dir");
+        std::fs::create_dir_all(root.join("app/src")).unwrap();
+        let workspace = Arc::new(Workspace::new(&root, &root, &[]).unwrap());
+        let set = SetWorkingDirTool::new(Arc::clone(&workspace));
+        let get = GetWorkingDirTool::new(Arc::clone(&workspace));
+        assert!(!get.side_effecting(&json!({})));
+        assert!(set.side_effecting(&json!({ "path": "app" })));
+
+        let moved = set.execute(json!({ "path": "app" })).await.unwrap();
+        assert_eq!(moved["cwd"], "app");
+        assert_eq!(moved["previous"], ".");
+        let moved = set.execute(json!({ "path": "src" })).await.unwrap();
+        assert_eq!(moved["cwd"], "app/src");
+        assert_eq!(get.execute(json!({})).await.unwrap()["cwd"], "app/src");
+        let missing = set.execute(json!({ "path": "lib" })).await.unwrap();
+        assert_eq!(missing["error"], "not_found");
+        let outside = set.execute(json!({ "path": "../../.." })).await.unwrap();
+        assert_eq!(outside["error"], "permission_denied");
+
+        let resumed = Workspace::new(&root, &root, &[]).unwrap();
+        let events = vec![
+            Event::new("working_dir", json!({ "cwd": "app/src" })),
+            Event::new("working_dir", json!({ "cwd": "app" })),
+        ];
+        restore_working_dir(&resumed, &events);
+        assert_eq!(
+            resumed.working_dir(),
+            root.canonicalize().unwrap().join("app")
+        );
+    }
+}
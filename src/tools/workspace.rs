@@ -0,0 +1,347 @@
+use crate::tools::search::SearchIgnore;
+use anyhow::{anyhow, Context, Result};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// Filesystem boundary for tools. Relative paths resolve against the working
+/// directory, which starts at `base_dir` (the launch directory, or
+/// `--workdir`) and moves with `set_working_dir`; every resolved path must
+/// stay inside `root` (or the narrower `scope`, when set) or one of the
+/// explicitly allowed external paths. Clones share the working directory.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    root: PathBuf,
+    base_dir: PathBuf,
+    cwd: Arc<RwLock<PathBuf>>,
+    allowed_paths: Vec<PathBuf>,
+    scope: Option<PathBuf>,
+    search_ignore: SearchIgnore,
+}
+
+impl Workspace {
+    pub fn new(root: &Path, base_dir: &Path, allowed_paths: &[PathBuf]) -> Result<Self> {
+        let root = strip_verbatim(
+            root.canonicalize()
+                .with_context(|| format!("workspace root {} is not accessible", root.display()))?,
+        );
+        let base_dir = canonicalize_lenient(&normalize(base_dir));
+        let allowed_paths = allowed_paths
+            .iter()
+            .map(|path| canonicalize_lenient(&normalize(&base_dir.join(path))))
+            .collect();
+
+        Ok(Self {
+            root,
+            cwd: Arc::new(RwLock::new(base_dir.clone())),
+            base_dir,
+            allowed_paths,
+            scope: None,
+            search_ignore: SearchIgnore::default(),
+        })
+    }
+
+    /// Narrows tool access to `scope`, a directory inside the root given
+    /// relative to the launch directory.
+    pub fn with_scope(mut self, scope: &Path) -> Result<Self> {
+        let resolved = canonicalize_lenient(&normalize(&self.base_dir.join(scope)));
+        if !resolved.starts_with(&self.root) {
+            return Err(anyhow!(
+                "scope '{}' is outside the workspace root {}",
+                scope.display(),
+                self.root.display()
+            ));
+        }
+        if !resolved.is_dir() {
+            return Err(anyhow!("scope '{}' is not a directory", scope.display()));
+        }
+        self.scope = Some(resolved);
+        Ok(self)
+    }
+
+    /// Lets tools touch `path` (absolute) in addition to the root.
+    pub fn with_allowed_path(mut self, path: &Path) -> Self {
+        self.allowed_paths
+            .push(canonicalize_lenient(&normalize(path)));
+        self
+    }
+
+    /// Sets the paths search tools skip by default.
+    pub fn with_search_ignore(mut self, search_ignore: SearchIgnore) -> Self {
+        self.search_ignore = search_ignore;
+        self
+    }
+
+    pub fn search_ignore(&self) -> &SearchIgnore {
+        &self.search_ignore
+    }
+
+    pub fn scope(&self) -> Option<&Path> {
+        self.scope.as_deref()
+    }
+
+    /// Uses the enclosing git repository as root, falling back to `cwd`.
+    pub fn discover(
+        cwd: &Path,
+        root_override: Option<&Path>,
+        allowed_paths: &[PathBuf],
+    ) -> Result<Self> {
+        let root = match root_override {
+            Some(path) => cwd.join(path),
+            None => find_git_root(cwd).unwrap_or_else(|| cwd.to_path_buf()),
+        };
+        Self::new(&root, cwd, allowed_paths)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Directory relative paths currently resolve against.
+    pub fn working_dir(&self) -> PathBuf {
+        self.cwd
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Moves the working directory to `path` (relative to the current one),
+    /// which must be an existing directory inside the boundary. Returns the
+    /// new working directory.
+    pub fn set_working_dir(&self, path: &str) -> Result<PathBuf> {
+        let resolved = self.resolve(path)?;
+        if !resolved.is_dir() {
+            return Err(anyhow!("'{}' is not a directory", path));
+        }
+        *self
+            .cwd
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = resolved.clone();
+        Ok(resolved)
+    }
+
+    /// `path` relative to the root with `/` separators (`.` for the root
+    /// itself), or absolute when it is outside the root.
+    pub fn display_path(&self, path: &Path) -> String {
+        match path.strip_prefix(&self.root) {
+            Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+            Ok(relative) => to_slash(relative),
+            Err(_) => to_slash(path),
+        }
+    }
+
+    /// Working directory of a spawned command: `cwd` relative to the working
+    /// directory, or the working directory itself.
+    pub fn command_dir(&self, cwd: Option<&str>) -> PathBuf {
+        match cwd {
+            Some(cwd) => self.working_dir().join(cwd),
+            None => self.working_dir(),
+        }
+    }
+
+    pub fn resolve(&self, path: &str) -> Result<PathBuf> {
+        if path.trim().is_empty() {
+            return Err(anyhow!("path cannot be empty"));
+        }
+
+        let resolved = canonicalize_lenient(&normalize(&self.working_dir().join(path)));
+        let boundary = self.scope.as_ref().unwrap_or(&self.root);
+        if resolved.starts_with(boundary)
+            || self
+                .allowed_paths
+                .iter()
+                .any(|allowed| resolved.starts_with(allowed))
+        {
+            return Ok(resolved);
+        }
+
+        if let Some(scope) = &self.scope {
+            return Err(anyhow!(
+                "path '{}' is outside the agent scope {}",
+                path,
+                scope.display()
+            ));
+        }
+        Err(anyhow!(
+            "path '{}' is outside the workspace root {}",
+            path,
+            self.root.display()
+        ))
+    }
+}
+
+fn find_git_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+/// Drops the `\\?\` prefix `canonicalize` puts on Windows paths, so
+/// resolved paths compare with and print like the ones users and models
+/// type. UNC paths keep theirs.
+fn strip_verbatim(path: PathBuf) -> PathBuf {
+    if cfg!(windows) {
+        if let Some(plain) = path
+            .to_str()
+            .and_then(|text| text.strip_prefix(r"\\?\"))
+            .filter(|plain| !plain.starts_with(r"UNC\"))
+        {
+            return PathBuf::from(plain);
+        }
+    }
+    path
+}
+
+/// `path` with `/` separators on every platform, as tool outputs report
+/// paths. On Unix a `\\` is part of a file name and is kept.
+pub fn to_slash(path: &Path) -> String {
+    let text = path.to_string_lossy();
+    if cfg!(windows) {
+        text.replace('\\', "/")
+    } else {
+        text.into_owned()
+    }
+}
+
+/// Canonicalizes the longest existing ancestor so symlinks cannot be used to
+/// escape the root, then re-appends the not-yet-existing tail.
+fn canonicalize_lenient(path: &Path) -> PathBuf {
+    let mut existing = path.to_path_buf();
+    let mut tail = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            let mut out = strip_verbatim(canonical);
+            for part in tail.iter().rev() {
+                out.push(part);
+            }
+            return out;
+        }
+        match (
+            existing.file_name().map(|name| name.to_os_string()),
+            existing.parent(),
+        ) {
+            (Some(name), Some(parent)) => {
+                tail.push(name);
+                existing = parent.to_path_buf();
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Workspace;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("rx-workspace-test-{}-{}", name, nanos));
+        fs::create_dir_all(&dir).expect("should create temp dir");
+        dir
+    }
+
+    #[test]
+    fn resolves_relative_paths_inside_root() {
+        let root = temp_dir("inside");
+        let workspace = Workspace::new(&root, &root, &[]).expect("workspace should build");
+
+        let resolved = workspace
+            .resolve("src/../new.txt")
+            .expect("path should resolve");
+        assert_eq!(resolved, workspace.root().join("new.txt"));
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn rejects_paths_escaping_root() {
+        let root = temp_dir("escape");
+        let workspace = Workspace::new(&root, &root, &[]).expect("workspace should build");
+
+        assert!(workspace.resolve("../outside.txt").is_err());
+        assert!(workspace.resolve("/etc/passwd").is_err());
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn scope_restricts_paths_to_subtree() {
+        let root = temp_dir("scope");
+        fs::create_dir_all(root.join("src/payments")).expect("should create scope dir");
+        let workspace = Workspace::new(&root, &root, &[])
+            .expect("workspace should build")
+            .with_scope(std::path::Path::new("src/payments"))
+            .expect("scope should apply");
+
+        assert!(workspace.resolve("src/payments/ledger.rs").is_ok());
+        let error = workspace
+            .resolve("src/billing/invoice.rs")
+            .expect_err("sibling directory should be rejected");
+        assert!(error.to_string().contains("agent scope"));
+        assert!(Workspace::new(&root, &root, &[])
+            .expect("workspace should build")
+            .with_scope(std::path::Path::new("../elsewhere"))
+            .is_err());
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn working_dir_moves_relative_resolution() {
+        let root = temp_dir("cwd");
+        fs::create_dir_all(root.join("crates/core")).expect("should create subdir");
+        let workspace = Workspace::new(&root, &root, &[]).expect("workspace should build");
+        let shared = workspace.clone();
+
+        let cwd = workspace
+            .set_working_dir("crates/core")
+            .expect("directory should be accepted");
+        assert_eq!(workspace.display_path(&cwd), "crates/core");
+        assert_eq!(
+            shared.resolve("lib.rs").expect("path should resolve"),
+            cwd.join("lib.rs")
+        );
+        assert_eq!(shared.command_dir(None), cwd);
+        assert!(workspace.set_working_dir("../../..").is_err());
+        assert!(workspace.set_working_dir("missing").is_err());
+        workspace.set_working_dir("../..").expect("root is allowed");
+        assert_eq!(workspace.display_path(&workspace.working_dir()), ".");
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn allowed_external_paths_are_accepted() {
+        let root = temp_dir("root");
+        let external = temp_dir("external");
+        let workspace = Workspace::new(&root, &root, std::slice::from_ref(&external))
+            .expect("workspace should build");
+
+        assert!(workspace
+            .resolve(&external.join("notes.txt").to_string_lossy())
+            .is_ok());
+
+        let _ = fs::remove_dir_all(root);
+        let _ = fs::remove_dir_all(external);
+    }
+}
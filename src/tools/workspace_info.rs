@@ -0,0 +1,427 @@
+use crate::tool::Tool;
+use crate::tools::glob::glob_match;
+use crate::tools::workspace::{to_slash, Workspace};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Directories never searched for Bazel packages.
+const SKIPPED_DIRS: [&str; 5] = [".git", "node_modules", "target", "bazel-out", "dist"];
+const MAX_BAZEL_DEPTH: usize = 6;
+const MAX_PROJECTS: usize = 500;
+
+pub struct WorkspaceInfoTool {
+    workspace: Arc<Workspace>,
+}
+
+impl WorkspaceInfoTool {
+    pub fn new(workspace: Arc<Workspace>) -> Self {
+        Self { workspace }
+    }
+}
+
+#[async_trait]
+impl Tool for WorkspaceInfoTool {
+    fn name(&self) -> &'static str {
+        "workspace_info"
+    }
+
+    fn description(&self) -> &'static str {
+        "Describe monorepo structure: Cargo workspaces, pnpm/npm workspaces, and Bazel packages with each project's name and path. Use this to map a goal like 'fix the failing package' to the right subproject."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "description": "No arguments; inspects the workspace root.",
+            "properties": {},
+            "examples": [{}]
+        })
+    }
+
+    fn side_effecting(&self, _input: &Value) -> bool {
+        false
+    }
+
+    async fn execute(&self, _input: Value) -> Result<Value> {
+        Ok(detect_workspace(self.workspace.root()))
+    }
+}
+
+/// Detects workspace manifests under `root`. Returns
+/// `{root, systems: [{kind, manifest, projects: [{name, path}]}]}`.
+pub fn detect_workspace(root: &Path) -> Value {
+    let mut systems = Vec::new();
+    if let Some(system) = detect_cargo(root) {
+        systems.push(system);
+    }
+    if let Some(system) = detect_pnpm(root) {
+        systems.push(system);
+    }
+    if let Some(system) = detect_npm(root) {
+        systems.push(system);
+    }
+    if let Some(system) = detect_bazel(root) {
+        systems.push(system);
+    }
+    json!({ "root": root.display().to_string(), "systems": systems })
+}
+
+/// Compact prompt section listing detected projects, or `None` for a
+/// single-project repository.
+pub fn workspace_context(info: &Value) -> Option<String> {
+    let systems = info.get("systems")?.as_array()?;
+    if systems.is_empty() {
+        return None;
+    }
+    let mut out = String::from("## Workspace projects\n\n");
+    for system in systems {
+        let projects = system
+            .get("projects")
+            .and_then(|value| value.as_array())
+            .cloned()
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "- {} ({}): {} projects\n",
+            system["kind"].as_str().unwrap_or("?"),
+            system["manifest"].as_str().unwrap_or("?"),
+            projects.len()
+        ));
+        for project in projects.iter().take(40) {
+            out.push_str(&format!(
+                "  - {} — {}\n",
+                project["name"].as_str().unwrap_or("?"),
+                project["path"].as_str().unwrap_or("?")
+            ));
+        }
+        if projects.len() > 40 {
+            out.push_str("  - … call `workspace_info` for the full list\n");
+        }
+    }
+    Some(out)
+}
+
+fn detect_cargo(root: &Path) -> Option<Value> {
+    let manifest: toml::Value = fs::read_to_string(root.join("Cargo.toml"))
+        .ok()?
+        .parse()
+        .ok()?;
+    let workspace = manifest.get("workspace")?;
+    let patterns = string_array(workspace.get("members"));
+    let excluded = string_array(workspace.get("exclude"));
+
+    let mut projects = Vec::new();
+    for dir in expand_patterns(root, &patterns, &excluded) {
+        let name = fs::read_to_string(dir.join("Cargo.toml"))
+            .ok()
+            .and_then(|text| text.parse::<toml::Value>().ok())
+            .and_then(|member| {
+                member
+                    .get("package")?
+                    .get("name")?
+                    .as_str()
+                    .map(str::to_string)
+            });
+        if let Some(name) = name {
+            projects.push(project(root, &dir, &name));
+        }
+    }
+    if manifest.get("package").is_some() {
+        if let Some(name) = manifest
+            .get("package")
+            .and_then(|package| package.get("name"))
+            .and_then(|name| name.as_str())
+        {
+            projects.insert(0, project(root, root, name));
+        }
+    }
+    Some(json!({ "kind": "cargo", "manifest": "Cargo.toml", "projects": projects }))
+}
+
+fn detect_pnpm(root: &Path) -> Option<Value> {
+    let text = fs::read_to_string(root.join("pnpm-workspace.yaml")).ok()?;
+    // Minimal reader for the `packages:` list; pnpm files rarely use more.
+    let mut patterns = Vec::new();
+    let mut in_packages = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if !line.starts_with([' ', '\t', '-']) && !trimmed.is_empty() {
+            in_packages = trimmed.starts_with("packages:");
+            continue;
+        }
+        if in_packages {
+            if let Some(item) = trimmed.strip_prefix('-') {
+                patterns.push(item.trim().trim_matches(['\'', '"']).to_string());
+            }
+        }
+    }
+    let (excluded, included): (Vec<String>, Vec<String>) =
+        patterns.into_iter().partition(|p| p.starts_with('!'));
+    let excluded: Vec<String> = excluded
+        .iter()
+        .map(|p| p.trim_start_matches('!').to_string())
+        .collect();
+    Some(json!({
+        "kind": "pnpm",
+        "manifest": "pnpm-workspace.yaml",
+        "projects": package_json_projects(root, &included, &excluded)
+    }))
+}
+
+fn detect_npm(root: &Path) -> Option<Value> {
+    if root.join("pnpm-workspace.yaml").exists() {
+        return None;
+    }
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(root.join("package.json")).ok()?).ok()?;
+    let workspaces = manifest.get("workspaces")?;
+    let patterns: Vec<String> = workspaces
+        .as_array()
+        .or_else(|| workspaces.get("packages").and_then(|v| v.as_array()))?
+        .iter()
+        .filter_map(|value| value.as_str().map(str::to_string))
+        .collect();
+    Some(json!({
+        "kind": "npm",
+        "manifest": "package.json",
+        "projects": package_json_projects(root, &patterns, &[])
+    }))
+}
+
+fn detect_bazel(root: &Path) -> Option<Value> {
+    let manifest = ["MODULE.bazel", "WORKSPACE.bazel", "WORKSPACE"]
+        .into_iter()
+        .find(|name| root.join(name).is_file())?;
+    let mut packages = Vec::new();
+    collect_bazel_packages(root, 0, &mut packages);
+    packages.sort();
+    let projects: Vec<Value> = packages
+        .iter()
+        .map(|dir| {
+            let relative = relative(root, dir);
+            let label = if relative == "." {
+                "//".to_string()
+            } else {
+                format!("//{}", relative)
+            };
+            json!({ "name": label, "path": relative })
+        })
+        .collect();
+    Some(json!({ "kind": "bazel", "manifest": manifest, "projects": projects }))
+}
+
+fn collect_bazel_packages(dir: &Path, depth: usize, out: &mut Vec<PathBuf>) {
+    if out.len() >= MAX_PROJECTS {
+        return;
+    }
+    if dir.join("BUILD").is_file() || dir.join("BUILD.bazel").is_file() {
+        out.push(dir.to_path_buf());
+    }
+    if depth >= MAX_BAZEL_DEPTH {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if path.is_dir()
+            && !name.starts_with('.')
+            && !name.starts_with("bazel-")
+            && !SKIPPED_DIRS.contains(&name.as_str())
+        {
+            collect_bazel_packages(&path, depth + 1, out);
+        }
+    }
+}
+
+fn package_json_projects(root: &Path, patterns: &[String], excluded: &[String]) -> Vec<Value> {
+    expand_patterns(root, patterns, excluded)
+        .into_iter()
+        .filter_map(|dir| {
+            let manifest: Value =
+                serde_json::from_str(&fs::read_to_string(dir.join("package.json")).ok()?).ok()?;
+            let name = manifest
+                .get("name")
+                .and_then(|name| name.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| relative(root, &dir));
+            Some(project(root, &dir, &name))
+        })
+        .collect()
+}
+
+/// Expands member globs where `*` matches within one path segment and `**`
+/// matches any number of directories.
+fn expand_patterns(root: &Path, patterns: &[String], excluded: &[String]) -> Vec<PathBuf> {
+    let excluded: Vec<PathBuf> = patterns_to_dirs(root, excluded);
+    let mut dirs: Vec<PathBuf> = patterns_to_dirs(root, patterns)
+        .into_iter()
+        .filter(|dir| !excluded.contains(dir))
+        .collect();
+    dirs.sort();
+    dirs.dedup();
+    dirs.truncate(MAX_PROJECTS);
+    dirs
+}
+
+fn patterns_to_dirs(root: &Path, patterns: &[String]) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for pattern in patterns {
+        let segments: Vec<&str> = pattern
+            .trim_end_matches('/')
+            .split('/')
+            .filter(|segment| !segment.is_empty() && *segment != ".")
+            .collect();
+        expand_segments(root, &segments, &mut dirs);
+    }
+    dirs
+}
+
+fn expand_segments(dir: &Path, segments: &[&str], out: &mut Vec<PathBuf>) {
+    let Some((first, rest)) = segments.split_first() else {
+        if dir.is_dir() {
+            out.push(dir.to_path_buf());
+        }
+        return;
+    };
+    if *first == "**" {
+        expand_segments(dir, rest, out);
+        for child in child_dirs(dir) {
+            expand_segments(&child, segments, out);
+        }
+    } else if first.contains('*') {
+        for child in child_dirs(dir) {
+            let name = child
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if glob_match(first, &name) {
+                expand_segments(&child, rest, out);
+            }
+        }
+    } else {
+        expand_segments(&dir.join(first), rest, out);
+    }
+}
+
+fn child_dirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_dir()
+                && path
+                    .file_name()
+                    .map(|name| {
+                        let name = name.to_string_lossy();
+                        !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref())
+                    })
+                    .unwrap_or(false)
+        })
+        .collect()
+}
+
+fn string_array(value: Option<&toml::Value>) -> Vec<String> {
+    value
+        .and_then(|value| value.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn project(root: &Path, dir: &Path, name: &str) -> Value {
+    json!({ "name": name, "path": relative(root, dir) })
+}
+
+fn relative(root: &Path, dir: &Path) -> String {
+    match dir.strip_prefix(root) {
+        Ok(path) if path.as_os_str().is_empty() => ".".to_string(),
+        Ok(path) => to_slash(path),
+        Err(_) => dir.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect_workspace, workspace_context};
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_dir() -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("rx-workspace-info-{}", nanos));
+        fs::create_dir_all(&dir).expect("should create temp dir");
+        dir
+    }
+
+    fn write(path: PathBuf, contents: &str) {
+        fs::create_dir_all(path.parent().expect("parent")).expect("should create dirs");
+        fs::write(path, contents).expect("should write file");
+    }
+
+    #[test]
+    fn detects_cargo_and_pnpm_workspaces() {
+        let root = temp_dir();
+        write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/scratch\"]\n",
+        );
+        write(
+            root.join("crates/core/Cargo.toml"),
+            "[package]\nname = \"app-core\"\n",
+        );
+        write(
+            root.join("crates/scratch/Cargo.toml"),
+            "[package]\nname = \"scratch\"\n",
+        );
+        write(
+            root.join("pnpm-workspace.yaml"),
+            "packages:\n  - 'packages/*'\n  - '!packages/legacy'\n",
+        );
+        write(
+            root.join("packages/web/package.json"),
+            r#"{"name":"@app/web"}"#,
+        );
+        write(
+            root.join("packages/legacy/package.json"),
+            r#"{"name":"@app/legacy"}"#,
+        );
+
+        let info = detect_workspace(&root);
+        let systems = info["systems"].as_array().expect("systems");
+        assert_eq!(systems.len(), 2);
+        assert_eq!(systems[0]["kind"], "cargo");
+        assert_eq!(systems[0]["projects"][0]["name"], "app-core");
+        assert_eq!(
+            systems[0]["projects"].as_array().expect("projects").len(),
+            1
+        );
+        assert_eq!(systems[1]["kind"], "pnpm");
+        assert_eq!(systems[1]["projects"][0]["path"], "packages/web");
+        assert_eq!(
+            systems[1]["projects"].as_array().expect("projects").len(),
+            1
+        );
+
+        let context = workspace_context(&info).expect("context");
+        assert!(context.contains("app-core — crates/core"));
+
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,856 @@
+use crate::approval::{
+    ApprovalDecision, ApprovalGate, CompletionDecision, CompletionReviewer, PlanReviewer,
+};
+use crate::event::Event;
+use crate::kernel::{KernelHandle, KernelStatus};
+use crate::model::{Action, ModelPricing, ModelUsage, ToolCall};
+use crate::runtime_hooks::EventHook;
+use crate::status_line::StatusLine;
+use crate::workspace_diff::current_diff;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use ratatui::crossterm::event::{
+    self as terminal_event, Event as TerminalEvent, KeyCode, KeyEvent, KeyEventKind, KeyModifiers,
+};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, Notify};
+use tracing::{Level, Metadata};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Log lines kept for the log pane.
+const RECENT_LOG_LINES: usize = 200;
+/// Lines of the log pane, not counting its title.
+const LOG_PANE_LINES: usize = 3;
+/// Model messages kept for the messages pane.
+const MAX_MESSAGES: usize = 100;
+/// Tail of the current tool call's output kept for the tool pane.
+const MAX_TOOL_OUTPUT: usize = 64 * 1024;
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+const KEY_HELP: &str = "[p] pause/resume  [a] approve  [d] deny  [q] abort";
+
+/// rx's own log lines in a `--tui` run. While the screen is up they are
+/// held for the log pane and printed when it closes; otherwise they print
+/// as usual.
+#[derive(Debug, Clone, Default)]
+pub struct LogLines {
+    inner: Arc<Mutex<LogBuffer>>,
+}
+
+#[derive(Debug, Default)]
+struct LogBuffer {
+    recent: VecDeque<String>,
+    /// Lines logged while the screen is up and whether each is a warning;
+    /// `None` while it is not.
+    held: Option<Vec<(bool, String)>>,
+}
+
+impl LogLines {
+    fn buffer(&self) -> MutexGuard<'_, LogBuffer> {
+        self.inner.lock().expect("log lines lock")
+    }
+
+    fn hold(&self) {
+        self.buffer().held = Some(Vec::new());
+    }
+
+    /// Prints the lines held while the screen was up.
+    fn release(&self) {
+        let held = self.buffer().held.take().unwrap_or_default();
+        for (warning, line) in held {
+            if warning {
+                eprintln!("{}", line);
+            } else {
+                println!("{}", line);
+            }
+        }
+    }
+
+    fn recent(&self, count: usize) -> Vec<String> {
+        let buffer = self.buffer();
+        let skip = buffer.recent.len().saturating_sub(count);
+        buffer.recent.iter().skip(skip).cloned().collect()
+    }
+
+    /// Keeps `text` for the log pane; returns whether it is held rather
+    /// than printed.
+    fn record(&self, text: &str, warning: bool) -> bool {
+        let mut buffer = self.buffer();
+        for line in text.lines() {
+            if buffer.recent.len() == RECENT_LOG_LINES {
+                buffer.recent.pop_front();
+            }
+            buffer.recent.push_back(line.to_string());
+            if let Some(held) = &mut buffer.held {
+                held.push((warning, line.to_string()));
+            }
+        }
+        buffer.held.is_some()
+    }
+}
+
+/// Log output of a `--tui` run: warnings and errors on stderr, the rest on
+/// stdout, while the screen is not up.
+pub struct LogWriter {
+    lines: LogLines,
+    warning: bool,
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self
+            .lines
+            .record(&String::from_utf8_lossy(buf), self.warning)
+        {
+            if self.warning {
+                io::stderr().write_all(buf)?;
+            } else {
+                io::stdout().write_all(buf)?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogLines {
+    type Writer = LogWriter;
+
+    fn make_writer(&'a self) -> LogWriter {
+        LogWriter {
+            lines: self.clone(),
+            warning: false,
+        }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> LogWriter {
+        LogWriter {
+            lines: self.clone(),
+            warning: *meta.level() <= Level::WARN,
+        }
+    }
+}
+
+/// Full-screen view of one run (`rx run --tui`): model messages, the
+/// current tool call with its live output, the git diff so far, and token
+/// and cost counters. It also answers the run's confirmation prompts.
+pub struct Tui {
+    view: Mutex<View>,
+    logs: LogLines,
+    changed: Notify,
+}
+
+struct View {
+    goal_id: String,
+    goal: String,
+    max_iterations: usize,
+    pricing: Option<ModelPricing>,
+    iteration: usize,
+    status: StatusLine,
+    usage: ModelUsage,
+    messages: VecDeque<String>,
+    tool: Option<ToolView>,
+    diff: DiffView,
+    kernel: KernelStatus,
+    pause_requested: bool,
+    aborting: bool,
+    prompt: Option<Prompt>,
+    termination: Option<String>,
+}
+
+struct ToolView {
+    call: ToolCall,
+    started: Instant,
+    output: String,
+    /// How long the call took, once its output is recorded.
+    took: Option<Duration>,
+    failed: bool,
+}
+
+#[derive(Default)]
+struct DiffView {
+    root: Option<PathBuf>,
+    exclude: Vec<PathBuf>,
+    text: String,
+    /// Set after each tool call; the next redraw takes the diff again.
+    stale: bool,
+}
+
+struct Prompt {
+    title: String,
+    details: Vec<String>,
+    answer: oneshot::Sender<bool>,
+}
+
+impl Tui {
+    pub fn new(logs: LogLines) -> Self {
+        Self {
+            view: Mutex::new(View {
+                goal_id: String::new(),
+                goal: String::new(),
+                max_iterations: 0,
+                pricing: None,
+                iteration: 0,
+                status: StatusLine::new(Instant::now()),
+                usage: ModelUsage::default(),
+                messages: VecDeque::new(),
+                tool: None,
+                diff: DiffView::default(),
+                kernel: KernelStatus::Starting,
+                pause_requested: false,
+                aborting: false,
+                prompt: None,
+                termination: None,
+            }),
+            logs,
+            changed: Notify::new(),
+        }
+    }
+
+    /// Names the run in the header, prices its usage, and shows the diff
+    /// of `root` against HEAD without the `exclude` directories.
+    pub fn attach(
+        &self,
+        goal_id: &str,
+        max_iterations: usize,
+        pricing: Option<ModelPricing>,
+        root: PathBuf,
+        exclude: Vec<PathBuf>,
+    ) {
+        let mut view = self.view();
+        view.goal_id = goal_id.to_string();
+        view.max_iterations = max_iterations;
+        view.pricing = pricing;
+        view.status = StatusLine::new(Instant::now());
+        view.diff = DiffView {
+            root: Some(root),
+            exclude,
+            text: String::new(),
+            stale: true,
+        };
+    }
+
+    /// A sender for `ExecTool::with_live_output` that appends to the
+    /// current tool call's output.
+    pub fn live_output(self: &Arc<Self>) -> mpsc::UnboundedSender<String> {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<String>();
+        let tui = Arc::clone(self);
+        tokio::spawn(async move {
+            while let Some(chunk) = receiver.recv().await {
+                if let Some(tool) = tui.view().tool.as_mut().filter(|tool| tool.took.is_none()) {
+                    push_tail(&mut tool.output, &chunk);
+                }
+                tui.changed.notify_one();
+            }
+        });
+        sender
+    }
+
+    /// Shows the run until it ends and returns its result. `p` pauses
+    /// before the next model request, `a` and `d` answer a prompt, and `q`
+    /// (or Ctrl-C) stops after the in-flight tool call; pressed again it
+    /// exits at once.
+    pub async fn run(self: &Arc<Self>, handle: KernelHandle) -> Result<()> {
+        let mut screen = Screen::enter()?;
+        self.logs.hold();
+        let stop_keys = Arc::new(AtomicBool::new(false));
+        let mut keys = read_keys(Arc::clone(&stop_keys));
+        let mut status = handle.status();
+        let mut ticker = tokio::time::interval(REDRAW_INTERVAL);
+        let result: Result<()> = loop {
+            self.view().kernel = status.borrow().clone();
+            self.refresh_diff().await;
+            let logs = self.logs.recent(LOG_PANE_LINES);
+            if let Err(error) = screen.0.draw(|frame| render(frame, &self.view(), &logs)) {
+                break Err(error.into());
+            }
+            if status.borrow().is_finished() {
+                break Ok(());
+            }
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = self.changed.notified() => {}
+                Ok(()) = status.changed() => {}
+                Some(key) = keys.recv() => match key.code {
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        if self.view().aborting {
+                            drop(screen);
+                            self.logs.release();
+                            std::process::exit(130);
+                        }
+                        self.view().aborting = true;
+                        self.answer(false);
+                        handle.cancel();
+                    }
+                    KeyCode::Char('p' | 'P') => {
+                        let mut view = self.view();
+                        view.pause_requested = !view.pause_requested;
+                        if view.pause_requested {
+                            handle.pause();
+                        } else {
+                            handle.resume();
+                        }
+                    }
+                    KeyCode::Char('a' | 'A' | 'y' | 'Y') => self.answer(true),
+                    KeyCode::Char('d' | 'D' | 'n' | 'N') => self.answer(false),
+                    KeyCode::Char('q' | 'Q') => {
+                        if self.view().aborting {
+                            drop(screen);
+                            self.logs.release();
+                            std::process::exit(130);
+                        }
+                        self.view().aborting = true;
+                        self.answer(false);
+                        handle.cancel();
+                    }
+                    _ => {}
+                },
+            }
+        };
+        stop_keys.store(true, Ordering::Relaxed);
+        drop(screen);
+        self.logs.release();
+        result?;
+        handle.join().await
+    }
+
+    fn view(&self) -> MutexGuard<'_, View> {
+        self.view.lock().expect("tui view lock")
+    }
+
+    async fn refresh_diff(&self) {
+        let (root, exclude) = {
+            let mut view = self.view();
+            match view.diff.root.clone().filter(|_| view.diff.stale) {
+                Some(root) => {
+                    view.diff.stale = false;
+                    (root, view.diff.exclude.clone())
+                }
+                None => return,
+            }
+        };
+        let text = match current_diff(&root, &exclude).await {
+            Ok(diff) if diff.is_empty() => "(no changes)".to_string(),
+            Ok(diff) => diff,
+            Err(error) => format!("(no git diff: {:#})", error),
+        };
+        self.view().diff.text = text;
+    }
+
+    /// Shows `title` and `details` and waits for `a` or `d`; aborting the
+    /// run denies.
+    async fn ask(&self, title: String, details: Vec<String>) -> bool {
+        let (answer, receiver) = oneshot::channel();
+        {
+            let mut view = self.view();
+            if view.aborting {
+                return false;
+            }
+            view.prompt = Some(Prompt {
+                title,
+                details,
+                answer,
+            });
+        }
+        self.changed.notify_one();
+        receiver.await.unwrap_or(false)
+    }
+
+    fn answer(&self, approved: bool) {
+        if let Some(prompt) = self.view().prompt.take() {
+            let _ = prompt.answer.send(approved);
+        }
+        self.changed.notify_one();
+    }
+}
+
+#[async_trait]
+impl EventHook for Tui {
+    async fn on_event(&self, event: &Event) -> Result<()> {
+        let payload = &event.payload;
+        {
+            let mut view = self.view();
+            match event.r#type.as_str() {
+                "goal" => {
+                    view.goal = payload["goal"].as_str().unwrap_or_default().to_string();
+                }
+                "iteration_started" => {
+                    view.iteration = payload["iteration"].as_u64().unwrap_or(0) as usize;
+                }
+                "action" => match serde_json::from_value::<Action>(payload.clone()) {
+                    Ok(Action::Message(message)) => view.push_message(message),
+                    Ok(Action::ToolCall(call)) => {
+                        view.tool = Some(ToolView {
+                            call,
+                            started: Instant::now(),
+                            output: String::new(),
+                            took: None,
+                            failed: false,
+                        });
+                    }
+                    Err(_) => {}
+                },
+                "tool_output" => {
+                    let output = &payload["output"];
+                    let view = &mut *view;
+                    if let Some(tool) = view.tool.as_mut().filter(|tool| {
+                        tool.call.id == payload["tool_call_id"].as_str().unwrap_or("")
+                    }) {
+                        tool.took = Some(tool.started.elapsed());
+                        tool.failed = output.get("error").is_some();
+                        tool.output = output_text(output);
+                        view.status.record(&tool.call, output);
+                    }
+                    view.diff.stale = true;
+                }
+                "user_message" => {
+                    let source = payload["source"].as_str().unwrap_or("user");
+                    let message = payload["message"].as_str().unwrap_or_default();
+                    view.push_message(format!("[{}] {}", source, message));
+                }
+                "iteration_finished" => {
+                    if let Ok(usage) =
+                        serde_json::from_value::<ModelUsage>(payload["usage"].clone())
+                    {
+                        view.usage.requests += usage.requests;
+                        view.usage.input_tokens += usage.input_tokens;
+                        view.usage.cached_input_tokens += usage.cached_input_tokens;
+                        view.usage.output_tokens += usage.output_tokens;
+                    }
+                }
+                "termination" => {
+                    let reason = payload["reason"].as_str().unwrap_or("ended").to_string();
+                    view.push_message(format!("Run ended: {}", reason));
+                    view.termination = Some(reason);
+                }
+                _ => return Ok(()),
+            }
+        }
+        self.changed.notify_one();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ApprovalGate for Tui {
+    async fn review(&self, tool_call: &ToolCall) -> Result<ApprovalDecision> {
+        let details = argument_lines(&tool_call.arguments);
+        let approved = self
+            .ask(
+                format!("Run {} [{}]?", tool_call.name, tool_call.id),
+                details,
+            )
+            .await;
+        Ok(if approved {
+            ApprovalDecision::Approved
+        } else {
+            ApprovalDecision::Denied
+        })
+    }
+}
+
+#[async_trait]
+impl CompletionReviewer for Tui {
+    async fn review_completion(&self, proposal: &ToolCall) -> Result<CompletionDecision> {
+        let details = argument_lines(&proposal.arguments);
+        Ok(
+            if self.ask("Accept completion?".to_string(), details).await {
+                CompletionDecision::Accepted
+            } else {
+                CompletionDecision::Rejected {
+                    feedback: "The user rejected this completion; keep working on the goal."
+                        .to_string(),
+                }
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl PlanReviewer for Tui {
+    async fn review_plan(&self, plan: &ToolCall) -> Result<CompletionDecision> {
+        let details = argument_lines(&plan.arguments);
+        Ok(if self.ask("Approve plan?".to_string(), details).await {
+            CompletionDecision::Accepted
+        } else {
+            CompletionDecision::Rejected {
+                feedback: "The user rejected this plan; submit a revised plan.".to_string(),
+            }
+        })
+    }
+}
+
+impl View {
+    fn push_message(&mut self, message: String) {
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(message);
+    }
+
+    fn state(&self) -> String {
+        if let Some(reason) = &self.termination {
+            return format!("ended: {}", reason);
+        }
+        if self.aborting {
+            return "aborting".to_string();
+        }
+        if self.prompt.is_some() {
+            return "waiting for approval".to_string();
+        }
+        match &self.kernel {
+            KernelStatus::Paused { .. } => "paused".to_string(),
+            _ if self.pause_requested => "pausing".to_string(),
+            KernelStatus::Starting => "starting".to_string(),
+            KernelStatus::Running { .. } => "running".to_string(),
+            KernelStatus::Finished { reason } => format!("ended: {}", reason),
+            KernelStatus::Failed { .. } => "failed".to_string(),
+        }
+    }
+}
+
+/// Draws the header, the messages and the current tool call beside the git
+/// diff, the log pane, and the key help.
+fn render(frame: &mut Frame, view: &View, logs: &[String]) {
+    let [header, goal, body, log, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Min(2),
+        Constraint::Length(LOG_PANE_LINES as u16 + 1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [left, right] = Layout::horizontal([Constraint::Fill(1); 2]).areas(body);
+    let [messages_area, tool_area] = Layout::vertical([Constraint::Fill(1); 2]).areas(left);
+
+    let usage = (view.usage.requests > 0).then_some(view.usage);
+    let status = format!(
+        " rx {} | {} | {}",
+        view.goal_id,
+        view.status
+            .render(view.iteration, view.max_iterations, usage, view.pricing),
+        view.state()
+    );
+    frame.render_widget(
+        Paragraph::new(clean(&status)).style(Style::new().reversed()),
+        header,
+    );
+    frame.render_widget(
+        Paragraph::new(clean(&format!(" Goal: {}", view.goal))),
+        goal,
+    );
+
+    let mut message_lines = Vec::new();
+    for message in &view.messages {
+        if !message_lines.is_empty() {
+            message_lines.push(String::new());
+        }
+        message_lines.extend(wrap(message, messages_area.width as usize));
+    }
+    let visible = messages_area.height.saturating_sub(1) as usize;
+    frame.render_widget(
+        pane("Messages", &tail(message_lines, visible), Borders::TOP),
+        messages_area,
+    );
+
+    let tool_title = match (&view.prompt, &view.tool) {
+        (Some(_), _) => "Approval".to_string(),
+        (None, Some(tool)) => {
+            let elapsed = tool.took.unwrap_or_else(|| tool.started.elapsed());
+            let state = match (tool.took, tool.failed) {
+                (None, _) => "running",
+                (Some(_), true) => "failed",
+                (Some(_), false) => "done",
+            };
+            format!(
+                "Tool: {} ({}, {:.1}s)",
+                tool.call.name,
+                state,
+                elapsed.as_secs_f64()
+            )
+        }
+        (None, None) => "Tool".to_string(),
+    };
+    let tool_lines = match (&view.prompt, &view.tool) {
+        (Some(prompt), _) => {
+            let mut lines = vec![prompt.title.clone()];
+            lines.extend(prompt.details.iter().cloned());
+            lines
+        }
+        (None, Some(tool)) => {
+            let mut lines = argument_lines(&tool.call.arguments);
+            lines.push(String::new());
+            lines.extend(tool.output.lines().map(str::to_string));
+            tail(lines, tool_area.height.saturating_sub(1) as usize)
+        }
+        (None, None) => Vec::new(),
+    };
+    frame.render_widget(pane(&tool_title, &tool_lines, Borders::TOP), tool_area);
+
+    let diff_lines: Vec<String> = view.diff.text.lines().map(str::to_string).collect();
+    frame.render_widget(
+        pane("Git diff", &diff_lines, Borders::TOP | Borders::LEFT),
+        right,
+    );
+    frame.render_widget(pane("Log", logs, Borders::TOP), log);
+
+    let help = match &view.prompt {
+        Some(prompt) => Paragraph::new(clean(&format!(" {}  [a] approve  [d] deny", prompt.title)))
+            .style(Style::new().black().on_yellow()),
+        None => Paragraph::new(format!(" {}", KEY_HELP)).style(Style::new().dim()),
+    };
+    frame.render_widget(help, footer);
+}
+
+/// A pane titled on its top border, showing the first lines that fit, with
+/// diff lines colored.
+fn pane(title: &str, content: &[String], borders: Borders) -> Paragraph<'static> {
+    let lines: Vec<Line> = content
+        .iter()
+        .map(|line| {
+            let style = match line.chars().next() {
+                Some('+') if !line.starts_with("+++") => Style::new().green(),
+                Some('-') if !line.starts_with("---") => Style::new().red(),
+                Some('@') if line.starts_with("@@") => Style::new().cyan(),
+                _ => Style::new(),
+            };
+            Line::styled(clean(line), style)
+        })
+        .collect();
+    let block = Block::new()
+        .borders(borders)
+        .title(format!(" {} ", title))
+        .title_style(Style::new().bold());
+    Paragraph::new(lines).block(block)
+}
+
+fn tail(lines: Vec<String>, count: usize) -> Vec<String> {
+    let skip = lines.len().saturating_sub(count);
+    lines.into_iter().skip(skip).collect()
+}
+
+fn clean(text: &str) -> String {
+    let mut cleaned = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                // CSI sequences end with a byte in `@`..`~`.
+                if chars.next_if_eq(&'[').is_some() {
+                    while chars.next().is_some_and(|c| !('@'..='~').contains(&c)) {}
+                }
+            }
+            '\t' => cleaned.push_str("    "),
+            c if c.is_control() => {}
+            c => cleaned.push(c),
+        }
+    }
+    cleaned
+}
+
+/// Breaks `text` into lines of at most `width` characters, at spaces when
+/// possible.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in clean(paragraph).split(' ') {
+            let needed =
+                line.chars().count() + usize::from(!line.is_empty()) + word.chars().count();
+            if needed > width && !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+            while line.chars().count() > width {
+                let rest: String = line.chars().skip(width).collect();
+                lines.push(line.chars().take(width).collect());
+                line = rest;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// One `key: value` line per argument; long values are cut by the pane.
+fn argument_lines(arguments: &Value) -> Vec<String> {
+    match arguments.as_object() {
+        Some(arguments) => arguments
+            .iter()
+            .map(|(key, value)| match value {
+                Value::String(text) => format!("{}: {}", key, text.replace('\n', "⏎")),
+                other => format!("{}: {}", key, other),
+            })
+            .collect(),
+        None => vec![arguments.to_string()],
+    }
+}
+
+/// A command's stdout and stderr, or the output as pretty JSON.
+fn output_text(output: &Value) -> String {
+    let stream = |name: &str| output.get(name).and_then(|value| value.as_str());
+    match (stream("stdout"), stream("stderr")) {
+        (None, None) => serde_json::to_string_pretty(output).unwrap_or_default(),
+        (stdout, stderr) => {
+            let mut text = format!("{}{}", stdout.unwrap_or(""), stderr.unwrap_or(""));
+            if let Some(status) = output.get("status").and_then(|value| value.as_str()) {
+                text.push_str(&format!("\n[{}]", status));
+            }
+            text
+        }
+    }
+}
+
+fn push_tail(output: &mut String, chunk: &str) {
+    output.push_str(chunk);
+    if output.len() > MAX_TOOL_OUTPUT {
+        let mut cut = output.len() - MAX_TOOL_OUTPUT;
+        while !output.is_char_boundary(cut) {
+            cut += 1;
+        }
+        output.drain(..cut);
+    }
+}
+
+/// The terminal in raw mode on the alternate screen; dropping it restores
+/// both.
+struct Screen(DefaultTerminal);
+
+impl Screen {
+    fn enter() -> Result<Self> {
+        if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+            return Err(anyhow!("--tui needs an interactive terminal"));
+        }
+        Ok(Self(
+            ratatui::try_init().context("failed to set up the terminal")?,
+        ))
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
+
+/// Key presses, read on a thread until `stop` is set.
+fn read_keys(stop: Arc<AtomicBool>) -> mpsc::UnboundedReceiver<KeyEvent> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        // Polls return after 100 ms without input, so the thread can
+        // notice when to stop.
+        while !stop.load(Ordering::Relaxed) {
+            match terminal_event::poll(Duration::from_millis(100)) {
+                Ok(false) => {}
+                Ok(true) => match terminal_event::read() {
+                    Ok(TerminalEvent::Key(key)) if key.kind == KeyEventKind::Press => {
+                        if sender.send(key).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(_) => return,
+                },
+                Err(_) => return,
+            }
+        }
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, LogLines, Tui};
+    use crate::approval::{ApprovalDecision, ApprovalGate};
+    use crate::event::Event;
+    use crate::model::ToolCall;
+    use crate::runtime_hooks::EventHook;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    /// The screen of `tui` at 100x24, one string per row.
+    fn screen(tui: &Tui, logs: &[String]) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+        terminal
+            .draw(|frame| render(frame, &tui.view(), logs))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..24)
+            .map(|y| (0..100).map(|x| buffer[(x, y)].symbol()).collect())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn events_fill_the_panes_and_keys_answer_prompts() {
+        let tui = Arc::new(Tui::new(LogLines::default()));
+        tui.attach("goal-1", 10, None, std::env::temp_dir(), Vec::new());
+        for (kind, payload) in [
+            ("goal", json!({ "goal": "fix the build" })),
+            ("iteration_started", json!({ "iteration": 2 })),
+            (
+                "action",
+                json!({ "Message": "Looking at the failing test." }),
+            ),
+            (
+                "action",
+                json!({ "ToolCall": { "id": "call-1", "name": "exec", "arguments": { "command": "cargo" } } }),
+            ),
+            (
+                "tool_output",
+                json!({ "tool_call_id": "call-1", "name": "exec", "output": { "stdout": "test result: ok\n", "stderr": "", "status": "exit status: 0" } }),
+            ),
+            (
+                "iteration_finished",
+                json!({ "usage": { "requests": 1, "input_tokens": 1200, "cached_input_tokens": 0, "output_tokens": 300 } }),
+            ),
+        ] {
+            tui.on_event(&Event::new(kind, payload)).await.unwrap();
+        }
+        tui.view().diff.text = "+fixed line\n".to_string();
+
+        let text = screen(&tui, &["Event log: logs/goal-1.jsonl".to_string()]).join("\n");
+        assert!(text.contains("rx goal-1 | Iteration 2/10"));
+        assert!(text.contains("1.5k tokens"));
+        assert!(text.contains("Goal: fix the build"));
+        assert!(text.contains("Looking at the failing test."));
+        assert!(text.contains("Tool: exec (done"));
+        assert!(text.contains("test result: ok"));
+        assert!(text.contains("+fixed line"));
+        assert!(text.contains("Event log: logs/goal-1.jsonl"));
+
+        let call = ToolCall {
+            id: "call-2".to_string(),
+            name: "write_file".to_string(),
+            arguments: json!({ "path": "a.rs" }),
+        };
+        let gate = Arc::clone(&tui);
+        let review = tokio::spawn(async move { gate.review(&call).await.unwrap() });
+        while tui.view().prompt.is_none() {
+            tokio::task::yield_now().await;
+        }
+        let screen = screen(&tui, &[]).join("\n");
+        assert!(screen.contains("Run write_file [call-2]?  [a] approve  [d] deny"));
+        assert!(screen.contains("path: a.rs"));
+        tui.answer(true);
+        assert_eq!(review.await.unwrap(), ApprovalDecision::Approved);
+    }
+}
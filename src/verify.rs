@@ -0,0 +1,115 @@
+use crate::tools::shell::shell_command;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Lines of the verify command's output kept when it fails.
+const TAIL_LINES: usize = 60;
+/// Characters of the verify command's output kept when it fails.
+const TAIL_CHARS: usize = 4_000;
+
+/// Runs `verify_command` through the shell at the workspace root before a
+/// `success` proposal is accepted, so "done" means the checks pass.
+pub struct Verifier {
+    command: String,
+    root: PathBuf,
+    env: BTreeMap<String, String>,
+    timeout: Duration,
+}
+
+impl Verifier {
+    pub fn new(command: String, root: PathBuf) -> Self {
+        Self {
+            command,
+            root,
+            env: BTreeMap::new(),
+            timeout: Duration::from_secs(600),
+        }
+    }
+
+    /// Variables exported to the command, as for `exec`.
+    pub fn with_env(mut self, env: BTreeMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// The command's result: `passed`, `code`, and on failure the tail of
+    /// its combined stdout and stderr.
+    pub async fn run(&self) -> Result<Value> {
+        let mut command = shell_command(&self.command);
+        command
+            .current_dir(&self.root)
+            .envs(&self.env)
+            .kill_on_drop(true);
+        let output = match tokio::time::timeout(self.timeout, command.output()).await {
+            Ok(output) => output
+                .with_context(|| format!("failed to run verify command '{}'", self.command))?,
+            Err(_) => {
+                return Ok(json!({
+                    "command": self.command,
+                    "passed": false,
+                    "code": null,
+                    "output_tail": format!("timed out after {}s", self.timeout.as_secs()),
+                }))
+            }
+        };
+        let passed = output.status.success();
+        let mut result = json!({
+            "command": self.command,
+            "passed": passed,
+            "code": output.status.code(),
+        });
+        if !passed {
+            let mut combined = String::from_utf8_lossy(&output.stdout).into_owned();
+            combined.push_str(&String::from_utf8_lossy(&output.stderr));
+            result["output_tail"] = json!(tail(&combined));
+        }
+        Ok(result)
+    }
+}
+
+/// The last `TAIL_LINES` lines of `text`, at most `TAIL_CHARS` characters.
+fn tail(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let tail = lines[lines.len().saturating_sub(TAIL_LINES)..].join("\n");
+    let skip = tail.chars().count().saturating_sub(TAIL_CHARS);
+    match tail.char_indices().nth(skip) {
+        Some((cut, _)) if skip > 0 => tail[cut..].to_string(),
+        _ => tail,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Verifier;
+
+    #[tokio::test]
+    async fn failing_command_reports_its_code_and_output_tail() {
+        let root = std::env::temp_dir();
+        let passed = Verifier::new("true".to_string(), root.clone())
+            .run()
+            .await
+            .unwrap();
+        assert_eq!(passed["passed"], true);
+        assert!(passed.get("output_tail").is_none());
+
+        let failed = Verifier::new(
+            "for i in $(seq 1 100); do echo line $i; done; echo broken >&2; exit 3".to_string(),
+            root,
+        )
+        .run()
+        .await
+        .unwrap();
+        assert_eq!(failed["passed"], false);
+        assert_eq!(failed["code"], 3);
+        let tail = failed["output_tail"].as_str().unwrap();
+        assert!(tail.starts_with("line 42\n"));
+        assert!(tail.ends_with("line 100\nbroken"));
+    }
+}
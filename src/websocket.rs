@@ -0,0 +1,264 @@
+use crate::event::Event;
+use crate::runtime_hooks::EventHook;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::{SinkExt, StreamExt};
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Empty, Full};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{header, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::Serialize;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+use tracing::warn;
+
+/// Live events buffered per channel for slow `/events` and `/ws` readers.
+pub const EVENT_BUFFER: usize = 1024;
+
+/// An event and the goal it was appended to, one WebSocket text message.
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalEvent {
+    pub goal_id: String,
+    pub event: Event,
+}
+
+/// Publishes each event of a goal on a WebSocket broadcast channel.
+pub struct EventChannelHook {
+    goal_id: String,
+    events: broadcast::Sender<GoalEvent>,
+}
+
+impl EventChannelHook {
+    pub fn new(goal_id: String, events: broadcast::Sender<GoalEvent>) -> Self {
+        Self { goal_id, events }
+    }
+}
+
+#[async_trait]
+impl EventHook for EventChannelHook {
+    async fn on_event(&self, event: &Event) -> Result<()> {
+        // No connected clients is fine.
+        let _ = self.events.send(GoalEvent {
+            goal_id: self.goal_id.clone(),
+            event: event.clone(),
+        });
+        Ok(())
+    }
+}
+
+type Body = BoxBody<Bytes, Infallible>;
+
+/// Whether an `Origin` header names a page served from this machine.
+/// Browsers send one on cross-site requests, so anything else is refused.
+pub fn is_loopback_origin(origin: &str) -> bool {
+    let Some((_, authority)) = origin.split_once("://") else {
+        return false;
+    };
+    let host = match authority.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Accepts a WebSocket handshake on `request` and streams `events` to the
+/// client, only those of `goal_id` when given. Events appended before the
+/// connection are not replayed. Handshakes from pages on other hosts are
+/// refused.
+pub fn upgrade(
+    mut request: Request<Incoming>,
+    events: broadcast::Receiver<GoalEvent>,
+    goal_id: Option<String>,
+) -> Result<Response<Body>> {
+    let header_value = |name: header::HeaderName| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+    };
+    if !header_value(header::UPGRADE).eq_ignore_ascii_case("websocket") {
+        return Err(anyhow!("expected a WebSocket upgrade request"));
+    }
+    if header_value(header::SEC_WEBSOCKET_VERSION) != "13" {
+        return Err(anyhow!("unsupported WebSocket version (expected 13)"));
+    }
+    let origin = header_value(header::ORIGIN);
+    if !origin.is_empty() && !is_loopback_origin(origin) {
+        return Err(anyhow!(
+            "cross-origin WebSocket connections are not allowed"
+        ));
+    }
+    let key = header_value(header::SEC_WEBSOCKET_KEY);
+    if key.is_empty() {
+        return Err(anyhow!("missing Sec-WebSocket-Key"));
+    }
+    let accept = derive_accept_key(key.as_bytes());
+
+    let upgrade = hyper::upgrade::on(&mut request);
+    tokio::spawn(async move {
+        match upgrade.await {
+            Ok(upgraded) => {
+                let socket =
+                    WebSocketStream::from_raw_socket(TokioIo::new(upgraded), Role::Server, None)
+                        .await;
+                stream_events(socket, events, goal_id).await;
+            }
+            Err(error) => warn!("WebSocket upgrade failed: {}", error),
+        }
+    });
+    Ok(Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(header::UPGRADE, "websocket")
+        .header(header::CONNECTION, "Upgrade")
+        .header(header::SEC_WEBSOCKET_ACCEPT, accept)
+        .body(BodyExt::boxed(Empty::new()))
+        .expect("valid response"))
+}
+
+/// Serves `ws://<addr>/ws` for `rx run --listen`, streaming the run's events.
+pub async fn listen(listener: TcpListener, events: broadcast::Sender<GoalEvent>) {
+    let events = Arc::new(events);
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(error) => {
+                warn!("failed to accept a WebSocket connection: {}", error);
+                continue;
+            }
+        };
+        let events = Arc::clone(&events);
+        tokio::spawn(async move {
+            let service = service_fn(move |request: Request<Incoming>| {
+                let response = if request.uri().path() == "/ws" {
+                    upgrade(request, events.subscribe(), None).unwrap_or_else(|error| {
+                        plain_response(StatusCode::BAD_REQUEST, &error.to_string())
+                    })
+                } else {
+                    plain_response(StatusCode::NOT_FOUND, "not found; connect to /ws")
+                };
+                async move { Ok::<_, Infallible>(response) }
+            });
+            if let Err(error) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .with_upgrades()
+                .await
+            {
+                warn!("connection error: {}", error);
+            }
+        });
+    }
+}
+
+fn plain_response(status: StatusCode, message: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(BodyExt::boxed(Full::new(Bytes::from(message.to_string()))))
+        .expect("valid response")
+}
+
+/// Sends each event as a JSON text message until the client closes the
+/// connection or the channel ends. Pings are answered by the socket.
+async fn stream_events<S>(
+    socket: WebSocketStream<S>,
+    mut events: broadcast::Receiver<GoalEvent>,
+    goal_id: Option<String>,
+) where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (mut writer, mut reader) = socket.split();
+    loop {
+        tokio::select! {
+            received = events.recv() => match received {
+                Ok(event) if goal_id.as_ref().is_none_or(|id| *id == event.goal_id) => {
+                    let text = serde_json::to_string(&event).unwrap_or_default();
+                    if writer.send(Message::text(text)).await.is_err() {
+                        return;
+                    }
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => {
+                    let _ = writer.close().await;
+                    return;
+                }
+            },
+            message = reader.next() => match message {
+                // Queued pongs go out with the next write; flush them now.
+                Some(Ok(Message::Ping(_))) => {
+                    if writer.flush().await.is_err() {
+                        return;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => continue,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_loopback_origin, listen, GoalEvent};
+    use crate::event::Event;
+    use futures_util::{SinkExt, StreamExt};
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+    use tokio::sync::broadcast;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+    use tokio_tungstenite::tungstenite::Message;
+
+    #[tokio::test]
+    async fn events_stream_to_websocket_clients() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (events, _) = broadcast::channel(16);
+        tokio::spawn(listen(listener, events.clone()));
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", address))
+            .await
+            .unwrap();
+        client
+            .send(Message::Ping(b"hi".to_vec().into()))
+            .await
+            .unwrap();
+        let pong = client.next().await.unwrap().unwrap();
+        assert_eq!(pong, Message::Pong(b"hi".to_vec().into()));
+
+        events
+            .send(GoalEvent {
+                goal_id: "goal-1".to_string(),
+                event: Event::new("goal", json!({ "goal": "fix it" })),
+            })
+            .unwrap();
+        let text = client.next().await.unwrap().unwrap().into_text().unwrap();
+        let message: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(message["goal_id"], "goal-1");
+        assert_eq!(message["event"]["type"], "goal");
+
+        let mut cross_site = format!("ws://{}/ws", address)
+            .into_client_request()
+            .unwrap();
+        cross_site
+            .headers_mut()
+            .insert("origin", "https://example.com".parse().unwrap());
+        assert!(tokio_tungstenite::connect_async(cross_site).await.is_err());
+        assert!(is_loopback_origin("http://localhost:3000"));
+        assert!(is_loopback_origin("http://[::1]:8080"));
+        assert!(!is_loopback_origin("http://127.0.0.1.example.com"));
+        assert!(!is_loopback_origin("null"));
+    }
+}
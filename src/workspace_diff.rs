@@ -0,0 +1,252 @@
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+/// Characters of one file's diff kept in a `workspace_diff` event.
+const MAX_FILE_DIFF_CHARS: usize = 4_000;
+/// Untracked files larger than this are listed without their content.
+const MAX_UNTRACKED_BYTES: u64 = 64 * 1024;
+
+/// Tracks the git working tree between tool calls (`[state].diff_events`)
+/// and reports the files whose diff against HEAD changed since the last
+/// look, so each step's `workspace_diff` event shows what it did.
+pub struct WorkspaceDiff {
+    root: PathBuf,
+    pathspec: Vec<String>,
+    previous: Mutex<BTreeMap<String, String>>,
+}
+
+impl WorkspaceDiff {
+    /// Starts from the current state so earlier uncommitted changes are not
+    /// attributed to the first tool call. `exclude` lists directories under
+    /// `root` to ignore, such as the event log directory.
+    pub async fn start(root: &Path, exclude: &[PathBuf]) -> Result<Self> {
+        let pathspec = pathspec(exclude);
+        let current = file_diffs(root, &pathspec).await?;
+        Ok(Self {
+            root: root.to_path_buf(),
+            pathspec,
+            previous: Mutex::new(current),
+        })
+    }
+
+    /// Files whose diff changed since the previous call, or `None` when the
+    /// working tree is as it was.
+    pub async fn changes(&self) -> Result<Option<Vec<Value>>> {
+        let current = file_diffs(&self.root, &self.pathspec).await?;
+        let mut previous = self.previous.lock().await;
+        let mut files = Vec::new();
+        for (path, diff) in &current {
+            if previous.get(path) != Some(diff) {
+                let (additions, deletions) = line_counts(diff);
+                files.push(json!({
+                    "path": path,
+                    "additions": additions,
+                    "deletions": deletions,
+                    "diff": truncate(diff),
+                }));
+            }
+        }
+        for path in previous.keys().filter(|path| !current.contains_key(*path)) {
+            files.push(json!({ "path": path, "reverted": true }));
+        }
+        *previous = current;
+        Ok((!files.is_empty()).then_some(files))
+    }
+}
+
+/// The whole working tree diff against HEAD, untracked files included,
+/// ignoring the `exclude` directories.
+pub async fn current_diff(root: &Path, exclude: &[PathBuf]) -> Result<String> {
+    Ok(file_diffs(root, &pathspec(exclude))
+        .await?
+        .into_values()
+        .collect())
+}
+
+fn pathspec(exclude: &[PathBuf]) -> Vec<String> {
+    let mut pathspec = vec![".".to_string()];
+    pathspec.extend(
+        exclude
+            .iter()
+            .map(|dir| format!(":(exclude){}", dir.display())),
+    );
+    pathspec
+}
+
+/// Each changed file's diff against HEAD, untracked files included, keyed
+/// by path.
+async fn file_diffs(root: &Path, pathspec: &[String]) -> Result<BTreeMap<String, String>> {
+    let with_pathspec = |args: &[&'static str]| {
+        let mut args: Vec<&str> = args.to_vec();
+        args.push("--");
+        args.extend(pathspec.iter().map(String::as_str));
+        args
+    };
+    // A repository without commits has no HEAD; diff against the index.
+    let tracked = match git(
+        root,
+        &with_pathspec(&["diff", "HEAD", "--no-color", "--no-ext-diff"]),
+    )
+    .await
+    {
+        Ok(diff) => diff,
+        Err(_) => {
+            git(
+                root,
+                &with_pathspec(&["diff", "--no-color", "--no-ext-diff"]),
+            )
+            .await?
+        }
+    };
+    let mut diffs = split_by_file(&tracked);
+    let untracked = git(
+        root,
+        &with_pathspec(&["ls-files", "--others", "--exclude-standard", "-z"]),
+    )
+    .await?;
+    for path in untracked.split('\0').filter(|path| !path.is_empty()) {
+        let size = std::fs::metadata(root.join(path)).map_or(0, |meta| meta.len());
+        let diff = if size > MAX_UNTRACKED_BYTES {
+            format!("new file ({} bytes)\n", size)
+        } else {
+            // `--no-index` exits 1 when the files differ, which they always do.
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(root)
+                .args(["diff", "--no-index", "--no-color", "--", "/dev/null", path])
+                .kill_on_drop(true)
+                .output()
+                .await
+                .context("failed to run git")?;
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+        diffs.insert(path.to_string(), diff);
+    }
+    Ok(diffs)
+}
+
+async fn git(root: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("failed to run git")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Splits `git diff` output at each `diff --git a/x b/y` header, keyed by
+/// the new path.
+fn split_by_file(diff: &str) -> BTreeMap<String, String> {
+    let mut files = BTreeMap::new();
+    let mut current: Option<(String, String)> = None;
+    for line in diff.split_inclusive('\n') {
+        if let Some(header) = line.strip_prefix("diff --git ") {
+            if let Some((path, text)) = current.take() {
+                files.insert(path, text);
+            }
+            let path = header
+                .trim_end()
+                .rsplit_once(" b/")
+                .map_or(header.trim_end(), |(_, path)| path);
+            current = Some((path.to_string(), String::new()));
+        }
+        if let Some((_, text)) = current.as_mut() {
+            text.push_str(line);
+        }
+    }
+    if let Some((path, text)) = current {
+        files.insert(path, text);
+    }
+    files
+}
+
+fn line_counts(diff: &str) -> (usize, usize) {
+    let mut additions = 0;
+    let mut deletions = 0;
+    for line in diff.lines() {
+        if line.starts_with('+') && !line.starts_with("+++") {
+            additions += 1;
+        } else if line.starts_with('-') && !line.starts_with("---") {
+            deletions += 1;
+        }
+    }
+    (additions, deletions)
+}
+
+fn truncate(diff: &str) -> String {
+    match diff.char_indices().nth(MAX_FILE_DIFF_CHARS) {
+        Some((cut, _)) => format!("{}\n[diff truncated]\n", &diff[..cut]),
+        None => diff.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WorkspaceDiff;
+    use std::fs;
+    use std::process::Command;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[tokio::test]
+    async fn reports_only_files_changed_since_the_last_step() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time should move forward")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("rx-workspace-diff-{}", nanos));
+        fs::create_dir_all(&root).unwrap();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .arg("-C")
+                .arg(&root)
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        git(&["init", "--quiet"]);
+        git(&["config", "user.name", "rx test"]);
+        git(&["config", "user.email", "rx@example.com"]);
+        fs::write(root.join("a.txt"), "one\n").unwrap();
+        fs::write(root.join("b.txt"), "keep\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "init"]);
+        // Already dirty before the run; not reported.
+        fs::write(root.join("b.txt"), "dirty\n").unwrap();
+
+        let diff = WorkspaceDiff::start(&root, &["logs".into()]).await.unwrap();
+        assert!(diff.changes().await.unwrap().is_none());
+
+        fs::write(root.join("a.txt"), "two\n").unwrap();
+        fs::write(root.join("new.txt"), "hello\n").unwrap();
+        fs::create_dir_all(root.join("logs")).unwrap();
+        fs::write(root.join("logs/goal.jsonl"), "{}\n").unwrap();
+        let files = diff.changes().await.unwrap().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0]["path"], "a.txt");
+        assert_eq!(files[0]["additions"], 1);
+        assert_eq!(files[0]["deletions"], 1);
+        assert!(files[0]["diff"].as_str().unwrap().contains("+two"));
+        assert_eq!(files[1]["path"], "new.txt");
+        assert_eq!(files[1]["additions"], 1);
+
+        fs::write(root.join("a.txt"), "one\n").unwrap();
+        let files = diff.changes().await.unwrap().unwrap();
+        assert_eq!(files[0]["path"], "a.txt");
+        assert_eq!(files[0]["reverted"], true);
+        let _ = fs::remove_dir_all(root);
+    }
+}
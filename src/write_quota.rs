@@ -0,0 +1,179 @@
+use crate::event::Event;
+use crate::model::{Action, ToolCall};
+use crate::snapshot::{touched_paths, SNAPSHOT_TOOLS};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashSet};
+
+/// Limits on what the file tools of one goal may write, so a runaway model
+/// cannot fill the disk. `0` disables a limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteQuota {
+    /// Bytes of new content (file contents, replacement text, added patch
+    /// lines) across the goal.
+    pub max_bytes: u64,
+    /// Distinct paths written across the goal.
+    pub max_files: usize,
+}
+
+impl Default for WriteQuota {
+    fn default() -> Self {
+        Self {
+            max_bytes: 100 * 1024 * 1024,
+            max_files: 1000,
+        }
+    }
+}
+
+/// What the successful file tool calls of a goal wrote so far.
+#[derive(Debug, Default, PartialEq)]
+pub struct WriteUsage {
+    pub bytes: u64,
+    pub files: BTreeSet<String>,
+}
+
+impl WriteUsage {
+    /// Adds up the file tool calls in `history` whose output has no error.
+    pub fn from_history(history: &[Event]) -> Self {
+        let succeeded: HashSet<&str> = history
+            .iter()
+            .filter(|event| event.r#type == "tool_output")
+            .filter(|event| event.payload["output"].get("error").is_none())
+            .filter_map(|event| event.payload["tool_call_id"].as_str())
+            .collect();
+        let mut usage = Self::default();
+        for event in history.iter().filter(|event| event.r#type == "action") {
+            if let Ok(Action::ToolCall(call)) = serde_json::from_value(event.payload.clone()) {
+                if succeeded.contains(call.id.as_str()) && counts(&call) {
+                    usage.bytes += written_bytes(&call);
+                    usage.files.extend(touched_paths(&call));
+                }
+            }
+        }
+        usage
+    }
+}
+
+impl WriteQuota {
+    /// A `quota_exceeded` output when `tool_call` would take the goal past
+    /// a limit, or `None` when it may run.
+    pub fn check(&self, history: &[Event], tool_call: &ToolCall) -> Option<Value> {
+        if !counts(tool_call) {
+            return None;
+        }
+        let usage = WriteUsage::from_history(history);
+        let bytes = usage.bytes + written_bytes(tool_call);
+        let mut files = usage.files.clone();
+        files.extend(touched_paths(tool_call));
+        let details = if self.max_bytes > 0 && bytes > self.max_bytes {
+            format!(
+                "this write would bring the goal to {} bytes written, over the quota of {}",
+                bytes, self.max_bytes
+            )
+        } else if self.max_files > 0 && files.len() > self.max_files {
+            format!(
+                "this write would bring the goal to {} files touched, over the quota of {}",
+                files.len(),
+                self.max_files
+            )
+        } else {
+            return None;
+        };
+        Some(json!({
+            "error": "quota_exceeded",
+            "details": format!("{}; stop generating files, or finish with propose_done", details),
+            "bytes_written": usage.bytes,
+            "files_touched": usage.files.len(),
+            "max_bytes": self.max_bytes,
+            "max_files": self.max_files,
+        }))
+    }
+}
+
+/// File tool calls that write; dry runs do not.
+fn counts(tool_call: &ToolCall) -> bool {
+    SNAPSHOT_TOOLS.contains(&tool_call.name.as_str())
+        && tool_call.arguments["dry_run"].as_bool() != Some(true)
+}
+
+/// Bytes of new content a file tool call puts on disk, from its arguments.
+fn written_bytes(tool_call: &ToolCall) -> u64 {
+    let arguments = &tool_call.arguments;
+    let text = |field: &str| arguments[field].as_str().map_or(0, str::len) as u64;
+    match tool_call.name.as_str() {
+        "write_file" | "create_file" | "append_file" | "edit_lines" => text("content"),
+        "replace_in_file" => text("new_text"),
+        "multi_edit" => arguments["edits"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|edit| edit["new_text"].as_str().map_or(0, str::len) as u64)
+            .sum(),
+        "apply_patch" | "apply_unified_patch" => arguments["patch"]
+            .as_str()
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| line.starts_with('+') && !line.starts_with("+++"))
+            .map(|line| line.len() as u64)
+            .sum(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WriteQuota, WriteUsage};
+    use crate::event::Event;
+    use crate::model::{Action, ToolCall};
+    use serde_json::json;
+
+    fn write(history: &mut Vec<Event>, id: &str, path: &str, content: &str, error: bool) {
+        let call = ToolCall {
+            id: id.to_string(),
+            name: "write_file".to_string(),
+            arguments: json!({ "path": path, "content": content }),
+        };
+        history.push(Event::new("action", json!(Action::ToolCall(call))));
+        let output = if error {
+            json!({ "error": "permission_denied" })
+        } else {
+            json!({ "path": path })
+        };
+        history.push(Event::new(
+            "tool_output",
+            json!({ "tool_call_id": id, "name": "write_file", "output": output }),
+        ));
+    }
+
+    #[test]
+    fn writes_past_the_quota_are_refused() {
+        let quota = WriteQuota {
+            max_bytes: 10,
+            max_files: 2,
+        };
+        let mut history = Vec::new();
+        write(&mut history, "c1", "a.txt", "12345", false);
+        write(&mut history, "c2", "b.txt", "123456789", true);
+        let usage = WriteUsage::from_history(&history);
+        assert_eq!(usage.bytes, 5);
+        assert_eq!(usage.files.len(), 1);
+
+        let call = |path: &str, content: &str| ToolCall {
+            id: "next".to_string(),
+            name: "write_file".to_string(),
+            arguments: json!({ "path": path, "content": content }),
+        };
+        assert!(quota.check(&history, &call("b.txt", "12345")).is_none());
+        let refused = quota.check(&history, &call("b.txt", "123456")).unwrap();
+        assert_eq!(refused["error"], "quota_exceeded");
+        assert_eq!(refused["bytes_written"], 5);
+        write(&mut history, "c3", "b.txt", "1", false);
+        let refused = quota.check(&history, &call("c.txt", "1")).unwrap();
+        assert!(refused["details"].as_str().unwrap().contains("files"));
+        let read = ToolCall {
+            id: "read".to_string(),
+            name: "read_file".to_string(),
+            arguments: json!({ "path": "c.txt" }),
+        };
+        assert!(quota.check(&history, &read).is_none());
+    }
+}